@@ -22,6 +22,9 @@
 //! | [`BatchItem`] | Wrapper for individual batch items with metadata |
 //! | [`BatchExecutor`] | Executes batches with configurable strategies |
 //! | [`BatchStrategy`] | Execution strategy (Sequential, Parallel, Concurrent) |
+//! | [`JobQueue`] | Durable work queue: idempotency keys, priorities, per-provider limits, retries, dead letters |
+//! | [`FileJobStore`] | Append-only JSONL journal backing [`JobQueue`] across restarts |
+//! | [`BatchClient`] | Provider batch jobs (OpenAI Batch, Anthropic Message Batches), re-exported from `ai_lib_core::batch` |
 //!
 //! ## Example
 //!
@@ -48,9 +51,59 @@
 //! - **Sequential**: Process items one at a time, preserving order
 //! - **Parallel**: Process all items concurrently with no limit
 //! - **Concurrent**: Process up to N items concurrently (recommended for rate-limited APIs)
+//!
+//! ## Durable queue
+//!
+//! For long-running jobs (e.g. nightly re-embedding) use [`JobQueue`] over a
+//! [`FileJobStore`]; re-running after a crash resumes from the journal.
+//!
+//! ```rust,no_run
+//! use ai_lib_contact::batch::{
+//!     FileJobStore, JobHandler, JobQueue, JobQueueConfig, JobRecord, JobSpec, ProviderLimits,
+//! };
+//! use std::sync::Arc;
+//!
+//! struct Embed;
+//! #[async_trait::async_trait]
+//! impl JobHandler for Embed {
+//!     async fn handle(&self, job: &JobRecord) -> ai_lib_core::Result<serde_json::Value> {
+//!         // call the embedding client with job.spec.payload ...
+//!         Ok(serde_json::Value::Null)
+//!     }
+//! }
+//!
+//! # async fn run() -> ai_lib_core::Result<()> {
+//! let store = Arc::new(FileJobStore::open("reembed.jsonl")?);
+//! let queue = JobQueue::new(
+//!     store,
+//!     JobQueueConfig::new().with_provider_limits("openai", ProviderLimits::new(8).with_rps(50.0)),
+//! )
+//! .with_progress(|p| eprintln!("{:.1}% done", p.completion_ratio() * 100.0));
+//! queue
+//!     .enqueue(JobSpec::new("doc-42", "openai", serde_json::json!({"doc": 42})))
+//!     .await?;
+//! let done = queue.run(Arc::new(Embed)).await?;
+//! println!("{} dead-lettered", done.dead_lettered);
+//! # Ok(())
+//! # }
+//! ```
 
 mod collector;
 mod executor;
+mod queue;
+mod store;
 
 pub use collector::{BatchAddResult, BatchCollector, BatchConfig, BatchItem};
 pub use executor::{BatchError, BatchExecutor, BatchExecutorConfig, BatchResult, BatchStrategy};
+pub use queue::{JobHandler, JobQueue, JobQueueConfig, ProviderLimits};
+pub use store::{
+    EnqueueOutcome, FileJobStore, JobRecord, JobSpec, JobState, JobStore, MemoryJobStore,
+    QueueProgress,
+};
+
+// Provider-side batch jobs (OpenAI Batch / Anthropic Message Batches) live in the core crate.
+#[cfg(not(target_arch = "wasm32"))]
+pub use ai_lib_core::batch::{
+    BatchApiStyle, BatchClient, BatchItemError, BatchItemErrorKind, BatchItemResult, BatchJob,
+    BatchPollConfig, BatchRequestCounts, BatchRequestItem, BatchStatus,
+};
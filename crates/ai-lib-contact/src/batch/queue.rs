@@ -0,0 +1,452 @@
+//! Durable job queue runner.
+//!
+//! Pulls jobs from a [`JobStore`], enforces per-provider concurrency and rate
+//! limits, retries retryable failures with exponential backoff, and dead-letters
+//! jobs that run out of attempts.
+
+use super::store::{now_ms, EnqueueOutcome, JobRecord, JobSpec, JobStore, QueueProgress};
+use crate::resilience::rate_limiter::{RateLimiter, RateLimiterConfig};
+use ai_lib_core::Result;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+
+/// Executes one job. Return the value to store as the job result.
+///
+/// Errors are classified with [`ai_lib_core::Error::is_retryable`]; a
+/// `retry_after` hint from the provider overrides the backoff delay.
+#[async_trait]
+pub trait JobHandler: Send + Sync {
+    /// Run one attempt of `job`; `job.attempts` counts this attempt.
+    async fn handle(&self, job: &JobRecord) -> Result<serde_json::Value>;
+}
+
+/// Per-provider execution limits.
+#[derive(Debug, Clone)]
+pub struct ProviderLimits {
+    /// Jobs for the provider running at once (at least 1).
+    pub max_concurrency: usize,
+    /// Requests per second; `None` means unlimited.
+    pub rps: Option<f64>,
+}
+
+impl ProviderLimits {
+    /// Limits allowing `max_concurrency` concurrent jobs, with no rate limit.
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            max_concurrency: max_concurrency.max(1),
+            rps: None,
+        }
+    }
+    /// Cap the provider at `rps` job starts per second.
+    pub fn with_rps(mut self, rps: f64) -> Self {
+        self.rps = Some(rps);
+        self
+    }
+}
+
+/// Limits, retry and polling settings for a [`JobQueue`].
+#[derive(Debug, Clone)]
+pub struct JobQueueConfig {
+    /// Limits for providers without an explicit entry.
+    pub default_limits: ProviderLimits,
+    /// Per-provider overrides of `default_limits`.
+    pub provider_limits: HashMap<String, ProviderLimits>,
+    /// Default attempts before dead-lettering (overridable per job).
+    pub max_attempts: u32,
+    /// Backoff after the first failed attempt; doubles with each further attempt.
+    pub retry_base_delay: Duration,
+    /// Upper bound on the backoff delay.
+    pub retry_max_delay: Duration,
+    /// Upper bound on idle waits when only delayed jobs remain.
+    pub poll_interval: Duration,
+}
+
+impl Default for JobQueueConfig {
+    fn default() -> Self {
+        Self {
+            default_limits: ProviderLimits::new(4),
+            provider_limits: HashMap::new(),
+            max_attempts: 5,
+            retry_base_delay: Duration::from_secs(1),
+            retry_max_delay: Duration::from_secs(300),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl JobQueueConfig {
+    /// Same as [`Default`]: 4 concurrent jobs per provider, 5 attempts, 1s–300s backoff.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Limits for providers without their own entry.
+    pub fn with_default_limits(mut self, limits: ProviderLimits) -> Self {
+        self.default_limits = limits;
+        self
+    }
+    /// Limits for one provider, overriding the defaults.
+    pub fn with_provider_limits(
+        mut self,
+        provider: impl Into<String>,
+        limits: ProviderLimits,
+    ) -> Self {
+        self.provider_limits.insert(provider.into(), limits);
+        self
+    }
+    /// Default attempts per job (at least 1).
+    pub fn with_max_attempts(mut self, n: u32) -> Self {
+        self.max_attempts = n.max(1);
+        self
+    }
+    /// Base and maximum retry backoff.
+    pub fn with_retry_delays(mut self, base: Duration, max: Duration) -> Self {
+        self.retry_base_delay = base;
+        self.retry_max_delay = max;
+        self
+    }
+    /// Longest idle wait between checks for due jobs.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    fn limits_for(&self, provider: &str) -> &ProviderLimits {
+        self.provider_limits
+            .get(provider)
+            .unwrap_or(&self.default_limits)
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(20);
+        self.retry_base_delay
+            .saturating_mul(1u32 << exp)
+            .min(self.retry_max_delay)
+    }
+}
+
+type ProgressCallback = Arc<dyn Fn(&QueueProgress) + Send + Sync>;
+
+/// Persistent work queue for long-running chat/embedding jobs.
+///
+/// Progress lives in the store, so restarting [`run`](Self::run) against the same
+/// [`FileJobStore`](super::FileJobStore) resumes where the previous process stopped.
+pub struct JobQueue {
+    store: Arc<dyn JobStore>,
+    config: JobQueueConfig,
+    limiters: std::sync::Mutex<HashMap<String, Arc<RateLimiter>>>,
+    on_progress: Option<ProgressCallback>,
+    stop: Arc<AtomicBool>,
+    wake: Arc<Notify>,
+}
+
+impl JobQueue {
+    /// Queue over `store`; call [`run`](Self::run) to process it.
+    pub fn new(store: Arc<dyn JobStore>, config: JobQueueConfig) -> Self {
+        Self {
+            store,
+            config,
+            limiters: std::sync::Mutex::new(HashMap::new()),
+            on_progress: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Called after every finished attempt with the current store counts.
+    pub fn with_progress(mut self, f: impl Fn(&QueueProgress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+
+    /// Backing store, for direct lookups such as [`JobStore::get`].
+    pub fn store(&self) -> &Arc<dyn JobStore> {
+        &self.store
+    }
+
+    /// Add a job and wake a running [`run`](Self::run); duplicate keys are left untouched.
+    pub async fn enqueue(&self, spec: JobSpec) -> Result<EnqueueOutcome> {
+        let outcome = self.store.enqueue(spec).await?;
+        self.wake.notify_one();
+        Ok(outcome)
+    }
+
+    /// Current job counts by state.
+    pub async fn progress(&self) -> Result<QueueProgress> {
+        self.store.progress().await
+    }
+
+    /// Jobs that ran out of attempts or failed permanently.
+    pub async fn dead_letters(&self) -> Result<Vec<JobRecord>> {
+        self.store.dead_letters().await
+    }
+
+    /// Put a dead-lettered job back in the queue.
+    pub async fn requeue(&self, key: &str) -> Result<bool> {
+        let requeued = self.store.requeue(key).await?;
+        self.wake.notify_one();
+        Ok(requeued)
+    }
+
+    /// Ask a running [`run`](Self::run) to stop claiming jobs; in-flight jobs finish.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.wake.notify_one();
+    }
+
+    fn limiter(&self, provider: &str) -> Option<Arc<RateLimiter>> {
+        let rps = self.config.limits_for(provider).rps?;
+        let mut map = self.limiters.lock().ok()?;
+        if let Some(l) = map.get(provider) {
+            return Some(l.clone());
+        }
+        let limiter = Arc::new(RateLimiter::new(RateLimiterConfig::from_rps(rps)?));
+        map.insert(provider.to_string(), limiter.clone());
+        Some(limiter)
+    }
+
+    /// Process jobs until the queue is drained or [`stop`](Self::stop) is called.
+    ///
+    /// On a store error or a panicking handler, the attempts already in flight are
+    /// awaited so they record their outcome before the error is returned.
+    pub async fn run(&self, handler: Arc<dyn JobHandler>) -> Result<QueueProgress> {
+        self.stop.store(false, Ordering::SeqCst);
+        let mut tasks: JoinSet<(String, Result<()>)> = JoinSet::new();
+        if let Err(e) = self.drive(handler, &mut tasks).await {
+            while tasks.join_next().await.is_some() {}
+            return Err(e);
+        }
+        self.store.progress().await
+    }
+
+    async fn drive(
+        &self,
+        handler: Arc<dyn JobHandler>,
+        tasks: &mut JoinSet<(String, Result<()>)>,
+    ) -> Result<()> {
+        let mut in_flight: HashMap<String, usize> = HashMap::new();
+        loop {
+            // Claim as many jobs as provider capacity allows.
+            while !self.stop.load(Ordering::SeqCst) {
+                let busy: HashSet<String> = in_flight
+                    .iter()
+                    .filter(|(p, n)| **n >= self.config.limits_for(p).max_concurrency)
+                    .map(|(p, _)| p.clone())
+                    .collect();
+                let Some(job) = self.store.claim(&busy).await? else {
+                    break;
+                };
+                let provider = job.spec.provider.clone();
+                *in_flight.entry(provider.clone()).or_default() += 1;
+                tasks.spawn(self.attempt(job, handler.clone(), provider));
+            }
+
+            if tasks.is_empty() {
+                if self.stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                match self.store.next_ready_at().await? {
+                    // Only delayed retries remain: sleep until the first is due.
+                    Some(t) => {
+                        let wait = Duration::from_millis(t.saturating_sub(now_ms()))
+                            .min(self.config.poll_interval);
+                        tokio::select! {
+                            _ = tokio::time::sleep(wait) => {}
+                            _ = self.wake.notified() => {}
+                        }
+                    }
+                    None => break,
+                }
+                continue;
+            }
+
+            tokio::select! {
+                joined = tasks.join_next() => {
+                    if let Some(joined) = joined {
+                        let (provider, res) = joined.map_err(|e| {
+                            ai_lib_core::Error::runtime_with_context(
+                                format!("job task panicked: {}", e),
+                                ai_lib_core::ErrorContext::new().with_source("job_queue"),
+                            )
+                        })?;
+                        if let Some(n) = in_flight.get_mut(&provider) {
+                            *n = n.saturating_sub(1);
+                        }
+                        res?;
+                        if let Some(cb) = &self.on_progress {
+                            cb(&self.store.progress().await?);
+                        }
+                    }
+                }
+                _ = tokio::time::sleep(self.config.poll_interval) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn attempt(
+        &self,
+        job: JobRecord,
+        handler: Arc<dyn JobHandler>,
+        provider: String,
+    ) -> impl std::future::Future<Output = (String, Result<()>)> + Send + 'static {
+        let store = self.store.clone();
+        let limiter = self.limiter(&provider);
+        let max_attempts = job.spec.max_attempts.unwrap_or(self.config.max_attempts);
+        let config = self.config.clone();
+        async move {
+            let res = async {
+                if let Some(l) = limiter {
+                    l.acquire().await?;
+                }
+                match handler.handle(&job).await {
+                    Ok(value) => store.complete(job.key(), value).await,
+                    Err(e) => {
+                        let retry_at =
+                            (e.is_retryable() && job.attempts < max_attempts).then(|| {
+                                let delay = e
+                                    .retry_after()
+                                    .unwrap_or_else(|| config.backoff(job.attempts));
+                                now_ms() + delay.as_millis() as u64
+                            });
+                        if retry_at.is_none() {
+                            tracing::warn!(
+                                key = job.key(),
+                                attempts = job.attempts,
+                                error = %e,
+                                "job dead-lettered"
+                            );
+                        }
+                        store.fail(job.key(), &e.to_string(), retry_at).await
+                    }
+                }
+            }
+            .await;
+            (provider, res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::{JobState, MemoryJobStore};
+    use ai_lib_core::{Error, ErrorContext};
+    use std::sync::atomic::AtomicUsize;
+
+    /// Fails retryably `flaky` times per job, then succeeds; jobs with
+    /// `"fatal": true` fail permanently.
+    struct TestHandler {
+        flaky: u32,
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl JobHandler for TestHandler {
+        async fn handle(&self, job: &JobRecord) -> Result<serde_json::Value> {
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            if job.spec.payload["fatal"] == true {
+                return Err(Error::validation("bad input"));
+            }
+            if job.attempts <= self.flaky {
+                return Err(Error::runtime_with_context(
+                    "rate limited",
+                    ErrorContext::new().with_retryable(true),
+                ));
+            }
+            Ok(serde_json::json!({"attempts": job.attempts}))
+        }
+    }
+
+    fn queue(max_concurrency: usize) -> JobQueue {
+        JobQueue::new(
+            Arc::new(MemoryJobStore::new()),
+            JobQueueConfig::new()
+                .with_default_limits(ProviderLimits::new(max_concurrency))
+                .with_retry_delays(Duration::from_millis(1), Duration::from_millis(5))
+                .with_poll_interval(Duration::from_millis(5))
+                .with_max_attempts(3),
+        )
+    }
+
+    #[tokio::test]
+    async fn retries_then_succeeds_and_dead_letters_fatal() {
+        let q = queue(2);
+        for i in 0..4 {
+            q.enqueue(JobSpec::new(format!("job-{i}"), "p", serde_json::json!({})))
+                .await
+                .unwrap();
+        }
+        q.enqueue(JobSpec::new("bad", "p", serde_json::json!({"fatal": true})))
+            .await
+            .unwrap();
+        let handler = Arc::new(TestHandler {
+            flaky: 1,
+            running: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        });
+        let progress = q.run(handler.clone()).await.unwrap();
+
+        assert_eq!(progress.succeeded, 4);
+        assert_eq!(progress.dead_lettered, 1);
+        assert!(progress.is_drained());
+        assert!(handler.peak.load(Ordering::SeqCst) <= 2);
+        let done = q.store().get("job-0").await.unwrap().unwrap();
+        assert_eq!(done.result, Some(serde_json::json!({"attempts": 2})));
+        let dead = q.dead_letters().await.unwrap();
+        assert_eq!((dead[0].key(), dead[0].attempts), ("bad", 1));
+    }
+
+    struct PanicHandler;
+
+    #[async_trait]
+    impl JobHandler for PanicHandler {
+        async fn handle(&self, job: &JobRecord) -> Result<serde_json::Value> {
+            if job.key() == "boom" {
+                panic!("handler bug");
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(serde_json::json!("done"))
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_run_waits_for_in_flight_jobs() {
+        let q = queue(2);
+        q.enqueue(JobSpec::new("slow", "p", serde_json::json!({})))
+            .await
+            .unwrap();
+        q.enqueue(JobSpec::new("boom", "p", serde_json::json!({})))
+            .await
+            .unwrap();
+        let err = q.run(Arc::new(PanicHandler)).await.unwrap_err();
+        assert!(err.to_string().contains("panicked"), "{}", err);
+        let slow = q.store().get("slow").await.unwrap().unwrap();
+        assert_eq!(slow.state, JobState::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_are_dead_lettered() {
+        let q = queue(1);
+        q.enqueue(JobSpec::new("x", "p", serde_json::json!({})).with_max_attempts(2))
+            .await
+            .unwrap();
+        let handler = Arc::new(TestHandler {
+            flaky: 10,
+            running: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        });
+        let progress = q.run(handler).await.unwrap();
+        assert_eq!(progress.dead_lettered, 1);
+        assert_eq!(q.store().get("x").await.unwrap().unwrap().attempts, 2);
+    }
+}
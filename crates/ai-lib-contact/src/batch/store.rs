@@ -0,0 +1,1035 @@
+//! Job store backends for the durable queue.
+//!
+//! [`MemoryJobStore`] keeps everything in process; [`FileJobStore`] adds an
+//! append-only JSONL journal that is replayed on open, so a crash loses at most
+//! the in-flight attempt (which is put back to pending on recovery). Journal I/O
+//! runs on a dedicated writer thread, and job results are kept in a side file
+//! rather than in memory.
+
+use ai_lib_core::{Error, ErrorContext, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// A unit of work to enqueue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSpec {
+    /// Caller-chosen key; enqueueing the same key twice is a no-op.
+    pub idempotency_key: String,
+    /// Provider the job runs against; concurrency and rate limits are per provider.
+    pub provider: String,
+    /// Opaque job input (chat request, document batch to embed, ...).
+    pub payload: serde_json::Value,
+    /// Higher runs first (same convention as [`BatchItem::priority`](super::BatchItem)).
+    pub priority: i32,
+    /// Attempts before the job is dead-lettered; `None` uses the queue default.
+    pub max_attempts: Option<u32>,
+}
+
+impl JobSpec {
+    /// Job with default priority and the queue's attempt budget.
+    pub fn new(
+        idempotency_key: impl Into<String>,
+        provider: impl Into<String>,
+        payload: serde_json::Value,
+    ) -> Self {
+        Self {
+            idempotency_key: idempotency_key.into(),
+            provider: provider.into(),
+            payload,
+            priority: 0,
+            max_attempts: None,
+        }
+    }
+    /// Run ahead of lower-priority jobs.
+    pub fn with_priority(mut self, p: i32) -> Self {
+        self.priority = p;
+        self
+    }
+    /// Override the queue's attempts before dead-lettering.
+    pub fn with_max_attempts(mut self, n: u32) -> Self {
+        self.max_attempts = Some(n);
+        self
+    }
+}
+
+/// Lifecycle of a job in the store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Waiting to be claimed, possibly delayed by `not_before`.
+    Pending,
+    /// Claimed by a runner; put back to pending when a [`FileJobStore`] is reopened.
+    Running,
+    /// Finished with a stored result.
+    Succeeded,
+    /// Out of attempts or failed permanently; see [`JobStore::requeue`].
+    DeadLettered,
+}
+
+/// Persisted state of one job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub spec: JobSpec,
+    pub state: JobState,
+    /// Attempts started so far, including one that is running.
+    pub attempts: u32,
+    /// Error of the most recent failed attempt.
+    pub last_error: Option<String>,
+    /// Handler output once the job succeeded. [`FileJobStore`] keeps results on
+    /// disk and only fills this in [`JobStore::get`].
+    pub result: Option<serde_json::Value>,
+    /// Earliest time (unix ms) a pending job may run again after a retryable failure.
+    pub not_before: Option<u64>,
+    /// Enqueue time (unix ms).
+    pub created_at: u64,
+    /// Time of the last state change (unix ms).
+    pub updated_at: u64,
+    /// Insertion order, used as FIFO tie-breaker within a priority.
+    seq: u64,
+    /// Location of the result in a [`FileJobStore`]'s results file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result_at: Option<ResultRef>,
+}
+
+/// Byte range of one result in a [`FileJobStore`]'s results file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct ResultRef {
+    offset: u64,
+    len: u64,
+}
+
+impl JobRecord {
+    /// The job's idempotency key.
+    pub fn key(&self) -> &str {
+        &self.spec.idempotency_key
+    }
+}
+
+/// Outcome of [`JobStore::enqueue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnqueueOutcome {
+    /// The job was added as pending.
+    Inserted,
+    /// A job with the same idempotency key exists; nothing was changed.
+    Duplicate(JobState),
+}
+
+/// Counts by state; computed from the store so it survives restarts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueProgress {
+    pub pending: usize,
+    pub running: usize,
+    pub succeeded: usize,
+    pub dead_lettered: usize,
+}
+
+impl QueueProgress {
+    /// Jobs in any state.
+    pub fn total(&self) -> usize {
+        self.pending + self.running + self.succeeded + self.dead_lettered
+    }
+    /// Finished (succeeded or dead-lettered) fraction in `[0, 1]`.
+    pub fn completion_ratio(&self) -> f64 {
+        let total = self.total();
+        if total == 0 {
+            1.0
+        } else {
+            (self.succeeded + self.dead_lettered) as f64 / total as f64
+        }
+    }
+    /// No job is pending or running.
+    pub fn is_drained(&self) -> bool {
+        self.pending == 0 && self.running == 0
+    }
+}
+
+/// Persistence backend for [`JobQueue`](super::JobQueue).
+#[async_trait]
+pub trait JobStore: Send + Sync {
+    /// Insert a pending job unless its idempotency key already exists.
+    async fn enqueue(&self, spec: JobSpec) -> Result<EnqueueOutcome>;
+    /// Claim the best ready job (highest priority, then oldest) whose provider is
+    /// not in `busy`, marking it running and counting the attempt.
+    async fn claim(&self, busy: &HashSet<String>) -> Result<Option<JobRecord>>;
+    /// Mark a running job succeeded with `result`.
+    async fn complete(&self, key: &str, result: serde_json::Value) -> Result<()>;
+    /// Record a failed attempt; `retry_at` (unix ms) re-queues, `None` dead-letters.
+    async fn fail(&self, key: &str, error: &str, retry_at: Option<u64>) -> Result<()>;
+    /// Move a dead-lettered job back to pending with a fresh attempt budget.
+    async fn requeue(&self, key: &str) -> Result<bool>;
+    /// Look up a job by idempotency key.
+    async fn get(&self, key: &str) -> Result<Option<JobRecord>>;
+    /// All dead-lettered jobs.
+    async fn dead_letters(&self) -> Result<Vec<JobRecord>>;
+    /// Job counts by state.
+    async fn progress(&self) -> Result<QueueProgress>;
+    /// Earliest `not_before` among delayed pending jobs, if any.
+    async fn next_ready_at(&self) -> Result<Option<u64>>;
+    /// Short backend name (`memory`, `file`).
+    fn name(&self) -> &'static str;
+}
+
+type ReadyKey = (Reverse<i32>, u64, String);
+
+/// In-memory queue state shared by both backends.
+#[derive(Default)]
+struct QueueState {
+    jobs: HashMap<String, JobRecord>,
+    /// Ready pending jobs per provider, best first.
+    ready: HashMap<String, BTreeSet<ReadyKey>>,
+    /// Pending jobs waiting for `not_before`.
+    delayed: BinaryHeap<Reverse<(u64, String)>>,
+    next_seq: u64,
+}
+
+impl QueueState {
+    fn ready_key(r: &JobRecord) -> ReadyKey {
+        (
+            Reverse(r.spec.priority),
+            r.seq,
+            r.spec.idempotency_key.clone(),
+        )
+    }
+
+    fn index_pending(&mut self, key: &str) {
+        let Some(r) = self.jobs.get(key) else { return };
+        if r.state != JobState::Pending {
+            return;
+        }
+        match r.not_before {
+            Some(t) if t > now_ms() => self.delayed.push(Reverse((t, key.to_string()))),
+            _ => {
+                let rk = Self::ready_key(r);
+                self.ready
+                    .entry(r.spec.provider.clone())
+                    .or_default()
+                    .insert(rk);
+            }
+        }
+    }
+
+    fn promote_due(&mut self, now: u64) {
+        while let Some(Reverse((t, _))) = self.delayed.peek() {
+            if *t > now {
+                break;
+            }
+            let Reverse((t, key)) = self.delayed.pop().expect("peeked");
+            // Skip stale heap entries (job re-indexed or no longer pending).
+            if let Some(r) = self.jobs.get(&key) {
+                if r.state == JobState::Pending && r.not_before == Some(t) {
+                    let rk = Self::ready_key(r);
+                    self.ready
+                        .entry(r.spec.provider.clone())
+                        .or_default()
+                        .insert(rk);
+                }
+            }
+        }
+    }
+
+    fn new_record(&self, spec: JobSpec, now: u64) -> JobRecord {
+        JobRecord {
+            spec,
+            state: JobState::Pending,
+            attempts: 0,
+            last_error: None,
+            result: None,
+            not_before: None,
+            created_at: now,
+            updated_at: now,
+            seq: self.next_seq,
+            result_at: None,
+        }
+    }
+
+    fn insert(&mut self, spec: JobSpec, now: u64) -> EnqueueOutcome {
+        if let Some(existing) = self.jobs.get(&spec.idempotency_key) {
+            return EnqueueOutcome::Duplicate(existing.state);
+        }
+        let record = self.new_record(spec, now);
+        self.put(record);
+        EnqueueOutcome::Inserted
+    }
+
+    /// Replay: store a full record as-is (used by snapshot lines).
+    fn put(&mut self, record: JobRecord) {
+        self.next_seq = self.next_seq.max(record.seq + 1);
+        let key = record.spec.idempotency_key.clone();
+        self.remove_from_ready(&key);
+        self.jobs.insert(key.clone(), record);
+        self.index_pending(&key);
+    }
+
+    fn remove_from_ready(&mut self, key: &str) {
+        if let Some(r) = self.jobs.get(key) {
+            let rk = Self::ready_key(r);
+            if let Some(set) = self.ready.get_mut(&r.spec.provider) {
+                set.remove(&rk);
+            }
+        }
+    }
+
+    fn best_ready(&mut self, busy: &HashSet<String>, now: u64) -> Option<String> {
+        self.promote_due(now);
+        self.ready
+            .iter()
+            .filter(|(provider, _)| !busy.contains(*provider))
+            .filter_map(|(_, set)| set.first())
+            .min()
+            .map(|rk| rk.2.clone())
+    }
+
+    fn claim(&mut self, busy: &HashSet<String>, now: u64) -> Option<JobRecord> {
+        let key = self.best_ready(busy, now)?;
+        self.mark_running(&key, now);
+        self.jobs.get(&key).cloned()
+    }
+
+    fn mark_running(&mut self, key: &str, now: u64) {
+        self.remove_from_ready(key);
+        if let Some(r) = self.jobs.get_mut(key) {
+            r.state = JobState::Running;
+            r.attempts += 1;
+            r.not_before = None;
+            r.updated_at = now;
+        }
+    }
+
+    /// Store the result inline, or only its location in a results file.
+    fn complete(
+        &mut self,
+        key: &str,
+        result: Option<serde_json::Value>,
+        result_at: Option<ResultRef>,
+        now: u64,
+    ) -> bool {
+        let Some(r) = self.jobs.get_mut(key) else {
+            return false;
+        };
+        r.state = JobState::Succeeded;
+        r.result = result;
+        r.result_at = result_at;
+        r.last_error = None;
+        r.updated_at = now;
+        true
+    }
+
+    fn fail(&mut self, key: &str, error: &str, retry_at: Option<u64>, now: u64) -> bool {
+        let Some(r) = self.jobs.get_mut(key) else {
+            return false;
+        };
+        r.last_error = Some(error.to_string());
+        r.updated_at = now;
+        match retry_at {
+            Some(t) => {
+                r.state = JobState::Pending;
+                r.not_before = Some(t);
+            }
+            None => r.state = JobState::DeadLettered,
+        }
+        self.index_pending(key);
+        true
+    }
+
+    fn requeue(&mut self, key: &str, now: u64) -> bool {
+        let Some(r) = self.jobs.get_mut(key) else {
+            return false;
+        };
+        if r.state != JobState::DeadLettered {
+            return false;
+        }
+        r.state = JobState::Pending;
+        r.attempts = 0;
+        r.not_before = None;
+        r.updated_at = now;
+        self.index_pending(key);
+        true
+    }
+
+    /// After a restart nothing is actually running: put those jobs back.
+    fn recover_running(&mut self) -> Vec<String> {
+        let keys: Vec<String> = self
+            .jobs
+            .values()
+            .filter(|r| r.state == JobState::Running)
+            .map(|r| r.key().to_string())
+            .collect();
+        for key in &keys {
+            if let Some(r) = self.jobs.get_mut(key) {
+                r.state = JobState::Pending;
+            }
+            self.index_pending(key);
+        }
+        keys
+    }
+
+    fn progress(&self) -> QueueProgress {
+        let mut p = QueueProgress::default();
+        for r in self.jobs.values() {
+            match r.state {
+                JobState::Pending => p.pending += 1,
+                JobState::Running => p.running += 1,
+                JobState::Succeeded => p.succeeded += 1,
+                JobState::DeadLettered => p.dead_lettered += 1,
+            }
+        }
+        p
+    }
+
+    fn next_ready_at(&self) -> Option<u64> {
+        self.delayed
+            .iter()
+            .filter(|Reverse((t, key))| {
+                self.jobs
+                    .get(key)
+                    .is_some_and(|r| r.state == JobState::Pending && r.not_before == Some(*t))
+            })
+            .map(|Reverse((t, _))| *t)
+            .min()
+    }
+
+    fn dead_letters(&self) -> Vec<JobRecord> {
+        let mut out: Vec<JobRecord> = self
+            .jobs
+            .values()
+            .filter(|r| r.state == JobState::DeadLettered)
+            .cloned()
+            .collect();
+        out.sort_by_key(|r| r.seq);
+        out
+    }
+
+    /// Every record in insertion order.
+    fn snapshot(&self) -> Vec<JobRecord> {
+        let mut out: Vec<JobRecord> = self.jobs.values().cloned().collect();
+        out.sort_by_key(|r| r.seq);
+        out
+    }
+}
+
+fn poisoned() -> Error {
+    Error::runtime_with_context(
+        "job store lock poisoned",
+        ErrorContext::new().with_source("job_store"),
+    )
+}
+
+/// Non-durable store, useful for tests and short-lived jobs.
+#[derive(Default)]
+pub struct MemoryJobStore {
+    state: Mutex<QueueState>,
+}
+
+impl MemoryJobStore {
+    /// Empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl JobStore for MemoryJobStore {
+    async fn enqueue(&self, spec: JobSpec) -> Result<EnqueueOutcome> {
+        let mut st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.insert(spec, now_ms()))
+    }
+    async fn claim(&self, busy: &HashSet<String>) -> Result<Option<JobRecord>> {
+        let mut st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.claim(busy, now_ms()))
+    }
+    async fn complete(&self, key: &str, result: serde_json::Value) -> Result<()> {
+        let mut st = self.state.lock().map_err(|_| poisoned())?;
+        st.complete(key, Some(result), None, now_ms());
+        Ok(())
+    }
+    async fn fail(&self, key: &str, error: &str, retry_at: Option<u64>) -> Result<()> {
+        let mut st = self.state.lock().map_err(|_| poisoned())?;
+        st.fail(key, error, retry_at, now_ms());
+        Ok(())
+    }
+    async fn requeue(&self, key: &str) -> Result<bool> {
+        let mut st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.requeue(key, now_ms()))
+    }
+    async fn get(&self, key: &str) -> Result<Option<JobRecord>> {
+        let st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.jobs.get(key).cloned())
+    }
+    async fn dead_letters(&self) -> Result<Vec<JobRecord>> {
+        let st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.dead_letters())
+    }
+    async fn progress(&self) -> Result<QueueProgress> {
+        let st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.progress())
+    }
+    async fn next_ready_at(&self) -> Result<Option<u64>> {
+        let st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.next_ready_at())
+    }
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+}
+
+/// One journal line. `Put` carries a full record (enqueue and compaction);
+/// the others are state transitions.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    Put {
+        job: JobRecord,
+    },
+    Claim {
+        key: String,
+        at: u64,
+    },
+    Complete {
+        key: String,
+        result_at: ResultRef,
+        at: u64,
+    },
+    Fail {
+        key: String,
+        error: String,
+        retry_at: Option<u64>,
+        at: u64,
+    },
+    Requeue {
+        key: String,
+        at: u64,
+    },
+}
+
+/// Work run on the journal writer thread.
+type WriterOp = Box<dyn FnOnce(&mut JournalFiles) + Send>;
+
+/// Journal and results files, owned by the writer thread.
+struct JournalFiles {
+    path: PathBuf,
+    journal: BufWriter<File>,
+    results_path: PathBuf,
+    results: File,
+    results_len: u64,
+}
+
+impl JournalFiles {
+    fn append(&mut self, line: &str, fsync: bool) -> Result<()> {
+        self.journal.write_all(line.as_bytes())?;
+        self.journal.write_all(b"\n")?;
+        self.journal.flush()?;
+        if fsync {
+            self.journal.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+
+    fn append_result(&mut self, result: &serde_json::Value, fsync: bool) -> Result<ResultRef> {
+        let mut line = serde_json::to_vec(result)?;
+        let at = ResultRef {
+            offset: self.results_len,
+            len: line.len() as u64,
+        };
+        line.push(b'\n');
+        self.results.write_all(&line)?;
+        if fsync {
+            self.results.sync_data()?;
+        }
+        self.results_len += line.len() as u64;
+        Ok(at)
+    }
+
+    fn read_result(&self, at: ResultRef) -> Result<serde_json::Value> {
+        let mut file = File::open(&self.results_path)?;
+        file.seek(SeekFrom::Start(at.offset))?;
+        let mut buf = vec![0; at.len as usize];
+        file.read_exact(&mut buf)?;
+        Ok(serde_json::from_slice(&buf)?)
+    }
+}
+
+/// Durable store backed by an append-only JSONL journal.
+///
+/// Every transition is written (and flushed to the OS) before it is applied, so
+/// the journal survives a process crash; enable [`with_fsync`](Self::with_fsync)
+/// to also survive power loss. Writes, reads of results and compaction run on a
+/// dedicated thread, so they never block the async runtime. Results go to a
+/// `<name>.results.jsonl` file next to the journal, keeping them out of memory and
+/// out of compaction. The journal is compacted on open and whenever it grows past
+/// `compact_threshold` entries beyond the live job count.
+pub struct FileJobStore {
+    path: PathBuf,
+    state: Mutex<QueueState>,
+    /// Entries appended since the last compaction. Held for the whole of each
+    /// transition, so the journal order matches the order changes are applied.
+    appended: tokio::sync::Mutex<usize>,
+    writer: mpsc::Sender<WriterOp>,
+    fsync: bool,
+    compact_threshold: usize,
+}
+
+impl FileJobStore {
+    /// Open (or create) a journal, replay it and recover interrupted jobs.
+    ///
+    /// Replay and the initial compaction run on the calling thread.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut state = QueueState::default();
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path)?);
+            for (lineno, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<JournalEntry>(&line) {
+                    Ok(entry) => Self::apply(&mut state, entry),
+                    // A torn final write from a crash: ignore it, the job stays in its prior state.
+                    Err(e) => tracing::warn!(
+                        path = %path.display(),
+                        line = lineno + 1,
+                        error = %e,
+                        "skipping unreadable job journal entry"
+                    ),
+                }
+            }
+        }
+        let recovered = state.recover_running();
+        if !recovered.is_empty() {
+            tracing::info!(
+                count = recovered.len(),
+                "re-queued jobs interrupted by restart"
+            );
+        }
+        let journal = Self::write_snapshot(&path, &state.snapshot())?;
+        let results_path = path.with_extension("results.jsonl");
+        let results = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&results_path)?;
+        let mut files = JournalFiles {
+            path: path.clone(),
+            journal,
+            results_len: results.metadata()?.len(),
+            results_path,
+            results,
+        };
+        let (writer, ops) = mpsc::channel::<WriterOp>();
+        std::thread::Builder::new()
+            .name("ai-lib-job-journal".to_string())
+            .spawn(move || {
+                for op in ops {
+                    op(&mut files);
+                }
+            })?;
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+            appended: tokio::sync::Mutex::new(0),
+            writer,
+            fsync: false,
+            compact_threshold: 100_000,
+        })
+    }
+
+    /// `fsync` every journal write (slower; survives power loss).
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Compact once the journal holds this many entries beyond the live jobs.
+    pub fn with_compact_threshold(mut self, entries: usize) -> Self {
+        self.compact_threshold = entries.max(1);
+        self
+    }
+
+    /// Journal file path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rewrite the journal as one `put` per job.
+    pub async fn compact(&self) -> Result<()> {
+        let mut appended = self.appended.lock().await;
+        self.write_compacted(&mut appended).await
+    }
+
+    fn apply(state: &mut QueueState, entry: JournalEntry) {
+        match entry {
+            JournalEntry::Put { job } => state.put(job),
+            JournalEntry::Claim { key, at } => state.mark_running(&key, at),
+            JournalEntry::Complete { key, result_at, at } => {
+                state.complete(&key, None, Some(result_at), at);
+            }
+            JournalEntry::Fail {
+                key,
+                error,
+                retry_at,
+                at,
+            } => {
+                state.remove_from_ready(&key);
+                state.fail(&key, &error, retry_at, at);
+            }
+            JournalEntry::Requeue { key, at } => {
+                state.requeue(&key, at);
+            }
+        }
+    }
+
+    /// Write `records` to a temp file, atomically replace the journal, and return
+    /// an append handle to it.
+    fn write_snapshot(path: &Path, records: &[JobRecord]) -> Result<BufWriter<File>> {
+        let tmp = path.with_extension("compact.tmp");
+        {
+            let mut w = BufWriter::new(File::create(&tmp)?);
+            for job in records {
+                let line = serde_json::to_string(&JournalEntry::Put { job: job.clone() })?;
+                w.write_all(line.as_bytes())?;
+                w.write_all(b"\n")?;
+            }
+            w.flush()?;
+            w.get_ref().sync_all()?;
+        }
+        std::fs::rename(&tmp, path)?;
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(BufWriter::new(file))
+    }
+
+    /// Run `op` on the writer thread and wait for its result.
+    async fn run<T: Send + 'static>(
+        &self,
+        op: impl FnOnce(&mut JournalFiles) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.writer
+            .send(Box::new(move |files: &mut JournalFiles| {
+                let _ = tx.send(op(files));
+            }))
+            .map_err(|_| writer_stopped())?;
+        rx.await.map_err(|_| writer_stopped())?
+    }
+
+    /// Append `entry` to the journal; callers apply it to the state afterwards.
+    async fn append(&self, appended: &mut usize, entry: &JournalEntry) -> Result<()> {
+        let line = serde_json::to_string(entry)?;
+        let fsync = self.fsync;
+        self.run(move |files| files.append(&line, fsync)).await?;
+        *appended += 1;
+        Ok(())
+    }
+
+    async fn maybe_compact(&self, appended: &mut usize) -> Result<()> {
+        let live = self.state.lock().map_err(|_| poisoned())?.jobs.len();
+        if *appended > live + self.compact_threshold {
+            self.write_compacted(appended).await?;
+        }
+        Ok(())
+    }
+
+    async fn write_compacted(&self, appended: &mut usize) -> Result<()> {
+        let records = self.state.lock().map_err(|_| poisoned())?.snapshot();
+        self.run(move |files| {
+            files.journal.flush()?;
+            files.journal = Self::write_snapshot(&files.path, &records)?;
+            Ok(())
+        })
+        .await?;
+        *appended = 0;
+        Ok(())
+    }
+
+    async fn transition<T>(
+        &self,
+        entry: JournalEntry,
+        f: impl FnOnce(&mut QueueState) -> T,
+    ) -> Result<T> {
+        let mut appended = self.appended.lock().await;
+        self.append(&mut appended, &entry).await?;
+        let out = f(&mut *self.state.lock().map_err(|_| poisoned())?);
+        self.maybe_compact(&mut appended).await?;
+        Ok(out)
+    }
+}
+
+fn writer_stopped() -> Error {
+    Error::runtime_with_context(
+        "job journal writer stopped",
+        ErrorContext::new().with_source("job_store"),
+    )
+}
+
+#[async_trait]
+impl JobStore for FileJobStore {
+    async fn enqueue(&self, spec: JobSpec) -> Result<EnqueueOutcome> {
+        let mut appended = self.appended.lock().await;
+        let job = {
+            let st = self.state.lock().map_err(|_| poisoned())?;
+            if let Some(existing) = st.jobs.get(&spec.idempotency_key) {
+                return Ok(EnqueueOutcome::Duplicate(existing.state));
+            }
+            st.new_record(spec, now_ms())
+        };
+        self.append(&mut appended, &JournalEntry::Put { job: job.clone() })
+            .await?;
+        self.state.lock().map_err(|_| poisoned())?.put(job);
+        self.maybe_compact(&mut appended).await?;
+        Ok(EnqueueOutcome::Inserted)
+    }
+
+    async fn claim(&self, busy: &HashSet<String>) -> Result<Option<JobRecord>> {
+        let now = now_ms();
+        let mut appended = self.appended.lock().await;
+        let Some(key) = self
+            .state
+            .lock()
+            .map_err(|_| poisoned())?
+            .best_ready(busy, now)
+        else {
+            return Ok(None);
+        };
+        let entry = JournalEntry::Claim {
+            key: key.clone(),
+            at: now,
+        };
+        self.append(&mut appended, &entry).await?;
+        let record = {
+            let mut st = self.state.lock().map_err(|_| poisoned())?;
+            st.mark_running(&key, now);
+            st.jobs.get(&key).cloned()
+        };
+        self.maybe_compact(&mut appended).await?;
+        Ok(record)
+    }
+
+    async fn complete(&self, key: &str, result: serde_json::Value) -> Result<()> {
+        let at = now_ms();
+        let fsync = self.fsync;
+        // The result is written first; a crash before the journal entry only leaves
+        // an unreferenced line in the results file.
+        let result_at = self
+            .run(move |files| files.append_result(&result, fsync))
+            .await?;
+        self.transition(
+            JournalEntry::Complete {
+                key: key.to_string(),
+                result_at,
+                at,
+            },
+            |st| {
+                st.complete(key, None, Some(result_at), at);
+            },
+        )
+        .await
+    }
+
+    async fn fail(&self, key: &str, error: &str, retry_at: Option<u64>) -> Result<()> {
+        let at = now_ms();
+        self.transition(
+            JournalEntry::Fail {
+                key: key.to_string(),
+                error: error.to_string(),
+                retry_at,
+                at,
+            },
+            |st| {
+                st.fail(key, error, retry_at, at);
+            },
+        )
+        .await
+    }
+
+    async fn requeue(&self, key: &str) -> Result<bool> {
+        let at = now_ms();
+        self.transition(
+            JournalEntry::Requeue {
+                key: key.to_string(),
+                at,
+            },
+            |st| st.requeue(key, at),
+        )
+        .await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<JobRecord>> {
+        let record = self
+            .state
+            .lock()
+            .map_err(|_| poisoned())?
+            .jobs
+            .get(key)
+            .cloned();
+        let Some(mut record) = record else {
+            return Ok(None);
+        };
+        if let Some(at) = record.result_at {
+            record.result = Some(self.run(move |files| files.read_result(at)).await?);
+        }
+        Ok(Some(record))
+    }
+    async fn dead_letters(&self) -> Result<Vec<JobRecord>> {
+        let st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.dead_letters())
+    }
+    async fn progress(&self) -> Result<QueueProgress> {
+        let st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.progress())
+    }
+    async fn next_ready_at(&self) -> Result<Option<u64>> {
+        let st = self.state.lock().map_err(|_| poisoned())?;
+        Ok(st.next_ready_at())
+    }
+    fn name(&self) -> &'static str {
+        "file"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_journal(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ai-lib-jobs-{}-{}-{}.jsonl",
+            name,
+            std::process::id(),
+            now_ms()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("results.jsonl"));
+        path
+    }
+
+    #[tokio::test]
+    async fn claims_by_priority_then_fifo_and_dedupes() {
+        let store = MemoryJobStore::new();
+        let none = HashSet::new();
+        for (key, prio) in [("a", 0), ("b", 5), ("c", 0)] {
+            let spec = JobSpec::new(key, "openai", serde_json::json!({})).with_priority(prio);
+            assert_eq!(store.enqueue(spec).await.unwrap(), EnqueueOutcome::Inserted);
+        }
+        assert_eq!(
+            store
+                .enqueue(JobSpec::new("a", "openai", serde_json::json!({})))
+                .await
+                .unwrap(),
+            EnqueueOutcome::Duplicate(JobState::Pending)
+        );
+        let order: Vec<String> = [
+            store.claim(&none).await.unwrap(),
+            store.claim(&none).await.unwrap(),
+            store.claim(&none).await.unwrap(),
+        ]
+        .into_iter()
+        .map(|r| r.unwrap().spec.idempotency_key)
+        .collect();
+        assert_eq!(order, ["b", "a", "c"]);
+        assert!(store.claim(&none).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn busy_providers_are_skipped() {
+        let store = MemoryJobStore::new();
+        store
+            .enqueue(JobSpec::new("a", "openai", serde_json::json!({})).with_priority(9))
+            .await
+            .unwrap();
+        store
+            .enqueue(JobSpec::new("b", "anthropic", serde_json::json!({})))
+            .await
+            .unwrap();
+        let busy: HashSet<String> = ["openai".to_string()].into();
+        let job = store.claim(&busy).await.unwrap().unwrap();
+        assert_eq!(job.spec.provider, "anthropic");
+    }
+
+    #[tokio::test]
+    async fn file_store_replays_and_recovers_interrupted_jobs() {
+        let path = temp_journal("replay");
+        {
+            let store = FileJobStore::open(&path).unwrap();
+            for key in ["a", "b", "c"] {
+                store
+                    .enqueue(JobSpec::new(key, "openai", serde_json::json!({"doc": key})))
+                    .await
+                    .unwrap();
+            }
+            let none = HashSet::new();
+            let a = store.claim(&none).await.unwrap().unwrap();
+            store
+                .complete(a.key(), serde_json::json!("ok"))
+                .await
+                .unwrap();
+            let b = store.claim(&none).await.unwrap().unwrap();
+            store.fail(b.key(), "boom", None).await.unwrap();
+            // "c" is claimed and the process "crashes" mid-attempt.
+            store.claim(&none).await.unwrap().unwrap();
+        }
+
+        let store = FileJobStore::open(&path).unwrap();
+        let p = store.progress().await.unwrap();
+        assert_eq!(
+            (p.pending, p.running, p.succeeded, p.dead_lettered),
+            (1, 0, 1, 1)
+        );
+        let c = store.get("c").await.unwrap().unwrap();
+        assert_eq!((c.state, c.attempts), (JobState::Pending, 1));
+        assert_eq!(
+            store.dead_letters().await.unwrap()[0].last_error.as_deref(),
+            Some("boom")
+        );
+        assert!(store.requeue("b").await.unwrap());
+        assert_eq!(store.progress().await.unwrap().pending, 2);
+        assert_eq!(
+            store.get("a").await.unwrap().unwrap().result,
+            Some(serde_json::json!("ok"))
+        );
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("results.jsonl"));
+    }
+
+    #[tokio::test]
+    async fn file_store_keeps_results_out_of_the_journal() {
+        let path = temp_journal("results");
+        let big = serde_json::json!({"embedding": vec![0.5; 1000]});
+        {
+            let store = FileJobStore::open(&path).unwrap().with_compact_threshold(1);
+            let none = HashSet::new();
+            for key in ["a", "b"] {
+                store
+                    .enqueue(JobSpec::new(key, "openai", serde_json::json!({})))
+                    .await
+                    .unwrap();
+                let job = store.claim(&none).await.unwrap().unwrap();
+                store.complete(job.key(), big.clone()).await.unwrap();
+            }
+            store.compact().await.unwrap();
+            let journal = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(journal.lines().count(), 2);
+            assert!(!journal.contains("embedding"));
+            assert_eq!(
+                store.get("b").await.unwrap().unwrap().result,
+                Some(big.clone())
+            );
+        }
+
+        let store = FileJobStore::open(&path).unwrap();
+        assert_eq!(store.get("a").await.unwrap().unwrap().result, Some(big));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("results.jsonl"));
+    }
+}
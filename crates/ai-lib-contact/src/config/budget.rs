@@ -0,0 +1,182 @@
+//! Token / cost budgets declared in the application config.
+
+use super::schema::{BudgetConfig, BudgetPricing};
+use ai_lib_core::{Error, ErrorContext, Result, StandardErrorCode};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct Usage {
+    tokens: u64,
+    cost: f64,
+    window_start: Instant,
+}
+
+/// Spend tracker shared by the clients a budget names.
+///
+/// Usage is charged after each call from the provider-reported token counts, so a
+/// single in-flight request may overshoot the limit; the next [`Budget::check`]
+/// rejects.
+#[derive(Debug)]
+pub struct Budget {
+    name: String,
+    clients: Vec<String>,
+    max_tokens: Option<u64>,
+    max_cost: Option<f64>,
+    pricing: Option<BudgetPricing>,
+    window: Option<Duration>,
+    usage: Mutex<Usage>,
+}
+
+impl Budget {
+    pub fn new(name: impl Into<String>, config: &BudgetConfig) -> Self {
+        Self {
+            name: name.into(),
+            clients: config.clients.clone(),
+            max_tokens: config.max_tokens,
+            max_cost: config.max_cost,
+            pricing: config.pricing,
+            window: config.window_secs.map(Duration::from_secs),
+            usage: Mutex::new(Usage {
+                tokens: 0,
+                cost: 0.0,
+                window_start: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether calls through `client` count against this budget.
+    pub fn applies_to(&self, client: &str) -> bool {
+        self.clients.is_empty() || self.clients.iter().any(|c| c == client)
+    }
+
+    /// Charge token usage.
+    pub fn record(&self, input_tokens: u64, output_tokens: u64) {
+        let cost = self.pricing.map_or(0.0, |p| {
+            (input_tokens as f64 / 1000.0) * p.input_per_1k
+                + (output_tokens as f64 / 1000.0) * p.output_per_1k
+        });
+        self.with_usage(|u| {
+            u.tokens = u.tokens.saturating_add(input_tokens + output_tokens);
+            u.cost += cost;
+        });
+    }
+
+    /// Charge a provider `usage` object (OpenAI or Anthropic token field names).
+    pub fn record_usage(&self, usage: &serde_json::Value) {
+        let field = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|k| usage.get(*k).and_then(|v| v.as_u64()))
+                .unwrap_or(0)
+        };
+        let input = field(&["prompt_tokens", "input_tokens"]);
+        let output = field(&["completion_tokens", "output_tokens"]);
+        if input == 0 && output == 0 {
+            // Only a total is known; price it as input.
+            self.record(field(&["total_tokens"]), 0);
+        } else {
+            self.record(input, output);
+        }
+    }
+
+    /// Tokens charged in the current window.
+    pub fn used_tokens(&self) -> u64 {
+        self.with_usage(|u| u.tokens)
+    }
+
+    /// Cost charged in the current window, in the pricing's currency.
+    pub fn spent(&self) -> f64 {
+        self.with_usage(|u| u.cost)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.with_usage(|u| {
+            self.max_tokens.is_some_and(|max| u.tokens >= max)
+                || self.max_cost.is_some_and(|max| u.cost >= max)
+        })
+    }
+
+    /// `Err` with [`StandardErrorCode::QuotaExhausted`] once a limit is reached.
+    pub fn check(&self) -> Result<()> {
+        if !self.is_exhausted() {
+            return Ok(());
+        }
+        let mut context = ErrorContext::new()
+            .with_source("budget")
+            .with_standard_code(StandardErrorCode::QuotaExhausted)
+            .with_retryable(false)
+            .with_fallbackable(false);
+        if let Some(window) = self.window {
+            context = context.with_hint(format!("usage resets every {}s", window.as_secs()));
+        }
+        Err(Error::runtime_with_context(
+            format!("budget '{}' exhausted", self.name),
+            context,
+        ))
+    }
+
+    pub fn reset(&self) {
+        self.with_usage(|u| {
+            u.tokens = 0;
+            u.cost = 0.0;
+            u.window_start = Instant::now();
+        });
+    }
+
+    fn with_usage<T>(&self, f: impl FnOnce(&mut Usage) -> T) -> T {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(window) = self.window {
+            if usage.window_start.elapsed() >= window {
+                usage.tokens = 0;
+                usage.cost = 0.0;
+                usage.window_start = Instant::now();
+            }
+        }
+        f(&mut usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_budget_tracks_tokens_and_cost() {
+        let budget = Budget::new(
+            "daily",
+            &BudgetConfig {
+                clients: vec!["primary".into()],
+                max_tokens: None,
+                max_cost: Some(0.02),
+                pricing: Some(BudgetPricing {
+                    input_per_1k: 0.005,
+                    output_per_1k: 0.015,
+                }),
+                window_secs: None,
+            },
+        );
+        assert!(budget.applies_to("primary") && !budget.applies_to("backup"));
+
+        budget.record_usage(&json!({ "prompt_tokens": 1000, "completion_tokens": 500 }));
+        assert_eq!(budget.used_tokens(), 1500);
+        assert!((budget.spent() - 0.0125).abs() < 1e-9);
+        assert!(budget.check().is_ok());
+
+        budget.record_usage(&json!({ "input_tokens": 2000, "output_tokens": 0 }));
+        let err = budget.check().unwrap_err();
+        assert!(err.to_string().contains("budget 'daily' exhausted"));
+        assert_eq!(
+            err.context().and_then(|c| c.standard_code),
+            Some(StandardErrorCode::QuotaExhausted)
+        );
+
+        budget.reset();
+        assert_eq!(budget.used_tokens(), 0);
+        assert!(budget.check().is_ok());
+    }
+}
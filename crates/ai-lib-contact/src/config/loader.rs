@@ -0,0 +1,417 @@
+//! Config parsing: YAML/TOML documents, profile overlays and `${VAR}` interpolation.
+
+use super::config_error;
+use super::schema::AppConfig;
+use ai_lib_core::{Error, Result};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Environment variable selecting the profile when [`ConfigLoader::profile`] is not set.
+pub const PROFILE_ENV: &str = "AI_LIB_PROFILE";
+
+/// Source format of a config document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// `.toml` files are TOML; everything else is read as YAML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+}
+
+/// Loads [`AppConfig`] documents.
+///
+/// Steps, in order: parse, overlay the selected profile, expand `${VAR}`
+/// placeholders, deserialize, validate. Placeholders are expanded after the
+/// overlay so variables only referenced by other profiles need not be set.
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    profile: Option<String>,
+    vars: Option<HashMap<String, String>>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    /// Loader using the process environment and the `AI_LIB_PROFILE` profile, if set.
+    pub fn new() -> Self {
+        Self {
+            profile: std::env::var(PROFILE_ENV).ok().filter(|p| !p.is_empty()),
+            vars: None,
+        }
+    }
+
+    /// Select the profile overlay from the document's `profiles` section.
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    /// Resolve placeholders from this map instead of the process environment.
+    pub fn vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.vars = Some(vars);
+        self
+    }
+
+    pub fn load_file(&self, path: impl AsRef<Path>) -> Result<AppConfig> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| config_error(".", format!("cannot read {}: {}", path.display(), e)))?;
+        self.load_str(&text, ConfigFormat::from_path(path))
+            .map_err(|e| with_file(e, path))
+    }
+
+    pub fn load_str(&self, text: &str, format: ConfigFormat) -> Result<AppConfig> {
+        let mut doc: Value = match format {
+            ConfigFormat::Yaml => serde_yaml::from_str(text)
+                .map_err(|e| config_error(".", format!("invalid YAML: {}", e)))?,
+            ConfigFormat::Toml => toml::from_str(text)
+                .map_err(|e| config_error(".", format!("invalid TOML: {}", e)))?,
+        };
+        if doc.is_null() {
+            doc = Value::Object(Map::new());
+        }
+        let Value::Object(root) = &mut doc else {
+            return Err(config_error(".", "the config document must be a mapping"));
+        };
+
+        let profiles = root.remove("profiles");
+        if let Some(name) = &self.profile {
+            let overlay = profiles.as_ref().and_then(|p| p.get(name)).ok_or_else(|| {
+                let known = profiles
+                    .as_ref()
+                    .and_then(Value::as_object)
+                    .map(|p| p.keys().cloned().collect::<Vec<_>>().join(", "))
+                    .unwrap_or_default();
+                Error::configuration_with_context(
+                    format!("profile '{}' is not defined", name),
+                    ai_lib_core::ErrorContext::new()
+                        .with_field_path(format!("profiles.{}", name))
+                        .with_source("app_config")
+                        .with_hint(format!("available profiles: [{}]", known)),
+                )
+            })?;
+            merge(&mut doc, overlay.clone());
+        }
+
+        self.interpolate(&mut doc, &mut String::new())?;
+
+        let config: AppConfig = serde_path_to_error::deserialize(doc).map_err(|e| {
+            let path = e.path().to_string();
+            config_error(path, e.into_inner().to_string())
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        match &self.vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    fn interpolate(&self, value: &mut Value, path: &mut String) -> Result<()> {
+        match value {
+            Value::String(s) if s.contains('$') => {
+                *value = self.expand(s, path)?;
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("[{}]", i));
+                    self.interpolate(item, path)?;
+                    path.truncate(len);
+                }
+            }
+            Value::Object(map) => {
+                for (key, item) in map.iter_mut() {
+                    let len = path.len();
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(key);
+                    self.interpolate(item, path)?;
+                    path.truncate(len);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Expand one string. A value that is exactly one placeholder and expands to a
+    /// number or boolean keeps that type, so `max_inflight: ${MAX_INFLIGHT}` works.
+    fn expand(&self, s: &str, path: &str) -> Result<Value> {
+        let mut out = String::new();
+        let mut rest = s;
+        let mut placeholders = 0;
+        while let Some(start) = rest.find('$') {
+            out.push_str(&rest[..start]);
+            let after = &rest[start..];
+            if let Some(tail) = after.strip_prefix("$${") {
+                out.push_str("${");
+                rest = tail;
+            } else if let Some(tail) = after.strip_prefix("${") {
+                let end = tail.find('}').ok_or_else(|| {
+                    config_error(path, format!("unterminated placeholder in '{}'", s))
+                })?;
+                let (name, default) = match tail[..end].split_once(":-") {
+                    Some((name, default)) => (name, Some(default)),
+                    None => (&tail[..end], None),
+                };
+                let resolved = match (self.lookup(name), default) {
+                    (Some(v), None) => v,
+                    (Some(v), Some(_)) if !v.is_empty() => v,
+                    (_, Some(default)) => default.to_string(),
+                    (None, None) => {
+                        return Err(Error::configuration_with_context(
+                            format!("environment variable {} is not set", name),
+                            ai_lib_core::ErrorContext::new()
+                                .with_field_path(path)
+                                .with_source("app_config")
+                                .with_hint(format!(
+                                    "set {} or give a default with ${{{}:-value}}",
+                                    name, name
+                                )),
+                        ))
+                    }
+                };
+                out.push_str(&resolved);
+                placeholders += 1;
+                rest = &tail[end + 1..];
+            } else {
+                out.push('$');
+                rest = &after[1..];
+            }
+        }
+        out.push_str(rest);
+
+        let whole = placeholders == 1 && s.starts_with("${") && s.ends_with('}');
+        if whole {
+            if let Ok(v @ (Value::Number(_) | Value::Bool(_))) = serde_yaml::from_str::<Value>(&out)
+            {
+                return Ok(v);
+            }
+        }
+        Ok(Value::String(out))
+    }
+}
+
+/// Deep-merge `overlay` into `base`; mappings merge key by key, `null` removes a key
+/// and any other value replaces the base value.
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                } else if let Some(existing) = base.get_mut(&key) {
+                    merge(existing, value);
+                } else {
+                    base.insert(key, value);
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Record which file a configuration error came from.
+fn with_file(err: Error, path: &Path) -> Error {
+    match err {
+        Error::Configuration {
+            message,
+            mut context,
+        } => {
+            context.details = Some(format!("file: {}", path.display()));
+            Error::Configuration { message, context }
+        }
+        other => other,
+    }
+}
+
+impl AppConfig {
+    /// Load a YAML or TOML file with [`ConfigLoader::new`].
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        ConfigLoader::new().load_file(path)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        ConfigLoader::new().load_str(yaml, ConfigFormat::Yaml)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        ConfigLoader::new().load_str(toml, ConfigFormat::Toml)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RoutingStrategy, TelemetryExporter};
+
+    const BASE: &str = r#"
+clients:
+  primary:
+    model: openai/gpt-4o
+    api_key: ${OPENAI_KEY}
+    base_url: ${PROXY_URL:-https://api.openai.com/v1}
+    max_inflight: ${MAX_INFLIGHT:-4}
+    fallbacks: [backup]
+    rate_limit: { rps: 5 }
+  backup:
+    model: anthropic/claude-3-5-sonnet
+routing:
+  chat: { strategy: weighted, members: [{ client: primary, weight: 3 }, { client: backup }] }
+telemetry:
+  exporters: [{ type: console }, { type: memory, max_events: 10 }]
+profiles:
+  dev:
+    clients:
+      primary: { model: openai/gpt-4o-mini, rate_limit: null, api_key: "$${literal}" }
+"#;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_interpolation_and_typed_placeholders() {
+        let config = ConfigLoader::new()
+            .vars(vars(&[("OPENAI_KEY", "sk-1"), ("MAX_INFLIGHT", "8")]))
+            .load_str(BASE, ConfigFormat::Yaml)
+            .unwrap();
+        let primary = &config.clients["primary"];
+        assert_eq!(primary.api_key.as_deref(), Some("sk-1"));
+        assert_eq!(
+            primary.base_url.as_deref(),
+            Some("https://api.openai.com/v1")
+        );
+        assert_eq!(primary.max_inflight, Some(8));
+        assert_eq!(config.routing["chat"].strategy, RoutingStrategy::Weighted);
+        assert_eq!(config.routing["chat"].members[1].weight, 1.0);
+        assert_eq!(
+            config.telemetry.as_ref().unwrap().exporters[0],
+            TelemetryExporter::Console {
+                prefix: "[ai-lib]".into()
+            }
+        );
+        assert_eq!(
+            config.resolve_fallback("backup"),
+            Some("anthropic/claude-3-5-sonnet")
+        );
+    }
+
+    #[test]
+    fn test_profile_overlay_merges_and_removes_keys() {
+        // OPENAI_KEY is only referenced by the base value the profile replaces.
+        let config = ConfigLoader::new()
+            .vars(HashMap::new())
+            .profile("dev")
+            .load_str(BASE, ConfigFormat::Yaml)
+            .unwrap();
+        let primary = &config.clients["primary"];
+        assert_eq!(primary.model, "openai/gpt-4o-mini");
+        assert_eq!(primary.api_key.as_deref(), Some("${literal}"));
+        assert_eq!(primary.rate_limit, None);
+        assert_eq!(primary.fallbacks, vec!["backup".to_string()]);
+
+        let err = ConfigLoader::new()
+            .vars(HashMap::new())
+            .profile("staging")
+            .load_str(BASE, ConfigFormat::Yaml)
+            .unwrap_err();
+        assert!(err.to_string().contains("profile 'staging' is not defined"));
+        assert!(err.to_string().contains("available profiles: [dev]"));
+    }
+
+    #[test]
+    fn test_errors_point_to_config_path() {
+        let missing = ConfigLoader::new()
+            .vars(HashMap::new())
+            .load_str(BASE, ConfigFormat::Yaml)
+            .unwrap_err();
+        assert!(missing.to_string().contains("OPENAI_KEY is not set"));
+        assert!(missing.to_string().contains("clients.primary.api_key"));
+
+        let loader = ConfigLoader::new().vars(HashMap::new());
+        let cases = [
+            (
+                "clients: { a: { model: openai/x, max_inflight: many } }",
+                "clients.a.max_inflight",
+            ),
+            (
+                "clients: { a: { model: openai/x, fallbacks: [openai/y, nope] } }",
+                "clients.a.fallbacks[1]",
+            ),
+            (
+                "clients: { a: { model: openai/x } }\nrouting: { r: { members: [{ client: b }] } }",
+                "routing.r.members[0].client",
+            ),
+            (
+                "clients: { a: { model: openai/x } }\nguardrails: { rules: [{ pattern: '(' }] }",
+                "guardrails.rules[0].pattern",
+            ),
+            (
+                "clients: { a: { model: openai/x } }\nbudgets: { b: { max_cost: 1.0 } }",
+                "budgets.b.pricing",
+            ),
+            (
+                "clients: { a: { model: openai/x } }\ntelemetry: { exporters: [{ type: otlp }] }",
+                "telemetry.exporters[0]",
+            ),
+        ];
+        for (yaml, path) in cases {
+            let err = loader.load_str(yaml, ConfigFormat::Yaml).unwrap_err();
+            assert!(
+                err.to_string().contains(&format!("field: {}", path)),
+                "{} -> {}",
+                yaml,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_toml_documents() {
+        let config = ConfigLoader::new()
+            .vars(vars(&[("KEY_FILE", "/run/secrets/openai")]))
+            .load_str(
+                r#"
+[clients.primary]
+model = "openai/gpt-4o"
+api_key_file = "${KEY_FILE}"
+
+[budgets.monthly]
+max_tokens = 1000000
+"#,
+                ConfigFormat::Toml,
+            )
+            .unwrap();
+        assert_eq!(
+            config.clients["primary"].api_key_file.as_deref(),
+            Some("/run/secrets/openai")
+        );
+        assert_eq!(config.budgets["monthly"].max_tokens, Some(1_000_000));
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("app.TOML")),
+            ConfigFormat::Toml
+        );
+    }
+}
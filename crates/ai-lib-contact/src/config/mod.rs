@@ -0,0 +1,110 @@
+//! 声明式应用配置：用单个 YAML/TOML 文件描述具名客户端、回退链、路由组、缓存、护栏、预算与遥测导出器，
+//! 并据此构建完整的运行时栈。支持环境变量插值与 dev/prod 等配置档覆盖。
+//!
+//! # Declarative Application Config
+//!
+//! Describes the whole client stack in one YAML or TOML file and builds the runtime
+//! graph from it, instead of wiring `AiClientBuilder`, `GuardrailsConfig`,
+//! `CacheManager`, rate limiters and routing by hand.
+//!
+//! ## Key Components
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`AppConfig`] | Typed schema of the config file |
+//! | [`ConfigLoader`] | Parsing, `${VAR}` interpolation, profile overlays and path-aware validation |
+//! | [`AppStack`] | Built clients, routing groups, cache, guardrails, limiters, budgets and telemetry |
+//! | [`Budget`] | Token / cost budget shared by a set of clients |
+//!
+//! ## File Format
+//!
+//! ```yaml
+//! protocol_path: ./ai-protocol
+//! clients:
+//!   primary:
+//!     model: openai/gpt-4o
+//!     api_key_env: OPENAI_API_KEY        # or api_key / api_key_file; manifest chain otherwise
+//!     base_url: ${OPENAI_PROXY:-https://api.openai.com/v1}
+//!     fallbacks: [backup]                # client names or provider/model ids
+//!     rate_limit: { rps: 5, burst: 10 }
+//!     circuit_breaker: { failure_threshold: 5, cooldown_secs: 30 }
+//!   backup:
+//!     model: anthropic/claude-3-5-sonnet
+//! routing:
+//!   chat:
+//!     strategy: weighted
+//!     members: [{ client: primary, weight: 3 }, { client: backup }]
+//! cache: { backend: memory, ttl_secs: 300, max_entries: 1000 }
+//! guardrails:
+//!   pii_detection: true
+//!   rules:
+//!     - { keyword: "project x", action: block, category: confidential }
+//!     - { pattern: '\b\d{16}\b', action: sanitize }
+//! budgets:
+//!   daily:
+//!     clients: [primary]                 # empty = every client
+//!     max_tokens: 2000000
+//!     max_cost: 25.0
+//!     pricing: { input_per_1k: 0.005, output_per_1k: 0.015 }
+//!     window_secs: 86400
+//! telemetry:
+//!   exporters: [{ type: console, prefix: "[ai]" }, { type: memory, max_events: 1000 }]
+//! profiles:
+//!   dev:
+//!     clients: { primary: { model: openai/gpt-4o-mini, rate_limit: null } }
+//! ```
+//!
+//! `${VAR}` and `${VAR:-default}` are expanded in every string value after the
+//! profile overlay is applied (`$${` keeps a literal `${`). The profile comes from
+//! [`ConfigLoader::profile`] or `AI_LIB_PROFILE`; its values are deep-merged over
+//! the base document and `null` removes a key. Every error names the offending
+//! config path, e.g. `clients.primary.fallbacks[0]`.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ai_lib_contact::config::ConfigLoader;
+//! use ai_lib_core::Message;
+//!
+//! #[tokio::main]
+//! async fn main() -> ai_lib_core::Result<()> {
+//!     let config = ConfigLoader::new().profile("prod").load_file("ai-lib.yaml")?;
+//!     let stack = config.build().await?;
+//!
+//!     let (name, client) = stack.route("chat").expect("routing group");
+//!     stack.admit(name).await?;
+//!     let (_resp, stats) = client
+//!         .chat()
+//!         .messages(vec![Message::user("Hello")])
+//!         .execute_with_stats()
+//!         .await?;
+//!     stack.record_success(name, &stats);
+//!     Ok(())
+//! }
+//! ```
+
+mod budget;
+mod loader;
+mod schema;
+mod stack;
+
+pub use budget::Budget;
+pub use loader::{ConfigFormat, ConfigLoader, PROFILE_ENV};
+pub use schema::{
+    AppConfig, BudgetConfig, BudgetPricing, CacheBackendKind, CacheSettings,
+    CircuitBreakerSettings, ClientConfig, GuardrailRule, GuardrailSettings, RateLimitSettings,
+    RoutingGroupConfig, RoutingMember, RoutingStrategy, TelemetryExporter, TelemetrySettings,
+};
+pub use stack::AppStack;
+
+use ai_lib_core::{Error, ErrorContext};
+
+/// Configuration error pointing at `path` inside the config document.
+pub(crate) fn config_error(path: impl Into<String>, msg: impl Into<String>) -> Error {
+    Error::configuration_with_context(
+        msg,
+        ErrorContext::new()
+            .with_field_path(path)
+            .with_source("app_config"),
+    )
+}
@@ -0,0 +1,442 @@
+//! Typed schema of the application config file and its cross-reference checks.
+
+use super::config_error;
+use crate::guardrails::FilterAction;
+use ai_lib_core::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Top-level application config (after profile overlay and interpolation).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppConfig {
+    /// AI-Protocol directory or URL shared by every client.
+    #[serde(default)]
+    pub protocol_path: Option<String>,
+    #[serde(default)]
+    pub clients: BTreeMap<String, ClientConfig>,
+    #[serde(default)]
+    pub routing: BTreeMap<String, RoutingGroupConfig>,
+    #[serde(default)]
+    pub cache: Option<CacheSettings>,
+    #[serde(default)]
+    pub guardrails: Option<GuardrailSettings>,
+    #[serde(default)]
+    pub budgets: BTreeMap<String, BudgetConfig>,
+    #[serde(default)]
+    pub telemetry: Option<TelemetrySettings>,
+}
+
+/// A named client: one `provider/model` target plus its credentials and limits.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    pub model: String,
+    /// Literal credential. Prefer `api_key_env` or `api_key_file` outside of tests.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// File holding the credential (e.g. a mounted secret); surrounding whitespace is trimmed.
+    #[serde(default)]
+    pub api_key_file: Option<String>,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Other client names (resolved to their model) or `provider/model` ids.
+    #[serde(default)]
+    pub fallbacks: Vec<String>,
+    #[serde(default)]
+    pub max_inflight: Option<usize>,
+    /// Rewrite requests to fit each candidate model (see `AiClientBuilder::degrade_requests`).
+    #[serde(default)]
+    pub degrade: bool,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitSettings>,
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerSettings>,
+}
+
+/// Token-bucket limits applied by [`AppStack::admit`](super::AppStack::admit).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitSettings {
+    pub rps: f64,
+    /// Defaults to one second worth of requests.
+    #[serde(default)]
+    pub burst: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerSettings {
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_cooldown_secs() -> u64 {
+    30
+}
+
+/// A routing group balancing traffic across configured clients.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingGroupConfig {
+    #[serde(default)]
+    pub strategy: RoutingStrategy,
+    pub members: Vec<RoutingMember>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingStrategy {
+    #[default]
+    RoundRobin,
+    Weighted,
+    LeastConnections,
+    HealthBased,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingMember {
+    pub client: String,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheBackendKind {
+    #[default]
+    Memory,
+    /// Keeps the cache API wired but stores nothing.
+    None,
+}
+
+/// Response cache shared by the stack.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheSettings {
+    #[serde(default)]
+    pub backend: CacheBackendKind,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    #[serde(default)]
+    pub key_prefix: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_ttl_secs() -> u64 {
+    300
+}
+
+fn default_max_entries() -> usize {
+    1000
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            backend: CacheBackendKind::Memory,
+            enabled: true,
+            ttl_secs: default_ttl_secs(),
+            max_entries: default_max_entries(),
+            key_prefix: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuardrailSettings {
+    #[serde(default = "default_true")]
+    pub filter_input: bool,
+    #[serde(default)]
+    pub filter_output: bool,
+    #[serde(default)]
+    pub pii_detection: bool,
+    #[serde(default)]
+    pub stop_on_first_block: bool,
+    #[serde(default)]
+    pub rules: Vec<GuardrailRule>,
+}
+
+impl Default for GuardrailSettings {
+    fn default() -> Self {
+        Self {
+            filter_input: true,
+            filter_output: false,
+            pii_detection: false,
+            stop_on_first_block: false,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// One filter rule; exactly one of `keyword` and `pattern` must be set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuardrailRule {
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// Regular expression.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default = "default_action")]
+    pub action: FilterAction,
+    /// Keywords default to case-insensitive, patterns to case-sensitive.
+    #[serde(default)]
+    pub case_sensitive: Option<bool>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+fn default_action() -> FilterAction {
+    FilterAction::Block
+}
+
+/// Spend limit over the clients it names (every client when `clients` is empty).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub clients: Vec<String>,
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    /// Requires `pricing`.
+    #[serde(default)]
+    pub max_cost: Option<f64>,
+    #[serde(default)]
+    pub pricing: Option<BudgetPricing>,
+    /// Rolling window after which usage resets; unset means the lifetime of the stack.
+    #[serde(default)]
+    pub window_secs: Option<u64>,
+}
+
+/// Application-supplied rates used to turn token usage into cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetPricing {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetrySettings {
+    #[serde(default)]
+    pub exporters: Vec<TelemetryExporter>,
+}
+
+/// Feedback sinks every client reports to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TelemetryExporter {
+    Console {
+        #[serde(default = "default_console_prefix")]
+        prefix: String,
+    },
+    Memory {
+        #[serde(default = "default_max_events")]
+        max_events: usize,
+    },
+}
+
+fn default_console_prefix() -> String {
+    "[ai-lib]".to_string()
+}
+
+fn default_max_events() -> usize {
+    1000
+}
+
+impl AppConfig {
+    /// Model id a fallback entry refers to: another client's model, or the entry itself.
+    pub fn resolve_fallback<'a>(&'a self, entry: &'a str) -> Option<&'a str> {
+        match self.clients.get(entry) {
+            Some(client) => Some(client.model.as_str()),
+            None if entry.contains('/') => Some(entry),
+            None => None,
+        }
+    }
+
+    /// Check values and cross references; errors carry the config path.
+    pub fn validate(&self) -> Result<()> {
+        if self.clients.is_empty() {
+            return Err(config_error("clients", "at least one client is required"));
+        }
+        for (name, client) in &self.clients {
+            let path = format!("clients.{}", name);
+            if !client.model.contains('/') {
+                return Err(config_error(
+                    format!("{}.model", path),
+                    format!("model '{}' must be in provider/model form", client.model),
+                ));
+            }
+            let sources = [&client.api_key, &client.api_key_env, &client.api_key_file];
+            if sources.iter().filter(|s| s.is_some()).count() > 1 {
+                return Err(config_error(
+                    path,
+                    "set at most one of api_key, api_key_env and api_key_file",
+                ));
+            }
+            for (i, entry) in client.fallbacks.iter().enumerate() {
+                let entry_path = format!("{}.fallbacks[{}]", path, i);
+                if entry == name {
+                    return Err(config_error(
+                        entry_path,
+                        "a client cannot fall back to itself",
+                    ));
+                }
+                if self.resolve_fallback(entry).is_none() {
+                    return Err(config_error(
+                        entry_path,
+                        format!(
+                            "'{}' is neither a configured client nor a provider/model id",
+                            entry
+                        ),
+                    ));
+                }
+            }
+            if client.max_inflight == Some(0) {
+                return Err(config_error(
+                    format!("{}.max_inflight", path),
+                    "must be greater than zero",
+                ));
+            }
+            if let Some(limit) = &client.rate_limit {
+                if !limit.rps.is_finite() || limit.rps <= 0.0 {
+                    return Err(config_error(
+                        format!("{}.rate_limit.rps", path),
+                        "must be a positive number",
+                    ));
+                }
+                if limit.burst.is_some_and(|b| !b.is_finite() || b < 1.0) {
+                    return Err(config_error(
+                        format!("{}.rate_limit.burst", path),
+                        "must be at least 1",
+                    ));
+                }
+            }
+            if client
+                .circuit_breaker
+                .is_some_and(|cb| cb.failure_threshold == 0)
+            {
+                return Err(config_error(
+                    format!("{}.circuit_breaker.failure_threshold", path),
+                    "must be greater than zero",
+                ));
+            }
+        }
+
+        for (name, group) in &self.routing {
+            let path = format!("routing.{}", name);
+            if group.members.is_empty() {
+                return Err(config_error(
+                    format!("{}.members", path),
+                    "a routing group needs at least one member",
+                ));
+            }
+            for (i, member) in group.members.iter().enumerate() {
+                let member_path = format!("{}.members[{}]", path, i);
+                if !self.clients.contains_key(&member.client) {
+                    return Err(config_error(
+                        format!("{}.client", member_path),
+                        format!("unknown client '{}'", member.client),
+                    ));
+                }
+                if !member.weight.is_finite() || member.weight <= 0.0 {
+                    return Err(config_error(
+                        format!("{}.weight", member_path),
+                        "must be a positive number",
+                    ));
+                }
+            }
+        }
+
+        if let Some(cache) = &self.cache {
+            if cache.backend == CacheBackendKind::Memory && cache.max_entries == 0 {
+                return Err(config_error(
+                    "cache.max_entries",
+                    "must be greater than zero",
+                ));
+            }
+        }
+
+        if let Some(guardrails) = &self.guardrails {
+            for (i, rule) in guardrails.rules.iter().enumerate() {
+                let path = format!("guardrails.rules[{}]", i);
+                match (&rule.keyword, &rule.pattern) {
+                    (Some(_), None) => {}
+                    (None, Some(pattern)) => {
+                        if let Err(e) = regex::Regex::new(pattern) {
+                            return Err(config_error(
+                                format!("{}.pattern", path),
+                                format!("invalid regular expression: {}", e),
+                            ));
+                        }
+                    }
+                    _ => return Err(config_error(path, "set exactly one of keyword and pattern")),
+                }
+            }
+        }
+
+        for (name, budget) in &self.budgets {
+            let path = format!("budgets.{}", name);
+            for (i, client) in budget.clients.iter().enumerate() {
+                if !self.clients.contains_key(client) {
+                    return Err(config_error(
+                        format!("{}.clients[{}]", path, i),
+                        format!("unknown client '{}'", client),
+                    ));
+                }
+            }
+            if budget.max_tokens.is_none() && budget.max_cost.is_none() {
+                return Err(config_error(path, "set max_tokens, max_cost or both"));
+            }
+            if budget.max_cost.is_some() && budget.pricing.is_none() {
+                return Err(config_error(
+                    format!("{}.pricing", path),
+                    "max_cost needs pricing to convert token usage into cost",
+                ));
+            }
+            if budget.window_secs == Some(0) {
+                return Err(config_error(
+                    format!("{}.window_secs", path),
+                    "must be greater than zero",
+                ));
+            }
+        }
+
+        if let Some(telemetry) = &self.telemetry {
+            for (i, exporter) in telemetry.exporters.iter().enumerate() {
+                if matches!(exporter, TelemetryExporter::Memory { max_events: 0 }) {
+                    return Err(config_error(
+                        format!("telemetry.exporters[{}].max_events", i),
+                        "must be greater than zero",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
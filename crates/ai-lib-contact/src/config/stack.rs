@@ -0,0 +1,379 @@
+//! Runtime graph built from an [`AppConfig`].
+
+use super::budget::Budget;
+use super::config_error;
+use super::schema::{
+    AppConfig, CacheBackendKind, ClientConfig, GuardrailSettings, RoutingGroupConfig,
+    RoutingStrategy, TelemetryExporter,
+};
+use crate::cache::{CacheConfig, CacheManager, MemoryCache, NullCache};
+use crate::guardrails::{FilterRule, Guardrails, GuardrailsConfig};
+use crate::resilience::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use crate::resilience::rate_limiter::{RateLimiter, RateLimiterConfig};
+use crate::routing::{LoadBalancingStrategy, ModelArray, ModelEndpoint};
+use crate::telemetry::{
+    CompositeFeedbackSink, ConsoleFeedbackSink, FeedbackSink, InMemoryFeedbackSink,
+};
+use ai_lib_core::{AiClient, AiClientBuilder, CallStats, Result};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct ManagedClient {
+    client: AiClient,
+    rate_limiter: Option<RateLimiter>,
+    circuit_breaker: Option<CircuitBreaker>,
+}
+
+/// Everything an [`AppConfig`] describes, built and ready to use.
+///
+/// Clients are plain [`AiClient`]s; the stack adds the policy around them.
+/// Call [`AppStack::admit`] before a request and [`AppStack::record_success`] /
+/// [`AppStack::record_failure`] after it to apply budgets, circuit breakers and
+/// rate limits.
+pub struct AppStack {
+    clients: BTreeMap<String, ManagedClient>,
+    routes: BTreeMap<String, Mutex<ModelArray>>,
+    cache: Option<CacheManager>,
+    guardrails: Option<Guardrails>,
+    budgets: Vec<Budget>,
+    feedback: Option<Arc<dyn FeedbackSink>>,
+    memory_sink: Option<Arc<InMemoryFeedbackSink>>,
+}
+
+impl AppConfig {
+    /// Build every client and policy component.
+    pub async fn build(&self) -> Result<AppStack> {
+        self.build_with(AiClientBuilder::new).await
+    }
+
+    /// Like [`AppConfig::build`], starting each client from `base()` so callers can
+    /// share manifests, transports or cassettes the file does not describe.
+    pub async fn build_with<F>(&self, base: F) -> Result<AppStack>
+    where
+        F: Fn() -> AiClientBuilder,
+    {
+        self.validate()?;
+
+        let (feedback, memory_sink) = match &self.telemetry {
+            Some(telemetry) if !telemetry.exporters.is_empty() => {
+                let mut composite = CompositeFeedbackSink::new();
+                let mut memory_sink = None;
+                for exporter in &telemetry.exporters {
+                    composite = match exporter {
+                        TelemetryExporter::Console { prefix } => {
+                            composite.add_sink(Arc::new(ConsoleFeedbackSink::new(prefix.clone())))
+                        }
+                        TelemetryExporter::Memory { max_events } => {
+                            let sink = Arc::new(InMemoryFeedbackSink::new(*max_events));
+                            memory_sink.get_or_insert_with(|| sink.clone());
+                            composite.add_sink(sink)
+                        }
+                    };
+                }
+                let feedback: Arc<dyn FeedbackSink> = Arc::new(composite);
+                (Some(feedback), memory_sink)
+            }
+            _ => (None, None),
+        };
+
+        let mut clients = BTreeMap::new();
+        for (name, config) in &self.clients {
+            let client = self
+                .build_client(name, config, base(), feedback.clone())
+                .await?;
+            clients.insert(
+                name.clone(),
+                ManagedClient {
+                    client,
+                    rate_limiter: config.rate_limit.map(|limit| {
+                        RateLimiter::new(RateLimiterConfig {
+                            rps: limit.rps,
+                            burst: limit.burst.unwrap_or(limit.rps.max(1.0)),
+                        })
+                    }),
+                    circuit_breaker: config.circuit_breaker.map(|cb| {
+                        CircuitBreaker::new(CircuitBreakerConfig {
+                            failure_threshold: cb.failure_threshold,
+                            cooldown: Duration::from_secs(cb.cooldown_secs),
+                        })
+                    }),
+                },
+            );
+        }
+
+        let routes = self
+            .routing
+            .iter()
+            .map(|(name, group)| (name.clone(), Mutex::new(self.model_array(name, group))))
+            .collect();
+
+        let cache = self.cache.as_ref().map(|cache| {
+            let mut config = CacheConfig::new()
+                .with_enabled(cache.enabled)
+                .with_ttl(Duration::from_secs(cache.ttl_secs));
+            if let Some(prefix) = &cache.key_prefix {
+                config = config.with_key_prefix(prefix.clone());
+            }
+            let backend: Box<dyn crate::cache::CacheBackend> = match cache.backend {
+                CacheBackendKind::Memory => Box::new(MemoryCache::new(cache.max_entries)),
+                CacheBackendKind::None => Box::new(NullCache::new()),
+            };
+            CacheManager::new(config, backend)
+        });
+
+        Ok(AppStack {
+            clients,
+            routes,
+            cache,
+            guardrails: self.guardrails.as_ref().map(guardrails),
+            budgets: self
+                .budgets
+                .iter()
+                .map(|(name, budget)| Budget::new(name.clone(), budget))
+                .collect(),
+            feedback,
+            memory_sink,
+        })
+    }
+
+    async fn build_client(
+        &self,
+        name: &str,
+        config: &ClientConfig,
+        mut builder: AiClientBuilder,
+        feedback: Option<Arc<dyn FeedbackSink>>,
+    ) -> Result<AiClient> {
+        let path = format!("clients.{}", name);
+        let mut fallbacks = Vec::with_capacity(config.fallbacks.len());
+        for entry in &config.fallbacks {
+            let Some(model) = self.resolve_fallback(entry) else {
+                continue;
+            };
+            fallbacks.push(model.to_string());
+            // A fallback naming another client uses that client's key and endpoint.
+            if let Some(target) = self.clients.get(entry) {
+                let target_path = format!("clients.{}", entry);
+                if let Some(credential) = credential(&target_path, target)? {
+                    builder = builder.fallback_credential(model, credential);
+                }
+                if let Some(base_url) = &target.base_url {
+                    builder = builder.fallback_base_url(model, base_url.clone());
+                }
+            }
+        }
+        builder = builder.with_fallbacks(fallbacks);
+        if let Some(protocol_path) = &self.protocol_path {
+            builder = builder.protocol_path(protocol_path.clone());
+        }
+        if let Some(credential) = credential(&path, config)? {
+            builder = builder.credential(credential);
+        }
+        if let Some(base_url) = &config.base_url {
+            builder = builder.base_url_override(base_url.clone());
+        }
+        if let Some(n) = config.max_inflight {
+            builder = builder.max_inflight(n);
+        }
+        if config.degrade {
+            builder = builder.degrade_requests(true);
+        }
+        if let Some(sink) = feedback {
+            builder = builder.feedback_sink(sink);
+        }
+        builder
+            .build(&config.model)
+            .await
+            .map_err(|e| config_error(path, format!("cannot build client '{}': {}", name, e)))
+    }
+
+    fn model_array(&self, name: &str, group: &RoutingGroupConfig) -> ModelArray {
+        let strategy = match group.strategy {
+            RoutingStrategy::RoundRobin => LoadBalancingStrategy::RoundRobin,
+            RoutingStrategy::Weighted => LoadBalancingStrategy::Weighted,
+            RoutingStrategy::LeastConnections => LoadBalancingStrategy::LeastConnections,
+            RoutingStrategy::HealthBased => LoadBalancingStrategy::HealthBased,
+        };
+        let mut array = ModelArray::new(name).with_strategy(strategy);
+        for member in &group.members {
+            let client = &self.clients[&member.client];
+            array.add_endpoint(ModelEndpoint {
+                name: member.client.clone(),
+                model_name: client.model.clone(),
+                url: client.base_url.clone().unwrap_or_default(),
+                weight: member.weight,
+                healthy: true,
+                connection_count: 0,
+            });
+        }
+        array
+    }
+}
+
+fn credential(path: &str, config: &ClientConfig) -> Result<Option<String>> {
+    if let Some(key) = &config.api_key {
+        return Ok(Some(key.clone()));
+    }
+    if let Some(var) = &config.api_key_env {
+        return std::env::var(var).map(Some).map_err(|_| {
+            config_error(
+                format!("{}.api_key_env", path),
+                format!("environment variable {} is not set", var),
+            )
+        });
+    }
+    if let Some(file) = &config.api_key_file {
+        return std::fs::read_to_string(file)
+            .map(|key| Some(key.trim().to_string()))
+            .map_err(|e| {
+                config_error(
+                    format!("{}.api_key_file", path),
+                    format!("cannot read {}: {}", file, e),
+                )
+            });
+    }
+    Ok(None)
+}
+
+fn guardrails(settings: &GuardrailSettings) -> Guardrails {
+    let mut builder = GuardrailsConfig::builder();
+    for rule in &settings.rules {
+        let mut filter = match (&rule.keyword, &rule.pattern) {
+            (Some(keyword), _) => FilterRule::keyword(keyword.clone(), rule.action),
+            (None, Some(pattern)) => FilterRule::regex(pattern.clone(), rule.action),
+            (None, None) => continue,
+        };
+        if let Some(case_sensitive) = rule.case_sensitive {
+            filter = filter.case_sensitive(case_sensitive);
+        }
+        if let Some(category) = &rule.category {
+            filter = filter.with_category(category.clone());
+        }
+        if let Some(description) = &rule.description {
+            filter = filter.with_description(description.clone());
+        }
+        builder = builder.add_rule(filter);
+    }
+    // Applied after the rules: `add_rule` switches input filtering on.
+    Guardrails::new(
+        builder
+            .filter_input(settings.filter_input)
+            .filter_output(settings.filter_output)
+            .enable_pii_detection(settings.pii_detection)
+            .stop_on_first_block(settings.stop_on_first_block)
+            .build(),
+    )
+}
+
+impl AppStack {
+    /// Load, validate and build a config file in one step (see [`super::ConfigLoader`]).
+    pub async fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        AppConfig::from_file(path)?.build().await
+    }
+
+    pub fn client(&self, name: &str) -> Option<&AiClient> {
+        self.clients.get(name).map(|c| &c.client)
+    }
+
+    pub fn client_names(&self) -> impl Iterator<Item = &str> {
+        self.clients.keys().map(String::as_str)
+    }
+
+    /// Pick a healthy member of a routing group; returns the client name and client.
+    pub fn route(&self, group: &str) -> Option<(&str, &AiClient)> {
+        let mut array = self
+            .routes
+            .get(group)?
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let endpoint = array.select_endpoint()?.name.clone();
+        self.clients
+            .get_key_value(&endpoint)
+            .map(|(name, c)| (name.as_str(), &c.client))
+    }
+
+    /// Take a member out of (or back into) rotation for every group containing it.
+    pub fn set_healthy(&self, client: &str, healthy: bool) {
+        for array in self.routes.values() {
+            let mut array = array.lock().unwrap_or_else(|e| e.into_inner());
+            if healthy {
+                array.mark_healthy(client);
+            } else {
+                array.mark_unhealthy(client);
+            }
+        }
+    }
+
+    pub fn cache(&self) -> Option<&CacheManager> {
+        self.cache.as_ref()
+    }
+
+    pub fn guardrails(&self) -> Option<&Guardrails> {
+        self.guardrails.as_ref()
+    }
+
+    pub fn budget(&self, name: &str) -> Option<&Budget> {
+        self.budgets.iter().find(|b| b.name() == name)
+    }
+
+    pub fn budgets(&self) -> &[Budget] {
+        &self.budgets
+    }
+
+    pub fn rate_limiter(&self, client: &str) -> Option<&RateLimiter> {
+        self.clients.get(client)?.rate_limiter.as_ref()
+    }
+
+    pub fn circuit_breaker(&self, client: &str) -> Option<&CircuitBreaker> {
+        self.clients.get(client)?.circuit_breaker.as_ref()
+    }
+
+    /// Composite sink built from `telemetry.exporters`, shared by every client.
+    pub fn feedback_sink(&self) -> Option<Arc<dyn FeedbackSink>> {
+        self.feedback.clone()
+    }
+
+    /// The first `memory` exporter, for inspecting recorded events.
+    pub fn memory_sink(&self) -> Option<Arc<InMemoryFeedbackSink>> {
+        self.memory_sink.clone()
+    }
+
+    /// Gate a request through `client`: budgets, then the circuit breaker, then the
+    /// rate limiter (which may wait for a token).
+    pub async fn admit(&self, client: &str) -> Result<()> {
+        let managed = self.clients.get(client).ok_or_else(|| {
+            config_error(
+                format!("clients.{}", client),
+                format!("unknown client '{}'", client),
+            )
+        })?;
+        for budget in self.budgets.iter().filter(|b| b.applies_to(client)) {
+            budget.check()?;
+        }
+        if let Some(breaker) = &managed.circuit_breaker {
+            breaker.allow()?;
+        }
+        if let Some(limiter) = &managed.rate_limiter {
+            limiter.acquire().await?;
+        }
+        Ok(())
+    }
+
+    /// Charge usage to the client's budgets and close its circuit breaker.
+    pub fn record_success(&self, client: &str, stats: &CallStats) {
+        if let Some(breaker) = self.circuit_breaker(client) {
+            breaker.on_success();
+        }
+        if let Some(usage) = &stats.usage {
+            for budget in self.budgets.iter().filter(|b| b.applies_to(client)) {
+                budget.record_usage(usage);
+            }
+        }
+    }
+
+    pub fn record_failure(&self, client: &str) {
+        if let Some(breaker) = self.circuit_breaker(client) {
+            breaker.on_failure();
+        }
+    }
+}
@@ -32,6 +32,7 @@
 //! | [`KeywordFilter`] | Simple keyword-based filtering |
 //! | [`PatternFilter`] | Regex-based pattern matching |
 //! | [`PiiDetector`] | Detection of personally identifiable information |
+//! | [`Pseudonymizer`] | Reversible PII replacement with per-request [`PiiVault`] tokens |
 //! | [`CheckResult`] | Result of content checking with violations |
 //!
 //! ## Example
@@ -57,6 +58,42 @@
 //! let sanitized = guardrails.sanitize("Email: user@example.com");
 //! ```
 //!
+//! ## Reversible pseudonymization
+//!
+//! When the user must still see the original values, replace them with stable
+//! tokens before the request leaves the process and restore them afterwards:
+//!
+//! ```rust
+//! use ai_lib_contact::guardrails::{PiiVault, Pseudonymizer};
+//!
+//! let pseudonymizer = Pseudonymizer::new().with_cn_id_numbers().with_iban();
+//! let mut vault = PiiVault::new();
+//!
+//! let outbound = pseudonymizer.pseudonymize("Reply to jane@example.com", &mut vault);
+//! let token = format!("<EMAIL_1_{}>", vault.nonce());
+//! assert_eq!(outbound, format!("Reply to {token}"));
+//!
+//! // ... provider answers using the token ...
+//! assert_eq!(vault.restore(&format!("Sent to {token}")), "Sent to jane@example.com");
+//! ```
+//!
+//! To do this on every chat call, register it as the client's message guard; each
+//! call gets its own vault and the response or stream is restored:
+//!
+//! ```rust,no_run
+//! use ai_lib_contact::guardrails::Pseudonymizer;
+//! use ai_lib_core::client::AiClientBuilder;
+//! use std::sync::Arc;
+//!
+//! # async fn demo() -> ai_lib_core::Result<()> {
+//! let client = AiClientBuilder::new()
+//!     .message_guard(Arc::new(Pseudonymizer::new()))
+//!     .build("openai/gpt-4o")
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Presets
 //!
 //! - [`Guardrails::permissive()`] - Allow all content (for development)
@@ -65,11 +102,16 @@
 mod config;
 mod filters;
 mod pii;
+mod pseudonymize;
 mod result;
 
 pub use config::{FilterAction, FilterRule, GuardrailsConfig, GuardrailsConfigBuilder};
 pub use filters::{ContentFilter, KeywordFilter, PatternFilter};
 pub use pii::PiiDetector;
+pub use pseudonymize::{
+    restore_stream, EntityRecognizer, EntitySpan, PiiVault, Pseudonymizer, RegexRecognizer,
+    StreamRestorer,
+};
 pub use result::{CheckResult, Violation, ViolationType};
 
 use ai_lib_core::types::message::Message;
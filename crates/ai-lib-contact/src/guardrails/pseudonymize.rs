@@ -0,0 +1,936 @@
+//! Reversible PII pseudonymization.
+//! 可逆 PII 假名化：请求发出前替换为稳定令牌，响应与流式增量中还原。
+//!
+//! [`Pseudonymizer`] replaces detected entities with stable tokens such as
+//! `<EMAIL_1_3f9a2c7e>` and records the mapping in a per-request [`PiiVault`].
+//! The trailing nonce is random per vault, so text that merely looks like a token
+//! (`<EMAIL_1>` typed by the user or echoed by the model) is never mistaken for
+//! one. The vault never leaves the process; it is used to restore originals in the final
+//! [`UnifiedResponse`] and, through [`StreamRestorer`] / [`restore_stream`], in
+//! streaming deltas where a token may be split across several chunks.
+//!
+//! As a [`MessageGuard`] (see `AiClientBuilder::message_guard`), a
+//! [`Pseudonymizer`] does this for every chat call of a client.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+
+use ai_lib_core::client::{GuardedCall, MessageGuard, UnifiedResponse};
+use ai_lib_core::types::events::StreamingEvent;
+use ai_lib_core::types::message::{ContentBlock, Message, MessageContent};
+use ai_lib_core::BoxStream;
+use once_cell::sync::Lazy;
+
+/// Matches a pseudonymization token (`<EMAIL_1_3f9a2c7e>`, `<CN_ID_12_3f9a2c7e>`).
+static TOKEN_PATTERN: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r"<([A-Z][A-Z0-9_]*)_([0-9]+)_([0-9a-z]+)>").unwrap());
+
+/// Longest partial token a [`StreamRestorer`] will hold back before giving up.
+const MAX_PENDING_TOKEN_LEN: usize = 64;
+
+/// A span of text recognized as a PII entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntitySpan {
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset one past the last character
+    pub end: usize,
+}
+
+/// Recognizer for one kind of PII entity.
+///
+/// Implement this for entities the built-in recognizers do not cover
+/// (employee IDs, internal account numbers, ...).
+pub trait EntityRecognizer: Send + Sync {
+    /// Entity label used in tokens, e.g. `EMAIL` produces `<EMAIL_1_3f9a2c7e>`.
+    ///
+    /// Must match `[A-Z][A-Z0-9_]*` so restored output can find the token again.
+    fn entity_type(&self) -> &str;
+
+    /// Find all occurrences of the entity in `text`.
+    fn find(&self, text: &str) -> Vec<EntitySpan>;
+}
+
+/// Regex-based recognizer with an optional validator (checksum, length, ...).
+#[derive(Clone)]
+pub struct RegexRecognizer {
+    entity_type: String,
+    pattern: regex::Regex,
+    validator: Option<fn(&str) -> bool>,
+}
+
+impl RegexRecognizer {
+    /// Create a recognizer from a regex pattern.
+    pub fn new(entity_type: impl Into<String>, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            entity_type: entity_type.into(),
+            pattern: regex::Regex::new(pattern)?,
+            validator: None,
+        })
+    }
+
+    /// Only accept matches for which `validator` returns true.
+    pub fn with_validator(mut self, validator: fn(&str) -> bool) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Email addresses.
+    pub fn email() -> Self {
+        Self::builtin(
+            "EMAIL",
+            r"[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}",
+            None,
+        )
+    }
+
+    /// Phone numbers (North American and international `+` formats).
+    pub fn phone() -> Self {
+        Self::builtin(
+            "PHONE",
+            r"(?:\+[0-9]{1,3}[-.\s]?)?(?:\(?[0-9]{3}\)?[-.\s]?)?[0-9]{3}[-.\s]?[0-9]{4}\b",
+            Some(|s| s.chars().filter(|c| c.is_ascii_digit()).count() >= 10),
+        )
+    }
+
+    /// Payment card numbers (Luhn-validated).
+    pub fn credit_card() -> Self {
+        Self::builtin(
+            "CREDIT_CARD",
+            r"\b(?:[0-9][ -]?){12,18}[0-9]\b",
+            Some(luhn_valid),
+        )
+    }
+
+    /// US Social Security Numbers.
+    pub fn ssn() -> Self {
+        Self::builtin("SSN", r"\b[0-9]{3}-[0-9]{2}-[0-9]{4}\b", None)
+    }
+
+    /// IPv4 addresses.
+    pub fn ip_address() -> Self {
+        Self::builtin(
+            "IP_ADDRESS",
+            r"\b(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\b",
+            None,
+        )
+    }
+
+    /// Mainland China resident identity numbers (GB 11643, checksum-validated).
+    pub fn cn_id_number() -> Self {
+        Self::builtin(
+            "CN_ID",
+            r"\b[1-9][0-9]{5}(?:18|19|20)[0-9]{2}(?:0[1-9]|1[0-2])(?:0[1-9]|[12][0-9]|3[01])[0-9]{3}[0-9Xx]\b",
+            Some(cn_id_valid),
+        )
+    }
+
+    /// International Bank Account Numbers (ISO 13616, mod-97 validated).
+    pub fn iban() -> Self {
+        Self::builtin(
+            "IBAN",
+            r"\b[A-Z]{2}[0-9]{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,4})?\b",
+            Some(iban_valid),
+        )
+    }
+
+    fn builtin(entity_type: &str, pattern: &str, validator: Option<fn(&str) -> bool>) -> Self {
+        Self {
+            entity_type: entity_type.to_string(),
+            pattern: regex::Regex::new(pattern).expect("built-in PII pattern must compile"),
+            validator,
+        }
+    }
+}
+
+impl fmt::Debug for RegexRecognizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegexRecognizer")
+            .field("entity_type", &self.entity_type)
+            .field("pattern", &self.pattern.as_str())
+            .finish()
+    }
+}
+
+impl EntityRecognizer for RegexRecognizer {
+    fn entity_type(&self) -> &str {
+        &self.entity_type
+    }
+
+    fn find(&self, text: &str) -> Vec<EntitySpan> {
+        self.pattern
+            .find_iter(text)
+            .filter(|m| self.validator.map_or(true, |v| v(m.as_str())))
+            .map(|m| EntitySpan {
+                start: m.start(),
+                end: m.end(),
+            })
+            .collect()
+    }
+}
+
+/// Per-request mapping between original values and their tokens.
+///
+/// Create one vault per request, pass it to [`Pseudonymizer`] on the way out and
+/// use it to restore the response on the way back. The same original value
+/// always maps to the same token within a vault, and every token carries the
+/// vault's nonce.
+#[derive(Debug, Clone)]
+pub struct PiiVault {
+    nonce: String,
+    by_original: HashMap<(String, String), String>,
+    by_token: HashMap<String, String>,
+    counters: HashMap<String, u32>,
+}
+
+impl Default for PiiVault {
+    fn default() -> Self {
+        Self::with_nonce(random_nonce())
+    }
+}
+
+impl PiiVault {
+    /// Create an empty vault with a random nonce.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty vault whose tokens end in `nonce` (lowercase ASCII letters
+    /// and digits), e.g. for deterministic tests.
+    pub fn with_nonce(nonce: impl Into<String>) -> Self {
+        Self {
+            nonce: nonce.into(),
+            by_original: HashMap::new(),
+            by_token: HashMap::new(),
+            counters: HashMap::new(),
+        }
+    }
+
+    /// Suffix shared by every token this vault issues.
+    pub fn nonce(&self) -> &str {
+        &self.nonce
+    }
+
+    /// Number of distinct values recorded.
+    pub fn len(&self) -> usize {
+        self.by_token.len()
+    }
+
+    /// Whether no values have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.by_token.is_empty()
+    }
+
+    /// Original value for a token, if the token was issued by this vault.
+    pub fn original(&self, token: &str) -> Option<&str> {
+        self.by_token.get(token).map(String::as_str)
+    }
+
+    /// Return the token for `original`, issuing a new one if needed.
+    pub fn tokenize(&mut self, entity_type: &str, original: &str) -> String {
+        let key = (entity_type.to_string(), original.to_string());
+        if let Some(token) = self.by_original.get(&key) {
+            return token.clone();
+        }
+        let counter = self.counters.entry(entity_type.to_string()).or_insert(0);
+        *counter += 1;
+        let token = format!("<{}_{}_{}>", entity_type, counter, self.nonce);
+        self.by_token.insert(token.clone(), original.to_string());
+        self.by_original.insert(key, token.clone());
+        token
+    }
+
+    /// Replace every token issued by this vault with its original value.
+    ///
+    /// Tokens this vault did not issue are left untouched.
+    pub fn restore(&self, text: &str) -> String {
+        if self.by_token.is_empty() || !text.contains('<') {
+            return text.to_string();
+        }
+        TOKEN_PATTERN
+            .replace_all(text, |caps: &regex::Captures| {
+                self.by_token
+                    .get(&caps[0])
+                    .cloned()
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+
+    /// Restore string values anywhere inside a JSON value (tool call arguments).
+    pub fn restore_json(&self, value: &mut serde_json::Value) {
+        map_json_strings(value, &mut |s| self.restore(s));
+    }
+
+    /// Restore content and tool call arguments of a complete response.
+    pub fn restore_response(&self, response: &mut UnifiedResponse) {
+        response.content = self.restore(&response.content);
+        for call in &mut response.tool_calls {
+            self.restore_json(&mut call.arguments);
+        }
+    }
+}
+
+/// Replaces PII with vault tokens before content leaves the process.
+#[derive(Clone)]
+pub struct Pseudonymizer {
+    recognizers: Vec<Arc<dyn EntityRecognizer>>,
+    nonce: Option<String>,
+}
+
+impl Pseudonymizer {
+    /// Create a pseudonymizer with the default recognizers
+    /// (email, credit card, SSN, phone, IP address).
+    pub fn new() -> Self {
+        Self::empty()
+            .with_recognizer(RegexRecognizer::email())
+            .with_recognizer(RegexRecognizer::credit_card())
+            .with_recognizer(RegexRecognizer::ssn())
+            .with_recognizer(RegexRecognizer::phone())
+            .with_recognizer(RegexRecognizer::ip_address())
+    }
+
+    /// Create a pseudonymizer with no recognizers.
+    pub fn empty() -> Self {
+        Self {
+            recognizers: Vec::new(),
+            nonce: None,
+        }
+    }
+
+    /// Give every per-call vault created as a [`MessageGuard`] this nonce instead of
+    /// a random one (see [`PiiVault::with_nonce`]); meant for deterministic tests.
+    pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// Add a recognizer. When spans overlap, the earliest and then longest
+    /// match wins; ties go to the recognizer added first.
+    pub fn with_recognizer<R: EntityRecognizer + 'static>(mut self, recognizer: R) -> Self {
+        self.recognizers.push(Arc::new(recognizer));
+        self
+    }
+
+    /// Add mainland China resident identity number recognition.
+    pub fn with_cn_id_numbers(self) -> Self {
+        self.with_recognizer(RegexRecognizer::cn_id_number())
+    }
+
+    /// Add IBAN recognition.
+    pub fn with_iban(self) -> Self {
+        self.with_recognizer(RegexRecognizer::iban())
+    }
+
+    /// Entity types this pseudonymizer recognizes, in priority order.
+    pub fn entity_types(&self) -> Vec<&str> {
+        self.recognizers.iter().map(|r| r.entity_type()).collect()
+    }
+
+    /// Replace every recognized entity in `text` with its vault token.
+    pub fn pseudonymize(&self, text: &str, vault: &mut PiiVault) -> String {
+        let spans = self.resolve_spans(text);
+        if spans.is_empty() {
+            return text.to_string();
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for (span, idx) in spans {
+            out.push_str(&text[cursor..span.start]);
+            let entity_type = self.recognizers[idx].entity_type();
+            out.push_str(&vault.tokenize(entity_type, &text[span.start..span.end]));
+            cursor = span.end;
+        }
+        out.push_str(&text[cursor..]);
+        out
+    }
+
+    /// Pseudonymize text, text blocks and tool payloads of every message in place.
+    ///
+    /// Media blocks (images, audio, documents) are left as-is.
+    pub fn pseudonymize_messages(&self, messages: &mut [Message], vault: &mut PiiVault) {
+        for message in messages {
+            match &mut message.content {
+                MessageContent::Text(text) => *text = self.pseudonymize(text, vault),
+                MessageContent::Blocks(blocks) => {
+                    for block in blocks {
+                        match block {
+                            ContentBlock::Text { text } => *text = self.pseudonymize(text, vault),
+                            ContentBlock::ToolUse { input, .. } => {
+                                map_json_strings(input, &mut |s| self.pseudonymize(s, vault))
+                            }
+                            ContentBlock::ToolResult { content, .. } => {
+                                map_json_strings(content, &mut |s| self.pseudonymize(s, vault))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collect non-overlapping spans, sorted by position, tagged with recognizer index.
+    fn resolve_spans(&self, text: &str) -> Vec<(EntitySpan, usize)> {
+        let mut candidates: Vec<(EntitySpan, usize)> = self
+            .recognizers
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, r)| r.find(text).into_iter().map(move |s| (s, idx)))
+            .filter(|(s, _)| {
+                s.start < s.end && text.is_char_boundary(s.start) && text.is_char_boundary(s.end)
+            })
+            .collect();
+        candidates.sort_by(|(a, ai), (b, bi)| {
+            a.start
+                .cmp(&b.start)
+                .then((b.end - b.start).cmp(&(a.end - a.start)))
+                .then(ai.cmp(bi))
+        });
+
+        let mut resolved = Vec::with_capacity(candidates.len());
+        let mut last_end = 0;
+        for (span, idx) in candidates {
+            if span.start >= last_end {
+                last_end = span.end;
+                resolved.push((span, idx));
+            }
+        }
+        resolved
+    }
+}
+
+impl MessageGuard for Pseudonymizer {
+    fn protect(&self, messages: &mut [Message]) -> Arc<dyn GuardedCall> {
+        let mut vault = match &self.nonce {
+            Some(nonce) => PiiVault::with_nonce(nonce.clone()),
+            None => PiiVault::new(),
+        };
+        self.pseudonymize_messages(messages, &mut vault);
+        Arc::new(VaultRestore(Arc::new(vault)))
+    }
+}
+
+/// Restores one guarded call from its vault.
+struct VaultRestore(Arc<PiiVault>);
+
+impl GuardedCall for VaultRestore {
+    fn restore_response(&self, response: &mut UnifiedResponse) {
+        self.0.restore_response(response);
+    }
+
+    fn restore_stream(
+        &self,
+        stream: BoxStream<'static, StreamingEvent>,
+    ) -> BoxStream<'static, StreamingEvent> {
+        restore_stream(stream, self.0.clone())
+    }
+}
+
+impl Default for Pseudonymizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Pseudonymizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pseudonymizer")
+            .field("entity_types", &self.entity_types())
+            .finish()
+    }
+}
+
+/// Incremental restorer for streamed text.
+///
+/// Tokens can arrive split across chunks (`"<EMA"`, `"IL_1_3f9a> ok"`); the restorer
+/// holds back a trailing partial token until it is complete or proven not to be one.
+#[derive(Debug, Clone)]
+pub struct StreamRestorer {
+    vault: Arc<PiiVault>,
+    pending: String,
+}
+
+impl StreamRestorer {
+    /// Create a restorer backed by `vault`.
+    pub fn new(vault: Arc<PiiVault>) -> Self {
+        Self {
+            vault,
+            pending: String::new(),
+        }
+    }
+
+    /// Feed a chunk; returns the restored text that is safe to emit now.
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.pending.push_str(chunk);
+        let hold_from = partial_token_start(&self.pending).unwrap_or(self.pending.len());
+        let ready: String = self.pending.drain(..hold_from).collect();
+        self.vault.restore(&ready)
+    }
+
+    /// Flush anything still held back.
+    pub fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.pending);
+        self.vault.restore(&rest)
+    }
+}
+
+/// Restore vault tokens in a stream of events.
+///
+/// Content, thinking and tool-argument deltas are restored incrementally; held-back
+/// text is flushed before `StreamEnd`, before an error, or when the stream ends
+/// without one. Text deltas that are entirely held back are not emitted.
+pub fn restore_stream(
+    stream: BoxStream<'static, StreamingEvent>,
+    vault: Arc<PiiVault>,
+) -> BoxStream<'static, StreamingEvent> {
+    use futures::StreamExt;
+
+    struct State {
+        inner: BoxStream<'static, StreamingEvent>,
+        content: StreamRestorer,
+        thinking: StreamRestorer,
+        tool_args: HashMap<String, StreamRestorer>,
+        vault: Arc<PiiVault>,
+        queued: std::collections::VecDeque<ai_lib_core::Result<StreamingEvent>>,
+        done: bool,
+    }
+
+    impl State {
+        fn flush(&mut self) {
+            let content = self.content.finish();
+            if !content.is_empty() {
+                self.queued
+                    .push_back(Ok(StreamingEvent::PartialContentDelta {
+                        content,
+                        sequence_id: None,
+                    }));
+            }
+            let thinking = self.thinking.finish();
+            if !thinking.is_empty() {
+                self.queued.push_back(Ok(StreamingEvent::ThinkingDelta {
+                    thinking,
+                    tool_consideration: None,
+                }));
+            }
+            let mut ids: Vec<String> = self.tool_args.keys().cloned().collect();
+            ids.sort();
+            for id in ids {
+                if let Some(mut restorer) = self.tool_args.remove(&id) {
+                    let arguments = restorer.finish();
+                    if !arguments.is_empty() {
+                        self.queued.push_back(Ok(StreamingEvent::PartialToolCall {
+                            tool_call_id: id,
+                            arguments,
+                            index: None,
+                            is_complete: None,
+                        }));
+                    }
+                }
+            }
+        }
+
+        fn map(&mut self, event: StreamingEvent) {
+            let event = match event {
+                StreamingEvent::PartialContentDelta {
+                    content,
+                    sequence_id,
+                } => {
+                    let content = self.content.push(&content);
+                    if content.is_empty() {
+                        return;
+                    }
+                    StreamingEvent::PartialContentDelta {
+                        content,
+                        sequence_id,
+                    }
+                }
+                StreamingEvent::ThinkingDelta {
+                    thinking,
+                    tool_consideration,
+                } => {
+                    let thinking = self.thinking.push(&thinking);
+                    if thinking.is_empty() && tool_consideration.is_none() {
+                        return;
+                    }
+                    StreamingEvent::ThinkingDelta {
+                        thinking,
+                        tool_consideration: tool_consideration.map(|t| self.vault.restore(&t)),
+                    }
+                }
+                StreamingEvent::PartialToolCall {
+                    tool_call_id,
+                    arguments,
+                    index,
+                    is_complete,
+                } => {
+                    let vault = self.vault.clone();
+                    let restorer = self
+                        .tool_args
+                        .entry(tool_call_id.clone())
+                        .or_insert_with(|| StreamRestorer::new(vault));
+                    let mut arguments = restorer.push(&arguments);
+                    if is_complete == Some(true) {
+                        arguments.push_str(&restorer.finish());
+                    }
+                    StreamingEvent::PartialToolCall {
+                        tool_call_id,
+                        arguments,
+                        index,
+                        is_complete,
+                    }
+                }
+                StreamingEvent::ToolCallEnded {
+                    tool_call_id,
+                    index,
+                } => {
+                    if let Some(mut restorer) = self.tool_args.remove(&tool_call_id) {
+                        let arguments = restorer.finish();
+                        if !arguments.is_empty() {
+                            self.queued.push_back(Ok(StreamingEvent::PartialToolCall {
+                                tool_call_id: tool_call_id.clone(),
+                                arguments,
+                                index,
+                                is_complete: None,
+                            }));
+                        }
+                    }
+                    StreamingEvent::ToolCallEnded {
+                        tool_call_id,
+                        index,
+                    }
+                }
+                StreamingEvent::StreamEnd { finish_reason } => {
+                    self.flush();
+                    StreamingEvent::StreamEnd { finish_reason }
+                }
+                other => other,
+            };
+            self.queued.push_back(Ok(event));
+        }
+    }
+
+    let state = State {
+        inner: stream,
+        content: StreamRestorer::new(vault.clone()),
+        thinking: StreamRestorer::new(vault.clone()),
+        tool_args: HashMap::new(),
+        vault,
+        queued: std::collections::VecDeque::new(),
+        done: false,
+    };
+
+    Box::pin(futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.queued.pop_front() {
+                return Some((item, state));
+            }
+            if state.done {
+                return None;
+            }
+            match state.inner.next().await {
+                Some(Ok(event)) => state.map(event),
+                Some(Err(e)) => {
+                    state.flush();
+                    state.queued.push_back(Err(e));
+                }
+                None => {
+                    state.done = true;
+                    state.flush();
+                }
+            }
+        }
+    }))
+}
+
+/// Byte offset of a trailing `<...` that could still grow into a token.
+fn partial_token_start(text: &str) -> Option<usize> {
+    let start = text.rfind('<')?;
+    let tail = &text[start + 1..];
+    let could_be_token = tail.len() < MAX_PENDING_TOKEN_LEN
+        && tail.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+        && tail.bytes().next().map_or(true, |b| b.is_ascii_uppercase());
+    could_be_token.then_some(start)
+}
+
+/// Eight hex digits from the std randomly-keyed hasher.
+fn random_nonce() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos()),
+    );
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+fn map_json_strings(value: &mut serde_json::Value, f: &mut dyn FnMut(&str) -> String) {
+    match value {
+        serde_json::Value::String(s) => *s = f(s),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                map_json_strings(item, f);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for item in map.values_mut() {
+                map_json_strings(item, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn luhn_valid(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 13 || digits.len() > 19 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                *d
+            }
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+fn cn_id_valid(id: &str) -> bool {
+    const WEIGHTS: [u32; 17] = [7, 9, 10, 5, 8, 4, 2, 1, 6, 3, 7, 9, 10, 5, 8, 4, 2];
+    const CHECK: [char; 11] = ['1', '0', 'X', '9', '8', '7', '6', '5', '4', '3', '2'];
+    let chars: Vec<char> = id.chars().collect();
+    if chars.len() != 18 {
+        return false;
+    }
+    let mut sum = 0;
+    for (c, w) in chars[..17].iter().zip(WEIGHTS) {
+        match c.to_digit(10) {
+            Some(d) => sum += d * w,
+            None => return false,
+        }
+    }
+    CHECK[(sum % 11) as usize] == chars[17].to_ascii_uppercase()
+}
+
+fn iban_valid(iban: &str) -> bool {
+    let compact: String = iban.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.len() < 15 || compact.len() > 34 {
+        return false;
+    }
+    let (head, tail) = compact.split_at(4);
+    let mut remainder: u32 = 0;
+    for c in tail.chars().chain(head.chars()) {
+        let value = match c.to_digit(36) {
+            Some(v) => v,
+            None => return false,
+        };
+        remainder = if value >= 10 {
+            (remainder * 100 + value) % 97
+        } else {
+            (remainder * 10 + value) % 97
+        };
+    }
+    remainder == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_round_trip_with_stable_tokens() {
+        let pseudonymizer = Pseudonymizer::new();
+        let mut vault = PiiVault::with_nonce("ab12");
+        let masked = pseudonymizer.pseudonymize(
+            "Mail a@example.com or b@example.com, again a@example.com",
+            &mut vault,
+        );
+        assert_eq!(
+            masked,
+            "Mail <EMAIL_1_ab12> or <EMAIL_2_ab12>, again <EMAIL_1_ab12>"
+        );
+        assert_eq!(vault.len(), 2);
+        assert_eq!(
+            vault.restore(&masked),
+            "Mail a@example.com or b@example.com, again a@example.com"
+        );
+    }
+
+    #[test]
+    fn test_token_lookalikes_in_user_text_are_left_alone() {
+        let pseudonymizer = Pseudonymizer::new();
+        let mut vault = PiiVault::with_nonce("ab12");
+        let text = "Template <EMAIL_1> and <EMAIL_1_ff00>, send to a@example.com";
+        let masked = pseudonymizer.pseudonymize(text, &mut vault);
+        assert_eq!(
+            masked,
+            "Template <EMAIL_1> and <EMAIL_1_ff00>, send to <EMAIL_1_ab12>"
+        );
+        assert_eq!(vault.restore(&masked), text);
+
+        let other = PiiVault::new();
+        assert_ne!(other.nonce(), vault.nonce());
+        assert_ne!(other.nonce(), PiiVault::new().nonce());
+    }
+
+    #[test]
+    fn test_card_wins_over_phone_overlap() {
+        let pseudonymizer = Pseudonymizer::new();
+        let mut vault = PiiVault::with_nonce("ab12");
+        let masked =
+            pseudonymizer.pseudonymize("Card 4111 1111 1111 1111, call 555-123-4567", &mut vault);
+        assert_eq!(masked, "Card <CREDIT_CARD_1_ab12>, call <PHONE_1_ab12>");
+    }
+
+    #[test]
+    fn test_locale_recognizers() {
+        let pseudonymizer = Pseudonymizer::empty().with_cn_id_numbers().with_iban();
+        let mut vault = PiiVault::with_nonce("ab12");
+        let masked = pseudonymizer.pseudonymize(
+            "ID 11010519491231002X, IBAN GB82 WEST 1234 5698 7654 32, bad 110105194912310021",
+            &mut vault,
+        );
+        assert_eq!(
+            masked,
+            "ID <CN_ID_1_ab12>, IBAN <IBAN_1_ab12>, bad 110105194912310021"
+        );
+    }
+
+    #[test]
+    fn test_custom_recognizer_and_messages() {
+        let employee = RegexRecognizer::new("EMPLOYEE_ID", r"\bEMP-[0-9]{6}\b").unwrap();
+        let pseudonymizer = Pseudonymizer::new().with_recognizer(employee);
+        let mut vault = PiiVault::with_nonce("ab12");
+        let mut messages = vec![
+            Message::user("EMP-004211 reports to boss@corp.io"),
+            Message::with_content(
+                ai_lib_core::types::message::MessageRole::User,
+                MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: "t1".into(),
+                    content: serde_json::json!({"owner": "EMP-004211"}),
+                }]),
+            ),
+        ];
+        pseudonymizer.pseudonymize_messages(&mut messages, &mut vault);
+
+        match &messages[0].content {
+            MessageContent::Text(t) => {
+                assert_eq!(t, "<EMPLOYEE_ID_1_ab12> reports to <EMAIL_1_ab12>")
+            }
+            _ => panic!("expected text"),
+        }
+        match &messages[1].content {
+            MessageContent::Blocks(b) => match &b[0] {
+                ContentBlock::ToolResult { content, .. } => {
+                    assert_eq!(content["owner"], "<EMPLOYEE_ID_1_ab12>")
+                }
+                _ => panic!("expected tool result"),
+            },
+            _ => panic!("expected blocks"),
+        }
+    }
+
+    #[test]
+    fn test_restore_response_and_unknown_tokens() {
+        let mut vault = PiiVault::with_nonce("ab12");
+        let token = vault.tokenize("EMAIL", "a@example.com");
+        let mut response = UnifiedResponse::new(format!("Sent to {} and <EMAIL_9_ab12>", token));
+        response.tool_calls = vec![ai_lib_core::ToolCall {
+            id: "c1".into(),
+            name: "send".into(),
+            arguments: serde_json::json!({"to": [token]}),
+        }];
+        vault.restore_response(&mut response);
+        assert_eq!(response.content, "Sent to a@example.com and <EMAIL_9_ab12>");
+        assert_eq!(response.tool_calls[0].arguments["to"][0], "a@example.com");
+    }
+
+    #[test]
+    fn test_stream_restorer_split_token() {
+        let mut vault = PiiVault::with_nonce("ab12");
+        vault.tokenize("EMAIL", "a@example.com");
+        let mut restorer = StreamRestorer::new(Arc::new(vault));
+        let mut out = String::new();
+        for chunk in ["Hi <", "EMA", "IL_1_ab", "12> a < b", " <PHO"] {
+            out.push_str(&restorer.push(chunk));
+        }
+        assert_eq!(out, "Hi a@example.com a < b ");
+        out.push_str(&restorer.finish());
+        assert_eq!(out, "Hi a@example.com a < b <PHO");
+    }
+
+    #[tokio::test]
+    async fn test_restore_stream_flushes_before_end() {
+        let mut vault = PiiVault::with_nonce("ab12");
+        vault.tokenize("EMAIL", "a@example.com");
+        let events: Vec<ai_lib_core::Result<StreamingEvent>> = vec![
+            Ok(StreamingEvent::PartialContentDelta {
+                content: "to <EMAIL_".into(),
+                sequence_id: None,
+            }),
+            Ok(StreamingEvent::PartialContentDelta {
+                content: "1_ab12> <EMAIL_1_ab".into(),
+                sequence_id: None,
+            }),
+            Ok(StreamingEvent::StreamEnd {
+                finish_reason: Some("stop".into()),
+            }),
+        ];
+        let restored = restore_stream(Box::pin(futures::stream::iter(events)), Arc::new(vault));
+        let out: Vec<StreamingEvent> = restored.map(|e| e.unwrap()).collect().await;
+
+        let text: String = out
+            .iter()
+            .filter_map(|e| match e {
+                StreamingEvent::PartialContentDelta { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "to a@example.com <EMAIL_1_ab");
+        assert!(matches!(out.last(), Some(StreamingEvent::StreamEnd { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_restore_stream_skips_held_back_deltas_and_flushes_before_error() {
+        let mut vault = PiiVault::with_nonce("ab12");
+        vault.tokenize("EMAIL", "a@example.com");
+        let events: Vec<ai_lib_core::Result<StreamingEvent>> = vec![
+            Ok(StreamingEvent::PartialContentDelta {
+                content: "<EMAIL_".into(),
+                sequence_id: Some(1),
+            }),
+            Ok(StreamingEvent::PartialContentDelta {
+                content: "1".into(),
+                sequence_id: Some(2),
+            }),
+            Err(ai_lib_core::Error::validation("connection reset")),
+        ];
+        let restored = restore_stream(Box::pin(futures::stream::iter(events)), Arc::new(vault));
+        let out: Vec<ai_lib_core::Result<StreamingEvent>> = restored.collect().await;
+
+        assert_eq!(out.len(), 2);
+        match &out[0] {
+            Ok(StreamingEvent::PartialContentDelta { content, .. }) => {
+                assert_eq!(content, "<EMAIL_1")
+            }
+            other => panic!("expected flushed content, got {other:?}"),
+        }
+        assert!(out[1].is_err());
+    }
+}
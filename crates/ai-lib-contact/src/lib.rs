@@ -1,6 +1,6 @@
 //! # ai-lib-contact
 //!
-//! 策略与横切能力层：缓存、批处理、路由、插件、拦截器、令牌、遥测、护栏、弹性（熔断/限流）。
+//! 策略与横切能力层：缓存、批处理、路由、插件、拦截器、令牌、遥测、护栏、弹性（熔断/限流）、检索增强（RAG）。
 //! 依赖 `ai-lib-core` 执行层类型与错误。
 //!
 //! Policy and cross-cutting modules for AI-Protocol. Depends on `ai-lib-core`.
@@ -12,10 +12,14 @@ pub mod resilience;
 
 #[cfg(feature = "batch")]
 pub mod batch;
+#[cfg(feature = "app_config")]
+pub mod config;
 #[cfg(feature = "guardrails")]
 pub mod guardrails;
 #[cfg(feature = "interceptors")]
 pub mod interceptors;
+#[cfg(feature = "rag")]
+pub mod rag;
 #[cfg(feature = "routing_mvp")]
 pub mod routing;
 #[cfg(feature = "telemetry")]
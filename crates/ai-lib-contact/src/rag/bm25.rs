@@ -0,0 +1,201 @@
+//! In-memory BM25 keyword index used for hybrid retrieval.
+
+use std::collections::HashMap;
+
+/// Okapi BM25 over chunk texts keyed by chunk id.
+///
+/// Tokens are lowercased alphanumeric runs; CJK characters are indexed one per token
+/// since those scripts have no word separators.
+#[derive(Debug, Clone)]
+pub struct Bm25Index {
+    k1: f32,
+    b: f32,
+    docs: HashMap<String, DocTerms>,
+    doc_freq: HashMap<String, u32>,
+    total_len: u64,
+}
+
+#[derive(Debug, Clone)]
+struct DocTerms {
+    len: u32,
+    term_freq: HashMap<String, u32>,
+}
+
+impl Default for Bm25Index {
+    fn default() -> Self {
+        Self::new(1.2, 0.75)
+    }
+}
+
+impl Bm25Index {
+    pub fn new(k1: f32, b: f32) -> Self {
+        Self {
+            k1,
+            b,
+            docs: HashMap::new(),
+            doc_freq: HashMap::new(),
+            total_len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Index `text` under `id`, replacing any previous text for that id.
+    pub fn insert(&mut self, id: impl Into<String>, text: &str) {
+        let id = id.into();
+        self.remove(&id);
+        let tokens = tokenize(text);
+        let mut term_freq: HashMap<String, u32> = HashMap::new();
+        for token in &tokens {
+            *term_freq.entry(token.clone()).or_default() += 1;
+        }
+        for term in term_freq.keys() {
+            *self.doc_freq.entry(term.clone()).or_default() += 1;
+        }
+        self.total_len += tokens.len() as u64;
+        self.docs.insert(
+            id,
+            DocTerms {
+                len: tokens.len() as u32,
+                term_freq,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(doc) = self.docs.remove(id) else {
+            return false;
+        };
+        self.total_len -= doc.len as u64;
+        for term in doc.term_freq.keys() {
+            if let Some(df) = self.doc_freq.get_mut(term) {
+                *df -= 1;
+                if *df == 0 {
+                    self.doc_freq.remove(term);
+                }
+            }
+        }
+        true
+    }
+
+    /// Top `k` ids by BM25 score (only documents sharing a query term), best first.
+    pub fn search(&self, query: &str, k: usize) -> Vec<(String, f32)> {
+        self.search_where(query, k, |_| true)
+    }
+
+    /// Like [`search`](Self::search), restricted to ids passing `accept`.
+    pub fn search_where(
+        &self,
+        query: &str,
+        k: usize,
+        accept: impl Fn(&str) -> bool,
+    ) -> Vec<(String, f32)> {
+        if self.docs.is_empty() || k == 0 {
+            return Vec::new();
+        }
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        let n = self.docs.len() as f32;
+        let avg_len = (self.total_len as f32 / n).max(1.0);
+        let idf: Vec<(&str, f32)> = terms
+            .iter()
+            .filter_map(|t| {
+                let df = *self.doc_freq.get(t)? as f32;
+                Some((t.as_str(), ((n - df + 0.5) / (df + 0.5) + 1.0).ln()))
+            })
+            .collect();
+        if idf.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(String, f32)> = self
+            .docs
+            .iter()
+            .filter(|(id, _)| accept(id))
+            .filter_map(|(id, doc)| {
+                let norm = self.k1 * (1.0 - self.b + self.b * doc.len as f32 / avg_len);
+                let score: f32 = idf
+                    .iter()
+                    .filter_map(|(term, idf)| {
+                        let tf = *doc.term_freq.get(*term)? as f32;
+                        Some(idf * tf * (self.k1 + 1.0) / (tf + norm))
+                    })
+                    .sum();
+                (score > 0.0).then(|| (id.clone(), score))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.truncate(k);
+        scored
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranks_by_term_rarity_and_frequency() {
+        let mut index = Bm25Index::default();
+        index.insert("a", "The cat sat on the mat");
+        index.insert("b", "The dog chased the cat, the cat ran");
+        index.insert("c", "Stock prices fell sharply");
+
+        let hits = index.search("cat", 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].0, "b");
+        assert!(index.search("unicorn", 10).is_empty());
+        assert_eq!(index.search("STOCK prices!", 1)[0].0, "c");
+
+        let only_a = index.search_where("cat", 10, |id| id == "a");
+        assert_eq!(only_a.len(), 1);
+
+        assert!(index.remove("b"));
+        assert_eq!(index.search("cat", 10)[0].0, "a");
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn test_tokenizes_cjk_per_character() {
+        assert_eq!(
+            tokenize("退货政策 Returns-Policy"),
+            ["退", "货", "政", "策", "returns", "policy"]
+        );
+        let mut index = Bm25Index::default();
+        index.insert("zh", "我们的退货政策是三十天");
+        index.insert("en", "Shipping takes two days");
+        assert_eq!(index.search("退货", 5)[0].0, "zh");
+    }
+}
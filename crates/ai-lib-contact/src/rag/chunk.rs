@@ -0,0 +1,469 @@
+//! Text chunkers: split documents into retrieval-sized pieces.
+//!
+//! All chunkers measure size with [`estimate_tokens`] (the same heuristic the context
+//! assembler budgets with), never split inside a word, and fall back to word splitting
+//! for units (sentences, paragraphs, code blocks) that are larger than a whole chunk.
+
+use crate::context::estimate_tokens;
+
+/// A piece of a source document. `start..end` is the byte range in the source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+    /// Heading path (`"Setup › Linux"`) for markdown chunks.
+    pub heading: Option<String>,
+}
+
+/// Splits a document into chunks.
+pub trait Chunker: Send + Sync {
+    fn chunk(&self, text: &str) -> Vec<TextChunk>;
+}
+
+/// Fixed-size windows of roughly `max_tokens`, cut at word boundaries, with
+/// `overlap_tokens` of trailing context repeated at the start of the next window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenChunker {
+    pub max_tokens: u32,
+    pub overlap_tokens: u32,
+}
+
+impl TokenChunker {
+    pub fn new(max_tokens: u32) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+            overlap_tokens: 0,
+        }
+    }
+
+    pub fn with_overlap(mut self, overlap_tokens: u32) -> Self {
+        self.overlap_tokens = overlap_tokens.min(self.max_tokens / 2);
+        self
+    }
+}
+
+impl Chunker for TokenChunker {
+    fn chunk(&self, text: &str) -> Vec<TextChunk> {
+        pack(
+            text,
+            words(text, 0, text.len()),
+            self.max_tokens,
+            self.overlap_tokens,
+        )
+    }
+}
+
+/// Whole sentences packed up to `max_tokens`; paragraph breaks also end a sentence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentenceChunker {
+    pub max_tokens: u32,
+    pub overlap_tokens: u32,
+}
+
+impl SentenceChunker {
+    pub fn new(max_tokens: u32) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+            overlap_tokens: 0,
+        }
+    }
+
+    pub fn with_overlap(mut self, overlap_tokens: u32) -> Self {
+        self.overlap_tokens = overlap_tokens.min(self.max_tokens / 2);
+        self
+    }
+}
+
+impl Default for SentenceChunker {
+    fn default() -> Self {
+        Self::new(256).with_overlap(32)
+    }
+}
+
+impl Chunker for SentenceChunker {
+    fn chunk(&self, text: &str) -> Vec<TextChunk> {
+        pack(
+            text,
+            sentences(text, 0, text.len()),
+            self.max_tokens,
+            self.overlap_tokens,
+        )
+    }
+}
+
+/// One chunk per markdown section (heading + body) when it fits; larger sections are
+/// split between paragraphs. Fenced code blocks are kept whole whenever they fit, and
+/// every chunk carries its heading path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarkdownChunker {
+    pub max_tokens: u32,
+}
+
+impl MarkdownChunker {
+    pub fn new(max_tokens: u32) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+        }
+    }
+}
+
+impl Default for MarkdownChunker {
+    fn default() -> Self {
+        Self::new(384)
+    }
+}
+
+impl Chunker for MarkdownChunker {
+    fn chunk(&self, text: &str) -> Vec<TextChunk> {
+        let mut chunks = Vec::new();
+        let mut path: Vec<(usize, String)> = Vec::new();
+        let mut emit = |start: usize, end: usize, path: &[(usize, String)]| {
+            let units = blocks(text, start, end);
+            if units.is_empty() {
+                return;
+            }
+            let heading = (!path.is_empty()).then(|| {
+                path.iter()
+                    .map(|(_, h)| h.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" › ")
+            });
+            for mut chunk in pack(text, units, self.max_tokens, 0) {
+                chunk.heading = heading.clone();
+                chunks.push(chunk);
+            }
+        };
+
+        let mut section_start = 0;
+        let mut fence: Option<&str> = None;
+        for (line_start, line) in lines(text) {
+            let trimmed = line.trim_start();
+            if let Some(marker) = fence {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                continue;
+            }
+            if let Some(marker) = fence_marker(trimmed) {
+                fence = Some(marker);
+                continue;
+            }
+            let level = trimmed.bytes().take_while(|b| *b == b'#').count();
+            let is_heading = (1..=6).contains(&level)
+                && trimmed[level..].starts_with([' ', '\t'])
+                && line.len() - trimmed.len() < 4;
+            if !is_heading {
+                continue;
+            }
+            emit(section_start, line_start, &path);
+            section_start = line_start;
+            path.retain(|(l, _)| *l < level);
+            path.push((
+                level,
+                trimmed[level..]
+                    .trim()
+                    .trim_end_matches('#')
+                    .trim()
+                    .to_string(),
+            ));
+        }
+        emit(section_start, text.len(), &path);
+        chunks
+    }
+}
+
+/// Source code split between top-level blocks (a blank line followed by an unindented
+/// line), so functions and types stay together with their doc comments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeChunker {
+    pub max_tokens: u32,
+}
+
+impl CodeChunker {
+    pub fn new(max_tokens: u32) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+        }
+    }
+}
+
+impl Default for CodeChunker {
+    fn default() -> Self {
+        Self::new(384)
+    }
+}
+
+impl Chunker for CodeChunker {
+    fn chunk(&self, text: &str) -> Vec<TextChunk> {
+        let mut units = Vec::new();
+        let mut start: Option<usize> = None;
+        let mut end = 0;
+        let mut previous_blank = true;
+        for (line_start, line) in lines(text) {
+            let blank = line.trim().is_empty();
+            let top_level = !blank && !line.starts_with([' ', '\t', '}', ')', ']']);
+            if top_level && previous_blank {
+                if let Some(s) = start.take() {
+                    units.push((s, end));
+                }
+            }
+            if !blank {
+                start.get_or_insert(line_start);
+                end = line_start + line.trim_end().len();
+            }
+            previous_blank = blank;
+        }
+        if let Some(s) = start {
+            units.push((s, end));
+        }
+        pack(text, units, self.max_tokens, 0)
+    }
+}
+
+/// Pack consecutive `(start, end)` units into chunks of at most `max_tokens`, carrying
+/// up to `overlap` tokens of trailing units into the next chunk.
+fn pack(text: &str, units: Vec<(usize, usize)>, max_tokens: u32, overlap: u32) -> Vec<TextChunk> {
+    let units = fit_units(text, units, max_tokens);
+    let tokens = |from: usize, to: usize| estimate_tokens(&text[units[from].0..units[to].1]);
+    let mut chunks = Vec::new();
+    let mut first = 0;
+    while first < units.len() {
+        let mut last = first;
+        while last + 1 < units.len() && tokens(first, last + 1) <= max_tokens {
+            last += 1;
+        }
+        let (start, end) = (units[first].0, units[last].1);
+        chunks.push(TextChunk {
+            text: text[start..end].to_string(),
+            start,
+            end,
+            heading: None,
+        });
+        if last + 1 >= units.len() {
+            break;
+        }
+        let mut next = last + 1;
+        while overlap > 0 && next > first + 1 && tokens(next - 1, last) <= overlap {
+            next -= 1;
+        }
+        first = next;
+    }
+    chunks
+}
+
+/// Replace units larger than `max_tokens` with their words, and words larger than
+/// that with fixed-size slices.
+fn fit_units(text: &str, units: Vec<(usize, usize)>, max_tokens: u32) -> Vec<(usize, usize)> {
+    let max_bytes = max_tokens as usize * crate::context::CHARS_PER_TOKEN as usize;
+    let mut out = Vec::with_capacity(units.len());
+    for (start, end) in units {
+        if estimate_tokens(&text[start..end]) <= max_tokens {
+            out.push((start, end));
+            continue;
+        }
+        for (ws, we) in words(text, start, end) {
+            let mut s = ws;
+            while we - s > max_bytes {
+                let mut cut = s + max_bytes;
+                while !text.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                if cut == s {
+                    cut = s + text[s..].chars().next().map_or(1, char::len_utf8);
+                }
+                out.push((s, cut));
+                s = cut;
+            }
+            out.push((s, we));
+        }
+    }
+    out
+}
+
+fn words(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    let mut word: Option<usize> = None;
+    for (i, c) in text[start..end].char_indices() {
+        match (c.is_whitespace(), word) {
+            (true, Some(s)) => {
+                out.push((s, start + i));
+                word = None;
+            }
+            (false, None) => word = Some(start + i),
+            _ => {}
+        }
+    }
+    if let Some(s) = word {
+        out.push((s, end));
+    }
+    out
+}
+
+fn sentences(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let slice = &text[start..end];
+    let mut out = Vec::new();
+    let mut begin: Option<usize> = None;
+    let mut chars = slice.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if begin.is_none() {
+            if c.is_whitespace() {
+                continue;
+            }
+            begin = Some(i);
+        }
+        let next = chars.peek().map(|(_, n)| *n);
+        let boundary = match c {
+            '。' | '！' | '？' => true,
+            '.' | '!' | '?' => next.map_or(true, char::is_whitespace),
+            '\n' => next == Some('\n') || next == Some('\r'),
+            _ => false,
+        };
+        if boundary {
+            let s = begin.take().unwrap_or(i);
+            let e = slice[..i + c.len_utf8()].trim_end().len();
+            if e > s {
+                out.push((start + s, start + e));
+            }
+        }
+    }
+    if let Some(s) = begin {
+        let e = slice.trim_end().len();
+        if e > s {
+            out.push((start + s, start + e));
+        }
+    }
+    out
+}
+
+/// Paragraphs (blank-line separated) and fenced code blocks within `start..end`.
+fn blocks(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    let mut block: Option<(usize, usize)> = None;
+    let mut fence: Option<&str> = None;
+    for (line_start, line) in lines(&text[start..end]) {
+        let (line_start, trimmed) = (start + line_start, line.trim_start());
+        let line_end = line_start + line.trim_end().len();
+        if let Some(marker) = fence {
+            block = block.map(|(s, _)| (s, line_end));
+            if trimmed.starts_with(marker) {
+                fence = None;
+                out.extend(block.take());
+            }
+            continue;
+        }
+        if let Some(marker) = fence_marker(trimmed) {
+            out.extend(block.take());
+            block = Some((line_start, line_end));
+            fence = Some(marker);
+            continue;
+        }
+        if trimmed.trim().is_empty() {
+            out.extend(block.take());
+        } else {
+            block = Some(block.map_or((line_start, line_end), |(s, _)| (s, line_end)));
+        }
+    }
+    out.extend(block);
+    out
+}
+
+fn fence_marker(trimmed: &str) -> Option<&'static str> {
+    if trimmed.starts_with("```") {
+        Some("```")
+    } else if trimmed.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+/// Lines with their byte offsets (line text excludes the newline).
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    text.split_inclusive('\n').map(move |line| {
+        let start = offset;
+        offset += line.len();
+        (start, line.trim_end_matches(['\n', '\r']))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(chunks: &[TextChunk]) -> Vec<&str> {
+        chunks.iter().map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_token_chunker_respects_budget_and_overlap() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta iota kappa";
+        let chunks = TokenChunker::new(5).with_overlap(2).chunk(text);
+        assert!(chunks.len() > 2);
+        for c in &chunks {
+            assert!(estimate_tokens(&c.text) <= 5, "{:?}", c.text);
+            assert_eq!(&text[c.start..c.end], c.text);
+        }
+        // Overlap repeats the previous chunk's last word.
+        let last_word = chunks[0].text.split(' ').next_back().unwrap();
+        assert!(chunks[1].text.starts_with(last_word));
+        assert!(chunks.last().unwrap().text.ends_with("kappa"));
+
+        let long = "x".repeat(50);
+        let chunks = TokenChunker::new(4).chunk(&long);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks.iter().map(|c| c.text.len()).sum::<usize>(), 50);
+    }
+
+    #[test]
+    fn test_sentence_chunker_keeps_sentences_whole() {
+        let text = "First one. Second sentence here! Third?\n\nNew paragraph without stop\n\n句子一。句子二。";
+        let chunks = SentenceChunker::new(8).chunk(text);
+        assert_eq!(
+            texts(&chunks),
+            [
+                "First one. Second sentence here!",
+                "Third?",
+                "New paragraph without stop",
+                "句子一。句子二。"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_chunker_tracks_headings_and_fences() {
+        let text = "# Guide\nIntro text.\n\n## Install\nRun this:\n\n```sh\n# not a heading\ncargo add ai-lib\n```\n\n## Use\nCall it.\n";
+        let chunks = MarkdownChunker::new(100).chunk(text);
+        let headings: Vec<_> = chunks.iter().map(|c| c.heading.as_deref()).collect();
+        assert_eq!(
+            headings,
+            [Some("Guide"), Some("Guide › Install"), Some("Guide › Use")]
+        );
+        assert!(chunks[1]
+            .text
+            .contains("# not a heading\ncargo add ai-lib\n```"));
+
+        // Oversized section splits between paragraphs, fence kept whole.
+        let chunks = MarkdownChunker::new(12).chunk(text);
+        assert!(chunks
+            .iter()
+            .any(|c| c.text.starts_with("```sh") && c.text.ends_with("```")));
+        assert!(chunks.iter().all(|c| c.heading.is_some()));
+    }
+
+    #[test]
+    fn test_code_chunker_splits_top_level_items() {
+        let text = "use std::io;\n\n/// Adds.\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n\n}\n\nstruct Point {\n    x: i32,\n}\n";
+        let chunks = CodeChunker::new(14).chunk(text);
+        assert_eq!(
+            texts(&chunks),
+            [
+                "use std::io;",
+                "/// Adds.\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n\n}",
+                "struct Point {\n    x: i32,\n}"
+            ]
+        );
+        assert_eq!(CodeChunker::new(1000).chunk(text).len(), 1);
+    }
+}
@@ -0,0 +1,51 @@
+//! 检索增强生成（RAG）：文本分块、向量/BM25 混合检索、重排序，并按上下文预算组装带来源标注的提示词。
+//!
+//! # Retrieval-Augmented Generation
+//!
+//! Ties [`EmbeddingClient`](ai_lib_core::embeddings::EmbeddingClient),
+//! [`VectorStore`](ai_lib_core::embeddings::VectorStore),
+//! [`RerankerClient`](ai_lib_core::rerank::RerankerClient) and
+//! [`MessageAssembler::assemble_layered`](crate::context::MessageAssembler::assemble_layered)
+//! into one pipeline. Retrieved chunks enter the prompt as numbered passages in the
+//! [`ContextLayer::Relevant`](crate::context::ContextLayer::Relevant) layer, best first,
+//! so the [`ContextBudget`](crate::context::ContextBudget) drops the weakest passages
+//! first; the passages that survive are returned as [`SourceAttribution`]s.
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`Chunker`] | Splits documents; [`TokenChunker`], [`SentenceChunker`], [`MarkdownChunker`], [`CodeChunker`] |
+//! | [`Bm25Index`] | Keyword index for hybrid retrieval (reciprocal-rank fusion with vectors) |
+//! | [`RagPipeline`] | `index` → `retrieve` → `build_context` → `answer` |
+//! | [`RagConfig`] | Candidate / top-k counts, hybrid weight, context budget, citation instruction |
+//! | [`RagAnswer`] | Model answer plus passages used and which of them it cites |
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ai_lib_contact::rag::{MarkdownChunker, RagConfig, RagPipeline, RagQuery, SourceDocument};
+//! use ai_lib_core::embeddings::EmbeddingClient;
+//!
+//! # async fn demo(embedder: EmbeddingClient, client: &ai_lib_core::AiClient) -> ai_lib_core::Result<()> {
+//! let mut rag = RagPipeline::new(embedder)
+//!     .with_chunker(MarkdownChunker::default())
+//!     .with_config(RagConfig::default().with_hybrid(0.3));
+//! rag.index(&[SourceDocument::new("handbook", std::fs::read_to_string("handbook.md")?)])
+//!     .await?;
+//!
+//! let answer = rag.answer(client, &RagQuery::new("How many vacation days do I get?")).await?;
+//! for source in answer.cited_sources() {
+//!     println!("[{}] {} {:?}", source.marker, source.source_id, source.heading);
+//! }
+//! # Ok(()) }
+//! ```
+
+mod bm25;
+mod chunk;
+mod pipeline;
+
+pub use bm25::Bm25Index;
+pub use chunk::{Chunker, CodeChunker, MarkdownChunker, SentenceChunker, TextChunk, TokenChunker};
+pub use pipeline::{
+    IndexReport, RagAnswer, RagConfig, RagContext, RagPipeline, RagQuery, RetrievedChunk,
+    SourceAttribution, SourceDocument,
+};
@@ -0,0 +1,613 @@
+//! Retrieval pipeline: index documents, retrieve (vector + optional BM25 + rerank),
+//! and assemble retrieved chunks into a budgeted prompt with source attributions.
+
+use std::collections::{BTreeMap, HashMap};
+
+use ai_lib_core::client::AiClient;
+use ai_lib_core::embeddings::{
+    Document, EmbeddingClient, EmbeddingUsage, MetadataFilter, SimilarityMetric, VectorStore,
+};
+use ai_lib_core::rerank::{RerankOptions, RerankerClient};
+use ai_lib_core::types::message::{Message, MessageContent};
+use ai_lib_core::{Error, ErrorContext, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::bm25::Bm25Index;
+use super::chunk::{Chunker, SentenceChunker};
+use crate::context::{
+    AssembleStrategy, ContextBudget, ContextLayer, LayeredAssembleOptions, MessageAssembler,
+    MessageChunk, ModelCapacity,
+};
+
+/// Reciprocal-rank-fusion constant (Cormack et al.); dampens the head of each list.
+const RRF_K: f32 = 60.0;
+
+/// Store score as "higher is better": distances map to `1 / (1 + d)`.
+fn relevance(metric: SimilarityMetric, score: f32) -> f32 {
+    match metric {
+        SimilarityMetric::Cosine | SimilarityMetric::DotProduct => score,
+        SimilarityMetric::Euclidean | SimilarityMetric::Manhattan => 1.0 / (1.0 + score),
+    }
+}
+
+/// Metadata keys the pipeline adds to every chunk.
+const SOURCE_ID: &str = "source_id";
+const CHUNK_INDEX: &str = "chunk_index";
+const HEADING: &str = "heading";
+
+/// A document to chunk and index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceDocument {
+    pub id: String,
+    pub text: String,
+    /// Copied onto every chunk (must be a JSON object to be merged; other values are
+    /// stored under `"metadata"`).
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+impl SourceDocument {
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+            metadata: json!({}),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+/// Retrieval and assembly settings.
+#[derive(Debug, Clone)]
+pub struct RagConfig {
+    /// Candidates fetched from each retriever before fusion / reranking.
+    pub candidates: usize,
+    /// Chunks kept after ranking (before the context budget is applied).
+    pub top_k: usize,
+    /// BM25 weight in reciprocal-rank fusion (`0.0..=1.0`); `None` = vector only.
+    pub hybrid_weight: Option<f32>,
+    pub budget: ContextBudget,
+    pub strategy: AssembleStrategy,
+    /// Instruction appended to the system prompt asking for `[n]` citations.
+    pub citation_instruction: Option<String>,
+}
+
+impl Default for RagConfig {
+    fn default() -> Self {
+        Self {
+            candidates: 20,
+            top_k: 5,
+            hybrid_weight: None,
+            budget: ContextBudget::from_capacity(ModelCapacity::UNKNOWN, 2),
+            strategy: AssembleStrategy::Chat,
+            citation_instruction: Some(
+                "Answer using the numbered context passages. Cite passages you rely on as [n]. \
+                 If the passages do not contain the answer, say so."
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+impl RagConfig {
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates.max(1);
+        self
+    }
+
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k.max(1);
+        self
+    }
+
+    pub fn with_hybrid(mut self, bm25_weight: f32) -> Self {
+        self.hybrid_weight = Some(bm25_weight.clamp(0.0, 1.0));
+        self
+    }
+
+    pub fn with_budget(mut self, budget: ContextBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: AssembleStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_citation_instruction(mut self, instruction: Option<String>) -> Self {
+        self.citation_instruction = instruction;
+        self
+    }
+}
+
+/// One question to answer, with optional system prompt, prior turns and filter.
+#[derive(Debug, Clone, Default)]
+pub struct RagQuery {
+    pub question: String,
+    pub system: Option<String>,
+    /// Earlier turns, oldest first; placed in the `Background` layer.
+    pub history: Vec<Message>,
+    pub filter: Option<MetadataFilter>,
+}
+
+impl RagQuery {
+    pub fn new(question: impl Into<String>) -> Self {
+        Self {
+            question: question.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    pub fn with_history(mut self, history: Vec<Message>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: MetadataFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
+
+/// A retrieved chunk with the scores that ranked it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetrievedChunk {
+    pub chunk_id: String,
+    pub source_id: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    /// Final ranking score, higher is better (rerank relevance, fused RRF score, or
+    /// vector similarity; `1 / (1 + distance)` for euclidean / manhattan stores).
+    pub score: f32,
+    /// The store's own score: similarity, or distance for euclidean / manhattan.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_score: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyword_score: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
+    pub metadata: Value,
+}
+
+/// A chunk placed in the prompt as passage `[marker]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceAttribution {
+    pub marker: usize,
+    pub chunk_id: String,
+    pub source_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    pub score: f32,
+    /// Set on [`RagAnswer::sources`] when the answer cites `[marker]`.
+    #[serde(default)]
+    pub cited: bool,
+}
+
+/// Assembled prompt plus the passages that made it under the budget.
+#[derive(Debug, Clone)]
+pub struct RagContext {
+    pub messages: Vec<Message>,
+    pub sources: Vec<SourceAttribution>,
+    /// Retrieved chunks dropped by the context budget.
+    pub omitted_chunks: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct RagAnswer {
+    pub content: String,
+    pub sources: Vec<SourceAttribution>,
+    pub usage: Option<Value>,
+}
+
+impl RagAnswer {
+    /// Sources the answer actually cites.
+    pub fn cited_sources(&self) -> impl Iterator<Item = &SourceAttribution> {
+        self.sources.iter().filter(|s| s.cited)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IndexReport {
+    pub documents: usize,
+    pub chunks: usize,
+    pub usage: EmbeddingUsage,
+}
+
+/// Chunk → embed → index, then retrieve → rerank → assemble.
+pub struct RagPipeline {
+    embedder: EmbeddingClient,
+    reranker: Option<RerankerClient>,
+    chunker: Box<dyn Chunker>,
+    store: VectorStore,
+    keywords: Bm25Index,
+    /// Source id → number of chunks indexed for it.
+    sources: HashMap<String, usize>,
+    config: RagConfig,
+}
+
+impl RagPipeline {
+    pub fn new(embedder: EmbeddingClient) -> Self {
+        Self {
+            embedder,
+            reranker: None,
+            chunker: Box::new(SentenceChunker::default()),
+            store: VectorStore::default(),
+            keywords: Bm25Index::default(),
+            sources: HashMap::new(),
+            config: RagConfig::default(),
+        }
+    }
+
+    pub fn with_reranker(mut self, reranker: RerankerClient) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    pub fn with_chunker(mut self, chunker: impl Chunker + 'static) -> Self {
+        self.chunker = Box::new(chunker);
+        self
+    }
+
+    pub fn with_config(mut self, config: RagConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Use an existing (e.g. [`VectorStore::open_mmap`]-loaded) store; the keyword index
+    /// is rebuilt from the chunk texts it holds.
+    pub fn with_store(mut self, store: VectorStore) -> Self {
+        self.keywords = Bm25Index::default();
+        self.sources.clear();
+        for id in store.ids() {
+            let Some(record) = store.get(id) else {
+                continue;
+            };
+            if let Some(text) = record.text {
+                self.keywords.insert(id, text);
+            }
+            if let Some(source) = record.metadata.get(SOURCE_ID).and_then(Value::as_str) {
+                *self.sources.entry(source.to_string()).or_default() += 1;
+            }
+        }
+        self.store = store;
+        self
+    }
+
+    pub fn store(&self) -> &VectorStore {
+        &self.store
+    }
+
+    pub fn config(&self) -> &RagConfig {
+        &self.config
+    }
+
+    /// Chunk, embed and index `documents`. Re-indexing a source id replaces its chunks.
+    pub async fn index(&mut self, documents: &[SourceDocument]) -> Result<IndexReport> {
+        let mut report = IndexReport::default();
+        let mut batch = Vec::new();
+        let mut counts = Vec::with_capacity(documents.len());
+        for doc in documents {
+            let chunks = self.chunker.chunk(&doc.text);
+            for (i, chunk) in chunks.iter().enumerate() {
+                let mut metadata = match &doc.metadata {
+                    Value::Object(map) => map.clone(),
+                    Value::Null => Default::default(),
+                    other => [("metadata".to_string(), other.clone())]
+                        .into_iter()
+                        .collect(),
+                };
+                metadata.insert(SOURCE_ID.into(), json!(doc.id));
+                metadata.insert(CHUNK_INDEX.into(), json!(i));
+                metadata.insert("start".into(), json!(chunk.start));
+                metadata.insert("end".into(), json!(chunk.end));
+                if let Some(heading) = &chunk.heading {
+                    metadata.insert(HEADING.into(), json!(heading));
+                }
+                batch.push(
+                    Document::new(format!("{}#{i}", doc.id), chunk.text.clone())
+                        .with_metadata(Value::Object(metadata)),
+                );
+            }
+            report.documents += 1;
+            report.chunks += chunks.len();
+            counts.push((doc.id.clone(), chunks.len()));
+        }
+        if batch.is_empty() {
+            for (id, _) in &counts {
+                self.remove(id);
+            }
+            return Ok(report);
+        }
+
+        // Embed with the heading path so sections are findable by their titles, but
+        // store the plain chunk text for the prompt.
+        let inputs: Vec<String> = batch
+            .iter()
+            .map(|d| match d.metadata.get(HEADING).and_then(Value::as_str) {
+                Some(h) => format!("{h}\n\n{}", d.text),
+                None => d.text.clone(),
+            })
+            .collect();
+        let response = self.embedder.embed_batch(&inputs).await?;
+        if response.embeddings.len() != batch.len() {
+            return Err(Error::api_with_context(
+                format!(
+                    "embedding response has {} vectors for {} chunks",
+                    response.embeddings.len(),
+                    batch.len()
+                ),
+                ErrorContext::new().with_source("rag"),
+            ));
+        }
+        report.usage = response.usage;
+
+        for (id, _) in &counts {
+            self.remove(id);
+        }
+        for emb in &response.embeddings {
+            let Some(doc) = batch.get(emb.index) else {
+                continue;
+            };
+            self.store.upsert(
+                doc.id.clone(),
+                &emb.vector,
+                doc.metadata.clone(),
+                Some(doc.text.clone()),
+            )?;
+            self.keywords.insert(doc.id.clone(), &doc.text);
+        }
+        self.sources
+            .extend(counts.into_iter().filter(|(_, n)| *n > 0));
+        Ok(report)
+    }
+
+    /// Drop every chunk of `source_id`; returns whether it was indexed.
+    pub fn remove(&mut self, source_id: &str) -> bool {
+        let Some(count) = self.sources.remove(source_id) else {
+            return false;
+        };
+        for i in 0..count {
+            let id = format!("{source_id}#{i}");
+            self.store.delete(&id);
+            self.keywords.remove(&id);
+        }
+        true
+    }
+
+    /// Ranked chunks for `query` (vector search, fused with BM25 when hybrid, then
+    /// reranked when a reranker is configured).
+    pub async fn retrieve(
+        &self,
+        query: &str,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<RetrievedChunk>> {
+        if self.store.is_empty() {
+            return Ok(Vec::new());
+        }
+        let vector_hits = self
+            .embedder
+            .search(&self.store, query, self.config.candidates, filter)
+            .await?;
+
+        let mut ranked: BTreeMap<String, RetrievedChunk> = BTreeMap::new();
+        let vector_weight = 1.0 - self.config.hybrid_weight.unwrap_or(0.0);
+        let metric = self.store.config().metric;
+        for (rank, hit) in vector_hits.into_iter().enumerate() {
+            let mut chunk = self.chunk(&hit.id, hit.text.unwrap_or_default(), hit.metadata);
+            chunk.vector_score = Some(hit.score);
+            chunk.score = match self.config.hybrid_weight {
+                Some(_) => vector_weight / (RRF_K + rank as f32 + 1.0),
+                None => relevance(metric, hit.score),
+            };
+            ranked.insert(chunk.chunk_id.clone(), chunk);
+        }
+        if let Some(weight) = self.config.hybrid_weight {
+            let keyword_hits = self
+                .keywords
+                .search_where(query, self.config.candidates, |id| {
+                    let record = self.store.get(id);
+                    record.is_some_and(|r| filter.map_or(true, |f| f.matches(r.metadata)))
+                });
+            for (rank, (id, score)) in keyword_hits.into_iter().enumerate() {
+                let fused = weight / (RRF_K + rank as f32 + 1.0);
+                if let Some(chunk) = ranked.get_mut(&id) {
+                    chunk.score += fused;
+                    chunk.keyword_score = Some(score);
+                } else if let Some(record) = self.store.get(&id) {
+                    let mut chunk = self.chunk(
+                        &id,
+                        record.text.unwrap_or_default().to_string(),
+                        record.metadata.clone(),
+                    );
+                    chunk.score = fused;
+                    chunk.keyword_score = Some(score);
+                    ranked.insert(id, chunk);
+                }
+            }
+        }
+
+        let mut chunks: Vec<RetrievedChunk> = ranked.into_values().collect();
+        chunks.sort_by(|a, b| b.score.total_cmp(&a.score));
+        chunks.truncate(self.config.candidates);
+
+        if let Some(reranker) = &self.reranker {
+            if !chunks.is_empty() {
+                let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+                let options = RerankOptions {
+                    top_n: Some(self.config.top_k),
+                    ..Default::default()
+                };
+                let results = reranker.rerank(query, &texts, &options).await?;
+                let mut reranked = Vec::with_capacity(results.len());
+                for r in results {
+                    if let Some(chunk) = chunks.get(r.index) {
+                        let mut chunk = chunk.clone();
+                        chunk.score = r.relevance_score;
+                        chunk.rerank_score = Some(r.relevance_score);
+                        reranked.push(chunk);
+                    }
+                }
+                reranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+                chunks = reranked;
+            }
+        }
+        chunks.truncate(self.config.top_k);
+        Ok(chunks)
+    }
+
+    /// Retrieve for `query.question` and assemble system prompt, history, numbered
+    /// passages (`Relevant` layer, best first) and the question under the budget.
+    pub async fn build_context(&self, query: &RagQuery) -> Result<RagContext> {
+        let chunks = self
+            .retrieve(&query.question, query.filter.as_ref())
+            .await?;
+        self.assemble(query, &chunks)
+    }
+
+    /// Assemble already-retrieved chunks (see [`build_context`](Self::build_context)).
+    pub fn assemble(&self, query: &RagQuery, chunks: &[RetrievedChunk]) -> Result<RagContext> {
+        let system = match (&query.system, &self.config.citation_instruction) {
+            (Some(s), Some(c)) => Some(format!("{s}\n\n{c}")),
+            (Some(s), None) => Some(s.clone()),
+            (None, c) => c.clone(),
+        };
+
+        let mut envelope = Vec::new();
+        if let Some(system) = system {
+            envelope.push(MessageChunk::new(
+                ContextLayer::System,
+                0,
+                Message::system(system),
+                "system",
+            ));
+        }
+        let mut ts = 1u64;
+        for (i, message) in query.history.iter().enumerate() {
+            envelope.push(MessageChunk::new(
+                ContextLayer::Background,
+                ts,
+                message.clone(),
+                format!("history-{i}"),
+            ));
+            ts += 1;
+        }
+        let mut passages = Vec::with_capacity(chunks.len());
+        for (i, chunk) in chunks.iter().enumerate() {
+            let marker = i + 1;
+            let passage = passage_text(marker, chunk);
+            envelope.push(MessageChunk::new(
+                ContextLayer::Relevant,
+                ts,
+                Message::system(passage.clone()),
+                chunk.chunk_id.clone(),
+            ));
+            ts += 1;
+            passages.push((passage, chunk, marker));
+        }
+        envelope.push(MessageChunk::new(
+            ContextLayer::Active,
+            ts,
+            Message::user(query.question.clone()),
+            "question",
+        ));
+
+        let options = LayeredAssembleOptions {
+            budget: self.config.budget,
+            strategy: self.config.strategy,
+            ..Default::default()
+        };
+        let report = MessageAssembler::assemble_layered(&envelope, &options).map_err(|e| {
+            Error::validation_with_context(
+                e.to_string(),
+                ErrorContext::new()
+                    .with_source("rag")
+                    .with_hint("raise the context budget or shorten the system prompt / question"),
+            )
+        })?;
+
+        let kept: Vec<&str> = report
+            .messages
+            .iter()
+            .filter_map(|m| match &m.content {
+                MessageContent::Text(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        let sources: Vec<SourceAttribution> = passages
+            .iter()
+            .filter(|(passage, _, _)| kept.contains(&passage.as_str()))
+            .map(|(_, chunk, marker)| SourceAttribution {
+                marker: *marker,
+                chunk_id: chunk.chunk_id.clone(),
+                source_id: chunk.source_id.clone(),
+                heading: chunk.heading.clone(),
+                score: chunk.score,
+                cited: false,
+            })
+            .collect();
+        Ok(RagContext {
+            omitted_chunks: chunks.len() - sources.len(),
+            messages: report.messages,
+            sources,
+        })
+    }
+
+    /// Retrieve, assemble and ask `client`; sources cited as `[n]` are flagged.
+    pub async fn answer(&self, client: &AiClient, query: &RagQuery) -> Result<RagAnswer> {
+        let context = self.build_context(query).await?;
+        let response = client.chat().messages(context.messages).execute().await?;
+        let sources = context
+            .sources
+            .into_iter()
+            .map(|mut s| {
+                s.cited = response.content.contains(&format!("[{}]", s.marker));
+                s
+            })
+            .collect();
+        Ok(RagAnswer {
+            content: response.content,
+            sources,
+            usage: response.usage,
+        })
+    }
+
+    fn chunk(&self, id: &str, text: String, metadata: Value) -> RetrievedChunk {
+        RetrievedChunk {
+            chunk_id: id.to_string(),
+            source_id: metadata
+                .get(SOURCE_ID)
+                .and_then(Value::as_str)
+                .unwrap_or(id)
+                .to_string(),
+            heading: metadata
+                .get(HEADING)
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            text,
+            score: 0.0,
+            vector_score: None,
+            keyword_score: None,
+            rerank_score: None,
+            metadata,
+        }
+    }
+}
+
+fn passage_text(marker: usize, chunk: &RetrievedChunk) -> String {
+    match &chunk.heading {
+        Some(h) => format!("[{marker}] {} — {h}\n{}", chunk.source_id, chunk.text),
+        None => format!("[{marker}] {}\n{}", chunk.source_id, chunk.text),
+    }
+}
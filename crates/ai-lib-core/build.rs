@@ -0,0 +1,58 @@
+//! Embeds an optional AI-Protocol snapshot for `protocol::remote`'s last-resort fallback.
+//!
+//! Set `AI_PROTOCOL_BUNDLE_DIR` to an ai-protocol checkout at build time to bundle its
+//! provider manifests; without it the bundled snapshot is empty.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+const SNAPSHOT_DIRS: &[&str] = &[
+    "dist/v2/providers",
+    "dist/v1/providers",
+    "v2/providers",
+    "v1/providers",
+];
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=AI_PROTOCOL_BUNDLE_DIR");
+    let mut entries = Vec::new();
+    if let Some(root) = std::env::var_os("AI_PROTOCOL_BUNDLE_DIR") {
+        let root = Path::new(&root);
+        for dir in SNAPSHOT_DIRS {
+            let path = root.join(dir);
+            println!("cargo:rerun-if-changed={}", path.display());
+            let Ok(read) = std::fs::read_dir(&path) else {
+                continue;
+            };
+            let mut files: Vec<_> = read
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    matches!(
+                        p.extension().and_then(|e| e.to_str()),
+                        Some("json" | "yaml")
+                    )
+                })
+                .collect();
+            files.sort();
+            for file in files {
+                let name = file.file_name().unwrap().to_string_lossy().to_string();
+                entries.push((format!("{dir}/{name}"), file));
+            }
+        }
+    }
+
+    let mut code = String::from("pub(crate) static BUNDLED_MANIFESTS: &[(&str, &str)] = &[\n");
+    for (rel, file) in &entries {
+        let abs = file.canonicalize().unwrap_or_else(|_| file.clone());
+        writeln!(
+            code,
+            "    ({rel:?}, include_str!({:?})),",
+            abs.display().to_string()
+        )
+        .unwrap();
+    }
+    code.push_str("];\n");
+    let out = Path::new(&std::env::var("OUT_DIR").unwrap()).join("bundled_manifests.rs");
+    std::fs::write(out, code).unwrap();
+}
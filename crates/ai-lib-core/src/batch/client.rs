@@ -0,0 +1,882 @@
+//! Provider batch API client.
+//!
+//! Protocol-driven: the batch endpoint is declared in the manifest as
+//! `endpoints.batches` (with `adapter: openai` or `adapter: anthropic`), and the
+//! OpenAI file upload endpoint as `endpoints.files`. Requests are encoded with the
+//! same [`ProviderDriver`] used for ordinary calls, so batch bodies match what
+//! `AiClient` would send.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+use super::types::{
+    BatchApiStyle, BatchItemError, BatchItemErrorKind, BatchItemResult, BatchJob, BatchPollConfig,
+    BatchRequestCounts, BatchRequestItem, BatchStatus,
+};
+use crate::client::endpoint::lookup_endpoint;
+use crate::client::guard::{GuardedCall, MessageGuard};
+use crate::client::{AiClient, UnifiedResponse};
+use crate::drivers::{AnthropicDriver, OpenAiDriver, ProviderDriver};
+use crate::protocol::{ProtocolError, ProtocolManifest};
+use crate::transport::HttpTransport;
+use crate::types::message::ContentBlock;
+use crate::types::tool::ToolCall;
+use crate::{Error, ErrorContext, Result};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Client for a provider's asynchronous batch endpoint.
+pub struct BatchClient {
+    transport: Arc<HttpTransport>,
+    driver: Box<dyn ProviderDriver>,
+    model: String,
+    style: BatchApiStyle,
+    batches_path: String,
+    files_path: Option<String>,
+    target_path: String,
+    completion_window: String,
+    message_guard: Option<Arc<dyn MessageGuard>>,
+    /// Restore state of guarded items by batch id, then `custom_id`; dropped once
+    /// the batch's results are restored.
+    guarded: Mutex<HashMap<String, GuardedItems>>,
+}
+
+type GuardedItems = HashMap<String, Arc<dyn GuardedCall>>;
+
+impl BatchClient {
+    /// Create a batch client that shares the transport (auth, proxy routes) and the
+    /// [`message_guard`](crate::client::AiClientBuilder::message_guard) of `client`.
+    pub fn from_client(client: &AiClient) -> Result<Self> {
+        let batch =
+            Self::with_transport(&client.manifest, &client.model_id, client.transport.clone())?;
+        Ok(match &client.message_guard {
+            Some(guard) => batch.with_message_guard(guard.clone()),
+            None => batch,
+        })
+    }
+
+    /// Create a batch client directly from a manifest.
+    ///
+    /// `base_url_override` and `credential` behave like the matching
+    /// [`AiClientBuilder`](crate::client::AiClientBuilder) options.
+    pub fn from_manifest(
+        manifest: &ProtocolManifest,
+        model_id: &str,
+        base_url_override: Option<&str>,
+        credential: Option<&str>,
+    ) -> Result<Self> {
+        let transport = Arc::new(HttpTransport::new_with_base_url_and_credential(
+            manifest,
+            model_id,
+            base_url_override,
+            credential,
+        )?);
+        Self::with_transport(manifest, model_id, transport)
+    }
+
+    fn with_transport(
+        manifest: &ProtocolManifest,
+        model_id: &str,
+        transport: Arc<HttpTransport>,
+    ) -> Result<Self> {
+        let endpoints = manifest.endpoints.as_ref();
+        let batches = lookup_endpoint(endpoints, "batches").ok_or_else(|| {
+            Error::Protocol(ProtocolError::NotFound {
+                id: "batches".to_string(),
+                hint: Some(
+                    "Declare endpoints.batches (adapter: openai | anthropic) in the provider manifest"
+                        .to_string(),
+                ),
+            })
+        })?;
+        let style = BatchApiStyle::from_adapter(batches.adapter.as_deref());
+        let files_path = lookup_endpoint(endpoints, "files").map(|e| e.path.clone());
+        if style == BatchApiStyle::OpenAi && files_path.is_none() {
+            return Err(Error::Protocol(ProtocolError::NotFound {
+                id: "files".to_string(),
+                hint: Some(
+                    "OpenAI-style batches upload a JSONL file; declare endpoints.files in the manifest"
+                        .to_string(),
+                ),
+            }));
+        }
+
+        // The JSONL `url` is the chat path as seen from the host root (e.g. `/v1/chat/completions`).
+        let chat_path = lookup_endpoint(endpoints, "chat")
+            .map(|e| e.path.clone())
+            .unwrap_or_else(|| "/chat/completions".to_string());
+        let base_path = url::Url::parse(manifest.get_base_url())
+            .map(|u| u.path().trim_end_matches('/').to_string())
+            .unwrap_or_default();
+        let target_path = format!("{}{}", base_path, chat_path);
+
+        let driver: Box<dyn ProviderDriver> = match style {
+            BatchApiStyle::OpenAi => Box::new(OpenAiDriver::new(manifest.id.clone(), Vec::new())),
+            BatchApiStyle::Anthropic => {
+                Box::new(AnthropicDriver::new(manifest.id.clone(), Vec::new()))
+            }
+        };
+
+        Ok(Self {
+            transport,
+            driver,
+            model: model_id.to_string(),
+            style,
+            batches_path: batches.path.trim_end_matches('/').to_string(),
+            files_path: files_path.map(|p| p.trim_end_matches('/').to_string()),
+            target_path,
+            completion_window: "24h".to_string(),
+            message_guard: None,
+            guarded: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Rewrite each item's messages before they are encoded and restore the
+    /// successful results (see [`crate::client::guard`]).
+    ///
+    /// The restore state of a submitted batch lives in this client until
+    /// [`results`](Self::results) returns it. Successful results this client cannot
+    /// restore (fetched by another `BatchClient`, after a restart, a second time, or
+    /// for JSONL from [`build_jsonl`](Self::build_jsonl) submitted elsewhere) are an
+    /// error rather than rewritten text.
+    pub fn with_message_guard(mut self, guard: Arc<dyn MessageGuard>) -> Self {
+        self.message_guard = Some(guard);
+        self
+    }
+
+    /// Override the OpenAI `completion_window` (default `24h`).
+    pub fn with_completion_window(mut self, window: impl Into<String>) -> Self {
+        self.completion_window = window.into();
+        self
+    }
+
+    /// Batch protocol selected from the manifest.
+    pub fn style(&self) -> BatchApiStyle {
+        self.style
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Encode items as an OpenAI batch JSONL file (one request per line).
+    pub fn build_jsonl(&self, items: &[BatchRequestItem]) -> Result<String> {
+        self.encode_jsonl(items, &mut GuardedItems::new())
+    }
+
+    fn encode_jsonl(
+        &self,
+        items: &[BatchRequestItem],
+        guarded: &mut GuardedItems,
+    ) -> Result<String> {
+        validate_custom_ids(items)?;
+        let mut out = String::new();
+        for item in items {
+            let line = serde_json::json!({
+                "custom_id": item.custom_id,
+                "method": "POST",
+                "url": self.target_path,
+                "body": self.encode_body(item, guarded)?,
+            });
+            out.push_str(&serde_json::to_string(&line)?);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Submit items as a new provider batch job.
+    pub async fn submit(&self, items: &[BatchRequestItem]) -> Result<BatchJob> {
+        if items.is_empty() {
+            return Err(Error::validation("batch must contain at least one request"));
+        }
+        let mut guarded = GuardedItems::new();
+        let job = match self.style {
+            BatchApiStyle::OpenAi => {
+                let jsonl = self.encode_jsonl(items, &mut guarded)?;
+                let file_id = self.upload_jsonl(jsonl).await?;
+                let body = serde_json::json!({
+                    "input_file_id": file_id,
+                    "endpoint": self.target_path,
+                    "completion_window": self.completion_window,
+                });
+                let job = self
+                    .send_json("POST", &self.batches_path, Some(&body))
+                    .await?;
+                parse_job(self.style, job)
+            }
+            BatchApiStyle::Anthropic => {
+                validate_custom_ids(items)?;
+                let mut requests = Vec::with_capacity(items.len());
+                for item in items {
+                    requests.push(serde_json::json!({
+                        "custom_id": item.custom_id,
+                        "params": self.encode_body(item, &mut guarded)?,
+                    }));
+                }
+                let body = serde_json::json!({ "requests": requests });
+                let job = self
+                    .send_json("POST", &self.batches_path, Some(&body))
+                    .await?;
+                parse_job(self.style, job)
+            }
+        }?;
+        if !guarded.is_empty() {
+            self.guarded
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(job.id.clone(), guarded);
+        }
+        Ok(job)
+    }
+
+    /// Fetch the current state of a batch job.
+    pub async fn get(&self, batch_id: &str) -> Result<BatchJob> {
+        let path = format!("{}/{}", self.batches_path, batch_id);
+        let job = self.send_json("GET", &path, None).await?;
+        parse_job(self.style, job)
+    }
+
+    /// Request cancellation. Items that already finished keep their results.
+    pub async fn cancel(&self, batch_id: &str) -> Result<BatchJob> {
+        let path = format!("{}/{}/cancel", self.batches_path, batch_id);
+        let job = self.send_json("POST", &path, None).await?;
+        parse_job(self.style, job)
+    }
+
+    /// Poll until the job reaches a terminal state, backing off between polls.
+    pub async fn wait(&self, batch_id: &str, poll: &BatchPollConfig) -> Result<BatchJob> {
+        let started = std::time::Instant::now();
+        let mut interval = poll.initial_interval;
+        loop {
+            let job = self.get(batch_id).await?;
+            if job.status.is_terminal() {
+                return Ok(job);
+            }
+            if let Some(timeout) = poll.timeout {
+                if started.elapsed() + interval > timeout {
+                    return Err(Error::runtime_with_context(
+                        format!(
+                            "batch {} still {:?} after {:?}",
+                            batch_id,
+                            job.status,
+                            started.elapsed()
+                        ),
+                        ErrorContext::new()
+                            .with_source("batch_poll")
+                            .with_retryable(true),
+                    ));
+                }
+            }
+            tracing::debug!(batch_id, status = ?job.status, "batch not finished, polling again");
+            tokio::time::sleep(interval).await;
+            interval = poll.next_interval(interval);
+        }
+    }
+
+    /// Download and decode the results of a finished (or cancelled) job.
+    ///
+    /// Successful and failed items are both returned; the order follows the
+    /// provider's output, so match on `custom_id`. A line that is not valid JSON
+    /// becomes an `Errored` item (with its `custom_id` when it can be recovered).
+    ///
+    /// With a message guard, the batch's restore state is consumed here; see
+    /// [`with_message_guard`](Self::with_message_guard).
+    pub async fn results(&self, job: &BatchJob) -> Result<Vec<BatchItemResult>> {
+        let mut out = Vec::new();
+        match self.style {
+            BatchApiStyle::OpenAi => {
+                let files_path = self.files_path.as_deref().unwrap_or("/files");
+                for file_id in [&job.output_file_id, &job.error_file_id]
+                    .into_iter()
+                    .flatten()
+                {
+                    let path = format!("{}/{}/content", files_path, file_id);
+                    let text = self.send_text("GET", &path).await?;
+                    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+                        out.push(match serde_json::from_str(line) {
+                            Ok(v) => self.parse_openai_line(&v),
+                            Err(e) => malformed_line(line, e),
+                        });
+                    }
+                }
+            }
+            BatchApiStyle::Anthropic => {
+                let path = format!("{}/{}/results", self.batches_path, job.id);
+                let text = self.send_text("GET", &path).await?;
+                for line in text.lines().filter(|l| !l.trim().is_empty()) {
+                    out.push(match serde_json::from_str(line) {
+                        Ok(v) => self.parse_anthropic_line(&v),
+                        Err(e) => malformed_line(line, e),
+                    });
+                }
+            }
+        }
+        self.restore(&job.id, &mut out)?;
+        Ok(out)
+    }
+
+    /// Undo the message guard's rewrite in the results of a batch this client
+    /// submitted, then drop its restore state.
+    fn restore(&self, batch_id: &str, results: &mut [BatchItemResult]) -> Result<()> {
+        if self.message_guard.is_none() {
+            return Ok(());
+        }
+        let mut guarded = self.guarded.lock().unwrap_or_else(|e| e.into_inner());
+        let calls = guarded.get(batch_id);
+        if let Some(result) = results
+            .iter()
+            .find(|r| r.outcome.is_ok() && calls.map_or(true, |c| !c.contains_key(&r.custom_id)))
+        {
+            return Err(Error::runtime_with_context(
+                format!(
+                    "no message guard restore state for item {} of batch {}; it was not \
+                     submitted by this client or its results were already collected",
+                    result.custom_id, batch_id
+                ),
+                ErrorContext::new().with_source("batch"),
+            ));
+        }
+        let Some(calls) = guarded.remove(batch_id) else {
+            return Ok(());
+        };
+        for result in results {
+            if let (Ok(response), Some(call)) = (&mut result.outcome, calls.get(&result.custom_id))
+            {
+                call.restore_response(response);
+            }
+        }
+        Ok(())
+    }
+
+    /// Submit, wait and collect results, returned in the order of `items`.
+    ///
+    /// Items missing from the provider output are reported as cancelled/expired
+    /// (matching the job status) or as errors.
+    pub async fn run(
+        &self,
+        items: &[BatchRequestItem],
+        poll: &BatchPollConfig,
+    ) -> Result<Vec<BatchItemResult>> {
+        let job = self.submit(items).await?;
+        let job = self.wait(&job.id, poll).await?;
+        let mut by_id: HashMap<String, BatchItemResult> = self
+            .results(&job)
+            .await?
+            .into_iter()
+            .map(|r| (r.custom_id.clone(), r))
+            .collect();
+
+        let missing_kind = match job.status {
+            BatchStatus::Cancelled => BatchItemErrorKind::Cancelled,
+            BatchStatus::Expired => BatchItemErrorKind::Expired,
+            _ => BatchItemErrorKind::Errored,
+        };
+        Ok(items
+            .iter()
+            .map(|item| {
+                by_id
+                    .remove(&item.custom_id)
+                    .unwrap_or_else(|| BatchItemResult {
+                        custom_id: item.custom_id.clone(),
+                        outcome: Err(BatchItemError {
+                            kind: missing_kind.clone(),
+                            status: None,
+                            code: None,
+                            message: format!("no result returned for batch {}", job.id),
+                        }),
+                    })
+            })
+            .collect())
+    }
+
+    /// Encode one item; its guarded call, if any, is added to `guarded`.
+    fn encode_body(&self, item: &BatchRequestItem, guarded: &mut GuardedItems) -> Result<Value> {
+        let req = &item.request;
+        let mut extra = serde_json::Map::new();
+        if let Some(tools) = &req.tools {
+            extra.insert("tools".to_string(), serde_json::to_value(tools)?);
+        }
+        if let Some(tool_choice) = &req.tool_choice {
+            extra.insert("tool_choice".to_string(), tool_choice.clone());
+        }
+        let extra = Value::Object(extra);
+        let mut messages = std::borrow::Cow::Borrowed(&req.messages);
+        if let Some(guard) = &self.message_guard {
+            guarded.insert(item.custom_id.clone(), guard.protect(messages.to_mut()));
+        }
+        let mut body = self
+            .driver
+            .build_request(
+                &messages,
+                &self.model,
+                req.temperature,
+                req.max_tokens,
+                false,
+                Some(&extra),
+            )?
+            .body;
+        // Batch endpoints never stream.
+        if let Value::Object(map) = &mut body {
+            map.remove("stream");
+        }
+        Ok(body)
+    }
+
+    async fn upload_jsonl(&self, jsonl: String) -> Result<String> {
+        let files_path = self.files_path.as_deref().unwrap_or("/files");
+        let resp = self
+            .transport
+            .send_with("POST", files_path, |req| {
+                let part = reqwest::multipart::Part::bytes(jsonl.clone().into_bytes())
+                    .file_name("batch.jsonl")
+                    .mime_str("application/jsonl")
+                    .expect("static mime type is valid");
+                let form = reqwest::multipart::Form::new()
+                    .text("purpose", "batch")
+                    .part("file", part);
+                req.multipart(form)
+            })
+            .await?;
+        let file = read_json(resp).await?;
+        file.get("id")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .ok_or_else(|| {
+                Error::api_with_context(
+                    "Invalid file upload response: missing id",
+                    ErrorContext::new().with_source("batch"),
+                )
+            })
+    }
+
+    async fn send_json(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let anthropic = self.style == BatchApiStyle::Anthropic;
+        let resp = self
+            .transport
+            .send_with(method, path, |mut req| {
+                if anthropic {
+                    req = req.header("anthropic-version", ANTHROPIC_VERSION);
+                }
+                if let Some(body) = body {
+                    req = req.json(body);
+                }
+                req.header("accept", "application/json")
+            })
+            .await?;
+        read_json(resp).await
+    }
+
+    async fn send_text(&self, method: &str, path: &str) -> Result<String> {
+        let anthropic = self.style == BatchApiStyle::Anthropic;
+        let resp = self
+            .transport
+            .send_with(method, path, |req| {
+                if anthropic {
+                    req.header("anthropic-version", ANTHROPIC_VERSION)
+                } else {
+                    req
+                }
+            })
+            .await?;
+        read_text(resp).await
+    }
+
+    fn parse_openai_line(&self, line: &Value) -> BatchItemResult {
+        let custom_id = line
+            .get("custom_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let status = line
+            .pointer("/response/status_code")
+            .and_then(|v| v.as_u64())
+            .map(|s| s as u16);
+        let error = line.get("error").filter(|e| !e.is_null()).or_else(|| {
+            status
+                .filter(|s| *s >= 400)
+                .and_then(|_| line.pointer("/response/body/error"))
+        });
+
+        let outcome = match error {
+            Some(err) => {
+                let code = err.get("code").and_then(|v| v.as_str()).map(String::from);
+                let kind = match code.as_deref() {
+                    Some("batch_cancelled") => BatchItemErrorKind::Cancelled,
+                    Some("batch_expired") => BatchItemErrorKind::Expired,
+                    _ => BatchItemErrorKind::Errored,
+                };
+                Err(BatchItemError {
+                    kind,
+                    status,
+                    code,
+                    message: error_message(err),
+                })
+            }
+            None => match line.pointer("/response/body") {
+                Some(body) => self.to_unified(body),
+                None => Err(BatchItemError {
+                    kind: BatchItemErrorKind::Errored,
+                    status,
+                    code: None,
+                    message: "batch result line has neither response body nor error".to_string(),
+                }),
+            },
+        };
+        BatchItemResult { custom_id, outcome }
+    }
+
+    fn parse_anthropic_line(&self, line: &Value) -> BatchItemResult {
+        let custom_id = line
+            .get("custom_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let result = line.get("result").cloned().unwrap_or(Value::Null);
+        let outcome = match result.get("type").and_then(|v| v.as_str()) {
+            Some("succeeded") => match result.get("message") {
+                Some(message) => self.to_unified(message),
+                None => Err(BatchItemError {
+                    kind: BatchItemErrorKind::Errored,
+                    status: None,
+                    code: None,
+                    message: "succeeded result without message".to_string(),
+                }),
+            },
+            Some("canceled") => Err(BatchItemError {
+                kind: BatchItemErrorKind::Cancelled,
+                status: None,
+                code: None,
+                message: "request cancelled before processing".to_string(),
+            }),
+            Some("expired") => Err(BatchItemError {
+                kind: BatchItemErrorKind::Expired,
+                status: None,
+                code: None,
+                message: "batch expired before the request was processed".to_string(),
+            }),
+            _ => {
+                let err = result
+                    .pointer("/error/error")
+                    .or_else(|| result.get("error"))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                Err(BatchItemError {
+                    kind: BatchItemErrorKind::Errored,
+                    status: None,
+                    code: err.get("type").and_then(|v| v.as_str()).map(String::from),
+                    message: error_message(&err),
+                })
+            }
+        };
+        BatchItemResult { custom_id, outcome }
+    }
+
+    fn to_unified(&self, body: &Value) -> std::result::Result<UnifiedResponse, BatchItemError> {
+        let parsed = self
+            .driver
+            .parse_response(body)
+            .map_err(|e| BatchItemError {
+                kind: BatchItemErrorKind::Errored,
+                status: None,
+                code: None,
+                message: e.to_string(),
+            })?;
+        let mut response = UnifiedResponse::new(parsed.content.unwrap_or_default());
+        response.tool_calls = parsed.tool_calls.iter().filter_map(to_tool_call).collect();
+        response.usage = body.get("usage").cloned();
+        response.images = ContentBlock::inline_images(body);
+        Ok(response)
+    }
+}
+
+fn validate_custom_ids(items: &[BatchRequestItem]) -> Result<()> {
+    let mut seen = HashSet::with_capacity(items.len());
+    for item in items {
+        if item.custom_id.is_empty() {
+            return Err(Error::validation("batch custom_id must not be empty"));
+        }
+        if !seen.insert(item.custom_id.as_str()) {
+            return Err(Error::validation(format!(
+                "duplicate batch custom_id: {}",
+                item.custom_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn parse_job(style: BatchApiStyle, raw: Value) -> Result<BatchJob> {
+    let id = raw
+        .get("id")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| {
+            Error::api_with_context(
+                "Invalid batch response: missing id",
+                ErrorContext::new().with_source("batch"),
+            )
+        })?;
+    let count = |key: &str| -> u64 {
+        raw.pointer(&format!("/request_counts/{}", key))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    };
+    let str_field = |key: &str| raw.get(key).and_then(|v| v.as_str()).map(String::from);
+
+    let (status, counts) = match style {
+        BatchApiStyle::OpenAi => (
+            BatchStatus::from_openai(raw.get("status").and_then(|v| v.as_str()).unwrap_or("")),
+            BatchRequestCounts {
+                total: count("total"),
+                succeeded: count("completed"),
+                failed: count("failed"),
+            },
+        ),
+        BatchApiStyle::Anthropic => {
+            // A cancel that lands after every request finished cancels nothing.
+            let cancelled = raw.get("cancel_initiated_at").is_some_and(|v| !v.is_null())
+                && count("canceled") > 0;
+            let status = match raw.get("processing_status").and_then(|v| v.as_str()) {
+                Some("canceling") => BatchStatus::Cancelling,
+                Some("ended") if cancelled => BatchStatus::Cancelled,
+                Some("ended") => BatchStatus::Completed,
+                _ => BatchStatus::InProgress,
+            };
+            let failed = count("errored") + count("canceled") + count("expired");
+            (
+                status,
+                BatchRequestCounts {
+                    total: count("processing") + count("succeeded") + failed,
+                    succeeded: count("succeeded"),
+                    failed,
+                },
+            )
+        }
+    };
+
+    Ok(BatchJob {
+        id,
+        status,
+        counts,
+        output_file_id: str_field("output_file_id"),
+        error_file_id: str_field("error_file_id"),
+        raw,
+    })
+}
+
+fn malformed_line(line: &str, err: serde_json::Error) -> BatchItemResult {
+    // A truncated line usually still starts with its id.
+    let custom_id = line
+        .split_once("\"custom_id\"")
+        .and_then(|(_, rest)| rest.trim_start().strip_prefix(':'))
+        .and_then(|rest| rest.trim_start().strip_prefix('"'))
+        .and_then(|rest| rest.split_once('"'))
+        .map(|(id, _)| id.to_string())
+        .unwrap_or_default();
+    BatchItemResult {
+        custom_id,
+        outcome: Err(BatchItemError {
+            kind: BatchItemErrorKind::Errored,
+            status: None,
+            code: None,
+            message: format!("malformed batch result line: {}", err),
+        }),
+    }
+}
+
+fn error_message(err: &Value) -> String {
+    err.get("message")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| err.to_string())
+}
+
+/// Normalize OpenAI (`function.arguments` string) and Anthropic (`input`) tool calls.
+fn to_tool_call(v: &Value) -> Option<ToolCall> {
+    let id = v.get("id")?.as_str()?.to_string();
+    if let Some(function) = v.get("function") {
+        let name = function.get("name")?.as_str()?.to_string();
+        let arguments = match function.get("arguments") {
+            Some(Value::String(s)) => {
+                serde_json::from_str(s).unwrap_or_else(|_| Value::String(s.clone()))
+            }
+            Some(other) => other.clone(),
+            None => Value::Null,
+        };
+        return Some(ToolCall {
+            id,
+            name,
+            arguments,
+        });
+    }
+    Some(ToolCall {
+        id,
+        name: v.get("name")?.as_str()?.to_string(),
+        arguments: v.get("input").cloned().unwrap_or(Value::Null),
+    })
+}
+
+async fn read_text(resp: reqwest::Response) -> Result<String> {
+    let status = resp.status();
+    let body = resp.text().await.map_err(|e| {
+        Error::network_with_context(
+            format!("Failed to read batch response: {}", e),
+            ErrorContext::new().with_source("batch"),
+        )
+    })?;
+    if !status.is_success() {
+        return Err(Error::api_with_context(
+            format!("Batch API error ({}): {}", status, body),
+            ErrorContext::new()
+                .with_source("batch")
+                .with_status_code(status.as_u16()),
+        ));
+    }
+    Ok(body)
+}
+
+async fn read_json(resp: reqwest::Response) -> Result<Value> {
+    let body = read_text(resp).await?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ChatBatchRequest;
+    use crate::types::message::Message;
+
+    fn manifest(adapter: &str) -> ProtocolManifest {
+        serde_yaml::from_str(&format!(
+            r#"
+id: test
+protocol_version: "2.0"
+status: stable
+category: ai_provider
+official_url: "https://example.com"
+support_contact: "https://example.com"
+endpoint:
+  base_url: "https://api.example.com/v1"
+capabilities:
+  streaming: true
+  tools: true
+  vision: false
+endpoints:
+  chat:
+    path: "/chat/completions"
+  batches:
+    path: "/batches"
+    adapter: {adapter}
+  files:
+    path: "/files"
+"#
+        ))
+        .unwrap()
+    }
+
+    fn items() -> Vec<BatchRequestItem> {
+        vec![
+            BatchRequestItem::new("a", ChatBatchRequest::new(vec![Message::user("hi")])),
+            BatchRequestItem::new(
+                "b",
+                ChatBatchRequest::new(vec![Message::user("yo")]).max_tokens(16),
+            ),
+        ]
+    }
+
+    #[test]
+    fn jsonl_targets_chat_path_under_base_url() {
+        let client =
+            BatchClient::from_manifest(&manifest("openai"), "gpt-4o-mini", None, Some("k"))
+                .unwrap();
+        let jsonl = client.build_jsonl(&items()).unwrap();
+        let lines: Vec<Value> = jsonl
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["url"], "/v1/chat/completions");
+        assert_eq!(lines[1]["custom_id"], "b");
+        assert_eq!(lines[1]["body"]["max_tokens"], 16);
+        assert!(lines[0]["body"].get("stream").is_none());
+    }
+
+    #[test]
+    fn duplicate_custom_ids_are_rejected() {
+        let client =
+            BatchClient::from_manifest(&manifest("openai"), "gpt-4o-mini", None, Some("k"))
+                .unwrap();
+        let mut dup = items();
+        dup[1].custom_id = "a".to_string();
+        assert!(client.build_jsonl(&dup).is_err());
+    }
+
+    #[test]
+    fn anthropic_results_map_partial_failures() {
+        let client =
+            BatchClient::from_manifest(&manifest("anthropic"), "claude", None, Some("k")).unwrap();
+        assert_eq!(client.style(), BatchApiStyle::Anthropic);
+        let ok = client.parse_anthropic_line(&serde_json::json!({
+            "custom_id": "a",
+            "result": {"type": "succeeded", "message": {
+                "content": [{"type": "text", "text": "hello"}],
+                "stop_reason": "end_turn",
+                "usage": {"input_tokens": 3, "output_tokens": 1}
+            }}
+        }));
+        assert_eq!(ok.outcome.unwrap().content, "hello");
+
+        let err = client.parse_anthropic_line(&serde_json::json!({
+            "custom_id": "b",
+            "result": {"type": "errored", "error": {"type": "error", "error": {"type": "invalid_request_error", "message": "bad"}}}
+        }));
+        let err = err.outcome.unwrap_err();
+        assert_eq!(err.kind, BatchItemErrorKind::Errored);
+        assert_eq!(err.code.as_deref(), Some("invalid_request_error"));
+        assert_eq!(err.message, "bad");
+    }
+
+    #[test]
+    fn anthropic_ended_after_cancel_is_cancelled() {
+        let job = parse_job(
+            BatchApiStyle::Anthropic,
+            serde_json::json!({
+                "id": "msgbatch_1",
+                "processing_status": "ended",
+                "cancel_initiated_at": "2026-01-01T00:00:00Z",
+                "request_counts": {"processing": 0, "succeeded": 1, "errored": 0, "canceled": 2, "expired": 0}
+            }),
+        )
+        .unwrap();
+        assert_eq!(job.status, BatchStatus::Cancelled);
+        assert_eq!(job.counts.total, 3);
+        assert_eq!(job.counts.failed, 2);
+
+        let job = parse_job(
+            BatchApiStyle::Anthropic,
+            serde_json::json!({
+                "id": "msgbatch_2",
+                "processing_status": "ended",
+                "cancel_initiated_at": "2026-01-01T00:00:00Z",
+                "request_counts": {"processing": 0, "succeeded": 3, "errored": 0, "canceled": 0, "expired": 0}
+            }),
+        )
+        .unwrap();
+        assert_eq!(job.status, BatchStatus::Completed);
+    }
+
+    #[test]
+    fn malformed_result_line_is_an_item_error() {
+        let e = serde_json::from_str::<Value>("{").unwrap_err();
+        let truncated = malformed_line(r#"{"custom_id": "b", "result": {"type": "succ"#, e);
+        assert_eq!(truncated.custom_id, "b");
+        let err = truncated.outcome.unwrap_err();
+        assert_eq!(err.kind, BatchItemErrorKind::Errored);
+        assert!(err.message.starts_with("malformed batch result line"));
+
+        let e = serde_json::from_str::<Value>("not json").unwrap_err();
+        assert_eq!(malformed_line("not json", e).custom_id, "");
+    }
+}
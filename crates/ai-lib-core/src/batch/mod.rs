@@ -0,0 +1,55 @@
+//! 供应商批处理 API 模块：OpenAI Batch 与 Anthropic Message Batches 的异步批量作业。
+//!
+//! # Provider Batch APIs
+//!
+//! Asynchronous, discounted batch endpoints offered by providers, as opposed to
+//! [`AiClient::chat_batch`](crate::AiClient::chat_batch) which fans out ordinary
+//! concurrent calls.
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`BatchClient`] | Submit, poll, cancel and collect provider batch jobs |
+//! | [`BatchRequestItem`] | A [`ChatBatchRequest`](crate::ChatBatchRequest) tagged with a `custom_id` |
+//! | [`BatchJob`] / [`BatchStatus`] | Normalized job state across providers |
+//! | [`BatchItemResult`] | Per-item `UnifiedResponse` or [`BatchItemError`], keyed by `custom_id` |
+//! | [`BatchPollConfig`] | Exponential backoff for [`BatchClient::wait`] |
+//!
+//! The manifest declares the endpoint:
+//!
+//! ```yaml
+//! endpoints:
+//!   batches: { path: "/batches", adapter: openai }   # or adapter: anthropic
+//!   files:   { path: "/files" }                       # OpenAI-style only
+//! ```
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ai_lib_core::batch::{BatchClient, BatchPollConfig, BatchRequestItem};
+//! use ai_lib_core::{AiClient, ChatBatchRequest, Message};
+//!
+//! # async fn run() -> ai_lib_core::Result<()> {
+//! let client = AiClient::new("openai/gpt-4o-mini").await?;
+//! let batch = BatchClient::from_client(&client)?;
+//! let items = vec![
+//!     BatchRequestItem::new("q1", ChatBatchRequest::new(vec![Message::user("2+2?")])),
+//!     BatchRequestItem::new("q2", ChatBatchRequest::new(vec![Message::user("3+3?")])),
+//! ];
+//! for result in batch.run(&items, &BatchPollConfig::default()).await? {
+//!     match result.outcome {
+//!         Ok(resp) => println!("{}: {}", result.custom_id, resp.content),
+//!         Err(e) => println!("{} failed: {}", result.custom_id, e),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod client;
+mod types;
+
+pub use client::BatchClient;
+pub use types::{
+    BatchApiStyle, BatchItemError, BatchItemErrorKind, BatchItemResult, BatchJob, BatchPollConfig,
+    BatchRequestCounts, BatchRequestItem, BatchStatus,
+};
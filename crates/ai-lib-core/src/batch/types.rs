@@ -0,0 +1,167 @@
+//! Provider batch API types.
+
+use std::time::Duration;
+
+use crate::client::{ChatBatchRequest, UnifiedResponse};
+
+/// Which provider batch protocol an endpoint speaks.
+///
+/// Selected by the `adapter` of the manifest's `endpoints.batches` entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchApiStyle {
+    /// OpenAI Batch API: JSONL file upload + `/batches` job.
+    OpenAi,
+    /// Anthropic Message Batches: inline `requests` array.
+    Anthropic,
+}
+
+impl BatchApiStyle {
+    pub(crate) fn from_adapter(adapter: Option<&str>) -> Self {
+        match adapter {
+            Some(a) if a.starts_with("anthropic") => Self::Anthropic,
+            _ => Self::OpenAi,
+        }
+    }
+}
+
+/// One request inside a batch, addressed by a caller-chosen `custom_id`.
+#[derive(Debug, Clone)]
+pub struct BatchRequestItem {
+    pub custom_id: String,
+    pub request: ChatBatchRequest,
+}
+
+impl BatchRequestItem {
+    pub fn new(custom_id: impl Into<String>, request: ChatBatchRequest) -> Self {
+        Self {
+            custom_id: custom_id.into(),
+            request,
+        }
+    }
+}
+
+/// Lifecycle state of a provider batch job (normalized across providers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchStatus {
+    Validating,
+    InProgress,
+    Finalizing,
+    Completed,
+    Failed,
+    Expired,
+    Cancelling,
+    Cancelled,
+}
+
+impl BatchStatus {
+    /// Whether the job will not change state anymore.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::Completed | Self::Failed | Self::Expired | Self::Cancelled
+        )
+    }
+
+    pub(crate) fn from_openai(s: &str) -> Self {
+        match s {
+            "validating" => Self::Validating,
+            "in_progress" => Self::InProgress,
+            "finalizing" => Self::Finalizing,
+            "completed" => Self::Completed,
+            "failed" => Self::Failed,
+            "expired" => Self::Expired,
+            "cancelling" => Self::Cancelling,
+            "cancelled" => Self::Cancelled,
+            _ => Self::InProgress,
+        }
+    }
+}
+
+/// Per-job request counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchRequestCounts {
+    pub total: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+/// A provider batch job as last reported by the provider.
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    pub id: String,
+    pub status: BatchStatus,
+    pub counts: BatchRequestCounts,
+    /// OpenAI: file holding successful results.
+    pub output_file_id: Option<String>,
+    /// OpenAI: file holding failed requests.
+    pub error_file_id: Option<String>,
+    /// Raw provider job object.
+    pub raw: serde_json::Value,
+}
+
+/// Why a single batch item did not produce a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchItemErrorKind {
+    /// The provider rejected or failed the request.
+    Errored,
+    /// The batch was cancelled before the request ran.
+    Cancelled,
+    /// The batch expired before the request ran.
+    Expired,
+}
+
+/// Failure details for one batch item.
+#[derive(Debug, Clone)]
+pub struct BatchItemError {
+    pub kind: BatchItemErrorKind,
+    pub status: Option<u16>,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for BatchItemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "{:?} ({}): {}", self.kind, code, self.message),
+            None => write!(f, "{:?}: {}", self.kind, self.message),
+        }
+    }
+}
+
+impl std::error::Error for BatchItemError {}
+
+/// Result of one batch item, mapped back by `custom_id`.
+#[derive(Debug)]
+pub struct BatchItemResult {
+    pub custom_id: String,
+    pub outcome: std::result::Result<UnifiedResponse, BatchItemError>,
+}
+
+/// Polling schedule used by [`BatchClient::wait`](super::BatchClient::wait).
+#[derive(Debug, Clone)]
+pub struct BatchPollConfig {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub multiplier: f64,
+    /// Give up (without cancelling) after this long; `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for BatchPollConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_secs(5),
+            max_interval: Duration::from_secs(300),
+            multiplier: 2.0,
+            timeout: None,
+        }
+    }
+}
+
+impl BatchPollConfig {
+    pub(crate) fn next_interval(&self, current: Duration) -> Duration {
+        current
+            .mul_f64(self.multiplier.max(1.0))
+            .min(self.max_interval)
+    }
+}
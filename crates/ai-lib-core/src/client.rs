@@ -8,11 +8,15 @@
 pub mod builder;
 pub mod chat;
 pub mod core;
+pub mod degrade;
 pub mod endpoint;
 pub mod error_classification;
 mod execution;
+pub mod guard;
+pub mod hedge;
 mod policy;
 mod preflight;
+pub mod recovery;
 pub mod signals;
 pub mod types;
 mod validation;
@@ -20,8 +24,12 @@ mod validation;
 pub use builder::AiClientBuilder;
 pub use chat::{ChatBatchRequest, ChatRequestBuilder};
 pub use core::{AiClient, UnifiedResponse};
+pub use degrade::{DegradationReport, DegradeOptions, ImageFallback, Rewrite, TargetCapabilities};
 pub use endpoint::EndpointExt;
 pub use error_classification::classify_error_from_response;
+pub use guard::{GuardedCall, MessageGuard};
+pub use hedge::{HedgeDelay, HedgePolicy, HedgeStats, HedgeWinner};
 pub use policy::{Decision, PolicyEngine};
+pub use recovery::{RecoveryMode, RecoveryPolicy};
 pub use signals::SignalsSnapshot;
 pub use types::{CallStats, CancelHandle, ClientMetrics};
@@ -34,6 +34,16 @@ pub struct AiClientBuilder {
     /// Override base URL (primarily for testing with mock servers)
     base_url_override: Option<String>,
     credential_override: Option<String>,
+    fallback_endpoints: std::collections::HashMap<String, crate::client::core::FallbackEndpoint>,
+    cassette: Option<Arc<crate::transport::Cassette>>,
+    responder: Option<Arc<dyn crate::transport::Responder>>,
+    manifests: Vec<crate::protocol::ProtocolManifest>,
+    degrade: Option<crate::client::degrade::DegradeOptions>,
+    hedge: Option<crate::client::hedge::HedgePolicy>,
+    recovery: Option<crate::client::recovery::RecoveryPolicy>,
+    message_guard: Option<Arc<dyn crate::client::guard::MessageGuard>>,
+    #[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+    files: Option<(Arc<crate::files::FilesClient>, usize)>,
 }
 
 impl AiClientBuilder {
@@ -47,6 +57,16 @@ impl AiClientBuilder {
             max_inflight: None,
             base_url_override: None,
             credential_override: None,
+            fallback_endpoints: std::collections::HashMap::new(),
+            cassette: None,
+            responder: None,
+            manifests: Vec::new(),
+            degrade: None,
+            hedge: None,
+            recovery: None,
+            message_guard: None,
+            #[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+            files: None,
         }
     }
 
@@ -107,11 +127,130 @@ impl AiClientBuilder {
         self
     }
 
+    /// Credential for one fallback model (`provider/model` as passed to
+    /// [`Self::with_fallbacks`]).
+    ///
+    /// Without one, a fallback on the same provider reuses [`Self::credential`] and a
+    /// fallback on another provider resolves its own key from the manifest chain.
+    pub fn fallback_credential(
+        mut self,
+        model: impl Into<String>,
+        credential: impl Into<String>,
+    ) -> Self {
+        self.fallback_endpoints
+            .entry(model.into())
+            .or_default()
+            .credential = Some(credential.into());
+        self
+    }
+
+    /// Base URL override for one fallback model.
+    pub fn fallback_base_url(
+        mut self,
+        model: impl Into<String>,
+        base_url: impl Into<String>,
+    ) -> Self {
+        self.fallback_endpoints
+            .entry(model.into())
+            .or_default()
+            .base_url = Some(base_url.into());
+        self
+    }
+
     /// Alias for [`Self::credential`] for API-key based providers.
     pub fn api_key(self, api_key: impl Into<String>) -> Self {
         self.credential(api_key)
     }
 
+    /// Record or replay HTTP exchanges through a cassette file.
+    ///
+    /// When unset, `AI_LIB_CASSETTE` / `AI_LIB_CASSETTE_MODE` are consulted. Fallback
+    /// clients share the same cassette.
+    pub fn cassette(mut self, cassette: Arc<crate::transport::Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Serve all HTTP traffic in-process (see [`crate::transport::Responder`]).
+    /// Fallback clients share the responder.
+    pub fn responder(mut self, responder: Arc<dyn crate::transport::Responder>) -> Self {
+        self.responder = Some(responder);
+        self
+    }
+
+    /// Rewrite requests that use capabilities a candidate model lacks (tools, structured
+    /// output, images, audio, documents, system role) instead of rejecting them.
+    ///
+    /// Applies to the primary model and every fallback; the rewrites are reported on
+    /// [`UnifiedResponse::degradation`](crate::client::UnifiedResponse::degradation) and
+    /// [`CallStats::degradation`](crate::client::CallStats::degradation). Responses and
+    /// streams are mapped back to what the original request asked for (e.g.
+    /// `<tool_call>` text into tool calls). See [`crate::client::degrade`].
+    pub fn degrade_requests(mut self, enable: bool) -> Self {
+        if enable {
+            self.degrade.get_or_insert_with(Default::default);
+        } else {
+            self.degrade = None;
+        }
+        self
+    }
+
+    /// Enable request degradation with custom [`DegradeOptions`](crate::client::DegradeOptions).
+    pub fn degrade_options(mut self, options: crate::client::degrade::DegradeOptions) -> Self {
+        self.degrade = Some(options);
+        self
+    }
+
+    /// Race slow streaming calls against a secondary model (see [`crate::client::hedge`]).
+    ///
+    /// When the primary has not produced its first event within the policy's delay,
+    /// the same request is started on the hedge model and the first stream to
+    /// produce an event is returned; the other is cancelled.
+    pub fn hedging(mut self, policy: crate::client::hedge::HedgePolicy) -> Self {
+        self.hedge = Some(policy);
+        self
+    }
+
+    /// Resume streams that fail after emitting output (see [`crate::client::recovery`]).
+    ///
+    /// The partial assistant text is sent to the same model or a remaining fallback
+    /// and the continuation is spliced into the caller's stream after a
+    /// `StreamRecovered` event.
+    pub fn stream_recovery(mut self, policy: crate::client::recovery::RecoveryPolicy) -> Self {
+        self.recovery = Some(policy);
+        self
+    }
+
+    /// Rewrite chat messages before they leave the process and restore the response
+    /// or stream (see [`crate::client::guard`]), e.g. PII pseudonymization.
+    ///
+    /// Provider batches created with `BatchClient::from_client` (`batch` feature) are
+    /// guarded too.
+    pub fn message_guard(mut self, guard: Arc<dyn crate::client::guard::MessageGuard>) -> Self {
+        self.message_guard = Some(guard);
+        self
+    }
+
+    /// Upload inline base64 documents and images of at least `min_bytes` decoded bytes
+    /// through `files` before each request is encoded, and send file references instead
+    /// (see [`FilesClient::offload`](crate::files::FilesClient::offload)).
+    ///
+    /// `files` must target this model's provider and account. Fallbacks on the same
+    /// provider that reuse this client's credential offload too; others get the
+    /// inline blocks.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+    pub fn files(mut self, files: crate::files::FilesClient, min_bytes: usize) -> Self {
+        self.files = Some((Arc::new(files), min_bytes));
+        self
+    }
+
+    /// Register an in-memory manifest, resolved by `"<manifest.id>/<model>"` ahead of
+    /// `protocol_path` and `AI_PROTOCOL_DIR`.
+    pub fn protocol_manifest(mut self, manifest: crate::protocol::ProtocolManifest) -> Self {
+        self.manifests.push(manifest);
+        self
+    }
+
     /// Build the client.
     pub async fn build(self, model: &str) -> Result<AiClient> {
         let mut loader = ProtocolLoader::new();
@@ -124,6 +263,10 @@ impl AiClientBuilder {
             loader = loader.with_hot_reload(true);
         }
 
+        for manifest in self.manifests {
+            loader = loader.with_manifest(manifest);
+        }
+
         // model is in form "provider/model-id" or "provider/org/model-name" (e.g. nvidia/minimaxai/minimax-m2)
         let parts: Vec<&str> = model.split('/').collect();
         let model_id = if parts.len() >= 2 {
@@ -142,14 +285,23 @@ impl AiClientBuilder {
             .base_url_override
             .or_else(|| std::env::var("MOCK_HTTP_URL").ok());
 
-        let transport = Arc::new(
-            crate::transport::HttpTransport::new_with_base_url_and_credential(
-                &manifest,
-                &model_id,
-                base_url_override.as_deref(),
-                self.credential_override.as_deref(),
-            )?,
-        );
+        let mut transport = crate::transport::HttpTransport::new_with_base_url_and_credential(
+            &manifest,
+            &model_id,
+            base_url_override.as_deref(),
+            self.credential_override.as_deref(),
+        )?;
+        let cassette = match self.cassette {
+            Some(c) => Some(c),
+            None => crate::transport::Cassette::from_env()?.map(Arc::new),
+        };
+        if let Some(cassette) = cassette {
+            transport = transport.with_cassette(cassette);
+        }
+        if let Some(responder) = self.responder {
+            transport = transport.with_responder(responder);
+        }
+        let transport = Arc::new(transport);
         let pipeline = Arc::new(crate::pipeline::Pipeline::from_manifest(&manifest)?);
 
         let max_inflight = self.max_inflight.or_else(|| {
@@ -179,10 +331,24 @@ impl AiClientBuilder {
             inflight,
             max_inflight,
             credential_override: self.credential_override,
+            base_url_override,
+            fallback_endpoints: Arc::new(self.fallback_endpoints),
             attempt_timeout,
+            degrade: self.degrade,
+            hedge: self
+                .hedge
+                .map(|policy| Arc::new(crate::client::hedge::Hedger::new(policy))),
+            recovery: self.recovery,
+            message_guard: self.message_guard,
+            #[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+            files: self.files,
             total_requests: AtomicU64::new(0),
             successful_requests: AtomicU64::new(0),
             total_tokens: AtomicU64::new(0),
+            hedged_requests: AtomicU64::new(0),
+            hedge_wins: AtomicU64::new(0),
+            hedge_cancelled_streams: AtomicU64::new(0),
+            hedge_loser_tokens: AtomicU64::new(0),
         })
     }
 }
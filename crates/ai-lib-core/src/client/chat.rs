@@ -61,6 +61,8 @@ pub struct ChatRequestBuilder<'a> {
     pub(crate) model: Option<String>,
     /// JSON / structured output (`response_format` in provider request body).
     pub(crate) response_format: Option<crate::structured::JsonModeConfig>,
+    /// Prompt template identity copied into the returned `CallStats`.
+    pub(crate) prompt: Option<crate::types::prompt::PromptRef>,
 }
 
 impl<'a> ChatRequestBuilder<'a> {
@@ -75,6 +77,7 @@ impl<'a> ChatRequestBuilder<'a> {
             tool_choice: None,
             model: None,
             response_format: None,
+            prompt: None,
         }
     }
 
@@ -152,6 +155,22 @@ impl<'a> ChatRequestBuilder<'a> {
         self
     }
 
+    /// Use the messages of a rendered prompt template and record its id/version in
+    /// the returned [`CallStats`](crate::CallStats).
+    #[cfg(feature = "prompts")]
+    pub fn prompt(mut self, rendered: crate::prompts::RenderedPrompt) -> Self {
+        self.prompt = Some(rendered.prompt);
+        self.messages = rendered.messages;
+        self
+    }
+
+    /// Record which prompt template produced the messages (for callers rendering
+    /// prompts themselves).
+    pub fn prompt_ref(mut self, prompt: crate::types::prompt::PromptRef) -> Self {
+        self.prompt = Some(prompt);
+        self
+    }
+
     /// Execute the request and return a stream of events.
     pub async fn execute_stream(
         self,
@@ -165,6 +184,15 @@ impl<'a> ChatRequestBuilder<'a> {
     /// Streaming semantics:
     /// - retry/fallback may happen only before any event is emitted to the caller
     /// - once an event is emitted, we will not retry automatically to avoid duplicate output
+    /// - with a [`HedgePolicy`](crate::client::HedgePolicy), a slow primary is raced
+    ///   against the hedge model
+    /// - with a [`RecoveryPolicy`](crate::client::RecoveryPolicy), a failure after the
+    ///   first event is resumed from the partial output instead of surfaced
+    /// - with a [`MessageGuard`](crate::client::MessageGuard), each candidate's
+    ///   request is rewritten and the stream it returns is restored
+    /// - with request degradation, the stream is mapped back to the original request's
+    ///   shape (see [`DegradationReport::restore_stream`](crate::client::DegradationReport::restore_stream))
+    ///   and the rewrites are in `stats.degradation`
     pub async fn execute_stream_with_cancel_and_stats(
         self,
     ) -> Result<(
@@ -172,12 +200,35 @@ impl<'a> ChatRequestBuilder<'a> {
         CancelHandle,
         crate::client::types::CallStats,
     )> {
-        // Validate request against protocol capabilities
-        self.client.validate_request(&self)?;
+        match self.client.hedge.clone() {
+            Some(hedger) => self.execute_stream_hedged(&hedger).await,
+            None => self.execute_stream_unhedged().await,
+        }
+    }
+
+    pub(crate) async fn execute_stream_unhedged(
+        self,
+    ) -> Result<(
+        Pin<Box<dyn Stream<Item = Result<StreamingEvent>> + Send + 'static>>,
+        CancelHandle,
+        crate::client::types::CallStats,
+    )> {
+        // Validate request against protocol capabilities; with degradation enabled each
+        // candidate validates its own rewritten request instead.
+        if self.client.degrade.is_none() {
+            self.client.validate_request(&self)?;
+        }
 
         self.client.record_request();
 
         let base_client = self.client;
+        let prompt = self.prompt.clone();
+        let mut recovery = base_client.recovery.clone().map(|policy| {
+            (
+                policy,
+                crate::client::recovery::RecoveryRequest::from_builder(&self),
+            )
+        });
         let unified_req = self.into_unified_request();
 
         // Pre-build fallback clients (async), then run unified policy loops.
@@ -201,6 +252,28 @@ impl<'a> ChatRequestBuilder<'a> {
             let mut attempt: u32 = 0;
             let mut retry_count: u32 = 0;
 
+            let mut req = unified_req.clone();
+            if candidate_idx > 0 {
+                req.model = client.model_id.clone();
+            }
+            let report = client.degrade_for_candidate(&mut req);
+            // Guard the request as this candidate will see it, degradation text included.
+            let guarded = base_client
+                .message_guard
+                .as_ref()
+                .map(|guard| guard.protect(&mut req.messages));
+            let prepared = match policy.validate_capabilities(&req) {
+                Ok(()) => client.offload_files(&mut req).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = prepared {
+                if !has_fallback {
+                    return Err(e);
+                }
+                last_err = Some(e);
+                continue;
+            }
+
             loop {
                 // Pre-decision based on signals (skip known-bad candidates, e.g. breaker open).
                 let sig = client.signals().await;
@@ -214,11 +287,6 @@ impl<'a> ChatRequestBuilder<'a> {
                     break;
                 }
 
-                let mut req = unified_req.clone();
-                if candidate_idx > 0 {
-                    req.model = client.model_id.clone();
-                }
-
                 match client.execute_stream_once(&req).await {
                     Ok((mut event_stream, permit, mut stats)) => {
                         // Peek the first item. If it errors BEFORE emitting anything, allow retry/fallback.
@@ -241,6 +309,8 @@ impl<'a> ChatRequestBuilder<'a> {
                             None => {
                                 stats.retry_count = retry_count;
                                 stats.emitted_any = false;
+                                stats.prompt = prompt.clone();
+                                stats.degradation = report;
                                 base_client.record_success(&stats);
                                 let wrapped = ControlledStream::new(
                                     Box::pin(futures::stream::empty()),
@@ -251,8 +321,35 @@ impl<'a> ChatRequestBuilder<'a> {
                             }
                             Some(Ok(first_ev)) => {
                                 let first_ms = stats.duration_ms;
-                                let stream = futures::stream::once(async move { Ok(first_ev) })
-                                    .chain(event_stream);
+                                let mut stream: Pin<
+                                    Box<dyn Stream<Item = Result<StreamingEvent>> + Send + 'static>,
+                                > = Box::pin(
+                                    futures::stream::once(async move { Ok(first_ev) })
+                                        .chain(event_stream),
+                                );
+                                if let Some(report) = &report {
+                                    stream = report.restore_stream(stream);
+                                }
+                                if let Some(call) = &guarded {
+                                    stream = call.restore_stream(stream);
+                                }
+                                if let Some((policy, request)) = recovery.take() {
+                                    // The continuation reuses this candidate's transport, so it
+                                    // goes to the same endpoint as the failed stream. It sees the
+                                    // restored partial text and guards its own request.
+                                    let remaining = base_client
+                                        .fallbacks
+                                        .get(candidate_idx..)
+                                        .unwrap_or_default()
+                                        .to_vec();
+                                    stream = crate::client::recovery::recoverable(
+                                        stream,
+                                        client.detached(),
+                                        remaining,
+                                        request,
+                                        policy,
+                                    );
+                                }
                                 let wrapped = ControlledStream::new(
                                     Box::pin(stream.map_err(|e| {
                                         // If it's already a crate::Error (like Transport error), preserve it.
@@ -266,6 +363,8 @@ impl<'a> ChatRequestBuilder<'a> {
                                 stats.retry_count = retry_count;
                                 stats.first_event_ms = Some(first_ms);
                                 stats.emitted_any = true;
+                                stats.prompt = prompt.clone();
+                                stats.degradation = report;
 
                                 base_client.record_success(&stats);
                                 return Ok((Box::pin(wrapped), cancel_handle, stats));
@@ -368,7 +467,7 @@ impl<'a> ChatRequestBuilder<'a> {
 
         // For streaming requests, collect all events
         // Rebuild builder for streaming execution
-        let mut stream = {
+        let (mut stream, _cancel, stats) = {
             let builder = ChatRequestBuilder {
                 client,
                 messages: unified_req.messages.clone(),
@@ -379,8 +478,9 @@ impl<'a> ChatRequestBuilder<'a> {
                 tool_choice: unified_req.tool_choice.clone(),
                 model: Some(unified_req.model.clone()),
                 response_format: unified_req.response_format.clone(),
+                prompt: None,
             };
-            builder.execute_stream().await?
+            builder.execute_stream_with_cancel_and_stats().await?
         };
         let mut response = UnifiedResponse::default();
         let mut tool_asm = crate::utils::tool_call_assembler::ToolCallAssembler::new();
@@ -413,7 +513,7 @@ impl<'a> ChatRequestBuilder<'a> {
                 StreamingEvent::StreamEnd { .. } => {
                     break;
                 }
-                StreamingEvent::ThinkingDelta { .. } => {}
+                StreamingEvent::ThinkingDelta { .. } | StreamingEvent::StreamRecovered { .. } => {}
                 other => {
                     // Log unexpected events for debugging
                     tracing::warn!("Unexpected event in execute(): {:?}", other);
@@ -440,10 +540,28 @@ impl<'a> ChatRequestBuilder<'a> {
         }
 
         response.tool_calls = tool_asm.finalize();
+        // The stream was already mapped back to the original request's shape.
+        response.degradation = stats.degradation;
 
         Ok(response)
     }
 
+    /// Execute a non-streaming request and return the response with per-call stats.
+    ///
+    /// The `stream` flag is ignored; use [`Self::execute_stream_with_cancel_and_stats`]
+    /// for streaming.
+    pub async fn execute_with_stats(
+        self,
+    ) -> Result<(UnifiedResponse, crate::client::types::CallStats)> {
+        let client = self.client;
+        let prompt = self.prompt.clone();
+        let mut unified_req = self.into_unified_request();
+        unified_req.stream = false;
+        let (response, mut stats) = client.call_model_with_stats(unified_req).await?;
+        stats.prompt = prompt;
+        Ok((response, stats))
+    }
+
     fn into_unified_request(self) -> crate::protocol::UnifiedRequest {
         let model = self.model.unwrap_or_else(|| self.client.model_id.clone());
         crate::protocol::UnifiedRequest {
@@ -6,6 +6,7 @@ use crate::client::types::{CallStats, ClientMetrics};
 use crate::protocol::ProtocolLoader;
 use crate::protocol::ProtocolManifest;
 use crate::{Error, ErrorContext, Result};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
@@ -28,18 +29,58 @@ pub struct AiClient {
     pub(crate) inflight: Option<Arc<tokio::sync::Semaphore>>,
     pub(crate) max_inflight: Option<usize>,
     pub(crate) credential_override: Option<String>,
+    /// Base URL this client was built with in place of the manifest's
+    /// (`base_url_override`, `MOCK_HTTP_URL` or a fallback's own base URL).
+    pub(crate) base_url_override: Option<String>,
+    /// Per-fallback credential / base URL, keyed by `provider/model`.
+    pub(crate) fallback_endpoints: Arc<HashMap<String, FallbackEndpoint>>,
     pub(crate) attempt_timeout: Option<std::time::Duration>,
+    pub(crate) degrade: Option<crate::client::degrade::DegradeOptions>,
+    pub(crate) hedge: Option<Arc<crate::client::hedge::Hedger>>,
+    pub(crate) recovery: Option<crate::client::recovery::RecoveryPolicy>,
+    pub(crate) message_guard: Option<Arc<dyn crate::client::guard::MessageGuard>>,
+    /// Files API client and size threshold for offloading inline attachments.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+    pub(crate) files: Option<(Arc<crate::files::FilesClient>, usize)>,
     pub(crate) total_requests: AtomicU64,
     pub(crate) successful_requests: AtomicU64,
     pub(crate) total_tokens: AtomicU64,
+    pub(crate) hedged_requests: AtomicU64,
+    pub(crate) hedge_wins: AtomicU64,
+    pub(crate) hedge_cancelled_streams: AtomicU64,
+    pub(crate) hedge_loser_tokens: AtomicU64,
+}
+
+/// Credential and base URL for one fallback model (see
+/// [`AiClientBuilder::fallback_credential`](crate::client::AiClientBuilder::fallback_credential)).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FallbackEndpoint {
+    pub(crate) credential: Option<String>,
+    pub(crate) base_url: Option<String>,
 }
 
 /// Unified response format.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct UnifiedResponse {
     pub content: String,
     pub tool_calls: Vec<crate::types::tool::ToolCall>,
     pub usage: Option<serde_json::Value>,
+    /// Images the model returned inline (non-streaming responses), see
+    /// [`ContentBlock::inline_images`](crate::types::message::ContentBlock::inline_images).
+    pub images: Vec<crate::types::message::ContentBlock>,
+    /// Rewrites applied because the serving model lacked a capability the request
+    /// used (see [`AiClientBuilder::degrade_requests`](crate::client::AiClientBuilder::degrade_requests)).
+    pub degradation: Option<crate::client::degrade::DegradationReport>,
+}
+
+impl UnifiedResponse {
+    /// Response carrying only assistant text.
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
 }
 
 impl AiClient {
@@ -51,6 +92,10 @@ impl AiClient {
             total_requests: self.total_requests.load(Ordering::Relaxed),
             successful_requests: self.successful_requests.load(Ordering::Relaxed),
             total_tokens: self.total_tokens.load(Ordering::Relaxed),
+            hedged_requests: self.hedged_requests.load(Ordering::Relaxed),
+            hedge_wins: self.hedge_wins.load(Ordering::Relaxed),
+            hedge_cancelled_streams: self.hedge_cancelled_streams.load(Ordering::Relaxed),
+            hedge_loser_tokens: self.hedge_loser_tokens.load(Ordering::Relaxed),
         }
     }
 
@@ -61,6 +106,15 @@ impl AiClient {
         }
     }
 
+    /// Count a hedge loser that was stopped rather than failing on its own.
+    pub(crate) fn record_hedge_loser(&self, cancelled_stream: bool, prompt_tokens: u64) {
+        if cancelled_stream {
+            self.hedge_cancelled_streams.fetch_add(1, Ordering::Relaxed);
+        }
+        self.hedge_loser_tokens
+            .fetch_add(prompt_tokens, Ordering::Relaxed);
+    }
+
     pub(crate) fn record_request(&self) {
         self.total_requests.fetch_add(1, Ordering::Relaxed);
     }
@@ -74,6 +128,11 @@ impl AiClient {
         })
     }
 
+    /// Current delay before a streaming call is hedged, when hedging is enabled.
+    pub fn hedge_delay(&self) -> Option<std::time::Duration> {
+        self.hedge.as_ref().map(|h| h.threshold())
+    }
+
     /// Snapshot current runtime signals (facts only) for application-layer orchestration.
     pub async fn signals(&self) -> crate::client::signals::SignalsSnapshot {
         let inflight = self.inflight.as_ref().and_then(|sem| {
@@ -102,6 +161,11 @@ impl AiClient {
 
     /// Create a new client instance for another model, reusing loader + shared runtime knobs
     /// (feedback, inflight) for consistent behavior.
+    ///
+    /// The credential is the one registered for `model` on the builder; otherwise this
+    /// client's explicit credential is reused only for the same provider, so a key is
+    /// never sent to a different provider. A clone of this client's own model also
+    /// keeps its base URL override.
     pub(crate) async fn with_model(&self, model: &str) -> Result<Self> {
         // model is in form "provider/model-id"
         let parts: Vec<&str> = model.split('/').collect();
@@ -113,14 +177,32 @@ impl AiClient {
         let manifest = self.loader.load_model(model).await?;
         validation::validate_manifest(&manifest, self.strict_streaming)?;
 
-        let transport = Arc::new(
-            crate::transport::HttpTransport::new_with_base_url_and_credential(
-                &manifest,
-                &model_id,
-                None,
-                self.credential_override.as_deref(),
-            )?,
-        );
+        let endpoint = self.fallback_endpoints.get(model);
+        let manifest_id_matches = manifest.id == self.manifest.id;
+        let same_model = manifest_id_matches && model_id == self.model_id;
+        let credential_override = match endpoint.and_then(|e| e.credential.clone()) {
+            Some(credential) => Some(credential),
+            None if manifest_id_matches => self.credential_override.clone(),
+            None => None,
+        };
+        let base_url_override = match endpoint.and_then(|e| e.base_url.clone()) {
+            Some(base_url) => Some(base_url),
+            None if same_model => self.base_url_override.clone(),
+            None => None,
+        };
+        let mut transport = crate::transport::HttpTransport::new_with_base_url_and_credential(
+            &manifest,
+            &model_id,
+            base_url_override.as_deref(),
+            credential_override.as_deref(),
+        )?;
+        if let Some(cassette) = self.transport.cassette() {
+            transport = transport.with_cassette(cassette);
+        }
+        if let Some(responder) = self.transport.responder() {
+            transport = transport.with_responder(responder);
+        }
+        let transport = Arc::new(transport);
         let pipeline = Arc::new(crate::pipeline::Pipeline::from_manifest(&manifest)?);
 
         Ok(AiClient {
@@ -134,14 +216,68 @@ impl AiClient {
             feedback: self.feedback.clone(),
             inflight: self.inflight.clone(),
             max_inflight: self.max_inflight,
-            credential_override: self.credential_override.clone(),
+            credential_override,
+            base_url_override,
+            fallback_endpoints: self.fallback_endpoints.clone(),
             attempt_timeout: self.attempt_timeout,
+            degrade: self.degrade.clone(),
+            // Fallback and hedge clients never hedge themselves; recovery is driven
+            // by the client that owns the original stream.
+            hedge: None,
+            recovery: None,
+            // Each candidate guards its own (possibly degraded) request.
+            message_guard: self.message_guard.clone(),
+            // Uploaded file ids are only valid for the provider account that owns them.
+            #[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+            files: self
+                .files
+                .clone()
+                .filter(|_| manifest_id_matches && endpoint.is_none()),
             total_requests: AtomicU64::new(0),
             successful_requests: AtomicU64::new(0),
             total_tokens: AtomicU64::new(0),
+            hedged_requests: AtomicU64::new(0),
+            hedge_wins: AtomicU64::new(0),
+            hedge_cancelled_streams: AtomicU64::new(0),
+            hedge_loser_tokens: AtomicU64::new(0),
         })
     }
 
+    /// Owned copy of this client for driving work after the call returns: it shares
+    /// the transport (base URL, credential, cassette, responder), pipeline and message
+    /// guard, but never hedges or recovers itself and starts with fresh counters.
+    pub(crate) fn detached(&self) -> Self {
+        AiClient {
+            manifest: self.manifest.clone(),
+            transport: self.transport.clone(),
+            pipeline: self.pipeline.clone(),
+            loader: self.loader.clone(),
+            fallbacks: Vec::new(),
+            model_id: self.model_id.clone(),
+            strict_streaming: self.strict_streaming,
+            feedback: self.feedback.clone(),
+            inflight: self.inflight.clone(),
+            max_inflight: self.max_inflight,
+            credential_override: self.credential_override.clone(),
+            base_url_override: self.base_url_override.clone(),
+            fallback_endpoints: self.fallback_endpoints.clone(),
+            attempt_timeout: self.attempt_timeout,
+            degrade: self.degrade.clone(),
+            hedge: None,
+            recovery: None,
+            message_guard: self.message_guard.clone(),
+            #[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+            files: self.files.clone(),
+            total_requests: AtomicU64::new(0),
+            successful_requests: AtomicU64::new(0),
+            total_tokens: AtomicU64::new(0),
+            hedged_requests: AtomicU64::new(0),
+            hedge_wins: AtomicU64::new(0),
+            hedge_cancelled_streams: AtomicU64::new(0),
+            hedge_loser_tokens: AtomicU64::new(0),
+        }
+    }
+
     /// Create a chat request builder.
     pub fn chat(&self) -> crate::client::chat::ChatRequestBuilder<'_> {
         crate::client::chat::ChatRequestBuilder::new(self)
@@ -260,6 +396,13 @@ impl AiClient {
     pub async fn call_model_with_stats(
         &self,
         request: crate::protocol::UnifiedRequest,
+    ) -> Result<(UnifiedResponse, CallStats)> {
+        self.call_candidates(request).await
+    }
+
+    async fn call_candidates(
+        &self,
+        request: crate::protocol::UnifiedRequest,
     ) -> Result<(UnifiedResponse, CallStats)> {
         self.record_request();
 
@@ -292,8 +435,23 @@ impl AiClient {
             let has_fallback = candidate_idx + 1 < (1 + fallback_clients.len());
             let policy = crate::client::policy::PolicyEngine::new(&client.manifest);
 
-            // 1. Validation check
-            if let Err(e) = policy.validate_capabilities(&request) {
+            let mut req = request.clone();
+            if candidate_idx > 0 {
+                req.model = client.model_id.clone();
+            }
+            let report = client.degrade_for_candidate(&mut req);
+            // Guard the request as this candidate will see it, degradation text included.
+            let guarded = self
+                .message_guard
+                .as_ref()
+                .map(|guard| guard.protect(&mut req.messages));
+
+            // 1. Validation check, then swap large inline attachments for uploads
+            let prepared = match policy.validate_capabilities(&req) {
+                Ok(()) => client.offload_files(&mut req).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = prepared {
                 if has_fallback {
                     last_err = Some(e);
                     continue; // Fallback to next candidate
@@ -314,16 +472,18 @@ impl AiClient {
                 continue;
             }
 
-            let mut req = request.clone();
-            if candidate_idx > 0 {
-                req.model = client.model_id.clone();
-            }
-
             // 3. Execution with Retry Policy
             // The `execute_with_retry` helper now encapsulates the retry loop,
             // paving the way for `RetryOperator` migration.
             match client.execute_with_retry(&req, &policy, has_fallback).await {
-                Ok((resp, stats)) => {
+                Ok((mut resp, mut stats)) => {
+                    if let Some(report) = report {
+                        report.restore(&mut resp);
+                        stats.degradation = Some(report);
+                    }
+                    if let Some(call) = guarded {
+                        call.restore_response(&mut resp);
+                    }
                     client.record_success(&stats);
                     return Ok((resp, stats));
                 }
@@ -348,6 +508,47 @@ impl AiClient {
         }))
     }
 
+    /// Rewrite `req` to fit this client's manifest when degradation is enabled.
+    /// Returns the report only if something was rewritten.
+    pub(crate) fn degrade_for_candidate(
+        &self,
+        req: &mut crate::protocol::UnifiedRequest,
+    ) -> Option<crate::client::degrade::DegradationReport> {
+        let options = self.degrade.as_ref()?;
+        let target = crate::client::degrade::TargetCapabilities::from_manifest(&self.manifest);
+        let (rewritten, report) = crate::client::degrade::degrade_request(req, &target, options);
+        if report.is_empty() {
+            return None;
+        }
+        tracing::info!(model = %report.model, "request degraded: {}", report);
+        *req = rewritten;
+        Some(report)
+    }
+
+    /// Replace inline base64 documents / images above the configured size with
+    /// Files API references (see [`AiClientBuilder::files`](crate::client::AiClientBuilder::files)).
+    #[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+    pub(crate) async fn offload_files(
+        &self,
+        req: &mut crate::protocol::UnifiedRequest,
+    ) -> Result<()> {
+        if let Some((files, min_bytes)) = &self.files {
+            let replaced = files.offload(&mut req.messages, *min_bytes).await?;
+            if replaced > 0 {
+                tracing::debug!(replaced, "offloaded inline attachments to the Files API");
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(all(not(target_arch = "wasm32"), feature = "files")))]
+    pub(crate) async fn offload_files(
+        &self,
+        _req: &mut crate::protocol::UnifiedRequest,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     /// Internal helper to execute with retry policy.
     /// In future versions, this Logic moves entirely into `RetryOperator`.
     async fn execute_with_retry(
@@ -0,0 +1,1054 @@
+//! 请求降级：按目标模型的能力改写请求（文本工具协议、结构化输出、图片、文档、system 角色），并在响应中记录每一次改写。
+//!
+//! Capability-driven request degradation.
+//!
+//! [`PolicyEngine::validate_capabilities`](crate::client::PolicyEngine::validate_capabilities)
+//! rejects requests a model cannot serve. With
+//! [`AiClientBuilder::degrade_requests`](crate::client::AiClientBuilder::degrade_requests)
+//! each candidate in the fallback chain instead receives a copy of the request rewritten
+//! to fit its manifest, and the response carries a [`DegradationReport`].
+//!
+//! | Missing capability | Rewrite |
+//! |--------------------|---------|
+//! | `tools` (or `mcp_client` for MCP tools) | Text-tool protocol prompt; tool history as text; `<tool_call>` replies parsed back into `tool_calls` |
+//! | `structured_output` | Schema forced as a single tool call when tools are native, otherwise JSON instructions |
+//! | vision | Images replaced by a short description or dropped ([`ImageFallback`]) |
+//! | audio | Audio replaced by a short description |
+//! | document understanding | Text extracted from plain-text and (with `pdf_text`) PDF documents, otherwise described |
+//!
+//! Streams are mapped back too ([`DegradationReport::restore_stream`]): text-tool
+//! calls arrive as `ToolCallStarted` / `PartialToolCall` events and a forced schema
+//! tool call as content deltas. Text from the first `<` on is held back until the
+//! stream ends so a tool-call block can be parsed as a whole.
+//!
+//! ```rust,no_run
+//! use ai_lib_core::client::AiClientBuilder;
+//! use ai_lib_core::types::message::Message;
+//!
+//! # async fn demo() -> ai_lib_core::Result<()> {
+//! let client = AiClientBuilder::new()
+//!     .with_fallbacks(vec!["deepseek/deepseek-chat".into()])
+//!     .degrade_requests(true)
+//!     .build("openai/gpt-4o")
+//!     .await?;
+//! let response = client
+//!     .chat()
+//!     .messages(vec![Message::user("What is in this picture?")])
+//!     .execute()
+//!     .await?;
+//! if let Some(report) = &response.degradation {
+//!     eprintln!("request degraded for {}: {}", report.model, report);
+//! }
+//! # Ok(()) }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+
+use base64::Engine;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+
+use crate::client::core::UnifiedResponse;
+use crate::protocol::{ProtocolManifest, UnifiedRequest};
+use crate::structured::JsonMode;
+use crate::types::events::StreamingEvent;
+use crate::types::message::{
+    AudioSource, ContentBlock, DocumentSource, ImageSource, Message, MessageContent, MessageRole,
+};
+use crate::types::text_tool::{StandardTextToolParser, TextToolConfig, TextToolParser};
+use crate::types::tool::{FunctionDefinition, ToolDefinition, ToolResult};
+use crate::Result;
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<StreamingEvent>> + Send + 'static>>;
+
+/// What to do with images sent to a model without vision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageFallback {
+    /// Replace each image with a bracketed note naming its type and source.
+    #[default]
+    Describe,
+    /// Remove images without a trace in the prompt.
+    Drop,
+}
+
+/// Knobs for [`degrade_request`].
+#[derive(Debug, Clone)]
+pub struct DegradeOptions {
+    pub images: ImageFallback,
+    /// Characters of extracted text kept per document.
+    pub max_document_chars: usize,
+}
+
+impl Default for DegradeOptions {
+    fn default() -> Self {
+        Self {
+            images: ImageFallback::Describe,
+            max_document_chars: 100_000,
+        }
+    }
+}
+
+impl DegradeOptions {
+    pub fn with_images(mut self, images: ImageFallback) -> Self {
+        self.images = images;
+        self
+    }
+
+    pub fn with_max_document_chars(mut self, max: usize) -> Self {
+        self.max_document_chars = max;
+        self
+    }
+}
+
+/// What a target model accepts, as read from its manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetCapabilities {
+    pub tools: bool,
+    pub mcp_client: bool,
+    pub structured_output: bool,
+    pub vision: bool,
+    pub audio: bool,
+    pub documents: bool,
+    /// Manifest `tool_calling` block, used to configure the text-tool parser.
+    pub tool_calling: Option<serde_json::Value>,
+}
+
+impl TargetCapabilities {
+    /// Capabilities declared by `manifest`.
+    ///
+    /// Document support is `multimodal.input.vision.document_understanding` when the
+    /// manifest declares it and follows vision otherwise.
+    pub fn from_manifest(manifest: &ProtocolManifest) -> Self {
+        let caps = &manifest.capabilities;
+        let vision = caps.vision || (caps.multimodal && !caps.audio);
+        let documents = manifest
+            .extra
+            .get("multimodal")
+            .and_then(|m| m.pointer("/input/vision/document_understanding"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(vision);
+        Self {
+            tools: caps.tools,
+            mcp_client: caps.mcp_client,
+            structured_output: caps.structured_output,
+            vision,
+            audio: caps.audio || (caps.multimodal && !caps.vision),
+            documents,
+            tool_calling: manifest.tool_calling().cloned(),
+        }
+    }
+
+    /// A plain chat model: text in, text out.
+    pub fn text_only() -> Self {
+        Self {
+            tools: false,
+            mcp_client: false,
+            structured_output: false,
+            vision: false,
+            audio: false,
+            documents: false,
+            tool_calling: None,
+        }
+    }
+
+    fn text_tool_parser(&self) -> StandardTextToolParser {
+        match &self.tool_calling {
+            Some(tool_calling) => StandardTextToolParser::from_manifest_tool_calling(tool_calling),
+            None => StandardTextToolParser::new(TextToolConfig::default()),
+        }
+    }
+}
+
+/// One change [`degrade_request`] made to a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Rewrite {
+    /// Native tools replaced by the text-tool protocol; `history_messages` tool calls /
+    /// results already in the conversation were rendered as text.
+    ToolsAsText {
+        tools: Vec<String>,
+        history_messages: usize,
+    },
+    /// `response_format` replaced by a forced call of `tool`.
+    ResponseFormatAsTool {
+        tool: String,
+    },
+    /// `response_format` replaced by JSON instructions in the system prompt.
+    ResponseFormatAsInstructions,
+    ImagesDescribed {
+        count: usize,
+    },
+    ImagesDropped {
+        count: usize,
+    },
+    AudioDescribed {
+        count: usize,
+    },
+    /// Documents replaced by their extracted text.
+    DocumentsExtracted {
+        count: usize,
+    },
+    /// Documents without extractable text replaced by a description.
+    DocumentsDescribed {
+        count: usize,
+    },
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rewrite::ToolsAsText {
+                tools,
+                history_messages,
+            } => {
+                write!(f, "tools [{}] sent as text-tool protocol", tools.join(", "))?;
+                if *history_messages > 0 {
+                    write!(f, " ({history_messages} history messages as text)")?;
+                }
+                Ok(())
+            }
+            Rewrite::ResponseFormatAsTool { tool } => {
+                write!(f, "response_format sent as forced tool call `{tool}`")
+            }
+            Rewrite::ResponseFormatAsInstructions => {
+                write!(f, "response_format sent as prompt instructions")
+            }
+            Rewrite::ImagesDescribed { count } => write!(f, "{count} image(s) described"),
+            Rewrite::ImagesDropped { count } => write!(f, "{count} image(s) dropped"),
+            Rewrite::AudioDescribed { count } => write!(f, "{count} audio clip(s) described"),
+            Rewrite::DocumentsExtracted { count } => {
+                write!(f, "{count} document(s) sent as extracted text")
+            }
+            Rewrite::DocumentsDescribed { count } => write!(f, "{count} document(s) described"),
+        }
+    }
+}
+
+/// Every rewrite applied to the request sent to `model`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DegradationReport {
+    pub model: String,
+    pub rewrites: Vec<Rewrite>,
+    /// Text-tool parser configuration of the target, kept for [`Self::restore`].
+    #[serde(skip)]
+    tool_calling: Option<serde_json::Value>,
+}
+
+impl DegradationReport {
+    pub fn is_empty(&self) -> bool {
+        self.rewrites.is_empty()
+    }
+
+    /// Map a response to the degraded request back to what the original request asked
+    /// for: `<tool_call>` text becomes `tool_calls`, a forced schema tool call becomes
+    /// JSON `content`. Also attaches the report as `response.degradation`.
+    ///
+    /// Applied automatically to chat responses; see [`Self::restore_stream`] for streams.
+    pub fn restore(&self, response: &mut UnifiedResponse) {
+        if self.is_empty() {
+            return;
+        }
+        for rewrite in &self.rewrites {
+            match rewrite {
+                Rewrite::ToolsAsText { .. } => {
+                    let (text, calls) = self.text_tool_parser().parse(&response.content);
+                    if !calls.is_empty() {
+                        response.content = text;
+                        response.tool_calls.extend(calls);
+                    }
+                }
+                Rewrite::ResponseFormatAsTool { tool } => {
+                    if let Some(pos) = response.tool_calls.iter().position(|c| &c.name == tool) {
+                        let call = response.tool_calls.remove(pos);
+                        response.content = call.arguments.to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+        response.degradation = Some(self.clone());
+    }
+
+    /// Stream counterpart of [`Self::restore`], applied automatically to chat streams:
+    /// `<tool_call>` text becomes `ToolCallStarted` / `PartialToolCall` events and the
+    /// forced schema tool call becomes content deltas.
+    ///
+    /// With tools sent as text, content from the first `<` on is held back (with the
+    /// events after it) until the stream ends or fails, then parsed as a whole.
+    pub fn restore_stream(&self, stream: EventStream) -> EventStream {
+        let tools_as_text = self
+            .rewrites
+            .iter()
+            .any(|r| matches!(r, Rewrite::ToolsAsText { .. }));
+        let schema_tool = self.rewrites.iter().find_map(|r| match r {
+            Rewrite::ResponseFormatAsTool { tool } => Some(tool.clone()),
+            _ => None,
+        });
+        if !tools_as_text && schema_tool.is_none() {
+            return stream;
+        }
+        let state = StreamRestore {
+            inner: stream,
+            parser: tools_as_text.then(|| self.text_tool_parser()),
+            schema_tool,
+            schema_call: None,
+            held: None,
+            queued: VecDeque::new(),
+            out: VecDeque::new(),
+            done: false,
+        };
+        Box::pin(futures::stream::unfold(state, |mut st| async move {
+            loop {
+                if let Some(item) = st.out.pop_front() {
+                    return Some((item, st));
+                }
+                if st.done {
+                    return None;
+                }
+                match st.inner.next().await {
+                    Some(Ok(ev)) => st.on_event(ev),
+                    Some(Err(e)) => {
+                        st.flush();
+                        st.out.push_back(Err(e));
+                    }
+                    None => {
+                        st.flush();
+                        st.done = true;
+                    }
+                }
+            }
+        }))
+    }
+
+    fn text_tool_parser(&self) -> StandardTextToolParser {
+        TargetCapabilities {
+            tool_calling: self.tool_calling.clone(),
+            ..TargetCapabilities::text_only()
+        }
+        .text_tool_parser()
+    }
+}
+
+/// State of [`DegradationReport::restore_stream`].
+struct StreamRestore {
+    inner: EventStream,
+    /// Set when tools were sent as text.
+    parser: Option<StandardTextToolParser>,
+    schema_tool: Option<String>,
+    /// Id of the forced schema tool call once it started.
+    schema_call: Option<String>,
+    /// Content from the first `<` on, not yet parsed.
+    held: Option<String>,
+    /// Events that arrived after `held` started.
+    queued: VecDeque<Result<StreamingEvent>>,
+    out: VecDeque<Result<StreamingEvent>>,
+    done: bool,
+}
+
+impl StreamRestore {
+    fn on_event(&mut self, ev: StreamingEvent) {
+        match ev {
+            StreamingEvent::PartialContentDelta {
+                content,
+                sequence_id,
+            } if self.parser.is_some() => match (&mut self.held, content.find('<')) {
+                (Some(held), _) => held.push_str(&content),
+                (None, Some(i)) => {
+                    if i > 0 {
+                        self.out.push_back(Ok(StreamingEvent::PartialContentDelta {
+                            content: content[..i].to_string(),
+                            sequence_id,
+                        }));
+                    }
+                    self.held = Some(content[i..].to_string());
+                }
+                (None, None) => self.out.push_back(Ok(StreamingEvent::PartialContentDelta {
+                    content,
+                    sequence_id,
+                })),
+            },
+            StreamingEvent::ToolCallStarted {
+                tool_call_id,
+                tool_name,
+                index,
+            } => {
+                if self.schema_tool.as_ref() == Some(&tool_name) {
+                    self.schema_call = Some(tool_call_id);
+                } else {
+                    self.emit(StreamingEvent::ToolCallStarted {
+                        tool_call_id,
+                        tool_name,
+                        index,
+                    });
+                }
+            }
+            StreamingEvent::PartialToolCall {
+                tool_call_id,
+                arguments,
+                ..
+            } if self.schema_call.as_ref() == Some(&tool_call_id) => {
+                self.emit(StreamingEvent::PartialContentDelta {
+                    content: arguments,
+                    sequence_id: None,
+                });
+            }
+            StreamingEvent::ToolCallEnded { tool_call_id, .. }
+                if self.schema_call.as_ref() == Some(&tool_call_id) => {}
+            ev @ StreamingEvent::StreamEnd { .. } => {
+                self.flush();
+                self.out.push_back(Ok(ev));
+            }
+            ev => self.emit(ev),
+        }
+    }
+
+    /// Pass `ev` on, behind any held-back content.
+    fn emit(&mut self, ev: StreamingEvent) {
+        if self.held.is_some() {
+            self.queued.push_back(Ok(ev));
+        } else {
+            self.out.push_back(Ok(ev));
+        }
+    }
+
+    /// Parse held-back content into text and tool-call events, then release the
+    /// events queued behind it.
+    fn flush(&mut self) {
+        let (Some(held), Some(parser)) = (self.held.take(), &self.parser) else {
+            return;
+        };
+        let (text, calls) = parser.parse(&held);
+        let text = if calls.is_empty() { held } else { text };
+        if !text.is_empty() {
+            self.out.push_back(Ok(StreamingEvent::PartialContentDelta {
+                content: text,
+                sequence_id: None,
+            }));
+        }
+        for (i, call) in calls.into_iter().enumerate() {
+            self.out.push_back(Ok(StreamingEvent::ToolCallStarted {
+                tool_call_id: call.id.clone(),
+                tool_name: call.name,
+                index: Some(i as u32),
+            }));
+            self.out.push_back(Ok(StreamingEvent::PartialToolCall {
+                tool_call_id: call.id,
+                arguments: call.arguments.to_string(),
+                index: Some(i as u32),
+                is_complete: Some(true),
+            }));
+        }
+        self.out.extend(self.queued.drain(..));
+    }
+}
+
+impl fmt::Display for DegradationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, rewrite) in self.rewrites.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{rewrite}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Rewrite `request` so `target` can serve it. The returned report is empty when the
+/// request already fits.
+pub fn degrade_request(
+    request: &UnifiedRequest,
+    target: &TargetCapabilities,
+    options: &DegradeOptions,
+) -> (UnifiedRequest, DegradationReport) {
+    let mut req = request.clone();
+    let mut report = DegradationReport {
+        model: req.model.clone(),
+        rewrites: Vec::new(),
+        tool_calling: target.tool_calling.clone(),
+    };
+
+    degrade_attachments(&mut req.messages, target, options, &mut report);
+
+    let tools = req.tools.take().unwrap_or_default();
+    let needs_mcp = tools
+        .iter()
+        .any(|t| t.tool_type.eq_ignore_ascii_case("mcp") || t.function.name.starts_with("mcp__"));
+    if !tools.is_empty() && (!target.tools || (needs_mcp && !target.mcp_client)) {
+        let parser = target.text_tool_parser();
+        let history_messages = tool_history_as_text(&mut req.messages, &parser);
+        let mut instructions = parser.prompt_instructions(&tools);
+        let schemas: serde_json::Map<String, serde_json::Value> = tools
+            .iter()
+            .filter_map(|t| Some((t.function.name.clone(), t.function.parameters.clone()?)))
+            .collect();
+        if !schemas.is_empty() {
+            instructions.push_str("\n\nTool arguments (JSON Schema):\n");
+            instructions.push_str(&serde_json::Value::Object(schemas).to_string());
+        }
+        append_system(&mut req.messages, &instructions);
+        req.tool_choice = None;
+        report.rewrites.push(Rewrite::ToolsAsText {
+            tools: tools.iter().map(|t| t.function.name.clone()).collect(),
+            history_messages,
+        });
+    } else if !tools.is_empty() {
+        req.tools = Some(tools);
+    }
+
+    if let Some(format) = req.response_format.take() {
+        if target.structured_output {
+            req.response_format = Some(format);
+        } else if format.mode != JsonMode::Off {
+            match format.schema {
+                Some(schema)
+                    if format.mode == JsonMode::JsonSchema
+                        && target.tools
+                        && !request.tools.as_ref().is_some_and(|t| !t.is_empty()) =>
+                {
+                    let name = format.schema_name.clone();
+                    req.tools = Some(vec![ToolDefinition {
+                        tool_type: "function".to_string(),
+                        function: FunctionDefinition {
+                            name: name.clone(),
+                            description: Some(
+                                "Return the final answer as the arguments of this function."
+                                    .to_string(),
+                            ),
+                            parameters: Some(schema),
+                        },
+                    }]);
+                    req.tool_choice = Some(serde_json::json!({
+                        "type": "function",
+                        "function": { "name": name },
+                    }));
+                    report
+                        .rewrites
+                        .push(Rewrite::ResponseFormatAsTool { tool: name });
+                }
+                schema => {
+                    let mut instructions = String::from(
+                        "Respond with a single JSON object and nothing else: no prose, no code fences.",
+                    );
+                    if let Some(schema) = schema {
+                        instructions.push_str(" The object must conform to this JSON Schema:\n");
+                        instructions.push_str(&schema.to_string());
+                    }
+                    append_system(&mut req.messages, &instructions);
+                    report.rewrites.push(Rewrite::ResponseFormatAsInstructions);
+                }
+            }
+        }
+    }
+
+    (req, report)
+}
+
+fn degrade_attachments(
+    messages: &mut [Message],
+    target: &TargetCapabilities,
+    options: &DegradeOptions,
+    report: &mut DegradationReport,
+) {
+    let (mut described, mut dropped, mut audio, mut extracted, mut documents) = (0, 0, 0, 0, 0);
+    for message in messages.iter_mut() {
+        let MessageContent::Blocks(blocks) = &mut message.content else {
+            continue;
+        };
+        let before = (described, dropped, audio, extracted, documents);
+        let mut kept = Vec::with_capacity(blocks.len());
+        for block in blocks.drain(..) {
+            match block {
+                ContentBlock::Image { source } if !target.vision => match options.images {
+                    ImageFallback::Describe => {
+                        described += 1;
+                        kept.push(ContentBlock::text(describe_image(&source)));
+                    }
+                    ImageFallback::Drop => dropped += 1,
+                },
+                ContentBlock::Audio { source } if !target.audio => {
+                    audio += 1;
+                    kept.push(ContentBlock::text(describe_audio(&source)));
+                }
+                ContentBlock::Document { source } if !target.documents => {
+                    match document_text(&source, options.max_document_chars) {
+                        Some(text) => {
+                            extracted += 1;
+                            kept.push(ContentBlock::text(format!(
+                                "[Document: {}]\n{}",
+                                document_name(&source),
+                                text
+                            )));
+                        }
+                        None => {
+                            documents += 1;
+                            kept.push(ContentBlock::text(format!(
+                                "[Document omitted ({}): this model cannot read attached documents]",
+                                document_name(&source)
+                            )));
+                        }
+                    }
+                }
+                other => kept.push(other),
+            }
+        }
+        *blocks = kept;
+        if (described, dropped, audio, extracted, documents) != before {
+            collapse_text_blocks(&mut message.content);
+        }
+    }
+
+    let counts = [
+        (described, Rewrite::ImagesDescribed { count: described }),
+        (dropped, Rewrite::ImagesDropped { count: dropped }),
+        (audio, Rewrite::AudioDescribed { count: audio }),
+        (extracted, Rewrite::DocumentsExtracted { count: extracted }),
+        (documents, Rewrite::DocumentsDescribed { count: documents }),
+    ];
+    report.rewrites.extend(
+        counts
+            .into_iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(_, rewrite)| rewrite),
+    );
+}
+
+fn describe_image(source: &ImageSource) -> String {
+    let kind = source.media_type.as_deref().unwrap_or("image");
+    match source.source_type.as_str() {
+        "url" => format!(
+            "[Image omitted ({kind}, {}): this model cannot view images]",
+            source.data
+        ),
+        _ => format!("[Image omitted ({kind}): this model cannot view images]"),
+    }
+}
+
+fn describe_audio(source: &AudioSource) -> String {
+    let kind = source.media_type.as_deref().unwrap_or("audio");
+    format!("[Audio omitted ({kind}): this model cannot listen to audio]")
+}
+
+fn document_name(source: &DocumentSource) -> &str {
+    source
+        .filename
+        .as_deref()
+        .or(source.mime_type.as_deref())
+        .unwrap_or("document")
+}
+
+/// Text of an inline document: UTF-8 for textual MIME types, extracted text for PDFs
+/// when the `pdf_text` feature is enabled.
+fn document_text(source: &DocumentSource, max_chars: usize) -> Option<String> {
+    if source.source_type != "base64" {
+        return None;
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(source.data.trim())
+        .ok()?;
+    let mime = source.mime_type.as_deref().unwrap_or_default();
+    let text = if mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/json" | "application/xml" | "application/x-yaml" | "application/yaml"
+        ) {
+        String::from_utf8(bytes).ok()?
+    } else if mime == "application/pdf" || bytes.starts_with(b"%PDF") {
+        pdf_text(&bytes)?
+    } else {
+        return None;
+    };
+    if text.trim().is_empty() {
+        return None;
+    }
+    if text.chars().count() > max_chars {
+        let mut truncated: String = text.chars().take(max_chars).collect();
+        truncated.push_str("\n[truncated]");
+        Some(truncated)
+    } else {
+        Some(text)
+    }
+}
+
+#[cfg(feature = "pdf_text")]
+fn pdf_text(bytes: &[u8]) -> Option<String> {
+    crate::utils::pdf_text::extract_pdf_text(bytes)
+}
+
+#[cfg(not(feature = "pdf_text"))]
+fn pdf_text(_bytes: &[u8]) -> Option<String> {
+    None
+}
+
+/// Text-only block lists become plain string content, which every provider accepts.
+fn collapse_text_blocks(content: &mut MessageContent) {
+    let MessageContent::Blocks(blocks) = content else {
+        return;
+    };
+    let texts: Option<Vec<&str>> = blocks
+        .iter()
+        .map(|b| match b {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    if let Some(texts) = texts {
+        *content = MessageContent::Text(texts.join("\n\n"));
+    }
+}
+
+fn content_text(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text(text) => text.clone(),
+        MessageContent::Blocks(blocks) => blocks
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    }
+}
+
+/// Render native tool calls / results in the history with the text-tool protocol.
+/// Returns the number of messages changed.
+fn tool_history_as_text(messages: &mut [Message], parser: &impl TextToolParser) -> usize {
+    let mut changed = 0;
+    for message in messages.iter_mut() {
+        if message.role == MessageRole::Tool {
+            let result = ToolResult {
+                tool_use_id: message.tool_call_id.take().unwrap_or_default(),
+                content: serde_json::Value::String(content_text(&message.content)),
+                is_error: false,
+            };
+            *message = Message::user(parser.format_results(&[result]));
+            changed += 1;
+            continue;
+        }
+        let MessageContent::Blocks(blocks) = &mut message.content else {
+            continue;
+        };
+        if !blocks.iter().any(|b| {
+            matches!(
+                b,
+                ContentBlock::ToolUse { .. } | ContentBlock::ToolResult { .. }
+            )
+        }) {
+            continue;
+        }
+        for block in blocks.iter_mut() {
+            let text = match block {
+                ContentBlock::ToolUse { name, input, .. } => format!(
+                    "<tool_call>\n{}\n</tool_call>",
+                    serde_json::json!({ "name": name, "arguments": input })
+                ),
+                ContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                } => parser.format_results(&[ToolResult {
+                    tool_use_id: tool_use_id.clone(),
+                    content: content.clone(),
+                    is_error: false,
+                }]),
+                _ => continue,
+            };
+            *block = ContentBlock::text(text);
+        }
+        collapse_text_blocks(&mut message.content);
+        changed += 1;
+    }
+    changed
+}
+
+/// Append `text` to the leading system message, creating one if needed.
+fn append_system(messages: &mut Vec<Message>, text: &str) {
+    match messages.first_mut() {
+        Some(first) if first.role == MessageRole::System => {
+            let existing = content_text(&first.content);
+            first.content = MessageContent::Text(if existing.is_empty() {
+                text.to_string()
+            } else {
+                format!("{existing}\n\n{text}")
+            });
+        }
+        _ => messages.insert(0, Message::system(text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structured::JsonModeConfig;
+    use crate::types::tool::ToolCall;
+    use serde_json::json;
+
+    fn weather_tool() -> ToolDefinition {
+        ToolDefinition {
+            tool_type: "function".into(),
+            function: FunctionDefinition {
+                name: "get_weather".into(),
+                description: Some("Current weather".into()),
+                parameters: Some(json!({
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } }
+                })),
+            },
+        }
+    }
+
+    fn request(messages: Vec<Message>) -> UnifiedRequest {
+        UnifiedRequest {
+            operation: "chat".into(),
+            model: "deepseek-chat".into(),
+            messages,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_request_that_fits_is_untouched() {
+        let mut req = request(vec![Message::user("hi")]);
+        req.tools = Some(vec![weather_tool()]);
+        let target = TargetCapabilities {
+            tools: true,
+            ..TargetCapabilities::text_only()
+        };
+        let (out, report) = degrade_request(&req, &target, &DegradeOptions::default());
+        assert!(report.is_empty());
+        assert_eq!(out.tools.unwrap().len(), 1);
+        assert_eq!(report.to_string(), "");
+    }
+
+    #[test]
+    fn test_tools_become_text_protocol_and_round_trip() {
+        let mut req = request(vec![
+            Message::system("Be brief."),
+            Message::user("Weather in Paris?"),
+            Message::with_content(
+                MessageRole::Assistant,
+                MessageContent::blocks(vec![ContentBlock::ToolUse {
+                    id: "c1".into(),
+                    name: "get_weather".into(),
+                    input: json!({ "city": "Paris" }),
+                }]),
+            ),
+            Message::tool("c1", "18C"),
+        ]);
+        req.tools = Some(vec![weather_tool()]);
+        req.tool_choice = Some(json!("auto"));
+
+        let (out, report) = degrade_request(
+            &req,
+            &TargetCapabilities::text_only(),
+            &DegradeOptions::default(),
+        );
+        assert!(out.tools.is_none() && out.tool_choice.is_none());
+        assert_eq!(
+            report.rewrites,
+            vec![Rewrite::ToolsAsText {
+                tools: vec!["get_weather".into()],
+                history_messages: 2,
+            }]
+        );
+        let system = content_text(&out.messages[0].content);
+        assert!(system.starts_with("Be brief.\n\n"));
+        assert!(system.contains("<tool_call>") && system.contains("\"city\""));
+        assert!(content_text(&out.messages[2].content).contains("\"get_weather\""));
+        assert_eq!(out.messages[3].role, MessageRole::User);
+        assert!(content_text(&out.messages[3].content).contains("<tool_result>"));
+
+        let mut response = UnifiedResponse::new(
+            "Checking.\n<tool_call>\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Rome\"}}\n</tool_call>",
+        );
+        report.restore(&mut response);
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].name, "get_weather");
+        assert_eq!(response.tool_calls[0].arguments, json!({ "city": "Rome" }));
+        assert!(!response.content.contains("<tool_call>"));
+        assert_eq!(response.degradation.as_ref(), Some(&report));
+    }
+
+    #[test]
+    fn test_response_format_as_forced_tool_or_instructions() {
+        let schema = json!({ "type": "object", "properties": { "answer": { "type": "string" } } });
+        let mut req = request(vec![Message::user("2+2?")]);
+        req.response_format = Some(JsonModeConfig::from_schema(schema, "answer", true));
+
+        let tools_only = TargetCapabilities {
+            tools: true,
+            ..TargetCapabilities::text_only()
+        };
+        let (out, report) = degrade_request(&req, &tools_only, &DegradeOptions::default());
+        assert!(out.response_format.is_none());
+        assert_eq!(out.tools.as_ref().unwrap()[0].function.name, "answer");
+        assert_eq!(out.tool_choice.unwrap()["function"]["name"], "answer");
+        let mut response = UnifiedResponse {
+            tool_calls: vec![ToolCall {
+                id: "t".into(),
+                name: "answer".into(),
+                arguments: json!({ "answer": "4" }),
+            }],
+            ..Default::default()
+        };
+        report.restore(&mut response);
+        assert!(response.tool_calls.is_empty());
+        assert_eq!(response.content, r#"{"answer":"4"}"#);
+
+        let (out, report) = degrade_request(
+            &req,
+            &TargetCapabilities::text_only(),
+            &DegradeOptions::default(),
+        );
+        assert!(out.tools.is_none() && out.response_format.is_none());
+        assert_eq!(report.rewrites, vec![Rewrite::ResponseFormatAsInstructions]);
+        assert_eq!(out.messages[0].role, MessageRole::System);
+        assert!(content_text(&out.messages[0].content).contains("\"answer\""));
+    }
+
+    #[test]
+    fn test_attachments_described_dropped_and_extracted() {
+        let notes = base64::engine::general_purpose::STANDARD.encode("Meeting notes: ship Friday.");
+        let req = request(vec![Message::with_content(
+            MessageRole::User,
+            MessageContent::blocks(vec![
+                ContentBlock::text("Summarize"),
+                ContentBlock::image_url("https://example.com/cat.png"),
+                ContentBlock::audio_base64("AAAA".into(), Some("audio/wav".into())),
+                ContentBlock::document_base64(
+                    notes,
+                    Some("text/plain".into()),
+                    Some("notes.txt".into()),
+                ),
+                ContentBlock::document_file("file-1".into(), Some("application/pdf".into()), None),
+            ]),
+        )]);
+
+        let (out, report) = degrade_request(
+            &req,
+            &TargetCapabilities::text_only(),
+            &DegradeOptions::default(),
+        );
+        assert_eq!(
+            report.rewrites,
+            vec![
+                Rewrite::ImagesDescribed { count: 1 },
+                Rewrite::AudioDescribed { count: 1 },
+                Rewrite::DocumentsExtracted { count: 1 },
+                Rewrite::DocumentsDescribed { count: 1 },
+            ]
+        );
+        let MessageContent::Text(text) = &out.messages[0].content else {
+            panic!("text-only content should collapse to a string");
+        };
+        assert!(text.contains("https://example.com/cat.png"));
+        assert!(text.contains("[Document: notes.txt]\nMeeting notes: ship Friday."));
+        assert!(text.contains("[Document omitted (application/pdf)"));
+
+        let options = DegradeOptions::default()
+            .with_images(ImageFallback::Drop)
+            .with_max_document_chars(7);
+        let (out, report) = degrade_request(&req, &TargetCapabilities::text_only(), &options);
+        assert_eq!(report.rewrites[0], Rewrite::ImagesDropped { count: 1 });
+        let text = content_text(&out.messages[0].content);
+        assert!(!text.contains("cat.png"));
+        assert!(text.contains("Meeting\n[truncated]"));
+    }
+
+    fn delta(content: &str) -> StreamingEvent {
+        StreamingEvent::PartialContentDelta {
+            content: content.into(),
+            sequence_id: None,
+        }
+    }
+
+    async fn restored(
+        report: &DegradationReport,
+        events: Vec<StreamingEvent>,
+    ) -> Vec<StreamingEvent> {
+        let stream = futures::stream::iter(events.into_iter().map(Ok));
+        report
+            .restore_stream(Box::pin(stream))
+            .map(|e| e.unwrap())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_restore_stream_passes_text_without_tool_calls() {
+        let report = DegradationReport {
+            model: "tiny".into(),
+            rewrites: vec![Rewrite::ToolsAsText {
+                tools: vec!["get_weather".into()],
+                history_messages: 0,
+            }],
+            tool_calling: None,
+        };
+        let out = restored(
+            &report,
+            vec![
+                delta("a "),
+                delta("< b"),
+                delta(" c"),
+                StreamingEvent::StreamEnd {
+                    finish_reason: None,
+                },
+            ],
+        )
+        .await;
+        let text: String = out
+            .iter()
+            .filter_map(|e| match e {
+                StreamingEvent::PartialContentDelta { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "a < b c");
+        assert!(matches!(out.last(), Some(StreamingEvent::StreamEnd { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_restore_stream_turns_schema_tool_into_content() {
+        let report = DegradationReport {
+            model: "m".into(),
+            rewrites: vec![Rewrite::ResponseFormatAsTool {
+                tool: "answer".into(),
+            }],
+            tool_calling: None,
+        };
+        let out = restored(
+            &report,
+            vec![
+                StreamingEvent::ToolCallStarted {
+                    tool_call_id: "c1".into(),
+                    tool_name: "answer".into(),
+                    index: Some(0),
+                },
+                StreamingEvent::PartialToolCall {
+                    tool_call_id: "c1".into(),
+                    arguments: "{\"ok\":".into(),
+                    index: Some(0),
+                    is_complete: None,
+                },
+                StreamingEvent::PartialToolCall {
+                    tool_call_id: "c1".into(),
+                    arguments: "true}".into(),
+                    index: Some(0),
+                    is_complete: Some(true),
+                },
+            ],
+        )
+        .await;
+        assert_eq!(out.len(), 2);
+        let text: String = out
+            .iter()
+            .filter_map(|e| match e {
+                StreamingEvent::PartialContentDelta { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "{\"ok\":true}");
+    }
+}
@@ -97,7 +97,31 @@ impl AiClient {
         paths
     }
 
+    /// OpenAI-style `{id, function: {name, arguments}}`; string arguments are parsed as JSON
+    /// when possible.
+    fn nonstream_tool_call(v: &serde_json::Value) -> Option<crate::types::tool::ToolCall> {
+        let f = v.get("function").unwrap_or(v);
+        let name = f.get("name")?.as_str()?.to_string();
+        let arguments = match f.get("arguments") {
+            Some(serde_json::Value::String(s)) => {
+                serde_json::from_str(s).unwrap_or_else(|_| serde_json::Value::String(s.clone()))
+            }
+            Some(other) => other.clone(),
+            None => serde_json::Value::Null,
+        };
+        Some(crate::types::tool::ToolCall {
+            id: v
+                .get("id")
+                .and_then(|i| i.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            name,
+            arguments,
+        })
+    }
+
     fn extract_nonstream_response(&self, json: &serde_json::Value, response: &mut UnifiedResponse) {
+        response.images = crate::types::message::ContentBlock::inline_images(json);
         for path in self.nonstream_response_paths() {
             if let Some(content) = crate::utils::json_path::PathMapper::get_string(json, path) {
                 if !content.is_empty() {
@@ -126,6 +150,21 @@ impl AiClient {
             }
         }
 
+        if response.tool_calls.is_empty() {
+            let path = self
+                .manifest
+                .response_paths
+                .as_ref()
+                .and_then(|p| p.get("tool_calls"))
+                .map(String::as_str)
+                .unwrap_or("choices[0].message.tool_calls");
+            if let Some(serde_json::Value::Array(calls)) =
+                crate::utils::json_path::PathMapper::get_path(json, path)
+            {
+                response.tool_calls = calls.iter().filter_map(Self::nonstream_tool_call).collect();
+            }
+        }
+
         if response.content.is_empty() {
             for path in self.nonstream_reasoning_paths() {
                 if let Some(content) = crate::utils::json_path::PathMapper::get_string(json, path) {
@@ -276,6 +315,9 @@ impl AiClient {
             error_class: None,
             usage: None,
             signals: self.signals().await,
+            prompt: None,
+            degradation: None,
+            hedge: None,
         };
 
         Ok((event_stream, permit, stats))
@@ -413,6 +455,9 @@ impl AiClient {
                 error_class: None,
                 usage: response.usage.clone(),
                 signals: self.signals().await,
+                prompt: None,
+                degradation: None,
+                hedge: None,
             };
 
             return Ok((response, stats));
@@ -574,6 +619,9 @@ impl AiClient {
             error_class: None,
             usage: response.usage.clone(),
             signals: self.signals().await,
+            prompt: None,
+            degradation: None,
+            hedge: None,
         };
 
         Ok((response, stats))
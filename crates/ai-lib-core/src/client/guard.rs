@@ -0,0 +1,40 @@
+//! 消息守卫：请求离开进程前改写消息（如 PII 假名化），并在响应与事件流中还原。
+//!
+//! Message guards.
+//!
+//! A [`MessageGuard`] registered with
+//! [`AiClientBuilder::message_guard`](crate::client::AiClientBuilder::message_guard)
+//! rewrites the messages of every chat call before the request is encoded. It runs
+//! once per candidate (primary, each fallback, hedge and stream continuation), after
+//! the request has been degraded for that candidate, so text added by degradation
+//! such as extracted documents is covered too; retries of a candidate resend the
+//! same rewritten request. The [`GuardedCall`] of the candidate that answered undoes
+//! the rewrite in its response or wraps its event stream. Provider batch clients
+//! built from the client rewrite each item the same way and restore its result.
+//!
+//! `ai-lib-contact`'s `Pseudonymizer` implements this trait to replace PII with
+//! vault tokens and restore the originals.
+
+use crate::client::core::UnifiedResponse;
+use crate::types::events::StreamingEvent;
+use crate::types::message::Message;
+use crate::BoxStream;
+use std::sync::Arc;
+
+/// Rewrites outgoing chat messages for one call.
+pub trait MessageGuard: Send + Sync {
+    /// Rewrite `messages` in place; the returned call state restores the output.
+    fn protect(&self, messages: &mut [Message]) -> Arc<dyn GuardedCall>;
+}
+
+/// Per-call state returned by [`MessageGuard::protect`].
+pub trait GuardedCall: Send + Sync {
+    /// Undo the rewrite in a complete response.
+    fn restore_response(&self, response: &mut UnifiedResponse);
+
+    /// Undo the rewrite in a stream of events.
+    fn restore_stream(
+        &self,
+        stream: BoxStream<'static, StreamingEvent>,
+    ) -> BoxStream<'static, StreamingEvent>;
+}
@@ -0,0 +1,443 @@
+//! 对冲请求：主模型在阈值内未产出首个事件时，在备用模型上发起同一请求，先产出事件者胜出，另一方被取消。
+//!
+//! Hedged streaming requests.
+//!
+//! With a [`HedgePolicy`] configured (see
+//! [`AiClientBuilder::hedging`](crate::client::AiClientBuilder::hedging)), a
+//! streaming chat that has not produced its first event within the hedge delay is
+//! started again on a secondary model. Whichever stream yields an event first is
+//! returned to the caller; a loser that already holds an open stream is stopped
+//! through its [`CancelHandle`], and a loser still waiting for headers is dropped,
+//! which aborts its HTTP request.
+//!
+//! The delay is either fixed or the p95 of recently observed time-to-first-event.
+//! A fired hedge is one extra upstream request; it is counted in
+//! [`ClientMetrics::hedged_requests`](crate::client::ClientMetrics) and described by
+//! [`CallStats::hedge`](crate::client::CallStats). A cancelled loser that had opened
+//! a stream keeps its own stats in [`HedgeStats::loser`]. Providers do not report
+//! usage for a stream cut off before its end, so the loser's cost is tracked as the
+//! estimated prompt tokens of the request (text, tool calls and tool results at ~4
+//! bytes per token, plus a flat estimate per image, audio or document block) in
+//! `ClientMetrics::hedge_loser_tokens`, whether it was cancelled or aborted in flight.
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`HedgePolicy`] | Secondary model and delay rule |
+//! | [`HedgeDelay`] | Fixed delay or learned p95 |
+//! | [`HedgeStats`] | What happened when a hedge fired |
+//!
+//! ```rust,no_run
+//! use ai_lib_core::client::{AiClientBuilder, HedgePolicy};
+//! use std::time::Duration;
+//!
+//! # async fn demo() -> ai_lib_core::Result<()> {
+//! let client = AiClientBuilder::new()
+//!     .hedging(
+//!         HedgePolicy::learned_p95(Duration::from_millis(800))
+//!             .with_model("anthropic/claude-3-5-haiku"),
+//!     )
+//!     .build("openai/gpt-4o")
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::chat::ChatRequestBuilder;
+use crate::client::core::AiClient;
+use crate::client::types::{CallStats, CancelHandle};
+use crate::types::events::StreamingEvent;
+use crate::types::message::{ContentBlock, Message, MessageContent};
+use crate::Result;
+use futures::{FutureExt, Stream};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+type StreamOutcome = (
+    Pin<Box<dyn Stream<Item = Result<StreamingEvent>> + Send + 'static>>,
+    CancelHandle,
+    CallStats,
+);
+
+/// When to start the hedge request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HedgeDelay {
+    Fixed(Duration),
+    /// p95 of recent time-to-first-event; `initial` is used until `min_samples`
+    /// calls have been observed.
+    LearnedP95 {
+        initial: Duration,
+        min_samples: usize,
+    },
+}
+
+/// Opt-in hedging policy for streaming chat requests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HedgePolicy {
+    /// `provider/model` to race against; defaults to the first fallback, else the
+    /// primary model itself.
+    pub model: Option<String>,
+    pub delay: HedgeDelay,
+    /// Number of recent time-to-first-event samples kept for the learned delay.
+    pub window: usize,
+}
+
+impl HedgePolicy {
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            model: None,
+            delay: HedgeDelay::Fixed(delay),
+            window: 200,
+        }
+    }
+
+    pub fn learned_p95(initial: Duration) -> Self {
+        Self {
+            model: None,
+            delay: HedgeDelay::LearnedP95 {
+                initial,
+                min_samples: 20,
+            },
+            window: 200,
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Observations needed before the learned p95 replaces the initial delay.
+    pub fn with_min_samples(mut self, n: usize) -> Self {
+        if let HedgeDelay::LearnedP95 { min_samples, .. } = &mut self.delay {
+            *min_samples = n.max(1);
+        }
+        self
+    }
+
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+}
+
+/// Which request produced the stream returned to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HedgeWinner {
+    Primary,
+    Hedge,
+}
+
+/// Outcome of a call whose hedge fired.
+#[derive(Debug, Clone)]
+pub struct HedgeStats {
+    /// Delay after which the hedge was started.
+    pub delay_ms: u128,
+    /// `provider/model` of the hedge request.
+    pub model: String,
+    pub winner: HedgeWinner,
+    /// Upstream requests made beyond the primary (the hedge itself).
+    pub extra_requests: u32,
+    /// Stats of the losing request when it had already opened a stream before
+    /// being cancelled; `None` when it was aborted before headers or failed.
+    pub loser: Option<Box<CallStats>>,
+}
+
+/// Per-client hedging state: the policy plus recent time-to-first-event samples.
+#[derive(Debug)]
+pub(crate) struct Hedger {
+    pub(crate) policy: HedgePolicy,
+    samples: Mutex<VecDeque<u64>>,
+}
+
+impl Hedger {
+    pub(crate) fn new(policy: HedgePolicy) -> Self {
+        Self {
+            policy,
+            samples: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn threshold(&self) -> Duration {
+        match self.policy.delay {
+            HedgeDelay::Fixed(delay) => delay,
+            HedgeDelay::LearnedP95 {
+                initial,
+                min_samples,
+            } => {
+                let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+                if samples.len() < min_samples {
+                    return initial;
+                }
+                let mut sorted: Vec<u64> = samples.iter().copied().collect();
+                sorted.sort_unstable();
+                let idx = ((sorted.len() as f64) * 0.95).ceil() as usize;
+                Duration::from_millis(sorted[idx.saturating_sub(1).min(sorted.len() - 1)])
+            }
+        }
+    }
+
+    /// Record the primary's time to first event. When the hedge won, the elapsed
+    /// time is a lower bound for the primary and is recorded as such.
+    pub(crate) fn observe(&self, elapsed: Duration) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples.push_back(elapsed.as_millis() as u64);
+        while samples.len() > self.policy.window {
+            samples.pop_front();
+        }
+    }
+}
+
+impl<'a> ChatRequestBuilder<'a> {
+    /// Same request, targeting `client`'s own model.
+    fn fork<'b>(&self, client: &'b AiClient) -> ChatRequestBuilder<'b> {
+        ChatRequestBuilder {
+            client,
+            messages: self.messages.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            stream: self.stream,
+            tools: self.tools.clone(),
+            tool_choice: self.tool_choice.clone(),
+            model: None,
+            response_format: self.response_format.clone(),
+            prompt: self.prompt.clone(),
+        }
+    }
+
+    pub(crate) async fn execute_stream_hedged(self, hedger: &Hedger) -> Result<StreamOutcome> {
+        let client = self.client;
+        let delay = hedger.threshold();
+        let hedge_model = hedger
+            .policy
+            .model
+            .clone()
+            .or_else(|| client.fallbacks.first().cloned())
+            .unwrap_or_else(|| format!("{}/{}", client.manifest.id, client.model_id));
+        let template = self.fork(client);
+        let prompt_tokens = estimate_prompt_tokens(&self.messages);
+        let start = Instant::now();
+
+        let primary = self.execute_stream_unhedged();
+        tokio::pin!(primary);
+        tokio::select! {
+            res = &mut primary => {
+                if res.is_ok() {
+                    hedger.observe(start.elapsed());
+                }
+                return res;
+            }
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        let secondary = tokio::select! {
+            res = &mut primary => {
+                if res.is_ok() {
+                    hedger.observe(start.elapsed());
+                }
+                return res;
+            }
+            c = client.with_model(&hedge_model) => c,
+        };
+        let secondary = match secondary {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!(model = %hedge_model, error = %e, "hedge client unavailable");
+                return primary.await;
+            }
+        };
+        tracing::debug!(
+            model = %hedge_model,
+            delay_ms = delay.as_millis() as u64,
+            "primary slow to first event; starting hedge"
+        );
+        client.hedged_requests.fetch_add(1, Ordering::Relaxed);
+        let hedge = template.fork(&secondary).execute_stream_unhedged();
+        tokio::pin!(hedge);
+
+        let mut loser_stats = None;
+        let mut loser_stopped = false;
+        let (mut winner, first) = tokio::select! {
+            res = &mut primary => (HedgeWinner::Primary, res),
+            res = &mut hedge => (HedgeWinner::Hedge, res),
+        };
+        let result = match first {
+            Ok(outcome) => {
+                // A loser that finished in the same poll already holds a stream.
+                let loser = match winner {
+                    HedgeWinner::Primary => hedge.as_mut().now_or_never(),
+                    HedgeWinner::Hedge => primary.as_mut().now_or_never(),
+                };
+                loser_stopped = !matches!(loser, Some(Err(_)));
+                loser_stats = cancel_loser(loser);
+                Ok(outcome)
+            }
+            Err(e) => {
+                // The other request may still succeed.
+                tracing::debug!(error = %e, "hedged candidate failed; awaiting the other");
+                winner = match winner {
+                    HedgeWinner::Primary => HedgeWinner::Hedge,
+                    HedgeWinner::Hedge => HedgeWinner::Primary,
+                };
+                match winner {
+                    HedgeWinner::Primary => primary.await,
+                    HedgeWinner::Hedge => hedge.await,
+                }
+            }
+        };
+
+        let (stream, handle, mut stats) = result?;
+        hedger.observe(start.elapsed());
+        if winner == HedgeWinner::Hedge {
+            client.hedge_wins.fetch_add(1, Ordering::Relaxed);
+            client.record_success(&stats);
+        }
+        if loser_stopped {
+            client.record_hedge_loser(loser_stats.is_some(), prompt_tokens);
+        }
+        stats.hedge = Some(HedgeStats {
+            delay_ms: delay.as_millis(),
+            model: hedge_model,
+            winner,
+            extra_requests: 1,
+            loser: loser_stats,
+        });
+        Ok((stream, handle, stats))
+    }
+}
+
+/// Flat token estimate for an image, audio or document block.
+const MEDIA_BLOCK_TOKENS: u64 = 256;
+
+/// Estimated prompt tokens of `messages`: ~4 bytes per token of text, tool
+/// arguments and tool results (as serialized JSON), plus [`MEDIA_BLOCK_TOKENS`]
+/// per media block.
+fn estimate_prompt_tokens(messages: &[Message]) -> u64 {
+    let mut bytes = 0;
+    let mut media = 0;
+    for message in messages {
+        match &message.content {
+            MessageContent::Text(text) => bytes += text.len(),
+            MessageContent::Blocks(blocks) => {
+                for block in blocks {
+                    match block {
+                        ContentBlock::Text { text } => bytes += text.len(),
+                        ContentBlock::ToolUse { name, input, .. } => {
+                            bytes += name.len() + input.to_string().len()
+                        }
+                        ContentBlock::ToolResult { content, .. } => {
+                            bytes += match content {
+                                serde_json::Value::String(text) => text.len(),
+                                other => other.to_string().len(),
+                            }
+                        }
+                        ContentBlock::Image { .. }
+                        | ContentBlock::Audio { .. }
+                        | ContentBlock::Document { .. } => media += 1,
+                    }
+                }
+            }
+        }
+    }
+    bytes.div_ceil(4) as u64 + media * MEDIA_BLOCK_TOKENS
+}
+
+/// Cancel a loser that already holds a stream and keep its stats.
+fn cancel_loser(loser: Option<Result<StreamOutcome>>) -> Option<Box<CallStats>> {
+    match loser {
+        Some(Ok((_stream, handle, stats))) => {
+            handle.cancel();
+            Some(Box::new(stats))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_loser_keeps_stats_of_open_stream() {
+        let (handle, mut cancelled) = crate::client::types::cancel_pair();
+        let stats = CallStats {
+            model: "fake/backup".into(),
+            usage: Some(serde_json::json!({ "total_tokens": 7 })),
+            ..Default::default()
+        };
+        let stream: Pin<Box<dyn Stream<Item = Result<StreamingEvent>> + Send>> =
+            Box::pin(futures::stream::empty());
+        let loser = cancel_loser(Some(Ok((stream, handle, stats)))).expect("loser stats");
+        assert_eq!(loser.model, "fake/backup");
+        assert_eq!(loser.usage.unwrap()["total_tokens"], 7);
+        assert!(cancelled.try_recv().is_ok());
+
+        assert!(cancel_loser(None).is_none());
+        assert!(cancel_loser(Some(Err(crate::Error::configuration("down")))).is_none());
+    }
+
+    #[test]
+    fn test_estimate_prompt_tokens_counts_text_tools_and_media() {
+        let messages = vec![
+            Message::system("Be brief."),
+            Message::with_content(
+                crate::types::message::MessageRole::User,
+                MessageContent::blocks(vec![
+                    ContentBlock::text("What is in this picture?"),
+                    ContentBlock::image_url("https://example.com/cat.jpg"),
+                ]),
+            ),
+        ];
+        // 9 + 24 bytes of text and one image.
+        assert_eq!(estimate_prompt_tokens(&messages), 9 + MEDIA_BLOCK_TOKENS);
+        assert_eq!(estimate_prompt_tokens(&[]), 0);
+
+        let tools = vec![Message::with_content(
+            crate::types::message::MessageRole::Assistant,
+            MessageContent::blocks(vec![
+                ContentBlock::ToolUse {
+                    id: "call_1".into(),
+                    name: "get_weather".into(),
+                    input: serde_json::json!({ "city": "Paris" }),
+                },
+                ContentBlock::ToolResult {
+                    tool_use_id: "call_1".into(),
+                    content: serde_json::json!("18C and sunny"),
+                },
+            ]),
+        )];
+        // 11 + 16 bytes of tool call, 13 bytes of result.
+        assert_eq!(estimate_prompt_tokens(&tools), 10);
+    }
+
+    #[test]
+    fn test_learned_delay_uses_p95_after_min_samples() {
+        let hedger = Hedger::new(
+            HedgePolicy::learned_p95(Duration::from_millis(500))
+                .with_min_samples(10)
+                .with_window(20),
+        );
+        for ms in 1..=9 {
+            hedger.observe(Duration::from_millis(ms * 10));
+        }
+        assert_eq!(hedger.threshold(), Duration::from_millis(500));
+
+        for ms in 10..=20 {
+            hedger.observe(Duration::from_millis(ms * 10));
+        }
+        // 20 samples 10..=200ms: p95 is the 19th.
+        assert_eq!(hedger.threshold(), Duration::from_millis(190));
+
+        // The window drops the oldest samples.
+        for _ in 0..20 {
+            hedger.observe(Duration::from_millis(30));
+        }
+        assert_eq!(hedger.threshold(), Duration::from_millis(30));
+
+        let fixed = Hedger::new(HedgePolicy::fixed(Duration::from_millis(250)));
+        fixed.observe(Duration::from_secs(5));
+        assert_eq!(fixed.threshold(), Duration::from_millis(250));
+    }
+}
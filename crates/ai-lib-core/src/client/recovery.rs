@@ -0,0 +1,349 @@
+//! 流中断恢复：流在已输出内容后失败时，保留部分输出，在同一模型或备用模型上续写，并无缝拼接到调用方的事件流中。
+//!
+//! Mid-stream failure recovery.
+//!
+//! Retry and fallback only apply before a stream's first event. With a
+//! [`RecoveryPolicy`] configured (see
+//! [`AiClientBuilder::stream_recovery`](crate::client::AiClientBuilder::stream_recovery)),
+//! a stream that fails after emitting text is resumed instead: the request is
+//! reissued with the partial assistant text appended, and the continuation is
+//! spliced into the caller's stream behind a
+//! [`StreamingEvent::StreamRecovered`] marker.
+//!
+//! | Target | Continuation request |
+//! |--------|----------------------|
+//! | supports assistant prefill | Original messages + the partial text as a trailing assistant message |
+//! | no prefill | Same, followed by a user message asking the model to continue |
+//! | nothing emitted yet | Original request, unchanged |
+//!
+//! Prefill support is declared by the manifest's `capabilities.feature_flags.assistant_prefill`
+//! ([`Capabilities::assistant_prefill`](crate::protocol::Capabilities::assistant_prefill)).
+//! Trailing whitespace is
+//! trimmed from the prefill (some providers reject it) and removed again from the
+//! start of the continuation so the spliced text is not doubled. Streams that
+//! already emitted tool-call events are not recovered; the error is surfaced.
+//!
+//! ```rust,no_run
+//! use ai_lib_core::client::{AiClientBuilder, RecoveryPolicy};
+//!
+//! # async fn demo() -> ai_lib_core::Result<()> {
+//! let client = AiClientBuilder::new()
+//!     .with_fallbacks(vec!["anthropic/claude-3-5-sonnet".to_string()])
+//!     .stream_recovery(RecoveryPolicy::new().with_max_recoveries(3))
+//!     .build("openai/gpt-4o")
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::chat::ChatRequestBuilder;
+use crate::client::core::AiClient;
+use crate::protocol::ProtocolManifest;
+use crate::types::events::StreamingEvent;
+use crate::types::message::Message;
+use crate::Result;
+use futures::{Stream, StreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<StreamingEvent>> + Send + 'static>>;
+
+const DEFAULT_CONTINUE_INSTRUCTION: &str = "Your previous reply was cut off. Continue exactly \
+where it stopped, without repeating any text and without commentary.";
+
+/// Opt-in policy for resuming streams that fail after emitting output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryPolicy {
+    /// Successful resumptions allowed per call.
+    pub max_recoveries: u32,
+    /// Try the model that failed before the remaining fallbacks.
+    pub same_model_first: bool,
+    /// User message sent after the partial text when the target cannot prefill.
+    pub continue_instruction: String,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            max_recoveries: 2,
+            same_model_first: true,
+            continue_instruction: DEFAULT_CONTINUE_INSTRUCTION.to_string(),
+        }
+    }
+}
+
+impl RecoveryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_recoveries(mut self, n: u32) -> Self {
+        self.max_recoveries = n;
+        self
+    }
+
+    pub fn with_same_model_first(mut self, enable: bool) -> Self {
+        self.same_model_first = enable;
+        self
+    }
+
+    pub fn with_continue_instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.continue_instruction = instruction.into();
+        self
+    }
+}
+
+/// How a continuation request carries the partial output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryMode {
+    /// Partial text sent as a trailing assistant message.
+    Prefill,
+    /// Partial text followed by a "continue" user message.
+    Continue,
+    /// Nothing was emitted; the original request is reissued.
+    Restart,
+}
+
+impl RecoveryMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecoveryMode::Prefill => "prefill",
+            RecoveryMode::Continue => "continue",
+            RecoveryMode::Restart => "restart",
+        }
+    }
+}
+
+/// Whether `manifest` continues a trailing assistant message.
+pub fn supports_assistant_prefill(manifest: &ProtocolManifest) -> bool {
+    manifest.capabilities.assistant_prefill
+}
+
+/// Request parameters needed to reissue a streaming call.
+#[derive(Debug, Clone)]
+pub(crate) struct RecoveryRequest {
+    messages: Vec<Message>,
+    temperature: Option<f64>,
+    max_tokens: Option<u32>,
+    tools: Option<Vec<crate::types::tool::ToolDefinition>>,
+    tool_choice: Option<serde_json::Value>,
+    response_format: Option<crate::structured::JsonModeConfig>,
+}
+
+impl RecoveryRequest {
+    pub(crate) fn from_builder(builder: &ChatRequestBuilder<'_>) -> Self {
+        Self {
+            messages: builder.messages.clone(),
+            temperature: builder.temperature,
+            max_tokens: builder.max_tokens,
+            tools: builder.tools.clone(),
+            tool_choice: builder.tool_choice.clone(),
+            response_format: builder.response_format.clone(),
+        }
+    }
+
+    fn continuation<'c>(
+        &self,
+        client: &'c AiClient,
+        partial: &str,
+        instruction: &str,
+    ) -> (ChatRequestBuilder<'c>, RecoveryMode) {
+        let mut messages = self.messages.clone();
+        let mode = if partial.is_empty() {
+            RecoveryMode::Restart
+        } else if supports_assistant_prefill(&client.manifest) {
+            messages.push(Message::assistant(partial));
+            RecoveryMode::Prefill
+        } else {
+            messages.push(Message::assistant(partial));
+            messages.push(Message::user(instruction));
+            RecoveryMode::Continue
+        };
+        let builder = ChatRequestBuilder {
+            client,
+            messages,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            stream: true,
+            tools: self.tools.clone(),
+            tool_choice: self.tool_choice.clone(),
+            model: None,
+            response_format: self.response_format.clone(),
+            prompt: None,
+        };
+        (builder, mode)
+    }
+}
+
+struct Recovery {
+    current: EventStream,
+    /// Client for the model that produced `current`'s first segment.
+    seed: AiClient,
+    /// Fallback models not yet used by the original call.
+    fallbacks: Vec<String>,
+    request: RecoveryRequest,
+    policy: RecoveryPolicy,
+    partial: String,
+    /// Whitespace trimmed off the prefill, stripped again from the continuation.
+    trimmed_ws: bool,
+    saw_tool_call: bool,
+    recoveries: u32,
+    pending: VecDeque<Result<StreamingEvent>>,
+    done: bool,
+}
+
+/// Wrap `stream` so failures after its first event are resumed per `policy`.
+pub(crate) fn recoverable(
+    stream: EventStream,
+    seed: AiClient,
+    fallbacks: Vec<String>,
+    request: RecoveryRequest,
+    policy: RecoveryPolicy,
+) -> EventStream {
+    let state = Recovery {
+        current: stream,
+        seed,
+        fallbacks,
+        request,
+        policy,
+        partial: String::new(),
+        trimmed_ws: false,
+        saw_tool_call: false,
+        recoveries: 0,
+        pending: VecDeque::new(),
+        done: false,
+    };
+    Box::pin(futures::stream::unfold(state, |mut st| async move {
+        loop {
+            if let Some(item) = st.pending.pop_front() {
+                return Some((item, st));
+            }
+            if st.done {
+                return None;
+            }
+            let (failure, original) = match st.current.next().await {
+                None => return None,
+                Some(Ok(StreamingEvent::StreamError { error, event_id })) => (
+                    error.to_string(),
+                    Ok(StreamingEvent::StreamError { error, event_id }),
+                ),
+                Some(Ok(ev)) => match st.observe(ev) {
+                    Some(ev) => return Some((Ok(ev), st)),
+                    None => continue,
+                },
+                Some(Err(e)) => (e.to_string(), Err(e)),
+            };
+            if st.saw_tool_call || st.recoveries >= st.policy.max_recoveries {
+                st.done = true;
+                return Some((original, st));
+            }
+            if let Err(e) = st.resume(&failure).await {
+                // Surface the original failure; the recovery error is only logged.
+                tracing::warn!(error = %e, "stream recovery failed");
+                st.done = true;
+                return Some((original, st));
+            }
+        }
+    }))
+}
+
+impl Recovery {
+    /// Track emitted output; `None` drops an event that was emptied by the splice.
+    fn observe(&mut self, ev: StreamingEvent) -> Option<StreamingEvent> {
+        match ev {
+            StreamingEvent::PartialContentDelta {
+                mut content,
+                sequence_id,
+            } => {
+                if self.trimmed_ws {
+                    let trimmed = content.trim_start();
+                    if trimmed.is_empty() {
+                        return None;
+                    }
+                    content = trimmed.to_string();
+                    self.trimmed_ws = false;
+                }
+                self.partial.push_str(&content);
+                Some(StreamingEvent::PartialContentDelta {
+                    content,
+                    sequence_id,
+                })
+            }
+            ev @ (StreamingEvent::ToolCallStarted { .. }
+            | StreamingEvent::PartialToolCall { .. }) => {
+                self.saw_tool_call = true;
+                Some(ev)
+            }
+            ev => Some(ev),
+        }
+    }
+
+    /// Start a continuation on the first candidate that yields an event.
+    async fn resume(&mut self, failure: &str) -> Result<()> {
+        let attempt = self.recoveries + 1;
+        let prefill = self.partial.trim_end().to_string();
+        let trimmed_ws = prefill.len() < self.partial.len();
+
+        let mut candidates: Vec<Option<&str>> =
+            self.fallbacks.iter().map(|m| Some(m.as_str())).collect();
+        if self.policy.same_model_first {
+            candidates.insert(0, None);
+        } else {
+            candidates.push(None);
+        }
+
+        let mut last_err = None;
+        for candidate in candidates {
+            let fallback_client;
+            let client = match candidate {
+                None => &self.seed,
+                Some(model) => match self.seed.with_model(model).await {
+                    Ok(c) => {
+                        fallback_client = c;
+                        &fallback_client
+                    }
+                    Err(e) => {
+                        last_err = Some(e);
+                        continue;
+                    }
+                },
+            };
+            let (builder, mode) =
+                self.request
+                    .continuation(client, &prefill, &self.policy.continue_instruction);
+            match builder.execute_stream_unhedged().await {
+                Ok((stream, _cancel, _stats)) => {
+                    let model = format!("{}/{}", client.manifest.id, client.model_id);
+                    tracing::info!(
+                        model = %model,
+                        mode = mode.as_str(),
+                        attempt,
+                        error = %failure,
+                        "resumed failed stream"
+                    );
+                    self.pending.push_back(Ok(StreamingEvent::StreamRecovered {
+                        model,
+                        mode: mode.as_str().to_string(),
+                        error: failure.to_string(),
+                        retained_chars: prefill.chars().count(),
+                        attempt,
+                    }));
+                    self.current = stream;
+                    self.recoveries = attempt;
+                    self.trimmed_ws = trimmed_ws && mode != RecoveryMode::Restart;
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::debug!(error = %e, "stream recovery candidate failed");
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            crate::Error::runtime_with_context(
+                "no stream recovery candidates",
+                crate::ErrorContext::new().with_source("stream_recovery"),
+            )
+        }))
+    }
+}
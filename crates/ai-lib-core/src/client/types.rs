@@ -13,6 +13,16 @@ pub struct ClientMetrics {
     pub total_requests: u64,
     pub successful_requests: u64,
     pub total_tokens: u64,
+    /// Hedge requests started because the primary was slow to its first event.
+    pub hedged_requests: u64,
+    /// Hedged calls served by the hedge rather than the primary.
+    pub hedge_wins: u64,
+    /// Hedge losers cancelled after they had opened a stream.
+    pub hedge_cancelled_streams: u64,
+    /// Estimated prompt tokens of hedge losers that were cancelled or aborted (text,
+    /// tool calls and results at ~4 bytes per token, plus a flat estimate per media
+    /// block): input the provider may bill although the output is discarded.
+    pub hedge_loser_tokens: u64,
 }
 
 /// Per-call statistics for observability and model selection.
@@ -36,6 +46,12 @@ pub struct CallStats {
     pub usage: Option<serde_json::Value>,
     /// Snapshot of runtime signals captured for this call.
     pub signals: SignalsSnapshot,
+    /// Prompt template that produced the request messages, if rendered from a registry.
+    pub prompt: Option<crate::types::prompt::PromptRef>,
+    /// Rewrites applied to fit the serving model, when request degradation is enabled.
+    pub degradation: Option<crate::client::degrade::DegradationReport>,
+    /// Set when a hedge request was started for this call (see [`crate::client::HedgePolicy`]).
+    pub hedge: Option<crate::client::hedge::HedgeStats>,
 }
 
 /// Handle to cancel an in-flight streaming request.
@@ -2,7 +2,7 @@
 //!
 //! 凭证解析模块：按显式覆盖、manifest 环境变量、兼容环境变量、系统 keyring 的顺序解析。
 
-use crate::protocol::{AuthConfig, ProtocolManifest};
+use crate::protocol::{AuthConfig, ManifestV2, ProtocolManifest};
 #[cfg(feature = "keyring")]
 use keyring::Entry;
 use std::env;
@@ -140,8 +140,29 @@ pub fn resolve_credential(
     manifest: &ProtocolManifest,
     explicit: Option<&str>,
 ) -> ResolvedCredential {
-    let required_envs = required_envs(manifest);
-    let conventional_envs = conventional_envs(provider_id(manifest));
+    resolve_chain(provider_id(manifest), required_envs(manifest), explicit)
+}
+
+/// [`resolve_credential`] for a V2 manifest, whose auth is declared in `endpoint.auth`.
+pub fn resolve_credential_v2(manifest: &ManifestV2, explicit: Option<&str>) -> ResolvedCredential {
+    let required_envs = manifest
+        .endpoint
+        .auth
+        .as_ref()
+        .and_then(|auth| auth.token_env.as_deref())
+        .map(str::trim)
+        .filter(|env| !env.is_empty())
+        .map(|env| vec![env.to_string()])
+        .unwrap_or_default();
+    resolve_chain(&manifest.id, required_envs, explicit)
+}
+
+fn resolve_chain(
+    provider_id: &str,
+    required_envs: Vec<String>,
+    explicit: Option<&str>,
+) -> ResolvedCredential {
+    let conventional_envs = conventional_envs(provider_id);
 
     if let Some(value) = explicit.map(str::trim).filter(|value| !value.is_empty()) {
         return ResolvedCredential {
@@ -178,17 +199,14 @@ pub fn resolve_credential(
     }
 
     #[cfg(feature = "keyring")]
-    {
-        let id = provider_id(manifest);
-        if let Some(value) = keyring_value(id) {
-            return ResolvedCredential {
-                secret: Some(value),
-                source_kind: CredentialSourceKind::Keyring,
-                source_name: Some(format!("ai-protocol/{id}")),
-                required_envs,
-                conventional_envs,
-            };
-        }
+    if let Some(value) = keyring_value(provider_id) {
+        return ResolvedCredential {
+            secret: Some(value),
+            source_kind: CredentialSourceKind::Keyring,
+            source_name: Some(format!("ai-protocol/{provider_id}")),
+            required_envs,
+            conventional_envs,
+        };
     }
 
     ResolvedCredential::missing(required_envs, conventional_envs)
@@ -16,13 +16,16 @@ use crate::protocol::v2::capabilities::Capability;
 use crate::protocol::v2::manifest::ApiStyle;
 use crate::protocol::ProtocolError;
 use crate::types::events::StreamingEvent;
-use crate::types::message::{Message, MessageContent, MessageRole};
+use crate::types::message::{ContentBlock, Message, MessageContent, MessageRole};
 
 use super::{DriverRequest, DriverResponse, ProviderDriver, UsageInfo};
 use crate::types::content_encode::encode_blocks_for_anthropic;
 
 const DEFAULT_MAX_TOKENS: u32 = 4096;
 
+/// `anthropic-beta` value required to reference Files API uploads.
+pub(crate) const FILES_API_BETA: &str = "files-api-2025-04-14";
+
 /// Anthropic Messages API driver.
 #[derive(Debug)]
 pub struct AnthropicDriver {
@@ -96,6 +99,17 @@ impl AnthropicDriver {
     }
 }
 
+fn references_uploaded_file(message: &Message) -> bool {
+    let MessageContent::Blocks(blocks) = &message.content else {
+        return false;
+    };
+    blocks.iter().any(|block| match block {
+        ContentBlock::Image { source } => source.source_type == "file",
+        ContentBlock::Document { source } => source.source_type == "file",
+        _ => false,
+    })
+}
+
 #[async_trait]
 impl ProviderDriver for AnthropicDriver {
     fn provider_id(&self) -> &str {
@@ -138,6 +152,9 @@ impl ProviderDriver for AnthropicDriver {
 
         let mut headers = HashMap::new();
         headers.insert("anthropic-version".into(), "2023-06-01".into());
+        if messages.iter().any(references_uploaded_file) {
+            headers.insert("anthropic-beta".into(), FILES_API_BETA.into());
+        }
 
         Ok(DriverRequest {
             url: String::new(),
@@ -313,6 +330,37 @@ mod tests {
         assert_eq!(req.body["max_tokens"], 1024);
         assert_eq!(req.body["model"], "claude-sonnet-4-20250514");
         assert!(req.headers.contains_key("anthropic-version"));
+        assert!(!req.headers.contains_key("anthropic-beta"));
+    }
+
+    #[test]
+    fn test_anthropic_file_reference_adds_beta_header() {
+        let driver = AnthropicDriver::new("anthropic", vec![Capability::Text]);
+        let messages = vec![Message::with_content(
+            MessageRole::User,
+            MessageContent::blocks(vec![ContentBlock::document_file(
+                "file_011".into(),
+                Some("application/pdf".into()),
+                None,
+            )]),
+        )];
+        let req = driver
+            .build_request(
+                &messages,
+                "claude-sonnet-4-20250514",
+                None,
+                None,
+                false,
+                None,
+            )
+            .unwrap();
+        assert_eq!(req.headers["anthropic-beta"], FILES_API_BETA);
+        let block = &req.body["messages"][0]["content"][0];
+        assert_eq!(block["type"], "document");
+        assert_eq!(
+            block["source"],
+            serde_json::json!({ "type": "file", "file_id": "file_011" })
+        );
     }
 
     #[test]
@@ -202,9 +202,10 @@ impl ProviderDriver for OpenAiDriver {
         for m in messages {
             if let MessageContent::Blocks(blocks) = &m.content {
                 for block in blocks {
-                    if matches!(block, ContentBlock::Document { .. }) {
+                    if matches!(block, ContentBlock::Document { source } if source.source_type != "file")
+                    {
                         return Err(Error::Protocol(ProtocolError::ValidationError(
-                            "OpenAI-compatible driver does not encode document blocks; use Anthropic or Gemini".into(),
+                            "OpenAI-compatible driver only encodes uploaded document blocks (ContentBlock::document_file); upload via the Files API or use Anthropic or Gemini".into(),
                         )));
                     }
                 }
@@ -217,9 +218,19 @@ impl ProviderDriver for OpenAiDriver {
                 let role = serde_json::to_value(&m.role).unwrap_or(Value::String("user".into()));
                 let content = match &m.content {
                     MessageContent::Text(s) => Value::String(s.clone()),
-                    MessageContent::Blocks(_) => {
-                        serde_json::to_value(&m.content).unwrap_or(Value::Null)
-                    }
+                    MessageContent::Blocks(blocks) => Value::Array(
+                        blocks
+                            .iter()
+                            .map(|block| match block {
+                                // Chat Completions file part for Files API uploads.
+                                ContentBlock::Document { source } => serde_json::json!({
+                                    "type": "file",
+                                    "file": { "file_id": source.data },
+                                }),
+                                other => serde_json::to_value(other).unwrap_or(Value::Null),
+                            })
+                            .collect(),
+                    ),
                 };
                 let mut obj = serde_json::json!({ "role": role, "content": content });
                 // OpenAI API requires tool_call_id for role "tool"
@@ -501,4 +512,25 @@ mod tests {
             .build_request(&messages, "gpt-4o", None, None, false, None)
             .is_err());
     }
+
+    #[test]
+    fn test_openai_driver_encodes_uploaded_documents_as_file_parts() {
+        use crate::types::message::{ContentBlock, MessageContent, MessageRole};
+
+        let driver = OpenAiDriver::new("openai", vec![Capability::Text]);
+        let messages = vec![Message::with_content(
+            MessageRole::User,
+            MessageContent::blocks(vec![
+                ContentBlock::text("Summarize"),
+                ContentBlock::document_file("file-abc".into(), None, None),
+            ]),
+        )];
+        let req = driver
+            .build_request(&messages, "gpt-4o", None, None, false, None)
+            .unwrap();
+        let content = &req.body["messages"][0]["content"];
+        assert_eq!(content[0]["text"], "Summarize");
+        assert_eq!(content[1]["type"], "file");
+        assert_eq!(content[1]["file"]["file_id"], "file-abc");
+    }
 }
@@ -0,0 +1,97 @@
+//! Request planning for [`EmbeddingClient::embed_batch`](super::EmbeddingClient::embed_batch):
+//! token estimates, over-long input splitting and sub-batch packing.
+
+use std::ops::Range;
+
+/// Rough token estimate (~4 UTF-8 bytes per token), matching the context assembler.
+pub(crate) fn estimate_tokens(text: &str) -> u32 {
+    text.len().div_ceil(4) as u32
+}
+
+/// Split `text` into pieces of at most `max_tokens` (estimated), preferring whitespace
+/// boundaries and never splitting inside a UTF-8 character.
+pub(crate) fn split_text(text: &str, max_tokens: u32) -> Vec<&str> {
+    let max_bytes = (max_tokens.max(1) as usize) * 4;
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.len() > max_bytes {
+        let mut cut = max_bytes;
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if let Some(space) = rest[..cut].rfind(char::is_whitespace) {
+            if space > 0 {
+                cut = space;
+            }
+        }
+        if cut == 0 {
+            cut = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        let (piece, tail) = rest.split_at(cut);
+        if !piece.trim().is_empty() {
+            pieces.push(piece);
+        }
+        rest = tail.trim_start();
+    }
+    if !rest.trim().is_empty() || pieces.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// Pack items (given their token counts) into consecutive sub-batches of at most
+/// `max_batch_size` items and, when set, `max_request_tokens` tokens.
+pub(crate) fn plan_batches(
+    tokens: &[u32],
+    max_batch_size: usize,
+    max_request_tokens: Option<u32>,
+) -> Vec<Range<usize>> {
+    let max_batch_size = max_batch_size.max(1);
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut used = 0u32;
+    for (i, &t) in tokens.iter().enumerate() {
+        let full = i - start >= max_batch_size
+            || max_request_tokens.is_some_and(|max| i > start && used.saturating_add(t) > max);
+        if full {
+            batches.push(start..i);
+            start = i;
+            used = 0;
+        }
+        used = used.saturating_add(t);
+    }
+    if start < tokens.len() {
+        batches.push(start..tokens.len());
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_text_prefers_whitespace_and_char_boundaries() {
+        let text = "one two three four five six";
+        let pieces = split_text(text, 3);
+        assert!(pieces.iter().all(|p| estimate_tokens(p) <= 3), "{pieces:?}");
+        assert_eq!(pieces.join(" "), text);
+
+        let cjk = "退货政策是三十天内全额退款";
+        let pieces = split_text(cjk, 2);
+        assert!(pieces.len() > 1);
+        assert_eq!(pieces.concat(), cjk);
+
+        assert_eq!(split_text("short", 10), ["short"]);
+        assert_eq!(split_text("", 10), [""]);
+    }
+
+    #[test]
+    fn test_plan_batches_respects_count_and_token_limits() {
+        assert_eq!(plan_batches(&[1; 5], 2, None), [0..2, 2..4, 4..5]);
+        assert_eq!(plan_batches(&[4, 4, 4, 1], 10, Some(8)), [0..2, 2..4]);
+        // An item above the request limit still goes out, alone.
+        assert_eq!(plan_batches(&[2, 20, 2], 10, Some(8)), [0..1, 1..2, 2..3]);
+        assert!(plan_batches(&[], 10, None).is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+//! Content-addressed embedding cache used by [`EmbeddingClient`](super::EmbeddingClient).
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+
+/// Storage for embeddings keyed by [`embedding_cache_key`].
+///
+/// Implement this to back the cache with Redis, SQLite, a file, …; the client only
+/// calls `get` before a request and `put` after a successful one.
+pub trait EmbeddingCache: Send + Sync {
+    fn get(&self, key: &str) -> Option<Vec<f32>>;
+    fn put(&self, key: &str, vector: &[f32]);
+}
+
+/// Cache key for `text` embedded by `model` at `dimensions`: hex SHA-256, so the same
+/// text maps to the same key across processes.
+pub fn embedding_cache_key(model: &str, dimensions: Option<usize>, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update([0]);
+    hasher.update(dimensions.unwrap_or(0).to_le_bytes());
+    hasher.update(text.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Bounded in-process LRU [`EmbeddingCache`] with hit/miss counters.
+#[derive(Debug)]
+pub struct InMemoryEmbeddingCache {
+    entries: Mutex<LruCache<String, Vec<f32>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl InMemoryEmbeddingCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|e| e.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+impl EmbeddingCache for InMemoryEmbeddingCache {
+    fn get(&self, key: &str) -> Option<Vec<f32>> {
+        let found = self.entries.lock().ok()?.get(key).cloned();
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    fn put(&self, key: &str, vector: &[f32]) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.put(key.to_string(), vector.to_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_depend_on_model_dimensions_and_text() {
+        let key = embedding_cache_key("m", None, "hello");
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, embedding_cache_key("m", None, "hello"));
+        assert_ne!(key, embedding_cache_key("m2", None, "hello"));
+        assert_ne!(key, embedding_cache_key("m", Some(256), "hello"));
+        assert_ne!(key, embedding_cache_key("m", None, "hello!"));
+    }
+
+    #[test]
+    fn test_lru_evicts_and_counts() {
+        let cache = InMemoryEmbeddingCache::new(2);
+        cache.put("a", &[1.0]);
+        cache.put("b", &[2.0]);
+        assert_eq!(cache.get("a"), Some(vec![1.0]));
+        cache.put("c", &[3.0]);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+    }
+}
@@ -4,11 +4,87 @@
 //! [`EmbeddingClientBuilder::from_model`]. Base URL and credentials come from the
 //! provider manifest ([ARCH-001]); there is no silent default to a vendor host.
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::{StreamExt, TryStreamExt};
+
+use super::batching::{estimate_tokens, plan_batches, split_text};
+use super::cache::{embedding_cache_key, EmbeddingCache};
+use super::store::{Document, MetadataFilter, SearchHit, VectorStore};
 use super::types::{Embedding, EmbeddingRequest, EmbeddingResponse, EmbeddingUsage};
+use super::vectors::{normalize_vector, weighted_average_vectors};
 use crate::credentials::{self, resolve_credential};
 use crate::protocol::{ProtocolLoader, ProtocolManifest};
 use crate::{Error, ErrorContext, Result};
 
+/// What [`EmbeddingClient::embed_batch`] does with an input above the per-input token
+/// limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlongInputPolicy {
+    /// Fail the call, naming the offending input.
+    #[default]
+    Error,
+    /// Split the input into limit-sized pieces, embed each, and return their
+    /// token-weighted mean re-normalised to unit length.
+    ChunkAndPool,
+}
+
+/// Request limits for an embedding endpoint.
+///
+/// Read by [`EmbeddingClientBuilder::from_manifest`] from the model's
+/// `metadata.models.<id>` entry, falling back to a top-level `embeddings` block:
+///
+/// ```yaml
+/// embeddings:
+///   max_batch_size: 2048
+///   max_input_tokens: 8191
+///   max_request_tokens: 300000
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddingLimits {
+    /// Inputs per request.
+    pub max_batch_size: usize,
+    /// Tokens per input.
+    pub max_input_tokens: Option<u32>,
+    /// Tokens across all inputs of one request.
+    pub max_request_tokens: Option<u32>,
+}
+
+impl Default for EmbeddingLimits {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 100,
+            max_input_tokens: None,
+            max_request_tokens: None,
+        }
+    }
+}
+
+impl EmbeddingLimits {
+    pub fn from_manifest(manifest: &ProtocolManifest, model_id: &str) -> Self {
+        let model_entry = manifest
+            .extra
+            .get("metadata")
+            .and_then(|m| m.get("models"))
+            .and_then(|m| m.get(model_id));
+        let block = manifest.extra.get("embeddings");
+        let read = |key: &str| {
+            model_entry
+                .and_then(|e| e.get(key))
+                .or_else(|| block.and_then(|b| b.get(key)))
+                .and_then(serde_json::Value::as_u64)
+                .filter(|v| *v > 0)
+        };
+        let defaults = Self::default();
+        Self {
+            max_batch_size: read("max_batch_size").map_or(defaults.max_batch_size, |v| v as usize),
+            max_input_tokens: read("max_input_tokens").map(|v| v as u32),
+            max_request_tokens: read("max_request_tokens").map(|v| v as u32),
+        }
+    }
+}
+
 pub struct EmbeddingClient {
     http_client: reqwest::Client,
     model: String,
@@ -16,7 +92,18 @@ pub struct EmbeddingClient {
     endpoint_path: String,
     api_key: String,
     dimensions: Option<usize>,
-    max_batch_size: usize,
+    limits: EmbeddingLimits,
+    max_concurrency: usize,
+    overlong: OverlongInputPolicy,
+    cache: Option<Arc<dyn EmbeddingCache>>,
+}
+
+/// One provider input: a whole unique text or one piece of an over-long one.
+struct WorkItem<'a> {
+    /// Index into the unique pending texts.
+    owner: usize,
+    text: &'a str,
+    tokens: u32,
 }
 
 impl EmbeddingClient {
@@ -25,37 +112,185 @@ impl EmbeddingClient {
     }
 
     pub async fn embed(&self, text: &str) -> Result<EmbeddingResponse> {
-        let request = EmbeddingRequest::single(&self.model, text);
-        self.execute(request).await
+        self.embed_batch(&[text]).await
     }
 
+    /// Embed `texts`, returning one embedding per input in input order.
+    ///
+    /// Cached and duplicate texts are embedded once; the rest are packed into
+    /// sub-batches under [`EmbeddingLimits`] and sent up to `max_concurrency` at a
+    /// time. Inputs over `max_input_tokens` follow the [`OverlongInputPolicy`].
     pub async fn embed_batch(&self, texts: &[impl AsRef<str>]) -> Result<EmbeddingResponse> {
-        let texts: Vec<String> = texts.iter().map(|t| t.as_ref().to_string()).collect();
-        if texts.len() <= self.max_batch_size {
-            return self
-                .execute(EmbeddingRequest::batch(&self.model, texts))
-                .await;
+        let texts: Vec<&str> = texts.iter().map(|t| t.as_ref()).collect();
+        let mut vectors: Vec<Option<Vec<f32>>> = vec![None; texts.len()];
+
+        // Unique uncached texts, in first-occurrence order, with the inputs they fill.
+        let mut pending: Vec<(&str, Vec<usize>)> = Vec::new();
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for (i, text) in texts.iter().enumerate() {
+            if let Some(&p) = seen.get(text) {
+                pending[p].1.push(i);
+                continue;
+            }
+            if let Some(hit) = self
+                .cache
+                .as_ref()
+                .and_then(|c| c.get(&self.cache_key(text)))
+            {
+                vectors[i] = Some(hit);
+                continue;
+            }
+            seen.insert(text, pending.len());
+            pending.push((text, vec![i]));
         }
-        let mut all_embeddings: Vec<Embedding> = Vec::new();
-        let mut total_usage = EmbeddingUsage::default();
-        for (batch_idx, chunk) in texts.chunks(self.max_batch_size).enumerate() {
-            let response = self
-                .execute(EmbeddingRequest::batch(&self.model, chunk.to_vec()))
-                .await?;
-            let offset = batch_idx * self.max_batch_size;
-            for mut emb in response.embeddings {
-                emb.index += offset;
-                all_embeddings.push(emb);
+
+        let mut items = Vec::with_capacity(pending.len());
+        for (owner, (text, inputs)) in pending.iter().enumerate() {
+            let tokens = estimate_tokens(text);
+            match self.limits.max_input_tokens {
+                Some(max) if tokens > max => match self.overlong {
+                    OverlongInputPolicy::Error => {
+                        return Err(Error::validation_with_context(
+                            format!(
+                                "embedding input {} is ~{tokens} tokens, above the {max}-token limit of {}",
+                                inputs[0], self.model
+                            ),
+                            ErrorContext::new()
+                                .with_source("embeddings")
+                                .with_field_path(format!("input[{}]", inputs[0]))
+                                .with_hint(
+                                    "split the text, or build the client with \
+                                     .overlong_inputs(OverlongInputPolicy::ChunkAndPool)",
+                                ),
+                        ));
+                    }
+                    OverlongInputPolicy::ChunkAndPool => {
+                        items.extend(split_text(text, max).into_iter().map(|piece| WorkItem {
+                            owner,
+                            text: piece,
+                            tokens: estimate_tokens(piece),
+                        }));
+                    }
+                },
+                _ => items.push(WorkItem {
+                    owner,
+                    text,
+                    tokens,
+                }),
             }
-            total_usage.add(&response.usage);
         }
+
+        let tokens: Vec<u32> = items.iter().map(|i| i.tokens).collect();
+        let batches = plan_batches(
+            &tokens,
+            self.limits.max_batch_size,
+            self.limits.max_request_tokens,
+        );
+        // Items of one pending text are contiguous and in pending order.
+        let mut spans = Vec::with_capacity(pending.len());
+        let mut start = 0;
+        for owner in 0..pending.len() {
+            let end = start
+                + items[start..]
+                    .iter()
+                    .take_while(|i| i.owner == owner)
+                    .count();
+            spans.push(start..end);
+            start = end;
+        }
+        let mut remaining: Vec<usize> = spans.iter().map(|s| s.len()).collect();
+
+        let items = &items;
+        let mut responses = futures::stream::iter(batches)
+            .map(|range| async move {
+                let inputs: Vec<String> = items[range.clone()]
+                    .iter()
+                    .map(|i| i.text.to_string())
+                    .collect();
+                let request = match inputs.len() {
+                    1 => EmbeddingRequest::single(&self.model, inputs[0].clone()),
+                    _ => EmbeddingRequest::batch(&self.model, inputs),
+                };
+                let response = self.execute(request).await?;
+                if response.embeddings.len() != range.len() {
+                    return Err(Error::api_with_context(
+                        format!(
+                            "embedding response has {} vectors for {} inputs",
+                            response.embeddings.len(),
+                            range.len()
+                        ),
+                        ErrorContext::new().with_source("embeddings"),
+                    ));
+                }
+                Ok::<_, Error>((range, response))
+            })
+            .buffer_unordered(self.max_concurrency.max(1));
+
+        // Texts are cached as soon as their last sub-batch lands, so a failing
+        // sub-batch does not discard (and later re-bill) the ones that succeeded.
+        let mut usage = EmbeddingUsage::default();
+        let mut item_vectors: Vec<Option<Vec<f32>>> = vec![None; items.len()];
+        let mut text_vectors: Vec<Option<Vec<f32>>> = vec![None; pending.len()];
+        while let Some((range, response)) = responses.try_next().await? {
+            usage.add(&response.usage);
+            for emb in response.embeddings {
+                if emb.index < range.len() {
+                    item_vectors[range.start + emb.index] = Some(emb.vector);
+                }
+            }
+            if item_vectors[range.clone()].iter().any(Option::is_none) {
+                return Err(Error::api_with_context(
+                    "embedding response is missing vectors for some inputs",
+                    ErrorContext::new().with_source("embeddings"),
+                ));
+            }
+            for k in range {
+                let owner = items[k].owner;
+                remaining[owner] -= 1;
+                if remaining[owner] > 0 {
+                    continue;
+                }
+                let span = spans[owner].clone();
+                let mut parts: Vec<Vec<f32>> = item_vectors[span.clone()]
+                    .iter_mut()
+                    .filter_map(Option::take)
+                    .collect();
+                let vector = if parts.len() == 1 {
+                    parts.remove(0)
+                } else {
+                    let weights: Vec<f32> =
+                        items[span].iter().map(|i| i.tokens.max(1) as f32).collect();
+                    normalize_vector(&weighted_average_vectors(&parts, &weights)?)
+                };
+                if let Some(cache) = &self.cache {
+                    cache.put(&self.cache_key(pending[owner].0), &vector);
+                }
+                text_vectors[owner] = Some(vector);
+            }
+        }
+
+        for ((_, inputs), vector) in pending.iter().zip(text_vectors) {
+            for &i in inputs {
+                vectors[i].clone_from(&vector);
+            }
+        }
+
+        let embeddings = vectors
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| Embedding::new(i, v.unwrap_or_default()))
+            .collect();
         Ok(EmbeddingResponse::new(
-            all_embeddings,
+            embeddings,
             self.model.clone(),
-            total_usage,
+            usage,
         ))
     }
 
+    fn cache_key(&self, text: &str) -> String {
+        embedding_cache_key(&self.model, self.dimensions, text)
+    }
+
     async fn execute(&self, mut request: EmbeddingRequest) -> Result<EmbeddingResponse> {
         if let Some(dims) = self.dimensions {
             request = request.with_dimensions(dims);
@@ -95,6 +330,67 @@ impl EmbeddingClient {
     pub fn model(&self) -> &str {
         &self.model
     }
+
+    pub fn limits(&self) -> &EmbeddingLimits {
+        &self.limits
+    }
+
+    /// Embed `documents` (batched like [`embed_batch`](Self::embed_batch)) and upsert
+    /// them into `store`, keeping each document's text alongside its metadata.
+    pub async fn index_documents(
+        &self,
+        store: &mut VectorStore,
+        documents: &[Document],
+    ) -> Result<EmbeddingUsage> {
+        if documents.is_empty() {
+            return Ok(EmbeddingUsage::default());
+        }
+        let texts: Vec<&str> = documents.iter().map(|d| d.text.as_str()).collect();
+        let response = self.embed_batch(&texts).await?;
+        if response.embeddings.len() != documents.len() {
+            return Err(Error::api_with_context(
+                format!(
+                    "embedding response has {} vectors for {} documents",
+                    response.embeddings.len(),
+                    documents.len()
+                ),
+                ErrorContext::new().with_source("embeddings"),
+            ));
+        }
+        for emb in &response.embeddings {
+            let doc = documents.get(emb.index).ok_or_else(|| {
+                Error::api_with_context(
+                    format!("embedding index {} out of range", emb.index),
+                    ErrorContext::new().with_source("embeddings"),
+                )
+            })?;
+            store.upsert(
+                doc.id.clone(),
+                &emb.vector,
+                doc.metadata.clone(),
+                Some(doc.text.clone()),
+            )?;
+        }
+        Ok(response.usage)
+    }
+
+    /// Embed `query` and return its `k` nearest records in `store`.
+    pub async fn search(
+        &self,
+        store: &VectorStore,
+        query: &str,
+        k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>> {
+        let response = self.embed(query).await?;
+        let vector = response.first().ok_or_else(|| {
+            Error::api_with_context(
+                "embedding response is empty",
+                ErrorContext::new().with_source("embeddings"),
+            )
+        })?;
+        store.search(&vector.vector, k, filter)
+    }
 }
 
 fn join_url(base: &str, path: &str) -> String {
@@ -128,7 +424,13 @@ pub struct EmbeddingClientBuilder {
     base_url: Option<String>,
     endpoint_path: Option<String>,
     dimensions: Option<usize>,
-    max_batch_size: usize,
+    manifest_limits: EmbeddingLimits,
+    max_batch_size: Option<usize>,
+    max_input_tokens: Option<u32>,
+    max_request_tokens: Option<u32>,
+    max_concurrency: usize,
+    overlong: OverlongInputPolicy,
+    cache: Option<Arc<dyn EmbeddingCache>>,
     timeout_secs: u64,
     protocol_path: Option<String>,
 }
@@ -141,7 +443,13 @@ impl EmbeddingClientBuilder {
             base_url: None,
             endpoint_path: None,
             dimensions: None,
-            max_batch_size: 100,
+            manifest_limits: EmbeddingLimits::default(),
+            max_batch_size: None,
+            max_input_tokens: None,
+            max_request_tokens: None,
+            max_concurrency: 4,
+            overlong: OverlongInputPolicy::Error,
+            cache: None,
             timeout_secs: 60,
             protocol_path: None,
         }
@@ -166,6 +474,35 @@ impl EmbeddingClientBuilder {
         self.dimensions = Some(dimensions);
         self
     }
+    /// Inputs per request; overrides the manifest limit.
+    pub fn max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = Some(size);
+        self
+    }
+    /// Estimated tokens per input; overrides the manifest limit.
+    pub fn max_input_tokens(mut self, tokens: u32) -> Self {
+        self.max_input_tokens = Some(tokens);
+        self
+    }
+    /// Estimated tokens per request; overrides the manifest limit.
+    pub fn max_request_tokens(mut self, tokens: u32) -> Self {
+        self.max_request_tokens = Some(tokens);
+        self
+    }
+    /// Sub-batch requests in flight at once (default 4).
+    pub fn max_concurrency(mut self, requests: usize) -> Self {
+        self.max_concurrency = requests.max(1);
+        self
+    }
+    pub fn overlong_inputs(mut self, policy: OverlongInputPolicy) -> Self {
+        self.overlong = policy;
+        self
+    }
+    /// Skip texts already embedded by this model, keyed by [`embedding_cache_key`].
+    pub fn cache(mut self, cache: Arc<dyn EmbeddingCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
     pub fn protocol_path(mut self, path: impl Into<String>) -> Self {
         self.protocol_path = Some(path.into());
         self
@@ -194,7 +531,9 @@ impl EmbeddingClientBuilder {
         if self.endpoint_path.is_none() {
             self.endpoint_path = Some(embeddings_path_from_manifest(manifest));
         }
-        self.model = Some(model_id.into());
+        let model_id = model_id.into();
+        self.manifest_limits = EmbeddingLimits::from_manifest(manifest, &model_id);
+        self.model = Some(model_id);
         Ok(self)
     }
 
@@ -244,7 +583,20 @@ impl EmbeddingClientBuilder {
             endpoint_path,
             api_key,
             dimensions: self.dimensions,
-            max_batch_size: self.max_batch_size,
+            limits: EmbeddingLimits {
+                max_batch_size: self
+                    .max_batch_size
+                    .unwrap_or(self.manifest_limits.max_batch_size),
+                max_input_tokens: self
+                    .max_input_tokens
+                    .or(self.manifest_limits.max_input_tokens),
+                max_request_tokens: self
+                    .max_request_tokens
+                    .or(self.manifest_limits.max_request_tokens),
+            },
+            max_concurrency: self.max_concurrency,
+            overlong: self.overlong,
+            cache: self.cache,
         })
     }
 }
@@ -292,6 +644,31 @@ mod tests {
         std::env::remove_var("TESTPROV_API_KEY");
     }
 
+    #[test]
+    fn limits_prefer_model_entry_over_embeddings_block() {
+        let mut m = minimal_manifest("https://example.test/v1");
+        m.extra.insert(
+            "embeddings".into(),
+            serde_json::json!({ "max_batch_size": 2048, "max_input_tokens": 8191 }),
+        );
+        m.extra.insert(
+            "metadata".into(),
+            serde_json::json!({ "models": { "emb-small": { "max_input_tokens": 512 } } }),
+        );
+        let small = EmbeddingLimits::from_manifest(&m, "emb-small");
+        assert_eq!(small.max_batch_size, 2048);
+        assert_eq!(small.max_input_tokens, Some(512));
+        assert_eq!(small.max_request_tokens, None);
+        assert_eq!(
+            EmbeddingLimits::from_manifest(&m, "other").max_input_tokens,
+            Some(8191)
+        );
+        assert_eq!(
+            EmbeddingLimits::from_manifest(&minimal_manifest("https://x"), "emb"),
+            EmbeddingLimits::default()
+        );
+    }
+
     #[tokio::test]
     async fn build_without_base_url_errors() {
         let res = EmbeddingClient::builder()
@@ -22,16 +22,23 @@
 //! | **Vector similarity** | Cosine, Euclidean, Manhattan, Dot product metrics |
 //! | **Vector operations** | Normalize, average, add, subtract, scale vectors |
 //! | **Similarity search** | Find most similar vectors in a collection |
+//! | **Vector store** | HNSW index with metadata filters and mmap-able persistence |
+//! | **Batch planning** | Splits by count/token limits, concurrent sub-batches, content-hash cache |
 //!
 //! ## Components
 //!
 //! | Component | Description |
 //! |-----------|-------------|
 //! | [`EmbeddingClient`] | Client for generating embeddings from AI providers |
+//! | [`EmbeddingLimits`] | Per-request input count and token limits (from the manifest or builder) |
+//! | [`OverlongInputPolicy`] | Reject or chunk-and-pool inputs above the per-input token limit |
+//! | [`InMemoryEmbeddingCache`] | LRU [`EmbeddingCache`] that skips already-embedded texts |
 //! | [`cosine_similarity`] | Cosine similarity between vectors (-1 to 1) |
 //! | [`euclidean_distance`] | Euclidean (L2) distance between vectors |
 //! | [`find_most_similar`] | Find top-k most similar vectors |
 //! | [`normalize_vector`] | Normalize vector to unit length |
+//! | [`VectorStore`] | HNSW approximate nearest-neighbour index with upsert/delete |
+//! | [`MetadataFilter`] | Eq / In / Range / Exists predicates over record metadata |
 //!
 //! ## Example
 //!
@@ -53,6 +60,52 @@
 //! let results = find_most_similar(&query, &candidates, 1, SimilarityMetric::Cosine).unwrap();
 //! ```
 //!
+//! ## Indexing documents
+//!
+//! ```rust,no_run
+//! use ai_lib_core::embeddings::{Document, EmbeddingClient, MetadataFilter, VectorStore};
+//! use serde_json::json;
+//!
+//! # async fn demo(client: &EmbeddingClient) -> ai_lib_core::Result<()> {
+//! let mut store = VectorStore::default(); // cosine
+//! client
+//!     .index_documents(&mut store, &[
+//!         Document::new("faq-1", "Orders ship within 2 days").with_metadata(json!({ "lang": "en" })),
+//!         Document::new("faq-2", "Les commandes partent sous 2 jours").with_metadata(json!({ "lang": "fr" })),
+//!     ])
+//!     .await?;
+//! store.save("faq.vstore")?;
+//!
+//! let store = VectorStore::open_mmap("faq.vstore")?;
+//! let hits = client
+//!     .search(&store, "when will my order ship?", 3, Some(&MetadataFilter::eq("lang", "en")))
+//!     .await?;
+//! # Ok(()) }
+//! ```
+//!
+//! ## Large batches
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//! use ai_lib_core::embeddings::{EmbeddingClient, InMemoryEmbeddingCache, OverlongInputPolicy};
+//!
+//! # async fn demo(texts: Vec<String>) -> ai_lib_core::Result<()> {
+//! // from_manifest/from_model read these limits from the manifest; explicit values win.
+//! let client = EmbeddingClient::builder()
+//!     .model("text-embedding-3-small")
+//!     .api_key("sk-...")
+//!     .base_url("https://api.openai.com/v1")
+//!     .max_input_tokens(8191)
+//!     .max_request_tokens(300_000)
+//!     .max_concurrency(8)
+//!     .overlong_inputs(OverlongInputPolicy::ChunkAndPool)
+//!     .cache(Arc::new(InMemoryEmbeddingCache::new(10_000)))
+//!     .build()
+//!     .await?;
+//! let response = client.embed_batch(&texts).await?; // one vector per text, in order
+//! # let _ = response; Ok(()) }
+//! ```
+//!
 //! ## Metrics Comparison
 //!
 //! | Metric | Range | Best For |
@@ -62,11 +115,18 @@
 //! | Dot Product | -∞ to ∞ | Magnitude-sensitive comparison |
 //! | Manhattan | 0 to ∞ | Sparse vectors, grid distances |
 
+mod batching;
+mod cache;
 mod client;
+mod store;
 mod types;
 mod vectors;
 
-pub use client::{EmbeddingClient, EmbeddingClientBuilder};
+pub use cache::{embedding_cache_key, EmbeddingCache, InMemoryEmbeddingCache};
+pub use client::{EmbeddingClient, EmbeddingClientBuilder, EmbeddingLimits, OverlongInputPolicy};
+pub use store::{
+    Document, MetadataFilter, SearchHit, VectorRecord, VectorStore, VectorStoreConfig,
+};
 pub use types::{
     Embedding, EmbeddingInput, EmbeddingModel, EmbeddingRequest, EmbeddingResponse, EmbeddingUsage,
 };
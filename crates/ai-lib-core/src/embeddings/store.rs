@@ -0,0 +1,1201 @@
+//! In-process vector store with an HNSW approximate nearest-neighbour index.
+//!
+//! Records carry an id, a vector, JSON metadata and optional source text. Search
+//! walks the HNSW graph (Malkov & Yashunin) and falls back to an exact scan for
+//! small stores or selective metadata filters. Deletes are tombstones that keep the
+//! graph navigable; [`VectorStore::compact`] rebuilds without them.
+//!
+//! Stores persist to a single binary file: [`VectorStore::load`] reads it into memory,
+//! [`VectorStore::open_mmap`] maps the vector section instead of copying it (copied on
+//! first write).
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::vectors::{normalize_vector, SimilarityMetric};
+use crate::{Error, ErrorContext, Result};
+
+const MAGIC: &[u8; 8] = b"AIVSTORE";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: usize = 64;
+const MAX_LEVEL: usize = 16;
+
+/// HNSW and search parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VectorStoreConfig {
+    pub metric: SimilarityMetric,
+    /// Links per node on upper layers (layer 0 keeps `2 * m`).
+    pub m: usize,
+    /// Candidate list size while inserting.
+    pub ef_construction: usize,
+    /// Candidate list size while searching (raised to `k` when smaller).
+    pub ef_search: usize,
+    /// Stores with at most this many live records are searched exactly.
+    pub exact_search_below: usize,
+}
+
+impl Default for VectorStoreConfig {
+    fn default() -> Self {
+        Self {
+            metric: SimilarityMetric::Cosine,
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+            exact_search_below: 256,
+        }
+    }
+}
+
+impl VectorStoreConfig {
+    pub fn new(metric: SimilarityMetric) -> Self {
+        Self {
+            metric,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_m(mut self, m: usize) -> Self {
+        self.m = m.max(2);
+        self
+    }
+
+    pub fn with_ef_construction(mut self, ef: usize) -> Self {
+        self.ef_construction = ef.max(1);
+        self
+    }
+
+    pub fn with_ef_search(mut self, ef: usize) -> Self {
+        self.ef_search = ef.max(1);
+        self
+    }
+
+    pub fn with_exact_search_below(mut self, n: usize) -> Self {
+        self.exact_search_below = n;
+        self
+    }
+}
+
+/// Predicate over record metadata. Keys are dotted paths (`source.lang`); `Eq` on an
+/// array field matches when the array contains the value (tags).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataFilter {
+    Eq {
+        key: String,
+        value: Value,
+    },
+    In {
+        key: String,
+        values: Vec<Value>,
+    },
+    Range {
+        key: String,
+        #[serde(default)]
+        gte: Option<f64>,
+        #[serde(default)]
+        lte: Option<f64>,
+    },
+    Exists {
+        key: String,
+    },
+    And(Vec<MetadataFilter>),
+    Or(Vec<MetadataFilter>),
+    Not(Box<MetadataFilter>),
+}
+
+impl MetadataFilter {
+    pub fn eq(key: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Eq {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn one_of(key: impl Into<String>, values: Vec<Value>) -> Self {
+        Self::In {
+            key: key.into(),
+            values,
+        }
+    }
+
+    pub fn range(key: impl Into<String>, gte: Option<f64>, lte: Option<f64>) -> Self {
+        Self::Range {
+            key: key.into(),
+            gte,
+            lte,
+        }
+    }
+
+    pub fn exists(key: impl Into<String>) -> Self {
+        Self::Exists { key: key.into() }
+    }
+
+    pub fn and(self, other: MetadataFilter) -> Self {
+        match self {
+            Self::And(mut all) => {
+                all.push(other);
+                Self::And(all)
+            }
+            first => Self::And(vec![first, other]),
+        }
+    }
+
+    pub fn or(self, other: MetadataFilter) -> Self {
+        match self {
+            Self::Or(mut any) => {
+                any.push(other);
+                Self::Or(any)
+            }
+            first => Self::Or(vec![first, other]),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+
+    pub fn matches(&self, metadata: &Value) -> bool {
+        let field = |key: &str| {
+            key.split('.')
+                .try_fold(metadata, |v, part| v.get(part))
+                .filter(|v| !v.is_null())
+        };
+        let equals = |field: &Value, value: &Value| match field {
+            Value::Array(items) if !value.is_array() => items.contains(value),
+            other => other == value,
+        };
+        match self {
+            Self::Eq { key, value } => field(key).is_some_and(|f| equals(f, value)),
+            Self::In { key, values } => {
+                field(key).is_some_and(|f| values.iter().any(|v| equals(f, v)))
+            }
+            Self::Range { key, gte, lte } => field(key).and_then(Value::as_f64).is_some_and(|x| {
+                gte.map_or(true, |min| x >= min) && lte.map_or(true, |max| x <= max)
+            }),
+            Self::Exists { key } => field(key).is_some(),
+            Self::And(all) => all.iter().all(|f| f.matches(metadata)),
+            Self::Or(any) => any.iter().any(|f| f.matches(metadata)),
+            Self::Not(inner) => !inner.matches(metadata),
+        }
+    }
+}
+
+/// One search result. `score` follows [`find_most_similar`](super::find_most_similar):
+/// similarity for cosine / dot product (higher is closer), distance for euclidean /
+/// manhattan (lower is closer).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub score: f32,
+    pub metadata: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Text to embed and index via [`EmbeddingClient::index_documents`](super::EmbeddingClient::index_documents).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+impl Document {
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+            metadata: Value::Object(Default::default()),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+/// A stored record, as returned by [`VectorStore::get`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorRecord<'a> {
+    pub id: &'a str,
+    /// Unit-normalised when the metric is cosine.
+    pub vector: &'a [f32],
+    pub metadata: &'a Value,
+    pub text: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NodeMeta {
+    id: String,
+    #[serde(default)]
+    metadata: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    deleted: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    meta: NodeMeta,
+    /// Neighbour slots per layer (`links[0]` is the base layer).
+    links: Vec<Vec<u32>>,
+}
+
+enum Vectors {
+    Owned(Vec<f32>),
+    #[cfg(target_endian = "little")]
+    Mapped {
+        map: memmap2::Mmap,
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl Vectors {
+    fn as_slice(&self) -> &[f32] {
+        match self {
+            Self::Owned(v) => v,
+            #[cfg(target_endian = "little")]
+            Self::Mapped { map, offset, len } => {
+                let bytes = &map[*offset..*offset + len * 4];
+                // SAFETY: every bit pattern is a valid f32; `align_to` only yields the
+                // aligned middle part, and the offset (64) keeps the section aligned.
+                let (prefix, floats, _) = unsafe { bytes.align_to::<f32>() };
+                debug_assert!(prefix.is_empty());
+                floats
+            }
+        }
+    }
+
+    /// Switch a mapped store to owned storage before mutating it.
+    fn make_owned(&mut self) -> &mut Vec<f32> {
+        #[cfg(target_endian = "little")]
+        if let Self::Mapped { .. } = self {
+            *self = Self::Owned(self.as_slice().to_vec());
+        }
+        match self {
+            Self::Owned(v) => v,
+            #[cfg(target_endian = "little")]
+            Self::Mapped { .. } => unreachable!("converted above"),
+        }
+    }
+}
+
+impl std::fmt::Debug for Vectors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Owned(v) => write!(f, "Owned({} floats)", v.len()),
+            #[cfg(target_endian = "little")]
+            Self::Mapped { len, .. } => write!(f, "Mapped({len} floats)"),
+        }
+    }
+}
+
+/// Total order over f32 distances for the search heaps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Dist(f32);
+
+impl Eq for Dist {}
+
+impl PartialOrd for Dist {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Dist {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Vector store with HNSW search, metadata filtering and on-disk persistence.
+#[derive(Debug)]
+pub struct VectorStore {
+    config: VectorStoreConfig,
+    /// Fixed by the first inserted vector.
+    dims: usize,
+    vectors: Vectors,
+    nodes: Vec<Node>,
+    /// Live id → slot.
+    ids: HashMap<String, u32>,
+    entry: Option<u32>,
+    max_level: usize,
+    deleted: usize,
+    rng: u64,
+}
+
+impl Default for VectorStore {
+    fn default() -> Self {
+        Self::new(VectorStoreConfig::default())
+    }
+}
+
+impl VectorStore {
+    pub fn new(config: VectorStoreConfig) -> Self {
+        Self {
+            config,
+            dims: 0,
+            vectors: Vectors::Owned(Vec::new()),
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            entry: None,
+            max_level: 0,
+            deleted: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub fn with_metric(metric: SimilarityMetric) -> Self {
+        Self::new(VectorStoreConfig::new(metric))
+    }
+
+    pub fn config(&self) -> &VectorStoreConfig {
+        &self.config
+    }
+
+    /// Vector dimensionality (0 until the first insert).
+    pub fn dimensions(&self) -> usize {
+        self.dims
+    }
+
+    /// Number of live records.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains_key(id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.ids.keys().map(String::as_str)
+    }
+
+    pub fn get(&self, id: &str) -> Option<VectorRecord<'_>> {
+        let slot = *self.ids.get(id)?;
+        let node = &self.nodes[slot as usize];
+        Some(VectorRecord {
+            id: &node.meta.id,
+            vector: self.vector(slot),
+            metadata: &node.meta.metadata,
+            text: node.meta.text.as_deref(),
+        })
+    }
+
+    /// Insert or replace a record. Replacing with an identical vector only updates
+    /// metadata and text.
+    pub fn upsert(
+        &mut self,
+        id: impl Into<String>,
+        vector: &[f32],
+        metadata: Value,
+        text: Option<String>,
+    ) -> Result<()> {
+        let id = id.into();
+        if vector.is_empty() {
+            return Err(dimension_error(format!("vector for '{id}' is empty")));
+        }
+        if self.dims == 0 {
+            self.dims = vector.len();
+        } else if vector.len() != self.dims {
+            return Err(dimension_error(format!(
+                "vector for '{id}' has {} dimensions, store has {}",
+                vector.len(),
+                self.dims
+            )));
+        }
+        if vector.iter().any(|x| !x.is_finite()) {
+            return Err(dimension_error(format!(
+                "vector for '{id}' contains NaN or infinity"
+            )));
+        }
+        let vector = self.prepare(vector);
+
+        if let Some(&slot) = self.ids.get(&id) {
+            if self.vector(slot) == vector.as_slice() {
+                let meta = &mut self.nodes[slot as usize].meta;
+                meta.metadata = metadata;
+                meta.text = text;
+                return Ok(());
+            }
+            self.tombstone(slot);
+        }
+        let slot = self.nodes.len() as u32;
+        self.vectors.make_owned().extend_from_slice(&vector);
+        self.nodes.push(Node {
+            meta: NodeMeta {
+                id: id.clone(),
+                metadata,
+                text,
+                deleted: false,
+            },
+            links: Vec::new(),
+        });
+        self.ids.insert(id, slot);
+        self.link(slot);
+        self.maybe_compact();
+        Ok(())
+    }
+
+    /// Remove a record; returns whether it existed.
+    pub fn delete(&mut self, id: &str) -> bool {
+        let Some(&slot) = self.ids.get(id) else {
+            return false;
+        };
+        self.tombstone(slot);
+        self.maybe_compact();
+        true
+    }
+
+    /// Rebuild the index without deleted records.
+    pub fn compact(&mut self) {
+        if self.deleted == 0 {
+            return;
+        }
+        let dims = self.dims;
+        let live: Vec<(Vec<f32>, NodeMeta)> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| !n.meta.deleted)
+            .map(|(slot, n)| (self.vector(slot as u32).to_vec(), n.meta.clone()))
+            .collect();
+        *self = Self {
+            dims,
+            ..Self::new(self.config)
+        };
+        for (vector, meta) in live {
+            let slot = self.nodes.len() as u32;
+            self.vectors.make_owned().extend_from_slice(&vector);
+            self.ids.insert(meta.id.clone(), slot);
+            self.nodes.push(Node {
+                meta,
+                links: Vec::new(),
+            });
+            self.link(slot);
+        }
+    }
+
+    /// `k` nearest live records, optionally restricted by `filter`.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>> {
+        if self.is_empty() || k == 0 {
+            return Ok(Vec::new());
+        }
+        if query.len() != self.dims {
+            return Err(dimension_error(format!(
+                "query has {} dimensions, store has {}",
+                query.len(),
+                self.dims
+            )));
+        }
+        let query = self.prepare(query);
+        let accept = |slot: u32| {
+            let meta = &self.nodes[slot as usize].meta;
+            !meta.deleted && filter.map_or(true, |f| f.matches(&meta.metadata))
+        };
+
+        // Exact scan when the store is small or the filter is selective (< 10% of
+        // records), where graph traversal would visit most nodes anyway.
+        let exact = self.len() <= self.config.exact_search_below
+            || filter.is_some_and(|_| {
+                let matching = (0..self.nodes.len() as u32).filter(|s| accept(*s)).count();
+                matching * 10 < self.len()
+            });
+        let found: Vec<(Dist, u32)> = if exact {
+            let mut all: Vec<(Dist, u32)> = (0..self.nodes.len() as u32)
+                .filter(|s| accept(*s))
+                .map(|s| (Dist(self.distance(&query, self.vector(s))), s))
+                .collect();
+            all.sort();
+            all.truncate(k);
+            all
+        } else {
+            let Some(mut ep) = self.entry else {
+                return Ok(Vec::new());
+            };
+            for layer in (1..=self.max_level).rev() {
+                ep = self.search_layer(&query, ep, 1, layer, |_| true)[0].1;
+            }
+            let mut found = self.search_layer(&query, ep, self.config.ef_search.max(k), 0, accept);
+            found.truncate(k);
+            found
+        };
+
+        Ok(found
+            .into_iter()
+            .map(|(d, slot)| {
+                let meta = &self.nodes[slot as usize].meta;
+                SearchHit {
+                    id: meta.id.clone(),
+                    score: self.score(d.0),
+                    metadata: meta.metadata.clone(),
+                    text: meta.text.clone(),
+                }
+            })
+            .collect())
+    }
+
+    fn vector(&self, slot: u32) -> &[f32] {
+        let start = slot as usize * self.dims;
+        &self.vectors.as_slice()[start..start + self.dims]
+    }
+
+    fn prepare(&self, v: &[f32]) -> Vec<f32> {
+        match self.config.metric {
+            SimilarityMetric::Cosine => normalize_vector(v),
+            _ => v.to_vec(),
+        }
+    }
+
+    /// Internal distance; smaller is closer for every metric.
+    fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        let pairs = a.iter().zip(b);
+        match self.config.metric {
+            SimilarityMetric::Cosine => 1.0 - pairs.map(|(x, y)| x * y).sum::<f32>(),
+            SimilarityMetric::DotProduct => -pairs.map(|(x, y)| x * y).sum::<f32>(),
+            SimilarityMetric::Euclidean => pairs.map(|(x, y)| (x - y) * (x - y)).sum::<f32>(),
+            SimilarityMetric::Manhattan => pairs.map(|(x, y)| (x - y).abs()).sum::<f32>(),
+        }
+    }
+
+    fn score(&self, distance: f32) -> f32 {
+        match self.config.metric {
+            SimilarityMetric::Cosine => 1.0 - distance,
+            SimilarityMetric::DotProduct => -distance,
+            SimilarityMetric::Euclidean => distance.sqrt(),
+            SimilarityMetric::Manhattan => distance,
+        }
+    }
+
+    fn slot_distance(&self, a: u32, b: u32) -> f32 {
+        self.distance(self.vector(a), self.vector(b))
+    }
+
+    fn tombstone(&mut self, slot: u32) {
+        let meta = &mut self.nodes[slot as usize].meta;
+        if !meta.deleted {
+            meta.deleted = true;
+            self.ids.remove(&meta.id);
+            self.deleted += 1;
+        }
+    }
+
+    fn maybe_compact(&mut self) {
+        if self.deleted >= 64 && self.deleted > self.len() {
+            self.compact();
+        }
+    }
+
+    fn random_level(&mut self) -> usize {
+        // xorshift64*: deterministic, dependency-free level sampling.
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        let uniform = (bits as f64 + 1.0) / (1u64 << 53) as f64;
+        let ml = 1.0 / (self.config.m as f64).ln();
+        ((-uniform.ln() * ml) as usize).min(MAX_LEVEL)
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.config.m * 2
+        } else {
+            self.config.m
+        }
+    }
+
+    /// Insert `slot` (vector already stored) into the graph.
+    fn link(&mut self, slot: u32) {
+        let level = self.random_level();
+        self.nodes[slot as usize].links = vec![Vec::new(); level + 1];
+        let Some(mut ep) = self.entry else {
+            self.entry = Some(slot);
+            self.max_level = level;
+            return;
+        };
+        let query = self.vector(slot).to_vec();
+        for layer in (level + 1..=self.max_level).rev() {
+            ep = self.search_layer(&query, ep, 1, layer, |_| true)[0].1;
+        }
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates =
+                self.search_layer(&query, ep, self.config.ef_construction, layer, |_| true);
+            let max = self.max_links(layer);
+            let neighbours = self.select_neighbours(&candidates, max);
+            for &n in &neighbours {
+                let links = &mut self.nodes[n as usize].links[layer];
+                links.push(slot);
+                if links.len() > max {
+                    let mut scored: Vec<(Dist, u32)> = links
+                        .clone()
+                        .into_iter()
+                        .map(|l| (Dist(self.slot_distance(n, l)), l))
+                        .collect();
+                    scored.sort();
+                    self.nodes[n as usize].links[layer] = self.select_neighbours(&scored, max);
+                }
+            }
+            self.nodes[slot as usize].links[layer] = neighbours;
+            ep = candidates[0].1;
+        }
+        if level > self.max_level {
+            self.entry = Some(slot);
+            self.max_level = level;
+        }
+    }
+
+    /// Neighbour selection heuristic: keep a candidate only if it is closer to the
+    /// base than to any already selected neighbour, then top up with the closest rest.
+    fn select_neighbours(&self, candidates: &[(Dist, u32)], max: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(max);
+        let mut skipped = Vec::new();
+        for &(d, c) in candidates {
+            if selected.len() >= max {
+                break;
+            }
+            if selected.iter().all(|&s| self.slot_distance(c, s) > d.0) {
+                selected.push(c);
+            } else {
+                skipped.push(c);
+            }
+        }
+        for c in skipped {
+            if selected.len() >= max {
+                break;
+            }
+            selected.push(c);
+        }
+        selected
+    }
+
+    /// Best-first search on one layer; results (closest first) only include slots
+    /// passing `accept`, but traversal goes through every node.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry: u32,
+        ef: usize,
+        layer: usize,
+        accept: impl Fn(u32) -> bool,
+    ) -> Vec<(Dist, u32)> {
+        let mut visited = HashSet::new();
+        let mut candidates = BinaryHeap::new();
+        let mut results: BinaryHeap<(Dist, u32)> = BinaryHeap::new();
+        let d = Dist(self.distance(query, self.vector(entry)));
+        visited.insert(entry);
+        candidates.push(Reverse((d, entry)));
+        // Upper-layer descent and construction always accept, so the entry point
+        // guarantees a non-empty result there.
+        let mut fallback = (d, entry);
+        if accept(entry) {
+            results.push((d, entry));
+        }
+        while let Some(Reverse((d, current))) = candidates.pop() {
+            if results.len() >= ef && results.peek().is_some_and(|(worst, _)| d > *worst) {
+                break;
+            }
+            let Some(links) = self.nodes[current as usize].links.get(layer) else {
+                continue;
+            };
+            for &n in links {
+                if !visited.insert(n) {
+                    continue;
+                }
+                let dn = Dist(self.distance(query, self.vector(n)));
+                if dn < fallback.0 {
+                    fallback = (dn, n);
+                }
+                if results.len() < ef || results.peek().is_some_and(|(worst, _)| dn < *worst) {
+                    candidates.push(Reverse((dn, n)));
+                    if accept(n) {
+                        results.push((dn, n));
+                        if results.len() > ef {
+                            results.pop();
+                        }
+                    }
+                }
+            }
+        }
+        let mut out = results.into_vec();
+        if out.is_empty() && accept(fallback.1) {
+            out.push(fallback);
+        }
+        out.sort();
+        out
+    }
+
+    /// Write the store to `path` (atomically, via a temporary file).
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let meta: Vec<&NodeMeta> = self.nodes.iter().map(|n| &n.meta).collect();
+        let meta = serde_json::to_vec(&meta)?;
+        let mut graph = Vec::new();
+        for node in &self.nodes {
+            graph.extend_from_slice(&(node.links.len() as u32).to_le_bytes());
+            for links in &node.links {
+                graph.extend_from_slice(&(links.len() as u32).to_le_bytes());
+                for l in links {
+                    graph.extend_from_slice(&l.to_le_bytes());
+                }
+            }
+        }
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        for value in [
+            FORMAT_VERSION,
+            self.dims as u32,
+            metric_code(self.config.metric),
+            self.config.m as u32,
+            self.config.ef_construction as u32,
+            self.config.ef_search as u32,
+            self.nodes.len() as u32,
+            self.entry.unwrap_or(u32::MAX),
+            self.max_level as u32,
+        ] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        header.extend_from_slice(&(graph.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(meta.len() as u64).to_le_bytes());
+        header.resize(HEADER_LEN, 0);
+
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let write = || -> std::io::Result<()> {
+            let mut out = BufWriter::new(std::fs::File::create(&tmp)?);
+            out.write_all(&header)?;
+            for x in self.vectors.as_slice() {
+                out.write_all(&x.to_le_bytes())?;
+            }
+            out.write_all(&graph)?;
+            out.write_all(&meta)?;
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            std::fs::rename(&tmp, path)
+        };
+        write().map_err(|e| {
+            let _ = std::fs::remove_file(&tmp);
+            store_error(path, format!("write failed: {e}"))
+        })
+    }
+
+    /// Read a store saved with [`save`](Self::save) fully into memory.
+    ///
+    /// A malformed or inconsistent file is an [`Error::Validation`]; I/O failures are
+    /// runtime errors.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| store_error(path, e))?;
+        let (mut store, offset, len) = Self::parse(path, &bytes)?;
+        store.vectors = Vectors::Owned(
+            bytes[offset..offset + len * 4]
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        );
+        Ok(store)
+    }
+
+    /// Open a saved store with the vector section memory-mapped rather than read.
+    ///
+    /// The file must not be modified while mapped. Mutations copy the vectors into
+    /// memory first; [`save`](Self::save) to a different path (or reopen) to persist.
+    pub fn open_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        #[cfg(target_endian = "little")]
+        {
+            let file = std::fs::File::open(path).map_err(|e| store_error(path, e))?;
+            // SAFETY: the mapping is read-only; callers must not truncate or rewrite the
+            // file while the store is open (documented above). `save` replaces files by
+            // rename, so saving over a mapped path leaves the mapping intact.
+            let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|e| store_error(path, e))?;
+            let (mut store, offset, len) = Self::parse(path, &map)?;
+            store.vectors = Vectors::Mapped { map, offset, len };
+            Ok(store)
+        }
+        #[cfg(not(target_endian = "little"))]
+        {
+            Self::load(path)
+        }
+    }
+
+    /// Parse header, graph and metadata; returns the store (without vectors) plus the
+    /// vector section offset and float count.
+    fn parse(path: &Path, bytes: &[u8]) -> Result<(Self, usize, usize)> {
+        let corrupt = |what: &str| {
+            Error::validation_with_context(
+                format!(
+                    "vector store {}: corrupt store file: {what}",
+                    path.display()
+                ),
+                ErrorContext::new().with_source("vector_store"),
+            )
+        };
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(corrupt("bad magic"));
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        if u32_at(8) != FORMAT_VERSION {
+            return Err(corrupt(&format!(
+                "unsupported format version {}",
+                u32_at(8)
+            )));
+        }
+        let dims = u32_at(12) as usize;
+        let metric = metric_from_code(u32_at(16)).ok_or_else(|| corrupt("unknown metric"))?;
+        let config = VectorStoreConfig {
+            metric,
+            m: u32_at(20) as usize,
+            ef_construction: u32_at(24) as usize,
+            ef_search: u32_at(28) as usize,
+            ..VectorStoreConfig::default()
+        };
+        if config.m == 0 || config.ef_construction == 0 || config.ef_search == 0 {
+            return Err(corrupt("m and ef must be positive"));
+        }
+        let slots = u32_at(32) as usize;
+        let entry = Some(u32_at(36)).filter(|e| *e != u32::MAX);
+        let max_level = u32_at(40) as usize;
+        let graph_len = usize::try_from(u64_at(44)).ok();
+        let meta_len = usize::try_from(u64_at(52)).ok();
+
+        let floats = slots
+            .checked_mul(dims)
+            .ok_or_else(|| corrupt("vector section too large"))?;
+        let sections = floats
+            .checked_mul(4)
+            .and_then(|n| n.checked_add(HEADER_LEN))
+            .and_then(|graph_start| {
+                let meta_start = graph_start.checked_add(graph_len?)?;
+                let end = meta_start.checked_add(meta_len?)?;
+                Some((graph_start, meta_start, end))
+            });
+        let (graph_start, meta_start) = match sections {
+            Some((graph_start, meta_start, end)) if end == bytes.len() => (graph_start, meta_start),
+            _ => return Err(corrupt("section lengths do not match file size")),
+        };
+        let metas: Vec<NodeMeta> = serde_json::from_slice(&bytes[meta_start..])
+            .map_err(|e| corrupt(&format!("metadata: {e}")))?;
+        if metas.len() != slots {
+            return Err(corrupt("record count mismatch"));
+        }
+
+        let graph = &bytes[graph_start..meta_start];
+        let mut pos = 0;
+        let mut next = || -> Result<u32> {
+            let v = graph
+                .get(pos..pos + 4)
+                .ok_or_else(|| corrupt("truncated graph"))?;
+            pos += 4;
+            Ok(u32::from_le_bytes(v.try_into().unwrap()))
+        };
+        let mut nodes = Vec::with_capacity(slots);
+        let mut ids = HashMap::new();
+        let mut deleted = 0;
+        for (slot, meta) in metas.into_iter().enumerate() {
+            let layers = next()? as usize;
+            if layers > MAX_LEVEL + 1 {
+                return Err(corrupt("too many layers"));
+            }
+            let mut links = Vec::with_capacity(layers);
+            for _ in 0..layers {
+                let n = next()? as usize;
+                let layer = (0..n).map(|_| next()).collect::<Result<Vec<u32>>>()?;
+                if layer.iter().any(|l| *l as usize >= slots) {
+                    return Err(corrupt("link out of range"));
+                }
+                links.push(layer);
+            }
+            if meta.deleted {
+                deleted += 1;
+            } else {
+                ids.insert(meta.id.clone(), slot as u32);
+            }
+            nodes.push(Node { meta, links });
+        }
+        if let Some(entry) = entry {
+            let layers = nodes
+                .get(entry as usize)
+                .map(|n| n.links.len())
+                .ok_or_else(|| corrupt("entry point out of range"))?;
+            if max_level >= layers {
+                return Err(corrupt("max level above the entry point's layers"));
+            }
+        }
+
+        let store = Self {
+            config,
+            dims,
+            vectors: Vectors::Owned(Vec::new()),
+            nodes,
+            ids,
+            entry,
+            max_level,
+            deleted,
+            rng: 0x2545_f491_4f6c_dd1d ^ slots as u64,
+        };
+        Ok((store, HEADER_LEN, floats))
+    }
+}
+
+fn metric_code(metric: SimilarityMetric) -> u32 {
+    match metric {
+        SimilarityMetric::Cosine => 0,
+        SimilarityMetric::Euclidean => 1,
+        SimilarityMetric::DotProduct => 2,
+        SimilarityMetric::Manhattan => 3,
+    }
+}
+
+fn metric_from_code(code: u32) -> Option<SimilarityMetric> {
+    Some(match code {
+        0 => SimilarityMetric::Cosine,
+        1 => SimilarityMetric::Euclidean,
+        2 => SimilarityMetric::DotProduct,
+        3 => SimilarityMetric::Manhattan,
+        _ => return None,
+    })
+}
+
+fn dimension_error(msg: String) -> Error {
+    Error::validation_with_context(msg, ErrorContext::new().with_source("vector_store"))
+}
+
+fn store_error(path: &Path, e: impl std::fmt::Display) -> Error {
+    Error::runtime_with_context(
+        format!("vector store {}: {e}", path.display()),
+        ErrorContext::new().with_source("vector_store"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::find_most_similar;
+    use serde_json::json;
+
+    /// Deterministic pseudo-random vectors.
+    fn vectors(n: usize, dims: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        (0..n)
+            .map(|_| {
+                (0..dims)
+                    .map(|_| {
+                        state = state
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn build(metric: SimilarityMetric, data: &[Vec<f32>]) -> VectorStore {
+        let config = VectorStoreConfig::new(metric)
+            .with_ef_construction(100)
+            .with_exact_search_below(0);
+        let mut store = VectorStore::new(config);
+        for (i, v) in data.iter().enumerate() {
+            store
+                .upsert(
+                    format!("doc-{i}"),
+                    v,
+                    json!({ "group": i % 4, "n": i }),
+                    None,
+                )
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn test_hnsw_recall_matches_exact_search() {
+        let data = vectors(600, 24, 7);
+        let queries = vectors(20, 24, 99);
+        for metric in [SimilarityMetric::Cosine, SimilarityMetric::Euclidean] {
+            let store = build(metric, &data);
+            let mut hits = 0;
+            for q in &queries {
+                let exact: HashSet<String> = find_most_similar(q, &data, 10, metric)
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| format!("doc-{}", r.index))
+                    .collect();
+                let found = store.search(q, 10, None).unwrap();
+                assert_eq!(found.len(), 10);
+                hits += found.iter().filter(|h| exact.contains(&h.id)).count();
+            }
+            let recall = hits as f64 / (queries.len() * 10) as f64;
+            assert!(recall >= 0.9, "{metric:?} recall {recall}");
+        }
+    }
+
+    #[test]
+    fn test_scores_follow_metric_conventions() {
+        let mut store = VectorStore::with_metric(SimilarityMetric::Euclidean);
+        store.upsert("a", &[0.0, 0.0], json!({}), None).unwrap();
+        store.upsert("b", &[3.0, 4.0], json!({}), None).unwrap();
+        let hits = store.search(&[0.0, 0.0], 2, None).unwrap();
+        assert_eq!(hits[0].id, "a");
+        assert!((hits[1].score - 5.0).abs() < 1e-6);
+
+        let mut store = VectorStore::default();
+        store.upsert("x", &[2.0, 0.0], json!({}), None).unwrap();
+        let hits = store.search(&[1.0, 1.0], 1, None).unwrap();
+        assert!((hits[0].score - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert!(store.upsert("bad", &[1.0], json!({}), None).is_err());
+        assert!(store.search(&[1.0], 1, None).is_err());
+    }
+
+    #[test]
+    fn test_filters_upsert_and_delete() {
+        let data = vectors(400, 16, 3);
+        let mut store = build(SimilarityMetric::Cosine, &data);
+        let q = &data[10];
+
+        let group = MetadataFilter::eq("group", 2);
+        let hits = store.search(q, 5, Some(&group)).unwrap();
+        assert_eq!(hits.len(), 5);
+        assert!(hits.iter().all(|h| h.metadata["group"] == 2));
+
+        // Selective filter takes the exact path.
+        let narrow = MetadataFilter::range("n", Some(100.0), Some(104.0))
+            .and(MetadataFilter::eq("group", 1).not());
+        let hits = store.search(q, 10, Some(&narrow)).unwrap();
+        let mut ns: Vec<i64> = hits
+            .iter()
+            .map(|h| h.metadata["n"].as_i64().unwrap())
+            .collect();
+        ns.sort();
+        assert_eq!(ns, [100, 102, 103, 104]);
+
+        assert_eq!(store.search(q, 1, None).unwrap()[0].id, "doc-10");
+        assert!(store.delete("doc-10"));
+        assert!(!store.delete("doc-10"));
+        assert_ne!(store.search(q, 1, None).unwrap()[0].id, "doc-10");
+
+        // Metadata-only upsert keeps the slot; new vector re-links.
+        store
+            .upsert(
+                "doc-11",
+                &data[11],
+                json!({"group": "moved"}),
+                Some("t".into()),
+            )
+            .unwrap();
+        assert_eq!(store.get("doc-11").unwrap().text, Some("t"));
+        store.upsert("doc-12", q, json!({}), None).unwrap();
+        assert_eq!(store.search(q, 1, None).unwrap()[0].id, "doc-12");
+        assert_eq!(store.len(), 399);
+
+        store.compact();
+        assert_eq!(store.len(), 399);
+        assert_eq!(store.nodes.len(), 399);
+        assert_eq!(store.search(q, 1, None).unwrap()[0].id, "doc-12");
+    }
+
+    #[test]
+    fn test_save_load_and_mmap_round_trip() {
+        let data = vectors(300, 8, 11);
+        let mut store = build(SimilarityMetric::DotProduct, &data);
+        store.delete("doc-5");
+        store
+            .upsert(
+                "doc-6",
+                &data[6],
+                json!({"tag": ["x", "y"]}),
+                Some("six".into()),
+            )
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("ai-lib-store-{}.bin", std::process::id()));
+        store.save(&path).unwrap();
+        let q = &data[42];
+        let expected = store.search(q, 5, None).unwrap();
+
+        let loaded = VectorStore::load(&path).unwrap();
+        assert_eq!(loaded.search(q, 5, None).unwrap(), expected);
+
+        let mut mapped = VectorStore::open_mmap(&path).unwrap();
+        assert_eq!(mapped.len(), 299);
+        assert_eq!(mapped.config().metric, SimilarityMetric::DotProduct);
+        assert_eq!(mapped.search(q, 5, None).unwrap(), expected);
+        let six = mapped
+            .search(&data[6], 1, Some(&MetadataFilter::eq("tag", "y")))
+            .unwrap();
+        assert_eq!(six[0].text.as_deref(), Some("six"));
+
+        mapped.upsert("new", &data[0], json!({}), None).unwrap();
+        assert_eq!(mapped.len(), 300);
+        assert!(matches!(mapped.vectors, Vectors::Owned(_)));
+
+        // Section lengths that overflow are rejected instead of wrapping.
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[44..52].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        match VectorStore::load(&path) {
+            Err(e) => assert!(e.to_string().contains("section lengths"), "{}", e),
+            Ok(_) => panic!("overflowing graph length was accepted"),
+        }
+
+        std::fs::write(&path, b"garbage").unwrap();
+        assert!(VectorStore::load(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_corrupted_header_and_graph_are_rejected() {
+        let data = vectors(20, 4, 3);
+        let store = build(SimilarityMetric::Cosine, &data);
+        let path =
+            std::env::temp_dir().join(format!("ai-lib-store-corrupt-{}.bin", std::process::id()));
+        store.save(&path).unwrap();
+        let saved = std::fs::read(&path).unwrap();
+        // The first node's layer count, then its layer-0 link count and first link.
+        let graph_start = HEADER_LEN + 20 * 4 * 4;
+        let first_link = graph_start + 8;
+        assert!(u32::from_le_bytes(saved[graph_start + 4..first_link].try_into().unwrap()) > 0);
+
+        let cases: [(usize, u32, &str); 4] = [
+            (20, 0, "m and ef"),
+            (28, 0, "m and ef"),
+            (40, MAX_LEVEL as u32, "max level"),
+            (first_link, 20, "link out of range"),
+        ];
+        for (offset, value, expected) in cases {
+            let mut bytes = saved.clone();
+            bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            std::fs::write(&path, &bytes).unwrap();
+            for result in [VectorStore::load(&path), VectorStore::open_mmap(&path)] {
+                match result {
+                    Err(e @ Error::Validation { .. }) => {
+                        assert!(e.to_string().contains(expected), "{e}")
+                    }
+                    Err(e) => panic!("expected a validation error, got {e}"),
+                    Ok(_) => panic!("corrupted store accepted ({expected})"),
+                }
+            }
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -41,7 +41,6 @@
 //! ```
 
 use crate::error_code::StandardErrorCode;
-#[cfg(not(target_arch = "wasm32"))]
 use crate::pipeline::PipelineError;
 use crate::protocol::ProtocolError;
 use std::time::Duration;
@@ -156,7 +155,6 @@ pub enum Error {
     #[error("Protocol specification error: {0}")]
     Protocol(#[from] ProtocolError),
 
-    #[cfg(not(target_arch = "wasm32"))]
     #[error("Pipeline processing error: {0}")]
     Pipeline(#[from] PipelineError),
 
@@ -476,6 +474,5 @@ impl Error {
 }
 
 // Re-export specific error types for convenience
-#[cfg(not(target_arch = "wasm32"))]
 pub use crate::pipeline::PipelineError as Pipeline;
 pub use crate::protocol::ProtocolError as Protocol;
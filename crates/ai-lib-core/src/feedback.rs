@@ -6,6 +6,7 @@
 //! by the client and other core modules. The full telemetry module (InMemoryFeedbackSink,
 //! ConsoleFeedbackSink, etc.) is feature-gated.
 
+use crate::types::prompt::PromptRef;
 use crate::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -28,6 +29,8 @@ pub struct ChoiceSelectionFeedback {
     pub latency_to_select_ms: Option<u64>,
     pub ui_context: Option<serde_json::Value>,
     pub candidate_hashes: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptRef>,
     pub timestamp: f64,
 }
 
@@ -40,6 +43,7 @@ impl ChoiceSelectionFeedback {
             latency_to_select_ms: None,
             ui_context: None,
             candidate_hashes: None,
+            prompt: None,
             timestamp: timestamp(),
         }
     }
@@ -61,6 +65,8 @@ pub struct RatingFeedback {
     pub max_rating: u32,
     pub category: Option<String>,
     pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptRef>,
     pub timestamp: f64,
 }
 impl RatingFeedback {
@@ -71,6 +77,7 @@ impl RatingFeedback {
             max_rating: 5,
             category: None,
             comment: None,
+            prompt: None,
             timestamp: timestamp(),
         }
     }
@@ -90,6 +97,8 @@ pub struct ThumbsFeedback {
     pub request_id: String,
     pub is_positive: bool,
     pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptRef>,
     pub timestamp: f64,
 }
 impl ThumbsFeedback {
@@ -98,6 +107,7 @@ impl ThumbsFeedback {
             request_id: request_id.into(),
             is_positive: true,
             reason: None,
+            prompt: None,
             timestamp: timestamp(),
         }
     }
@@ -106,6 +116,7 @@ impl ThumbsFeedback {
             request_id: request_id.into(),
             is_positive: false,
             reason: None,
+            prompt: None,
             timestamp: timestamp(),
         }
     }
@@ -121,6 +132,8 @@ pub struct TextFeedback {
     pub request_id: String,
     pub text: String,
     pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptRef>,
     pub timestamp: f64,
 }
 impl TextFeedback {
@@ -129,6 +142,7 @@ impl TextFeedback {
             request_id: request_id.into(),
             text: text.into(),
             category: None,
+            prompt: None,
             timestamp: timestamp(),
         }
     }
@@ -142,6 +156,8 @@ pub struct CorrectionFeedback {
     pub corrected_hash: String,
     pub edit_distance: Option<u32>,
     pub correction_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptRef>,
     pub timestamp: f64,
 }
 impl CorrectionFeedback {
@@ -156,6 +172,7 @@ impl CorrectionFeedback {
             corrected_hash: corrected.into(),
             edit_distance: None,
             correction_type: None,
+            prompt: None,
             timestamp: timestamp(),
         }
     }
@@ -167,6 +184,8 @@ pub struct RegenerateFeedback {
     pub request_id: String,
     pub regeneration_count: u32,
     pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptRef>,
     pub timestamp: f64,
 }
 impl RegenerateFeedback {
@@ -175,6 +194,7 @@ impl RegenerateFeedback {
             request_id: request_id.into(),
             regeneration_count: 1,
             reason: None,
+            prompt: None,
             timestamp: timestamp(),
         }
     }
@@ -186,6 +206,8 @@ pub struct StopFeedback {
     pub request_id: String,
     pub tokens_generated: Option<u32>,
     pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptRef>,
     pub timestamp: f64,
 }
 impl StopFeedback {
@@ -194,6 +216,7 @@ impl StopFeedback {
             request_id: request_id.into(),
             tokens_generated: None,
             reason: None,
+            prompt: None,
             timestamp: timestamp(),
         }
     }
@@ -223,6 +246,38 @@ impl FeedbackEvent {
             FeedbackEvent::Stop(f) => &f.request_id,
         }
     }
+
+    /// Prompt template the feedback refers to, if one was attached.
+    pub fn prompt(&self) -> Option<&PromptRef> {
+        match self {
+            FeedbackEvent::ChoiceSelection(f) => f.prompt.as_ref(),
+            FeedbackEvent::Rating(f) => f.prompt.as_ref(),
+            FeedbackEvent::Thumbs(f) => f.prompt.as_ref(),
+            FeedbackEvent::Text(f) => f.prompt.as_ref(),
+            FeedbackEvent::Correction(f) => f.prompt.as_ref(),
+            FeedbackEvent::Regenerate(f) => f.prompt.as_ref(),
+            FeedbackEvent::Stop(f) => f.prompt.as_ref(),
+        }
+    }
+
+    /// Attach the prompt template that produced the answer this feedback is about
+    /// (e.g. from [`CallStats::prompt`](crate::CallStats)).
+    ///
+    /// Every feedback type has a `prompt` field for this; it defaults to `None` and
+    /// is left out of the serialized event when unset.
+    pub fn with_prompt(mut self, prompt: Option<PromptRef>) -> Self {
+        let slot = match &mut self {
+            FeedbackEvent::ChoiceSelection(f) => &mut f.prompt,
+            FeedbackEvent::Rating(f) => &mut f.prompt,
+            FeedbackEvent::Thumbs(f) => &mut f.prompt,
+            FeedbackEvent::Text(f) => &mut f.prompt,
+            FeedbackEvent::Correction(f) => &mut f.prompt,
+            FeedbackEvent::Regenerate(f) => &mut f.prompt,
+            FeedbackEvent::Stop(f) => &mut f.prompt,
+        };
+        *slot = prompt;
+        self
+    }
 }
 
 /// Feedback sink trait.
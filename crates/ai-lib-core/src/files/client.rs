@@ -0,0 +1,643 @@
+//! Provider Files API client.
+//!
+//! Protocol-driven construction: prefer [`FilesClientBuilder::from_manifest`] /
+//! [`FilesClientBuilder::from_model`]. Base URL and credentials come from the
+//! provider manifest ([ARCH-001]); the API dialect comes from the `adapter` of
+//! `endpoints.files` (`openai` | `anthropic` | `gemini`).
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use serde_json::{json, Value};
+
+use super::registry::{file_registry_key, FileRecord, FileRegistry, InMemoryFileRegistry};
+use super::types::{FileObject, FilesApi};
+use crate::client::endpoint::lookup_endpoint;
+use crate::credentials::{self, primary_auth, resolve_credential};
+use crate::drivers::anthropic::FILES_API_BETA;
+use crate::protocol::{ProtocolLoader, ProtocolManifest};
+use crate::types::message::{ContentBlock, Message, MessageContent};
+use crate::{Error, ErrorContext, Result};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Gemini deletes uploads after 48 hours; re-upload a little before that.
+const GEMINI_REUSE_SECS: u64 = 46 * 60 * 60;
+
+/// Client for a provider's Files API.
+pub struct FilesClient {
+    http_client: reqwest::Client,
+    base_url: String,
+    endpoint_path: String,
+    api: FilesApi,
+    api_key: String,
+    auth_header: Option<(String, String)>,
+    purpose: String,
+    registry: Arc<dyn FileRegistry>,
+    reuse_for: Option<Duration>,
+    timeout: Duration,
+}
+
+impl FilesClient {
+    pub fn builder() -> FilesClientBuilder {
+        FilesClientBuilder::new()
+    }
+
+    /// Upload `data` unconditionally.
+    pub async fn upload(&self, data: &[u8], filename: &str, mime_type: &str) -> Result<FileObject> {
+        let file = match self.api {
+            FilesApi::OpenAi | FilesApi::Anthropic => {
+                let part = reqwest::multipart::Part::bytes(data.to_vec())
+                    .file_name(filename.to_string())
+                    .mime_str(mime_type)
+                    .map_err(|e| Error::configuration(format!("Invalid mime: {}", e)))?;
+                let mut form = reqwest::multipart::Form::new().part("file", part);
+                if self.api == FilesApi::OpenAi {
+                    form = form.text("purpose", self.purpose.clone());
+                }
+                let response = self
+                    .send(
+                        self.request(reqwest::Method::POST, &self.files_url())
+                            .multipart(form),
+                    )
+                    .await?;
+                self.parse(&response)?
+            }
+            FilesApi::Gemini => self.gemini_upload(data, filename, mime_type).await?,
+        };
+        match file.state.as_deref() {
+            Some("PROCESSING") => self.wait_active(&file.id).await,
+            _ => Ok(file),
+        }
+    }
+
+    /// Upload `data` unless identical bytes were already uploaded to this provider.
+    ///
+    /// Uploads are looked up in the client's [`FileRegistry`] by content hash; entries
+    /// older than the reuse window (Gemini files expire) are uploaded again.
+    pub async fn upload_once(
+        &self,
+        data: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<FileObject> {
+        let key = file_registry_key(&self.scope(), data);
+        if let Some(record) = self.registry.get(&key) {
+            let fresh = match self.reuse_for {
+                Some(max) => now().saturating_sub(record.uploaded_at) < max.as_secs(),
+                None => true,
+            };
+            if fresh {
+                return Ok(record.file);
+            }
+        }
+        let file = self.upload(data, filename, mime_type).await?;
+        self.registry.put(
+            &key,
+            &FileRecord {
+                file: file.clone(),
+                uploaded_at: now(),
+            },
+        );
+        Ok(file)
+    }
+
+    /// All files, following pagination.
+    pub async fn list(&self) -> Result<Vec<FileObject>> {
+        let mut files = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut request = self.request(reqwest::Method::GET, &self.files_url());
+            if let Some(cursor) = &cursor {
+                let param = match self.api {
+                    FilesApi::OpenAi => "after",
+                    FilesApi::Anthropic => "after_id",
+                    FilesApi::Gemini => "pageToken",
+                };
+                request = request.query(&[(param, cursor)]);
+            }
+            let response = self.send(request).await?;
+            let key = match self.api {
+                FilesApi::Gemini => "files",
+                FilesApi::OpenAi | FilesApi::Anthropic => "data",
+            };
+            let page = response[key].as_array().cloned().unwrap_or_default();
+            for item in &page {
+                files.extend(self.parse(item).ok());
+            }
+            cursor = match self.api {
+                FilesApi::Gemini => response["nextPageToken"]
+                    .as_str()
+                    .filter(|t| !t.is_empty())
+                    .map(String::from),
+                FilesApi::OpenAi | FilesApi::Anthropic
+                    if response["has_more"].as_bool() == Some(true) =>
+                {
+                    response["last_id"]
+                        .as_str()
+                        .map(String::from)
+                        .or_else(|| files.last().map(|f| f.id.clone()))
+                }
+                _ => None,
+            };
+            if cursor.is_none() || page.is_empty() {
+                return Ok(files);
+            }
+        }
+    }
+
+    /// Metadata of one file.
+    pub async fn get(&self, file_id: &str) -> Result<FileObject> {
+        let response = self
+            .send(self.request(reqwest::Method::GET, &self.file_url(file_id)))
+            .await?;
+        self.parse(&response)
+    }
+
+    /// Delete a file and drop it from the registry.
+    pub async fn delete(&self, file_id: &str) -> Result<()> {
+        self.send(self.request(reqwest::Method::DELETE, &self.file_url(file_id)))
+            .await?;
+        self.registry.forget(file_id);
+        Ok(())
+    }
+
+    /// Replace inline base64 documents (and images, where the provider accepts
+    /// uploaded images in messages) of at least `min_bytes` decoded bytes with
+    /// references to uploaded files, uploading each distinct payload once.
+    ///
+    /// Returns how many blocks were replaced. The request encoders send the
+    /// replaced blocks as file references (`file_id` / `fileData`).
+    pub async fn offload(&self, messages: &mut [Message], min_bytes: usize) -> Result<usize> {
+        let mut replaced = 0;
+        for message in messages.iter_mut() {
+            let MessageContent::Blocks(blocks) = &mut message.content else {
+                continue;
+            };
+            for block in blocks.iter_mut() {
+                let new_block = match block {
+                    ContentBlock::Document { source }
+                        if source.source_type == "base64"
+                            && decoded_len(&source.data) >= min_bytes =>
+                    {
+                        let mime_type = source
+                            .mime_type
+                            .clone()
+                            .unwrap_or_else(|| "application/pdf".to_string());
+                        let filename = source
+                            .filename
+                            .clone()
+                            .unwrap_or_else(|| default_filename("document", &mime_type));
+                        let file = self
+                            .upload_once(&decode(&source.data)?, &filename, &mime_type)
+                            .await?;
+                        ContentBlock::document_file(
+                            file.reference().to_string(),
+                            Some(mime_type),
+                            Some(filename),
+                        )
+                    }
+                    ContentBlock::Image { source }
+                        if self.api.references_images()
+                            && source.source_type == "base64"
+                            && decoded_len(&source.data) >= min_bytes =>
+                    {
+                        let media_type = source
+                            .media_type
+                            .clone()
+                            .unwrap_or_else(|| "image/png".to_string());
+                        let file = self
+                            .upload_once(
+                                &decode(&source.data)?,
+                                &default_filename("image", &media_type),
+                                &media_type,
+                            )
+                            .await?;
+                        ContentBlock::image_file(file.reference().to_string(), Some(media_type))
+                    }
+                    _ => continue,
+                };
+                *block = new_block;
+                replaced += 1;
+            }
+        }
+        Ok(replaced)
+    }
+
+    pub fn api(&self) -> FilesApi {
+        self.api
+    }
+
+    /// Registry scope: uploads are reusable only against the same endpoint and key.
+    fn scope(&self) -> String {
+        let key_hash = file_registry_key("api_key", self.api_key.as_bytes());
+        format!("{}|{}", self.files_url(), &key_hash[..16])
+    }
+
+    fn files_url(&self) -> String {
+        format!(
+            "{}{}",
+            self.base_url.trim_end_matches('/'),
+            self.endpoint_path.trim_end_matches('/')
+        )
+    }
+
+    fn file_url(&self, file_id: &str) -> String {
+        format!(
+            "{}/{}",
+            self.files_url(),
+            file_id.trim_start_matches("files/")
+        )
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.http_client.request(method, url);
+        let request = match &self.auth_header {
+            Some((name, prefix)) => request.header(name, format!("{prefix}{}", self.api_key)),
+            None => request.bearer_auth(&self.api_key),
+        };
+        match self.api {
+            FilesApi::Anthropic => request
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .header("anthropic-beta", FILES_API_BETA),
+            FilesApi::OpenAi | FilesApi::Gemini => request,
+        }
+    }
+
+    /// Gemini resumable upload: start a session, then send the bytes and finalize.
+    async fn gemini_upload(
+        &self,
+        data: &[u8],
+        filename: &str,
+        mime_type: &str,
+    ) -> Result<FileObject> {
+        let files_url = self.files_url();
+        let (origin, path) = split_origin(&files_url);
+        let start = self
+            .request(reqwest::Method::POST, &format!("{origin}/upload{path}"))
+            .header("X-Goog-Upload-Protocol", "resumable")
+            .header("X-Goog-Upload-Command", "start")
+            .header("X-Goog-Upload-Header-Content-Length", data.len())
+            .header("X-Goog-Upload-Header-Content-Type", mime_type)
+            .json(&json!({ "file": { "display_name": filename } }));
+        let response = start.send().await.map_err(network)?;
+        let status = response.status();
+        if !status.is_success() {
+            let body_str = response.text().await.unwrap_or_default();
+            return Err(api_error(status, &body_str));
+        }
+        let upload_url = response
+            .headers()
+            .get("x-goog-upload-url")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+            .ok_or_else(|| {
+                Error::api_with_context(
+                    "Gemini upload session returned no x-goog-upload-url header",
+                    ErrorContext::new().with_source("files"),
+                )
+            })?;
+        let response = self
+            .send(
+                self.request(reqwest::Method::POST, &upload_url)
+                    .header("X-Goog-Upload-Command", "upload, finalize")
+                    .header("X-Goog-Upload-Offset", 0)
+                    .body(data.to_vec()),
+            )
+            .await?;
+        self.parse(response.get("file").unwrap_or(&response))
+    }
+
+    /// Poll until a Gemini file leaves `PROCESSING`.
+    async fn wait_active(&self, file_id: &str) -> Result<FileObject> {
+        let started = std::time::Instant::now();
+        loop {
+            let file = self.get(file_id).await?;
+            match file.state.as_deref() {
+                Some("PROCESSING") if started.elapsed() < self.timeout => {
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+                Some("PROCESSING") => {
+                    return Err(Error::api_with_context(
+                        format!("File {} is still processing", file_id),
+                        ErrorContext::new()
+                            .with_source("files")
+                            .with_hint("Raise timeout_secs for large uploads"),
+                    ))
+                }
+                Some("FAILED") => {
+                    return Err(Error::api_with_context(
+                        format!("Provider failed to process file {}", file_id),
+                        ErrorContext::new().with_source("files"),
+                    ))
+                }
+                _ => return Ok(file),
+            }
+        }
+    }
+
+    fn parse(&self, value: &Value) -> Result<FileObject> {
+        let file = match self.api {
+            FilesApi::OpenAi => FileObject::from_openai(value),
+            FilesApi::Anthropic => FileObject::from_anthropic(value),
+            FilesApi::Gemini => FileObject::from_gemini(value.get("file").unwrap_or(value)),
+        };
+        file.ok_or_else(|| {
+            Error::api_with_context(
+                format!("Unexpected file object: {}", value),
+                ErrorContext::new().with_source("files"),
+            )
+        })
+    }
+
+    /// Send and parse the JSON body (empty bodies parse as `null`), failing on
+    /// non-success status.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value> {
+        let response = request.send().await.map_err(network)?;
+        let status = response.status();
+        let body_str = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(api_error(status, &body_str));
+        }
+        if body_str.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&body_str).map_err(|e| {
+            Error::api_with_context(
+                format!("Failed to parse files response: {}", e),
+                ErrorContext::new().with_source("files"),
+            )
+        })
+    }
+}
+
+fn network(e: reqwest::Error) -> Error {
+    Error::network_with_context(
+        format!("Files request failed: {}", e),
+        ErrorContext::new().with_source("files"),
+    )
+}
+
+fn api_error(status: reqwest::StatusCode, body: &str) -> Error {
+    Error::api_with_context(
+        format!("Files API error ({}): {}", status, body),
+        ErrorContext::new().with_source("files"),
+    )
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn decoded_len(data: &str) -> usize {
+    data.len() / 4 * 3
+}
+
+fn decode(data: &str) -> Result<Vec<u8>> {
+    BASE64.decode(data).map_err(|e| {
+        Error::validation_with_context(
+            format!("Invalid base64 content block: {}", e),
+            ErrorContext::new().with_source("files"),
+        )
+    })
+}
+
+fn default_filename(stem: &str, mime_type: &str) -> String {
+    let ext = match mime_type {
+        "application/pdf" => "pdf",
+        "image/jpeg" => "jpg",
+        "text/plain" => "txt",
+        other => other.rsplit('/').next().unwrap_or("bin"),
+    };
+    format!("{stem}.{ext}")
+}
+
+/// `https://host/v1beta/files` → (`https://host`, `/v1beta/files`).
+fn split_origin(url: &str) -> (&str, &str) {
+    let host_start = url.find("://").map_or(0, |i| i + 3);
+    match url[host_start..].find('/') {
+        Some(i) => url.split_at(host_start + i),
+        None => (url, ""),
+    }
+}
+
+pub struct FilesClientBuilder {
+    api_key: Option<String>,
+    base_url: Option<String>,
+    endpoint_path: Option<String>,
+    api: Option<FilesApi>,
+    auth_header: Option<(String, String)>,
+    purpose: String,
+    registry: Option<Arc<dyn FileRegistry>>,
+    reuse_for: Option<Option<Duration>>,
+    timeout_secs: u64,
+    protocol_path: Option<String>,
+}
+
+impl FilesClientBuilder {
+    pub fn new() -> Self {
+        Self {
+            api_key: None,
+            base_url: None,
+            endpoint_path: None,
+            api: None,
+            auth_header: None,
+            purpose: "user_data".to_string(),
+            registry: None,
+            reuse_for: None,
+            timeout_secs: 300,
+            protocol_path: None,
+        }
+    }
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+    /// Files collection path (default `/files`).
+    pub fn endpoint_path(mut self, path: impl Into<String>) -> Self {
+        self.endpoint_path = Some(path.into());
+        self
+    }
+    /// Force the API dialect instead of detecting it.
+    pub fn api(mut self, api: FilesApi) -> Self {
+        self.api = Some(api);
+        self
+    }
+    /// Send the key in `name` (after `prefix`) instead of `Authorization: Bearer`.
+    /// Anthropic defaults to `x-api-key`, Gemini to `x-goog-api-key`.
+    pub fn auth_header(mut self, name: impl Into<String>, prefix: impl Into<String>) -> Self {
+        self.auth_header = Some((name.into(), prefix.into()));
+        self
+    }
+    /// OpenAI upload purpose (default `user_data`).
+    pub fn purpose(mut self, purpose: impl Into<String>) -> Self {
+        self.purpose = purpose.into();
+        self
+    }
+    /// Where [`FilesClient::upload_once`] remembers uploads (default: in memory).
+    /// Share one registry between clients, or use a
+    /// [`JsonFileRegistry`](super::JsonFileRegistry) to keep uploads across restarts.
+    pub fn registry(mut self, registry: Arc<dyn FileRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+    /// How long a registered upload is reused (`None`: until deleted). Defaults to
+    /// 46 hours for Gemini, whose files expire after 48, and no limit otherwise.
+    pub fn reuse_for(mut self, max_age: Option<Duration>) -> Self {
+        self.reuse_for = Some(max_age);
+        self
+    }
+    /// Request timeout; also bounds waiting for Gemini to finish processing an upload.
+    pub fn timeout_secs(mut self, secs: u64) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+    pub fn protocol_path(mut self, path: impl Into<String>) -> Self {
+        self.protocol_path = Some(path.into());
+        self
+    }
+
+    /// Build from an already-loaded protocol manifest ([ARCH-001]).
+    pub fn from_manifest(mut self, manifest: &ProtocolManifest) -> Result<Self> {
+        let cred = resolve_credential(manifest, self.api_key.as_deref());
+        let secret = cred.secret().ok_or_else(|| {
+            Error::configuration(format!(
+                "API key required for files (provider={}; tried {:?})",
+                credentials::provider_id(manifest),
+                cred.required_envs
+                    .iter()
+                    .chain(cred.conventional_envs.iter())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            ))
+        })?;
+        self.api_key = Some(secret.to_string());
+        self.base_url = Some(manifest.get_base_url().to_string());
+        let endpoint = lookup_endpoint(manifest.endpoints.as_ref(), "files");
+        if self.api.is_none() {
+            self.api = Some(FilesApi::detect(
+                endpoint.and_then(|e| e.adapter.as_deref()),
+                manifest.get_base_url(),
+            ));
+        }
+        if self.endpoint_path.is_none() {
+            self.endpoint_path = endpoint.map(|e| e.path.clone()).or_else(|| {
+                manifest
+                    .services
+                    .as_ref()
+                    .and_then(|s| s.get("files"))
+                    .map(|s| s.path.clone())
+            });
+        }
+        if self.auth_header.is_none() {
+            if let Some(header) = primary_auth(manifest).and_then(|a| a.header_name.clone()) {
+                let prefix = primary_auth(manifest)
+                    .and_then(|a| a.prefix.clone())
+                    .unwrap_or_default();
+                self.auth_header = Some((header, prefix));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Load the provider of `model` (`provider/model-id`) via [`ProtocolLoader`]
+    /// then build.
+    pub async fn from_model(self, model: &str) -> Result<FilesClient> {
+        let mut loader = ProtocolLoader::new();
+        if let Some(path) = &self.protocol_path {
+            loader = loader.with_base_path(path);
+        }
+        let manifest = loader.load_model(model).await.map_err(Error::Protocol)?;
+        self.from_manifest(&manifest)?.build().await
+    }
+
+    pub async fn build(self) -> Result<FilesClient> {
+        let api_key = self.api_key.ok_or_else(|| {
+            Error::configuration(
+                "API key required: use from_manifest/from_model or set api_key explicitly",
+            )
+        })?;
+        let base_url = self.base_url.ok_or_else(|| {
+            Error::configuration(
+                "base_url required: use from_manifest/from_model or set base_url explicitly (no vendor default)",
+            )
+        })?;
+        let api = self
+            .api
+            .unwrap_or_else(|| FilesApi::detect(None, &base_url));
+        let endpoint_path = self.endpoint_path.unwrap_or_else(|| "/files".to_string());
+        let endpoint_path = if endpoint_path.starts_with('/') {
+            endpoint_path
+        } else {
+            format!("/{}", endpoint_path)
+        };
+        let auth_header = self.auth_header.or_else(|| match api {
+            FilesApi::OpenAi => None,
+            FilesApi::Anthropic => Some(("x-api-key".to_string(), String::new())),
+            FilesApi::Gemini => Some(("x-goog-api-key".to_string(), String::new())),
+        });
+        let reuse_for = self.reuse_for.unwrap_or(match api {
+            FilesApi::Gemini => Some(Duration::from_secs(GEMINI_REUSE_SECS)),
+            FilesApi::OpenAi | FilesApi::Anthropic => None,
+        });
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .build()
+            .map_err(|e| Error::configuration(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(FilesClient {
+            http_client,
+            base_url,
+            endpoint_path,
+            api,
+            api_key,
+            auth_header,
+            purpose: self.purpose,
+            registry: self
+                .registry
+                .unwrap_or_else(|| Arc::new(InMemoryFileRegistry::new())),
+            reuse_for,
+            timeout: Duration::from_secs(self.timeout_secs),
+        })
+    }
+}
+
+impl Default for FilesClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_origin() {
+        assert_eq!(
+            split_origin("https://generativelanguage.googleapis.com/v1beta/files"),
+            ("https://generativelanguage.googleapis.com", "/v1beta/files")
+        );
+        assert_eq!(
+            split_origin("http://127.0.0.1:1234"),
+            ("http://127.0.0.1:1234", "")
+        );
+    }
+
+    #[test]
+    fn test_default_filename() {
+        assert_eq!(
+            default_filename("document", "application/pdf"),
+            "document.pdf"
+        );
+        assert_eq!(default_filename("image", "image/jpeg"), "image.jpg");
+        assert_eq!(default_filename("image", "image/webp"), "image.webp");
+    }
+}
@@ -0,0 +1,53 @@
+//! Files（文件）模块：通过 OpenAI、Anthropic、Gemini 的 Files API 上传、列出、查询与删除文件，并按内容哈希对每个服务商只上传一次。
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`FilesClient::upload`] / [`FilesClient::upload_once`] | Upload bytes; `upload_once` reuses an earlier upload of identical content |
+//! | [`FilesClient::list`] / [`FilesClient::get`] / [`FilesClient::delete`] | File metadata as [`FileObject`]s |
+//! | [`FilesClient::offload`] | Swap large inline base64 document / image blocks for uploaded file references |
+//! | [`FileRegistry`] | Content-hash → upload record; [`InMemoryFileRegistry`] or persisted [`JsonFileRegistry`] |
+//! | [`FilesApi`] | Wire dialect, from the manifest's `endpoints.files.adapter` |
+//!
+//! File references are [`ContentBlock::document_file`](crate::types::message::ContentBlock::document_file)
+//! / [`ContentBlock::image_file`](crate::types::message::ContentBlock::image_file) blocks; the
+//! Anthropic, Gemini and OpenAI drivers encode them as `file_id` sources, `fileData` parts and
+//! `file` content parts respectively. To offload on every chat request, attach the client
+//! with [`AiClientBuilder::files`](crate::client::AiClientBuilder::files).
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//! use ai_lib_core::files::{FilesClient, JsonFileRegistry};
+//! use ai_lib_core::types::message::{ContentBlock, Message, MessageContent, MessageRole};
+//!
+//! # async fn demo() -> ai_lib_core::Result<()> {
+//! let registry = Arc::new(JsonFileRegistry::open(".ai-lib/files.json")?);
+//! let files = FilesClient::builder()
+//!     .registry(registry)
+//!     .from_model("anthropic/claude-sonnet-4")
+//!     .await?;
+//!
+//! let pdf = base64::Engine::encode(
+//!     &base64::engine::general_purpose::STANDARD,
+//!     std::fs::read("report.pdf")?,
+//! );
+//! let mut messages = vec![Message::with_content(
+//!     MessageRole::User,
+//!     MessageContent::blocks(vec![
+//!         ContentBlock::text("Summarize the report"),
+//!         ContentBlock::document_base64(pdf, Some("application/pdf".into()), None),
+//!     ]),
+//! )];
+//! // Uploads the PDF on the first call only; later calls reuse the file id.
+//! files.offload(&mut messages, 1 << 20).await?;
+//! # Ok(()) }
+//! ```
+
+mod client;
+mod registry;
+mod types;
+
+pub use client::{FilesClient, FilesClientBuilder};
+pub use registry::{
+    file_registry_key, FileRecord, FileRegistry, InMemoryFileRegistry, JsonFileRegistry,
+};
+pub use types::{FileObject, FilesApi};
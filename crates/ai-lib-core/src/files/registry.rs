@@ -0,0 +1,211 @@
+//! Content-addressed record of uploads used by [`FilesClient::upload_once`](super::FilesClient::upload_once).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::types::FileObject;
+use crate::{Error, ErrorContext, Result};
+
+/// An upload remembered by a [`FileRegistry`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRecord {
+    pub file: FileObject,
+    /// Unix seconds when the file was uploaded.
+    pub uploaded_at: u64,
+}
+
+/// Storage for uploads keyed by [`file_registry_key`].
+///
+/// Implement this to share uploads through Redis, SQLite, …; the client calls
+/// `get` before uploading, `put` after a successful upload and `forget` when a
+/// file is deleted.
+pub trait FileRegistry: Send + Sync {
+    fn get(&self, key: &str) -> Option<FileRecord>;
+    fn put(&self, key: &str, record: &FileRecord);
+    /// Drop every entry that points at `file_id`.
+    fn forget(&self, file_id: &str);
+}
+
+/// Registry key for `data` uploaded to `scope` (one provider account / endpoint):
+/// hex SHA-256, so identical bytes map to the same key across processes.
+pub fn file_registry_key(scope: &str, data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(scope.as_bytes());
+    hasher.update([0]);
+    hasher.update(data);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// In-process [`FileRegistry`]; uploads are forgotten when the process exits.
+#[derive(Debug, Default)]
+pub struct InMemoryFileRegistry {
+    entries: Mutex<HashMap<String, FileRecord>>,
+}
+
+impl InMemoryFileRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|e| e.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl FileRegistry for InMemoryFileRegistry {
+    fn get(&self, key: &str) -> Option<FileRecord> {
+        self.entries.lock().ok()?.get(key).cloned()
+    }
+
+    fn put(&self, key: &str, record: &FileRecord) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.to_string(), record.clone());
+        }
+    }
+
+    fn forget(&self, file_id: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|_, record| record.file.id != file_id);
+        }
+    }
+}
+
+/// [`FileRegistry`] persisted as a JSON object in one file, so uploads survive
+/// restarts. The file is rewritten on every change.
+#[derive(Debug)]
+pub struct JsonFileRegistry {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, FileRecord>>,
+}
+
+impl JsonFileRegistry {
+    /// Load `path` if it exists; otherwise start empty and create it on the first upload.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let entries = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                Error::validation_with_context(
+                    format!("Invalid file registry {}: {}", path.display(), e),
+                    ErrorContext::new()
+                        .with_source("files")
+                        .with_hint("Delete the registry file to start over"),
+                )
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn persist(&self, entries: &HashMap<String, FileRecord>) {
+        let result = serde_json::to_vec_pretty(entries)
+            .map_err(std::io::Error::from)
+            .and_then(|bytes| {
+                if let Some(parent) = self.path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&self.path, bytes)
+            });
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to write file registry {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+impl FileRegistry for JsonFileRegistry {
+    fn get(&self, key: &str) -> Option<FileRecord> {
+        self.entries.lock().ok()?.get(key).cloned()
+    }
+
+    fn put(&self, key: &str, record: &FileRecord) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.to_string(), record.clone());
+            self.persist(&entries);
+        }
+    }
+
+    fn forget(&self, file_id: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let before = entries.len();
+            entries.retain(|_, record| record.file.id != file_id);
+            if entries.len() != before {
+                self.persist(&entries);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str) -> FileRecord {
+        FileRecord {
+            file: FileObject {
+                id: id.to_string(),
+                filename: Some("a.pdf".into()),
+                mime_type: None,
+                size_bytes: Some(3),
+                created_at: None,
+                expires_at: None,
+                purpose: None,
+                uri: None,
+                state: None,
+            },
+            uploaded_at: 1,
+        }
+    }
+
+    #[test]
+    fn test_keys_depend_on_scope_and_bytes() {
+        let key = file_registry_key("openai", b"pdf");
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, file_registry_key("openai", b"pdf"));
+        assert_ne!(key, file_registry_key("anthropic", b"pdf"));
+        assert_ne!(key, file_registry_key("openai", b"pdf2"));
+    }
+
+    #[test]
+    fn test_json_registry_round_trips_and_forgets() {
+        let path =
+            std::env::temp_dir().join(format!("ai-lib-file-registry-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let registry = JsonFileRegistry::open(&path).unwrap();
+        registry.put("k1", &record("file-1"));
+        registry.put("k2", &record("file-2"));
+
+        let reopened = JsonFileRegistry::open(&path).unwrap();
+        assert_eq!(reopened.get("k1"), Some(record("file-1")));
+        reopened.forget("file-1");
+        assert_eq!(reopened.get("k1"), None);
+        assert_eq!(
+            JsonFileRegistry::open(&path).unwrap().get("k2"),
+            Some(record("file-2"))
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
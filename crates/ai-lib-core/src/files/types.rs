@@ -0,0 +1,176 @@
+//! Files API types.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Which Files API an endpoint speaks.
+///
+/// Selected by the `adapter` of the manifest's `endpoints.files` entry, or
+/// guessed from the base URL when no entry is declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilesApi {
+    /// OpenAI `/files` (multipart upload) and OpenAI-compatible servers.
+    #[default]
+    OpenAi,
+    /// Anthropic `/v1/files` (beta).
+    Anthropic,
+    /// Gemini `files` with resumable upload.
+    Gemini,
+}
+
+impl FilesApi {
+    pub(crate) fn detect(adapter: Option<&str>, base_url: &str) -> Self {
+        match adapter {
+            Some(a) if a.starts_with("anthropic") => Self::Anthropic,
+            Some(a) if a.starts_with("gemini") || a.starts_with("google") => Self::Gemini,
+            Some(_) => Self::OpenAi,
+            None if base_url.contains("anthropic.com") => Self::Anthropic,
+            None if base_url.contains("generativelanguage") => Self::Gemini,
+            None => Self::OpenAi,
+        }
+    }
+
+    /// Whether uploaded images can be referenced from chat messages. OpenAI Chat
+    /// Completions only takes file ids for documents.
+    pub(crate) fn references_images(self) -> bool {
+        !matches!(self, Self::OpenAi)
+    }
+}
+
+/// Metadata of an uploaded file, normalized across providers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileObject {
+    /// `file-…` (OpenAI), `file_…` (Anthropic) or `files/…` (Gemini).
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+    /// As returned: unix seconds (OpenAI) or RFC 3339 (Anthropic, Gemini).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// RFC 3339 expiry (Gemini deletes uploads after 48 hours).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Upload purpose (OpenAI).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    /// URI to reference the file by in requests (Gemini).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// Processing state (Gemini: `PROCESSING`, `ACTIVE`, `FAILED`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+}
+
+impl FileObject {
+    /// What message content blocks reference the file by: the Gemini URI, else the id.
+    pub fn reference(&self) -> &str {
+        self.uri.as_deref().unwrap_or(&self.id)
+    }
+
+    pub(crate) fn from_openai(value: &Value) -> Option<Self> {
+        Some(Self {
+            id: value.get("id")?.as_str()?.to_string(),
+            filename: string(value, "filename"),
+            mime_type: None,
+            size_bytes: value.get("bytes").and_then(Value::as_u64),
+            created_at: value
+                .get("created_at")
+                .and_then(Value::as_i64)
+                .map(|t| t.to_string()),
+            expires_at: value
+                .get("expires_at")
+                .and_then(Value::as_i64)
+                .map(|t| t.to_string()),
+            purpose: string(value, "purpose"),
+            uri: None,
+            state: string(value, "status"),
+        })
+    }
+
+    pub(crate) fn from_anthropic(value: &Value) -> Option<Self> {
+        Some(Self {
+            id: value.get("id")?.as_str()?.to_string(),
+            filename: string(value, "filename"),
+            mime_type: string(value, "mime_type"),
+            size_bytes: value.get("size_bytes").and_then(Value::as_u64),
+            created_at: string(value, "created_at"),
+            expires_at: None,
+            purpose: None,
+            uri: None,
+            state: None,
+        })
+    }
+
+    pub(crate) fn from_gemini(value: &Value) -> Option<Self> {
+        Some(Self {
+            id: value.get("name")?.as_str()?.to_string(),
+            filename: string(value, "displayName"),
+            mime_type: string(value, "mimeType"),
+            // int64 fields arrive as JSON strings.
+            size_bytes: value.get("sizeBytes").and_then(|v| {
+                v.as_u64()
+                    .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+            }),
+            created_at: string(value, "createTime"),
+            expires_at: string(value, "expirationTime"),
+            purpose: None,
+            uri: string(value, "uri"),
+            state: string(value, "state"),
+        })
+    }
+}
+
+fn string(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_api_detection() {
+        assert_eq!(
+            FilesApi::detect(None, "https://api.anthropic.com/v1"),
+            FilesApi::Anthropic
+        );
+        assert_eq!(
+            FilesApi::detect(None, "https://generativelanguage.googleapis.com/v1beta"),
+            FilesApi::Gemini
+        );
+        assert_eq!(
+            FilesApi::detect(Some("gemini"), "http://localhost"),
+            FilesApi::Gemini
+        );
+        assert_eq!(
+            FilesApi::detect(None, "http://localhost:8080/v1"),
+            FilesApi::OpenAi
+        );
+    }
+
+    #[test]
+    fn test_gemini_object_references_uri() {
+        let file = FileObject::from_gemini(&json!({
+            "name": "files/abc",
+            "uri": "https://generativelanguage.googleapis.com/v1beta/files/abc",
+            "mimeType": "application/pdf",
+            "sizeBytes": "2048",
+            "state": "ACTIVE"
+        }))
+        .unwrap();
+        assert_eq!(file.id, "files/abc");
+        assert_eq!(file.size_bytes, Some(2048));
+        assert_eq!(
+            file.reference(),
+            "https://generativelanguage.googleapis.com/v1beta/files/abc"
+        );
+
+        let file = FileObject::from_openai(&json!({ "id": "file-1", "bytes": 3 })).unwrap();
+        assert_eq!(file.reference(), "file-1");
+    }
+}
@@ -0,0 +1,616 @@
+//! Image generation client.
+//!
+//! Protocol-driven construction: prefer [`ImageClientBuilder::from_manifest`] /
+//! [`ImageClientBuilder::from_model`]. Base URL and credentials come from the
+//! provider manifest ([ARCH-001]); the API dialect comes from the `adapter` of
+//! `endpoints.images` (`openai` | `gemini` | `imagen`).
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use serde_json::{json, Value};
+
+use super::types::{
+    media_type_for, GeneratedImage, ImageApi, ImageData, ImageInput, ImageOptions, ImageResponse,
+};
+use crate::client::endpoint::lookup_endpoint;
+use crate::credentials::{self, primary_auth, resolve_credential};
+use crate::protocol::{ProtocolLoader, ProtocolManifest};
+use crate::{Error, ErrorContext, Result};
+
+/// Client for image generation, edits and variations.
+pub struct ImageClient {
+    http_client: reqwest::Client,
+    model: String,
+    base_url: String,
+    endpoint_path: String,
+    api: ImageApi,
+    api_key: String,
+    auth_header: Option<(String, String)>,
+}
+
+impl ImageClient {
+    pub fn builder() -> ImageClientBuilder {
+        ImageClientBuilder::new()
+    }
+
+    /// Text-to-image.
+    pub async fn generate(&self, prompt: &str, options: &ImageOptions) -> Result<ImageResponse> {
+        match self.api {
+            ImageApi::OpenAi => {
+                let mut body = json!({ "model": self.model, "prompt": prompt });
+                openai_options(&mut body, options);
+                if let Some(style) = &options.style {
+                    body["style"] = json!(style);
+                }
+                let response = self
+                    .send(self.request(&self.openai_url("generations")).json(&body))
+                    .await?;
+                Ok(parse_openai(&response, options))
+            }
+            ImageApi::Gemini => self.gemini(prompt, &[], options).await,
+            ImageApi::Imagen => {
+                let body = json!({
+                    "instances": [{ "prompt": prompt }],
+                    "parameters": imagen_parameters(options),
+                });
+                self.imagen(body).await
+            }
+        }
+    }
+
+    /// Edit `images` as described by `prompt`. With a `mask`, only its transparent
+    /// (OpenAI) or white (Imagen) area is repainted; Gemini takes no mask and edits
+    /// from the prompt alone.
+    pub async fn edit(
+        &self,
+        prompt: &str,
+        images: &[ImageInput],
+        mask: Option<&ImageInput>,
+        options: &ImageOptions,
+    ) -> Result<ImageResponse> {
+        if images.is_empty() {
+            return Err(Error::validation_with_context(
+                "image edit needs at least one input image",
+                ErrorContext::new()
+                    .with_source("images")
+                    .with_field_path("images"),
+            ));
+        }
+        match self.api {
+            ImageApi::OpenAi => {
+                let field = if images.len() > 1 { "image[]" } else { "image" };
+                let mut form = reqwest::multipart::Form::new()
+                    .text("model", self.model.clone())
+                    .text("prompt", prompt.to_string());
+                for (i, image) in images.iter().enumerate() {
+                    form = form.part(field, part(image, &format!("image{i}"))?);
+                }
+                if let Some(mask) = mask {
+                    form = form.part("mask", part(mask, "mask")?);
+                }
+                let form = openai_form(form, options);
+                let response = self
+                    .send(self.request(&self.openai_url("edits")).multipart(form))
+                    .await?;
+                Ok(parse_openai(&response, options))
+            }
+            ImageApi::Gemini => {
+                if mask.is_some() {
+                    return Err(unsupported(
+                        "Gemini image editing does not take a mask",
+                        "describe the region to change in the prompt instead",
+                    ));
+                }
+                self.gemini(prompt, images, options).await
+            }
+            ImageApi::Imagen => {
+                let mut references = vec![json!({
+                    "referenceType": "REFERENCE_TYPE_RAW",
+                    "referenceId": 1,
+                    "referenceImage": { "bytesBase64Encoded": images[0].base64() },
+                })];
+                if let Some(mask) = mask {
+                    references.push(json!({
+                        "referenceType": "REFERENCE_TYPE_MASK",
+                        "referenceId": 2,
+                        "referenceImage": { "bytesBase64Encoded": mask.base64() },
+                        "maskImageConfig": { "maskMode": "MASK_MODE_USER_PROVIDED" },
+                    }));
+                }
+                let mut parameters = imagen_parameters(options);
+                parameters["editMode"] = json!(if mask.is_some() {
+                    "EDIT_MODE_INPAINT_INSERTION"
+                } else {
+                    "EDIT_MODE_DEFAULT"
+                });
+                let body = json!({
+                    "instances": [{ "prompt": prompt, "referenceImages": references }],
+                    "parameters": parameters,
+                });
+                self.imagen(body).await
+            }
+        }
+    }
+
+    /// Variations of `image`. Gemini has no variations endpoint, so it is asked for
+    /// a variation in a prompt; Imagen is not supported.
+    pub async fn variations(
+        &self,
+        image: &ImageInput,
+        options: &ImageOptions,
+    ) -> Result<ImageResponse> {
+        match self.api {
+            ImageApi::OpenAi => {
+                let form = reqwest::multipart::Form::new()
+                    .text("model", self.model.clone())
+                    .part("image", part(image, "image")?);
+                let form = openai_form(form, options);
+                let response = self
+                    .send(self.request(&self.openai_url("variations")).multipart(form))
+                    .await?;
+                Ok(parse_openai(&response, options))
+            }
+            ImageApi::Gemini => {
+                self.gemini(
+                    "Create a variation of this image: keep its subject, composition and style.",
+                    std::slice::from_ref(image),
+                    options,
+                )
+                .await
+            }
+            ImageApi::Imagen => Err(unsupported(
+                "Imagen has no image variations API",
+                "use edit() with a prompt, or a Gemini image model",
+            )),
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn api(&self) -> ImageApi {
+        self.api
+    }
+
+    fn openai_url(&self, operation: &str) -> String {
+        format!(
+            "{}{}/{}",
+            self.base_url.trim_end_matches('/'),
+            self.endpoint_path.trim_end_matches('/'),
+            operation
+        )
+    }
+
+    fn model_url(&self, method: &str) -> String {
+        let model = self.model.trim_start_matches("models/");
+        let path = self.endpoint_path.replace("{model}", model);
+        format!(
+            "{}{}:{}",
+            self.base_url.trim_end_matches('/'),
+            path.trim_end_matches('/'),
+            method
+        )
+    }
+
+    fn request(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.http_client.post(url);
+        match &self.auth_header {
+            Some((name, prefix)) => request.header(name, format!("{prefix}{}", self.api_key)),
+            None => request.bearer_auth(&self.api_key),
+        }
+    }
+
+    async fn gemini(
+        &self,
+        prompt: &str,
+        images: &[ImageInput],
+        options: &ImageOptions,
+    ) -> Result<ImageResponse> {
+        let mut parts = vec![json!({ "text": prompt })];
+        parts.extend(images.iter().map(|image| {
+            json!({ "inlineData": { "mimeType": image.media_type, "data": image.base64() } })
+        }));
+        let mut generation = json!({ "responseModalities": ["TEXT", "IMAGE"] });
+        if let Some(n) = options.n {
+            generation["candidateCount"] = json!(n);
+        }
+        if let Some(seed) = options.seed {
+            generation["seed"] = json!(seed);
+        }
+        if let Some(ratio) = options.aspect_ratio() {
+            generation["imageConfig"] = json!({ "aspectRatio": ratio });
+        }
+        let body = json!({
+            "contents": [{ "role": "user", "parts": parts }],
+            "generationConfig": generation,
+        });
+        let response = self
+            .send(self.request(&self.model_url("generateContent")).json(&body))
+            .await?;
+
+        let mut images = Vec::new();
+        let mut text = String::new();
+        let candidates = response["candidates"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        for candidate in &candidates {
+            let parts = candidate
+                .pointer("/content/parts")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for part in parts {
+                if let Some(t) = part.get("text").and_then(Value::as_str) {
+                    text.push_str(t);
+                }
+                let inline = part.get("inlineData").or_else(|| part.get("inline_data"));
+                if let Some(image) = inline.and_then(|i| decoded(i, "data", "mimeType")) {
+                    images.push(image);
+                }
+            }
+        }
+        Ok(ImageResponse {
+            images,
+            text: (!text.is_empty()).then_some(text),
+            usage: response.get("usageMetadata").cloned(),
+        })
+    }
+
+    async fn imagen(&self, body: Value) -> Result<ImageResponse> {
+        let response = self
+            .send(self.request(&self.model_url("predict")).json(&body))
+            .await?;
+        let predictions = response["predictions"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let images = predictions
+            .iter()
+            .filter_map(|p| {
+                let mut image = decoded(p, "bytesBase64Encoded", "mimeType")?;
+                image.revised_prompt = p.get("prompt").and_then(Value::as_str).map(String::from);
+                Some(image)
+            })
+            .collect();
+        Ok(ImageResponse {
+            images,
+            text: None,
+            usage: None,
+        })
+    }
+
+    /// Send and parse the JSON body, failing on non-success status.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value> {
+        let response = request.send().await.map_err(|e| {
+            Error::network_with_context(
+                format!("Image request failed: {}", e),
+                ErrorContext::new().with_source("images"),
+            )
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body_str = response.text().await.unwrap_or_default();
+            return Err(Error::api_with_context(
+                format!("Image API error ({}): {}", status, body_str),
+                ErrorContext::new().with_source("images"),
+            ));
+        }
+        response.json().await.map_err(|e| {
+            Error::api_with_context(
+                format!("Failed to parse image response: {}", e),
+                ErrorContext::new().with_source("images"),
+            )
+        })
+    }
+}
+
+fn unsupported(message: &str, hint: &str) -> Error {
+    Error::validation_with_context(
+        message,
+        ErrorContext::new().with_source("images").with_hint(hint),
+    )
+}
+
+fn part(image: &ImageInput, stem: &str) -> Result<reqwest::multipart::Part> {
+    reqwest::multipart::Part::bytes(image.data.clone())
+        .file_name(image.file_name(stem))
+        .mime_str(&image.media_type)
+        .map_err(|e| Error::configuration(format!("Invalid mime: {}", e)))
+}
+
+fn openai_options(body: &mut Value, options: &ImageOptions) {
+    let fields = [
+        ("size", &options.size),
+        ("quality", &options.quality),
+        ("output_format", &options.output_format),
+        ("response_format", &options.response_format),
+        ("background", &options.background),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            body[key] = json!(value);
+        }
+    }
+    if let Some(n) = options.n {
+        body["n"] = json!(n);
+    }
+}
+
+fn openai_form(form: reqwest::multipart::Form, options: &ImageOptions) -> reqwest::multipart::Form {
+    let mut fields = json!({});
+    openai_options(&mut fields, options);
+    let mut form = form;
+    for (key, value) in fields.as_object().into_iter().flatten() {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        form = form.text(key.clone(), value);
+    }
+    form
+}
+
+fn parse_openai(response: &Value, options: &ImageOptions) -> ImageResponse {
+    let media_type = response
+        .get("output_format")
+        .and_then(Value::as_str)
+        .map(media_type_for)
+        .or_else(|| options.mime_type())
+        .unwrap_or_else(|| "image/png".to_string());
+    let data = response["data"].as_array().cloned().unwrap_or_default();
+    let images = data
+        .iter()
+        .filter_map(|item| {
+            let revised_prompt = item
+                .get("revised_prompt")
+                .and_then(Value::as_str)
+                .map(String::from);
+            if let Some(b64) = item.get("b64_json").and_then(Value::as_str) {
+                let bytes = BASE64.decode(b64).ok()?;
+                return Some(GeneratedImage {
+                    data: ImageData::Bytes(bytes),
+                    media_type: Some(media_type.clone()),
+                    revised_prompt,
+                });
+            }
+            let url = item.get("url").and_then(Value::as_str)?;
+            Some(GeneratedImage {
+                data: ImageData::Url(url.to_string()),
+                media_type: None,
+                revised_prompt,
+            })
+        })
+        .collect();
+    ImageResponse {
+        images,
+        text: None,
+        usage: response.get("usage").cloned(),
+    }
+}
+
+fn imagen_parameters(options: &ImageOptions) -> Value {
+    let mut parameters = json!({ "sampleCount": options.n.unwrap_or(1) });
+    if let Some(ratio) = options.aspect_ratio() {
+        parameters["aspectRatio"] = json!(ratio);
+    }
+    if let Some(negative) = &options.negative_prompt {
+        parameters["negativePrompt"] = json!(negative);
+    }
+    if let Some(seed) = options.seed {
+        parameters["seed"] = json!(seed);
+        // Imagen rejects a seed while its invisible watermark is on.
+        parameters["addWatermark"] = json!(false);
+    }
+    if let Some(mime) = options.mime_type() {
+        parameters["outputOptions"] = json!({ "mimeType": mime });
+    }
+    parameters
+}
+
+/// Base64 image at `data_key`, typed by `mime_key` (images only).
+fn decoded(value: &Value, data_key: &str, mime_key: &str) -> Option<GeneratedImage> {
+    let media_type = value
+        .get(mime_key)
+        .or_else(|| value.get("mime_type"))
+        .and_then(Value::as_str)
+        .unwrap_or("image/png");
+    if !media_type.starts_with("image/") {
+        return None;
+    }
+    let bytes = BASE64.decode(value.get(data_key)?.as_str()?).ok()?;
+    Some(GeneratedImage {
+        data: ImageData::Bytes(bytes),
+        media_type: Some(media_type.to_string()),
+        revised_prompt: None,
+    })
+}
+
+pub struct ImageClientBuilder {
+    model: Option<String>,
+    api_key: Option<String>,
+    base_url: Option<String>,
+    endpoint_path: Option<String>,
+    api: Option<ImageApi>,
+    auth_header: Option<(String, String)>,
+    timeout_secs: u64,
+    protocol_path: Option<String>,
+}
+
+impl ImageClientBuilder {
+    pub fn new() -> Self {
+        Self {
+            model: None,
+            api_key: None,
+            base_url: None,
+            endpoint_path: None,
+            api: None,
+            auth_header: None,
+            timeout_secs: 120,
+            protocol_path: None,
+        }
+    }
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+    /// Images root for OpenAI (default `/images`; `/generations`, `/edits` and
+    /// `/variations` are appended) or the model path for Gemini / Imagen (default
+    /// `/models/{model}`).
+    pub fn endpoint_path(mut self, path: impl Into<String>) -> Self {
+        self.endpoint_path = Some(path.into());
+        self
+    }
+    /// Force the API dialect instead of detecting it.
+    pub fn api(mut self, api: ImageApi) -> Self {
+        self.api = Some(api);
+        self
+    }
+    /// Send the key in `name` (after `prefix`) instead of `Authorization: Bearer`.
+    /// Gemini and Imagen default to `x-goog-api-key`.
+    pub fn auth_header(mut self, name: impl Into<String>, prefix: impl Into<String>) -> Self {
+        self.auth_header = Some((name.into(), prefix.into()));
+        self
+    }
+    pub fn timeout_secs(mut self, secs: u64) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+    pub fn protocol_path(mut self, path: impl Into<String>) -> Self {
+        self.protocol_path = Some(path.into());
+        self
+    }
+
+    /// Build from an already-loaded protocol manifest ([ARCH-001]).
+    pub fn from_manifest(
+        mut self,
+        manifest: &ProtocolManifest,
+        model_id: impl Into<String>,
+    ) -> Result<Self> {
+        let cred = resolve_credential(manifest, self.api_key.as_deref());
+        let secret = cred.secret().ok_or_else(|| {
+            Error::configuration(format!(
+                "API key required for images (provider={}; tried {:?})",
+                credentials::provider_id(manifest),
+                cred.required_envs
+                    .iter()
+                    .chain(cred.conventional_envs.iter())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            ))
+        })?;
+        self.api_key = Some(secret.to_string());
+        self.base_url = Some(manifest.get_base_url().to_string());
+        let model_id = model_id.into();
+        let endpoint = lookup_endpoint(manifest.endpoints.as_ref(), "images");
+        if self.api.is_none() {
+            self.api = Some(ImageApi::detect(
+                endpoint.and_then(|e| e.adapter.as_deref()),
+                manifest.get_base_url(),
+                &model_id,
+            ));
+        }
+        if self.endpoint_path.is_none() {
+            self.endpoint_path = endpoint.map(|e| e.path.clone()).or_else(|| {
+                manifest
+                    .services
+                    .as_ref()
+                    .and_then(|s| s.get("images"))
+                    .map(|s| s.path.clone())
+            });
+        }
+        if self.auth_header.is_none() {
+            if let Some(header) = primary_auth(manifest).and_then(|a| a.header_name.clone()) {
+                let prefix = primary_auth(manifest)
+                    .and_then(|a| a.prefix.clone())
+                    .unwrap_or_default();
+                self.auth_header = Some((header, prefix));
+            }
+        }
+        self.model = Some(model_id);
+        Ok(self)
+    }
+
+    /// Load provider/model via [`ProtocolLoader`] then build.
+    ///
+    /// `model` uses `provider/model-id` form (same as [`crate::AiClientBuilder`]).
+    pub async fn from_model(self, model: &str) -> Result<ImageClient> {
+        let mut loader = ProtocolLoader::new();
+        if let Some(path) = &self.protocol_path {
+            loader = loader.with_base_path(path);
+        }
+        let manifest = loader.load_model(model).await.map_err(Error::Protocol)?;
+        let model_id = match model.split_once('/') {
+            Some((_, id)) => id.to_string(),
+            None => model.to_string(),
+        };
+        self.from_manifest(&manifest, model_id)?.build().await
+    }
+
+    pub async fn build(self) -> Result<ImageClient> {
+        let model = self
+            .model
+            .ok_or_else(|| Error::configuration("Model must be specified"))?;
+        let api_key = self.api_key.ok_or_else(|| {
+            Error::configuration(
+                "API key required: use from_manifest/from_model or set api_key explicitly",
+            )
+        })?;
+        let base_url = self.base_url.ok_or_else(|| {
+            Error::configuration(
+                "base_url required: use from_manifest/from_model or set base_url explicitly (no vendor default)",
+            )
+        })?;
+        let api = self
+            .api
+            .unwrap_or_else(|| ImageApi::detect(None, &base_url, &model));
+        let endpoint_path = self.endpoint_path.unwrap_or_else(|| match api {
+            ImageApi::OpenAi => "/images".to_string(),
+            ImageApi::Gemini | ImageApi::Imagen => "/models/{model}".to_string(),
+        });
+        let endpoint_path = if endpoint_path.starts_with('/') {
+            endpoint_path
+        } else {
+            format!("/{}", endpoint_path)
+        };
+        // Manifests may name the generations endpoint itself.
+        let endpoint_path = endpoint_path
+            .trim_end_matches('/')
+            .trim_end_matches("/generations")
+            .to_string();
+        let auth_header = self.auth_header.or_else(|| match api {
+            ImageApi::OpenAi => None,
+            ImageApi::Gemini | ImageApi::Imagen => {
+                Some(("x-goog-api-key".to_string(), String::new()))
+            }
+        });
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(self.timeout_secs))
+            .build()
+            .map_err(|e| Error::configuration(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(ImageClient {
+            http_client,
+            model,
+            base_url,
+            endpoint_path,
+            api,
+            api_key,
+            auth_header,
+        })
+    }
+}
+
+impl Default for ImageClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
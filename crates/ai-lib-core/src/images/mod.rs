@@ -0,0 +1,31 @@
+//! Images（图像生成）模块：通过 OpenAI Images、Gemini / Imagen 或 OpenAI 兼容服务进行文生图、带遮罩编辑与变体生成。
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`ImageClient::generate`] | Text-to-image |
+//! | [`ImageClient::edit`] | Edit input images, optionally inside a mask |
+//! | [`ImageClient::variations`] | Variations of an input image |
+//! | [`ImageResponse`] | [`GeneratedImage`]s as bytes or URLs, revised prompts, accompanying text and usage |
+//! | [`ImageApi`] | Wire dialect, from the manifest's `endpoints.images.adapter` |
+//!
+//! Images a chat model returns inline are available as
+//! [`UnifiedResponse::images`](crate::client::UnifiedResponse::images).
+//!
+//! ```rust,no_run
+//! use ai_lib_core::images::{ImageClient, ImageOptions};
+//!
+//! # async fn demo() -> ai_lib_core::Result<()> {
+//! let client = ImageClient::builder().from_model("openai/gpt-image-1").await?;
+//! let options = ImageOptions { size: Some("1024x1024".into()), ..Default::default() };
+//! let response = client.generate("A lighthouse at dusk, watercolor", &options).await?;
+//! if let Some(png) = response.images[0].bytes() {
+//!     std::fs::write("lighthouse.png", png)?;
+//! }
+//! # Ok(()) }
+//! ```
+
+mod client;
+mod types;
+
+pub use client::{ImageClient, ImageClientBuilder};
+pub use types::{GeneratedImage, ImageApi, ImageData, ImageInput, ImageOptions, ImageResponse};
@@ -0,0 +1,230 @@
+//! Image generation types.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use serde_json::Value;
+
+use crate::types::message::ContentBlock;
+
+/// Which image API an endpoint speaks.
+///
+/// Selected by the `adapter` of the manifest's `endpoints.images` entry, or
+/// guessed from the base URL and model when no entry is declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageApi {
+    /// OpenAI Images (`/images/generations`, `/images/edits`, `/images/variations`)
+    /// and OpenAI-compatible servers.
+    #[default]
+    OpenAi,
+    /// Gemini `generateContent` with image output.
+    Gemini,
+    /// Imagen `:predict`.
+    Imagen,
+}
+
+impl ImageApi {
+    pub(crate) fn detect(adapter: Option<&str>, base_url: &str, model: &str) -> Self {
+        match adapter {
+            Some(a) if a.starts_with("imagen") => Self::Imagen,
+            Some(a) if a.starts_with("gemini") || a.starts_with("google") => Self::Gemini,
+            Some(_) => Self::OpenAi,
+            None if base_url.contains("generativelanguage") || base_url.contains("aiplatform") => {
+                if model.starts_with("imagen") {
+                    Self::Imagen
+                } else {
+                    Self::Gemini
+                }
+            }
+            None => Self::OpenAi,
+        }
+    }
+}
+
+/// Options for generation, edits and variations. Unset fields use provider defaults;
+/// fields a provider has no equivalent for are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct ImageOptions {
+    /// Number of images.
+    pub n: Option<u32>,
+    /// `"1024x1024"` etc. (OpenAI).
+    pub size: Option<String>,
+    /// `"16:9"` etc. (Gemini / Imagen); derived from `size` when unset.
+    pub aspect_ratio: Option<String>,
+    pub quality: Option<String>,
+    pub style: Option<String>,
+    /// `"png"`, `"jpeg"` or `"webp"`.
+    pub output_format: Option<String>,
+    /// `"b64_json"` or `"url"` (OpenAI; GPT image models always return base64).
+    pub response_format: Option<String>,
+    /// What to keep out of the image (Imagen).
+    pub negative_prompt: Option<String>,
+    pub seed: Option<i64>,
+    /// `"transparent"`, `"opaque"` or `"auto"` (OpenAI).
+    pub background: Option<String>,
+}
+
+impl ImageOptions {
+    pub(crate) fn aspect_ratio(&self) -> Option<String> {
+        if let Some(ratio) = &self.aspect_ratio {
+            return Some(ratio.clone());
+        }
+        let (w, h) = self.size.as_deref()?.split_once('x')?;
+        let (w, h): (u32, u32) = (w.trim().parse().ok()?, h.trim().parse().ok()?);
+        if w == 0 || h == 0 {
+            return None;
+        }
+        let (mut a, mut b) = (w, h);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        Some(format!("{}:{}", w / a, h / a))
+    }
+
+    pub(crate) fn mime_type(&self) -> Option<String> {
+        self.output_format.as_deref().map(media_type_for)
+    }
+}
+
+/// `"png"` → `"image/png"`; `"jpg"` → `"image/jpeg"`.
+pub(crate) fn media_type_for(format: &str) -> String {
+    match format {
+        "jpg" | "jpeg" => "image/jpeg".to_string(),
+        other => format!("image/{other}"),
+    }
+}
+
+/// An input image for edits and variations.
+#[derive(Debug, Clone)]
+pub struct ImageInput {
+    pub data: Vec<u8>,
+    pub media_type: String,
+}
+
+impl ImageInput {
+    pub fn new(data: Vec<u8>, media_type: impl Into<String>) -> Self {
+        Self {
+            data,
+            media_type: media_type.into(),
+        }
+    }
+
+    pub fn png(data: Vec<u8>) -> Self {
+        Self::new(data, "image/png")
+    }
+
+    pub(crate) fn file_name(&self, stem: &str) -> String {
+        let ext = match self.media_type.as_str() {
+            "image/jpeg" => "jpg",
+            other => other.strip_prefix("image/").unwrap_or("png"),
+        };
+        format!("{stem}.{ext}")
+    }
+
+    pub(crate) fn base64(&self) -> String {
+        BASE64.encode(&self.data)
+    }
+}
+
+/// Image content: decoded bytes, or a URL the provider hosts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageData {
+    Bytes(Vec<u8>),
+    Url(String),
+}
+
+/// One generated image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedImage {
+    pub data: ImageData,
+    /// Known for base64 results (`image/png` unless another format was requested).
+    pub media_type: Option<String>,
+    /// The prompt the provider actually used, when it rewrote it.
+    pub revised_prompt: Option<String>,
+}
+
+impl GeneratedImage {
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.data {
+            ImageData::Bytes(bytes) => Some(bytes),
+            ImageData::Url(_) => None,
+        }
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        match &self.data {
+            ImageData::Url(url) => Some(url),
+            ImageData::Bytes(_) => None,
+        }
+    }
+
+    /// As a message content block, e.g. to show the image to a chat model.
+    pub fn to_content_block(&self) -> ContentBlock {
+        match &self.data {
+            ImageData::Bytes(bytes) => {
+                ContentBlock::image_base64(BASE64.encode(bytes), self.media_type.clone())
+            }
+            ImageData::Url(url) => ContentBlock::image_url(url.clone()),
+        }
+    }
+}
+
+/// Result of an image request.
+#[derive(Debug, Clone, Default)]
+pub struct ImageResponse {
+    pub images: Vec<GeneratedImage>,
+    /// Text the model returned alongside the images (Gemini).
+    pub text: Option<String>,
+    /// Provider usage block as returned (`usage` or `usageMetadata`).
+    pub usage: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aspect_ratio_from_size() {
+        let options = |size: &str| ImageOptions {
+            size: Some(size.into()),
+            ..Default::default()
+        };
+        assert_eq!(options("1024x1024").aspect_ratio().as_deref(), Some("1:1"));
+        assert_eq!(options("1792x1024").aspect_ratio().as_deref(), Some("7:4"));
+        assert_eq!(options("1920x1080").aspect_ratio().as_deref(), Some("16:9"));
+        assert_eq!(options("auto").aspect_ratio(), None);
+        let explicit = ImageOptions {
+            size: Some("1024x1024".into()),
+            aspect_ratio: Some("3:4".into()),
+            ..Default::default()
+        };
+        assert_eq!(explicit.aspect_ratio().as_deref(), Some("3:4"));
+    }
+
+    #[test]
+    fn test_api_detection() {
+        assert_eq!(
+            ImageApi::detect(Some("imagen"), "https://x", "m"),
+            ImageApi::Imagen
+        );
+        assert_eq!(
+            ImageApi::detect(
+                None,
+                "https://generativelanguage.googleapis.com/v1beta",
+                "imagen-4.0"
+            ),
+            ImageApi::Imagen
+        );
+        assert_eq!(
+            ImageApi::detect(
+                None,
+                "https://generativelanguage.googleapis.com/v1beta",
+                "gemini-2.5-flash-image"
+            ),
+            ImageApi::Gemini
+        );
+        assert_eq!(
+            ImageApi::detect(None, "http://localhost:8080/v1", "sd"),
+            ImageApi::OpenAi
+        );
+    }
+}
@@ -4,8 +4,8 @@
 //!
 //! Execution-layer runtime for AI-Protocol (protocol, client, pipeline, transport, types).
 //!
-//! On `wasm32` targets, only protocol parsing, drivers, types, and structured helpers are built
-//! (no async client, transport, or pipeline). See PT-072 / `ai-lib-wasm`.
+//! On `wasm32` targets, only protocol parsing, drivers, types, structured helpers and the
+//! streaming pipeline operators are built (no async client or transport). See PT-072 / `ai-lib-wasm`.
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod client;
@@ -14,7 +14,6 @@ pub mod credentials;
 pub mod drivers;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod feedback;
-#[cfg(not(target_arch = "wasm32"))]
 pub mod pipeline;
 pub mod protocol;
 #[cfg(not(target_arch = "wasm32"))]
@@ -25,18 +24,30 @@ pub mod transport;
 pub mod types;
 pub mod utils;
 
+#[cfg(all(not(target_arch = "wasm32"), feature = "batch"))]
+pub mod batch;
 #[cfg(all(not(target_arch = "wasm32"), feature = "computer_use"))]
 pub mod computer_use;
 #[cfg(all(not(target_arch = "wasm32"), feature = "embeddings"))]
 pub mod embeddings;
+#[cfg(all(not(target_arch = "wasm32"), feature = "files"))]
+pub mod files;
+#[cfg(all(not(target_arch = "wasm32"), feature = "images"))]
+pub mod images;
 #[cfg(all(not(target_arch = "wasm32"), feature = "mcp"))]
 pub mod mcp;
 #[cfg(all(not(target_arch = "wasm32"), feature = "multimodal"))]
 pub mod multimodal;
+#[cfg(all(not(target_arch = "wasm32"), feature = "prompts"))]
+pub mod prompts;
+#[cfg(all(not(target_arch = "wasm32"), feature = "realtime"))]
+pub mod realtime;
 #[cfg(all(not(target_arch = "wasm32"), feature = "reranking"))]
 pub mod rerank;
 #[cfg(all(not(target_arch = "wasm32"), feature = "stt"))]
 pub mod stt;
+#[cfg(all(not(target_arch = "wasm32"), feature = "testing"))]
+pub mod testing;
 #[cfg(all(not(target_arch = "wasm32"), feature = "tts"))]
 pub mod tts;
 
@@ -67,9 +78,7 @@ pub use types::text_tool::{
     StandardTextToolParser, TextToolConfig, TextToolDeviation, TextToolParser, ToolCallingPolicy,
 };
 
-#[cfg(not(target_arch = "wasm32"))]
 use futures::Stream;
-#[cfg(not(target_arch = "wasm32"))]
 use std::pin::Pin;
 
 /// Result type alias for the library
@@ -79,7 +88,6 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub type PipeResult<T> = std::result::Result<T, Error>;
 
 /// A unified pinned, boxed stream that emits `PipeResult<T>`
-#[cfg(not(target_arch = "wasm32"))]
 pub type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = PipeResult<T>> + Send + 'a>>;
 
 pub mod error;
@@ -6,6 +6,10 @@
 //! - Provider-specific content formatting helpers
 //! - Input modality detection and validation
 //! - Output modality negotiation
+//! - Image resizing / re-encoding to provider limits ([`preprocess`], `image_processing` feature)
+
+#[cfg(feature = "image_processing")]
+pub mod preprocess;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
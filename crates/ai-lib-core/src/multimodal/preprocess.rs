@@ -0,0 +1,737 @@
+//! Image preprocessing to provider limits (`image_processing` feature).
+//!
+//! 图像预处理：按 manifest `multimodal.input.vision` 声明的最大分辨率、最大字节数与支持格式，
+//! 在编码前对图像进行缩放、转码、去除 EXIF 或切片，并估算每张图像的 token 开销。
+//!
+//! Limits come from [`VisionConfig`]: `max_resolution` (`"8000x8000"`, or `"1568"`
+//! for the long edge), `max_file_size` (`"5MB"`, `"500KB"`) and `formats`. Images
+//! already within limits and without metadata are passed through byte-for-byte.
+
+use std::io::Cursor;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+
+use crate::protocol::v2::manifest::{ApiStyle, ManifestV2, VisionConfig};
+use crate::types::message::{ContentBlock, Message, MessageContent};
+use crate::{Error, ErrorContext, Result};
+
+/// Encodings the preprocessor can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageEncoding {
+    Png,
+    Jpeg,
+    /// Lossless WebP.
+    WebP,
+}
+
+impl ImageEncoding {
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::WebP => "image/webp",
+        }
+    }
+
+    fn names(self) -> &'static [&'static str] {
+        match self {
+            Self::Png => &["png"],
+            Self::Jpeg => &["jpeg", "jpg"],
+            Self::WebP => &["webp"],
+        }
+    }
+
+    fn from_format(format: ImageFormat) -> Option<Self> {
+        match format {
+            ImageFormat::Png => Some(Self::Png),
+            ImageFormat::Jpeg => Some(Self::Jpeg),
+            ImageFormat::WebP => Some(Self::WebP),
+            _ => None,
+        }
+    }
+}
+
+/// How a provider bills image input, used for [`ImageCost::estimated_tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageTokenModel {
+    /// 85 + 170 per 512px tile after fitting 2048×2048 and a 768px short side.
+    #[default]
+    OpenAi,
+    /// `width × height / 750`.
+    Anthropic,
+    /// 258 per 768px tile; 258 flat up to 384×384.
+    Gemini,
+}
+
+impl ImageTokenModel {
+    pub fn from_api_style(style: ApiStyle) -> Self {
+        match style {
+            ApiStyle::AnthropicMessages => Self::Anthropic,
+            ApiStyle::GeminiGenerate => Self::Gemini,
+            ApiStyle::OpenAiCompatible | ApiStyle::Custom => Self::OpenAi,
+        }
+    }
+
+    /// Estimated input tokens for one image of `width × height` pixels.
+    pub fn estimate(self, width: u32, height: u32) -> u32 {
+        let (w, h) = (f64::from(width.max(1)), f64::from(height.max(1)));
+        match self {
+            Self::OpenAi => {
+                let fit = (2048.0 / w.max(h)).min(1.0);
+                let (w, h) = (w * fit, h * fit);
+                let short = (768.0 / w.min(h)).min(1.0);
+                let (w, h) = (w * short, h * short);
+                let tiles = (w / 512.0).ceil() * (h / 512.0).ceil();
+                85 + 170 * tiles as u32
+            }
+            Self::Anthropic => (w * h / 750.0).ceil() as u32,
+            Self::Gemini if width <= 384 && height <= 384 => 258,
+            Self::Gemini => 258 * ((w / 768.0).ceil() * (h / 768.0).ceil()) as u32,
+        }
+    }
+}
+
+/// What to do with images larger than the provider's maximum resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizePolicy {
+    /// Downscale to fit, keeping the aspect ratio.
+    #[default]
+    Resize,
+    /// Fit the short side, then cut the long side into tiles that each fit, so
+    /// tall screenshots and panoramas stay legible.
+    Tile,
+}
+
+/// Provider image limits; `None` / empty means unrestricted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageLimits {
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub max_bytes: Option<usize>,
+    /// Accepted format names (`"png"`, `"jpeg"`, `"webp"`, …).
+    pub formats: Vec<String>,
+}
+
+impl ImageLimits {
+    /// Parse `max_resolution`, `max_file_size` and `formats` from a manifest vision section.
+    pub fn from_vision(vision: &VisionConfig) -> Self {
+        let (max_width, max_height) = vision
+            .max_resolution
+            .as_deref()
+            .and_then(parse_resolution)
+            .map_or((None, None), |(w, h)| (Some(w), Some(h)));
+        Self {
+            max_width,
+            max_height,
+            max_bytes: vision.max_file_size.as_deref().and_then(parse_size),
+            formats: vision.formats.clone(),
+        }
+    }
+
+    fn accepts(&self, encoding: ImageEncoding) -> bool {
+        self.formats.is_empty()
+            || self.formats.iter().any(|f| {
+                let f = f.trim_start_matches("image/");
+                encoding.names().iter().any(|n| f.eq_ignore_ascii_case(n))
+            })
+    }
+}
+
+/// `"8000x8000"` → (8000, 8000); `"1568"` → (1568, 1568).
+fn parse_resolution(s: &str) -> Option<(u32, u32)> {
+    let s = s.trim();
+    match s.split_once(['x', 'X', '*']) {
+        Some((w, h)) => Some((w.trim().parse().ok()?, h.trim().parse().ok()?)),
+        None => s.parse().ok().map(|edge| (edge, edge)),
+    }
+}
+
+/// `"20MB"` → 20_000_000; `"500KB"`, `"1.5 MB"` and plain byte counts also parse.
+fn parse_size(s: &str) -> Option<usize> {
+    let s = s.trim().to_ascii_uppercase();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let scale = match unit.trim().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1.0,
+        "K" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        _ => return None,
+    };
+    Some((number * scale) as usize)
+}
+
+/// Size and estimated token cost of one image as sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageCost {
+    pub width: u32,
+    pub height: u32,
+    pub bytes: usize,
+    pub media_type: String,
+    pub estimated_tokens: u32,
+}
+
+/// An image ready to send.
+#[derive(Debug, Clone)]
+pub struct PreparedImage {
+    pub data: Vec<u8>,
+    pub cost: ImageCost,
+}
+
+impl PreparedImage {
+    pub fn to_content_block(&self) -> ContentBlock {
+        ContentBlock::image_base64(
+            BASE64.encode(&self.data),
+            Some(self.cost.media_type.clone()),
+        )
+    }
+}
+
+/// What happened to one input image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageReport {
+    pub original_width: u32,
+    pub original_height: u32,
+    pub original_bytes: usize,
+    /// `false` when the original bytes were sent unchanged.
+    pub modified: bool,
+    /// One entry, or one per tile.
+    pub outputs: Vec<ImageCost>,
+}
+
+impl ImageReport {
+    pub fn estimated_tokens(&self) -> u32 {
+        self.outputs.iter().map(|o| o.estimated_tokens).sum()
+    }
+}
+
+/// Result of [`ImagePreprocessor::process_bytes`].
+#[derive(Debug, Clone)]
+pub struct PreparedImages {
+    /// One image, or the tiles in reading order.
+    pub images: Vec<PreparedImage>,
+    pub report: ImageReport,
+}
+
+/// Per-request summary from [`ImagePreprocessor::process_messages`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreprocessReport {
+    pub images: Vec<ImageReport>,
+}
+
+impl PreprocessReport {
+    pub fn estimated_tokens(&self) -> u32 {
+        self.images.iter().map(ImageReport::estimated_tokens).sum()
+    }
+
+    pub fn modified(&self) -> usize {
+        self.images.iter().filter(|i| i.modified).count()
+    }
+}
+
+/// Brings images within a provider's limits before they are encoded into a request.
+#[derive(Debug, Clone)]
+pub struct ImagePreprocessor {
+    limits: ImageLimits,
+    token_model: ImageTokenModel,
+    oversize: OversizePolicy,
+    max_long_edge: Option<u32>,
+    strip_metadata: bool,
+    jpeg_quality: u8,
+}
+
+impl ImagePreprocessor {
+    pub fn new(limits: ImageLimits) -> Self {
+        Self {
+            limits,
+            token_model: ImageTokenModel::default(),
+            oversize: OversizePolicy::default(),
+            max_long_edge: None,
+            strip_metadata: true,
+            jpeg_quality: 85,
+        }
+    }
+
+    /// Limits from `multimodal.input.vision` and the token model from the API style.
+    pub fn from_manifest(manifest: &ManifestV2) -> Self {
+        let vision = manifest
+            .multimodal
+            .as_ref()
+            .and_then(|m| m.input.as_ref())
+            .and_then(|i| i.vision.as_ref());
+        let limits = vision.map(ImageLimits::from_vision).unwrap_or_default();
+        Self::new(limits)
+            .with_token_model(ImageTokenModel::from_api_style(manifest.detect_api_style()))
+    }
+
+    pub fn with_token_model(mut self, model: ImageTokenModel) -> Self {
+        self.token_model = model;
+        self
+    }
+
+    pub fn with_oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.oversize = policy;
+        self
+    }
+
+    /// Downscale further so neither side exceeds `pixels` (e.g. 1568 to avoid
+    /// server-side resizing, or less to save tokens).
+    pub fn with_max_long_edge(mut self, pixels: u32) -> Self {
+        self.max_long_edge = Some(pixels);
+        self
+    }
+
+    /// Re-encode images that carry EXIF metadata (default `true`). Orientation is
+    /// applied to the pixels first, so rotated phone photos stay upright.
+    pub fn with_strip_metadata(mut self, strip: bool) -> Self {
+        self.strip_metadata = strip;
+        self
+    }
+
+    /// Starting JPEG quality; lowered when an image must shrink to `max_bytes`.
+    pub fn with_jpeg_quality(mut self, quality: u8) -> Self {
+        self.jpeg_quality = quality.clamp(1, 100);
+        self
+    }
+
+    pub fn limits(&self) -> &ImageLimits {
+        &self.limits
+    }
+
+    /// Read and prepare an image file.
+    pub fn process_file(&self, path: impl AsRef<Path>) -> Result<PreparedImages> {
+        self.process_bytes(&std::fs::read(path)?)
+    }
+
+    /// Prepare encoded image bytes (PNG, JPEG or WebP).
+    pub fn process_bytes(&self, bytes: &[u8]) -> Result<PreparedImages> {
+        let reader = ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|e| invalid(format!("Unreadable image: {}", e)))?;
+        let source = reader.format().and_then(ImageEncoding::from_format);
+        let mut decoder = reader
+            .into_decoder()
+            .map_err(|e| invalid(format!("Unsupported image: {}", e)))?;
+        let exif = decoder.exif_metadata().ok().flatten();
+        let orientation = exif
+            .as_deref()
+            .and_then(Orientation::from_exif_chunk)
+            .unwrap_or(Orientation::NoTransforms);
+        let mut img = DynamicImage::from_decoder(decoder)
+            .map_err(|e| invalid(format!("Failed to decode image: {}", e)))?;
+        let (original_width, original_height) = (img.width(), img.height());
+        img.apply_orientation(orientation);
+
+        let (max_w, max_h) = self.max_dimensions();
+        let oversized = img.width() > max_w || img.height() > max_h;
+        let too_large = self.limits.max_bytes.is_some_and(|max| bytes.len() > max);
+        let keep = source.filter(|s| self.limits.accepts(*s));
+        let rotated = orientation != Orientation::NoTransforms;
+
+        if let Some(encoding) = keep {
+            let has_metadata = self.strip_metadata && exif.is_some();
+            if !(oversized || too_large || rotated || has_metadata) {
+                let cost = self.cost(img.width(), img.height(), bytes.len(), encoding);
+                return Ok(PreparedImages {
+                    report: ImageReport {
+                        original_width,
+                        original_height,
+                        original_bytes: bytes.len(),
+                        modified: false,
+                        outputs: vec![cost.clone()],
+                    },
+                    images: vec![PreparedImage {
+                        data: bytes.to_vec(),
+                        cost,
+                    }],
+                });
+            }
+        }
+
+        let pieces = if oversized {
+            match self.oversize {
+                OversizePolicy::Resize => vec![fit(&img, max_w, max_h)],
+                OversizePolicy::Tile => tile(&img, max_w, max_h),
+            }
+        } else {
+            vec![img]
+        };
+        let encoding =
+            keep.unwrap_or_else(|| self.fallback_encoding(pieces[0].color().has_alpha()));
+        let images = pieces
+            .iter()
+            .map(|piece| self.encode_within_limit(piece, encoding))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PreparedImages {
+            report: ImageReport {
+                original_width,
+                original_height,
+                original_bytes: bytes.len(),
+                modified: true,
+                outputs: images.iter().map(|i| i.cost.clone()).collect(),
+            },
+            images,
+        })
+    }
+
+    /// Prepare a content block; non-image and non-base64 blocks are returned as is.
+    pub fn process_block(
+        &self,
+        block: &ContentBlock,
+    ) -> Result<(Vec<ContentBlock>, Option<ImageReport>)> {
+        match block {
+            ContentBlock::Image { source } if source.source_type == "base64" => {
+                let bytes = BASE64
+                    .decode(&source.data)
+                    .map_err(|e| invalid(format!("Invalid base64 image: {}", e)))?;
+                let prepared = self.process_bytes(&bytes)?;
+                if !prepared.report.modified {
+                    return Ok((vec![block.clone()], Some(prepared.report)));
+                }
+                let blocks = prepared
+                    .images
+                    .iter()
+                    .map(PreparedImage::to_content_block)
+                    .collect();
+                Ok((blocks, Some(prepared.report)))
+            }
+            other => Ok((vec![other.clone()], None)),
+        }
+    }
+
+    /// Prepare every inline image in `messages` in place (tiles become consecutive
+    /// image blocks) and report sizes and estimated tokens.
+    pub fn process_messages(&self, messages: &mut [Message]) -> Result<PreprocessReport> {
+        let mut report = PreprocessReport::default();
+        for message in messages.iter_mut() {
+            let MessageContent::Blocks(blocks) = &mut message.content else {
+                continue;
+            };
+            let mut processed = Vec::with_capacity(blocks.len());
+            for block in blocks.iter() {
+                let (new_blocks, image) = self.process_block(block)?;
+                processed.extend(new_blocks);
+                report.images.extend(image);
+            }
+            *blocks = processed;
+        }
+        Ok(report)
+    }
+
+    fn max_dimensions(&self) -> (u32, u32) {
+        let edge = self.max_long_edge.unwrap_or(u32::MAX);
+        (
+            self.limits.max_width.unwrap_or(u32::MAX).min(edge),
+            self.limits.max_height.unwrap_or(u32::MAX).min(edge),
+        )
+    }
+
+    /// JPEG for opaque images, PNG (or lossless WebP) when transparency must survive.
+    fn fallback_encoding(&self, has_alpha: bool) -> ImageEncoding {
+        let order: [ImageEncoding; 3] = if has_alpha {
+            [ImageEncoding::Png, ImageEncoding::WebP, ImageEncoding::Jpeg]
+        } else {
+            [ImageEncoding::Jpeg, ImageEncoding::Png, ImageEncoding::WebP]
+        };
+        order
+            .into_iter()
+            .find(|e| self.limits.accepts(*e))
+            .unwrap_or(ImageEncoding::Jpeg)
+    }
+
+    /// Encode, then lower JPEG quality and downscale until `max_bytes` is met.
+    fn encode_within_limit(
+        &self,
+        img: &DynamicImage,
+        encoding: ImageEncoding,
+    ) -> Result<PreparedImage> {
+        let Some(max_bytes) = self.limits.max_bytes else {
+            return self.encode(img, encoding, self.jpeg_quality);
+        };
+        let mut img = img.clone();
+        let mut encoding = encoding;
+        let mut quality = self.jpeg_quality;
+        for _ in 0..12 {
+            let prepared = self.encode(&img, encoding, quality)?;
+            if prepared.data.len() <= max_bytes {
+                return Ok(prepared);
+            }
+            if encoding != ImageEncoding::Jpeg
+                && !img.color().has_alpha()
+                && self.limits.accepts(ImageEncoding::Jpeg)
+            {
+                encoding = ImageEncoding::Jpeg;
+            } else if encoding == ImageEncoding::Jpeg && quality > 50 {
+                quality = quality.saturating_sub(15).max(50);
+            } else {
+                let (w, h) = (img.width() * 4 / 5, img.height() * 4 / 5);
+                img = img.resize(w.max(1), h.max(1), FilterType::Lanczos3);
+            }
+        }
+        Err(Error::validation_with_context(
+            format!("Could not shrink image below {} bytes", max_bytes),
+            ErrorContext::new()
+                .with_source("image_preprocess")
+                .with_hint("Crop the image or send it by URL / Files API instead"),
+        ))
+    }
+
+    fn encode(
+        &self,
+        img: &DynamicImage,
+        encoding: ImageEncoding,
+        quality: u8,
+    ) -> Result<PreparedImage> {
+        let mut data = Vec::new();
+        let result = match encoding {
+            ImageEncoding::Png => img.write_to(&mut Cursor::new(&mut data), ImageFormat::Png),
+            ImageEncoding::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8())
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut data, quality)),
+            ImageEncoding::WebP => DynamicImage::ImageRgba8(img.to_rgba8())
+                .write_with_encoder(WebPEncoder::new_lossless(&mut data)),
+        };
+        result.map_err(|e| invalid(format!("Failed to encode image: {}", e)))?;
+        let cost = self.cost(img.width(), img.height(), data.len(), encoding);
+        Ok(PreparedImage { data, cost })
+    }
+
+    fn cost(&self, width: u32, height: u32, bytes: usize, encoding: ImageEncoding) -> ImageCost {
+        ImageCost {
+            width,
+            height,
+            bytes,
+            media_type: encoding.media_type().to_string(),
+            estimated_tokens: self.token_model.estimate(width, height),
+        }
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::validation_with_context(message, ErrorContext::new().with_source("image_preprocess"))
+}
+
+fn fit(img: &DynamicImage, max_w: u32, max_h: u32) -> DynamicImage {
+    img.resize(
+        max_w.min(img.width()),
+        max_h.min(img.height()),
+        FilterType::Lanczos3,
+    )
+}
+
+/// Scale so the short side fits, then cut the long side into equal tiles that fit.
+fn tile(img: &DynamicImage, max_w: u32, max_h: u32) -> Vec<DynamicImage> {
+    let (w, h) = (img.width(), img.height());
+    let vertical = u64::from(h) * u64::from(max_w) >= u64::from(w) * u64::from(max_h);
+    let scale = if vertical {
+        (f64::from(max_w) / f64::from(w)).min(1.0)
+    } else {
+        (f64::from(max_h) / f64::from(h)).min(1.0)
+    };
+    let (sw, sh) = (
+        ((f64::from(w) * scale).round() as u32).max(1),
+        ((f64::from(h) * scale).round() as u32).max(1),
+    );
+    let scaled = if (sw, sh) == (w, h) {
+        img.clone()
+    } else {
+        img.resize_exact(sw, sh, FilterType::Lanczos3)
+    };
+    let (length, max) = if vertical { (sh, max_h) } else { (sw, max_w) };
+    let count = length.div_ceil(max);
+    let step = length.div_ceil(count);
+    (0..count)
+        .map(|i| {
+            let start = i * step;
+            let size = step.min(length - start);
+            if vertical {
+                scaled.crop_imm(0, start, sw, size)
+            } else {
+                scaled.crop_imm(start, 0, size, sh)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(parse_resolution("8000x8000"), Some((8000, 8000)));
+        assert_eq!(parse_resolution("1568"), Some((1568, 1568)));
+        assert_eq!(parse_size("20MB"), Some(20_000_000));
+        assert_eq!(parse_size("500 KB"), Some(500_000));
+        assert_eq!(parse_size("1.5MiB"), Some(1_500_000));
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("lots"), None);
+    }
+
+    #[test]
+    fn test_token_estimates() {
+        assert_eq!(ImageTokenModel::OpenAi.estimate(1024, 1024), 765);
+        assert_eq!(ImageTokenModel::OpenAi.estimate(2048, 4096), 1105);
+        assert_eq!(ImageTokenModel::Anthropic.estimate(1000, 1000), 1334);
+        assert_eq!(ImageTokenModel::Gemini.estimate(300, 300), 258);
+        assert_eq!(ImageTokenModel::Gemini.estimate(1024, 1024), 1032);
+    }
+
+    #[test]
+    fn test_tiles_cover_the_long_side() {
+        let img = DynamicImage::new_rgb8(1000, 5000);
+        let tiles = tile(&img, 500, 1000);
+        assert_eq!(tiles.len(), 3);
+        assert!(tiles.iter().all(|t| t.width() == 500 && t.height() <= 1000));
+        assert_eq!(tiles.iter().map(DynamicImage::height).sum::<u32>(), 2500);
+    }
+
+    fn encoded(img: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        img.write_to(&mut Cursor::new(&mut data), format).unwrap();
+        data
+    }
+
+    /// JPEG with an EXIF APP1 segment carrying `Orientation = 6` (rotate 90° CW).
+    fn rotated_phone_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let jpeg = encoded(&DynamicImage::new_rgb8(width, height), ImageFormat::Jpeg);
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+        exif.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, 0, 0, 0, 0]);
+        let len = (exif.len() + 2) as u16;
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(&exif);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    #[test]
+    fn test_within_limits_passes_through() {
+        let png = encoded(&DynamicImage::new_rgba8(64, 32), ImageFormat::Png);
+        let prepared = ImagePreprocessor::new(ImageLimits::default())
+            .process_bytes(&png)
+            .unwrap();
+        assert!(!prepared.report.modified);
+        assert_eq!(prepared.images[0].data, png);
+        assert_eq!(prepared.images[0].cost.media_type, "image/png");
+    }
+
+    #[test]
+    fn test_exif_orientation_applied_and_stripped() {
+        let jpeg = rotated_phone_jpeg(40, 20);
+        let prepared = ImagePreprocessor::new(ImageLimits::default())
+            .process_bytes(&jpeg)
+            .unwrap();
+        assert!(prepared.report.modified);
+        let out = &prepared.images[0];
+        assert_eq!((out.cost.width, out.cost.height), (20, 40));
+        let mut decoder = ImageReader::new(Cursor::new(&out.data))
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        assert_eq!(decoder.exif_metadata().unwrap(), None);
+    }
+
+    #[test]
+    fn test_resize_and_convert_to_accepted_format() {
+        let png = encoded(&DynamicImage::new_rgb8(3000, 1500), ImageFormat::Png);
+        let limits = ImageLimits {
+            max_width: Some(1000),
+            max_height: Some(1000),
+            formats: vec!["jpeg".into(), "webp".into()],
+            ..Default::default()
+        };
+        let prepared = ImagePreprocessor::new(limits)
+            .with_token_model(ImageTokenModel::Anthropic)
+            .process_bytes(&png)
+            .unwrap();
+        let cost = &prepared.images[0].cost;
+        assert_eq!((cost.width, cost.height), (1000, 500));
+        assert_eq!(cost.media_type, "image/jpeg");
+        assert_eq!(cost.estimated_tokens, 667);
+    }
+
+    #[test]
+    fn test_byte_limit_shrinks_until_it_fits() {
+        let noise = image::RgbImage::from_fn(512, 512, |x, y| {
+            let v = (x.wrapping_mul(2_654_435_761) ^ y.wrapping_mul(40_503)) as u8;
+            image::Rgb([v, v.wrapping_mul(7), v.wrapping_add(y as u8)])
+        });
+        let png = encoded(&DynamicImage::ImageRgb8(noise), ImageFormat::Png);
+        let limits = ImageLimits {
+            max_bytes: Some(60_000),
+            formats: vec!["png".into(), "jpeg".into()],
+            ..Default::default()
+        };
+        let prepared = ImagePreprocessor::new(limits).process_bytes(&png).unwrap();
+        assert!(png.len() > 60_000);
+        assert!(prepared.images[0].data.len() <= 60_000);
+        assert_eq!(prepared.images[0].cost.media_type, "image/jpeg");
+    }
+
+    #[test]
+    fn test_manifest_limits_and_tiled_messages() {
+        let manifest: ManifestV2 = serde_yaml::from_str(
+            r#"
+id: anthropic
+protocol_version: "2.0"
+endpoint:
+  base_url: https://api.anthropic.com/v1
+  chat: /messages
+capabilities:
+  required: [text, vision]
+multimodal:
+  input:
+    vision:
+      supported: true
+      formats: [jpeg, png, gif, webp]
+      max_file_size: 5MB
+      max_resolution: 400x400
+"#,
+        )
+        .unwrap();
+        let preprocessor =
+            ImagePreprocessor::from_manifest(&manifest).with_oversize_policy(OversizePolicy::Tile);
+        assert_eq!(
+            preprocessor.limits().max_bytes,
+            Some(5_000_000),
+            "limits come from the manifest"
+        );
+
+        let screenshot = encoded(&DynamicImage::new_rgb8(400, 1000), ImageFormat::Png);
+        let mut messages = vec![Message::with_content(
+            crate::types::message::MessageRole::User,
+            MessageContent::blocks(vec![
+                ContentBlock::text("What does this page say?"),
+                ContentBlock::image_base64(BASE64.encode(&screenshot), Some("image/png".into())),
+            ]),
+        )];
+        let report = preprocessor.process_messages(&mut messages).unwrap();
+
+        let MessageContent::Blocks(blocks) = &messages[0].content else {
+            panic!("blocks expected");
+        };
+        assert_eq!(blocks.len(), 4, "text plus three tiles");
+        assert_eq!(report.modified(), 1);
+        let outputs = &report.images[0].outputs;
+        assert!(outputs.iter().all(|o| o.width == 400 && o.height <= 400));
+        assert_eq!(
+            report.estimated_tokens(),
+            outputs
+                .iter()
+                .map(|o| ImageTokenModel::Anthropic.estimate(o.width, o.height))
+                .sum::<u32>()
+        );
+    }
+}
@@ -62,6 +62,7 @@
 //! ```
 
 pub mod accumulate;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod compliance;
 pub mod decode;
 pub mod event_map;
@@ -70,6 +71,7 @@ pub mod select;
 
 // Resilience Operators
 pub mod fallback;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod retry;
 
 #[cfg(test)]
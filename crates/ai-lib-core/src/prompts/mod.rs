@@ -0,0 +1,42 @@
+//! 提示词模板：类型化变量、多模态消息模板与带版本的文件注册表。
+//!
+//! # Prompt Templates
+//!
+//! Renders versioned prompt templates (minijinja syntax) into `Vec<Message>`,
+//! validating variables against a typed schema at render time.
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`PromptTemplate`] | Typed variables + message/content-block templates |
+//! | [`PromptRegistry`] | File-backed `id` → versions map, `"latest"` alias |
+//! | [`RenderedPrompt`] | Messages plus the [`PromptRef`] that produced them |
+//!
+//! Pass a [`RenderedPrompt`] to [`ChatRequestBuilder::prompt`](crate::client::ChatRequestBuilder::prompt)
+//! and the prompt id/version shows up in [`CallStats::prompt`](crate::CallStats); attach
+//! it to feedback with [`FeedbackEvent::with_prompt`](crate::FeedbackEvent::with_prompt)
+//! to compare prompt versions.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ai_lib_core::prompts::PromptRegistry;
+//! use serde_json::json;
+//!
+//! # async fn demo(client: &ai_lib_core::AiClient) -> ai_lib_core::Result<()> {
+//! let registry = PromptRegistry::from_dir("prompts")?;
+//! let rendered = registry.render("support.answer", "latest", json!({ "question": "Where is my order?" }))?;
+//!
+//! let (resp, stats) = client.chat().prompt(rendered).execute_with_stats().await?;
+//! println!("{} ({})", resp.content, stats.prompt.unwrap());
+//! # Ok(()) }
+//! ```
+
+mod registry;
+mod template;
+
+pub use crate::types::prompt::PromptRef;
+pub use registry::{compare_versions, PromptRegistry, LATEST};
+pub use template::{
+    BlockTemplate, ContentTemplate, MediaTemplate, MessageTemplate, PromptTemplate, RenderedPrompt,
+    VariableSpec, VariableType,
+};
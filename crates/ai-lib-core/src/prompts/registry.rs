@@ -0,0 +1,248 @@
+//! File-backed registry of versioned prompt templates.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use super::template::{PromptTemplate, RenderedPrompt};
+use crate::{Error, ErrorContext, Result};
+
+/// Version alias resolving to the highest registered version.
+pub const LATEST: &str = "latest";
+
+/// Prompt templates keyed by id, each with one or more versions.
+///
+/// Templates are loaded from `*.yaml` / `*.yml` / `*.json` files (one template per
+/// file, any directory layout, e.g. `prompts/support.answer/1.2.0.yaml`); the `id`
+/// and `version` inside the file are authoritative.
+#[derive(Debug, Clone, Default)]
+pub struct PromptRegistry {
+    templates: BTreeMap<String, Vec<Arc<PromptTemplate>>>,
+}
+
+impl PromptRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every template under `dir` (recursively).
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let mut registry = Self::new();
+        registry.load_dir(dir)?;
+        Ok(registry)
+    }
+
+    /// Load every template under `dir` (recursively); returns how many were added.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir).map_err(|e| {
+            Error::configuration_with_context(
+                format!("cannot read prompt directory {}: {e}", dir.display()),
+                ErrorContext::new().with_source("prompt_registry"),
+            )
+        })?;
+        let mut paths: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        let mut loaded = 0;
+        for path in paths {
+            if path.is_dir() {
+                loaded += self.load_dir(&path)?;
+            } else if matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("yaml" | "yml" | "json")
+            ) {
+                self.load_file(&path)?;
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Load and register a single template file.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| {
+            Error::configuration_with_context(
+                format!("cannot read prompt template {}: {e}", path.display()),
+                ErrorContext::new().with_source("prompt_registry"),
+            )
+        })?;
+        let template = PromptTemplate::from_yaml(&source).map_err(|e| {
+            Error::validation_with_context(
+                format!("{}: {e}", path.display()),
+                ErrorContext::new()
+                    .with_field_path(path.display().to_string())
+                    .with_source("prompt_registry"),
+            )
+        })?;
+        self.register(template)
+    }
+
+    /// Register a template. Re-registering an existing id/version is an error, since
+    /// published versions must stay immutable for tracing to mean anything.
+    pub fn register(&mut self, template: PromptTemplate) -> Result<()> {
+        template.check()?;
+        if template.version == LATEST {
+            return Err(Error::validation(format!(
+                "prompt {}: '{LATEST}' is reserved and cannot be used as a version",
+                template.id
+            )));
+        }
+        let versions = self.templates.entry(template.id.clone()).or_default();
+        if versions.iter().any(|t| t.version == template.version) {
+            return Err(Error::validation_with_context(
+                format!("prompt {} is already registered", template.prompt_ref()),
+                ErrorContext::new().with_source("prompt_registry"),
+            ));
+        }
+        versions.push(Arc::new(template));
+        versions.sort_by(|a, b| compare_versions(&a.version, &b.version));
+        Ok(())
+    }
+
+    /// Template `id` at `version` (`"latest"` for the highest version).
+    pub fn get(&self, id: &str, version: &str) -> Option<Arc<PromptTemplate>> {
+        let versions = self.templates.get(id)?;
+        if version == LATEST {
+            return versions.last().cloned();
+        }
+        versions.iter().find(|t| t.version == version).cloned()
+    }
+
+    pub fn latest(&self, id: &str) -> Option<Arc<PromptTemplate>> {
+        self.get(id, LATEST)
+    }
+
+    /// Registered versions of `id`, lowest first.
+    pub fn versions(&self, id: &str) -> Vec<&str> {
+        self.templates
+            .get(id)
+            .map(|v| v.iter().map(|t| t.version.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.templates.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// Render `id` at `version` (`"latest"` allowed). The result carries the resolved
+    /// [`PromptRef`](crate::types::prompt::PromptRef) for `CallStats` and feedback.
+    pub fn render(&self, id: &str, version: &str, vars: impl Serialize) -> Result<RenderedPrompt> {
+        let template = self.get(id, version).ok_or_else(|| {
+            let known = self.versions(id);
+            Error::validation_with_context(
+                format!("prompt {id}@{version} is not registered"),
+                ErrorContext::new()
+                    .with_source("prompt_registry")
+                    .with_hint(if known.is_empty() {
+                        "no versions of this prompt are loaded".to_string()
+                    } else {
+                        format!("available versions: {}", known.join(", "))
+                    }),
+            )
+        })?;
+        template.render(vars)
+    }
+}
+
+/// Compare dotted versions segment by segment, numerically where both sides are
+/// numbers (`1.10.0` > `1.9.2`), lexically otherwise (`2024-05-01`, `v2-beta`).
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |s: &str| -> Vec<String> {
+        s.trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .map(str::to_string)
+            .collect()
+    };
+    let (a, b) = (parts(a), parts(b));
+    for (x, y) in a.iter().zip(&b) {
+        let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn template(version: &str, greeting: &str) -> String {
+        format!(
+            r#"
+id: greet
+version: "{version}"
+variables:
+  name: {{ type: string }}
+messages:
+  - role: user
+    content: "{greeting}, {{{{ name }}}}!"
+"#
+        )
+    }
+
+    #[test]
+    fn test_load_dir_and_resolve_versions() {
+        let dir = std::env::temp_dir().join(format!("ai-lib-prompts-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("greet")).unwrap();
+        std::fs::write(dir.join("greet/1.9.0.yaml"), template("1.9.0", "Hi")).unwrap();
+        std::fs::write(dir.join("greet/1.10.0.yaml"), template("1.10.0", "Hello")).unwrap();
+        std::fs::write(dir.join("README.md"), "not a template").unwrap();
+
+        let registry = PromptRegistry::from_dir(&dir).unwrap();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.versions("greet"), ["1.9.0", "1.10.0"]);
+
+        let latest = registry
+            .render("greet", LATEST, json!({"name": "Ada"}))
+            .unwrap();
+        assert_eq!(latest.prompt.to_string(), "greet@1.10.0");
+        let pinned = registry
+            .render("greet", "1.9.0", json!({"name": "Ada"}))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&pinned.messages[0].content).unwrap(),
+            "Hi, Ada!"
+        );
+
+        let err = registry.render("greet", "3.0.0", json!({})).unwrap_err();
+        assert!(err.to_string().contains("greet@3.0.0 is not registered"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_versions_are_immutable() {
+        let mut registry = PromptRegistry::new();
+        registry
+            .register(PromptTemplate::from_yaml(&template("1.0.0", "Hi")).unwrap())
+            .unwrap();
+        let dup = PromptTemplate::from_yaml(&template("1.0.0", "Yo")).unwrap();
+        assert!(registry.register(dup).is_err());
+        let latest = PromptTemplate::from_yaml(&template(LATEST, "Yo")).unwrap();
+        assert!(registry.register(latest).is_err());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.10.0", "1.9.2"), Ordering::Greater);
+        assert_eq!(compare_versions("v2", "1.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("2024-05-01", "2024-11-20"), Ordering::Less);
+    }
+}
@@ -0,0 +1,660 @@
+//! Prompt template definition, variable schema and rendering.
+
+use std::collections::BTreeMap;
+
+use base64::Engine as _;
+use minijinja::{Environment, UndefinedBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::types::message::{
+    AudioSource, ContentBlock, DocumentSource, ImageSource, Message, MessageContent, MessageRole,
+};
+use crate::types::prompt::PromptRef;
+use crate::{Error, ErrorContext, Result};
+
+/// A versioned prompt: typed variables plus message templates (minijinja syntax).
+///
+/// ```yaml
+/// id: support.answer
+/// version: "1.2.0"
+/// variables:
+///   question: { type: string }
+///   tone: { type: string, enum: [formal, casual], default: formal }
+///   screenshot: { type: image, required: false }
+///   history: { type: messages, required: false }
+/// messages:
+///   - role: system
+///     content: "You are a {{ tone }} support assistant."
+///   - splice: history
+///   - role: user
+///     content:
+///       - { type: text, text: "{{ question }}" }
+///       - { type: image, url: "{{ screenshot }}", when: screenshot }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, VariableSpec>,
+    pub messages: Vec<MessageTemplate>,
+}
+
+/// Declared type and constraints of a template variable.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct VariableSpec {
+    #[serde(rename = "type", default)]
+    pub var_type: VariableType,
+    /// Defaults to `true` unless a `default` is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    /// Allowed values, if restricted.
+    #[serde(rename = "enum", default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl VariableSpec {
+    pub fn is_required(&self) -> bool {
+        self.required.unwrap_or(self.default.is_none())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    #[default]
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Array,
+    Object,
+    /// A list of chat messages (`[{role, content}]`), spliced with `splice:`.
+    Messages,
+    /// An image URL or `data:<media type>;base64,...` URI.
+    Image,
+    Any,
+}
+
+impl VariableType {
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            Self::String => value.is_string(),
+            Self::Integer => value.is_i64() || value.is_u64(),
+            Self::Number => value.is_number(),
+            Self::Boolean => value.is_boolean(),
+            Self::Array => value.is_array(),
+            Self::Object => value.is_object(),
+            Self::Messages => serde_json::from_value::<Vec<Message>>(value.clone()).is_ok(),
+            Self::Image => value.as_str().is_some_and(|s| !s.trim().is_empty()),
+            Self::Any => true,
+        }
+    }
+}
+
+/// One entry of `messages`: a templated message, or a splice of a `messages` variable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageTemplate {
+    Splice {
+        /// Name of a `messages`-typed variable; absent optional values splice nothing.
+        splice: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        when: Option<String>,
+    },
+    Message {
+        role: MessageRole,
+        content: ContentTemplate,
+        /// Expression; the message is dropped when it is falsy.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        when: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ContentTemplate {
+    Text(String),
+    Blocks(Vec<BlockTemplate>),
+}
+
+/// Multimodal block template. Media blocks take either `url` (URL or `data:` URI) or
+/// base64 `data`; every string field is a template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockTemplate {
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        when: Option<String>,
+    },
+    Image {
+        #[serde(flatten)]
+        media: MediaTemplate,
+    },
+    Audio {
+        #[serde(flatten)]
+        media: MediaTemplate,
+    },
+    Document {
+        #[serde(flatten)]
+        media: MediaTemplate,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MediaTemplate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+}
+
+/// Output of [`PromptTemplate::render`].
+#[derive(Debug, Clone)]
+pub struct RenderedPrompt {
+    pub prompt: PromptRef,
+    pub messages: Vec<Message>,
+}
+
+impl RenderedPrompt {
+    pub fn into_messages(self) -> Vec<Message> {
+        self.messages
+    }
+}
+
+fn environment<'source>() -> Environment<'source> {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env
+}
+
+fn template_error(prompt: &PromptRef, what: &str, e: impl std::fmt::Display) -> Error {
+    Error::validation_with_context(
+        format!("prompt {prompt}: {what}: {e}"),
+        ErrorContext::new().with_source("prompt_template"),
+    )
+}
+
+impl PromptTemplate {
+    /// Parse a YAML (or JSON) template and [`check`](Self::check) it.
+    pub fn from_yaml(source: &str) -> Result<Self> {
+        let template: Self = serde_yaml::from_str(source).map_err(|e| {
+            Error::validation_with_context(
+                format!("invalid prompt template: {e}"),
+                ErrorContext::new().with_source("prompt_template"),
+            )
+        })?;
+        template.check()?;
+        Ok(template)
+    }
+
+    pub fn prompt_ref(&self) -> PromptRef {
+        PromptRef::new(&self.id, &self.version)
+    }
+
+    /// Compile every template string and reject references to undeclared variables,
+    /// splices of non-`messages` variables and invalid defaults.
+    pub fn check(&self) -> Result<()> {
+        let me = self.prompt_ref();
+        if self.id.trim().is_empty() || self.version.trim().is_empty() {
+            return Err(template_error(
+                &me,
+                "template",
+                "id and version are required",
+            ));
+        }
+        for (name, spec) in &self.variables {
+            if let Some(default) = &spec.default {
+                self.check_value(name, spec, default)?;
+            }
+        }
+
+        // (location, template source) and (location, `when` expression) pairs.
+        let mut sources: Vec<(String, &str)> = Vec::new();
+        let mut conditions: Vec<(String, &str)> = Vec::new();
+        for (i, message) in self.messages.iter().enumerate() {
+            let what = format!("messages[{i}]");
+            match message {
+                MessageTemplate::Splice { splice, when } => {
+                    conditions.extend(when.as_deref().map(|w| (what.clone(), w)));
+                    let is_messages = self
+                        .variables
+                        .get(splice)
+                        .is_some_and(|spec| spec.var_type == VariableType::Messages);
+                    if !is_messages {
+                        return Err(template_error(
+                            &me,
+                            &what,
+                            format!("splice '{splice}' must name a `messages` variable"),
+                        ));
+                    }
+                }
+                MessageTemplate::Message { content, when, .. } => {
+                    conditions.extend(when.as_deref().map(|w| (what.clone(), w)));
+                    match content {
+                        ContentTemplate::Text(text) => sources.push((what, text)),
+                        ContentTemplate::Blocks(blocks) => {
+                            for (j, block) in blocks.iter().enumerate() {
+                                let what = format!("{what}.content[{j}]");
+                                let (when, fields) = block.parts();
+                                conditions.extend(when.as_deref().map(|w| (what.clone(), w)));
+                                for field in fields.into_iter().flatten() {
+                                    sources.push((what.clone(), field));
+                                }
+                                if let Some(media) = block.media() {
+                                    if media.url.is_some() == media.data.is_some() {
+                                        return Err(template_error(
+                                            &me,
+                                            &what,
+                                            "media blocks need exactly one of `url` or `data`",
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let env = environment();
+        for (what, source) in sources {
+            let template = env
+                .template_from_str(source)
+                .map_err(|e| template_error(&me, &what, e))?;
+            self.check_names(&me, &what, template.undeclared_variables(false))?;
+        }
+        for (what, expr) in conditions {
+            let expr = env
+                .compile_expression(expr)
+                .map_err(|e| template_error(&me, &what, e))?;
+            self.check_names(&me, &what, expr.undeclared_variables(false))?;
+        }
+        Ok(())
+    }
+
+    fn check_names(
+        &self,
+        me: &PromptRef,
+        what: &str,
+        names: std::collections::HashSet<String>,
+    ) -> Result<()> {
+        let mut unknown: Vec<String> = names
+            .into_iter()
+            .filter(|n| !self.variables.contains_key(n))
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        unknown.sort();
+        Err(template_error(
+            me,
+            what,
+            format!("undeclared variable(s): {}", unknown.join(", ")),
+        ))
+    }
+
+    fn check_value(&self, name: &str, spec: &VariableSpec, value: &Value) -> Result<()> {
+        let fail = |msg: String| {
+            Error::validation_with_context(
+                format!("prompt {}: {msg}", self.prompt_ref()),
+                ErrorContext::new()
+                    .with_field_path(format!("variables.{name}"))
+                    .with_source("prompt_template"),
+            )
+        };
+        if !spec.var_type.accepts(value) {
+            return Err(fail(format!(
+                "variable '{name}' expects {:?}, got {value}",
+                spec.var_type
+            )));
+        }
+        if !spec.allowed.is_empty() && !spec.allowed.contains(value) {
+            return Err(fail(format!(
+                "variable '{name}' must be one of {}, got {value}",
+                Value::Array(spec.allowed.clone())
+            )));
+        }
+        Ok(())
+    }
+
+    /// Validate `vars` against the declared schema and fill in defaults. Unknown
+    /// variables are rejected so typos do not silently render as the default.
+    pub fn resolve_variables(&self, vars: &Value) -> Result<Map<String, Value>> {
+        let empty = Map::new();
+        let given = match vars {
+            Value::Object(map) => map,
+            Value::Null => &empty,
+            other => {
+                return Err(Error::validation(format!(
+                    "prompt {}: variables must be an object, got {other}",
+                    self.prompt_ref()
+                )))
+            }
+        };
+        if let Some(unknown) = given.keys().find(|k| !self.variables.contains_key(*k)) {
+            return Err(Error::validation_with_context(
+                format!("prompt {}: unknown variable '{unknown}'", self.prompt_ref()),
+                ErrorContext::new()
+                    .with_field_path(format!("variables.{unknown}"))
+                    .with_source("prompt_template"),
+            ));
+        }
+        let mut resolved = Map::new();
+        for (name, spec) in &self.variables {
+            match given
+                .get(name)
+                .filter(|v| !v.is_null())
+                .or(spec.default.as_ref())
+            {
+                Some(value) => {
+                    self.check_value(name, spec, value)?;
+                    resolved.insert(name.clone(), value.clone());
+                }
+                None if spec.is_required() => {
+                    return Err(Error::validation_with_context(
+                        format!(
+                            "prompt {}: missing required variable '{name}'",
+                            self.prompt_ref()
+                        ),
+                        ErrorContext::new()
+                            .with_field_path(format!("variables.{name}"))
+                            .with_source("prompt_template"),
+                    ))
+                }
+                // Optional and absent: visible to templates as `none`.
+                None => {
+                    resolved.insert(name.clone(), Value::Null);
+                }
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Render to messages. `vars` is any serializable object (e.g. `json!({...})`).
+    pub fn render(&self, vars: impl Serialize) -> Result<RenderedPrompt> {
+        let me = self.prompt_ref();
+        let vars = serde_json::to_value(vars).map_err(|e| template_error(&me, "variables", e))?;
+        let resolved = self.resolve_variables(&vars)?;
+        let ctx = minijinja::Value::from_serialize(&resolved);
+        let env = environment();
+        let render = |what: &str, source: &str| {
+            env.render_str(source, &ctx)
+                .map_err(|e| template_error(&me, what, e))
+        };
+        let enabled = |what: &str, when: &Option<String>| -> Result<bool> {
+            let Some(expr) = when else {
+                return Ok(true);
+            };
+            let value = env
+                .compile_expression_owned(expr.clone())
+                .and_then(|e| e.eval(&ctx))
+                .map_err(|e| template_error(&me, what, e))?;
+            Ok(value.is_true())
+        };
+
+        let mut messages = Vec::new();
+        for (i, message) in self.messages.iter().enumerate() {
+            let what = format!("messages[{i}]");
+            match message {
+                MessageTemplate::Splice { splice, when } => {
+                    if !enabled(&what, when)? {
+                        continue;
+                    }
+                    if let Some(value) = resolved.get(splice).filter(|v| !v.is_null()) {
+                        let spliced: Vec<Message> = serde_json::from_value(value.clone())
+                            .map_err(|e| template_error(&me, &what, e))?;
+                        messages.extend(spliced);
+                    }
+                }
+                MessageTemplate::Message {
+                    role,
+                    content,
+                    when,
+                } => {
+                    if !enabled(&what, when)? {
+                        continue;
+                    }
+                    let content = match content {
+                        ContentTemplate::Text(text) => MessageContent::Text(render(&what, text)?),
+                        ContentTemplate::Blocks(blocks) => {
+                            let mut out = Vec::with_capacity(blocks.len());
+                            for (j, block) in blocks.iter().enumerate() {
+                                let what = format!("{what}.content[{j}]");
+                                if !enabled(&what, block.parts().0)? {
+                                    continue;
+                                }
+                                out.push(block.render(|s| render(&what, s))?);
+                            }
+                            MessageContent::Blocks(out)
+                        }
+                    };
+                    messages.push(Message::with_content(role.clone(), content));
+                }
+            }
+        }
+        Ok(RenderedPrompt {
+            prompt: me,
+            messages,
+        })
+    }
+}
+
+impl BlockTemplate {
+    /// `when` condition and template string fields.
+    fn parts(&self) -> (&Option<String>, [Option<&String>; 4]) {
+        match self {
+            Self::Text { text, when } => (when, [Some(text), None, None, None]),
+            Self::Image { media } | Self::Audio { media } => (
+                &media.when,
+                [
+                    media.url.as_ref(),
+                    media.data.as_ref(),
+                    media.media_type.as_ref(),
+                    None,
+                ],
+            ),
+            Self::Document { media, filename } => (
+                &media.when,
+                [
+                    media.url.as_ref(),
+                    media.data.as_ref(),
+                    media.media_type.as_ref(),
+                    filename.as_ref(),
+                ],
+            ),
+        }
+    }
+
+    fn media(&self) -> Option<&MediaTemplate> {
+        match self {
+            Self::Text { .. } => None,
+            Self::Image { media } | Self::Audio { media } | Self::Document { media, .. } => {
+                Some(media)
+            }
+        }
+    }
+
+    fn render(&self, render: impl Fn(&str) -> Result<String>) -> Result<ContentBlock> {
+        let opt = |s: &Option<String>| -> Result<Option<String>> {
+            s.as_deref()
+                .map(&render)
+                .transpose()
+                .map(|v| v.filter(|v| !v.is_empty()))
+        };
+        let media = match self {
+            Self::Text { text, .. } => return Ok(ContentBlock::text(render(text)?)),
+            Self::Image { media } | Self::Audio { media } | Self::Document { media, .. } => media,
+        };
+        let media_type = opt(&media.media_type)?;
+        let (source_type, media_type, data) = match (opt(&media.url)?, opt(&media.data)?) {
+            (Some(url), _) => match parse_data_uri(&url) {
+                Some((mt, data)) => ("base64", media_type.or(mt), data),
+                None => ("url", media_type, url),
+            },
+            (None, Some(data)) => ("base64", media_type, data),
+            (None, None) => {
+                return Err(Error::validation(
+                    "prompt media block rendered an empty url/data",
+                ))
+            }
+        };
+        let source_type = source_type.to_string();
+        Ok(match self {
+            Self::Audio { .. } => ContentBlock::Audio {
+                source: AudioSource {
+                    source_type,
+                    media_type,
+                    data,
+                },
+            },
+            Self::Document { filename, .. } => ContentBlock::Document {
+                source: DocumentSource {
+                    source_type,
+                    mime_type: media_type,
+                    data,
+                    filename: opt(filename)?,
+                },
+            },
+            _ => ContentBlock::Image {
+                source: ImageSource {
+                    source_type,
+                    media_type,
+                    data,
+                },
+            },
+        })
+    }
+}
+
+/// Split `data:<media type>;base64,<payload>` into its parts.
+fn parse_data_uri(uri: &str) -> Option<(Option<String>, String)> {
+    let rest = uri.strip_prefix("data:")?;
+    let (meta, payload) = rest.split_once(',')?;
+    let media_type = meta.strip_suffix(";base64")?;
+    base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .ok()?;
+    let media_type = (!media_type.is_empty()).then(|| media_type.to_string());
+    Some((media_type, payload.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SUPPORT: &str = r#"
+id: support.answer
+version: "1.2.0"
+variables:
+  question: { type: string }
+  tone: { type: string, enum: [formal, casual], default: formal }
+  screenshot: { type: image, required: false }
+  history: { type: messages, required: false }
+  max_words: { type: integer, default: 80 }
+messages:
+  - role: system
+    content: "You are a {{ tone }} support assistant. Answer in at most {{ max_words }} words."
+  - splice: history
+  - role: user
+    content:
+      - { type: text, text: "{{ question | trim }}" }
+      - { type: image, url: "{{ screenshot }}", when: screenshot }
+"#;
+
+    fn text(content: &MessageContent) -> String {
+        match content {
+            MessageContent::Text(t) => t.clone(),
+            MessageContent::Blocks(b) => serde_json::to_string(b).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_render_with_defaults_history_and_image() {
+        let template = PromptTemplate::from_yaml(SUPPORT).unwrap();
+        let rendered = template
+            .render(json!({
+                "question": "  Why is my invoice late? ",
+                "history": [{"role": "user", "content": "hi"}, {"role": "assistant", "content": "hello"}],
+                "screenshot": "data:image/png;base64,aGVsbG8=",
+            }))
+            .unwrap();
+        assert_eq!(rendered.prompt, PromptRef::new("support.answer", "1.2.0"));
+        let m = &rendered.messages;
+        assert_eq!(m.len(), 4);
+        assert_eq!(
+            text(&m[0].content),
+            "You are a formal support assistant. Answer in at most 80 words."
+        );
+        assert_eq!(m[2].role, MessageRole::Assistant);
+        let MessageContent::Blocks(blocks) = &m[3].content else {
+            panic!("expected blocks");
+        };
+        assert!(
+            matches!(&blocks[0], ContentBlock::Text { text } if text == "Why is my invoice late?")
+        );
+        let ContentBlock::Image { source } = &blocks[1] else {
+            panic!("expected image");
+        };
+        assert_eq!(source.source_type, "base64");
+        assert_eq!(source.media_type.as_deref(), Some("image/png"));
+        assert_eq!(source.data, "aGVsbG8=");
+
+        // Optional image and history absent: no splice, no image block.
+        let plain = template
+            .render(json!({"question": "q", "tone": "casual"}))
+            .unwrap();
+        assert_eq!(plain.messages.len(), 2);
+        assert!(text(&plain.messages[0].content).starts_with("You are a casual"));
+        let MessageContent::Blocks(blocks) = &plain.messages[1].content else {
+            panic!("expected blocks");
+        };
+        assert_eq!(blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_variables_are_validated() {
+        let template = PromptTemplate::from_yaml(SUPPORT).unwrap();
+        let err = |vars: Value| template.render(vars).unwrap_err().to_string();
+        assert!(err(json!({})).contains("missing required variable 'question'"));
+        assert!(err(json!({"question": "q", "tone": "rude"})).contains("must be one of"));
+        assert!(err(json!({"question": "q", "max_words": "ten"})).contains("expects Integer"));
+        assert!(err(json!({"question": "q", "questoin": "typo"})).contains("unknown variable"));
+        assert!(err(json!({"question": "q", "history": [{"role": "x"}]})).contains("Messages"));
+    }
+
+    #[test]
+    fn test_check_rejects_broken_templates() {
+        let undeclared = SUPPORT.replace("{{ tone }}", "{{ persona }}");
+        let e = PromptTemplate::from_yaml(&undeclared).unwrap_err();
+        assert!(
+            e.to_string().contains("undeclared variable(s): persona"),
+            "{e}"
+        );
+
+        let syntax = SUPPORT.replace("{{ tone }}", "{{ tone ");
+        assert!(PromptTemplate::from_yaml(&syntax).is_err());
+
+        let bad_splice = SUPPORT.replace("splice: history", "splice: question");
+        let e = PromptTemplate::from_yaml(&bad_splice).unwrap_err();
+        assert!(e.to_string().contains("must name a `messages` variable"));
+
+        let bad_default = SUPPORT.replace("default: formal", "default: shouty");
+        assert!(PromptTemplate::from_yaml(&bad_default).is_err());
+    }
+}
@@ -109,6 +109,9 @@ pub struct Capabilities {
     /// MCP client tool-bridge (`mcp_client` in V2 optional/required lists).
     #[serde(default, skip_serializing_if = "is_false")]
     pub mcp_client: bool,
+    /// Continues a trailing assistant message (`feature_flags.assistant_prefill`).
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub assistant_prefill: bool,
     /// VL-TTC / tool-calling manifest block (V2 `capabilities.tool_calling`).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tool_calling: Option<serde_json::Value>,
@@ -134,6 +137,8 @@ impl<'de> Deserialize<'de> for Capabilities {
             multimodal: bool,
             #[serde(default)]
             audio: bool,
+            #[serde(default)]
+            assistant_prefill: bool,
         }
 
         #[derive(Deserialize, Default)]
@@ -144,6 +149,8 @@ impl<'de> Deserialize<'de> for Capabilities {
             extended_thinking: bool,
             #[serde(default)]
             structured_output: bool,
+            #[serde(default)]
+            assistant_prefill: bool,
         }
 
         #[derive(Deserialize)]
@@ -178,6 +185,7 @@ impl<'de> Deserialize<'de> for Capabilities {
                 audio: false,
                 structured_output: false,
                 mcp_client: false,
+                assistant_prefill: false,
                 tool_calling: None,
             };
             for t in tags {
@@ -218,6 +226,7 @@ impl<'de> Deserialize<'de> for Capabilities {
                 audio: v.audio,
                 structured_output: false,
                 mcp_client: false,
+                assistant_prefill: v.assistant_prefill,
                 tool_calling: None,
             }),
             Input::V2(v) => {
@@ -236,6 +245,7 @@ impl<'de> Deserialize<'de> for Capabilities {
                     audio: has("audio"),
                     structured_output: has("structured_output") || flags.structured_output,
                     mcp_client: has("mcp_client"),
+                    assistant_prefill: flags.assistant_prefill,
                     tool_calling: v.tool_calling,
                 })
             }
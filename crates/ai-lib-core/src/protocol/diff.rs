@@ -0,0 +1,294 @@
+//! 清单语义差异：忽略表示形式差异（能力格式、认证位置、字段别名），报告真正的行为变化。
+//!
+//! Semantic diff between two manifests. Both sides are normalised first so that
+//! representation-only differences do not show up: V1 boolean vs V2 list capabilities,
+//! top-level `auth` vs `endpoint.auth`, `header_name` vs `header`, endpoint shorthand
+//! strings vs objects, and the order of set-like scalar lists (regions, statuses).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::lint::declared_capabilities;
+
+/// Paths whose removal or modification breaks existing callers.
+const BREAKING_PREFIXES: &[&str] = &[
+    "id",
+    "endpoint.base_url",
+    "endpoint.auth",
+    "endpoints",
+    "services",
+    "parameter_mappings",
+    "response_paths",
+    "streaming.decoder",
+    "streaming.event_map",
+];
+
+/// Kind of difference at a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One semantic difference.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffEntry {
+    /// Dotted path in the normalised manifest (`capabilities.tools`, `endpoint.auth.header`).
+    pub path: String,
+    pub kind: DiffKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+    /// Whether callers relying on the old manifest may break.
+    pub breaking: bool,
+}
+
+/// Differences between two manifests, in path order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ManifestDiff {
+    pub changes: Vec<DiffEntry>,
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn has_breaking(&self) -> bool {
+        self.changes.iter().any(|c| c.breaking)
+    }
+}
+
+/// Compute the semantic difference from `old` to `new`.
+pub fn diff_manifests(old: &Value, new: &Value) -> ManifestDiff {
+    let mut changes = Vec::new();
+    walk(
+        "",
+        Some(&normalize(old)),
+        Some(&normalize(new)),
+        &mut changes,
+    );
+    ManifestDiff { changes }
+}
+
+/// Canonical form used for comparison.
+fn normalize(manifest: &Value) -> Value {
+    let Value::Object(source) = strip_nulls(manifest) else {
+        return manifest.clone();
+    };
+    let mut out = source.clone();
+    out.remove("$schema");
+
+    let mut caps: Map<String, Value> = declared_capabilities(manifest)
+        .into_iter()
+        .map(|(name, level)| (name, serde_json::to_value(level).unwrap_or(Value::Null)))
+        .collect();
+    if let Some(structured) = source.get("capabilities").and_then(Value::as_object) {
+        for key in ["feature_flags", "tool_calling"] {
+            if let Some(v) = structured.get(key) {
+                caps.insert(key.to_string(), v.clone());
+            }
+        }
+    }
+    if source.contains_key("capabilities") {
+        out.insert("capabilities".to_string(), Value::Object(caps));
+    }
+
+    // Effective auth: endpoint.auth wins over top-level auth (see `credentials`).
+    let top_auth = if out.get("endpoint").is_some_and(Value::is_object) {
+        out.remove("auth")
+    } else {
+        None
+    };
+    if let Some(Value::Object(endpoint)) = out.get_mut("endpoint") {
+        if let Some(mut auth) = endpoint.remove("auth").or(top_auth) {
+            if let Value::Object(map) = &mut auth {
+                for (old, new) in [("header_name", "header"), ("key_env", "token_env")] {
+                    if let Some(v) = map.remove(old) {
+                        map.entry(new).or_insert(v);
+                    }
+                }
+            }
+            endpoint.insert("auth".to_string(), auth);
+        }
+    }
+
+    if let Some(Value::Object(endpoints)) = out.get_mut("endpoints") {
+        for ep in endpoints.values_mut() {
+            if let Value::String(path) = ep {
+                *ep = serde_json::json!({ "path": path, "method": "POST" });
+            }
+        }
+    }
+    Value::Object(out)
+}
+
+fn strip_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), strip_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(strip_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+fn is_breaking(path: &str, kind: DiffKind) -> bool {
+    if let Some(cap) = path.strip_prefix("capabilities.") {
+        // Dropping a capability breaks callers; moving it between required/optional does not.
+        return kind == DiffKind::Removed && !cap.contains('.');
+    }
+    kind != DiffKind::Added
+        && BREAKING_PREFIXES.iter().any(|p| {
+            path == *p || path.starts_with(&format!("{p}.")) || path.starts_with(&format!("{p}["))
+        })
+}
+
+fn push(
+    out: &mut Vec<DiffEntry>,
+    path: &str,
+    kind: DiffKind,
+    old: Option<&Value>,
+    new: Option<&Value>,
+) {
+    out.push(DiffEntry {
+        path: path.to_string(),
+        kind,
+        old: old.cloned(),
+        new: new.cloned(),
+        breaking: is_breaking(path, kind),
+    });
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn is_scalar_list(items: &[Value]) -> bool {
+    items
+        .iter()
+        .all(|v| !matches!(v, Value::Object(_) | Value::Array(_)))
+}
+
+fn walk(path: &str, old: Option<&Value>, new: Option<&Value>, out: &mut Vec<DiffEntry>) {
+    match (old, new) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                walk(&child(path, key), a.get(key), b.get(key), out);
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b)))
+            if is_scalar_list(a) && is_scalar_list(b) =>
+        {
+            for item in a.iter().filter(|v| !b.contains(v)) {
+                push(out, path, DiffKind::Removed, Some(item), None);
+            }
+            for item in b.iter().filter(|v| !a.contains(v)) {
+                push(out, path, DiffKind::Added, None, Some(item));
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => {
+            for i in 0..a.len().max(b.len()) {
+                walk(&format!("{path}[{i}]"), a.get(i), b.get(i), out);
+            }
+        }
+        (Some(a), Some(b)) if a == b => {}
+        (Some(a), Some(b)) => push(out, path, DiffKind::Changed, Some(a), Some(b)),
+        (Some(a), None) => push(out, path, DiffKind::Removed, Some(a), None),
+        (None, Some(b)) => push(out, path, DiffKind::Added, None, Some(b)),
+        (None, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_representation_changes_are_not_differences() {
+        let v1 = yaml(
+            r#"
+id: acme
+protocol_version: "2.0"
+endpoint: { base_url: https://api.acme.test }
+auth: { type: bearer, header_name: Authorization }
+capabilities: { streaming: true, tools: true, vision: false }
+endpoints: { chat: /chat/completions }
+availability: { regions: [us, eu] }
+"#,
+        );
+        let v2 = yaml(
+            r#"
+$schema: https://example.test/v2.json
+id: acme
+protocol_version: "2.0"
+endpoint:
+  base_url: https://api.acme.test
+  auth: { type: bearer, header: Authorization }
+capabilities: { required: [text, streaming], optional: [tools] }
+endpoints: { chat: { path: /chat/completions, method: POST } }
+availability: { regions: [eu, us] }
+"#,
+        );
+        let diff = diff_manifests(&v1, &v2);
+        assert!(diff.is_empty(), "{:?}", diff.changes);
+    }
+
+    #[test]
+    fn test_reports_semantic_changes() {
+        let old = yaml(
+            r#"
+id: acme
+endpoint: { base_url: https://api.acme.test }
+capabilities: { required: [text, streaming], optional: [tools, vision] }
+parameter_mappings: { model: model, max_tokens: max_tokens }
+availability: { regions: [us] }
+"#,
+        );
+        let new = yaml(
+            r#"
+id: acme
+endpoint: { base_url: https://api2.acme.test }
+capabilities: { required: [text, streaming, tools], optional: [reasoning] }
+parameter_mappings: { model: model, max_tokens: max_completion_tokens }
+availability: { regions: [us, eu] }
+"#,
+        );
+        let diff = diff_manifests(&old, &new);
+        let summary: Vec<(&str, DiffKind, bool)> = diff
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.kind, c.breaking))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("availability.regions", DiffKind::Added, false),
+                ("capabilities.reasoning", DiffKind::Added, false),
+                ("capabilities.tools", DiffKind::Changed, false),
+                ("capabilities.vision", DiffKind::Removed, true),
+                ("endpoint.base_url", DiffKind::Changed, true),
+                ("parameter_mappings.max_tokens", DiffKind::Changed, true),
+            ]
+        );
+        assert_eq!(diff.changes[0].new, Some(Value::from("eu")));
+        assert!(diff.has_breaking());
+    }
+}
@@ -39,6 +39,9 @@ pub enum ProtocolError {
 
     #[error("YAML syntax error: {0}")]
     YamlError(String),
+
+    #[error("Manifest integrity check failed for {path}: {reason}")]
+    IntegrityError { path: String, reason: String },
 }
 
 impl ProtocolError {
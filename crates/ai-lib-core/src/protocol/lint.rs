@@ -0,0 +1,547 @@
+//! 清单质量检查：未使用的参数映射、不可达的事件规则、缺失的重试策略、能力与端点不一致、废弃字段。
+//!
+//! Manifest linting. [`ProtocolValidator`](super::ProtocolValidator) answers "is this
+//! manifest valid?"; the linter answers "is it any good?". It works on the raw YAML/JSON
+//! value so V1 and V2 manifests (and manifests that fail typed parsing) can be linted alike.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+
+/// Parameter names consumed by [`ProtocolManifest::compile_request`](super::ProtocolManifest::compile_request).
+pub const CONSUMED_PARAMETERS: &[&str] = &[
+    "model",
+    "messages",
+    "temperature",
+    "max_tokens",
+    "stream",
+    "tools",
+    "tool_choice",
+];
+
+/// `emit` values the rule-based event mapper knows how to build.
+pub const KNOWN_EMITS: &[&str] = &[
+    "PartialContentDelta",
+    "Metadata",
+    "ThinkingDelta",
+    "StreamEnd",
+    "FinalCandidate",
+    "ToolCallStarted",
+    "PartialToolCall",
+];
+
+/// Emits that always produce an event once matched, shadowing later rules with the same match.
+const UNCONDITIONAL_EMITS: &[&str] = &["Metadata"];
+
+/// Capabilities that need a dedicated endpoint, with the endpoint keys that satisfy them.
+const CAPABILITY_ENDPOINTS: &[(&str, &[&str])] = &[
+    ("embeddings", &["embeddings"]),
+    ("stt", &["stt", "transcriptions", "audio_transcriptions"]),
+    ("tts", &["tts", "speech", "audio_speech"]),
+    ("rerank", &["rerank"]),
+    ("batch", &["batch", "batches"]),
+    (
+        "image_generation",
+        &["image_generation", "images", "images_generations"],
+    ),
+];
+
+/// HTTP statuses that are never worth retrying.
+const NON_RETRYABLE_STATUSES: &[u64] = &[400, 401, 403, 404, 422];
+
+/// Finding severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single lint finding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintFinding {
+    /// Stable rule identifier, e.g. `unused_parameter_mapping`.
+    pub code: String,
+    pub severity: LintSeverity,
+    /// Dotted path of the offending field (`streaming.event_map[2]`).
+    pub path: String,
+    pub message: String,
+}
+
+/// All findings for one manifest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LintReport {
+    pub id: Option<String>,
+    pub findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    /// Number of findings at `severity`.
+    pub fn count(&self, severity: LintSeverity) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == severity)
+            .count()
+    }
+
+    /// Whether any finding is at or above `severity`.
+    pub fn has_at_least(&self, severity: LintSeverity) -> bool {
+        self.findings.iter().any(|f| f.severity >= severity)
+    }
+
+    fn push(
+        &mut self,
+        code: &str,
+        severity: LintSeverity,
+        path: impl Into<String>,
+        message: String,
+    ) {
+        self.findings.push(LintFinding {
+            code: code.to_string(),
+            severity,
+            path: path.into(),
+            message,
+        });
+    }
+}
+
+/// Whether a capability is required or optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CapabilityLevel {
+    Required,
+    Optional,
+}
+
+/// Capability names declared by a manifest in any supported shape.
+///
+/// V1 boolean maps are normalised the way [`CapabilitiesV2::promote_to_v2`](super::CapabilitiesV2::promote_to_v2)
+/// does (`text` and `streaming` required, the rest optional); shorthand tag lists count as
+/// required, with `chat` read as `text`.
+pub fn declared_capabilities(manifest: &Value) -> BTreeMap<String, CapabilityLevel> {
+    let mut out = BTreeMap::new();
+    match manifest.get("capabilities") {
+        Some(Value::Array(tags)) => {
+            for tag in tags.iter().filter_map(Value::as_str) {
+                let name = if tag == "chat" { "text" } else { tag };
+                out.insert(name.to_string(), CapabilityLevel::Required);
+            }
+        }
+        Some(Value::Object(map)) if map.contains_key("required") => {
+            for (key, level) in [
+                ("optional", CapabilityLevel::Optional),
+                ("required", CapabilityLevel::Required),
+            ] {
+                for name in map
+                    .get(key)
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                {
+                    out.insert(name.to_string(), level);
+                }
+            }
+        }
+        Some(Value::Object(map)) => {
+            out.insert("text".to_string(), CapabilityLevel::Required);
+            for (name, flag) in map {
+                if flag.as_bool() == Some(true) {
+                    let level = if name == "streaming" {
+                        CapabilityLevel::Required
+                    } else {
+                        CapabilityLevel::Optional
+                    };
+                    out.insert(name.clone(), level);
+                }
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+/// Whether the manifest declares protocol version 2 or later.
+pub(crate) fn is_v2(manifest: &Value) -> bool {
+    manifest
+        .get("protocol_version")
+        .and_then(|v| match v {
+            Value::String(s) => s.split('.').next().and_then(|m| m.parse::<u32>().ok()),
+            Value::Number(n) => n.as_f64().map(|f| f as u32),
+            _ => None,
+        })
+        .is_some_and(|major| major >= 2)
+}
+
+/// Whether `capabilities` uses the V1 flat boolean shape.
+pub(crate) fn has_legacy_capabilities(manifest: &Value) -> bool {
+    matches!(manifest.get("capabilities"), Some(Value::Object(m)) if !m.contains_key("required"))
+}
+
+/// Endpoint keys declared under `endpoints`, `endpoint` (V2 paths) and `services`.
+fn declared_endpoints(manifest: &Value) -> HashSet<String> {
+    let mut out = HashSet::new();
+    for section in ["endpoints", "services"] {
+        if let Some(map) = manifest.get(section).and_then(Value::as_object) {
+            out.extend(map.keys().cloned());
+        }
+    }
+    if let Some(map) = manifest.get("endpoint").and_then(Value::as_object) {
+        out.extend(
+            map.keys()
+                .filter(|k| !matches!(k.as_str(), "base_url" | "protocol" | "timeout_ms" | "auth"))
+                .cloned(),
+        );
+    }
+    out
+}
+
+/// Lint a manifest parsed from YAML or JSON.
+pub fn lint_manifest(manifest: &Value) -> LintReport {
+    let mut report = LintReport {
+        id: manifest
+            .get("id")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        findings: Vec::new(),
+    };
+    let caps = declared_capabilities(manifest);
+    lint_parameter_mappings(manifest, &caps, &mut report);
+    lint_event_map(manifest, &mut report);
+    lint_retry_policy(manifest, &mut report);
+    lint_capabilities(manifest, &caps, &mut report);
+    lint_deprecated(manifest, &mut report);
+    report
+}
+
+fn lint_parameter_mappings(
+    manifest: &Value,
+    caps: &BTreeMap<String, CapabilityLevel>,
+    report: &mut LintReport,
+) {
+    let Some(mappings) = manifest
+        .get("parameter_mappings")
+        .and_then(Value::as_object)
+    else {
+        return;
+    };
+    for (name, target) in mappings {
+        let path = format!("parameter_mappings.{name}");
+        if !CONSUMED_PARAMETERS.contains(&name.as_str()) {
+            report.push(
+                "unused_parameter_mapping",
+                LintSeverity::Warning,
+                path,
+                format!(
+                    "'{name}' is not a unified request parameter; the mapping is never applied"
+                ),
+            );
+        } else if !target.as_str().is_some_and(|t| !t.trim().is_empty()) {
+            report.push(
+                "unused_parameter_mapping",
+                LintSeverity::Error,
+                path,
+                format!("mapping for '{name}' has no target path"),
+            );
+        }
+    }
+    for (cap, param) in [("tools", "tools"), ("streaming", "stream")] {
+        if caps.contains_key(cap) && !mappings.contains_key(param) {
+            report.push(
+                "capability_mismatch",
+                LintSeverity::Warning,
+                "parameter_mappings",
+                format!(
+                    "capability '{cap}' is declared but parameter_mappings has no '{param}' entry; \
+                     the parameter is dropped from compiled requests"
+                ),
+            );
+        }
+    }
+}
+
+fn lint_event_map(manifest: &Value, report: &mut LintReport) {
+    let Some(rules) = manifest
+        .get("streaming")
+        .and_then(|s| s.get("event_map"))
+        .and_then(Value::as_array)
+    else {
+        return;
+    };
+    let mut seen: Vec<(String, String)> = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        let path = format!("streaming.event_map[{i}]");
+        let match_expr = rule
+            .get("match")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .trim();
+        let emit = rule.get("emit").and_then(Value::as_str).unwrap_or("");
+        if match_expr.is_empty() {
+            report.push(
+                "unreachable_event_rule",
+                LintSeverity::Error,
+                path,
+                "rule has an empty match expression and fails to compile".to_string(),
+            );
+            continue;
+        }
+        if !KNOWN_EMITS.contains(&emit) {
+            report.push(
+                "unreachable_event_rule",
+                LintSeverity::Warning,
+                path,
+                format!(
+                    "emit '{emit}' is not a known streaming event ({}); the rule never produces output",
+                    KNOWN_EMITS.join(", ")
+                ),
+            );
+            continue;
+        }
+        if let Some(j) = seen.iter().position(|(m, e)| {
+            m == match_expr && (e == emit || UNCONDITIONAL_EMITS.contains(&e.as_str()))
+        }) {
+            report.push(
+                "unreachable_event_rule",
+                LintSeverity::Warning,
+                path,
+                format!(
+                    "shadowed by streaming.event_map[{j}] (same match '{match_expr}'), which always wins"
+                ),
+            );
+        }
+        seen.push((match_expr.to_string(), emit.to_string()));
+    }
+}
+
+fn lint_retry_policy(manifest: &Value, report: &mut LintReport) {
+    let Some(policy) = manifest.get("retry_policy") else {
+        report.push(
+            "missing_retry_policy",
+            LintSeverity::Warning,
+            "retry_policy",
+            "no retry_policy; transient failures (429/5xx) are surfaced without retry".to_string(),
+        );
+        return;
+    };
+    if policy.get("max_retries").and_then(Value::as_u64) == Some(0) {
+        report.push(
+            "missing_retry_policy",
+            LintSeverity::Info,
+            "retry_policy.max_retries",
+            "max_retries is 0; the policy never retries".to_string(),
+        );
+    }
+    for status in policy
+        .get("retry_on_http_status")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_u64)
+    {
+        if NON_RETRYABLE_STATUSES.contains(&status) {
+            report.push(
+                "missing_retry_policy",
+                LintSeverity::Warning,
+                "retry_policy.retry_on_http_status",
+                format!("HTTP {status} is a client error and retrying it cannot succeed"),
+            );
+        }
+    }
+}
+
+fn lint_capabilities(
+    manifest: &Value,
+    caps: &BTreeMap<String, CapabilityLevel>,
+    report: &mut LintReport,
+) {
+    let endpoints = declared_endpoints(manifest);
+    for (cap, keys) in CAPABILITY_ENDPOINTS {
+        let has_endpoint = keys.iter().any(|k| endpoints.contains(*k));
+        match (caps.contains_key(*cap), has_endpoint) {
+            (true, false) => report.push(
+                "capability_mismatch",
+                LintSeverity::Warning,
+                "capabilities",
+                format!("capability '{cap}' is declared but no endpoint provides it (expected one of: {})", keys.join(", ")),
+            ),
+            (false, true) => report.push(
+                "capability_mismatch",
+                LintSeverity::Info,
+                "endpoints",
+                format!("an endpoint for '{cap}' is declared but the capability is not"),
+            ),
+            _ => {}
+        }
+    }
+
+    let streaming_block = manifest.get("streaming").is_some_and(|s| !s.is_null());
+    match (caps.contains_key("streaming"), streaming_block) {
+        (true, false) => report.push(
+            "capability_mismatch",
+            LintSeverity::Info,
+            "streaming",
+            "streaming is declared without a streaming block; OpenAI-style SSE defaults apply"
+                .to_string(),
+        ),
+        (false, true) => report.push(
+            "capability_mismatch",
+            LintSeverity::Warning,
+            "capabilities",
+            "a streaming block is configured but the streaming capability is not declared"
+                .to_string(),
+        ),
+        _ => {}
+    }
+}
+
+fn lint_deprecated(manifest: &Value, report: &mut LintReport) {
+    for prefix in ["auth", "endpoint.auth"] {
+        let auth = prefix.split('.').try_fold(manifest, |v, key| v.get(key));
+        for (old, new) in [("header_name", "header"), ("key_env", "token_env")] {
+            if auth.is_some_and(|a| a.get(old).is_some()) {
+                report.push(
+                    "deprecated_field",
+                    LintSeverity::Warning,
+                    format!("{prefix}.{old}"),
+                    format!("{old} is deprecated; use {new}"),
+                );
+            }
+        }
+    }
+    if !is_v2(manifest) {
+        return;
+    }
+    if has_legacy_capabilities(manifest) {
+        report.push(
+            "deprecated_field",
+            LintSeverity::Warning,
+            "capabilities",
+            "V1 boolean capability flags in a V2 manifest; use required/optional lists".to_string(),
+        );
+    }
+    if manifest.get("auth").is_some() {
+        let message = if manifest
+            .get("endpoint")
+            .and_then(|e| e.get("auth"))
+            .is_some()
+        {
+            "top-level auth is shadowed by endpoint.auth and ignored; remove it"
+        } else {
+            "top-level auth is deprecated in V2 manifests; move it to endpoint.auth"
+        };
+        report.push(
+            "deprecated_field",
+            LintSeverity::Warning,
+            "auth",
+            message.to_string(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(yaml: &str) -> LintReport {
+        lint_manifest(&serde_yaml::from_str(yaml).unwrap())
+    }
+
+    fn codes(report: &LintReport) -> Vec<(&str, &str)> {
+        report
+            .findings
+            .iter()
+            .map(|f| (f.code.as_str(), f.path.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_v2_manifest_has_no_findings() {
+        let report = lint(
+            r#"
+id: acme
+protocol_version: "2.0"
+endpoint:
+  base_url: https://api.acme.test/v1
+  auth: { type: bearer, header: Authorization }
+capabilities:
+  required: [text, streaming, tools]
+  optional: [embeddings]
+endpoints:
+  chat: /chat/completions
+  embeddings: /embeddings
+parameter_mappings: { model: model, messages: messages, stream: stream, tools: tools }
+streaming:
+  event_map:
+    - { match: "exists($.choices[*].delta.content)", emit: PartialContentDelta }
+    - { match: "exists($.usage)", emit: Metadata }
+retry_policy: { strategy: exponential_backoff, max_retries: 3, retry_on_http_status: [429, 503] }
+"#,
+        );
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn test_reports_each_rule() {
+        let report = lint(
+            r#"
+id: legacy
+protocol_version: "2.0"
+endpoint: { base_url: https://x.test }
+auth: { type: bearer, header_name: Authorization }
+capabilities: { streaming: true, tools: true, vision: false, embeddings: true }
+parameter_mappings: { model: model, messages: messages, stream: stream, top_k: top_k }
+streaming:
+  event_map:
+    - { match: "exists($.usage)", emit: Metadata }
+    - { match: "exists($.usage)", emit: PartialContentDelta }
+    - { match: "exists($.x)", emit: Delta }
+    - { match: "", emit: Metadata }
+"#,
+        );
+        let found = codes(&report);
+        for expected in [
+            ("unused_parameter_mapping", "parameter_mappings.top_k"),
+            ("capability_mismatch", "parameter_mappings"),
+            ("unreachable_event_rule", "streaming.event_map[1]"),
+            ("unreachable_event_rule", "streaming.event_map[2]"),
+            ("unreachable_event_rule", "streaming.event_map[3]"),
+            ("missing_retry_policy", "retry_policy"),
+            ("capability_mismatch", "capabilities"),
+            ("deprecated_field", "auth.header_name"),
+            ("deprecated_field", "auth"),
+        ] {
+            assert!(
+                found.contains(&expected),
+                "missing {expected:?} in {found:?}"
+            );
+        }
+        assert_eq!(report.count(LintSeverity::Error), 1);
+        assert!(report.has_at_least(LintSeverity::Warning));
+    }
+
+    #[test]
+    fn test_declared_capabilities_shapes() {
+        let legacy = declared_capabilities(&serde_json::json!({
+            "capabilities": {"streaming": true, "tools": true, "vision": false}
+        }));
+        assert_eq!(legacy.get("text"), Some(&CapabilityLevel::Required));
+        assert_eq!(legacy.get("streaming"), Some(&CapabilityLevel::Required));
+        assert_eq!(legacy.get("tools"), Some(&CapabilityLevel::Optional));
+        assert!(!legacy.contains_key("vision"));
+
+        let tags = declared_capabilities(&serde_json::json!({"capabilities": ["chat", "tools"]}));
+        assert_eq!(tags.keys().collect::<Vec<_>>(), ["text", "tools"]);
+    }
+}
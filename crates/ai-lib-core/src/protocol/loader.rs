@@ -2,6 +2,7 @@
 //! Heartbeat sync - 2026-01-06
 //! Includes hot-reload capability using ArcSwap
 
+use crate::protocol::remote::{FetchedManifest, RemoteManifestConfig, RemoteManifestStore};
 use crate::protocol::{ProtocolError, ProtocolManifest};
 use arc_swap::ArcSwap;
 use lru::LruCache;
@@ -14,6 +15,12 @@ pub struct ProtocolLoader {
     hot_reload: bool,
     validator: crate::protocol::validator::ProtocolValidator,
     cache: Mutex<LruCache<String, Arc<ProtocolManifest>>>,
+    /// In-memory manifests by provider id, consulted before any file or URL source.
+    preloaded: std::collections::HashMap<String, ProtocolManifest>,
+    /// Cache, pin and trust policy for remote manifests.
+    remote: RemoteManifestStore,
+    /// Invalid `AI_PROTOCOL_*` trust settings; every remote load fails with it.
+    remote_error: Option<String>,
 }
 
 impl ProtocolLoader {
@@ -28,7 +35,11 @@ impl ProtocolLoader {
                 std::num::NonZeroUsize::new(100)
                     .expect("Cache size must be non-zero (this should never happen)"),
             )),
+            preloaded: std::collections::HashMap::new(),
+            remote: RemoteManifestStore::new(RemoteManifestConfig::default()),
+            remote_error: None,
         }
+        .with_remote_from_env()
     }
 
     /// Set base path for protocol files
@@ -43,6 +54,29 @@ impl ProtocolLoader {
         self
     }
 
+    /// Configure remote fetching (cache, offline mode, pin, trusted keys). Defaults to
+    /// [`RemoteManifestConfig::from_env`].
+    pub fn with_remote(mut self, config: RemoteManifestConfig) -> Self {
+        self.remote = RemoteManifestStore::new(config);
+        self.remote_error = None;
+        self
+    }
+
+    fn with_remote_from_env(mut self) -> Self {
+        match RemoteManifestConfig::from_env() {
+            Ok(config) => self.remote = RemoteManifestStore::new(config),
+            Err(e) => self.remote_error = Some(e.to_string()),
+        }
+        self
+    }
+
+    /// Register an in-memory manifest; `"<manifest.id>/<model>"` then resolves to it
+    /// without touching the file system.
+    pub fn with_manifest(mut self, manifest: ProtocolManifest) -> Self {
+        self.preloaded.insert(manifest.id.clone(), manifest);
+        self
+    }
+
     /// Load a model configuration
     /// Model identifier format: "provider/model-name"
     pub async fn load_model(&self, model: &str) -> Result<ProtocolManifest, ProtocolError> {
@@ -71,13 +105,18 @@ impl ProtocolLoader {
         let provider = parts[0];
         let model_name = parts[1..].join("/");
 
-        // First, try to load model registry to get provider reference.
+        // In-memory manifests (`with_manifest`) take precedence over every other source.
+        // Otherwise, try to load model registry to get provider reference.
         // If registry doesn't contain this model (common for providers like deepseek),
         // fall back to loading provider manifest directly using the provider segment.
-        let manifest = match self.load_model_config(&model_name).await {
-            Ok(model_config) => self.load_provider(&model_config.provider).await?,
-            Err(ProtocolError::NotFound { .. }) => self.load_provider(provider).await?,
-            Err(e) => return Err(e),
+        let manifest = if let Some(manifest) = self.preloaded.get(provider) {
+            manifest.clone()
+        } else {
+            match self.load_model_config(&model_name).await {
+                Ok(model_config) => self.load_provider(&model_config.provider).await?,
+                Err(ProtocolError::NotFound { .. }) => self.load_provider(provider).await?,
+                Err(e) => return Err(e),
+            }
         };
 
         // 2. Update Cache
@@ -99,11 +138,15 @@ impl ProtocolLoader {
         &self,
         provider_id: &str,
     ) -> Result<ProtocolManifest, ProtocolError> {
+        if let Some(manifest) = self.preloaded.get(provider_id) {
+            return Ok(manifest.clone());
+        }
+
         // Try multiple sources in order:
         // 1. Local file system (dist JSON) - PREFERRED
         // 2. Local file system (source YAML) - FALLBACK
-        // 3. GitHub URL (if AI_PROTOCOL_DIR is a URL)
-        // 4. Embedded assets (future)
+        // 3. Remote URL (if AI_PROTOCOL_DIR is a URL), through the manifest cache
+        // 4. Canonical GitHub source at the configured pin, then the bundled snapshot
 
         // Path prioritization helper
         let mut search_locations: Vec<(PathBuf, bool)> = Vec::new(); // (path_base, is_json_preferred)
@@ -125,27 +168,13 @@ impl ProtocolLoader {
             std::env::var("AI_PROTOCOL_DIR").or_else(|_| std::env::var("AI_PROTOCOL_PATH"))
         {
             if root.starts_with("http://") || root.starts_with("https://") {
-                // Handling URL sources (Remote)
-                // Try JSON first if it looks like a raw github url, but typically raw github urls are specific.
-                // For simplicity, we stick to the existing logic for URLs but could enhance later to try .json
-                let url = if root.ends_with('/') {
-                    format!("{}dist/v1/providers/{}.json", root, provider_id)
-                } else {
-                    format!("{}/dist/v1/providers/{}.json", root, provider_id)
-                };
-
-                // Try JSON from remote
-                if let Ok(manifest) = self.load_from_json_url(&url).await {
-                    return Ok(manifest);
-                }
-
-                // Fallback to YAML from remote
-                let url_yaml = if root.ends_with('/') {
-                    format!("{}v1/providers/{}.yaml", root, provider_id)
-                } else {
-                    format!("{}/v1/providers/{}.yaml", root, provider_id)
-                };
-                return self.load_from_url(&url_yaml).await;
+                // Remote root: same cache / trust policy, but the URL already names a ref.
+                let store = RemoteManifestStore::new(self.remote.config().clone().with_root(root));
+                let candidates = [
+                    format!("dist/v1/providers/{}.json", provider_id),
+                    format!("v1/providers/{}.yaml", provider_id),
+                ];
+                return self.load_from_remote(&store, &candidates, false).await;
             } else {
                 // Local Path from Env
                 let root = PathBuf::from(root);
@@ -186,31 +215,17 @@ impl ProtocolLoader {
             }
         }
 
-        // Last resort: try GitHub raw URL (canonical source) - JSON (v2 first)
-        let github_json_v2 = format!(
-            "https://raw.githubusercontent.com/ailib-official/ai-protocol/main/dist/v2/providers/{}.json",
-            provider_id
-        );
-        if let Ok(manifest) = self.load_from_json_url(&github_json_v2).await {
-            return Ok(manifest);
-        }
-
-        // Last resort fallback: v1 JSON
-        let github_json = format!(
-            "https://raw.githubusercontent.com/ailib-official/ai-protocol/main/dist/v1/providers/{}.json",
-            provider_id
-        );
-        if let Ok(manifest) = self.load_from_json_url(&github_json).await {
-            return Ok(manifest);
-        }
-
-        // Last resort fallback: YAML
-        let github_yaml = format!(
-            "https://raw.githubusercontent.com/ailib-official/ai-protocol/main/v1/providers/{}.yaml",
-            provider_id
-        );
-        if let Ok(manifest) = self.load_from_url(&github_yaml).await {
-            return Ok(manifest);
+        // Last resort: canonical GitHub source at the configured pin (v2 first), then the
+        // bundled snapshot. Integrity failures are surfaced rather than skipped.
+        let candidates = [
+            format!("dist/v2/providers/{}.json", provider_id),
+            format!("dist/v1/providers/{}.json", provider_id),
+            format!("v1/providers/{}.yaml", provider_id),
+        ];
+        match self.load_from_remote(&self.remote, &candidates, true).await {
+            Ok(manifest) => return Ok(manifest),
+            Err(e @ ProtocolError::IntegrityError { .. }) => return Err(e),
+            Err(_) => {}
         }
 
         Err(ProtocolError::NotFound {
@@ -288,95 +303,62 @@ impl ProtocolLoader {
         Ok(manifest)
     }
 
-    /// Load protocol from remote JSON URL
-    async fn load_from_json_url(&self, url: &str) -> Result<ProtocolManifest, ProtocolError> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .map_err(|e| ProtocolError::Internal(format!("Failed to create HTTP client: {}", e)))?;
-
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| ProtocolError::LoadError {
-                path: url.to_string(),
-                reason: format!("HTTP request failed: {}", e),
-                hint: None,
-            })?;
-
-        if !response.status().is_success() {
-            return Err(ProtocolError::LoadError {
-                path: url.to_string(),
-                reason: format!("HTTP {}", response.status()),
-                hint: None,
+    /// Load the first available candidate (paths relative to the store's root) through
+    /// the manifest cache, falling back to the bundled snapshot when `bundled` is set.
+    async fn load_from_remote(
+        &self,
+        store: &RemoteManifestStore,
+        candidates: &[String],
+        bundled: bool,
+    ) -> Result<ProtocolManifest, ProtocolError> {
+        if let Some(reason) = &self.remote_error {
+            return Err(ProtocolError::IntegrityError {
+                path: "AI_PROTOCOL_TRUSTED_KEYS".to_string(),
+                reason: reason.clone(),
             });
         }
-
-        let content = response
-            .bytes()
-            .await
-            .map_err(|e| ProtocolError::LoadError {
-                path: url.to_string(),
-                reason: format!("Failed to read bytes: {}", e),
-                hint: None,
-            })?;
-
-        let manifest: ProtocolManifest = serde_json::from_slice(&content).map_err(|e| {
-            ProtocolError::ValidationError(format!("Invalid JSON manifest from URL: {}", e))
-        })?;
-
-        self.validator.validate(&manifest)?;
-        Ok(manifest)
-    }
-
-    /// Load protocol from remote URL (GitHub raw URL)
-    async fn load_from_url(&self, url: &str) -> Result<ProtocolManifest, ProtocolError> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .map_err(|e| ProtocolError::Internal(format!("Failed to create HTTP client: {}", e)))?;
-
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| ProtocolError::LoadError {
-                path: url.to_string(),
-                reason: format!("HTTP request failed: {}", e),
-                hint: Some(
-                    "Check your internet connection and verify the URL is accessible.".to_string(),
-                ),
-            })?;
-
-        if !response.status().is_success() {
-            return Err(ProtocolError::LoadError {
-                path: url.to_string(),
-                reason: format!(
-                    "HTTP {}: {}",
-                    response.status(),
-                    response.text().await.unwrap_or_default()
-                ),
-                hint: Some(
-                    "Verify the remote registry URL and your API permissions if any.".to_string(),
-                ),
-            });
+        let mut last_error = None;
+        for path in candidates {
+            match store.fetch(path).await {
+                Ok(fetched) => return self.parse_fetched(path, &fetched),
+                Err(e @ ProtocolError::IntegrityError { .. }) => return Err(e),
+                Err(e) => last_error = Some(e),
+            }
         }
+        if bundled {
+            if let Some((path, fetched)) = candidates
+                .iter()
+                .find_map(|p| store.bundled(p).map(|f| (p, f)))
+            {
+                tracing::warn!(url = %fetched.url, "using bundled protocol snapshot");
+                return self.parse_fetched(path, &fetched);
+            }
+        }
+        Err(last_error.unwrap_or_else(|| ProtocolError::NotFound {
+            id: candidates.join(", "),
+            hint: None,
+        }))
+    }
 
-        let content = response
-            .text()
-            .await
-            .map_err(|e| ProtocolError::LoadError {
-                path: url.to_string(),
-                reason: format!("Failed to read response: {}", e),
-                hint: None,
-            })?;
-
-        let manifest: ProtocolManifest = Self::parse_manifest_yaml(&content)?;
-
-        // Validate against JSON Schema
+    fn parse_fetched(
+        &self,
+        path: &str,
+        fetched: &FetchedManifest,
+    ) -> Result<ProtocolManifest, ProtocolError> {
+        let manifest: ProtocolManifest = if path.ends_with(".json") {
+            serde_json::from_slice(&fetched.body).map_err(|e| {
+                ProtocolError::ValidationError(format!("Invalid JSON manifest from URL: {}", e))
+            })?
+        } else {
+            let content =
+                std::str::from_utf8(&fetched.body).map_err(|e| ProtocolError::LoadError {
+                    path: fetched.url.clone(),
+                    reason: format!("Invalid UTF-8: {}", e),
+                    hint: None,
+                })?;
+            Self::parse_manifest_yaml(content)?
+        };
         self.validator.validate(&manifest)?;
-
         Ok(manifest)
     }
 
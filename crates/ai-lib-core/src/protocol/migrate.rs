@@ -0,0 +1,260 @@
+//! V1 → V2 清单迁移：能力提升、认证迁移到 endpoint.auth、废弃字段改名。
+//!
+//! Rewrites V1 manifests into the V2 shape. Capabilities are promoted through
+//! [`CapabilitiesV2::promote_to_v2`]; everything the runtime still reads (`endpoints`,
+//! `parameter_mappings`, streaming paths, ...) is carried over unchanged so the migrated
+//! manifest keeps loading through [`ProtocolLoader`](super::ProtocolLoader).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::lint::{has_legacy_capabilities, is_v2};
+use super::v2::{CapabilitiesV2, Capability};
+
+/// Protocol version written by [`migrate_to_v2`].
+pub const TARGET_PROTOCOL_VERSION: &str = "2.0";
+
+/// Result of a migration: the rewritten manifest plus a description of every change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub manifest: Value,
+    pub changes: Vec<MigrationChange>,
+}
+
+impl MigrationReport {
+    /// Whether the input was already a V2 manifest with nothing to rewrite.
+    pub fn is_noop(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// One rewrite applied to the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationChange {
+    pub path: String,
+    pub description: String,
+}
+
+/// Rewrite a V1 manifest (YAML/JSON value) into the V2 shape.
+///
+/// Already-V2 manifests only have their deprecated fields rewritten, so running the
+/// migration twice is a no-op.
+pub fn migrate_to_v2(manifest: &Value) -> Result<MigrationReport, String> {
+    let Value::Object(source) = manifest else {
+        return Err("manifest must be a mapping".to_string());
+    };
+    let mut out = source.clone();
+    let mut changes = Vec::new();
+    let mut change = |path: &str, description: String| {
+        changes.push(MigrationChange {
+            path: path.to_string(),
+            description,
+        })
+    };
+
+    if !is_v2(manifest) {
+        let from = source
+            .get("protocol_version")
+            .map(|v| {
+                v.as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| v.to_string())
+            })
+            .unwrap_or_else(|| "unset".to_string());
+        out.insert(
+            "protocol_version".to_string(),
+            Value::String(TARGET_PROTOCOL_VERSION.to_string()),
+        );
+        change(
+            "protocol_version",
+            format!("{from} -> {TARGET_PROTOCOL_VERSION}"),
+        );
+    }
+
+    if has_legacy_capabilities(manifest) {
+        let legacy = source.get("capabilities").cloned().unwrap_or(Value::Null);
+        out.insert("capabilities".to_string(), promote_capabilities(&legacy)?);
+        change(
+            "capabilities",
+            "promoted boolean flags to required/optional lists".to_string(),
+        );
+    }
+
+    let endpoint = out
+        .entry("endpoint")
+        .or_insert_with(|| Value::Object(Map::new()));
+    let Value::Object(endpoint) = endpoint else {
+        return Err("endpoint must be a mapping".to_string());
+    };
+    if let Some(auth) = rewrite_endpoint_auth(endpoint, &mut change) {
+        endpoint.insert("auth".to_string(), auth);
+    }
+    if let Some(top) = out.remove("auth") {
+        let endpoint = out
+            .get_mut("endpoint")
+            .and_then(Value::as_object_mut)
+            .expect("endpoint is a mapping");
+        if endpoint.contains_key("auth") {
+            change(
+                "auth",
+                "removed top-level auth shadowed by endpoint.auth".to_string(),
+            );
+        } else {
+            let mut auth = top;
+            rename_auth_fields(&mut auth, "auth", &mut change);
+            endpoint.insert("auth".to_string(), auth);
+            change("auth", "moved to endpoint.auth".to_string());
+        }
+    }
+
+    Ok(MigrationReport {
+        manifest: Value::Object(out),
+        changes,
+    })
+}
+
+/// Rename deprecated fields in an existing `endpoint.auth`, returning it when rewritten.
+fn rewrite_endpoint_auth(
+    endpoint: &mut Map<String, Value>,
+    change: &mut impl FnMut(&str, String),
+) -> Option<Value> {
+    let mut auth = endpoint.get("auth")?.clone();
+    rename_auth_fields(&mut auth, "endpoint.auth", change).then_some(auth)
+}
+
+/// `header_name` → `header`, `key_env` → `token_env`. Returns whether anything changed.
+fn rename_auth_fields(
+    auth: &mut Value,
+    prefix: &str,
+    change: &mut impl FnMut(&str, String),
+) -> bool {
+    let Value::Object(map) = auth else {
+        return false;
+    };
+    let mut changed = false;
+    for (old, new) in [("header_name", "header"), ("key_env", "token_env")] {
+        if let Some(value) = map.remove(old) {
+            changed = true;
+            if map.contains_key(new) {
+                change(
+                    &format!("{prefix}.{old}"),
+                    format!("removed; {new} is already set"),
+                );
+            } else {
+                map.insert(new.to_string(), value);
+                change(&format!("{prefix}.{old}"), format!("renamed to {new}"));
+            }
+        }
+    }
+    changed
+}
+
+/// Promote a V1 boolean capability map.
+///
+/// Flags outside the V1 set handled by `promote_to_v2` (`audio`, `structured_output`,
+/// `embeddings`, ...) are appended to `optional` when they name a known [`Capability`];
+/// `multimodal` is dropped since V2 declares modalities individually.
+fn promote_capabilities(legacy: &Value) -> Result<Value, String> {
+    let parsed: CapabilitiesV2 = serde_json::from_value(legacy.clone())
+        .map_err(|e| format!("cannot read legacy capabilities: {e}"))?;
+    let promoted = parsed.promote_to_v2();
+    let mut value = serde_json::to_value(&promoted).map_err(|e| e.to_string())?;
+
+    let declared = promoted.all_capabilities();
+    let extra: Vec<Value> = legacy
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(_, flag)| flag.as_bool() == Some(true))
+        .filter_map(|(name, _)| {
+            serde_json::from_value::<Capability>(Value::String(name.clone())).ok()
+        })
+        .filter(|cap| !declared.contains(cap))
+        .map(|cap| serde_json::to_value(cap).expect("capability serializes"))
+        .collect();
+    if let Some(optional) = value.get_mut("optional").and_then(Value::as_array_mut) {
+        optional.extend(extra);
+    }
+    if let Some(map) = value.as_object_mut() {
+        // Default feature flags carry no information; keep the output minimal.
+        if map.get("feature_flags").is_some_and(|f| {
+            f.as_object()
+                .is_some_and(|o| o.values().all(|v| v.is_null() || v == &Value::Bool(false)))
+        }) {
+            map.remove("feature_flags");
+        }
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{ManifestV2, ProtocolManifest};
+
+    const V1: &str = r#"
+id: acme
+protocol_version: "1.5"
+name: Acme
+status: stable
+category: ai_provider
+official_url: https://acme.test
+support_contact: ops@acme.test
+endpoint: { base_url: "https://api.acme.test/v1" }
+auth: { type: bearer, header_name: Authorization, key_env: ACME_API_KEY }
+capabilities: { streaming: true, tools: true, vision: false, audio: true, multimodal: true }
+endpoints:
+  chat: { path: /chat/completions, method: POST }
+parameter_mappings: { model: model, messages: messages, stream: stream, tools: tools }
+streaming:
+  content_path: choices[0].delta.content
+"#;
+
+    #[test]
+    fn test_migrates_v1_manifest() {
+        let v1: Value = serde_yaml::from_str(V1).unwrap();
+        let report = migrate_to_v2(&v1).unwrap();
+        let m = &report.manifest;
+        assert_eq!(m["protocol_version"], "2.0");
+        assert_eq!(
+            m["capabilities"]["required"],
+            serde_json::json!(["text", "streaming"])
+        );
+        assert_eq!(
+            m["capabilities"]["optional"],
+            serde_json::json!(["tools", "audio"])
+        );
+        assert!(m.get("auth").is_none());
+        assert_eq!(m["endpoint"]["auth"]["header"], "Authorization");
+        assert_eq!(m["endpoint"]["auth"]["token_env"], "ACME_API_KEY");
+        assert_eq!(m["streaming"]["content_path"], "choices[0].delta.content");
+
+        let paths: Vec<&str> = report.changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "protocol_version",
+                "capabilities",
+                "auth.header_name",
+                "auth.key_env",
+                "auth"
+            ]
+        );
+
+        // Output stays loadable by both the runtime and the V2 parser.
+        let runtime: ProtocolManifest = serde_json::from_value(m.clone()).unwrap();
+        assert!(runtime.capabilities.tools && runtime.capabilities.audio);
+        assert!(runtime.endpoint.auth.is_some());
+        let v2: ManifestV2 = serde_json::from_value(m.clone()).unwrap();
+        assert!(v2.is_v2() && v2.has_capability(Capability::Tools));
+    }
+
+    #[test]
+    fn test_migration_is_idempotent() {
+        let v1: Value = serde_yaml::from_str(V1).unwrap();
+        let once = migrate_to_v2(&v1).unwrap();
+        let twice = migrate_to_v2(&once.manifest).unwrap();
+        assert!(twice.is_noop());
+        assert_eq!(twice.manifest, once.manifest);
+    }
+}
@@ -25,6 +25,10 @@
 //! | [`config`] | Configuration structures (streaming, auth, endpoints) |
 //! | [`error`] | Protocol-specific error types |
 //! | [`request`] | Unified request format for cross-provider compatibility |
+//! | [`lint`] | Manifest quality checks beyond schema validity |
+//! | [`diff`] | Semantic differences between two manifests |
+//! | [`migrate`] | V1 → V2 manifest rewriting |
+//! | [`remote`] | Cached, pinned and signature-checked remote manifest fetching |
 //!
 //! ## Example
 //!
@@ -51,10 +55,15 @@
 //! ```
 
 pub mod config;
+pub mod diff;
 pub mod error;
+pub mod lint;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod loader;
 pub mod manifest;
+pub mod migrate;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
 pub mod request;
 pub mod schema;
 pub mod v2;
@@ -64,10 +73,18 @@ pub mod wasm_manifest;
 
 // Re-export main types for convenient access
 pub use config::*;
+pub use diff::{diff_manifests, DiffEntry, DiffKind, ManifestDiff};
 pub use error::ProtocolError;
+pub use lint::{lint_manifest, LintFinding, LintReport, LintSeverity};
 #[cfg(not(target_arch = "wasm32"))]
 pub use loader::ProtocolLoader;
 pub use manifest::ProtocolManifest;
+pub use migrate::{migrate_to_v2, MigrationChange, MigrationReport};
+#[cfg(not(target_arch = "wasm32"))]
+pub use remote::{
+    FetchSource, FetchedManifest, ProtocolPin, Provenance, RemoteManifestConfig,
+    RemoteManifestStore, TrustPolicy,
+};
 pub use request::UnifiedRequest;
 pub use schema::ProtocolSchema;
 pub use v2::{CapabilitiesV2, Capability, FeatureFlags, ManifestV2};
@@ -0,0 +1,948 @@
+//! 远程清单获取：磁盘缓存（ETag/If-Modified-Since 重新验证）、离线模式、版本固定、
+//! ed25519 签名 / SHA-256 校验和验证，以及编译期打包快照兜底。
+//!
+//! Remote manifest fetching for [`ProtocolLoader`](super::ProtocolLoader).
+//!
+//! - **Pinning**: the default GitHub root is addressed by a [`ProtocolPin`] (branch,
+//!   release tag or commit) instead of always tracking `main`.
+//! - **Cache** (opt-in): with a cache directory configured, responses are stored on disk
+//!   with their `ETag` / `Last-Modified` and revalidated with conditional requests once
+//!   older than `max_age`. Commit pins are immutable and never revalidated. Offline mode
+//!   serves the cache only, and a network failure falls back to a stale entry.
+//! - **Integrity**: a [`TrustPolicy`] verifies every body (including cache hits) against
+//!   a pinned SHA-256 checksum or, with the `manifest_signatures` feature, a detached
+//!   ed25519 signature (`<url>.sig`, base64) from a trusted key set. A bad checksum or
+//!   signature is always fatal. Once trusted keys are configured a missing signature is
+//!   fatal too; without keys, unverified bodies are rejected when `require_verified` is
+//!   set and logged otherwise.
+//! - **Bundled snapshot**: manifests embedded at build time from `AI_PROTOCOL_BUNDLE_DIR`
+//!   are the last resort when nothing else is reachable.
+//!
+//! Environment (read by [`RemoteManifestConfig::from_env`]): `AI_PROTOCOL_PIN`,
+//! `AI_PROTOCOL_CACHE_DIR` (a path, `default` for `~/.cache/ai-lib/manifests`, or `off`),
+//! `AI_PROTOCOL_CACHE_MAX_AGE_SECS`, `AI_PROTOCOL_OFFLINE`, `AI_PROTOCOL_TRUSTED_KEYS`
+//! (comma-separated base64 or hex ed25519 public keys; an error without the
+//! `manifest_signatures` feature) and `AI_PROTOCOL_REQUIRE_VERIFIED`.
+//!
+//! The module is not built for `wasm32`, so neither the disk cache nor the network
+//! fetcher exists there.
+
+#[cfg(feature = "manifest_signatures")]
+use base64::Engine as _;
+#[cfg(feature = "manifest_signatures")]
+use ed25519_dalek::{Signature, VerifyingKey};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use super::ProtocolError;
+
+mod bundled {
+    include!(concat!(env!("OUT_DIR"), "/bundled_manifests.rs"));
+}
+
+/// Canonical remote root; a [`ProtocolPin`] selects the git ref beneath it.
+pub const DEFAULT_REMOTE_ROOT: &str =
+    "https://raw.githubusercontent.com/ailib-official/ai-protocol";
+
+/// Git ref of the protocol repository to fetch manifests from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolPin {
+    /// Moving branch; cached entries are revalidated.
+    Branch(String),
+    /// Release tag, e.g. `v0.8.0`.
+    Release(String),
+    /// Commit SHA; content is immutable so cached entries never expire.
+    Commit(String),
+}
+
+impl Default for ProtocolPin {
+    fn default() -> Self {
+        Self::Branch("main".to_string())
+    }
+}
+
+impl ProtocolPin {
+    /// Classify a ref: a full 40-char SHA (or `commit:<sha>`, which may be abbreviated
+    /// to 7 chars) is a commit, `v<digit>...` a release, else a branch. Short hex names
+    /// without the prefix stay branches (`cafe`, `deadbeef`).
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        let is_sha = |r: &str, min: usize| {
+            (min..=40).contains(&r.len()) && r.chars().all(|c| c.is_ascii_hexdigit())
+        };
+        if let Some(sha) = s.strip_prefix("commit:").filter(|r| is_sha(r, 7)) {
+            Self::Commit(sha.to_ascii_lowercase())
+        } else if is_sha(s, 40) {
+            Self::Commit(s.to_ascii_lowercase())
+        } else if s.starts_with('v') && s[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            Self::Release(s.to_string())
+        } else {
+            Self::Branch(s.to_string())
+        }
+    }
+
+    pub fn git_ref(&self) -> &str {
+        match self {
+            Self::Branch(r) | Self::Release(r) | Self::Commit(r) => r,
+        }
+    }
+
+    pub fn is_immutable(&self) -> bool {
+        matches!(self, Self::Commit(_))
+    }
+}
+
+/// How a manifest body was authenticated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Provenance {
+    /// Detached ed25519 signature from a trusted key (`key_id` = first 8 key bytes, hex).
+    Signature { key_id: String },
+    /// Matched a pinned SHA-256 checksum.
+    Checksum,
+    /// Embedded at build time.
+    Bundled,
+    /// No trust anchor applied.
+    Unverified,
+}
+
+/// Trusted keys and pinned checksums used to authenticate downloaded manifests.
+#[derive(Debug, Clone, Default)]
+pub struct TrustPolicy {
+    #[cfg(feature = "manifest_signatures")]
+    keys: Vec<(String, VerifyingKey)>,
+    /// Relative manifest path (`dist/v2/providers/openai.json`) -> lowercase SHA-256 hex.
+    checksums: HashMap<String, String>,
+    require_verified: bool,
+}
+
+impl TrustPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust an ed25519 public key given as base64 or hex (32 bytes).
+    #[cfg(feature = "manifest_signatures")]
+    pub fn with_trusted_key(self, key: &str) -> Result<Self, ProtocolError> {
+        let key = key.trim();
+        let bytes = decode_hex(key)
+            .or_else(|| base64::engine::general_purpose::STANDARD.decode(key).ok())
+            .ok_or_else(|| {
+                ProtocolError::ManifestError(format!("trusted key '{key}' is not base64 or hex"))
+            })?;
+        let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+            ProtocolError::ManifestError("ed25519 public keys are 32 bytes".to_string())
+        })?;
+        let key = VerifyingKey::from_bytes(&bytes)
+            .map_err(|e| ProtocolError::ManifestError(format!("invalid ed25519 key: {e}")))?;
+        Ok(self.with_verifying_key(key))
+    }
+
+    /// Without the `manifest_signatures` feature no key can be trusted; this always
+    /// fails so that a configured key is never silently ignored.
+    #[cfg(not(feature = "manifest_signatures"))]
+    pub fn with_trusted_key(self, key: &str) -> Result<Self, ProtocolError> {
+        Err(ProtocolError::ManifestError(format!(
+            "cannot trust key '{}': signature verification needs the `manifest_signatures` feature",
+            key.trim()
+        )))
+    }
+
+    #[cfg(feature = "manifest_signatures")]
+    pub fn with_verifying_key(mut self, key: VerifyingKey) -> Self {
+        self.keys.push((to_hex(&key.as_bytes()[..8]), key));
+        self
+    }
+
+    /// Pin the SHA-256 of a manifest by its path relative to the protocol root.
+    pub fn with_checksum(mut self, path: impl Into<String>, sha256_hex: &str) -> Self {
+        self.checksums
+            .insert(path.into(), sha256_hex.trim().to_ascii_lowercase());
+        self
+    }
+
+    /// Reject manifests that no key or checksum vouches for. Implied once any trusted
+    /// key is configured.
+    pub fn require_verified(mut self, require: bool) -> Self {
+        self.require_verified = require;
+        self
+    }
+
+    #[cfg(feature = "manifest_signatures")]
+    pub fn has_keys(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    #[cfg(not(feature = "manifest_signatures"))]
+    pub fn has_keys(&self) -> bool {
+        false
+    }
+
+    fn has_checksum(&self, path: &str) -> bool {
+        self.checksums.contains_key(path)
+    }
+
+    /// Authenticate `body` fetched for `path`; `signature` is the base64 detached signature.
+    pub fn verify(
+        &self,
+        path: &str,
+        body: &[u8],
+        signature: Option<&str>,
+    ) -> Result<Provenance, ProtocolError> {
+        let fail = |reason: String| ProtocolError::IntegrityError {
+            path: path.to_string(),
+            reason,
+        };
+        if let Some(expected) = self.checksums.get(path) {
+            let actual = sha256_hex(body);
+            return if &actual == expected {
+                Ok(Provenance::Checksum)
+            } else {
+                Err(fail(format!(
+                    "sha256 {actual} does not match pinned {expected}"
+                )))
+            };
+        }
+        #[cfg(feature = "manifest_signatures")]
+        if let Some(signature) = signature {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(signature.trim())
+                .map_err(|e| fail(format!("signature is not base64: {e}")))?;
+            let signature = Signature::from_slice(&bytes)
+                .map_err(|e| fail(format!("malformed ed25519 signature: {e}")))?;
+            if let Some((key_id, _)) = self
+                .keys
+                .iter()
+                .find(|(_, key)| key.verify_strict(body, &signature).is_ok())
+            {
+                return Ok(Provenance::Signature {
+                    key_id: key_id.clone(),
+                });
+            }
+            if self.has_keys() {
+                return Err(fail("signature does not match any trusted key".to_string()));
+            }
+        }
+        #[cfg(not(feature = "manifest_signatures"))]
+        let _ = signature;
+        if self.require_verified || self.has_keys() {
+            return Err(fail(if self.has_keys() {
+                "no signature published for this manifest".to_string()
+            } else {
+                "verification required but no trusted keys or checksum configured".to_string()
+            }));
+        }
+        Ok(Provenance::Unverified)
+    }
+}
+
+/// Where [`RemoteManifestStore::fetch`] got the body from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchSource {
+    /// Downloaded (HTTP 200).
+    Network,
+    /// Cached entry confirmed by HTTP 304.
+    Revalidated,
+    /// Fresh cached entry; no request made.
+    Cache,
+    /// Expired cached entry served because the network was unavailable or offline mode is on.
+    StaleCache,
+    /// Build-time snapshot.
+    Bundled,
+}
+
+/// A fetched, authenticated manifest body.
+#[derive(Debug, Clone)]
+pub struct FetchedManifest {
+    pub url: String,
+    pub body: Vec<u8>,
+    pub source: FetchSource,
+    pub provenance: Provenance,
+}
+
+/// Remote fetch settings.
+#[derive(Debug, Clone)]
+pub struct RemoteManifestConfig {
+    root: String,
+    /// `None` for custom roots that already address a specific ref.
+    pin: Option<ProtocolPin>,
+    cache_dir: Option<PathBuf>,
+    max_age: Duration,
+    offline: bool,
+    bundled: bool,
+    timeout: Duration,
+    trust: TrustPolicy,
+}
+
+impl Default for RemoteManifestConfig {
+    fn default() -> Self {
+        Self {
+            root: DEFAULT_REMOTE_ROOT.to_string(),
+            pin: Some(ProtocolPin::default()),
+            cache_dir: None,
+            max_age: Duration::from_secs(3600),
+            offline: false,
+            bundled: true,
+            timeout: Duration::from_secs(30),
+            trust: TrustPolicy::default(),
+        }
+    }
+}
+
+impl RemoteManifestConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defaults overridden by the `AI_PROTOCOL_*` environment variables (see module docs).
+    ///
+    /// A malformed `AI_PROTOCOL_TRUSTED_KEYS` entry is an error rather than skipped, so a
+    /// typo cannot silently turn signature verification off.
+    pub fn from_env() -> Result<Self, ProtocolError> {
+        let mut config = Self::default();
+        let flag = |name: &str| {
+            std::env::var(name)
+                .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false)
+        };
+        if let Ok(pin) = std::env::var("AI_PROTOCOL_PIN") {
+            config.pin = Some(ProtocolPin::parse(&pin));
+        }
+        match std::env::var("AI_PROTOCOL_CACHE_DIR") {
+            Ok(dir) if dir.eq_ignore_ascii_case("off") => config.cache_dir = None,
+            Ok(dir) if dir.eq_ignore_ascii_case("default") => {
+                config.cache_dir = default_cache_dir()
+            }
+            Ok(dir) => config.cache_dir = Some(PathBuf::from(dir)),
+            Err(_) => {}
+        }
+        if let Some(secs) = std::env::var("AI_PROTOCOL_CACHE_MAX_AGE_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+        {
+            config.max_age = Duration::from_secs(secs);
+        }
+        config.offline = flag("AI_PROTOCOL_OFFLINE");
+        let mut trust = TrustPolicy::new().require_verified(flag("AI_PROTOCOL_REQUIRE_VERIFIED"));
+        if let Ok(keys) = std::env::var("AI_PROTOCOL_TRUSTED_KEYS") {
+            for key in keys.split(',').filter(|k| !k.trim().is_empty()) {
+                trust = trust
+                    .with_trusted_key(key)
+                    .map_err(|e| ProtocolError::IntegrityError {
+                        path: "AI_PROTOCOL_TRUSTED_KEYS".to_string(),
+                        reason: e.to_string(),
+                    })?;
+            }
+        }
+        config.trust = trust;
+        Ok(config)
+    }
+
+    /// Fetch from a custom root (e.g. an `AI_PROTOCOL_DIR` URL or a mirror). The URL is
+    /// used as-is, so any pin is dropped.
+    pub fn with_root(mut self, root: impl Into<String>) -> Self {
+        self.root = root.into().trim_end_matches('/').to_string();
+        self.pin = None;
+        self
+    }
+
+    /// Pin the git ref under the default GitHub root.
+    pub fn with_pin(mut self, pin: ProtocolPin) -> Self {
+        self.root = DEFAULT_REMOTE_ROOT.to_string();
+        self.pin = Some(pin);
+        self
+    }
+
+    /// Cache fetched manifests on disk under `dir`. The cache is off by default.
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Cache under `$XDG_CACHE_HOME/ai-lib/manifests` (or `~/.cache/...`,
+    /// `%LOCALAPPDATA%`); no cache when none of those is set.
+    pub fn with_default_cache_dir(mut self) -> Self {
+        self.cache_dir = default_cache_dir();
+        self
+    }
+
+    pub fn without_cache(mut self) -> Self {
+        self.cache_dir = None;
+        self
+    }
+
+    /// Age after which a cached entry is revalidated (ignored for commit pins).
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Serve only cached or bundled manifests; never touch the network.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Whether the build-time snapshot may be used as a last resort (default on).
+    pub fn with_bundled(mut self, enabled: bool) -> Self {
+        self.bundled = enabled;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_trust(mut self, trust: TrustPolicy) -> Self {
+        self.trust = trust;
+        self
+    }
+
+    pub fn trust(&self) -> &TrustPolicy {
+        &self.trust
+    }
+
+    /// Full URL of a manifest path relative to the protocol root.
+    pub fn url_for(&self, path: &str) -> String {
+        match &self.pin {
+            Some(pin) => format!("{}/{}/{}", self.root, pin.git_ref(), path),
+            None => format!("{}/{}", self.root, path),
+        }
+    }
+}
+
+/// On-disk cache metadata stored next to each body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+    fetched_at: u64,
+    sha256: String,
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+/// Fetches manifests according to a [`RemoteManifestConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct RemoteManifestStore {
+    config: RemoteManifestConfig,
+}
+
+impl RemoteManifestStore {
+    pub fn new(config: RemoteManifestConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &RemoteManifestConfig {
+        &self.config
+    }
+
+    /// Fetch `path` (relative to the protocol root) through cache, network and bundled
+    /// snapshot. Integrity failures are returned immediately rather than falling through.
+    pub async fn fetch(&self, path: &str) -> Result<FetchedManifest, ProtocolError> {
+        let url = self.config.url_for(path);
+        let cached = self.read_cache(path, &url)?;
+
+        if let Some((entry, body, provenance)) = &cached {
+            let fresh = self
+                .config
+                .pin
+                .as_ref()
+                .is_some_and(ProtocolPin::is_immutable)
+                || now_secs().saturating_sub(entry.fetched_at) < self.config.max_age.as_secs();
+            if fresh || self.config.offline {
+                return Ok(FetchedManifest {
+                    url,
+                    body: body.clone(),
+                    source: if fresh {
+                        FetchSource::Cache
+                    } else {
+                        FetchSource::StaleCache
+                    },
+                    provenance: provenance.clone(),
+                });
+            }
+        }
+
+        let network_error = if self.config.offline {
+            "offline mode and no cached copy".to_string()
+        } else {
+            match self
+                .download(path, &url, cached.as_ref().map(|c| &c.0))
+                .await
+            {
+                Ok(Some(fetched)) => return Ok(fetched),
+                Ok(None) => {
+                    // 304 Not Modified: the cached body is current.
+                    let (mut entry, body, provenance) =
+                        cached.expect("304 only follows a conditional request");
+                    entry.fetched_at = now_secs();
+                    self.write_meta(&url, &entry);
+                    return Ok(FetchedManifest {
+                        url,
+                        body,
+                        source: FetchSource::Revalidated,
+                        provenance,
+                    });
+                }
+                Err(e @ ProtocolError::IntegrityError { .. }) => return Err(e),
+                Err(e @ ProtocolError::NotFound { .. }) => return Err(e),
+                Err(e) => e.to_string(),
+            }
+        };
+
+        if let Some((_, body, provenance)) = cached {
+            warn!(url = %url, "serving stale cached manifest: {network_error}");
+            return Ok(FetchedManifest {
+                url,
+                body,
+                source: FetchSource::StaleCache,
+                provenance,
+            });
+        }
+        Err(ProtocolError::LoadError {
+            path: url,
+            reason: network_error,
+            hint: Some(
+                "Check connectivity, or populate the manifest cache / bundled snapshot for offline use."
+                    .to_string(),
+            ),
+        })
+    }
+
+    /// Build-time snapshot entry for `path`, if bundling is enabled and it was embedded.
+    pub fn bundled(&self, path: &str) -> Option<FetchedManifest> {
+        if !self.config.bundled {
+            return None;
+        }
+        bundled::BUNDLED_MANIFESTS
+            .iter()
+            .find(|(p, _)| *p == path)
+            .map(|(p, body)| FetchedManifest {
+                url: format!("bundled:{p}"),
+                body: body.as_bytes().to_vec(),
+                source: FetchSource::Bundled,
+                provenance: Provenance::Bundled,
+            })
+    }
+
+    /// GET (conditional when `cached` is set). `Ok(None)` means 304.
+    async fn download(
+        &self,
+        path: &str,
+        url: &str,
+        cached: Option<&CacheEntry>,
+    ) -> Result<Option<FetchedManifest>, ProtocolError> {
+        let client = reqwest::Client::builder()
+            .timeout(self.config.timeout)
+            .build()
+            .map_err(|e| ProtocolError::Internal(format!("Failed to create HTTP client: {}", e)))?;
+        let mut request = client.get(url);
+        if let Some(entry) = cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, modified);
+            }
+        }
+        let response = request.send().await.map_err(|e| ProtocolError::LoadError {
+            path: url.to_string(),
+            reason: format!("HTTP request failed: {}", e),
+            hint: None,
+        })?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED && cached.is_some() {
+            return Ok(None);
+        }
+        if status == StatusCode::NOT_FOUND {
+            return Err(ProtocolError::NotFound {
+                id: url.to_string(),
+                hint: None,
+            });
+        }
+        if !status.is_success() {
+            return Err(ProtocolError::LoadError {
+                path: url.to_string(),
+                reason: format!("HTTP {}", status),
+                hint: None,
+            });
+        }
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| ProtocolError::LoadError {
+                path: url.to_string(),
+                reason: format!("Failed to read bytes: {}", e),
+                hint: None,
+            })?
+            .to_vec();
+
+        let trust = &self.config.trust;
+        let signature = if trust.has_keys() && !trust.has_checksum(path) {
+            self.download_signature(&client, url).await?
+        } else {
+            None
+        };
+        let provenance = trust.verify(path, &body, signature.as_deref())?;
+        if provenance == Provenance::Unverified {
+            warn!(url = %url, "loaded manifest without signature or checksum verification");
+        }
+
+        let entry = CacheEntry {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            fetched_at: now_secs(),
+            sha256: sha256_hex(&body),
+            signature,
+        };
+        self.write_cache(url, &entry, &body);
+        Ok(Some(FetchedManifest {
+            url: url.to_string(),
+            body,
+            source: FetchSource::Network,
+            provenance,
+        }))
+    }
+
+    /// Detached signature at `<url>.sig`; `None` when not published.
+    async fn download_signature(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<Option<String>, ProtocolError> {
+        let sig_url = format!("{url}.sig");
+        let response = client
+            .get(&sig_url)
+            .send()
+            .await
+            .map_err(|e| ProtocolError::LoadError {
+                path: sig_url.clone(),
+                reason: format!("HTTP request failed: {}", e),
+                hint: None,
+            })?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(ProtocolError::LoadError {
+                path: sig_url,
+                reason: format!("HTTP {}", response.status()),
+                hint: None,
+            });
+        }
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ProtocolError::LoadError {
+                path: sig_url,
+                reason: format!("Failed to read signature: {}", e),
+                hint: None,
+            })?;
+        Ok(Some(text.trim().to_string()))
+    }
+
+    fn cache_paths(&self, url: &str) -> Option<(PathBuf, PathBuf)> {
+        let dir = self.config.cache_dir.as_ref()?;
+        let key = &sha256_hex(url.as_bytes())[..32];
+        Some((
+            dir.join(format!("{key}.body")),
+            dir.join(format!("{key}.meta.json")),
+        ))
+    }
+
+    /// Cached entry re-verified against the current trust policy. Corrupt or foreign
+    /// entries are ignored; entries failing verification are an error when offline.
+    fn read_cache(
+        &self,
+        path: &str,
+        url: &str,
+    ) -> Result<Option<(CacheEntry, Vec<u8>, Provenance)>, ProtocolError> {
+        let Some((body_path, meta_path)) = self.cache_paths(url) else {
+            return Ok(None);
+        };
+        let entry: Option<CacheEntry> = std::fs::read(&meta_path)
+            .ok()
+            .and_then(|m| serde_json::from_slice(&m).ok());
+        let (Some(entry), Ok(body)) = (entry, std::fs::read(&body_path)) else {
+            return Ok(None);
+        };
+        if entry.url != url || entry.sha256 != sha256_hex(&body) {
+            return Ok(None);
+        }
+        match self
+            .config
+            .trust
+            .verify(path, &body, entry.signature.as_deref())
+        {
+            Ok(provenance) => Ok(Some((entry, body, provenance))),
+            Err(e) if self.config.offline => Err(e),
+            Err(e) => {
+                warn!(url = %url, "discarding cached manifest: {e}");
+                Ok(None)
+            }
+        }
+    }
+
+    fn write_cache(&self, url: &str, entry: &CacheEntry, body: &[u8]) {
+        let Some((body_path, _)) = self.cache_paths(url) else {
+            return;
+        };
+        if let Err(e) = write_atomic(&body_path, body) {
+            warn!(path = %body_path.display(), "failed to write manifest cache: {e}");
+            return;
+        }
+        self.write_meta(url, entry);
+    }
+
+    fn write_meta(&self, url: &str, entry: &CacheEntry) {
+        let Some((_, meta_path)) = self.cache_paths(url) else {
+            return;
+        };
+        let result = serde_json::to_vec_pretty(entry)
+            .map_err(std::io::Error::other)
+            .and_then(|meta| write_atomic(&meta_path, &meta));
+        if let Err(e) = result {
+            warn!(path = %meta_path.display(), "failed to write manifest cache: {e}");
+        }
+    }
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// `$XDG_CACHE_HOME/ai-lib/manifests`, `~/.cache/ai-lib/manifests`, or `%LOCALAPPDATA%`.
+fn default_cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(base.join("ai-lib").join("manifests"))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(feature = "manifest_signatures")]
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "manifest_signatures")]
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const PATH: &str = "dist/v2/providers/acme.json";
+    const BODY: &[u8] = br#"{"id":"acme"}"#;
+
+    #[cfg(feature = "manifest_signatures")]
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[cfg(feature = "manifest_signatures")]
+    fn sign(key: &SigningKey, body: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(key.sign(body).to_bytes())
+    }
+
+    #[test]
+    fn test_pin_parse() {
+        assert_eq!(
+            ProtocolPin::parse("main"),
+            ProtocolPin::Branch("main".into())
+        );
+        assert_eq!(
+            ProtocolPin::parse("v0.8.0"),
+            ProtocolPin::Release("v0.8.0".into())
+        );
+        assert_eq!(
+            ProtocolPin::parse("commit:3f2a9c1D"),
+            ProtocolPin::Commit("3f2a9c1d".into())
+        );
+        let sha = "3F2A9C1D00112233445566778899AABBCCDDEEFF";
+        assert_eq!(
+            ProtocolPin::parse(sha),
+            ProtocolPin::Commit(sha.to_ascii_lowercase())
+        );
+        assert_eq!(
+            ProtocolPin::parse("deadbeef"),
+            ProtocolPin::Branch("deadbeef".into())
+        );
+        assert!(!ProtocolPin::parse(&sha[..39]).is_immutable());
+        let config = RemoteManifestConfig::new().with_pin(ProtocolPin::parse("v1.2.0"));
+        assert_eq!(
+            config.url_for(PATH),
+            format!("{DEFAULT_REMOTE_ROOT}/v1.2.0/{PATH}")
+        );
+        let mirror = RemoteManifestConfig::new().with_root("https://mirror.test/proto/");
+        assert_eq!(
+            mirror.url_for(PATH),
+            format!("https://mirror.test/proto/{PATH}")
+        );
+    }
+
+    #[test]
+    fn test_disk_cache_is_opt_in() {
+        assert_eq!(RemoteManifestConfig::new().cache_dir, None);
+        let config = RemoteManifestConfig::new().with_cache_dir("/tmp/manifests");
+        assert_eq!(config.cache_dir, Some(PathBuf::from("/tmp/manifests")));
+        assert_eq!(config.without_cache().cache_dir, None);
+    }
+
+    #[cfg(feature = "manifest_signatures")]
+    #[test]
+    fn test_signature_verification() {
+        let key = signing_key(7);
+        let hex_key = to_hex(key.verifying_key().as_bytes());
+        let trust = TrustPolicy::new().with_trusted_key(&hex_key).unwrap();
+
+        let ok = trust.verify(PATH, BODY, Some(&sign(&key, BODY))).unwrap();
+        assert_eq!(
+            ok,
+            Provenance::Signature {
+                key_id: hex_key[..16].to_string()
+            }
+        );
+
+        let forged = sign(&signing_key(8), BODY);
+        assert!(matches!(
+            trust.verify(PATH, BODY, Some(&forged)),
+            Err(ProtocolError::IntegrityError { .. })
+        ));
+        assert!(trust.verify(PATH, b"{}", Some(&sign(&key, BODY))).is_err());
+
+        // With trusted keys, a stripped signature is rejected.
+        let err = trust.verify(PATH, BODY, None).unwrap_err();
+        assert!(err.to_string().contains("no signature published"));
+        assert_eq!(
+            TrustPolicy::new().verify(PATH, BODY, None).unwrap(),
+            Provenance::Unverified
+        );
+        assert!(TrustPolicy::new()
+            .require_verified(true)
+            .verify(PATH, BODY, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_checksum_pin_overrides_signatures() {
+        let trust = TrustPolicy::new().with_checksum(PATH, &sha256_hex(BODY).to_uppercase());
+        assert_eq!(
+            trust.verify(PATH, BODY, None).unwrap(),
+            Provenance::Checksum
+        );
+        let err = trust.verify(PATH, b"tampered", None).unwrap_err();
+        assert!(err.to_string().contains("does not match pinned"));
+    }
+
+    #[cfg(not(feature = "manifest_signatures"))]
+    #[test]
+    fn test_trusted_keys_need_signature_feature() {
+        let err = TrustPolicy::new().with_trusted_key("abcd").unwrap_err();
+        assert!(err.to_string().contains("manifest_signatures"));
+        assert!(!TrustPolicy::new().has_keys());
+    }
+
+    #[cfg(feature = "manifest_signatures")]
+    #[test]
+    fn test_trusted_key_formats() {
+        let key = signing_key(3).verifying_key();
+        let b64 = base64::engine::general_purpose::STANDARD.encode(key.as_bytes());
+        assert!(TrustPolicy::new()
+            .with_trusted_key(&b64)
+            .unwrap()
+            .has_keys());
+        assert!(TrustPolicy::new().with_trusted_key("not a key").is_err());
+        assert!(TrustPolicy::new().with_trusted_key("abcd").is_err());
+    }
+
+    #[cfg(feature = "manifest_signatures")]
+    #[tokio::test]
+    async fn test_offline_serves_verified_cache_only() {
+        let dir = std::env::temp_dir().join(format!("ai-lib-remote-{}", std::process::id()));
+        let key = signing_key(9);
+        let trust = TrustPolicy::new().with_verifying_key(key.verifying_key());
+        let config = RemoteManifestConfig::new()
+            .with_root("http://127.0.0.1:9")
+            .with_cache_dir(&dir)
+            .with_trust(trust)
+            .offline(true);
+        let store = RemoteManifestStore::new(config.clone());
+        let url = config.url_for(PATH);
+
+        assert!(store.fetch(PATH).await.is_err());
+
+        let entry = CacheEntry {
+            url: url.clone(),
+            etag: Some("\"v1\"".into()),
+            last_modified: None,
+            fetched_at: 0,
+            sha256: sha256_hex(BODY),
+            signature: Some(sign(&key, BODY)),
+        };
+        store.write_cache(&url, &entry, BODY);
+        let fetched = store.fetch(PATH).await.unwrap();
+        assert_eq!(fetched.body, BODY);
+        assert_eq!(fetched.source, FetchSource::StaleCache);
+        assert!(matches!(fetched.provenance, Provenance::Signature { .. }));
+
+        // A cache entry whose signature no longer verifies is refused.
+        let other = RemoteManifestStore::new(
+            config
+                .with_trust(TrustPolicy::new().with_verifying_key(signing_key(1).verifying_key())),
+        );
+        assert!(matches!(
+            other.fetch(PATH).await,
+            Err(ProtocolError::IntegrityError { .. })
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -236,6 +236,9 @@ pub struct FeatureFlags {
     pub system_messages: bool,
     #[serde(default)]
     pub image_generation: bool,
+    /// The provider continues a trailing assistant message (used by stream recovery).
+    #[serde(default)]
+    pub assistant_prefill: bool,
     /// Additional provider-specific flags.
     #[serde(flatten)]
     pub extra: HashMap<String, bool>,
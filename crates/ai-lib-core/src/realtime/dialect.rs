@@ -0,0 +1,494 @@
+//! Wire formats of the supported realtime APIs.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use serde_json::{json, Value};
+
+use super::types::{RealtimeConfig, RealtimeEvent, TranscriptRole, TurnDetection};
+
+/// Which realtime protocol a session speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RealtimeDialect {
+    /// OpenAI Realtime (`session.update`, `input_audio_buffer.*`, `response.*`).
+    #[default]
+    OpenAi,
+    /// Gemini Live `BidiGenerateContent` (`setup`, `realtimeInput`, `serverContent`).
+    Gemini,
+}
+
+impl RealtimeDialect {
+    /// Messages sent right after connecting.
+    pub(crate) fn session_setup(&self, config: &RealtimeConfig) -> Value {
+        match self {
+            Self::OpenAi => {
+                let mut session = json!({
+                    "modalities": config.modalities,
+                    "input_audio_format": "pcm16",
+                    "output_audio_format": "pcm16",
+                    "turn_detection": match &config.turn_detection {
+                        TurnDetection::Manual => Value::Null,
+                        TurnDetection::ServerVad { threshold, prefix_padding_ms, silence_duration_ms } => {
+                            let mut vad = json!({ "type": "server_vad" });
+                            if let Some(v) = threshold { vad["threshold"] = json!(v); }
+                            if let Some(v) = prefix_padding_ms { vad["prefix_padding_ms"] = json!(v); }
+                            if let Some(v) = silence_duration_ms { vad["silence_duration_ms"] = json!(v); }
+                            vad
+                        }
+                    },
+                });
+                if let Some(instructions) = &config.instructions {
+                    session["instructions"] = json!(instructions);
+                }
+                if let Some(voice) = &config.voice {
+                    session["voice"] = json!(voice);
+                }
+                if let Some(temperature) = config.temperature {
+                    session["temperature"] = json!(temperature);
+                }
+                if config.transcribe_input {
+                    session["input_audio_transcription"] = json!({ "model": "whisper-1" });
+                }
+                if !config.tools.is_empty() {
+                    session["tools"] = config
+                        .tools
+                        .iter()
+                        .map(|t| {
+                            json!({
+                                "type": "function",
+                                "name": t.function.name,
+                                "description": t.function.description,
+                                "parameters": t.function.parameters,
+                            })
+                        })
+                        .collect();
+                }
+                json!({ "type": "session.update", "session": session })
+            }
+            Self::Gemini => {
+                let modalities: Vec<String> = config
+                    .modalities
+                    .iter()
+                    .map(|m| m.to_uppercase())
+                    .filter(|m| m != "TEXT" || config.modalities.len() == 1)
+                    .collect();
+                let mut generation = json!({ "responseModalities": modalities });
+                if let Some(temperature) = config.temperature {
+                    generation["temperature"] = json!(temperature);
+                }
+                if let Some(voice) = &config.voice {
+                    generation["speechConfig"] =
+                        json!({ "voiceConfig": { "prebuiltVoiceConfig": { "voiceName": voice } } });
+                }
+                let model = match config.model.starts_with("models/") {
+                    true => config.model.clone(),
+                    false => format!("models/{}", config.model),
+                };
+                let mut setup = json!({
+                    "model": model,
+                    "generationConfig": generation,
+                    "realtimeInputConfig": {
+                        "automaticActivityDetection": {
+                            "disabled": config.turn_detection == TurnDetection::Manual
+                        }
+                    },
+                    "outputAudioTranscription": {},
+                });
+                if let Some(instructions) = &config.instructions {
+                    setup["systemInstruction"] = json!({ "parts": [{ "text": instructions }] });
+                }
+                if config.transcribe_input {
+                    setup["inputAudioTranscription"] = json!({});
+                }
+                if !config.tools.is_empty() {
+                    let declarations: Vec<Value> = config
+                        .tools
+                        .iter()
+                        .map(|t| {
+                            json!({
+                                "name": t.function.name,
+                                "description": t.function.description,
+                                "parameters": t.function.parameters,
+                            })
+                        })
+                        .collect();
+                    setup["tools"] = json!([{ "functionDeclarations": declarations }]);
+                }
+                json!({ "setup": setup })
+            }
+        }
+    }
+
+    /// Append 16-bit mono PCM at `sample_rate` to the input stream.
+    pub(crate) fn audio(&self, pcm: &[u8], sample_rate: u32) -> Value {
+        let data = BASE64.encode(pcm);
+        match self {
+            Self::OpenAi => json!({ "type": "input_audio_buffer.append", "audio": data }),
+            Self::Gemini => json!({
+                "realtimeInput": {
+                    "audio": { "data": data, "mimeType": format!("audio/pcm;rate={sample_rate}") }
+                }
+            }),
+        }
+    }
+
+    /// End the current user turn of streamed audio.
+    pub(crate) fn commit_audio(&self) -> Vec<Value> {
+        match self {
+            Self::OpenAi => vec![json!({ "type": "input_audio_buffer.commit" })],
+            Self::Gemini => vec![json!({ "realtimeInput": { "audioStreamEnd": true } })],
+        }
+    }
+
+    /// A complete user text turn, followed by a response request where needed.
+    pub(crate) fn text(&self, text: &str) -> Vec<Value> {
+        match self {
+            Self::OpenAi => vec![
+                json!({
+                    "type": "conversation.item.create",
+                    "item": {
+                        "type": "message",
+                        "role": "user",
+                        "content": [{ "type": "input_text", "text": text }]
+                    }
+                }),
+                json!({ "type": "response.create" }),
+            ],
+            Self::Gemini => vec![json!({
+                "clientContent": {
+                    "turns": [{ "role": "user", "parts": [{ "text": text }] }],
+                    "turnComplete": true
+                }
+            })],
+        }
+    }
+
+    /// Ask the model to respond now (manual turn detection). Gemini responds to
+    /// completed turns on its own, so nothing is sent.
+    pub(crate) fn create_response(&self) -> Vec<Value> {
+        match self {
+            Self::OpenAi => vec![json!({ "type": "response.create" })],
+            Self::Gemini => Vec::new(),
+        }
+    }
+
+    /// Cancel the in-progress response.
+    pub(crate) fn cancel_response(&self) -> Vec<Value> {
+        match self {
+            Self::OpenAi => vec![json!({ "type": "response.cancel" })],
+            Self::Gemini => Vec::new(),
+        }
+    }
+
+    /// Return a function result and let the model continue.
+    pub(crate) fn tool_result(&self, call_id: &str, name: &str, output: &Value) -> Vec<Value> {
+        match self {
+            Self::OpenAi => vec![
+                json!({
+                    "type": "conversation.item.create",
+                    "item": {
+                        "type": "function_call_output",
+                        "call_id": call_id,
+                        "output": match output {
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        }
+                    }
+                }),
+                json!({ "type": "response.create" }),
+            ],
+            Self::Gemini => vec![json!({
+                "toolResponse": {
+                    "functionResponses": [{
+                        "id": call_id,
+                        "name": name,
+                        "response": match output {
+                            Value::Object(_) => output.clone(),
+                            other => json!({ "output": other }),
+                        }
+                    }]
+                }
+            })],
+        }
+    }
+
+    /// Normalize one server message into zero or more events.
+    pub(crate) fn parse(&self, message: Value) -> Vec<RealtimeEvent> {
+        match self {
+            Self::OpenAi => vec![parse_openai(message)],
+            Self::Gemini => parse_gemini(message),
+        }
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn decode_audio(data: &str) -> RealtimeEvent {
+    match BASE64.decode(data) {
+        Ok(audio) => RealtimeEvent::AudioDelta { audio },
+        Err(e) => RealtimeEvent::Error {
+            code: Some("invalid_audio".into()),
+            message: format!("server sent invalid base64 audio: {e}"),
+        },
+    }
+}
+
+fn parse_openai(message: Value) -> RealtimeEvent {
+    let kind = message
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    match kind {
+        "session.created" | "session.updated" => RealtimeEvent::SessionReady {
+            session: message.get("session").cloned().unwrap_or(Value::Null),
+        },
+        "response.text.delta" | "response.output_text.delta" => RealtimeEvent::TextDelta {
+            text: str_field(&message, "delta"),
+        },
+        "response.audio.delta" | "response.output_audio.delta" => {
+            decode_audio(&str_field(&message, "delta"))
+        }
+        "response.audio.done" | "response.output_audio.done" => RealtimeEvent::AudioDone,
+        "response.audio_transcript.delta" | "response.output_audio_transcript.delta" => {
+            RealtimeEvent::TranscriptDelta {
+                role: TranscriptRole::Assistant,
+                text: str_field(&message, "delta"),
+            }
+        }
+        "response.audio_transcript.done" | "response.output_audio_transcript.done" => {
+            RealtimeEvent::TranscriptDone {
+                role: TranscriptRole::Assistant,
+                text: str_field(&message, "transcript"),
+            }
+        }
+        "conversation.item.input_audio_transcription.delta" => RealtimeEvent::TranscriptDelta {
+            role: TranscriptRole::User,
+            text: str_field(&message, "delta"),
+        },
+        "conversation.item.input_audio_transcription.completed" => RealtimeEvent::TranscriptDone {
+            role: TranscriptRole::User,
+            text: str_field(&message, "transcript"),
+        },
+        "input_audio_buffer.speech_started" => RealtimeEvent::SpeechStarted,
+        "input_audio_buffer.speech_stopped" => RealtimeEvent::SpeechStopped,
+        "input_audio_buffer.committed" => RealtimeEvent::InputCommitted,
+        "response.function_call_arguments.done" => {
+            let raw = str_field(&message, "arguments");
+            RealtimeEvent::ToolCall {
+                call_id: str_field(&message, "call_id"),
+                name: str_field(&message, "name"),
+                arguments: serde_json::from_str(&raw).unwrap_or(Value::String(raw)),
+            }
+        }
+        "response.done" => RealtimeEvent::ResponseDone {
+            usage: message
+                .get("response")
+                .and_then(|r| r.get("usage"))
+                .filter(|u| !u.is_null())
+                .cloned(),
+        },
+        "error" => {
+            let error = message.get("error").unwrap_or(&message);
+            RealtimeEvent::Error {
+                code: error.get("code").and_then(Value::as_str).map(String::from),
+                message: str_field(error, "message"),
+            }
+        }
+        _ => RealtimeEvent::Other(message),
+    }
+}
+
+fn parse_gemini(message: Value) -> Vec<RealtimeEvent> {
+    let mut events = Vec::new();
+    if let Some(setup) = message.get("setupComplete") {
+        events.push(RealtimeEvent::SessionReady {
+            session: setup.clone(),
+        });
+    }
+    if let Some(content) = message.get("serverContent") {
+        if let Some(text) = content
+            .pointer("/inputTranscription/text")
+            .and_then(Value::as_str)
+        {
+            events.push(RealtimeEvent::TranscriptDelta {
+                role: TranscriptRole::User,
+                text: text.to_string(),
+            });
+        }
+        let parts = content
+            .pointer("/modelTurn/parts")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for part in parts {
+            if let Some(text) = part.get("text").and_then(Value::as_str) {
+                events.push(RealtimeEvent::TextDelta {
+                    text: text.to_string(),
+                });
+            }
+            if let Some(data) = part.pointer("/inlineData/data").and_then(Value::as_str) {
+                events.push(decode_audio(data));
+            }
+        }
+        if let Some(text) = content
+            .pointer("/outputTranscription/text")
+            .and_then(Value::as_str)
+        {
+            events.push(RealtimeEvent::TranscriptDelta {
+                role: TranscriptRole::Assistant,
+                text: text.to_string(),
+            });
+        }
+        if content.get("interrupted").and_then(Value::as_bool) == Some(true) {
+            events.push(RealtimeEvent::Interrupted);
+        }
+        if content.get("generationComplete").and_then(Value::as_bool) == Some(true) {
+            events.push(RealtimeEvent::AudioDone);
+        }
+        if content.get("turnComplete").and_then(Value::as_bool) == Some(true) {
+            events.push(RealtimeEvent::ResponseDone {
+                usage: message.get("usageMetadata").cloned(),
+            });
+        }
+    }
+    let calls = message
+        .pointer("/toolCall/functionCalls")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for call in calls {
+        events.push(RealtimeEvent::ToolCall {
+            call_id: str_field(call, "id"),
+            name: str_field(call, "name"),
+            arguments: call.get("args").cloned().unwrap_or_else(|| json!({})),
+        });
+    }
+    if events.is_empty() {
+        events.push(RealtimeEvent::Other(message));
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::tool::{FunctionDefinition, ToolDefinition};
+
+    fn config() -> RealtimeConfig {
+        RealtimeConfig::new("live-model")
+            .with_instructions("Be brief.")
+            .with_voice("alloy")
+            .with_tools(vec![ToolDefinition {
+                tool_type: "function".into(),
+                function: FunctionDefinition {
+                    name: "get_time".into(),
+                    description: Some("Current time".into()),
+                    parameters: Some(json!({ "type": "object" })),
+                },
+            }])
+    }
+
+    #[test]
+    fn test_openai_setup_and_tool_result() {
+        let setup = RealtimeDialect::OpenAi.session_setup(&config());
+        assert_eq!(setup["type"], "session.update");
+        assert_eq!(setup["session"]["turn_detection"]["type"], "server_vad");
+        assert_eq!(setup["session"]["tools"][0]["name"], "get_time");
+        let manual = RealtimeDialect::OpenAi
+            .session_setup(&config().with_turn_detection(TurnDetection::Manual));
+        assert!(manual["session"]["turn_detection"].is_null());
+
+        let result = RealtimeDialect::OpenAi.tool_result("c1", "get_time", &json!({ "t": 1 }));
+        assert_eq!(result[0]["item"]["output"], "{\"t\":1}");
+        assert_eq!(result[1]["type"], "response.create");
+    }
+
+    #[test]
+    fn test_openai_event_mapping() {
+        let parse = |v: Value| RealtimeDialect::OpenAi.parse(v).remove(0);
+        assert_eq!(
+            parse(json!({ "type": "response.audio.delta", "delta": "AQI=" })),
+            RealtimeEvent::AudioDelta { audio: vec![1, 2] }
+        );
+        assert_eq!(
+            parse(json!({
+                "type": "response.function_call_arguments.done",
+                "call_id": "c1", "name": "get_time", "arguments": "{\"tz\":\"UTC\"}"
+            })),
+            RealtimeEvent::ToolCall {
+                call_id: "c1".into(),
+                name: "get_time".into(),
+                arguments: json!({ "tz": "UTC" })
+            }
+        );
+        assert_eq!(
+            parse(
+                json!({ "type": "conversation.item.input_audio_transcription.completed", "transcript": "hi" })
+            ),
+            RealtimeEvent::TranscriptDone {
+                role: TranscriptRole::User,
+                text: "hi".into()
+            }
+        );
+        assert_eq!(
+            parse(json!({ "type": "error", "error": { "code": "bad", "message": "nope" } })),
+            RealtimeEvent::Error {
+                code: Some("bad".into()),
+                message: "nope".into()
+            }
+        );
+        assert!(matches!(
+            parse(json!({ "type": "rate_limits.updated" })),
+            RealtimeEvent::Other(_)
+        ));
+    }
+
+    #[test]
+    fn test_gemini_setup_and_event_mapping() {
+        let setup = RealtimeDialect::Gemini.session_setup(&config());
+        assert_eq!(setup["setup"]["model"], "models/live-model");
+        assert_eq!(
+            setup["setup"]["generationConfig"]["responseModalities"],
+            json!(["AUDIO"])
+        );
+        assert_eq!(
+            setup["setup"]["tools"][0]["functionDeclarations"][0]["name"],
+            "get_time"
+        );
+        assert_eq!(
+            RealtimeDialect::Gemini.audio(&[1, 2], 16_000)["realtimeInput"]["audio"]["mimeType"],
+            "audio/pcm;rate=16000"
+        );
+
+        let events = RealtimeDialect::Gemini.parse(json!({
+            "serverContent": {
+                "modelTurn": { "parts": [{ "inlineData": { "mimeType": "audio/pcm", "data": "AQI=" } }] },
+                "outputTranscription": { "text": "Hel" },
+                "turnComplete": true
+            },
+            "usageMetadata": { "totalTokenCount": 9 }
+        }));
+        assert_eq!(
+            events,
+            [
+                RealtimeEvent::AudioDelta { audio: vec![1, 2] },
+                RealtimeEvent::TranscriptDelta {
+                    role: TranscriptRole::Assistant,
+                    text: "Hel".into()
+                },
+                RealtimeEvent::ResponseDone {
+                    usage: Some(json!({ "totalTokenCount": 9 }))
+                },
+            ]
+        );
+        let calls = RealtimeDialect::Gemini.parse(json!({
+            "toolCall": { "functionCalls": [{ "id": "f1", "name": "get_time", "args": { "tz": "UTC" } }] }
+        }));
+        assert!(matches!(&calls[0], RealtimeEvent::ToolCall { call_id, .. } if call_id == "f1"));
+    }
+}
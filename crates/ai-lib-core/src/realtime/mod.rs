@@ -0,0 +1,44 @@
+//! Realtime（实时语音）模块：通过 WebSocket 与 OpenAI Realtime / Gemini Live 建立双向会话，流式发送音频与文本并接收统一事件。
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`RealtimeSession`] | Connected session; send audio/text/tool results and read events |
+//! | [`RealtimeSessionBuilder`] | URL, key, [`RealtimeDialect`] and [`RealtimeConfig`], or all of them from a V2 manifest |
+//! | [`RealtimeSender`] / [`RealtimeEvents`] | The two halves of a split session, for full-duplex use across tasks |
+//! | [`RealtimeEvent`] | Provider events normalized: text/audio deltas, transcripts, VAD, tool calls, errors |
+//!
+//! ```rust,no_run
+//! use ai_lib_core::realtime::{RealtimeConfig, RealtimeEvent, RealtimeSession};
+//! use futures::StreamExt;
+//!
+//! # async fn demo(microphone: Vec<Vec<u8>>) -> ai_lib_core::Result<()> {
+//! let session = RealtimeSession::builder()
+//!     .url("wss://api.openai.com/v1/realtime?model=gpt-4o-realtime-preview")
+//!     .api_key(std::env::var("OPENAI_API_KEY").unwrap_or_default())
+//!     .config(RealtimeConfig::new("gpt-4o-realtime-preview").with_voice("alloy"))
+//!     .connect()
+//!     .await?;
+//! let (sender, mut events) = session.split();
+//! tokio::spawn(async move {
+//!     for frame in microphone {
+//!         sender.send_audio(&frame).await?; // 16-bit mono PCM, 24 kHz
+//!     }
+//!     ai_lib_core::Result::Ok(())
+//! });
+//! while let Some(event) = events.next().await {
+//!     match event? {
+//!         RealtimeEvent::AudioDelta { audio } => { /* play */ let _ = audio; }
+//!         RealtimeEvent::ResponseDone { .. } => break,
+//!         _ => {}
+//!     }
+//! }
+//! # Ok(()) }
+//! ```
+
+mod dialect;
+mod session;
+mod types;
+
+pub use dialect::RealtimeDialect;
+pub use session::{RealtimeEvents, RealtimeSender, RealtimeSession, RealtimeSessionBuilder};
+pub use types::{RealtimeConfig, RealtimeEvent, TranscriptRole, TurnDetection};
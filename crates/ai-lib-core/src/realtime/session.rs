@@ -0,0 +1,486 @@
+//! WebSocket transport for realtime sessions.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, Stream, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use super::dialect::RealtimeDialect;
+use super::types::{RealtimeConfig, RealtimeEvent};
+use crate::credentials::resolve_credential_v2;
+use crate::protocol::v2::manifest::ApiStyle;
+use crate::protocol::v2::ManifestV2;
+use crate::{Error, ErrorContext, Result};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+fn realtime_error(message: String) -> Error {
+    Error::network_with_context(message, ErrorContext::new().with_source("realtime"))
+}
+
+/// A live bidirectional session: stream audio or text in, read [`RealtimeEvent`]s out.
+///
+/// To send while reading events from another task, [`split`](Self::split) the session.
+pub struct RealtimeSession {
+    sender: RealtimeSender,
+    events: RealtimeEvents,
+}
+
+impl RealtimeSession {
+    pub fn builder() -> RealtimeSessionBuilder {
+        RealtimeSessionBuilder::new()
+    }
+
+    pub fn sender(&self) -> &RealtimeSender {
+        &self.sender
+    }
+
+    /// Next server event; `None` once the server closes the socket.
+    pub async fn next_event(&mut self) -> Option<Result<RealtimeEvent>> {
+        self.events.next().await
+    }
+
+    pub async fn send_audio(&self, pcm: &[u8]) -> Result<()> {
+        self.sender.send_audio(pcm).await
+    }
+
+    pub async fn commit_audio(&self) -> Result<()> {
+        self.sender.commit_audio().await
+    }
+
+    pub async fn send_text(&self, text: &str) -> Result<()> {
+        self.sender.send_text(text).await
+    }
+
+    pub async fn create_response(&self) -> Result<()> {
+        self.sender.create_response().await
+    }
+
+    pub async fn cancel_response(&self) -> Result<()> {
+        self.sender.cancel_response().await
+    }
+
+    pub async fn send_tool_result(&self, call_id: &str, name: &str, output: Value) -> Result<()> {
+        self.sender.send_tool_result(call_id, name, output).await
+    }
+
+    pub async fn close(self) -> Result<()> {
+        self.sender.close().await
+    }
+
+    /// Separate the sending half (cloneable) from the event stream.
+    pub fn split(self) -> (RealtimeSender, RealtimeEvents) {
+        (self.sender, self.events)
+    }
+}
+
+/// Sending half of a [`RealtimeSession`]; clones share the socket.
+#[derive(Clone)]
+pub struct RealtimeSender {
+    sink: Arc<Mutex<SplitSink<Socket, Message>>>,
+    dialect: RealtimeDialect,
+    sample_rate: u32,
+}
+
+impl RealtimeSender {
+    async fn send_all(&self, messages: Vec<Value>) -> Result<()> {
+        let mut sink = self.sink.lock().await;
+        for message in messages {
+            sink.send(Message::Text(message.to_string()))
+                .await
+                .map_err(|e| realtime_error(format!("realtime send failed: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Stream 16-bit mono PCM at the configured input sample rate.
+    pub async fn send_audio(&self, pcm: &[u8]) -> Result<()> {
+        self.send_all(vec![self.dialect.audio(pcm, self.sample_rate)])
+            .await
+    }
+
+    /// End the user's audio turn (needed with [`TurnDetection::Manual`](super::TurnDetection::Manual)).
+    pub async fn commit_audio(&self) -> Result<()> {
+        self.send_all(self.dialect.commit_audio()).await
+    }
+
+    /// Send a complete user text turn; the model responds to it.
+    pub async fn send_text(&self, text: &str) -> Result<()> {
+        self.send_all(self.dialect.text(text)).await
+    }
+
+    /// Ask for a response to the committed input.
+    pub async fn create_response(&self) -> Result<()> {
+        self.send_all(self.dialect.create_response()).await
+    }
+
+    pub async fn cancel_response(&self) -> Result<()> {
+        self.send_all(self.dialect.cancel_response()).await
+    }
+
+    /// Answer a [`RealtimeEvent::ToolCall`]; the model continues with the result.
+    pub async fn send_tool_result(&self, call_id: &str, name: &str, output: Value) -> Result<()> {
+        self.send_all(self.dialect.tool_result(call_id, name, &output))
+            .await
+    }
+
+    pub async fn close(&self) -> Result<()> {
+        self.sink
+            .lock()
+            .await
+            .close()
+            .await
+            .map_err(|e| realtime_error(format!("realtime close failed: {}", e)))
+    }
+}
+
+/// Receiving half of a [`RealtimeSession`].
+pub struct RealtimeEvents {
+    stream: SplitStream<Socket>,
+    dialect: RealtimeDialect,
+    pending: VecDeque<RealtimeEvent>,
+}
+
+impl Stream for RealtimeEvents {
+    type Item = Result<RealtimeEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            let payload = match futures::ready!(self.stream.poll_next_unpin(cx)) {
+                None | Some(Ok(Message::Close(_))) => return Poll::Ready(None),
+                Some(Err(e)) => {
+                    return Poll::Ready(Some(Err(realtime_error(format!(
+                        "realtime connection failed: {}",
+                        e
+                    )))))
+                }
+                Some(Ok(Message::Text(text))) => text.into_bytes(),
+                // Gemini Live sends its JSON messages as binary frames.
+                Some(Ok(Message::Binary(bytes))) => bytes,
+                Some(Ok(_)) => continue,
+            };
+            let message: Value = match serde_json::from_slice(&payload) {
+                Ok(message) => message,
+                Err(e) => {
+                    return Poll::Ready(Some(Err(Error::api_with_context(
+                        format!("realtime server sent invalid JSON: {}", e),
+                        ErrorContext::new().with_source("realtime"),
+                    ))))
+                }
+            };
+            let events = self.dialect.parse(message);
+            self.pending.extend(events);
+        }
+    }
+}
+
+pub struct RealtimeSessionBuilder {
+    url: Option<String>,
+    api_key: Option<String>,
+    dialect: RealtimeDialect,
+    config: Option<RealtimeConfig>,
+    headers: Vec<(String, String)>,
+}
+
+impl RealtimeSessionBuilder {
+    pub fn new() -> Self {
+        Self {
+            url: None,
+            api_key: None,
+            dialect: RealtimeDialect::default(),
+            config: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// `ws://` or `wss://` endpoint, including any query the provider expects.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Sent as `Authorization: Bearer` (OpenAI) or the `key` query parameter (Gemini).
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn dialect(mut self, dialect: RealtimeDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn config(mut self, config: RealtimeConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Configure from a V2 manifest that declares `multimodal.omni_mode` realtime
+    /// voice chat. The URL comes from `services.realtime` when present, otherwise
+    /// the provider's standard realtime endpoint under `endpoint.base_url`; the API
+    /// key goes through the usual credential chain ([ARCH-001]).
+    pub fn from_manifest(mut self, manifest: &ManifestV2, model: &str) -> Result<Self> {
+        let omni = manifest
+            .multimodal
+            .as_ref()
+            .and_then(|m| m.omni_mode.as_ref());
+        if !omni.is_some_and(|o| o.supported && o.real_time_voice_chat) {
+            return Err(Error::validation_with_context(
+                format!(
+                    "provider '{}' does not declare realtime voice chat",
+                    manifest.id
+                ),
+                ErrorContext::new()
+                    .with_source("realtime")
+                    .with_field_path("multimodal.omni_mode.real_time_voice_chat"),
+            ));
+        }
+        let cred = resolve_credential_v2(manifest, self.api_key.as_deref());
+        let secret = cred.secret().ok_or_else(|| {
+            Error::configuration(format!(
+                "API key required for realtime (provider={}; tried {:?})",
+                manifest.id,
+                cred.required_envs
+                    .iter()
+                    .chain(cred.conventional_envs.iter())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            ))
+        })?;
+        self.api_key = Some(secret.to_string());
+        let base = manifest.base_url().trim_end_matches('/');
+        let ws_base = match base.split_once("://") {
+            Some(("http", rest)) => format!("ws://{rest}"),
+            Some((_, rest)) => format!("wss://{rest}"),
+            None => format!("wss://{base}"),
+        };
+        self.dialect = match manifest.detect_api_style() {
+            ApiStyle::GeminiGenerate => RealtimeDialect::Gemini,
+            _ => RealtimeDialect::OpenAi,
+        };
+        let service = manifest
+            .services
+            .as_ref()
+            .and_then(|s| s.get("realtime"))
+            .map(|s| s.path.as_str());
+        self.url = Some(match (service, self.dialect) {
+            (Some(path), _) if path.contains("://") => path.to_string(),
+            (Some(path), _) => format!("{ws_base}/{}", path.trim_start_matches('/')),
+            (None, RealtimeDialect::Gemini) => {
+                let host = ws_base.splitn(4, '/').take(3).collect::<Vec<_>>().join("/");
+                format!(
+                    "{host}/ws/google.ai.generativelanguage.v1beta.GenerativeService.BidiGenerateContent"
+                )
+            }
+            (None, RealtimeDialect::OpenAi) => {
+                format!("{ws_base}/realtime?model={}", query_value(model))
+            }
+        });
+        let config = self
+            .config
+            .take()
+            .unwrap_or_else(|| RealtimeConfig::new(model));
+        self.config = Some(RealtimeConfig {
+            model: model.to_string(),
+            ..config
+        });
+        Ok(self)
+    }
+
+    /// Open the socket and send the session configuration.
+    pub async fn connect(self) -> Result<RealtimeSession> {
+        let config = self.config.ok_or_else(|| {
+            Error::configuration(
+                "realtime session config required: use config() or from_manifest()",
+            )
+        })?;
+        let mut url = self.url.ok_or_else(|| {
+            Error::configuration("realtime url required: use url() or from_manifest()")
+        })?;
+        if let (RealtimeDialect::Gemini, Some(key)) = (self.dialect, &self.api_key) {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            url = format!("{url}{separator}key={}", query_value(key));
+        }
+        let mut request = url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| Error::configuration(format!("invalid realtime url: {}", e)))?;
+        let mut headers = self.headers;
+        if self.dialect == RealtimeDialect::OpenAi {
+            if let Some(key) = &self.api_key {
+                headers.push(("Authorization".into(), format!("Bearer {key}")));
+            }
+            headers.push(("OpenAI-Beta".into(), "realtime=v1".into()));
+        }
+        for (name, value) in headers {
+            let name =
+                tokio_tungstenite::tungstenite::http::HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|e| Error::configuration(format!("invalid header name: {}", e)))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|e| Error::configuration(format!("invalid header value: {}", e)))?;
+            request.headers_mut().insert(name, value);
+        }
+
+        let (socket, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| realtime_error(format!("realtime connect failed: {}", e)))?;
+        let (sink, stream) = socket.split();
+        let sender = RealtimeSender {
+            sink: Arc::new(Mutex::new(sink)),
+            dialect: self.dialect,
+            sample_rate: config.input_sample_rate,
+        };
+        sender
+            .send_all(vec![self.dialect.session_setup(&config)])
+            .await?;
+        Ok(RealtimeSession {
+            sender,
+            events: RealtimeEvents {
+                stream,
+                dialect: self.dialect,
+                pending: VecDeque::new(),
+            },
+        })
+    }
+}
+
+fn query_value(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+impl Default for RealtimeSessionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(yaml: &str) -> ManifestV2 {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_from_manifest_derives_url_and_dialect() {
+        let openai = manifest(
+            r#"
+id: openai
+protocol_version: "2.0"
+endpoint:
+  base_url: https://api.openai.com/v1
+capabilities:
+  required: [text]
+multimodal:
+  omni_mode: { supported: true, real_time_voice_chat: true }
+"#,
+        );
+        let builder = RealtimeSession::builder()
+            .api_key("sk-test")
+            .from_manifest(&openai, "rt-model")
+            .unwrap();
+        assert_eq!(builder.dialect, RealtimeDialect::OpenAi);
+        assert_eq!(
+            builder.url.as_deref(),
+            Some("wss://api.openai.com/v1/realtime?model=rt-model")
+        );
+        assert_eq!(builder.config.unwrap().model, "rt-model");
+
+        let gemini = manifest(
+            r#"
+id: google
+protocol_version: "2.0"
+endpoint:
+  base_url: https://generativelanguage.googleapis.com/v1beta
+  chat: "/models/{model}:generateContent"
+capabilities:
+  required: [text]
+multimodal:
+  omni_mode: { supported: true, real_time_voice_chat: true }
+"#,
+        );
+        let builder = RealtimeSession::builder()
+            .api_key("g-key")
+            .from_manifest(&gemini, "live-model")
+            .unwrap();
+        assert_eq!(builder.dialect, RealtimeDialect::Gemini);
+        assert_eq!(
+            builder.url.as_deref(),
+            Some("wss://generativelanguage.googleapis.com/ws/google.ai.generativelanguage.v1beta.GenerativeService.BidiGenerateContent")
+        );
+    }
+
+    #[test]
+    fn test_from_manifest_resolves_key_and_encodes_query() {
+        let m = manifest(
+            r#"
+id: realtime-cred-test
+protocol_version: "2.0"
+endpoint:
+  base_url: https://rt.example.com/v1
+  auth: { type: bearer, token_env: AI_LIB_REALTIME_CRED_TEST_KEY }
+capabilities:
+  required: [text]
+multimodal:
+  omni_mode: { supported: true, real_time_voice_chat: true }
+"#,
+        );
+        let err = RealtimeSession::builder()
+            .from_manifest(&m, "m")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("AI_LIB_REALTIME_CRED_TEST_KEY"));
+
+        std::env::set_var("AI_LIB_REALTIME_CRED_TEST_KEY", "env-key");
+        let builder = RealtimeSession::builder()
+            .from_manifest(&m, "gpt rt&beta=1")
+            .unwrap();
+        std::env::remove_var("AI_LIB_REALTIME_CRED_TEST_KEY");
+        assert_eq!(builder.api_key.as_deref(), Some("env-key"));
+        assert_eq!(
+            builder.url.as_deref(),
+            Some("wss://rt.example.com/v1/realtime?model=gpt+rt%26beta%3D1")
+        );
+        assert_eq!(query_value("a+b/c=="), "a%2Bb%2Fc%3D%3D");
+    }
+
+    #[test]
+    fn test_from_manifest_requires_realtime_voice() {
+        let plain = manifest(
+            r#"
+id: plain
+protocol_version: "2.0"
+endpoint:
+  base_url: https://example.com/v1
+capabilities:
+  required: [text]
+"#,
+        );
+        let err = RealtimeSession::builder()
+            .from_manifest(&plain, "m")
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("does not declare realtime voice chat"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Realtime session configuration and events.
+
+use serde_json::Value;
+
+use crate::types::tool::ToolDefinition;
+
+/// Voice-activity detection for a realtime session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TurnDetection {
+    /// The server detects speech start/end and replies automatically.
+    ServerVad {
+        /// Activation threshold in 0.0–1.0 (provider default when `None`).
+        threshold: Option<f32>,
+        /// Audio kept before detected speech.
+        prefix_padding_ms: Option<u32>,
+        /// Silence that ends a turn.
+        silence_duration_ms: Option<u32>,
+    },
+    /// The client ends turns itself with `commit_audio` / `create_response`.
+    Manual,
+}
+
+impl Default for TurnDetection {
+    fn default() -> Self {
+        Self::ServerVad {
+            threshold: None,
+            prefix_padding_ms: None,
+            silence_duration_ms: None,
+        }
+    }
+}
+
+/// Session settings sent when the socket opens.
+#[derive(Debug, Clone)]
+pub struct RealtimeConfig {
+    pub model: String,
+    pub instructions: Option<String>,
+    pub voice: Option<String>,
+    /// `"text"` and/or `"audio"`.
+    pub modalities: Vec<String>,
+    /// Sample rate of the 16-bit mono PCM sent with `send_audio`.
+    pub input_sample_rate: u32,
+    pub turn_detection: TurnDetection,
+    pub tools: Vec<ToolDefinition>,
+    pub temperature: Option<f32>,
+    /// Also emit transcripts of the user's speech.
+    pub transcribe_input: bool,
+}
+
+impl RealtimeConfig {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            instructions: None,
+            voice: None,
+            modalities: vec!["text".into(), "audio".into()],
+            input_sample_rate: 24_000,
+            turn_detection: TurnDetection::default(),
+            tools: Vec::new(),
+            temperature: None,
+            transcribe_input: true,
+        }
+    }
+
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    pub fn with_voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+
+    pub fn with_modalities<I, S>(mut self, modalities: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.modalities = modalities.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_input_sample_rate(mut self, hz: u32) -> Self {
+        self.input_sample_rate = hz;
+        self
+    }
+
+    pub fn with_turn_detection(mut self, turn_detection: TurnDetection) -> Self {
+        self.turn_detection = turn_detection;
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_input_transcription(mut self, enabled: bool) -> Self {
+        self.transcribe_input = enabled;
+        self
+    }
+}
+
+/// Whose speech a transcript belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptRole {
+    User,
+    Assistant,
+}
+
+/// A server event, normalized across providers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RealtimeEvent {
+    /// The session was created or its configuration accepted.
+    SessionReady {
+        session: Value,
+    },
+    /// Model text output.
+    TextDelta {
+        text: String,
+    },
+    /// Model audio output (16-bit PCM unless configured otherwise).
+    AudioDelta {
+        audio: Vec<u8>,
+    },
+    /// The current response has no more audio.
+    AudioDone,
+    TranscriptDelta {
+        role: TranscriptRole,
+        text: String,
+    },
+    TranscriptDone {
+        role: TranscriptRole,
+        text: String,
+    },
+    /// Server VAD heard the user start speaking.
+    SpeechStarted,
+    /// Server VAD heard the user stop speaking.
+    SpeechStopped,
+    /// Buffered input audio became a user turn.
+    InputCommitted,
+    /// The user spoke over the model and its output was cut off.
+    Interrupted,
+    /// The model wants a function run; answer with `send_tool_result`.
+    ToolCall {
+        call_id: String,
+        name: String,
+        arguments: Value,
+    },
+    /// The model finished its turn.
+    ResponseDone {
+        usage: Option<Value>,
+    },
+    /// Non-fatal error reported by the server; the session stays open.
+    Error {
+        code: Option<String>,
+        message: String,
+    },
+    /// Any event without a normalized form, as sent.
+    Other(Value),
+}
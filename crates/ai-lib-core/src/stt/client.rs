@@ -1,7 +1,9 @@
 //! STT (Speech-to-Text) client.
 
+use super::long_audio::{merge_transcriptions, parse_wav, plan_chunks, PcmFormat};
 use super::types::{SttOptions, Transcription};
 use crate::{Error, ErrorContext, Result};
+use futures::{StreamExt, TryStreamExt};
 
 /// Client for speech-to-text transcription.
 pub struct SttClient {
@@ -10,6 +12,9 @@ pub struct SttClient {
     base_url: String,
     endpoint_path: String,
     api_key: String,
+    max_upload_bytes: usize,
+    chunk_overlap_secs: f32,
+    max_concurrency: usize,
 }
 
 impl SttClient {
@@ -42,6 +47,9 @@ impl SttClient {
         if let Some(rf) = &options.response_format {
             form = form.text("response_format", rf.clone());
         }
+        for granularity in &options.timestamp_granularities {
+            form = form.text("timestamp_granularities[]", granularity.clone());
+        }
         let response = self
             .http_client
             .post(&endpoint)
@@ -69,20 +77,58 @@ impl SttClient {
             ));
         }
         let json: serde_json::Value = serde_json::from_str(&body)?;
-        let text = json
-            .get("text")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-        Ok(Transcription {
-            text,
-            language: json
-                .get("language")
-                .and_then(|v| v.as_str())
-                .map(String::from),
-            confidence: None,
-            segments: None,
-        })
+        Ok(Transcription::from_json(&json))
+    }
+
+    /// Transcribe a PCM WAV file of any length.
+    ///
+    /// Files above `max_upload_bytes` are split on silence (energy-based, no external
+    /// tools) into overlapping chunks, transcribed up to `max_concurrency` at a time,
+    /// and merged with timestamps on the whole-file timeline and overlap duplicates
+    /// removed. Smaller files go out unchanged in one request.
+    pub async fn transcribe_long(&self, wav: &[u8], options: &SttOptions) -> Result<Transcription> {
+        if wav.len() <= self.max_upload_bytes {
+            return self.transcribe(wav, options).await;
+        }
+        let (format, data) = parse_wav(wav)?;
+        self.transcribe_chunked(data, format, options).await
+    }
+
+    /// Like [`transcribe_long`](Self::transcribe_long) for headerless PCM samples;
+    /// each request carries a WAV header built from `format`.
+    pub async fn transcribe_long_pcm(
+        &self,
+        pcm: &[u8],
+        format: PcmFormat,
+        options: &SttOptions,
+    ) -> Result<Transcription> {
+        self.transcribe_chunked(pcm, format, options).await
+    }
+
+    async fn transcribe_chunked(
+        &self,
+        data: &[u8],
+        format: PcmFormat,
+        options: &SttOptions,
+    ) -> Result<Transcription> {
+        const WAV_HEADER_BYTES: usize = 44;
+        let max_bytes = self.max_upload_bytes.saturating_sub(WAV_HEADER_BYTES);
+        let chunks = plan_chunks(data, format, max_bytes, self.chunk_overlap_secs);
+        let bytes_per_sec = format.sample_rate as f32 * format.block_align() as f32;
+        let parts: Vec<(f32, f32, Transcription)> = futures::stream::iter(chunks)
+            .map(|range| async move {
+                let wav = format.to_wav(&data[range.clone()]);
+                let transcription = self.transcribe(&wav, options).await?;
+                Ok::<_, Error>((
+                    range.start as f32 / bytes_per_sec,
+                    range.end as f32 / bytes_per_sec,
+                    transcription,
+                ))
+            })
+            .buffered(self.max_concurrency.max(1))
+            .try_collect()
+            .await?;
+        Ok(merge_transcriptions(parts))
     }
 
     pub fn model(&self) -> &str {
@@ -96,6 +142,9 @@ pub struct SttClientBuilder {
     base_url: Option<String>,
     endpoint_path: Option<String>,
     timeout_secs: u64,
+    max_upload_bytes: usize,
+    chunk_overlap_secs: f32,
+    max_concurrency: usize,
 }
 
 impl SttClientBuilder {
@@ -106,6 +155,9 @@ impl SttClientBuilder {
             base_url: None,
             endpoint_path: None,
             timeout_secs: 60,
+            max_upload_bytes: 25 * 1024 * 1024,
+            chunk_overlap_secs: 1.0,
+            max_concurrency: 4,
         }
     }
     pub fn model(mut self, model: impl Into<String>) -> Self {
@@ -124,6 +176,21 @@ impl SttClientBuilder {
         self.endpoint_path = Some(path.into());
         self
     }
+    /// Provider upload limit; `transcribe_long` splits larger audio (default 25 MiB).
+    pub fn max_upload_bytes(mut self, bytes: usize) -> Self {
+        self.max_upload_bytes = bytes;
+        self
+    }
+    /// Audio repeated at the start of each chunk after the first (default 1 s).
+    pub fn chunk_overlap_secs(mut self, secs: f32) -> Self {
+        self.chunk_overlap_secs = secs.max(0.0);
+        self
+    }
+    /// Chunks `transcribe_long` transcribes at once (default 4).
+    pub fn max_concurrency(mut self, requests: usize) -> Self {
+        self.max_concurrency = requests.max(1);
+        self
+    }
 
     pub async fn build(self) -> Result<SttClient> {
         let model = self
@@ -154,6 +221,9 @@ impl SttClientBuilder {
             base_url,
             endpoint_path,
             api_key,
+            max_upload_bytes: self.max_upload_bytes,
+            chunk_overlap_secs: self.chunk_overlap_secs,
+            max_concurrency: self.max_concurrency,
         })
     }
 }
@@ -0,0 +1,429 @@
+//! Long-audio support for [`SttClient::transcribe_long`](super::SttClient::transcribe_long):
+//! WAV parsing, energy-based silence detection, chunk planning and transcript merging.
+
+use std::ops::Range;
+
+use super::types::{Transcription, TranscriptionSegment, TranscriptionWord};
+use crate::{Error, ErrorContext, Result};
+
+/// Layout of uncompressed PCM audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcmFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// 8 (unsigned), 16, 24 or 32 (signed integer or, with `float`, IEEE float).
+    pub bits_per_sample: u16,
+    pub float: bool,
+}
+
+impl PcmFormat {
+    /// Signed 16-bit integer PCM.
+    pub fn s16(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            bits_per_sample: 16,
+            float: false,
+        }
+    }
+
+    pub(crate) fn block_align(&self) -> usize {
+        self.channels.max(1) as usize * (self.bits_per_sample as usize / 8)
+    }
+
+    fn bytes_per_second(&self) -> f64 {
+        self.sample_rate as f64 * self.block_align() as f64
+    }
+
+    fn validate(&self) -> Result<()> {
+        let supported = matches!(
+            (self.bits_per_sample, self.float),
+            (8 | 16 | 24 | 32, false) | (32, true)
+        );
+        if !supported || self.sample_rate == 0 || self.channels == 0 {
+            return Err(Error::validation_with_context(
+                format!("unsupported PCM layout {self:?}"),
+                ErrorContext::new().with_source("stt").with_hint(
+                    "long-audio splitting needs 8/16/24/32-bit integer or 32-bit float PCM",
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Wrap `data` in a canonical 44-byte WAV header.
+    pub(crate) fn to_wav(self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(44 + data.len());
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&(if self.float { 3u16 } else { 1u16 }).to_le_bytes());
+        out.extend_from_slice(&self.channels.to_le_bytes());
+        out.extend_from_slice(&self.sample_rate.to_le_bytes());
+        out.extend_from_slice(&(self.bytes_per_second() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.block_align() as u16).to_le_bytes());
+        out.extend_from_slice(&self.bits_per_sample.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+}
+
+fn invalid_wav(message: &str) -> Error {
+    Error::validation_with_context(
+        format!("cannot split audio: {message}"),
+        ErrorContext::new()
+            .with_source("stt")
+            .with_hint("transcribe_long accepts PCM WAV; use transcribe_long_pcm for raw PCM"),
+    )
+}
+
+/// PCM layout and sample bytes of a WAV file.
+pub(crate) fn parse_wav(bytes: &[u8]) -> Result<(PcmFormat, &[u8])> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid_wav("not a RIFF/WAVE file"));
+    }
+    let le16 = |b: &[u8], at: usize| u16::from_le_bytes([b[at], b[at + 1]]);
+    let le32 = |b: &[u8], at: usize| u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]]);
+    let mut format = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let size = le32(bytes, pos + 4) as usize;
+        let body = pos + 8..(pos + 8).saturating_add(size).min(bytes.len());
+        match &bytes[pos..pos + 4] {
+            b"fmt " if body.len() >= 16 => {
+                let fmt = &bytes[body.clone()];
+                let mut tag = le16(fmt, 0);
+                // WAVE_FORMAT_EXTENSIBLE: the real tag leads the sub-format GUID.
+                if tag == 0xfffe && fmt.len() >= 26 {
+                    tag = le16(fmt, 24);
+                }
+                if tag != 1 && tag != 3 {
+                    return Err(invalid_wav("WAV is not PCM"));
+                }
+                format = Some(PcmFormat {
+                    sample_rate: le32(fmt, 4),
+                    channels: le16(fmt, 2),
+                    bits_per_sample: le16(fmt, 14),
+                    float: tag == 3,
+                });
+            }
+            b"data" => {
+                let format = format.ok_or_else(|| invalid_wav("data chunk before fmt chunk"))?;
+                format.validate()?;
+                return Ok((format, &bytes[body]));
+            }
+            _ => {}
+        }
+        pos = body.end + (size & 1);
+    }
+    Err(invalid_wav("no data chunk"))
+}
+
+/// Analysis frame length for silence detection.
+const FRAME_SECS: f64 = 0.03;
+/// RMS below this (about -40 dBFS) always counts as silence.
+const SILENCE_FLOOR: f32 = 0.01;
+/// RMS above this (about -26 dBFS) never does, however loud the recording.
+const SILENCE_CEILING: f32 = 0.05;
+
+/// Per-frame RMS energy of `data`, channels averaged, samples scaled to [-1, 1].
+fn frame_energies(data: &[u8], format: PcmFormat) -> (usize, Vec<f32>) {
+    let block = format.block_align();
+    let frame_bytes = ((format.sample_rate as f64 * FRAME_SECS) as usize).max(1) * block;
+    let width = format.bits_per_sample as usize / 8;
+    let sample = |b: &[u8]| -> f32 {
+        match (width, format.float) {
+            (1, _) => (b[0] as f32 - 128.0) / 128.0,
+            (2, _) => i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            (3, _) => (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0,
+            (_, true) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            _ => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        }
+    };
+    let energies = data
+        .chunks(frame_bytes)
+        .map(|frame| {
+            let blocks = frame.chunks_exact(block);
+            let n = blocks.len().max(1) as f32;
+            let sum: f32 = blocks
+                .map(|b| {
+                    let mono =
+                        b.chunks_exact(width).map(sample).sum::<f32>() / format.channels as f32;
+                    mono * mono
+                })
+                .sum();
+            (sum / n).sqrt()
+        })
+        .collect();
+    (frame_bytes, energies)
+}
+
+/// Byte ranges of `data` to transcribe separately: each at most `max_bytes`, cut in the
+/// middle of the last silent frame in the second half of the window (or its quietest
+/// frame), and starting `overlap_secs` before the previous cut.
+pub(crate) fn plan_chunks(
+    data: &[u8],
+    format: PcmFormat,
+    max_bytes: usize,
+    overlap_secs: f32,
+) -> Vec<Range<usize>> {
+    let block = format.block_align();
+    let max_bytes = (max_bytes / block * block).max(block);
+    let mut chunks = Vec::new();
+    if data.len() <= max_bytes {
+        chunks.push(0..data.len());
+        return chunks;
+    }
+    let (frame_bytes, energies) = frame_energies(data, format);
+    let threshold = {
+        let mut sorted = energies.clone();
+        sorted.sort_by(f32::total_cmp);
+        (sorted[sorted.len() / 10] * 1.5).clamp(SILENCE_FLOOR, SILENCE_CEILING)
+    };
+    let overlap = ((overlap_secs.max(0.0) as f64 * format.bytes_per_second()) as usize)
+        .min(max_bytes / 4)
+        / block
+        * block;
+
+    let mut start = 0;
+    loop {
+        let end = start + max_bytes;
+        if end >= data.len() {
+            chunks.push(start..data.len());
+            break;
+        }
+        // Whole frames inside the second half of [start, end).
+        let first = (start + max_bytes / 2).div_ceil(frame_bytes);
+        let last = end / frame_bytes;
+        // The latest silent frame keeps chunks large; without one, the quietest frame.
+        let cut = (first..last)
+            .rev()
+            .find(|f| energies[*f] < threshold)
+            .or_else(|| (first..last).min_by(|a, b| energies[*a].total_cmp(&energies[*b])))
+            .map_or(end, |frame| {
+                frame * frame_bytes + frame_bytes / 2 / block * block
+            });
+        chunks.push(start..cut);
+        start = (cut - overlap).max(start + block);
+    }
+    chunks
+}
+
+/// Merge per-chunk transcriptions, where chunk `i` started at `offsets[i]` seconds.
+///
+/// Timestamps are shifted onto the whole-file timeline. In each overlap, segments and
+/// words are taken from the earlier chunk up to the middle of the overlap and from the
+/// later chunk after it; the plain text drops the words the later chunk repeats.
+pub(crate) fn merge_transcriptions(parts: Vec<(f32, f32, Transcription)>) -> Transcription {
+    let mut merged = Transcription {
+        text: String::new(),
+        language: None,
+        confidence: None,
+        segments: None,
+        words: None,
+        duration: None,
+    };
+    let count = parts.len();
+    let bounds: Vec<(f32, f32)> = parts.iter().map(|(s, e, _)| (*s, *e)).collect();
+    for (i, (offset, _, part)) in parts.into_iter().enumerate() {
+        // Keep items starting in [from, until) on the shared timeline.
+        let from = match i {
+            0 => f32::NEG_INFINITY,
+            _ => (offset + bounds[i - 1].1) / 2.0,
+        };
+        let until = match i + 1 < count {
+            true => (bounds[i + 1].0 + bounds[i].1) / 2.0,
+            false => f32::INFINITY,
+        };
+        let keep = |start: f32| start >= from && start < until;
+
+        if let Some(segments) = part.segments {
+            merged.segments.get_or_insert_with(Vec::new).extend(
+                segments
+                    .into_iter()
+                    .map(|s| TranscriptionSegment {
+                        start: s.start + offset,
+                        end: s.end + offset,
+                        ..s
+                    })
+                    .filter(|s| keep(s.start)),
+            );
+        }
+        if let Some(words) = part.words {
+            merged.words.get_or_insert_with(Vec::new).extend(
+                words
+                    .into_iter()
+                    .map(|w| TranscriptionWord {
+                        start: w.start + offset,
+                        end: w.end + offset,
+                        ..w
+                    })
+                    .filter(|w| keep(w.start)),
+            );
+        }
+        merged.text = join_dedup(&merged.text, &part.text);
+        merged.language = merged.language.or(part.language);
+    }
+    merged.duration = bounds.last().map(|(_, end)| *end);
+    merged
+}
+
+/// Append `next` to `text`, replacing the longest run of trailing words of `text` that
+/// `next` repeats at its start (compared case- and punctuation-insensitively).
+fn join_dedup(text: &str, next: &str) -> String {
+    let next = next.trim();
+    if text.is_empty() {
+        return next.to_string();
+    }
+    if next.is_empty() {
+        return text.to_string();
+    }
+    let norm = |w: &str| {
+        w.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    let tail: Vec<String> = text.split_whitespace().map(norm).collect();
+    let head: Vec<&str> = next.split_whitespace().collect();
+    let head_norm: Vec<String> = head.iter().map(|w| norm(w)).collect();
+    let max = tail.len().min(head.len()).min(30);
+    let repeated = (1..=max)
+        .rev()
+        .find(|&k| {
+            tail[tail.len() - k..] == head_norm[..k]
+                // A single short word ("I", "a") is too likely to repeat by chance.
+                && (k > 1 || head_norm[0].chars().count() > 3)
+        })
+        .unwrap_or(0);
+    if repeated == 0 {
+        return format!("{} {}", text, next);
+    }
+    // Keep the later chunk's copy: it heard the words with more following context.
+    let kept: Vec<&str> = text.split_whitespace().collect();
+    format!("{} {}", kept[..kept.len() - repeated].join(" "), next)
+        .trim_start()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mono 16-bit 1 kHz audio: `loud` seconds of tone, `quiet` of silence, repeated.
+    fn speech_like(pattern: &[(bool, f32)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for &(loud, secs) in pattern {
+            for i in 0..(secs * 1000.0) as usize {
+                let v: i16 = if loud && i % 2 == 0 {
+                    12_000
+                } else if loud {
+                    -12_000
+                } else {
+                    20
+                };
+                data.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_wav_round_trip() {
+        let format = PcmFormat::s16(16_000, 2);
+        let wav = format.to_wav(&[1, 2, 3, 4]);
+        let (parsed, data) = parse_wav(&wav).unwrap();
+        assert_eq!(parsed, format);
+        assert_eq!(data, [1, 2, 3, 4]);
+        assert!(parse_wav(b"ID3 not a wav").is_err());
+    }
+
+    #[test]
+    fn test_plan_chunks_cuts_in_silence_with_overlap() {
+        let format = PcmFormat::s16(1000, 1);
+        // speech 0-3.0s, silence 3.0-3.5s, speech 3.5-7s
+        let data = speech_like(&[(true, 3.0), (false, 0.5), (true, 3.5)]);
+        let chunks = plan_chunks(&data, format, 8000, 0.2); // 4 s windows
+        assert_eq!(chunks.len(), 2);
+        let cut_secs = chunks[0].end as f32 / 2000.0;
+        assert!((3.0..3.5).contains(&cut_secs), "cut at {cut_secs}");
+        assert_eq!(chunks[1].start, chunks[0].end - 400);
+        assert_eq!(chunks[1].end, data.len());
+        assert!(chunks.iter().all(|c| c.len() <= 8000 && c.start % 2 == 0));
+
+        let whole = plan_chunks(&data, format, data.len(), 1.0);
+        assert_eq!((whole.len(), whole[0].end), (1, data.len()));
+    }
+
+    #[test]
+    fn test_merge_offsets_and_dedupes_overlap() {
+        let part = |text: &str, words: &[(&str, f32)]| Transcription {
+            text: text.into(),
+            language: Some("en".into()),
+            confidence: None,
+            segments: None,
+            words: Some(
+                words
+                    .iter()
+                    .map(|(w, s)| TranscriptionWord {
+                        start: *s,
+                        end: s + 0.3,
+                        word: w.to_string(),
+                        speaker: None,
+                    })
+                    .collect(),
+            ),
+            duration: None,
+        };
+        // Chunk 0 covers 0-10s, chunk 1 covers 9-15s: overlap midpoint 9.5s.
+        let merged = merge_transcriptions(vec![
+            (
+                0.0,
+                10.0,
+                part(
+                    "we ship on Monday",
+                    &[("we", 8.0), ("ship", 8.6), ("on", 9.2), ("Monday", 9.7)],
+                ),
+            ),
+            (
+                9.0,
+                15.0,
+                part(
+                    "on Monday. Returns are free.",
+                    &[("on", 0.2), ("Monday.", 0.7), ("Returns", 1.5)],
+                ),
+            ),
+        ]);
+        assert_eq!(merged.text, "we ship on Monday. Returns are free.");
+        let words: Vec<(&str, f32)> = merged
+            .words
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|w| (w.word.as_str(), w.start))
+            .collect();
+        assert_eq!(
+            words,
+            [
+                ("we", 8.0),
+                ("ship", 8.6),
+                ("on", 9.2),
+                ("Monday.", 9.7),
+                ("Returns", 10.5)
+            ]
+        );
+        assert_eq!(merged.duration, Some(15.0));
+        assert_eq!(merged.language.as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn test_join_dedup_requires_meaningful_overlap() {
+        assert_eq!(join_dedup("so I", "I think"), "so I I think");
+        assert_eq!(join_dedup("a b c", ""), "a b c");
+        assert_eq!(join_dedup("", "x"), "x");
+        assert_eq!(join_dedup("hello world", "World"), "hello World");
+    }
+}
@@ -1,7 +1,16 @@
-//! STT（语音转文字）模块：通过 Provider API（如 OpenAI Whisper）将音频转录为文本。
+//! STT（语音转文字）模块：通过 Provider API（如 OpenAI Whisper）将音频转录为文本，支持长音频静音切分、词级时间戳、说话人标签与 SRT/VTT 导出。
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`SttClient::transcribe`] | One request; parses segments, words and speakers when returned |
+//! | [`SttClient::transcribe_long`] | Splits WAV over `max_upload_bytes` on silence, transcribes chunks concurrently, merges |
+//! | [`SttClient::transcribe_long_pcm`] | Same for headerless PCM described by a [`PcmFormat`] |
+//! | [`Transcription::to_srt`] / [`Transcription::to_vtt`] | Subtitle export with speaker labels |
 
 mod client;
+mod long_audio;
 mod types;
 
 pub use client::{SttClient, SttClientBuilder};
-pub use types::{SttOptions, Transcription, TranscriptionSegment};
+pub use long_audio::PcmFormat;
+pub use types::{SttOptions, Transcription, TranscriptionSegment, TranscriptionWord};
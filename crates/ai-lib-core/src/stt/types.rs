@@ -1,6 +1,9 @@
 //! STT (Speech-to-Text) types.
 
+use std::fmt::Write as _;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 /// Transcription result from STT.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +15,12 @@ pub struct Transcription {
     pub confidence: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub segments: Option<Vec<TranscriptionSegment>>,
+    /// Word-level timestamps, when requested and returned by the provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<TranscriptionWord>>,
+    /// Audio duration in seconds, when reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f32>,
 }
 
 /// A segment of transcribed text with timing.
@@ -20,6 +29,179 @@ pub struct TranscriptionSegment {
     pub start: f32,
     pub end: f32,
     pub text: String,
+    /// Speaker label from diarizing models (e.g. `"A"`, `"speaker_0"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+/// A single word with timing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionWord {
+    pub start: f32,
+    pub end: f32,
+    pub word: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+impl Transcription {
+    /// Parse an OpenAI-compatible transcription body (`json`, `verbose_json` or
+    /// `diarized_json`): `text`, `language`, `duration`, `segments[]` and `words[]`.
+    pub fn from_json(json: &Value) -> Self {
+        let segments = json.get("segments").and_then(Value::as_array).map(|items| {
+            items
+                .iter()
+                .map(|s| TranscriptionSegment {
+                    start: seconds(s.get("start")),
+                    end: seconds(s.get("end")),
+                    text: s
+                        .get("text")
+                        .and_then(Value::as_str)
+                        .unwrap_or("")
+                        .trim()
+                        .to_string(),
+                    speaker: speaker(s),
+                })
+                .collect::<Vec<_>>()
+        });
+        let words = json.get("words").and_then(Value::as_array).map(|items| {
+            items
+                .iter()
+                .map(|w| TranscriptionWord {
+                    start: seconds(w.get("start")),
+                    end: seconds(w.get("end")),
+                    word: w
+                        .get("word")
+                        .or_else(|| w.get("text"))
+                        .and_then(Value::as_str)
+                        .unwrap_or("")
+                        .trim()
+                        .to_string(),
+                    speaker: speaker(w),
+                })
+                .collect::<Vec<_>>()
+        });
+        let text = match json.get("text").and_then(Value::as_str) {
+            Some(text) => text.trim().to_string(),
+            None => segments
+                .iter()
+                .flatten()
+                .map(|s| s.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        Self {
+            text,
+            language: json
+                .get("language")
+                .and_then(Value::as_str)
+                .map(String::from),
+            confidence: None,
+            segments,
+            words,
+            duration: json
+                .get("duration")
+                .and_then(Value::as_f64)
+                .map(|d| d as f32),
+        }
+    }
+
+    /// SubRip subtitles: one cue per segment, or per group of words when only word
+    /// timestamps are available. Speaker labels become a `Speaker: ` prefix.
+    pub fn to_srt(&self) -> String {
+        let mut out = String::new();
+        for (i, cue) in self.cues().iter().enumerate() {
+            let _ = write!(
+                out,
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                timestamp(cue.start, ','),
+                timestamp(cue.end, ','),
+                match &cue.speaker {
+                    Some(speaker) => format!("{speaker}: {}", cue.text),
+                    None => cue.text.clone(),
+                }
+            );
+        }
+        out
+    }
+
+    /// WebVTT subtitles; speaker labels use `<v Speaker>` voice spans.
+    pub fn to_vtt(&self) -> String {
+        let mut out = String::from("WEBVTT\n\n");
+        for cue in self.cues() {
+            let _ = write!(
+                out,
+                "{} --> {}\n{}\n\n",
+                timestamp(cue.start, '.'),
+                timestamp(cue.end, '.'),
+                match &cue.speaker {
+                    Some(speaker) => format!("<v {speaker}>{}", cue.text),
+                    None => cue.text.clone(),
+                }
+            );
+        }
+        out
+    }
+
+    fn cues(&self) -> Vec<TranscriptionSegment> {
+        if let Some(segments) = self.segments.as_ref().filter(|s| !s.is_empty()) {
+            return segments.clone();
+        }
+        let Some(words) = self.words.as_ref().filter(|w| !w.is_empty()) else {
+            return Vec::new();
+        };
+        // Break on speaker change, every 12 words, or after 7 seconds.
+        let mut cues: Vec<TranscriptionSegment> = Vec::new();
+        let mut count = 0;
+        for word in words {
+            match cues.last_mut() {
+                Some(cue)
+                    if cue.speaker == word.speaker && count < 12 && word.end - cue.start <= 7.0 =>
+                {
+                    cue.text.push(' ');
+                    cue.text.push_str(&word.word);
+                    cue.end = word.end;
+                    count += 1;
+                }
+                _ => {
+                    cues.push(TranscriptionSegment {
+                        start: word.start,
+                        end: word.end,
+                        text: word.word.clone(),
+                        speaker: word.speaker.clone(),
+                    });
+                    count = 1;
+                }
+            }
+        }
+        cues
+    }
+}
+
+fn seconds(value: Option<&Value>) -> f32 {
+    value.and_then(Value::as_f64).unwrap_or(0.0) as f32
+}
+
+fn speaker(item: &Value) -> Option<String> {
+    match item.get("speaker").or_else(|| item.get("speaker_label"))? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// `HH:MM:SS,mmm` (SRT) or `HH:MM:SS.mmm` (VTT).
+fn timestamp(seconds: f32, separator: char) -> String {
+    let millis = (seconds.max(0.0) as f64 * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
 }
 
 /// Options for STT transcription.
@@ -29,4 +211,81 @@ pub struct SttOptions {
     pub prompt: Option<String>,
     pub temperature: Option<f32>,
     pub response_format: Option<String>,
+    /// `"word"` and/or `"segment"`; sent as `timestamp_granularities[]` (needs
+    /// `response_format: "verbose_json"` on OpenAI-compatible APIs).
+    pub timestamp_granularities: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parses_words_and_speakers() {
+        let t = Transcription::from_json(&json!({
+            "text": " Hello there. Hi! ",
+            "language": "english",
+            "duration": 2.5,
+            "segments": [
+                { "start": 0.0, "end": 1.2, "text": " Hello there.", "speaker": "A" },
+                { "start": 1.4, "end": 2.5, "text": "Hi!", "speaker": 1 }
+            ],
+            "words": [{ "start": 0.0, "end": 0.5, "word": " Hello" }]
+        }));
+        assert_eq!(t.text, "Hello there. Hi!");
+        assert_eq!(t.duration, Some(2.5));
+        let segments = t.segments.unwrap();
+        assert_eq!(segments[0].speaker.as_deref(), Some("A"));
+        assert_eq!(segments[1].speaker.as_deref(), Some("1"));
+        assert_eq!(t.words.unwrap()[0].word, "Hello");
+    }
+
+    #[test]
+    fn test_srt_and_vtt_export() {
+        let t = Transcription::from_json(&json!({
+            "text": "Hello there. Hi!",
+            "segments": [
+                { "start": 0.0, "end": 1.2, "text": "Hello there.", "speaker": "A" },
+                { "start": 3661.5, "end": 3662.25, "text": "Hi!" }
+            ]
+        }));
+        assert_eq!(
+            t.to_srt(),
+            "1\n00:00:00,000 --> 00:00:01,200\nA: Hello there.\n\n\
+             2\n01:01:01,500 --> 01:01:02,250\nHi!\n\n"
+        );
+        assert_eq!(
+            t.to_vtt(),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:01.200\n<v A>Hello there.\n\n\
+             01:01:01.500 --> 01:01:02.250\nHi!\n\n"
+        );
+    }
+
+    #[test]
+    fn test_cues_from_words_break_on_speaker() {
+        let word = |start: f32, word: &str, speaker: &str| TranscriptionWord {
+            start,
+            end: start + 0.4,
+            word: word.into(),
+            speaker: Some(speaker.into()),
+        };
+        let t = Transcription {
+            text: "Hi there yes".into(),
+            language: None,
+            confidence: None,
+            segments: None,
+            words: Some(vec![
+                word(0.0, "Hi", "A"),
+                word(0.5, "there", "A"),
+                word(1.0, "yes", "B"),
+            ]),
+            duration: None,
+        };
+        assert_eq!(
+            t.to_vtt(),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:00.900\n<v A>Hi there\n\n\
+             00:00:01.000 --> 00:00:01.400\n<v B>yes\n\n"
+        );
+    }
 }
@@ -0,0 +1,855 @@
+//! Scriptable in-process provider.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use crate::client::{AiClient, AiClientBuilder, UnifiedResponse};
+use crate::error_code::StandardErrorCode;
+use crate::protocol::{ProtocolManifest, RetryPolicy};
+use crate::transport::cassette::SyncStream;
+use crate::transport::Responder;
+use crate::types::events::StreamingEvent;
+use crate::types::tool::ToolCall;
+use crate::{Error, ErrorContext, Result};
+
+/// Provider id of the built-in manifest: clients are built for `"fake/<model>"`.
+pub const FAKE_PROVIDER_ID: &str = "fake";
+
+const FAKE_MANIFEST_YAML: &str = r#"
+id: fake
+protocol_version: "2.0"
+name: Fake Provider
+status: stable
+category: ai_provider
+official_url: "https://example.invalid"
+support_contact: "https://example.invalid"
+endpoint:
+  base_url: "http://fake.invalid/v1"
+capabilities:
+  streaming: true
+  tools: true
+  vision: true
+endpoints:
+  chat:
+    path: "/chat/completions"
+    method: POST
+    adapter: openai
+parameter_mappings:
+  model: "model"
+  messages: "messages"
+  stream: "stream"
+  temperature: "temperature"
+  max_tokens: "max_tokens"
+  tools: "tools"
+  tool_choice: "tool_choice"
+response_paths:
+  content: "choices[0].message.content"
+  tool_calls: "choices[0].message.tool_calls"
+  usage: "usage"
+  finish_reason: "choices[0].finish_reason"
+streaming:
+  decoder:
+    format: "sse"
+    strategy: "openai_chat"
+    delimiter: "\n\n"
+    prefix: "data: "
+    done_signal: "[DONE]"
+  frame_selector: "exists($.choices) || exists($.error)"
+  content_path: "choices[0].delta.content"
+  tool_call_path: "choices[0].delta.tool_calls"
+  usage_path: "usage"
+retry_policy:
+  strategy: exponential_backoff
+  max_retries: 2
+  min_delay_ms: 0
+  max_delay_ms: 1000
+  retry_on_http_status: [429, 500, 502, 503, 504]
+error_classification:
+  by_http_status:
+    "400": "invalid_request"
+    "401": "authentication"
+    "403": "permission_denied"
+    "404": "not_found"
+    "409": "conflict"
+    "413": "request_too_large"
+    "429": "rate_limited"
+    "500": "server_error"
+    "503": "overloaded"
+    "504": "timeout"
+"#;
+
+/// HTTP status a provider would typically answer with for `code`.
+fn status_for(code: StandardErrorCode) -> u16 {
+    match code {
+        StandardErrorCode::InvalidRequest => 400,
+        StandardErrorCode::Authentication => 401,
+        StandardErrorCode::PermissionDenied => 403,
+        StandardErrorCode::NotFound => 404,
+        StandardErrorCode::Conflict => 409,
+        StandardErrorCode::RequestTooLarge => 413,
+        StandardErrorCode::RateLimited | StandardErrorCode::QuotaExhausted => 429,
+        StandardErrorCode::Cancelled => 499,
+        StandardErrorCode::ServerError | StandardErrorCode::Unknown => 500,
+        StandardErrorCode::Overloaded => 503,
+        StandardErrorCode::Timeout => 504,
+    }
+}
+
+#[derive(Debug, Clone)]
+enum FakeBody {
+    Reply {
+        response: UnifiedResponse,
+        finish_reason: String,
+    },
+    Events(Vec<StreamingEvent>),
+    Error {
+        code: StandardErrorCode,
+        message: String,
+    },
+    Raw {
+        status: u16,
+        body: String,
+    },
+}
+
+/// One scripted reply of a [`FakeProvider`].
+///
+/// Replies are rendered as OpenAI chat completions: a JSON body for non-streaming
+/// requests, SSE chunks for streaming ones (so `text` / `events` work for both).
+#[derive(Debug, Clone)]
+pub struct FakeResponse {
+    body: FakeBody,
+    headers: Vec<(String, String)>,
+    latency: Duration,
+    chunk_delay: Duration,
+}
+
+impl FakeResponse {
+    fn new(body: FakeBody) -> Self {
+        Self {
+            body,
+            headers: Vec::new(),
+            latency: Duration::ZERO,
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    /// Assistant text, finish reason `stop`.
+    pub fn text(content: impl Into<String>) -> Self {
+        Self::response(UnifiedResponse::new(content))
+    }
+
+    /// Content, tool calls and usage of a complete response.
+    pub fn response(response: UnifiedResponse) -> Self {
+        let finish_reason = if response.tool_calls.is_empty() {
+            "stop"
+        } else {
+            "tool_calls"
+        };
+        Self::new(FakeBody::Reply {
+            response,
+            finish_reason: finish_reason.to_string(),
+        })
+    }
+
+    /// A single tool call (finish reason `tool_calls`).
+    pub fn tool_call(id: impl Into<String>, name: impl Into<String>, arguments: Value) -> Self {
+        Self::tool_calls(vec![ToolCall {
+            id: id.into(),
+            name: name.into(),
+            arguments,
+        }])
+    }
+
+    pub fn tool_calls(calls: Vec<ToolCall>) -> Self {
+        Self::response(UnifiedResponse {
+            tool_calls: calls,
+            ..Default::default()
+        })
+    }
+
+    /// Exact streaming event sequence, one SSE chunk per event. `StreamError`
+    /// aborts the body at that point (a transport error on the client side).
+    pub fn events(events: Vec<StreamingEvent>) -> Self {
+        Self::new(FakeBody::Events(events))
+    }
+
+    /// Provider error that classifies as `code` (status and provider error code).
+    pub fn error(code: StandardErrorCode, message: impl Into<String>) -> Self {
+        Self::new(FakeBody::Error {
+            code,
+            message: message.into(),
+        })
+    }
+
+    /// `429` with a `retry-after` header (rounded up to whole seconds, as the header requires).
+    pub fn rate_limited(retry_after: Duration) -> Self {
+        let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        Self::error(StandardErrorCode::RateLimited, "rate limit exceeded")
+            .with_header("retry-after", secs.to_string())
+    }
+
+    /// Arbitrary status and body, sent as is.
+    pub fn raw(status: u16, body: impl Into<String>) -> Self {
+        Self::new(FakeBody::Raw {
+            status,
+            body: body.into(),
+        })
+    }
+
+    /// Attach OpenAI-style usage to a text / tool-call reply.
+    pub fn with_usage(mut self, prompt_tokens: u64, completion_tokens: u64) -> Self {
+        if let FakeBody::Reply { response, .. } = &mut self.body {
+            response.usage = Some(json!({
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": completion_tokens,
+                "total_tokens": prompt_tokens + completion_tokens,
+            }));
+        }
+        self
+    }
+
+    pub fn with_finish_reason(mut self, reason: impl Into<String>) -> Self {
+        if let FakeBody::Reply { finish_reason, .. } = &mut self.body {
+            *finish_reason = reason.into();
+        }
+        self
+    }
+
+    /// Delay before response headers are returned.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Delay before each streamed chunk.
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = delay;
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// OpenAI-style `x-ratelimit-*-requests` headers.
+    pub fn with_rate_limit(self, limit: u64, remaining: u64, reset: Duration) -> Self {
+        self.with_header("x-ratelimit-limit-requests", limit.to_string())
+            .with_header("x-ratelimit-remaining-requests", remaining.to_string())
+            .with_header(
+                "x-ratelimit-reset-requests",
+                format!("{}ms", reset.as_millis()),
+            )
+    }
+
+    fn into_http(self, request: &ReceivedRequest) -> Result<reqwest::Response> {
+        let model = request.model.clone().unwrap_or_default();
+        let (status, content_type, body) = match self.body {
+            FakeBody::Error { code, message } => (
+                status_for(code),
+                "application/json",
+                reqwest::Body::from(
+                    json!({ "error": { "message": message, "type": code.name(), "code": code.name() } })
+                        .to_string(),
+                ),
+            ),
+            FakeBody::Raw { status, body } => (status, "application/json", reqwest::Body::from(body)),
+            FakeBody::Reply {
+                response,
+                finish_reason,
+            } if request.stream => (
+                200,
+                "text/event-stream",
+                sse_body(reply_events(response, finish_reason), self.chunk_delay),
+            ),
+            FakeBody::Events(events) if request.stream => {
+                (200, "text/event-stream", sse_body(events, self.chunk_delay))
+            }
+            FakeBody::Reply {
+                response,
+                finish_reason,
+            } => (
+                200,
+                "application/json",
+                reqwest::Body::from(completion_json(&model, &response, &finish_reason).to_string()),
+            ),
+            FakeBody::Events(events) => {
+                let (response, finish_reason) = fold_events(events);
+                (
+                    200,
+                    "application/json",
+                    reqwest::Body::from(
+                        completion_json(&model, &response, &finish_reason).to_string(),
+                    ),
+                )
+            }
+        };
+        let mut builder = http::Response::builder()
+            .status(status)
+            .header("content-type", content_type)
+            .header("x-request-id", format!("fake-{}", request.sequence));
+        for (k, v) in &self.headers {
+            builder = builder.header(k.as_str(), v.as_str());
+        }
+        builder
+            .body(body)
+            .map(reqwest::Response::from)
+            .map_err(|e| {
+                Error::validation_with_context(
+                    format!("invalid fake response: {e}"),
+                    ErrorContext::new().with_source("fake_provider"),
+                )
+            })
+    }
+}
+
+fn completion_json(model: &str, response: &UnifiedResponse, finish_reason: &str) -> Value {
+    let mut message = json!({
+        "role": "assistant",
+        "content": if response.content.is_empty() && !response.tool_calls.is_empty() {
+            Value::Null
+        } else {
+            Value::String(response.content.clone())
+        },
+    });
+    if !response.tool_calls.is_empty() {
+        message["tool_calls"] = response
+            .tool_calls
+            .iter()
+            .map(|c| {
+                json!({
+                    "id": c.id,
+                    "type": "function",
+                    "function": { "name": c.name, "arguments": c.arguments.to_string() },
+                })
+            })
+            .collect();
+    }
+    let mut body = json!({
+        "id": "chatcmpl-fake",
+        "object": "chat.completion",
+        "model": model,
+        "choices": [{ "index": 0, "message": message, "finish_reason": finish_reason }],
+    });
+    if let Some(usage) = &response.usage {
+        body["usage"] = usage.clone();
+    }
+    body
+}
+
+/// Streaming shape of a complete reply: content split on whitespace boundaries,
+/// each tool call as start + full arguments, then usage and end.
+fn reply_events(response: UnifiedResponse, finish_reason: String) -> Vec<StreamingEvent> {
+    let mut events: Vec<StreamingEvent> = response
+        .content
+        .split_inclusive(' ')
+        .map(|piece| StreamingEvent::PartialContentDelta {
+            content: piece.to_string(),
+            sequence_id: None,
+        })
+        .collect();
+    for (i, call) in response.tool_calls.into_iter().enumerate() {
+        events.push(StreamingEvent::ToolCallStarted {
+            tool_call_id: call.id.clone(),
+            tool_name: call.name,
+            index: Some(i as u32),
+        });
+        events.push(StreamingEvent::PartialToolCall {
+            tool_call_id: call.id,
+            arguments: call.arguments.to_string(),
+            index: Some(i as u32),
+            is_complete: Some(true),
+        });
+    }
+    if let Some(usage) = response.usage {
+        events.push(StreamingEvent::Metadata {
+            usage: Some(usage),
+            finish_reason: None,
+            stop_reason: None,
+        });
+    }
+    events.push(StreamingEvent::StreamEnd {
+        finish_reason: Some(finish_reason),
+    });
+    events
+}
+
+/// Non-streaming view of an event script.
+fn fold_events(events: Vec<StreamingEvent>) -> (UnifiedResponse, String) {
+    let mut response = UnifiedResponse::default();
+    let mut finish = None;
+    let mut asm = crate::utils::tool_call_assembler::ToolCallAssembler::new();
+    for ev in events {
+        match ev {
+            StreamingEvent::PartialContentDelta { content, .. } => {
+                response.content.push_str(&content)
+            }
+            StreamingEvent::ToolCallStarted {
+                tool_call_id,
+                tool_name,
+                ..
+            } => asm.on_started(tool_call_id, tool_name),
+            StreamingEvent::PartialToolCall {
+                tool_call_id,
+                arguments,
+                ..
+            } => asm.on_partial(&tool_call_id, &arguments),
+            StreamingEvent::Metadata {
+                usage,
+                finish_reason,
+                ..
+            } => {
+                response.usage = usage.or(response.usage);
+                finish = finish_reason.or(finish);
+            }
+            StreamingEvent::StreamEnd { finish_reason } => finish = finish_reason.or(finish),
+            _ => {}
+        }
+    }
+    response.tool_calls = asm.finalize();
+    let default = if response.tool_calls.is_empty() {
+        "stop"
+    } else {
+        "tool_calls"
+    };
+    (response, finish.unwrap_or_else(|| default.to_string()))
+}
+
+fn chunk(delta: Value) -> Value {
+    json!({ "object": "chat.completion.chunk", "choices": [{ "index": 0, "delta": delta }] })
+}
+
+fn sse_body(events: Vec<StreamingEvent>, delay: Duration) -> reqwest::Body {
+    let mut frames: Vec<std::result::Result<Bytes, std::io::Error>> = Vec::new();
+    let mut indices: HashMap<String, u32> = HashMap::new();
+    let mut aborted = false;
+    let frame = |v: Value| Ok(Bytes::from(format!("data: {v}\n\n")));
+    for ev in events {
+        let value = match ev {
+            StreamingEvent::PartialContentDelta { content, .. } => {
+                chunk(json!({ "content": content }))
+            }
+            StreamingEvent::ThinkingDelta { thinking, .. } => {
+                chunk(json!({ "reasoning_content": thinking }))
+            }
+            StreamingEvent::ToolCallStarted {
+                tool_call_id,
+                tool_name,
+                index,
+            } => {
+                let next = indices.len() as u32;
+                let index = *indices
+                    .entry(tool_call_id.clone())
+                    .or_insert(index.unwrap_or(next));
+                chunk(json!({ "tool_calls": [{
+                    "index": index,
+                    "id": tool_call_id,
+                    "type": "function",
+                    "function": { "name": tool_name, "arguments": "" },
+                }] }))
+            }
+            StreamingEvent::PartialToolCall {
+                tool_call_id,
+                arguments,
+                index,
+                ..
+            } => {
+                let index = indices
+                    .get(&tool_call_id)
+                    .copied()
+                    .or(index)
+                    .unwrap_or_default();
+                chunk(
+                    json!({ "tool_calls": [{ "index": index, "function": { "arguments": arguments } }] }),
+                )
+            }
+            StreamingEvent::Metadata {
+                usage,
+                finish_reason,
+                ..
+            } => {
+                let mut v = json!({ "object": "chat.completion.chunk", "choices": [] });
+                if let Some(reason) = finish_reason {
+                    v["choices"] = json!([{ "index": 0, "delta": {}, "finish_reason": reason }]);
+                }
+                if let Some(usage) = usage {
+                    v["usage"] = usage;
+                }
+                v
+            }
+            StreamingEvent::StreamEnd { finish_reason } => json!({
+                "object": "chat.completion.chunk",
+                "choices": [{
+                    "index": 0,
+                    "delta": {},
+                    "finish_reason": finish_reason.unwrap_or_else(|| "stop".to_string()),
+                }],
+            }),
+            StreamingEvent::StreamError { error, .. } => {
+                frames.push(Err(std::io::Error::other(format!(
+                    "fake provider stream error: {error}"
+                ))));
+                aborted = true;
+                break;
+            }
+            StreamingEvent::ToolCallEnded { .. }
+            | StreamingEvent::FinalCandidate { .. }
+            | StreamingEvent::StreamRecovered { .. } => continue,
+        };
+        frames.push(frame(value));
+    }
+    if !aborted {
+        frames.push(Ok(Bytes::from_static(b"data: [DONE]\n\n")));
+    }
+    let stream = futures::stream::iter(frames).then(move |f| async move {
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        f
+    });
+    reqwest::Body::wrap_stream(SyncStream::new(stream.boxed()))
+}
+
+/// A request received by a [`FakeProvider`].
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    /// 1-based arrival order across all clients sharing the fake.
+    pub sequence: usize,
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    /// Lower-cased header names.
+    pub headers: BTreeMap<String, String>,
+    /// Provider request body (`Null` when empty or not JSON).
+    pub body: Value,
+    /// `model` field of the body.
+    pub model: Option<String>,
+    pub stream: bool,
+}
+
+impl ReceivedRequest {
+    fn from_request(sequence: usize, request: &reqwest::Request) -> Self {
+        let body: Value = request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .and_then(|b| serde_json::from_slice(b).ok())
+            .unwrap_or(Value::Null);
+        Self {
+            sequence,
+            method: request.method().as_str().to_string(),
+            path: request.url().path().to_string(),
+            query: request.url().query().map(str::to_string),
+            headers: request
+                .headers()
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.as_str().to_string(),
+                        String::from_utf8_lossy(v.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+            model: body
+                .get("model")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            stream: body.get("stream").and_then(Value::as_bool).unwrap_or(false),
+            body,
+        }
+    }
+
+    pub fn messages(&self) -> &[Value] {
+        self.body
+            .get("messages")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Text of the last `user` message (string content or concatenated text blocks).
+    pub fn last_user_text(&self) -> Option<String> {
+        let msg = self
+            .messages()
+            .iter()
+            .rev()
+            .find(|m| m.get("role").and_then(Value::as_str) == Some("user"))?;
+        match msg.get("content")? {
+            Value::String(s) => Some(s.clone()),
+            Value::Array(blocks) => Some(
+                blocks
+                    .iter()
+                    .filter_map(|b| b.get("text").and_then(Value::as_str))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+struct Scripted {
+    model: Option<String>,
+    response: FakeResponse,
+}
+
+#[derive(Default)]
+struct FakeState {
+    script: VecDeque<Scripted>,
+    default: Option<FakeResponse>,
+    requests: Vec<ReceivedRequest>,
+}
+
+/// In-process provider for unit tests: a manifest plus a [`Responder`] that serves
+/// scripted [`FakeResponse`]s in FIFO order.
+///
+/// Clones share the script and the request log, so a fake can be kept for
+/// assertions after handing it to a client.
+#[derive(Clone)]
+pub struct FakeProvider {
+    manifest: ProtocolManifest,
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl Default for FakeProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeProvider {
+    /// OpenAI-compatible fake (`id: fake`) retrying 429/5xx twice without backoff.
+    pub fn new() -> Self {
+        let manifest = serde_yaml::from_str(FAKE_MANIFEST_YAML.trim_start())
+            .expect("built-in fake manifest is valid");
+        Self {
+            manifest,
+            state: Arc::new(Mutex::new(FakeState::default())),
+        }
+    }
+
+    /// Override `retry_policy.max_retries` (0 disables retries).
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        let policy = self
+            .manifest
+            .retry_policy
+            .get_or_insert_with(|| RetryPolicy {
+                strategy: "exponential_backoff".to_string(),
+                max_retries: None,
+                min_delay_ms: None,
+                max_delay_ms: None,
+                jitter: None,
+                retry_on_http_status: None,
+                retry_on_error_status: None,
+            });
+        policy.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Adjust the manifest (capabilities, error classification, ...) before building clients.
+    pub fn with_manifest(mut self, f: impl FnOnce(&mut ProtocolManifest)) -> Self {
+        f(&mut self.manifest);
+        self
+    }
+
+    pub fn manifest(&self) -> &ProtocolManifest {
+        &self.manifest
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue a reply for the next request (any model).
+    pub fn push(&self, response: FakeResponse) -> &Self {
+        self.lock().script.push_back(Scripted {
+            model: None,
+            response,
+        });
+        self
+    }
+
+    /// Queue a reply served only to requests for `model` (`"m"` or `"fake/m"`).
+    pub fn push_for_model(&self, model: impl Into<String>, response: FakeResponse) -> &Self {
+        self.lock().script.push_back(Scripted {
+            model: Some(model.into()),
+            response,
+        });
+        self
+    }
+
+    /// Reply used once the script has no match (otherwise such requests fail).
+    pub fn set_default(&self, response: FakeResponse) -> &Self {
+        self.lock().default = Some(response);
+        self
+    }
+
+    /// Builder preconfigured with this fake's manifest and responder.
+    pub fn client_builder(&self) -> AiClientBuilder {
+        AiClientBuilder::new()
+            .protocol_manifest(self.manifest.clone())
+            .responder(Arc::new(self.clone()))
+    }
+
+    /// Client for `"fake/<model>"` (a bare model name gets the prefix).
+    pub async fn client(&self, model: &str) -> Result<AiClient> {
+        let model = if model.contains('/') {
+            model.to_string()
+        } else {
+            format!("{}/{}", self.manifest.id, model)
+        };
+        self.client_builder().build(&model).await
+    }
+
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.lock().requests.clone()
+    }
+
+    pub fn last_request(&self) -> Option<ReceivedRequest> {
+        self.lock().requests.last().cloned()
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.lock().requests.len()
+    }
+
+    /// Scripted replies not served yet.
+    pub fn remaining(&self) -> usize {
+        self.lock().script.len()
+    }
+
+    /// Panics unless exactly `n` requests were received.
+    #[track_caller]
+    pub fn assert_request_count(&self, n: usize) {
+        let st = self.lock();
+        assert_eq!(
+            st.requests.len(),
+            n,
+            "fake provider: expected {n} request(s), got {}: {:?}",
+            st.requests.len(),
+            st.requests
+                .iter()
+                .map(|r| (r.model.clone(), r.last_user_text_or_path()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    /// Panics if scripted replies were left unused.
+    #[track_caller]
+    pub fn assert_exhausted(&self) {
+        let st = self.lock();
+        assert!(
+            st.script.is_empty(),
+            "fake provider: {} scripted response(s) never requested",
+            st.script.len()
+        );
+    }
+}
+
+impl ReceivedRequest {
+    fn last_user_text_or_path(&self) -> String {
+        self.last_user_text().unwrap_or_else(|| self.path.clone())
+    }
+}
+
+fn model_matches(filter: &str, model: Option<&str>) -> bool {
+    let Some(model) = model else {
+        return false;
+    };
+    filter == model || filter.rsplit_once('/').is_some_and(|(_, m)| m == model)
+}
+
+#[async_trait::async_trait]
+impl Responder for FakeProvider {
+    async fn respond(&self, request: reqwest::Request) -> Result<reqwest::Response> {
+        let (received, response) = {
+            let mut st = self.lock();
+            let received = ReceivedRequest::from_request(st.requests.len() + 1, &request);
+            st.requests.push(received.clone());
+            let pos = st.script.iter().position(|s| match &s.model {
+                Some(m) => model_matches(m, received.model.as_deref()),
+                None => true,
+            });
+            let response = match pos {
+                Some(i) => st.script.remove(i).map(|s| s.response),
+                None => st.default.clone(),
+            };
+            (received, response)
+        };
+        let response = response.ok_or_else(|| {
+            Error::validation_with_context(
+                format!(
+                    "fake provider has no scripted response for request #{} ({} {}, model {})",
+                    received.sequence,
+                    received.method,
+                    received.path,
+                    received.model.as_deref().unwrap_or("?")
+                ),
+                ErrorContext::new()
+                    .with_source("fake_provider")
+                    .with_hint("Queue one with FakeProvider::push or set_default"),
+            )
+        })?;
+        if !response.latency.is_zero() {
+            tokio::time::sleep(response.latency).await;
+        }
+        response.into_http(&received)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_manifest_parses_with_retry_policy() {
+        let fake = FakeProvider::new().with_max_retries(0);
+        assert_eq!(fake.manifest().id, FAKE_PROVIDER_ID);
+        assert_eq!(
+            fake.manifest().retry_policy.as_ref().unwrap().max_retries,
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn fold_events_assembles_tool_calls_and_finish_reason() {
+        let (resp, finish) = fold_events(vec![
+            StreamingEvent::PartialContentDelta {
+                content: "a".into(),
+                sequence_id: None,
+            },
+            StreamingEvent::ToolCallStarted {
+                tool_call_id: "c1".into(),
+                tool_name: "lookup".into(),
+                index: None,
+            },
+            StreamingEvent::PartialToolCall {
+                tool_call_id: "c1".into(),
+                arguments: "{\"q\":1}".into(),
+                index: None,
+                is_complete: None,
+            },
+        ]);
+        assert_eq!(resp.content, "a");
+        assert_eq!(resp.tool_calls[0].arguments, json!({"q": 1}));
+        assert_eq!(finish, "tool_calls");
+    }
+
+    #[test]
+    fn model_filter_accepts_bare_and_prefixed_names() {
+        assert!(model_matches("fake/m1", Some("m1")));
+        assert!(model_matches("m1", Some("m1")));
+        assert!(!model_matches("fake/m1", Some("m2")));
+        assert!(!model_matches("m1", None));
+    }
+}
@@ -0,0 +1,41 @@
+//! 测试支持模块：可脚本化的进程内假供应商，用于下游单元测试。
+//!
+//! # Test Support
+//!
+//! In-process doubles for code that depends on [`AiClient`](crate::AiClient),
+//! without mock HTTP servers or provider JSON fixtures.
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`FakeProvider`] | OpenAI-shaped manifest + in-process [`Responder`](crate::transport::Responder) |
+//! | [`FakeResponse`] | Scripted reply: text, tool calls, event sequence, error, raw body |
+//! | [`ReceivedRequest`] | What the fake saw, for assertions |
+//!
+//! Scripted replies are rendered to provider wire format (JSON, or SSE when the
+//! request streams), so they go through the real `Pipeline`, retry / fallback
+//! policy and `CallStats` accounting.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ai_lib_core::error_code::StandardErrorCode;
+//! use ai_lib_core::testing::{FakeProvider, FakeResponse};
+//! use ai_lib_core::Message;
+//!
+//! # async fn demo() -> ai_lib_core::Result<()> {
+//! let fake = FakeProvider::new();
+//! fake.push(FakeResponse::error(StandardErrorCode::Overloaded, "busy"));
+//! fake.push(FakeResponse::text("Hello!"));
+//!
+//! let client = fake.client("fake/model").await?;
+//! let resp = client.chat().messages(vec![Message::user("hi")]).execute().await?;
+//! assert_eq!(resp.content, "Hello!");
+//!
+//! fake.assert_request_count(2); // one retry
+//! assert_eq!(fake.last_request().unwrap().last_user_text().as_deref(), Some("hi"));
+//! # Ok(()) }
+//! ```
+
+mod fake;
+
+pub use fake::{FakeProvider, FakeResponse, ReceivedRequest, FAKE_PROVIDER_ID};
@@ -0,0 +1,922 @@
+//! Record / replay ("cassette") mode for [`HttpTransport`](super::HttpTransport).
+//!
+//! 中文：录制 / 回放传输。录制模式下捕获真实的请求 / 响应（含流式分块时序）并写入
+//! cassette 文件（凭证已脱敏）；回放模式下离线提供录制的响应，未匹配的请求直接报错。
+//!
+//! ```no_run
+//! # async fn demo() -> ai_lib_core::Result<()> {
+//! use ai_lib_core::transport::cassette::{Cassette, ReplayTiming};
+//! use ai_lib_core::AiClientBuilder;
+//! use std::sync::Arc;
+//!
+//! // Record once against the real provider...
+//! let cassette = Arc::new(Cassette::record("tests/cassettes/chat.json"));
+//! // ...then replay offline in CI.
+//! let cassette = Arc::new(
+//!     Cassette::replay("tests/cassettes/chat.json")?.with_replay_timing(ReplayTiming::Instant),
+//! );
+//! let client = AiClientBuilder::new()
+//!     .cassette(cassette)
+//!     .build("openai/gpt-4o")
+//!     .await?;
+//! # Ok(()) }
+//! ```
+//!
+//! The same switch is available without code changes through `AI_LIB_CASSETTE=<path>`
+//! and `AI_LIB_CASSETTE_MODE=record|replay` (default `replay`).
+//!
+//! Recording keeps the chunk boundaries and inter-chunk delays of streamed bodies.
+//! Before anything is written, the transport credential, sensitive headers
+//! (`authorization`, `x-api-key`, …), credential query parameters and any
+//! configured patterns are replaced with `<REDACTED>`. Replay matches on method,
+//! path and normalized JSON body by default (see [`RequestMatcher`]); each recorded
+//! interaction is served once unless [`Cassette::with_allow_repeats`] is set.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use base64::Engine as _;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Error, ErrorContext, Result};
+
+/// Placeholder written in place of scrubbed values.
+pub const REDACTED: &str = "<REDACTED>";
+
+const CASSETTE_FORMAT_VERSION: u32 = 1;
+
+const DEFAULT_SCRUB_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "api-key",
+    "x-goog-api-key",
+    "cookie",
+    "set-cookie",
+];
+
+const DEFAULT_SCRUB_QUERY: &[&str] = &["key", "api_key", "apikey", "access_token", "token"];
+
+/// Well-known API key shapes (OpenAI / Anthropic `sk-…`, Google `AIza…`).
+const DEFAULT_SCRUB_PATTERNS: &[&str] = &[
+    r"sk-(?:ant-)?[A-Za-z0-9_\-]{20,}",
+    r"AIza[0-9A-Za-z_\-]{35}",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Forward requests to the network and capture every exchange.
+    Record,
+    /// Serve recorded exchanges; never touch the network.
+    Replay,
+}
+
+/// How recorded latency and chunk delays are reproduced on replay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayTiming {
+    /// No delays (default).
+    Instant,
+    /// Original latency and inter-chunk delays.
+    Recorded,
+    /// Recorded delays multiplied by the factor (e.g. `0.1` for 10x faster).
+    Scaled(f64),
+}
+
+impl ReplayTiming {
+    fn apply(self, ms: u64) -> Duration {
+        match self {
+            ReplayTiming::Instant => Duration::ZERO,
+            ReplayTiming::Recorded => Duration::from_millis(ms),
+            ReplayTiming::Scaled(f) => Duration::from_secs_f64(ms as f64 / 1000.0 * f.max(0.0)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyMatch {
+    /// Bodies are not compared.
+    Ignore,
+    /// Byte-for-byte (after scrubbing).
+    Exact,
+    /// JSON bodies compared structurally (key order and whitespace ignored),
+    /// minus [`RequestMatcher::ignore_body_field`] entries; non-JSON bodies compared trimmed.
+    Normalized,
+}
+
+/// Which parts of a request must agree with a recorded one.
+#[derive(Debug, Clone)]
+pub struct RequestMatcher {
+    pub method: bool,
+    pub path: bool,
+    pub query: bool,
+    pub body: BodyMatch,
+    /// Top-level keys or JSON pointers (`/metadata/request_id`) dropped before comparing.
+    pub ignore_body_fields: Vec<String>,
+}
+
+impl Default for RequestMatcher {
+    fn default() -> Self {
+        Self {
+            method: true,
+            path: true,
+            query: false,
+            body: BodyMatch::Normalized,
+            ignore_body_fields: Vec::new(),
+        }
+    }
+}
+
+impl RequestMatcher {
+    pub fn with_query(mut self, enable: bool) -> Self {
+        self.query = enable;
+        self
+    }
+
+    pub fn with_body(mut self, body: BodyMatch) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn ignore_body_field(mut self, field: impl Into<String>) -> Self {
+        self.ignore_body_fields.push(field.into());
+        self
+    }
+
+    fn matches(&self, recorded: &RecordedRequest, actual: &RecordedRequest) -> bool {
+        if self.method && !recorded.method.eq_ignore_ascii_case(&actual.method) {
+            return false;
+        }
+        if self.path && recorded.path != actual.path {
+            return false;
+        }
+        if self.query && recorded.query != actual.query {
+            return false;
+        }
+        match self.body {
+            BodyMatch::Ignore => true,
+            BodyMatch::Exact => recorded.body == actual.body,
+            BodyMatch::Normalized => {
+                let norm = |b: &Option<String>| b.as_deref().map(|s| self.normalize(s));
+                norm(&recorded.body) == norm(&actual.body)
+            }
+        }
+    }
+
+    fn normalize(&self, body: &str) -> Value {
+        let Ok(mut v) = serde_json::from_str::<Value>(body) else {
+            return Value::String(body.trim().to_string());
+        };
+        for field in &self.ignore_body_fields {
+            if let Some(ptr) = field.strip_prefix('/') {
+                let (parent, key) = match ptr.rsplit_once('/') {
+                    Some((p, k)) => (format!("/{p}"), k),
+                    None => (String::new(), ptr),
+                };
+                if let Some(Value::Object(obj)) = v.pointer_mut(&parent) {
+                    obj.remove(key);
+                }
+            } else if let Value::Object(obj) = &mut v {
+                obj.remove(field);
+            }
+        }
+        v
+    }
+}
+
+/// One captured request/response exchange.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// UTF-8 body; `None` for empty or streamed (multipart) bodies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+impl RecordedRequest {
+    fn describe(&self) -> String {
+        match &self.query {
+            Some(q) => format!("{} {}?{}", self.method, self.path, q),
+            None => format!("{} {}", self.method, self.path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Time from sending the request to receiving response headers.
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub chunks: Vec<RecordedChunk>,
+    /// The body stream failed or was dropped before completion while recording.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// A body chunk as received, with the delay since the previous chunk (or headers).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedChunk {
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Set instead of `text` when the chunk is not valid UTF-8 on its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+}
+
+impl RecordedChunk {
+    fn new(delay_ms: u64, data: &[u8]) -> Self {
+        match std::str::from_utf8(data) {
+            Ok(s) => Self {
+                delay_ms,
+                text: Some(s.to_string()),
+                base64: None,
+            },
+            Err(_) => Self {
+                delay_ms,
+                text: None,
+                base64: Some(base64::engine::general_purpose::STANDARD.encode(data)),
+            },
+        }
+    }
+
+    fn bytes(&self) -> Bytes {
+        if let Some(t) = &self.text {
+            return Bytes::from(t.clone());
+        }
+        self.base64
+            .as_deref()
+            .and_then(|b| base64::engine::general_purpose::STANDARD.decode(b).ok())
+            .map(Bytes::from)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CassetteFile {
+    version: u32,
+    #[serde(default)]
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone)]
+struct Scrubber {
+    headers: Vec<String>,
+    query_params: Vec<String>,
+    patterns: Vec<Regex>,
+    secrets: Vec<String>,
+}
+
+impl Default for Scrubber {
+    fn default() -> Self {
+        Self {
+            headers: DEFAULT_SCRUB_HEADERS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            query_params: DEFAULT_SCRUB_QUERY.iter().map(|s| s.to_string()).collect(),
+            patterns: DEFAULT_SCRUB_PATTERNS
+                .iter()
+                .filter_map(|p| Regex::new(p).ok())
+                .collect(),
+            secrets: Vec::new(),
+        }
+    }
+}
+
+impl Scrubber {
+    fn text(&self, s: &str, extra_secret: Option<&str>) -> String {
+        let mut out = s.to_string();
+        for secret in self.secrets.iter().map(String::as_str).chain(extra_secret) {
+            if !secret.is_empty() {
+                out = out.replace(secret, REDACTED);
+            }
+        }
+        for re in &self.patterns {
+            if re.is_match(&out) {
+                out = re.replace_all(&out, REDACTED).into_owned();
+            }
+        }
+        out
+    }
+
+    fn headers(
+        &self,
+        headers: &reqwest::header::HeaderMap,
+        secret: Option<&str>,
+    ) -> BTreeMap<String, String> {
+        let mut out: BTreeMap<String, String> = BTreeMap::new();
+        for (name, value) in headers {
+            let name = name.as_str().to_ascii_lowercase();
+            let value = if self.headers.contains(&name) {
+                REDACTED.to_string()
+            } else {
+                self.text(&String::from_utf8_lossy(value.as_bytes()), secret)
+            };
+            out.entry(name)
+                .and_modify(|v| {
+                    v.push_str(", ");
+                    v.push_str(&value);
+                })
+                .or_insert(value);
+        }
+        out
+    }
+
+    fn query(&self, url: &reqwest::Url, secret: Option<&str>) -> Option<String> {
+        url.query()?;
+        let pairs: Vec<String> = url
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if self.query_params.iter().any(|p| p.eq_ignore_ascii_case(&k)) {
+                    REDACTED.to_string()
+                } else {
+                    self.text(&v, secret)
+                };
+                format!("{k}={v}")
+            })
+            .collect();
+        Some(pairs.join("&"))
+    }
+
+    fn request(&self, req: &reqwest::Request, secret: Option<&str>) -> RecordedRequest {
+        let body = req
+            .body()
+            .and_then(|b| b.as_bytes())
+            .filter(|b| !b.is_empty())
+            .map(|b| self.text(&String::from_utf8_lossy(b), secret));
+        RecordedRequest {
+            method: req.method().as_str().to_string(),
+            path: req.url().path().to_string(),
+            query: self.query(req.url(), secret),
+            headers: self.headers(req.headers(), secret),
+            body,
+        }
+    }
+
+    fn chunks(&self, chunks: &mut [RecordedChunk], secret: Option<&str>) {
+        for c in chunks {
+            if let Some(t) = &c.text {
+                c.text = Some(self.text(t, secret));
+            }
+        }
+    }
+}
+
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    played: Vec<bool>,
+}
+
+/// Recorded HTTP exchanges backed by a JSON file. Share one `Arc<Cassette>` between
+/// transports (the builder does this for fallback clients).
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    matcher: RequestMatcher,
+    timing: ReplayTiming,
+    allow_repeats: bool,
+    scrubber: Scrubber,
+    state: Mutex<CassetteState>,
+}
+
+impl std::fmt::Debug for Cassette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cassette")
+            .field("path", &self.path)
+            .field("mode", &self.mode)
+            .field("interactions", &self.len())
+            .finish()
+    }
+}
+
+fn cassette_error(msg: impl Into<String>, path: &Path) -> Error {
+    Error::configuration_with_context(
+        msg,
+        ErrorContext::new()
+            .with_source("cassette")
+            .with_field_path(path.display().to_string()),
+    )
+}
+
+impl Cassette {
+    fn with_interactions(
+        path: PathBuf,
+        mode: CassetteMode,
+        interactions: Vec<Interaction>,
+    ) -> Self {
+        let played = vec![false; interactions.len()];
+        Self {
+            path,
+            mode,
+            matcher: RequestMatcher::default(),
+            timing: ReplayTiming::Instant,
+            allow_repeats: false,
+            scrubber: Scrubber::default(),
+            state: Mutex::new(CassetteState {
+                interactions,
+                played,
+            }),
+        }
+    }
+
+    /// Start a new recording; the file is (re)written after every completed exchange.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self::with_interactions(path.into(), CassetteMode::Record, Vec::new())
+    }
+
+    /// Load a cassette for offline replay. Fails if the file is missing or malformed.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let raw = std::fs::read_to_string(&path).map_err(|e| {
+            Error::configuration_with_context(
+                format!("cannot read cassette: {e}"),
+                ErrorContext::new()
+                    .with_source("cassette")
+                    .with_field_path(path.display().to_string())
+                    .with_hint("Record it first with AI_LIB_CASSETTE_MODE=record"),
+            )
+        })?;
+        let file: CassetteFile = serde_json::from_str(&raw)
+            .map_err(|e| cassette_error(format!("invalid cassette: {e}"), &path))?;
+        if file.version > CASSETTE_FORMAT_VERSION {
+            return Err(cassette_error(
+                format!(
+                    "cassette format version {} is newer than supported ({})",
+                    file.version, CASSETTE_FORMAT_VERSION
+                ),
+                &path,
+            ));
+        }
+        Ok(Self::with_interactions(
+            path,
+            CassetteMode::Replay,
+            file.interactions,
+        ))
+    }
+
+    pub fn open(path: impl Into<PathBuf>, mode: CassetteMode) -> Result<Self> {
+        match mode {
+            CassetteMode::Record => Ok(Self::record(path)),
+            CassetteMode::Replay => Self::replay(path),
+        }
+    }
+
+    /// Cassette configured by `AI_LIB_CASSETTE` / `AI_LIB_CASSETTE_MODE`, if any.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(path) = std::env::var("AI_LIB_CASSETTE")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+        let mode = match std::env::var("AI_LIB_CASSETTE_MODE")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "replay" => CassetteMode::Replay,
+            "record" => CassetteMode::Record,
+            other => {
+                return Err(Error::configuration_with_context(
+                    format!("unknown AI_LIB_CASSETTE_MODE '{other}'"),
+                    ErrorContext::new()
+                        .with_source("cassette")
+                        .with_hint("Use 'record' or 'replay'"),
+                ))
+            }
+        };
+        Self::open(path, mode).map(Some)
+    }
+
+    pub fn with_matcher(mut self, matcher: RequestMatcher) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
+    pub fn with_replay_timing(mut self, timing: ReplayTiming) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Serve the last matching interaction again once all matches were played.
+    pub fn with_allow_repeats(mut self, allow: bool) -> Self {
+        self.allow_repeats = allow;
+        self
+    }
+
+    /// Redact this header (case-insensitive) in addition to the defaults.
+    pub fn with_scrub_header(mut self, name: impl Into<String>) -> Self {
+        self.scrubber.headers.push(name.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Redact this query parameter in addition to the defaults.
+    pub fn with_scrub_query_param(mut self, name: impl Into<String>) -> Self {
+        self.scrubber.query_params.push(name.into());
+        self
+    }
+
+    /// Redact every match of `pattern` in URLs, headers and bodies.
+    pub fn with_scrub_pattern(mut self, pattern: &str) -> Result<Self> {
+        let re = Regex::new(pattern)
+            .map_err(|e| cassette_error(format!("invalid scrub pattern: {e}"), &self.path))?;
+        self.scrubber.patterns.push(re);
+        Ok(self)
+    }
+
+    /// Redact this literal value (the transport credential is always redacted).
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.scrubber.secrets.push(secret.into());
+        self
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().interactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.lock().interactions.clone()
+    }
+
+    /// Recorded requests that were never served during replay.
+    pub fn unplayed(&self) -> Vec<String> {
+        let st = self.lock();
+        st.interactions
+            .iter()
+            .zip(&st.played)
+            .filter(|(_, played)| !**played)
+            .map(|(i, _)| i.request.describe())
+            .collect()
+    }
+
+    /// Write the cassette file (done automatically while recording).
+    pub fn save(&self) -> Result<()> {
+        let file = CassetteFile {
+            version: CASSETTE_FORMAT_VERSION,
+            interactions: self.interactions(),
+        };
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&file)?)?;
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CassetteState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, mut interaction: Interaction, secret: Option<&str>) {
+        self.scrubber
+            .chunks(&mut interaction.response.chunks, secret);
+        {
+            let mut st = self.lock();
+            st.interactions.push(interaction);
+            st.played.push(true);
+        }
+        if let Err(e) = self.save() {
+            tracing::warn!(path = %self.path.display(), error = %e, "failed to write cassette");
+        }
+    }
+
+    fn find(&self, actual: &RecordedRequest) -> Result<Interaction> {
+        let mut st = self.lock();
+        let candidates: Vec<usize> = (0..st.interactions.len())
+            .filter(|&i| self.matcher.matches(&st.interactions[i].request, actual))
+            .collect();
+        let pick = candidates
+            .iter()
+            .copied()
+            .find(|&i| !st.played[i])
+            .or_else(|| candidates.last().copied().filter(|_| self.allow_repeats));
+        if let Some(i) = pick {
+            st.played[i] = true;
+            return Ok(st.interactions[i].clone());
+        }
+        let reason = if candidates.is_empty() {
+            let same_route: Vec<String> = st
+                .interactions
+                .iter()
+                .filter(|i| i.request.path == actual.path)
+                .take(3)
+                .map(|i| i.request.body.clone().unwrap_or_default())
+                .collect();
+            if same_route.is_empty() {
+                "no recorded interaction for this method/path".to_string()
+            } else {
+                format!(
+                    "recorded bodies for this path differ: {}",
+                    same_route.join(" | ")
+                )
+            }
+        } else {
+            format!(
+                "all {} matching interactions were already played",
+                candidates.len()
+            )
+        };
+        Err(Error::validation_with_context(
+            format!("cassette has no match for {}", actual.describe()),
+            ErrorContext::new()
+                .with_source("cassette")
+                .with_field_path(self.path.display().to_string())
+                .with_details(reason)
+                .with_hint("Re-record the cassette (AI_LIB_CASSETTE_MODE=record) or relax the RequestMatcher"),
+        ))
+    }
+
+    /// Execute `req` according to the cassette mode. The outer error is a cassette
+    /// failure (never retried across routes); the inner one is a network error.
+    pub(crate) async fn send(
+        self: &Arc<Self>,
+        req: reqwest::RequestBuilder,
+        secret: Option<&str>,
+    ) -> Result<reqwest::Result<reqwest::Response>> {
+        let (client, request) = req.build_split();
+        let request = match request {
+            Ok(r) => r,
+            Err(e) => return Ok(Err(e)),
+        };
+        let recorded = self.scrubber.request(&request, secret);
+        match self.mode {
+            CassetteMode::Replay => self.replay_response(&recorded).await.map(Ok),
+            CassetteMode::Record => {
+                let started = Instant::now();
+                let resp = match client.execute(request).await {
+                    Ok(r) => r,
+                    Err(e) => return Ok(Err(e)),
+                };
+                Ok(Ok(self.record_response(recorded, resp, started, secret)))
+            }
+        }
+    }
+
+    async fn replay_response(&self, actual: &RecordedRequest) -> Result<reqwest::Response> {
+        let interaction = self.find(actual)?;
+        let resp = interaction.response;
+        let latency = self.timing.apply(resp.latency_ms);
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        let mut builder = http::Response::builder().status(resp.status);
+        for (k, v) in &resp.headers {
+            // The body is re-framed from chunks; stale framing headers would confuse readers.
+            if k == "content-length" || k == "transfer-encoding" || k == "content-encoding" {
+                continue;
+            }
+            builder = builder.header(k.as_str(), v.as_str());
+        }
+        let timing = self.timing;
+        let chunks: Vec<(Duration, Bytes)> = resp
+            .chunks
+            .iter()
+            .map(|c| (timing.apply(c.delay_ms), c.bytes()))
+            .collect();
+        let body = futures::stream::iter(chunks).then(|(delay, data)| async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            Ok::<Bytes, std::io::Error>(data)
+        });
+        let body = reqwest::Body::wrap_stream(SyncStream::new(body.boxed()));
+        builder
+            .body(body)
+            .map(reqwest::Response::from)
+            .map_err(|e| cassette_error(format!("invalid recorded response: {e}"), &self.path))
+    }
+
+    fn record_response(
+        self: &Arc<Self>,
+        request: RecordedRequest,
+        resp: reqwest::Response,
+        started: Instant,
+        secret: Option<&str>,
+    ) -> reqwest::Response {
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let version = resp.version();
+        let recorder = Recorder {
+            cassette: Arc::clone(self),
+            secret: secret.map(str::to_string),
+            last: Instant::now(),
+            interaction: Some(Interaction {
+                request,
+                response: RecordedResponse {
+                    status: status.as_u16(),
+                    headers: self.scrubber.headers(&headers, secret),
+                    latency_ms: started.elapsed().as_millis() as u64,
+                    chunks: Vec::new(),
+                    truncated: false,
+                },
+            }),
+        };
+        let body = RecordingStream {
+            inner: resp.bytes_stream().boxed(),
+            recorder,
+        };
+        let mut builder = http::Response::builder().status(status).version(version);
+        if let Some(h) = builder.headers_mut() {
+            *h = headers;
+        }
+        builder
+            .body(reqwest::Body::wrap_stream(SyncStream::new(body)))
+            .map(reqwest::Response::from)
+            .expect("status and headers come from a valid response")
+    }
+}
+
+/// Accumulates one exchange while the caller consumes the body; written on drop,
+/// so partially read or failed streams are kept (flagged `truncated`).
+struct Recorder {
+    cassette: Arc<Cassette>,
+    secret: Option<String>,
+    last: Instant,
+    interaction: Option<Interaction>,
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Some(i) = self.interaction.take() {
+            self.cassette.push(i, self.secret.as_deref());
+        }
+    }
+}
+
+struct RecordingStream {
+    inner: futures::stream::BoxStream<'static, reqwest::Result<Bytes>>,
+    recorder: Recorder,
+}
+
+impl Stream for RecordingStream {
+    type Item = reqwest::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let polled = this.inner.poll_next_unpin(cx);
+        let rec = &mut this.recorder;
+        if let (Poll::Ready(item), Some(i)) = (&polled, rec.interaction.as_mut()) {
+            match item {
+                Some(Ok(data)) => {
+                    let delay = rec.last.elapsed().as_millis() as u64;
+                    rec.last = Instant::now();
+                    i.response.chunks.push(RecordedChunk::new(delay, data));
+                }
+                Some(Err(_)) => i.response.truncated = true,
+                None => {}
+            }
+        }
+        if matches!(polled, Poll::Ready(None) | Poll::Ready(Some(Err(_)))) {
+            // Finished: persist now rather than whenever the response is dropped.
+            if let Some(i) = rec.interaction.take() {
+                rec.cassette.push(i, rec.secret.as_deref());
+            }
+        }
+        polled
+    }
+}
+
+impl Drop for RecordingStream {
+    fn drop(&mut self) {
+        if let Some(i) = self.recorder.interaction.as_mut() {
+            // Dropped mid-body: the caller stopped reading.
+            i.response.truncated = true;
+        }
+    }
+}
+
+/// `reqwest::Body::wrap_stream` needs `Sync`; a `Mutex` provides it for any `Send`
+/// stream without ever contending (it is only reached through `&mut`).
+pub(crate) struct SyncStream<S>(Mutex<S>);
+
+impl<S> SyncStream<S> {
+    pub(crate) fn new(s: S) -> Self {
+        Self(Mutex::new(s))
+    }
+}
+
+impl<S: Stream + Unpin> Stream for SyncStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .0
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(method: &str, path: &str, body: Option<&str>) -> RecordedRequest {
+        RecordedRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: None,
+            headers: BTreeMap::new(),
+            body: body.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn normalized_body_ignores_order_whitespace_and_ignored_fields() {
+        let m = RequestMatcher::default().ignore_body_field("/metadata/request_id");
+        let a = req(
+            "POST",
+            "/v1/chat",
+            Some(r#"{"model":"m","metadata":{"request_id":"1","k":2}}"#),
+        );
+        let b = req(
+            "post",
+            "/v1/chat",
+            Some("{ \"metadata\": {\"k\": 2, \"request_id\": \"2\"}, \"model\": \"m\" }"),
+        );
+        assert!(m.matches(&a, &b));
+        assert!(!m.clone().with_body(BodyMatch::Exact).matches(&a, &b));
+        assert!(!m.matches(&a, &req("POST", "/v1/other", a.body.as_deref())));
+    }
+
+    #[test]
+    fn scrubber_redacts_secret_headers_query_and_patterns() {
+        let s = Scrubber::default();
+        let request = reqwest::Client::new()
+            .post("https://api.example.com/v1/chat?key=abc&alt=sse")
+            .header("authorization", "Bearer live-secret")
+            .header("x-trace", "live-secret-trace")
+            .body(r#"{"note":"sk-ant-REDACTED"}"#)
+            .build()
+            .unwrap();
+        let r = s.request(&request, Some("live-secret"));
+        assert_eq!(r.headers["authorization"], REDACTED);
+        assert_eq!(r.headers["x-trace"], format!("{REDACTED}-trace"));
+        assert_eq!(r.query.as_deref(), Some("key=<REDACTED>&alt=sse"));
+        assert_eq!(r.body.as_deref(), Some(r#"{"note":"<REDACTED>"}"#));
+    }
+
+    #[test]
+    fn replay_serves_each_interaction_once_unless_repeats_allowed() {
+        let interaction = Interaction {
+            request: req("GET", "/v1/models", None),
+            response: RecordedResponse {
+                status: 200,
+                headers: BTreeMap::new(),
+                latency_ms: 0,
+                chunks: vec![RecordedChunk::new(0, b"{}")],
+                truncated: false,
+            },
+        };
+        let c = Cassette::with_interactions(
+            PathBuf::from("unused.json"),
+            CassetteMode::Replay,
+            vec![interaction],
+        );
+        let actual = req("GET", "/v1/models", None);
+        assert!(c.find(&actual).is_ok());
+        let err = c.find(&actual).unwrap_err().to_string();
+        assert!(err.contains("already played"), "{err}");
+        assert!(c.unplayed().is_empty());
+
+        let c = c.with_allow_repeats(true);
+        assert!(c.find(&actual).is_ok());
+        assert!(c.find(&req("GET", "/v1/other", None)).is_err());
+    }
+}
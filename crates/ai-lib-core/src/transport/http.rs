@@ -43,6 +43,16 @@ fn auth_header_value(prefix: &str, secret: &str) -> String {
     }
 }
 
+/// In-process stand-in for the network (cassettes aside), e.g. `testing::FakeProvider`.
+///
+/// Receives the fully built request (auth applied) and returns the response that
+/// the transport would otherwise have read from the wire; an `Err` is surfaced as
+/// is and does not trigger route failover.
+#[async_trait::async_trait]
+pub trait Responder: Send + Sync {
+    async fn respond(&self, request: reqwest::Request) -> Result<reqwest::Response>;
+}
+
 pub struct HttpTransport {
     routes: Vec<TransportRoute>,
     preferred_route: AtomicUsize,
@@ -50,6 +60,8 @@ pub struct HttpTransport {
     model: String,
     credential: crate::credentials::ResolvedCredential,
     auth: Option<crate::protocol::AuthConfig>,
+    cassette: Option<std::sync::Arc<super::cassette::Cassette>>,
+    responder: Option<std::sync::Arc<dyn Responder>>,
 }
 
 impl HttpTransport {
@@ -129,9 +141,51 @@ impl HttpTransport {
             model: model.to_string(),
             credential,
             auth,
+            cassette: None,
+            responder: None,
         })
     }
 
+    /// Route every request through a record/replay [`Cassette`](super::cassette::Cassette).
+    pub fn with_cassette(mut self, cassette: std::sync::Arc<super::cassette::Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    pub fn cassette(&self) -> Option<std::sync::Arc<super::cassette::Cassette>> {
+        self.cassette.clone()
+    }
+
+    /// Serve every request from `responder` instead of the network (takes
+    /// precedence over a cassette).
+    pub fn with_responder(mut self, responder: std::sync::Arc<dyn Responder>) -> Self {
+        self.responder = Some(responder);
+        self
+    }
+
+    pub fn responder(&self) -> Option<std::sync::Arc<dyn Responder>> {
+        self.responder.clone()
+    }
+
+    /// `req.send()`, or the responder / cassette when one is attached. The outer
+    /// error comes from those hooks and must not fall through to another route.
+    async fn send(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Result<reqwest::Response>> {
+        if let Some(responder) = &self.responder {
+            let request = match req.build() {
+                Ok(r) => r,
+                Err(e) => return Ok(Err(e)),
+            };
+            return responder.respond(request).await.map(Ok);
+        }
+        match &self.cassette {
+            Some(cassette) => cassette.send(req, self.credential.secret()).await,
+            None => Ok(req.send().await),
+        }
+    }
+
     /// Explicit ai-lib proxy override routes for failover (see `build_routes`).
     ///
     /// Standard `HTTP_PROXY` / `HTTPS_PROXY` / `NO_PROXY` env vars are handled by
@@ -295,7 +349,7 @@ impl HttpTransport {
                 req = req.header("x-ai-protocol-request-id", id);
             }
 
-            match req.send().await {
+            match self.send(req).await? {
                 Ok(resp) => {
                     if self.routes.len() > 1
                         && Self::should_try_alternate_route(resp.status().as_u16())
@@ -328,6 +382,54 @@ impl HttpTransport {
         }))
     }
 
+    /// Send a request whose body is not plain JSON (multipart uploads, raw downloads, ...).
+    ///
+    /// `build` receives a request builder that already targets `path` on the current
+    /// route; it is called once per route attempt, so it must be able to rebuild the
+    /// body. Auth and route failover follow the same rules as the JSON helpers.
+    pub async fn send_with<F>(
+        &self,
+        method: &str,
+        path: &str,
+        build: F,
+    ) -> Result<reqwest::Response>
+    where
+        F: Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+    {
+        let interpolated_path = path.replace("{model}", &self.model);
+        let url = format!("{}{}", self.base_url, interpolated_path);
+        let mut last_err = None;
+        for idx in self.preferred_route_indices() {
+            let route = &self.routes[idx];
+            let req = match method.to_uppercase().as_str() {
+                "POST" => route.client.post(&url),
+                "PUT" => route.client.put(&url),
+                "DELETE" => route.client.delete(&url),
+                _ => route.client.get(&url),
+            };
+
+            match self.send(build(self.apply_auth(req))).await? {
+                Ok(resp) => {
+                    if self.routes.len() > 1
+                        && Self::should_try_alternate_route(resp.status().as_u16())
+                    {
+                        continue;
+                    }
+                    self.preferred_route.store(idx, Ordering::Relaxed);
+                    return Ok(resp);
+                }
+                Err(e) => last_err = Some(e),
+            }
+        }
+
+        Err(crate::Error::Transport(match last_err {
+            Some(e) => crate::transport::TransportError::Http(e),
+            None => crate::transport::TransportError::Other(
+                "all HTTP routes exhausted with retryable status codes".to_string(),
+            ),
+        }))
+    }
+
     pub async fn execute_stream<'a>(
         &'a self,
         method: &str,
@@ -378,7 +480,7 @@ impl HttpTransport {
                 request = request.query(params);
             }
 
-            match request.send().await {
+            match self.send(request).await? {
                 Ok(response) => {
                     if self.routes.len() > 1
                         && Self::should_try_alternate_route(response.status().as_u16())
@@ -444,6 +546,8 @@ mod tests {
             model: "model".to_string(),
             credential,
             auth,
+            cassette: None,
+            responder: None,
         }
     }
 
@@ -15,7 +15,9 @@
 //! for their own HTTP clients; LLM traffic via `HttpTransport` follows the rules above
 //! unless the host sets matching process env vars before initializing `AiClient`.
 
+pub mod cassette;
 pub mod http;
 pub mod middleware;
 
-pub use http::{HttpTransport, TransportError};
+pub use cassette::{BodyMatch, Cassette, CassetteMode, ReplayTiming, RequestMatcher};
+pub use http::{HttpTransport, Responder, TransportError};
@@ -1,9 +1,12 @@
 //! TTS (Text-to-Speech) client.
 //! TTS（文本转语音）客户端。
 
+use super::long_form::{concat_audio, split_for_synthesis};
+use super::stream::AudioStream;
 use super::types;
 use super::types::{AudioOutput, TtsOptions};
 use crate::{Error, ErrorContext, Result};
+use futures::{StreamExt, TryStreamExt};
 use std::str::FromStr;
 
 /// Client for text-to-speech synthesis.
@@ -13,6 +16,8 @@ pub struct TtsClient {
     base_url: String,
     endpoint_path: String,
     api_key: String,
+    max_input_chars: usize,
+    max_concurrency: usize,
 }
 
 impl TtsClient {
@@ -21,6 +26,65 @@ impl TtsClient {
     }
 
     pub async fn synthesize(&self, text: &str, options: &TtsOptions) -> Result<AudioOutput> {
+        let response = self.send(text, options).await?;
+        let bytes = response.bytes().await.map_err(|e| {
+            Error::network_with_context(
+                format!("Failed to read TTS response: {}", e),
+                ErrorContext::new(),
+            )
+        })?;
+        Ok(AudioOutput {
+            data: bytes.to_vec(),
+            format: response_format(options),
+        })
+    }
+
+    /// Synthesize `text`, yielding audio as it arrives instead of after the whole
+    /// response.
+    ///
+    /// Raw chunked responses are passed through; `text/event-stream` responses (set
+    /// `stream_format: Some("sse")` where the provider supports it) are decoded from
+    /// base64 audio deltas.
+    pub async fn synthesize_stream(&self, text: &str, options: &TtsOptions) -> Result<AudioStream> {
+        let response = self.send(text, options).await?;
+        let format = response_format(options);
+        let is_sse = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if is_sse {
+            AudioStream::sse(format, response).await
+        } else {
+            Ok(AudioStream::raw(format, response))
+        }
+    }
+
+    /// Synthesize text longer than the provider's input limit.
+    ///
+    /// The text is split on sentence boundaries into segments of at most
+    /// `max_input_chars`, up to `max_concurrency` segments are synthesized at once,
+    /// and the results are joined with [`concat_audio`] for the requested format.
+    pub async fn synthesize_long(&self, text: &str, options: &TtsOptions) -> Result<AudioOutput> {
+        let segments = split_for_synthesis(text, self.max_input_chars);
+        if segments.len() <= 1 {
+            return self.synthesize(text.trim(), options).await;
+        }
+        let outputs: Vec<AudioOutput> = futures::stream::iter(&segments)
+            .map(|segment| self.synthesize(segment, options))
+            .buffered(self.max_concurrency.max(1))
+            .try_collect()
+            .await?;
+        let format = response_format(options);
+        let data: Vec<Vec<u8>> = outputs.into_iter().map(|o| o.data).collect();
+        Ok(AudioOutput {
+            data: concat_audio(format, &data)?,
+            format,
+        })
+    }
+
+    /// POST the speech request and fail on non-success status.
+    async fn send(&self, text: &str, options: &TtsOptions) -> Result<reqwest::Response> {
         let endpoint = format!(
             "{}{}",
             self.base_url.trim_end_matches('/'),
@@ -39,6 +103,9 @@ impl TtsClient {
         if let Some(rf) = &options.response_format {
             body["response_format"] = serde_json::Value::String(rf.clone());
         }
+        if let Some(sf) = &options.stream_format {
+            body["stream_format"] = serde_json::Value::String(sf.clone());
+        }
         let response = self
             .http_client
             .post(&endpoint)
@@ -54,28 +121,14 @@ impl TtsClient {
                 )
             })?;
         let status = response.status();
-        let bytes = response.bytes().await.map_err(|e| {
-            Error::network_with_context(
-                format!("Failed to read TTS response: {}", e),
-                ErrorContext::new(),
-            )
-        })?;
         if !status.is_success() {
-            let body_str = String::from_utf8_lossy(&bytes);
+            let body_str = response.text().await.unwrap_or_default();
             return Err(Error::api_with_context(
                 format!("TTS API error ({}): {}", status, body_str),
                 ErrorContext::new(),
             ));
         }
-        let format = options
-            .response_format
-            .as_deref()
-            .map(|s| types::AudioFormat::from_str(s).unwrap_or(types::AudioFormat::Mp3))
-            .unwrap_or(types::AudioFormat::Mp3);
-        Ok(AudioOutput {
-            data: bytes.to_vec(),
-            format,
-        })
+        Ok(response)
     }
 
     pub fn model(&self) -> &str {
@@ -89,6 +142,8 @@ pub struct TtsClientBuilder {
     base_url: Option<String>,
     endpoint_path: Option<String>,
     timeout_secs: u64,
+    max_input_chars: usize,
+    max_concurrency: usize,
 }
 
 impl TtsClientBuilder {
@@ -99,6 +154,8 @@ impl TtsClientBuilder {
             base_url: None,
             endpoint_path: None,
             timeout_secs: 60,
+            max_input_chars: 4096,
+            max_concurrency: 4,
         }
     }
     pub fn model(mut self, model: impl Into<String>) -> Self {
@@ -117,6 +174,16 @@ impl TtsClientBuilder {
         self.endpoint_path = Some(path.into());
         self
     }
+    /// Provider input limit used by `synthesize_long` to size segments (default 4096).
+    pub fn max_input_chars(mut self, chars: usize) -> Self {
+        self.max_input_chars = chars.max(1);
+        self
+    }
+    /// Segments `synthesize_long` synthesizes at once (default 4).
+    pub fn max_concurrency(mut self, requests: usize) -> Self {
+        self.max_concurrency = requests.max(1);
+        self
+    }
 
     pub async fn build(self) -> Result<TtsClient> {
         let model = self
@@ -147,10 +214,20 @@ impl TtsClientBuilder {
             base_url,
             endpoint_path,
             api_key,
+            max_input_chars: self.max_input_chars,
+            max_concurrency: self.max_concurrency,
         })
     }
 }
 
+fn response_format(options: &TtsOptions) -> types::AudioFormat {
+    options
+        .response_format
+        .as_deref()
+        .map(|s| types::AudioFormat::from_str(s).unwrap_or(types::AudioFormat::Mp3))
+        .unwrap_or(types::AudioFormat::Mp3)
+}
+
 impl Default for TtsClientBuilder {
     fn default() -> Self {
         Self::new()
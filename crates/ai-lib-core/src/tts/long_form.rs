@@ -0,0 +1,413 @@
+//! Long-form synthesis helpers: sentence-aware text splitting and per-format audio
+//! concatenation.
+
+use super::types::AudioFormat;
+use crate::{Error, ErrorContext, Result};
+
+/// Split `text` into segments of at most `max_chars` characters, breaking after
+/// sentence-ending punctuation where possible, then at whitespace, then anywhere.
+pub fn split_for_synthesis(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for sentence in sentences(text) {
+        let len = sentence.chars().count();
+        if current_len > 0 && current_len + 1 + len > max_chars {
+            segments.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if len > max_chars {
+            segments.extend(hard_split(sentence, max_chars));
+            continue;
+        }
+        // CJK full stops already separate sentences; everything else gets a space.
+        if current_len > 0 && !current.ends_with(['。', '！', '？']) {
+            current.push(' ');
+            current_len += 1;
+        }
+        current.push_str(sentence);
+        current_len += len;
+    }
+    if current_len > 0 {
+        segments.push(current);
+    }
+    segments
+}
+
+/// Trimmed sentences of `text`; terminators stay with their sentence.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let end = i + c.len_utf8();
+        let boundary = match c {
+            '。' | '！' | '？' | '\n' => true,
+            '.' | '!' | '?' => chars.peek().map_or(true, |(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if boundary {
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                out.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let tail = text[start..].trim();
+    if !tail.is_empty() {
+        out.push(tail);
+    }
+    out
+}
+
+fn hard_split(sentence: &str, max_chars: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest = sentence;
+    while rest.chars().count() > max_chars {
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map_or(rest.len(), |(i, _)| i);
+        let cut = match rest[..limit].rfind(char::is_whitespace) {
+            Some(space) if space > 0 => space,
+            _ => limit,
+        };
+        pieces.push(rest[..cut].trim_end().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest.to_string());
+    }
+    pieces
+}
+
+/// Join separately synthesized segments into one playable file of `format`.
+///
+/// | Format | Strategy |
+/// |--------|----------|
+/// | WAV | Concatenate `data` payloads under one rewritten RIFF header (formats must match) |
+/// | PCM, AAC (ADTS) | Byte concatenation |
+/// | MP3 | Frame concatenation, dropping ID3v2 tags after the first segment and ID3v1 tags before the last |
+/// | Opus (Ogg) | Chained Ogg streams; clashing serial numbers are rewritten and page CRCs recomputed |
+/// | FLAC | Not supported |
+pub fn concat_audio(format: AudioFormat, segments: &[Vec<u8>]) -> Result<Vec<u8>> {
+    match segments {
+        [] => return Ok(Vec::new()),
+        [only] => return Ok(only.clone()),
+        _ => {}
+    }
+    match format {
+        AudioFormat::Pcm | AudioFormat::Aac => Ok(segments.concat()),
+        AudioFormat::Wav => concat_wav(segments),
+        AudioFormat::Mp3 => Ok(concat_mp3(segments)),
+        AudioFormat::Opus => concat_ogg(segments),
+        AudioFormat::Flac => Err(Error::validation_with_context(
+            "FLAC segments cannot be concatenated",
+            ErrorContext::new()
+                .with_source("tts")
+                .with_hint("request wav, pcm, mp3 or opus for long-form synthesis"),
+        )),
+    }
+}
+
+fn invalid_audio(message: impl Into<String>) -> Error {
+    Error::validation_with_context(message, ErrorContext::new().with_source("tts"))
+}
+
+/// `fmt ` chunk body and `data` payload of a RIFF/WAVE file.
+fn parse_wav(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid_audio("segment is not a RIFF/WAVE file"));
+    }
+    let mut fmt = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes([
+            bytes[pos + 4],
+            bytes[pos + 5],
+            bytes[pos + 6],
+            bytes[pos + 7],
+        ]) as usize;
+        let body_start = pos + 8;
+        // Streamed WAVs often carry a placeholder size; take the rest of the file.
+        let body_end = body_start.saturating_add(size).min(bytes.len());
+        match id {
+            b"fmt " => fmt = Some(&bytes[body_start..body_end]),
+            b"data" => {
+                let fmt = fmt.ok_or_else(|| invalid_audio("WAV data chunk before fmt chunk"))?;
+                return Ok((fmt, &bytes[body_start..body_end]));
+            }
+            _ => {}
+        }
+        pos = body_end + (size & 1);
+    }
+    Err(invalid_audio("WAV segment has no data chunk"))
+}
+
+fn concat_wav(segments: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let (fmt, _) = parse_wav(&segments[0])?;
+    let mut data = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let (segment_fmt, payload) = parse_wav(segment)?;
+        if segment_fmt != fmt {
+            return Err(invalid_audio(format!(
+                "WAV segment {i} has a different sample format from segment 0"
+            )));
+        }
+        data.extend_from_slice(payload);
+    }
+    let fmt_padded = fmt.len() + (fmt.len() & 1);
+    let riff_size = 4 + 8 + fmt_padded + 8 + data.len();
+    let mut out = Vec::with_capacity(8 + riff_size);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(riff_size as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    out.extend_from_slice(fmt);
+    if fmt.len() & 1 == 1 {
+        out.push(0);
+    }
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&data);
+    Ok(out)
+}
+
+fn concat_mp3(segments: &[Vec<u8>]) -> Vec<u8> {
+    let last = segments.len() - 1;
+    let mut out = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let mut frames = segment.as_slice();
+        if i > 0 {
+            frames = &frames[id3v2_len(frames).min(frames.len())..];
+        }
+        if i < last && frames.len() >= 128 && &frames[frames.len() - 128..][..3] == b"TAG" {
+            frames = &frames[..frames.len() - 128];
+        }
+        out.extend_from_slice(frames);
+    }
+    out
+}
+
+/// Length of a leading ID3v2 tag (header, body and optional footer), or 0.
+fn id3v2_len(bytes: &[u8]) -> usize {
+    if bytes.len() < 10 || &bytes[0..3] != b"ID3" {
+        return 0;
+    }
+    let size = bytes[6..10]
+        .iter()
+        .fold(0usize, |acc, b| (acc << 7) | (*b & 0x7f) as usize);
+    let footer = if bytes[5] & 0x10 != 0 { 10 } else { 0 };
+    10 + size + footer
+}
+
+fn concat_ogg(segments: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let mut used = std::collections::HashSet::new();
+    let mut out = Vec::new();
+    for segment in segments {
+        let mut pages = segment.clone();
+        let serials = ogg_serials(&pages)?;
+        for serial in serials {
+            if used.insert(serial) {
+                continue;
+            }
+            let mut fresh = serial;
+            while used.contains(&fresh) {
+                fresh = fresh.wrapping_add(1);
+            }
+            used.insert(fresh);
+            rewrite_ogg_serial(&mut pages, serial, fresh)?;
+        }
+        out.extend_from_slice(&pages);
+    }
+    Ok(out)
+}
+
+/// Byte ranges of the Ogg pages in `bytes`.
+fn ogg_pages(bytes: &[u8]) -> Result<Vec<std::ops::Range<usize>>> {
+    let mut pages = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes.len() < pos + 27 || &bytes[pos..pos + 4] != b"OggS" {
+            return Err(invalid_audio("Opus segment is not a valid Ogg stream"));
+        }
+        let lacing_count = bytes[pos + 26] as usize;
+        let header_len = 27 + lacing_count;
+        if bytes.len() < pos + header_len {
+            return Err(invalid_audio("truncated Ogg page header"));
+        }
+        let body_len: usize = bytes[pos + 27..pos + header_len]
+            .iter()
+            .map(|b| *b as usize)
+            .sum();
+        let end = pos + header_len + body_len;
+        if end > bytes.len() {
+            return Err(invalid_audio("truncated Ogg page"));
+        }
+        pages.push(pos..end);
+        pos = end;
+    }
+    Ok(pages)
+}
+
+fn ogg_serials(bytes: &[u8]) -> Result<Vec<u32>> {
+    let mut serials = Vec::new();
+    for page in ogg_pages(bytes)? {
+        let serial = ogg_serial(&bytes[page]);
+        if !serials.contains(&serial) {
+            serials.push(serial);
+        }
+    }
+    Ok(serials)
+}
+
+fn ogg_serial(page: &[u8]) -> u32 {
+    u32::from_le_bytes([page[14], page[15], page[16], page[17]])
+}
+
+fn rewrite_ogg_serial(bytes: &mut [u8], from: u32, to: u32) -> Result<()> {
+    for page in ogg_pages(bytes)? {
+        let page = &mut bytes[page];
+        if ogg_serial(page) != from {
+            continue;
+        }
+        page[14..18].copy_from_slice(&to.to_le_bytes());
+        page[22..26].fill(0);
+        let crc = ogg_crc(page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+    }
+    Ok(())
+}
+
+/// CRC-32 as used by Ogg (polynomial 0x04c11db7, no reflection, zero init).
+fn ogg_crc(bytes: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for byte in bytes {
+        crc ^= (*byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(samples: &[u8]) -> Vec<u8> {
+        let fmt = [1, 0, 1, 0, 0x80, 0x3e, 0, 0, 0, 0x7d, 0, 0, 2, 0, 16, 0];
+        let mut out = b"RIFF".to_vec();
+        out.extend_from_slice(&((4 + 8 + 16 + 8 + samples.len()) as u32).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&fmt);
+        out.extend_from_slice(b"LIST");
+        out.extend_from_slice(&3u32.to_le_bytes());
+        out.extend_from_slice(&[1, 2, 3, 0]);
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        out.extend_from_slice(samples);
+        out
+    }
+
+    fn ogg_page(serial: u32, body: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS".to_vec();
+        page.extend_from_slice(&[0, 2]);
+        page.extend_from_slice(&[0; 8]);
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&[0; 8]);
+        page.push(1);
+        page.push(body.len() as u8);
+        page.extend_from_slice(body);
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    #[test]
+    fn test_split_prefers_sentence_boundaries() {
+        let text = "First sentence. Second one is here! Third? 第四句。第五句。";
+        assert_eq!(
+            split_for_synthesis(text, 35),
+            [
+                "First sentence. Second one is here!",
+                "Third? 第四句。第五句。"
+            ]
+        );
+        assert_eq!(split_for_synthesis("v1.2 is out.", 100), ["v1.2 is out."]);
+
+        let long = "word ".repeat(30);
+        let segments = split_for_synthesis(&long, 22);
+        assert!(segments.iter().all(|s| s.chars().count() <= 22));
+        assert_eq!(segments.join(" "), long.trim());
+        assert!(split_for_synthesis("  ", 10).is_empty());
+    }
+
+    #[test]
+    fn test_concat_wav_rewrites_header() {
+        let joined = concat_audio(AudioFormat::Wav, &[wav(&[1, 2]), wav(&[3, 4, 5, 6])]).unwrap();
+        let (fmt, data) = parse_wav(&joined).unwrap();
+        assert_eq!(fmt.len(), 16);
+        assert_eq!(data, [1, 2, 3, 4, 5, 6]);
+        let riff = u32::from_le_bytes(joined[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff, joined.len() - 8);
+
+        let mut stereo = wav(&[0, 0]);
+        stereo[22] = 2;
+        assert!(concat_audio(AudioFormat::Wav, &[wav(&[1, 2]), stereo]).is_err());
+    }
+
+    #[test]
+    fn test_concat_mp3_strips_inner_tags() {
+        let frames = [0xff, 0xfb, 0x90, 0x00];
+        let mut first = b"ID3\x04\x00\x00\x00\x00\x00\x02ab".to_vec();
+        first.extend_from_slice(&frames);
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+        first.extend_from_slice(&id3v1);
+        let mut second = b"ID3\x04\x00\x00\x00\x00\x00\x01x".to_vec();
+        second.extend_from_slice(&frames);
+
+        let joined = concat_audio(AudioFormat::Mp3, &[first, second]).unwrap();
+        let mut expected = b"ID3\x04\x00\x00\x00\x00\x00\x02ab".to_vec();
+        expected.extend_from_slice(&frames);
+        expected.extend_from_slice(&frames);
+        assert_eq!(joined, expected);
+    }
+
+    #[test]
+    fn test_concat_ogg_chains_with_unique_serials() {
+        let a = [ogg_page(7, b"head"), ogg_page(7, b"audio")].concat();
+        let b = [ogg_page(7, b"head"), ogg_page(7, b"more")].concat();
+        let joined = concat_audio(AudioFormat::Opus, &[a.clone(), b]).unwrap();
+
+        assert_eq!(&joined[..a.len()], a.as_slice());
+        assert_eq!(ogg_serials(&joined).unwrap(), [7, 8]);
+        for page in ogg_pages(&joined).unwrap() {
+            let mut page = joined[page].to_vec();
+            let stored = u32::from_le_bytes(page[22..26].try_into().unwrap());
+            page[22..26].fill(0);
+            assert_eq!(ogg_crc(&page), stored);
+        }
+        assert!(concat_audio(AudioFormat::Opus, &[a, b"junk".to_vec()]).is_err());
+    }
+
+    #[test]
+    fn test_concat_pcm_and_flac() {
+        assert_eq!(
+            concat_audio(AudioFormat::Pcm, &[vec![1, 2], vec![3]]).unwrap(),
+            [1, 2, 3]
+        );
+        assert!(concat_audio(AudioFormat::Flac, &[vec![1], vec![2]]).is_err());
+        assert_eq!(concat_audio(AudioFormat::Flac, &[vec![1]]).unwrap(), [1]);
+    }
+}
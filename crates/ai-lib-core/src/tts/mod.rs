@@ -1,7 +1,32 @@
-//! TTS（文字转语音）模块：通过 Provider API（如 OpenAI TTS）将文本合成为音频。
+//! TTS（文字转语音）模块：通过 Provider API（如 OpenAI TTS）将文本合成为音频，支持流式输出与长文本分段合成。
+//!
+//! | Component | Description |
+//! |-----------|-------------|
+//! | [`TtsClient::synthesize`] | One request, one [`AudioOutput`] buffer |
+//! | [`TtsClient::synthesize_stream`] | [`AudioStream`] of chunks as they arrive (chunked HTTP or SSE base64 deltas) |
+//! | [`TtsClient::synthesize_long`] | Sentence-split segments synthesized concurrently and joined per [`AudioFormat`] |
+//! | [`split_for_synthesis`] / [`concat_audio`] | The splitting and joining steps on their own |
+//!
+//! ```rust,no_run
+//! use ai_lib_core::tts::{TtsClient, TtsOptions};
+//! use futures::StreamExt;
+//!
+//! # async fn demo(client: &TtsClient) -> ai_lib_core::Result<()> {
+//! let options = TtsOptions { response_format: Some("pcm".into()), ..Default::default() };
+//! let mut audio = client.synthesize_stream("Hello there!", &options).await?;
+//! while let Some(chunk) = audio.next().await {
+//!     let chunk = chunk?; // hand to the audio device immediately
+//! #   let _ = chunk;
+//! }
+//! # Ok(()) }
+//! ```
 
 mod client;
+mod long_form;
+mod stream;
 mod types;
 
 pub use client::{TtsClient, TtsClientBuilder};
+pub use long_form::{concat_audio, split_for_synthesis};
+pub use stream::AudioStream;
 pub use types::{AudioFormat, AudioOutput, TtsOptions};
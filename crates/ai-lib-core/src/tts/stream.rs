@@ -0,0 +1,132 @@
+//! Streaming TTS output: raw chunked audio or SSE events carrying base64 audio deltas.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use base64::Engine as _;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+
+use super::types::{AudioFormat, AudioOutput};
+use crate::pipeline::decode::SseDecoder;
+use crate::pipeline::Decoder;
+use crate::{BoxStream, Error, ErrorContext, Result};
+
+/// Audio chunks from [`TtsClient::synthesize_stream`](super::TtsClient::synthesize_stream),
+/// yielded as soon as the provider sends them.
+///
+/// Chunks are pieces of one `format` file: write or play them in order.
+pub struct AudioStream {
+    format: AudioFormat,
+    chunks: BoxStream<'static, Bytes>,
+}
+
+impl AudioStream {
+    pub(crate) fn new(format: AudioFormat, chunks: BoxStream<'static, Bytes>) -> Self {
+        Self { format, chunks }
+    }
+
+    /// Wrap a raw audio byte stream (chunked transfer encoding).
+    pub(crate) fn raw(format: AudioFormat, response: reqwest::Response) -> Self {
+        let chunks = response.bytes_stream().map(|chunk| {
+            chunk.map_err(|e| {
+                Error::network_with_context(
+                    format!("TTS stream interrupted: {}", e),
+                    ErrorContext::new().with_source("tts"),
+                )
+            })
+        });
+        Self::new(format, Box::pin(chunks))
+    }
+
+    /// Decode an SSE response whose events carry base64 audio in `audio`, `delta` or
+    /// `data`; events without audio (e.g. `speech.audio.done`) are skipped.
+    pub(crate) async fn sse(format: AudioFormat, response: reqwest::Response) -> Result<Self> {
+        let raw = Self::raw(format, response).chunks;
+        let events = SseDecoder::new(None, None, None).decode_stream(raw).await?;
+        let chunks = events.filter_map(|event| async move {
+            match event {
+                Ok(event) => audio_delta(&event).transpose(),
+                Err(e) => Some(Err(e)),
+            }
+        });
+        Ok(Self::new(format, Box::pin(chunks)))
+    }
+
+    pub fn format(&self) -> AudioFormat {
+        self.format
+    }
+
+    /// Drain the stream into one buffer.
+    pub async fn collect(mut self) -> Result<AudioOutput> {
+        let mut data = Vec::new();
+        while let Some(chunk) = self.chunks.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(AudioOutput {
+            data,
+            format: self.format,
+        })
+    }
+}
+
+impl Stream for AudioStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.chunks.as_mut().poll_next(cx)
+    }
+}
+
+fn audio_delta(event: &Value) -> Result<Option<Bytes>> {
+    if let Some(error) = event.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .map_or_else(|| error.to_string(), str::to_string);
+        return Err(Error::api_with_context(
+            format!("TTS stream error: {}", message),
+            ErrorContext::new().with_source("tts"),
+        ));
+    }
+    let Some(encoded) = ["audio", "delta", "data"]
+        .iter()
+        .find_map(|key| event.get(*key).and_then(Value::as_str))
+    else {
+        return Ok(None);
+    };
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map(|bytes| Some(Bytes::from(bytes)))
+        .map_err(|e| {
+            Error::api_with_context(
+                format!("TTS stream carried invalid base64 audio: {}", e),
+                ErrorContext::new().with_source("tts"),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_audio_delta_fields() {
+        let delta = json!({ "type": "speech.audio.delta", "audio": "AQID" });
+        assert_eq!(audio_delta(&delta).unwrap().unwrap().as_ref(), [1, 2, 3]);
+        assert_eq!(
+            audio_delta(&json!({ "delta": "BA==" }))
+                .unwrap()
+                .unwrap()
+                .as_ref(),
+            [4]
+        );
+        assert!(audio_delta(&json!({ "type": "speech.audio.done" }))
+            .unwrap()
+            .is_none());
+        assert!(audio_delta(&json!({ "audio": "not base64!" })).is_err());
+        assert!(audio_delta(&json!({ "error": { "message": "quota" } })).is_err());
+    }
+}
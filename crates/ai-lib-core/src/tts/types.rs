@@ -53,4 +53,7 @@ pub struct TtsOptions {
     pub voice: Option<String>,
     pub speed: Option<f32>,
     pub response_format: Option<String>,
+    /// Streaming transport for `synthesize_stream` where the provider offers a choice
+    /// (e.g. `"sse"` or `"audio"`); omitted from the request when `None`.
+    pub stream_format: Option<String>,
 }
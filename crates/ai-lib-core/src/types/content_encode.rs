@@ -73,4 +73,31 @@ mod tests {
         )];
         assert!(encode_blocks_for_gemini(&blocks).is_err());
     }
+
+    #[test]
+    fn anthropic_file_references() {
+        let blocks = vec![
+            ContentBlock::document_file("file_011".into(), Some("application/pdf".into()), None),
+            ContentBlock::image_file("file_012".into(), Some("image/png".into())),
+        ];
+        let encoded = encode_blocks_for_anthropic(&blocks).unwrap();
+        assert_eq!(encoded[0]["type"], "document");
+        assert_eq!(encoded[0]["source"]["type"], "file");
+        assert_eq!(encoded[0]["source"]["file_id"], "file_011");
+        assert_eq!(encoded[1]["type"], "image");
+        assert_eq!(encoded[1]["source"]["file_id"], "file_012");
+    }
+
+    #[test]
+    fn gemini_file_data_shape() {
+        let uri = "https://generativelanguage.googleapis.com/v1beta/files/abc";
+        let blocks = vec![
+            ContentBlock::document_file(uri.into(), None, None),
+            ContentBlock::image_file(uri.into(), Some("image/jpeg".into())),
+        ];
+        let parts = encode_blocks_for_gemini(&blocks).unwrap();
+        assert_eq!(parts[0]["fileData"]["fileUri"], uri);
+        assert_eq!(parts[0]["fileData"]["mimeType"], "application/pdf");
+        assert_eq!(parts[1]["fileData"]["mimeType"], "image/jpeg");
+    }
 }
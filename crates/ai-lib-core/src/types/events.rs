@@ -82,4 +82,20 @@ pub enum StreamingEvent {
         #[serde(skip_serializing_if = "Option::is_none")]
         event_id: Option<String>,
     },
+
+    /// The upstream stream failed mid-generation and was resumed; the events that
+    /// follow continue the same assistant output.
+    #[serde(rename = "StreamRecovered")]
+    StreamRecovered {
+        /// `provider/model` serving the continuation.
+        model: String,
+        /// `prefill`, `continue` or `restart` (nothing was emitted yet).
+        mode: String,
+        /// The failure that ended the previous stream.
+        error: String,
+        /// Characters of partial output carried into the continuation.
+        retained_chars: usize,
+        /// 1-based recovery count for this call.
+        attempt: u32,
+    },
 }
@@ -136,6 +136,10 @@ fn encode_anthropic_media_source(source: &ImageSource, kind: &str) -> Result<Val
             "type": "url",
             "url": source.data,
         })),
+        "file" => Ok(json!({
+            "type": "file",
+            "file_id": source.data,
+        })),
         other => Err(validation(format!(
             "unsupported {kind} source type: {other}"
         ))),
@@ -162,6 +166,10 @@ fn encode_anthropic_document_source(
             "type": "url",
             "url": source.data,
         })),
+        "file" => Ok(json!({
+            "type": "file",
+            "file_id": source.data,
+        })),
         "ref" => Err(validation(
             "document ref must be resolved to base64 or url before sending to Anthropic",
         )),
@@ -199,6 +207,9 @@ fn encode_gemini_inline_data(
             mapping.format
         )));
     }
+    if source.source_type == "file" {
+        return Ok(gemini_file_data(&source.data, source.media_type.as_deref()));
+    }
     if source.source_type != "base64" {
         return Err(validation(format!(
             "Gemini {kind} blocks require base64 inline data (got {})",
@@ -232,6 +243,13 @@ fn encode_gemini_document_inline(
             "Gemini document blocks require base64 inline data; resolve ref before send",
         ));
     }
+    if source.source_type == "file" {
+        let mime_type = source
+            .mime_type
+            .as_deref()
+            .unwrap_or_else(|| mapping.default_mime());
+        return Ok(gemini_file_data(&source.data, Some(mime_type)));
+    }
     if source.source_type != "base64" {
         return Err(validation(
             "Gemini document blocks require base64 inline data; resolve ref before send",
@@ -249,6 +267,15 @@ fn encode_gemini_document_inline(
     }))
 }
 
+/// `fileData` part for a file uploaded via the Gemini Files API.
+fn gemini_file_data(file_uri: &str, mime_type: Option<&str>) -> Value {
+    let mut file_data = json!({ "fileUri": file_uri });
+    if let Some(mime_type) = mime_type {
+        file_data["mimeType"] = json!(mime_type);
+    }
+    json!({ "fileData": file_data })
+}
+
 /// Map Gemini `parts` JSON to OpenAI-compatible multimodal message `content` array.
 pub fn gemini_parts_to_openai_multimodal_content(parts: &Value) -> Result<Vec<Value>, Error> {
     let arr = parts
@@ -225,6 +225,34 @@ impl ContentBlock {
         }
     }
 
+    /// Document already uploaded to the provider's Files API (`file_id` is the
+    /// OpenAI / Anthropic file id or the Gemini file URI).
+    pub fn document_file(
+        file_id: String,
+        mime_type: Option<String>,
+        filename: Option<String>,
+    ) -> Self {
+        ContentBlock::Document {
+            source: DocumentSource {
+                source_type: "file".to_string(),
+                mime_type,
+                data: file_id,
+                filename,
+            },
+        }
+    }
+
+    /// Image already uploaded to the provider's Files API.
+    pub fn image_file(file_id: String, media_type: Option<String>) -> Self {
+        ContentBlock::Image {
+            source: ImageSource {
+                source_type: "file".to_string(),
+                media_type,
+                data: file_id,
+            },
+        }
+    }
+
     pub fn image_from_file(path: impl AsRef<Path>) -> crate::Result<Self> {
         let path = path.as_ref();
         let bytes = std::fs::read(path)?;
@@ -240,6 +268,118 @@ impl ContentBlock {
         let data = base64::engine::general_purpose::STANDARD.encode(bytes);
         Ok(Self::audio_base64(data, media_type))
     }
+
+    /// Image by URL; `data:<media-type>;base64,` URLs become base64 blocks.
+    pub fn image_url(url: impl Into<String>) -> Self {
+        let url = url.into();
+        if let Some((header, data)) = url
+            .strip_prefix("data:")
+            .and_then(|rest| rest.split_once(','))
+        {
+            if let Some(media_type) = header.strip_suffix(";base64") {
+                let media_type = (!media_type.is_empty()).then(|| media_type.to_string());
+                return Self::image_base64(data.to_string(), media_type);
+            }
+        }
+        ContentBlock::Image {
+            source: ImageSource {
+                source_type: "url".to_string(),
+                media_type: None,
+                data: url,
+            },
+        }
+    }
+
+    /// Images a model returned inline in a non-streaming chat response.
+    ///
+    /// Recognizes OpenAI-compatible `choices[].message.images` and image content
+    /// parts, OpenAI Responses `image_generation_call` outputs, Gemini
+    /// `candidates[].content.parts[].inlineData`, and Anthropic-style `image` blocks.
+    pub fn inline_images(body: &serde_json::Value) -> Vec<Self> {
+        use serde_json::Value;
+
+        fn from_part(part: &Value, out: &mut Vec<ContentBlock>) {
+            match part.get("type").and_then(Value::as_str) {
+                Some("image_url") | Some("output_image") => {
+                    let url = part
+                        .pointer("/image_url/url")
+                        .or_else(|| part.get("image_url"))
+                        .or_else(|| part.get("url"))
+                        .and_then(Value::as_str);
+                    if let Some(url) = url {
+                        out.push(ContentBlock::image_url(url));
+                    }
+                    return;
+                }
+                Some("image") => {
+                    if let Ok(block) = serde_json::from_value(part.clone()) {
+                        out.push(block);
+                    }
+                    return;
+                }
+                _ => {}
+            }
+            if let Some(inline) = part.get("inlineData").or_else(|| part.get("inline_data")) {
+                let media_type = inline
+                    .get("mimeType")
+                    .or_else(|| inline.get("mime_type"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                if let (true, Some(data)) = (
+                    media_type.starts_with("image/"),
+                    inline.get("data").and_then(Value::as_str),
+                ) {
+                    out.push(ContentBlock::image_base64(
+                        data.to_string(),
+                        Some(media_type.to_string()),
+                    ));
+                }
+            }
+        }
+
+        fn items(v: Option<&Value>) -> std::slice::Iter<'_, Value> {
+            v.and_then(Value::as_array)
+                .map(|a| a.iter())
+                .unwrap_or_default()
+        }
+
+        let mut out = Vec::new();
+        for choice in items(body.get("choices")) {
+            let message = choice.get("message").unwrap_or(&Value::Null);
+            for image in items(message.get("images")) {
+                from_part(image, &mut out);
+            }
+            for part in items(message.get("content")) {
+                from_part(part, &mut out);
+            }
+        }
+        for item in items(body.get("output")) {
+            if item.get("type").and_then(Value::as_str) == Some("image_generation_call") {
+                if let Some(data) = item.get("result").and_then(Value::as_str) {
+                    let format = item
+                        .get("output_format")
+                        .and_then(Value::as_str)
+                        .unwrap_or("png");
+                    out.push(ContentBlock::image_base64(
+                        data.to_string(),
+                        Some(format!("image/{format}")),
+                    ));
+                }
+            }
+            for part in items(item.get("content")) {
+                from_part(part, &mut out);
+            }
+        }
+        for candidate in items(body.get("candidates")) {
+            for part in items(candidate.pointer("/content/parts")) {
+                from_part(part, &mut out);
+            }
+        }
+        for part in items(body.get("content")) {
+            from_part(part, &mut out);
+        }
+        out
+    }
 }
 
 fn guess_media_type(path: &Path) -> Option<String> {
@@ -314,4 +454,47 @@ mod tests {
         assert!(msg.contains_document());
         assert!(!Message::user("plain").contains_document());
     }
+
+    #[test]
+    fn test_inline_images_from_chat_responses() {
+        let source = |block: &ContentBlock| match block {
+            ContentBlock::Image { source } => (
+                source.source_type.clone(),
+                source.media_type.clone(),
+                source.data.clone(),
+            ),
+            other => panic!("expected image, got {other:?}"),
+        };
+        let openai = ContentBlock::inline_images(&serde_json::json!({
+            "choices": [{ "message": {
+                "content": "Here you go",
+                "images": [{ "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBO" } }]
+            }}]
+        }));
+        assert_eq!(
+            source(&openai[0]),
+            ("base64".into(), Some("image/png".into()), "iVBO".into())
+        );
+
+        let gemini = ContentBlock::inline_images(&serde_json::json!({
+            "candidates": [{ "content": { "parts": [
+                { "text": "A cat" },
+                { "inlineData": { "mimeType": "image/jpeg", "data": "/9j/" } }
+            ]}}]
+        }));
+        assert_eq!(gemini.len(), 1);
+        assert_eq!(source(&gemini[0]).1.as_deref(), Some("image/jpeg"));
+
+        let responses = ContentBlock::inline_images(&serde_json::json!({
+            "output": [
+                { "type": "image_generation_call", "result": "UklG", "output_format": "webp" },
+                { "type": "message", "content": [{ "type": "output_image", "image_url": "https://x/y.png" }] }
+            ]
+        }));
+        assert_eq!(source(&responses[0]).1.as_deref(), Some("image/webp"));
+        assert_eq!(
+            source(&responses[1]),
+            ("url".into(), None, "https://x/y.png".into())
+        );
+    }
 }
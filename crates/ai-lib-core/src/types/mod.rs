@@ -29,6 +29,7 @@
 //! |--------|-------------|
 //! | [`events`] | Streaming event types and variants |
 //! | [`message`] | Message types with multi-modal content support |
+//! | [`prompt`] | Prompt template identity attached to calls and feedback |
 //! | [`tool`] | Tool/function calling types |
 //!
 //! ## Example
@@ -61,6 +62,7 @@ pub mod events;
 pub mod execution_result;
 pub mod manifest_encode;
 pub mod message;
+pub mod prompt;
 pub mod text_tool;
 pub mod tool;
 
@@ -68,5 +70,6 @@ pub use content_encode::{encode_blocks_for_anthropic, encode_blocks_for_gemini};
 pub use events::StreamingEvent;
 pub use execution_result::{ExecutionMetadata, ExecutionResult, ExecutionUsage};
 pub use message::{Message, MessageRole};
+pub use prompt::PromptRef;
 pub use text_tool::{PromptLevel, StandardTextToolParser, TextToolConfig, TextToolParser};
 pub use tool::{ToolCall, ToolDefinition};
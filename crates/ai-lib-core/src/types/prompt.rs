@@ -0,0 +1,29 @@
+//! Identity of a versioned prompt template, carried on call stats and feedback.
+
+use serde::{Deserialize, Serialize};
+
+/// Which prompt template (and version) produced a request.
+///
+/// Set by `prompts::PromptRegistry::render` and attached to
+/// [`CallStats`](crate::client::CallStats) and feedback events so answers can be
+/// traced back to, and compared across, prompt versions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PromptRef {
+    pub id: String,
+    pub version: String,
+}
+
+impl PromptRef {
+    pub fn new(id: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            version: version.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for PromptRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.id, self.version)
+    }
+}
@@ -1,6 +1,8 @@
 //! Utility modules
 
 pub mod json_path;
+#[cfg(feature = "pdf_text")]
+pub mod pdf_text;
 pub mod tool_call_assembler;
 
 pub use json_path::{JsonPathEvaluator, PathMapper};
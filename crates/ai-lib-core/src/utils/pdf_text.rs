@@ -0,0 +1,297 @@
+//! Best-effort PDF text extraction for request degradation.
+//!
+//! Reads the text-showing operators (`Tj`, `TJ`, `'`, `"`) of every content stream,
+//! inflating `FlateDecode` streams. Fonts with custom encodings (CID / ToUnicode maps)
+//! and scanned pages are not decoded; callers should treat an empty result as
+//! "no extractable text".
+
+use std::io::Read;
+
+/// Extract the plain text of a PDF, or `None` when no text could be recovered.
+pub fn extract_pdf_text(pdf: &[u8]) -> Option<String> {
+    if !pdf.starts_with(b"%PDF") {
+        return None;
+    }
+    let mut out = String::new();
+    let mut pos = 0;
+    while let Some(start) = find(pdf, b"stream", pos) {
+        pos = start + b"stream".len();
+        // `endstream` also contains "stream"; skip it.
+        if start >= 3 && &pdf[start - 3..start] == b"end" {
+            continue;
+        }
+        let mut data_start = pos;
+        if pdf.get(data_start) == Some(&b'\r') {
+            data_start += 1;
+        }
+        if pdf.get(data_start) == Some(&b'\n') {
+            data_start += 1;
+        }
+        let Some(end) = find(pdf, b"endstream", data_start) else {
+            break;
+        };
+        pos = end + b"endstream".len();
+
+        let dict_start = pdf[..start]
+            .windows(2)
+            .rposition(|w| w == b"<<")
+            .unwrap_or(0);
+        let dict = &pdf[dict_start..start];
+        let raw = &pdf[data_start..end];
+        let content = if find(dict, b"/FlateDecode", 0).is_some() {
+            let mut inflated = Vec::new();
+            if flate2::read::ZlibDecoder::new(raw)
+                .read_to_end(&mut inflated)
+                .is_err()
+                && inflated.is_empty()
+            {
+                continue;
+            }
+            inflated
+        } else if find(dict, b"/Filter", 0).is_some() {
+            // Images and other encodings carry no text we can read.
+            continue;
+        } else {
+            raw.to_vec()
+        };
+        append_content_text(&content, &mut out);
+    }
+    let text = out
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+/// Interpret the text operators of one content stream.
+fn append_content_text(content: &[u8], out: &mut String) {
+    let mut strings: Vec<String> = Vec::new();
+    let mut in_text = false;
+    let mut i = 0;
+    while i < content.len() {
+        match content[i] {
+            b'(' => {
+                let (s, next) = literal_string(content, i + 1);
+                strings.push(s);
+                i = next;
+                continue;
+            }
+            b'<' if content.get(i + 1) != Some(&b'<') => {
+                let end = find(content, b">", i + 1).unwrap_or(content.len());
+                strings.push(hex_string(&content[i + 1..end]));
+                i = end + 1;
+                continue;
+            }
+            b'%' => {
+                while i < content.len() && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'/' => {
+                // Names (fonts, resources) are never text.
+                i += 1;
+                while i < content.len()
+                    && !content[i].is_ascii_whitespace()
+                    && !b"/[]()<>".contains(&content[i])
+                {
+                    i += 1;
+                }
+                continue;
+            }
+            c if c.is_ascii_alphabetic() || c == b'\'' || c == b'"' || c == b'*' => {
+                let start = i;
+                while i < content.len()
+                    && (content[i].is_ascii_alphabetic()
+                        || matches!(content[i], b'\'' | b'"' | b'*'))
+                {
+                    i += 1;
+                }
+                match &content[start..i] {
+                    b"BT" => {
+                        in_text = true;
+                        strings.clear();
+                    }
+                    b"ET" => {
+                        in_text = false;
+                        if !out.is_empty() && !out.ends_with('\n') {
+                            out.push('\n');
+                        }
+                    }
+                    b"Tj" | b"TJ" if in_text => out.extend(strings.drain(..)),
+                    b"'" | b"\"" if in_text => {
+                        out.push('\n');
+                        out.extend(strings.drain(..));
+                    }
+                    b"T*" | b"Td" | b"TD" if in_text && !out.is_empty() && !out.ends_with('\n') => {
+                        out.push('\n');
+                    }
+                    _ => {}
+                }
+                // Operands belong to exactly one operator.
+                strings.clear();
+                continue;
+            }
+            b'[' | b']' => {}
+            c if c == b'-' || c == b'.' || c.is_ascii_digit() => {
+                // Large negative kerning inside TJ arrays stands for a word gap.
+                let start = i;
+                while i < content.len()
+                    && (content[i] == b'-' || content[i] == b'.' || content[i].is_ascii_digit())
+                {
+                    i += 1;
+                }
+                let kern = std::str::from_utf8(&content[start..i])
+                    .ok()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .unwrap_or(0.0);
+                if kern <= -200.0 && !strings.is_empty() {
+                    strings.push(" ".to_string());
+                }
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+fn literal_string(content: &[u8], mut i: usize) -> (String, usize) {
+    let mut bytes = Vec::new();
+    let mut depth = 1;
+    while i < content.len() {
+        let c = content[i];
+        i += 1;
+        match c {
+            b'\\' => {
+                let Some(&e) = content.get(i) else { break };
+                i += 1;
+                match e {
+                    b'n' => bytes.push(b'\n'),
+                    b'r' => bytes.push(b'\r'),
+                    b't' => bytes.push(b'\t'),
+                    b'b' | b'f' => {}
+                    b'0'..=b'7' => {
+                        let mut value = u32::from(e - b'0');
+                        for _ in 0..2 {
+                            match content.get(i) {
+                                Some(&d @ b'0'..=b'7') => {
+                                    value = value * 8 + u32::from(d - b'0');
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        bytes.push(value as u8);
+                    }
+                    b'\r' | b'\n' => {
+                        if e == b'\r' && content.get(i) == Some(&b'\n') {
+                            i += 1;
+                        }
+                    }
+                    other => bytes.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                bytes.push(c);
+            }
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+                bytes.push(c);
+            }
+            _ => bytes.push(c),
+        }
+    }
+    (decode_pdf_bytes(&bytes), i)
+}
+
+fn hex_string(hex: &[u8]) -> String {
+    let digits: Vec<u8> = hex
+        .iter()
+        .filter_map(|c| (*c as char).to_digit(16).map(|d| d as u8))
+        .collect();
+    let bytes: Vec<u8> = digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect();
+    decode_pdf_bytes(&bytes)
+}
+
+/// UTF-16BE with BOM, otherwise PDFDocEncoding approximated as Latin-1. Control
+/// bytes (typically glyph ids from CID fonts) are dropped.
+fn decode_pdf_bytes(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks(2)
+            .map(|p| u16::from_be_bytes([p[0], p.get(1).copied().unwrap_or(0)]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    bytes
+        .iter()
+        .filter(|b| **b >= 0x20 || matches!(**b, b'\n' | b'\t'))
+        .map(|b| *b as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn pdf_with_stream(dict: &str, stream: &[u8]) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n".to_vec();
+        pdf.extend_from_slice(
+            format!("<< /Length {} {} >>\nstream\n", stream.len(), dict).as_bytes(),
+        );
+        pdf.extend_from_slice(stream);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n%%EOF");
+        pdf
+    }
+
+    #[test]
+    fn test_extracts_plain_and_flate_streams() {
+        let content = b"BT /F1 12 Tf 72 712 Td (Quarterly \\(Q3\\) report) Tj 0 -14 Td [(Rev)-30(enue)-250(up)] TJ ET";
+        assert_eq!(
+            extract_pdf_text(&pdf_with_stream("", content)).as_deref(),
+            Some("Quarterly (Q3) report\nRevenue up")
+        );
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(
+            extract_pdf_text(&pdf_with_stream("/Filter /FlateDecode", &compressed)).as_deref(),
+            Some("Quarterly (Q3) report\nRevenue up")
+        );
+    }
+
+    #[test]
+    fn test_hex_strings_and_non_pdf_input() {
+        let content = b"BT <FEFF00480069> Tj T* <4F4B> Tj ET";
+        assert_eq!(
+            extract_pdf_text(&pdf_with_stream("", content)).as_deref(),
+            Some("Hi\nOK")
+        );
+        assert_eq!(extract_pdf_text(b"not a pdf"), None);
+        assert_eq!(
+            extract_pdf_text(&pdf_with_stream("/Filter /DCTDecode", b"\xff\xd8")),
+            None
+        );
+    }
+}
@@ -2,7 +2,7 @@ use crate::types::tool::ToolCall;
 
 /// Collects tool call events (started + argument fragments) into final ToolCall objects.
 /// This is intentionally tolerant: if JSON parsing fails, it keeps the raw string.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ToolCallAssembler {
     tool_calls: Vec<ToolCall>,
 }
@@ -0,0 +1,286 @@
+//! 网关配置：模型路由、租户虚拟密钥与配额、缓存与护栏（YAML）。
+//!
+//! Gateway configuration loaded from YAML.
+//!
+//! ```yaml
+//! listen: "127.0.0.1:8080"
+//! protocol_path: "./ai-protocol"
+//! providers:
+//!   - id: openai
+//!     base_url: "https://proxy.internal/v1"   # optional override
+//! models:
+//!   - name: smart                            # name clients send as `model`
+//!     target: openai/gpt-4o
+//!     fallbacks: [anthropic/claude-3-5-sonnet]
+//!     api_key_env: OPENAI_API_KEY            # optional; manifest env vars otherwise
+//! embedding_models:
+//!   - name: embed
+//!     target: openai/text-embedding-3-small
+//! tenants:
+//!   - id: team-a
+//!     keys: ["sk-gw-team-a"]
+//!     models: [smart]                        # empty = every model
+//!     quota: { requests_per_minute: 60, tokens_per_minute: 100000 }
+//! # allow_anonymous: true                   # required to start without tenants
+//! cache: { enabled: true, ttl_secs: 300, max_entries: 1000 }
+//! guardrails: { block_keywords: ["secret project"], pii_detection: true }
+//! ```
+
+use crate::error::GatewayError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Top-level gateway configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GatewayConfig {
+    /// Socket address to bind (`--listen` overrides it).
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    /// AI-Protocol directory or URL; falls back to `AI_PROTOCOL_DIR` handling in the loader.
+    #[serde(default)]
+    pub protocol_path: Option<String>,
+    #[serde(default)]
+    pub providers: Vec<ProviderOverride>,
+    #[serde(default)]
+    pub models: Vec<ModelRoute>,
+    #[serde(default)]
+    pub embedding_models: Vec<ModelRoute>,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+    /// Serve requests without a virtual key when no tenants are configured
+    /// (`--allow-anonymous`). Off by default: an unauthenticated gateway spends the
+    /// operator's provider keys for anyone who can reach it.
+    #[serde(default)]
+    pub allow_anonymous: bool,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    #[serde(default)]
+    pub guardrails: Option<GuardrailSettings>,
+}
+
+fn default_listen() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+/// Per-provider manifest overrides, applied to primary and fallback targets alike.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderOverride {
+    pub id: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// A public model name mapped to a manifest-defined `provider/model` target.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelRoute {
+    pub name: String,
+    pub target: String,
+    #[serde(default)]
+    pub fallbacks: Vec<String>,
+    /// Literal upstream credential. Prefer `api_key_env` outside of tests.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+}
+
+impl ModelRoute {
+    /// Provider segment of `target` (`openai` in `openai/gpt-4o`).
+    pub fn provider(&self) -> &str {
+        self.target.split('/').next().unwrap_or(&self.target)
+    }
+
+    /// Explicit upstream credential, if configured.
+    pub fn credential(&self) -> Result<Option<String>, GatewayError> {
+        if let Some(key) = &self.api_key {
+            return Ok(Some(key.clone()));
+        }
+        match &self.api_key_env {
+            Some(var) => std::env::var(var).map(Some).map_err(|_| {
+                GatewayError::config(format!(
+                    "model '{}': environment variable {} is not set",
+                    self.name, var
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A tenant and the virtual API keys that authenticate as it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    pub id: String,
+    pub keys: Vec<String>,
+    /// Public model names this tenant may use; empty allows every model.
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub quota: QuotaSettings,
+}
+
+impl TenantConfig {
+    pub fn allows(&self, model: &str) -> bool {
+        self.models.is_empty() || self.models.iter().any(|m| m == model)
+    }
+}
+
+/// Fixed one-minute window limits; `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuotaSettings {
+    #[serde(default)]
+    pub requests_per_minute: Option<u64>,
+    #[serde(default)]
+    pub tokens_per_minute: Option<u64>,
+}
+
+/// Response cache for non-streaming chat completions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_ttl_secs() -> u64 {
+    300
+}
+
+fn default_max_entries() -> usize {
+    1000
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_ttl_secs(),
+            max_entries: default_max_entries(),
+        }
+    }
+}
+
+/// Input guardrails; a blocking violation rejects the request with HTTP 400.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuardrailSettings {
+    #[serde(default)]
+    pub block_keywords: Vec<String>,
+    #[serde(default)]
+    pub block_patterns: Vec<String>,
+    #[serde(default)]
+    pub pii_detection: bool,
+}
+
+impl GatewayConfig {
+    /// Parse YAML and validate cross references.
+    pub fn from_yaml(yaml: &str) -> Result<Self, GatewayError> {
+        let config: Self = serde_yaml::from_str(yaml)
+            .map_err(|e| GatewayError::config(format!("invalid gateway config: {}", e)))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, GatewayError> {
+        let path = path.as_ref();
+        let yaml = std::fs::read_to_string(path)
+            .map_err(|e| GatewayError::config(format!("cannot read {}: {}", path.display(), e)))?;
+        Self::from_yaml(&yaml)
+    }
+
+    pub fn validate(&self) -> Result<(), GatewayError> {
+        let mut names = HashSet::new();
+        for route in self.models.iter().chain(&self.embedding_models) {
+            if !route.target.contains('/') {
+                return Err(GatewayError::config(format!(
+                    "model '{}': target '{}' must be in provider/model form",
+                    route.name, route.target
+                )));
+            }
+            if !names.insert(route.name.as_str()) {
+                return Err(GatewayError::config(format!(
+                    "duplicate model name '{}'",
+                    route.name
+                )));
+            }
+        }
+        let mut keys = HashSet::new();
+        for tenant in &self.tenants {
+            if tenant.keys.is_empty() {
+                return Err(GatewayError::config(format!(
+                    "tenant '{}' has no keys",
+                    tenant.id
+                )));
+            }
+            for key in &tenant.keys {
+                if !keys.insert(key.as_str()) {
+                    return Err(GatewayError::config(format!(
+                        "virtual key for tenant '{}' is already assigned",
+                        tenant.id
+                    )));
+                }
+            }
+            if let Some(unknown) = tenant.models.iter().find(|m| !names.contains(m.as_str())) {
+                return Err(GatewayError::config(format!(
+                    "tenant '{}' references unknown model '{}'",
+                    tenant.id, unknown
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = GatewayConfig::from_yaml(
+            r#"
+protocol_path: ./protocols
+models:
+  - name: smart
+    target: openai/gpt-4o
+    fallbacks: [openai/gpt-4o-mini]
+tenants:
+  - id: a
+    keys: [k1]
+    models: [smart]
+    quota: { requests_per_minute: 5 }
+cache: { enabled: true }
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.listen, "127.0.0.1:8080");
+        assert_eq!(config.models[0].provider(), "openai");
+        assert_eq!(config.tenants[0].quota.requests_per_minute, Some(5));
+        assert_eq!(config.cache.ttl_secs, 300);
+    }
+
+    #[test]
+    fn test_rejects_unknown_tenant_model_and_shared_keys() {
+        let unknown = GatewayConfig::from_yaml(
+            "models: [{name: a, target: openai/x}]\ntenants: [{id: t, keys: [k], models: [b]}]",
+        );
+        assert!(unknown
+            .unwrap_err()
+            .to_string()
+            .contains("unknown model 'b'"));
+
+        let shared =
+            GatewayConfig::from_yaml("tenants: [{id: t1, keys: [k]}, {id: t2, keys: [k]}]");
+        assert!(shared.unwrap_err().to_string().contains("already assigned"));
+    }
+}
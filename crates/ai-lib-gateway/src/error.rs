@@ -0,0 +1,140 @@
+//! OpenAI 形状的错误响应。
+//!
+//! Errors rendered in the OpenAI wire shape:
+//! `{"error": {"message", "type", "param", "code"}}`.
+
+use ai_lib_rust::error_code::StandardErrorCode;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use std::fmt;
+use std::time::Duration;
+
+/// Gateway error with the HTTP status and OpenAI error fields it renders to.
+#[derive(Debug, Clone)]
+pub struct GatewayError {
+    pub status: StatusCode,
+    pub error_type: &'static str,
+    pub code: Option<String>,
+    pub message: String,
+    pub retry_after: Option<Duration>,
+}
+
+impl GatewayError {
+    fn new(status: StatusCode, error_type: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            error_type,
+            code: None,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Startup / configuration failure (never sent to clients in normal operation).
+    pub fn config(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "server_error", message)
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_request_error", message)
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "invalid_request_error",
+            "Incorrect or missing API key",
+        )
+        .with_code("invalid_api_key")
+    }
+
+    /// Unknown model, or a model the tenant may not use (indistinguishable on purpose).
+    pub fn model_not_found(model: &str) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "invalid_request_error",
+            format!(
+                "The model '{}' does not exist or you do not have access to it",
+                model
+            ),
+        )
+        .with_code("model_not_found")
+    }
+
+    pub fn content_policy(message: impl Into<String>) -> Self {
+        Self::invalid_request(message).with_code("content_policy_violation")
+    }
+
+    pub fn quota_exceeded(message: impl Into<String>, retry_after: Duration) -> Self {
+        let mut err = Self::new(StatusCode::TOO_MANY_REQUESTS, "rate_limit_error", message)
+            .with_code("rate_limit_exceeded");
+        err.retry_after = Some(retry_after);
+        err
+    }
+
+    /// Map a runtime error from the upstream call.
+    ///
+    /// Upstream credential failures are the gateway's problem, not the caller's,
+    /// so they surface as 502 rather than 401.
+    pub fn upstream(err: &ai_lib_rust::Error) -> Self {
+        let code = err.standard_code().unwrap_or(StandardErrorCode::Unknown);
+        let (status, error_type) = match code {
+            StandardErrorCode::InvalidRequest | StandardErrorCode::RequestTooLarge => {
+                (StatusCode::BAD_REQUEST, "invalid_request_error")
+            }
+            StandardErrorCode::NotFound => (StatusCode::NOT_FOUND, "invalid_request_error"),
+            StandardErrorCode::RateLimited | StandardErrorCode::QuotaExhausted => {
+                (StatusCode::TOO_MANY_REQUESTS, "rate_limit_error")
+            }
+            StandardErrorCode::Overloaded => (StatusCode::SERVICE_UNAVAILABLE, "server_error"),
+            StandardErrorCode::Timeout => (StatusCode::GATEWAY_TIMEOUT, "server_error"),
+            _ => (StatusCode::BAD_GATEWAY, "server_error"),
+        };
+        Self::new(status, error_type, err.to_string()).with_code(code.name())
+    }
+
+    /// Upstream stream failed after the first chunk was sent.
+    pub fn stream_aborted(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_GATEWAY, "server_error", message).with_code("stream_aborted")
+    }
+
+    /// The `{"error": {...}}` body.
+    pub fn body(&self) -> serde_json::Value {
+        json!({
+            "error": {
+                "message": self.message,
+                "type": self.error_type,
+                "param": null,
+                "code": self.code,
+            }
+        })
+    }
+}
+
+impl fmt::Display for GatewayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for GatewayError {}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, Json(self.body())).into_response();
+        if let Some(after) = self.retry_after {
+            let secs = after.as_secs() + u64::from(after.subsec_nanos() > 0);
+            if let Ok(value) = HeaderValue::from_str(&secs.max(1).to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+        }
+        response
+    }
+}
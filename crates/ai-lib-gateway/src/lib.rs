@@ -0,0 +1,29 @@
+//! # ai-lib-gateway
+//!
+//! OpenAI 兼容网关：对外暴露 `/v1/chat/completions`、`/v1/embeddings`、`/v1/models`，
+//! 对内经 `AiClient` 路由到任意清单定义的供应商，并应用回退、护栏、缓存与租户配额。
+//!
+//! OpenAI-compatible HTTP gateway. Clients that only speak the OpenAI wire
+//! format reach any manifest-defined provider through ai-lib's runtime.
+//! Virtual API keys map to tenants with model allow-lists and per-minute
+//! quotas; see [`config`] for the YAML shape.
+//!
+//! ```rust,no_run
+//! use ai_lib_gateway::{Gateway, GatewayConfig};
+//!
+//! # async fn demo() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = GatewayConfig::from_file("gateway.yaml")?;
+//! let listener = tokio::net::TcpListener::bind(&config.listen).await?;
+//! Gateway::new(config).await?.serve(listener).await?;
+//! # Ok(()) }
+//! ```
+
+pub mod config;
+pub mod error;
+pub mod openai;
+pub mod quota;
+pub mod server;
+
+pub use config::GatewayConfig;
+pub use error::GatewayError;
+pub use server::{Gateway, CACHE_HEADER};
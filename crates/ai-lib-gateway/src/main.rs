@@ -0,0 +1,101 @@
+//! ai-lib-gateway — OpenAI 兼容网关服务
+//!
+//! Usage:
+//!   ai-lib-gateway --config <file> [--listen <addr>] [--allow-anonymous]
+
+use ai_lib_gateway::{Gateway, GatewayConfig};
+
+fn print_usage() {
+    println!(
+        r#"ai-lib-gateway — OpenAI-compatible gateway over AI-Protocol providers
+
+USAGE:
+    ai-lib-gateway --config <file> [--listen <addr>] [--allow-anonymous]
+
+OPTIONS:
+    --config <file>     Gateway YAML configuration
+    --listen <addr>     Bind address (overrides `listen` in the config)
+    --allow-anonymous   Serve unauthenticated requests when no tenants are configured
+    --help              Show this help message
+
+ENVIRONMENT:
+    AI_PROTOCOL_DIR     Protocol repository root path (when `protocol_path` is unset)
+    RUST_LOG            Log filter, e.g. `info,ai_lib_gateway=debug`"#
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut config_path = None;
+    let mut listen = None;
+    let mut allow_anonymous = false;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--config" | "-c" if i + 1 < args.len() => {
+                config_path = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--listen" | "-l" if i + 1 < args.len() => {
+                listen = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--allow-anonymous" => allow_anonymous = true,
+            "--help" | "-h" => {
+                print_usage();
+                return;
+            }
+            other => {
+                eprintln!("Unknown or incomplete argument: {other}");
+                print_usage();
+                std::process::exit(1);
+            }
+        }
+        i += 1;
+    }
+    let Some(config_path) = config_path else {
+        print_usage();
+        std::process::exit(1);
+    };
+
+    let mut config = match GatewayConfig::from_file(&config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(listen) = listen {
+        config.listen = listen;
+    }
+    config.allow_anonymous |= allow_anonymous;
+    let listener = match tokio::net::TcpListener::bind(&config.listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("cannot bind {}: {e}", config.listen);
+            std::process::exit(1);
+        }
+    };
+    let gateway = match Gateway::new(config).await {
+        Ok(gateway) => gateway,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    if let Ok(addr) = listener.local_addr() {
+        tracing::info!("ai-lib-gateway listening on http://{addr}");
+    }
+    if let Err(e) = gateway.serve(listener).await {
+        eprintln!("server error: {e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,476 @@
+//! OpenAI 线格式：请求解析、响应与 SSE 分块编码。
+//!
+//! OpenAI wire format: request parsing into ai-lib types, `chat.completion`
+//! bodies from [`UnifiedResponse`], and `chat.completion.chunk` re-encoding of
+//! [`StreamingEvent`]s.
+
+use crate::error::GatewayError;
+use ai_lib_rust::client::UnifiedResponse;
+use ai_lib_rust::types::events::StreamingEvent;
+use ai_lib_rust::types::message::{ContentBlock, ImageSource, MessageContent, MessageRole};
+use ai_lib_rust::types::tool::{ToolCall, ToolDefinition};
+use ai_lib_rust::Message;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// `POST /v1/chat/completions` body. Unknown fields are accepted and ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub max_completion_tokens: Option<u32>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(default)]
+    pub tools: Option<Vec<ToolDefinition>>,
+    #[serde(default)]
+    pub tool_choice: Option<Value>,
+}
+
+impl ChatCompletionRequest {
+    pub fn max_tokens(&self) -> Option<u32> {
+        self.max_completion_tokens.or(self.max_tokens)
+    }
+
+    pub fn include_usage(&self) -> bool {
+        self.stream_options
+            .as_ref()
+            .map(|o| o.include_usage)
+            .unwrap_or(false)
+    }
+
+    pub fn to_messages(&self) -> Result<Vec<Message>, GatewayError> {
+        self.messages.iter().map(ChatMessage::to_message).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Option<Value>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<WireToolCall>>,
+    #[serde(default)]
+    pub tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WireToolCall {
+    pub id: String,
+    pub function: WireFunctionCall,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WireFunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+impl ChatMessage {
+    pub fn to_message(&self) -> Result<Message, GatewayError> {
+        let role = match self.role.as_str() {
+            "system" | "developer" => MessageRole::System,
+            "user" => MessageRole::User,
+            "assistant" => MessageRole::Assistant,
+            "tool" => MessageRole::Tool,
+            other => {
+                return Err(GatewayError::invalid_request(format!(
+                    "unsupported message role '{}'",
+                    other
+                )))
+            }
+        };
+        let mut blocks = match &self.content {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::String(text)) => vec![ContentBlock::text(text.clone())],
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .map(content_part)
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => {
+                return Err(GatewayError::invalid_request(
+                    "message content must be a string or an array of parts",
+                ))
+            }
+        };
+        for call in self.tool_calls.iter().flatten() {
+            let input = serde_json::from_str(&call.function.arguments)
+                .unwrap_or_else(|_| Value::String(call.function.arguments.clone()));
+            blocks.push(ContentBlock::ToolUse {
+                id: call.id.clone(),
+                name: call.function.name.clone(),
+                input,
+            });
+        }
+        let content = match blocks.as_slice() {
+            [] => MessageContent::text(""),
+            [ContentBlock::Text { text }] => MessageContent::text(text.clone()),
+            _ if blocks
+                .iter()
+                .all(|b| matches!(b, ContentBlock::Text { .. })) =>
+            {
+                MessageContent::text(text_of(&blocks))
+            }
+            _ => MessageContent::blocks(blocks),
+        };
+        let mut message = Message::with_content(role, content);
+        message.tool_call_id = self.tool_call_id.clone();
+        Ok(message)
+    }
+}
+
+fn text_of(blocks: &[ContentBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|b| match b {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn content_part(part: &Value) -> Result<ContentBlock, GatewayError> {
+    match part["type"].as_str() {
+        Some("text") => Ok(ContentBlock::text(
+            part["text"].as_str().unwrap_or_default(),
+        )),
+        Some("image_url") => {
+            let url = part["image_url"]["url"]
+                .as_str()
+                .or_else(|| part["image_url"].as_str())
+                .ok_or_else(|| GatewayError::invalid_request("image_url part without url"))?;
+            Ok(image_block(url))
+        }
+        other => Err(GatewayError::invalid_request(format!(
+            "unsupported content part type {:?}",
+            other.unwrap_or("<missing>")
+        ))),
+    }
+}
+
+/// `data:<mime>;base64,<data>` becomes an inline image; anything else is passed as a URL.
+fn image_block(url: &str) -> ContentBlock {
+    if let Some(rest) = url.strip_prefix("data:") {
+        if let Some((meta, data)) = rest.split_once(',') {
+            if let Some(mime) = meta.strip_suffix(";base64") {
+                let mime = (!mime.is_empty()).then(|| mime.to_string());
+                return ContentBlock::image_base64(data.to_string(), mime);
+            }
+        }
+    }
+    ContentBlock::Image {
+        source: ImageSource {
+            source_type: "url".to_string(),
+            media_type: None,
+            data: url.to_string(),
+        },
+    }
+}
+
+/// `POST /v1/embeddings` body.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: EmbeddingInput,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl EmbeddingInput {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            EmbeddingInput::One(s) => vec![s],
+            EmbeddingInput::Many(v) => v,
+        }
+    }
+}
+
+/// Normalise provider usage (`input_tokens` / `output_tokens` style included)
+/// to OpenAI's `prompt_tokens` / `completion_tokens` / `total_tokens`.
+pub fn openai_usage(usage: &Value) -> Value {
+    let field = |names: &[&str]| names.iter().find_map(|n| usage[*n].as_u64());
+    let prompt = field(&["prompt_tokens", "input_tokens", "promptTokenCount"]).unwrap_or(0);
+    let completion =
+        field(&["completion_tokens", "output_tokens", "candidatesTokenCount"]).unwrap_or(0);
+    let total = field(&["total_tokens", "totalTokenCount"]).unwrap_or(prompt + completion);
+    json!({
+        "prompt_tokens": prompt,
+        "completion_tokens": completion,
+        "total_tokens": total,
+    })
+}
+
+/// Total tokens reported in provider usage, for quota accounting.
+pub fn total_tokens(usage: Option<&Value>) -> u64 {
+    usage
+        .map(|u| openai_usage(u)["total_tokens"].as_u64().unwrap_or(0))
+        .unwrap_or(0)
+}
+
+fn wire_tool_call(call: &ToolCall) -> Value {
+    let arguments = match &call.arguments {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    json!({
+        "id": call.id,
+        "type": "function",
+        "function": {"name": call.name, "arguments": arguments},
+    })
+}
+
+/// Render a complete `chat.completion` object.
+pub fn completion_body(id: &str, created: u64, model: &str, resp: &UnifiedResponse) -> Value {
+    let mut message = json!({"role": "assistant", "content": resp.content});
+    let finish_reason = if resp.tool_calls.is_empty() {
+        "stop"
+    } else {
+        if resp.content.is_empty() {
+            message["content"] = Value::Null;
+        }
+        message["tool_calls"] = resp.tool_calls.iter().map(wire_tool_call).collect();
+        "tool_calls"
+    };
+    let mut body = json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": created,
+        "model": model,
+        "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}],
+    });
+    if let Some(usage) = &resp.usage {
+        body["usage"] = openai_usage(usage);
+    }
+    body
+}
+
+/// Re-encodes a [`StreamingEvent`] stream as `chat.completion.chunk` objects.
+#[derive(Debug)]
+pub struct ChunkEncoder {
+    id: String,
+    created: u64,
+    model: String,
+    tool_indices: HashMap<String, u32>,
+    finish_reason: Option<String>,
+    usage: Option<Value>,
+}
+
+impl ChunkEncoder {
+    pub fn new(id: impl Into<String>, created: u64, model: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            created,
+            model: model.into(),
+            tool_indices: HashMap::new(),
+            finish_reason: None,
+            usage: None,
+        }
+    }
+
+    /// Provider usage seen so far (from `Metadata` events).
+    pub fn usage(&self) -> Option<&Value> {
+        self.usage.as_ref()
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+        })
+    }
+
+    /// The opening chunk carrying the assistant role.
+    pub fn start(&self) -> Value {
+        self.chunk(json!({"role": "assistant", "content": ""}), None)
+    }
+
+    fn tool_index(&mut self, tool_call_id: &str, hint: Option<u32>) -> (u32, bool) {
+        if let Some(&idx) = self.tool_indices.get(tool_call_id) {
+            return (idx, false);
+        }
+        let idx = hint.unwrap_or(self.tool_indices.len() as u32);
+        self.tool_indices.insert(tool_call_id.to_string(), idx);
+        (idx, true)
+    }
+
+    /// Encode one event; events with no OpenAI counterpart yield `None`.
+    pub fn encode(&mut self, event: StreamingEvent) -> Option<Value> {
+        match event {
+            StreamingEvent::PartialContentDelta { content, .. } if !content.is_empty() => {
+                Some(self.chunk(json!({"content": content}), None))
+            }
+            StreamingEvent::ThinkingDelta { thinking, .. } if !thinking.is_empty() => {
+                Some(self.chunk(json!({"reasoning_content": thinking}), None))
+            }
+            StreamingEvent::ToolCallStarted {
+                tool_call_id,
+                tool_name,
+                index,
+            } => {
+                let (idx, _) = self.tool_index(&tool_call_id, index);
+                Some(self.chunk(
+                    json!({"tool_calls": [{
+                        "index": idx,
+                        "id": tool_call_id,
+                        "type": "function",
+                        "function": {"name": tool_name, "arguments": ""},
+                    }]}),
+                    None,
+                ))
+            }
+            StreamingEvent::PartialToolCall {
+                tool_call_id,
+                arguments,
+                index,
+                ..
+            } => {
+                let (idx, new) = self.tool_index(&tool_call_id, index);
+                let mut call = json!({"index": idx, "function": {"arguments": arguments}});
+                if new {
+                    call["id"] = json!(tool_call_id);
+                    call["type"] = json!("function");
+                }
+                Some(self.chunk(json!({"tool_calls": [call]}), None))
+            }
+            StreamingEvent::Metadata {
+                usage,
+                finish_reason,
+                ..
+            } => {
+                if usage.is_some() {
+                    self.usage = usage;
+                }
+                if finish_reason.is_some() {
+                    self.finish_reason = finish_reason;
+                }
+                None
+            }
+            StreamingEvent::FinalCandidate { finish_reason, .. } => {
+                self.finish_reason = Some(finish_reason);
+                None
+            }
+            StreamingEvent::StreamEnd { finish_reason } => {
+                if finish_reason.is_some() {
+                    self.finish_reason = finish_reason;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Closing chunks: the `finish_reason` chunk, plus a usage-only chunk when requested.
+    pub fn finish(&self, include_usage: bool) -> Vec<Value> {
+        let default_reason = if self.tool_indices.is_empty() {
+            "stop"
+        } else {
+            "tool_calls"
+        };
+        let reason = self.finish_reason.as_deref().unwrap_or(default_reason);
+        let mut chunks = vec![self.chunk(json!({}), Some(reason))];
+        if include_usage {
+            let mut usage = self.chunk(json!({}), None);
+            usage["choices"] = json!([]);
+            usage["usage"] = self.usage.as_ref().map(openai_usage).unwrap_or(Value::Null);
+            chunks.push(usage);
+        }
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_conversion() {
+        let req: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "m",
+            "messages": [
+                {"role": "developer", "content": "be brief"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "what is"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+                ]},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "c1", "type": "function", "function": {"name": "f", "arguments": "{\"a\":1}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "c1", "content": "42"}
+            ],
+            "max_completion_tokens": 10,
+            "user": "ignored"
+        }))
+        .unwrap();
+        let messages = req.to_messages().unwrap();
+        assert_eq!(req.max_tokens(), Some(10));
+        assert_eq!(messages[0].role, MessageRole::System);
+        assert!(messages[1].contains_image());
+        match &messages[2].content {
+            MessageContent::Blocks(blocks) => assert!(matches!(
+                &blocks[0],
+                ContentBlock::ToolUse { input, .. } if input["a"] == 1
+            )),
+            other => panic!("unexpected content {other:?}"),
+        }
+        assert_eq!(messages[3].tool_call_id.as_deref(), Some("c1"));
+    }
+
+    #[test]
+    fn test_chunk_encoder_tool_calls_and_usage() {
+        let mut enc = ChunkEncoder::new("id", 1, "m");
+        let started = enc
+            .encode(StreamingEvent::ToolCallStarted {
+                tool_call_id: "c1".into(),
+                tool_name: "f".into(),
+                index: None,
+            })
+            .unwrap();
+        assert_eq!(started["choices"][0]["delta"]["tool_calls"][0]["id"], "c1");
+        let partial = enc
+            .encode(StreamingEvent::PartialToolCall {
+                tool_call_id: "c1".into(),
+                arguments: "{}".into(),
+                index: None,
+                is_complete: None,
+            })
+            .unwrap();
+        let call = &partial["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(call["index"], 0);
+        assert!(call.get("id").is_none());
+        assert!(enc
+            .encode(StreamingEvent::Metadata {
+                usage: Some(json!({"input_tokens": 3, "output_tokens": 4})),
+                finish_reason: None,
+                stop_reason: None,
+            })
+            .is_none());
+        let end = enc.finish(true);
+        assert_eq!(end[0]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(end[1]["usage"]["total_tokens"], 7);
+    }
+}
@@ -0,0 +1,135 @@
+//! 租户配额：固定一分钟窗口的请求数与令牌数计数。
+//!
+//! Per-tenant fixed one-minute windows. Requests are counted on admission;
+//! tokens are counted once the upstream reports usage, so a request that
+//! starts under the token limit may finish over it and block the rest of the
+//! window.
+
+use crate::config::QuotaSettings;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started: Instant,
+    requests: u64,
+    tokens: u64,
+}
+
+impl Window {
+    fn fresh(now: Instant) -> Self {
+        Self {
+            started: now,
+            requests: 0,
+            tokens: 0,
+        }
+    }
+}
+
+/// Why a request was refused and when the window resets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub limit: &'static str,
+    pub retry_after: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct QuotaTracker {
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl QuotaTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one request against `tenant`, or refuse it if a limit is reached.
+    pub fn admit(&self, tenant: &str, limits: &QuotaSettings) -> Result<(), QuotaExceeded> {
+        self.admit_at(tenant, limits, Instant::now())
+    }
+
+    fn admit_at(
+        &self,
+        tenant: &str,
+        limits: &QuotaSettings,
+        now: Instant,
+    ) -> Result<(), QuotaExceeded> {
+        if limits.requests_per_minute.is_none() && limits.tokens_per_minute.is_none() {
+            return Ok(());
+        }
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let window = windows
+            .entry(tenant.to_string())
+            .or_insert_with(|| Window::fresh(now));
+        if now.duration_since(window.started) >= WINDOW {
+            *window = Window::fresh(now);
+        }
+        let retry_after = WINDOW.saturating_sub(now.duration_since(window.started));
+        if matches!(limits.requests_per_minute, Some(max) if window.requests >= max) {
+            return Err(QuotaExceeded {
+                limit: "requests_per_minute",
+                retry_after,
+            });
+        }
+        if matches!(limits.tokens_per_minute, Some(max) if window.tokens >= max) {
+            return Err(QuotaExceeded {
+                limit: "tokens_per_minute",
+                retry_after,
+            });
+        }
+        window.requests += 1;
+        Ok(())
+    }
+
+    /// Add reported token usage to the tenant's current window.
+    pub fn record_tokens(&self, tenant: &str, tokens: u64) {
+        if tokens == 0 {
+            return;
+        }
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(window) = windows.get_mut(tenant) {
+            window.tokens += tokens;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_limit_resets_with_window() {
+        let tracker = QuotaTracker::new();
+        let limits = QuotaSettings {
+            requests_per_minute: Some(2),
+            tokens_per_minute: None,
+        };
+        let t0 = Instant::now();
+        assert!(tracker.admit_at("a", &limits, t0).is_ok());
+        assert!(tracker.admit_at("a", &limits, t0).is_ok());
+        let err = tracker
+            .admit_at("a", &limits, t0 + Duration::from_secs(20))
+            .unwrap_err();
+        assert_eq!(err.limit, "requests_per_minute");
+        assert_eq!(err.retry_after, Duration::from_secs(40));
+        assert!(tracker.admit_at("b", &limits, t0).is_ok());
+        assert!(tracker.admit_at("a", &limits, t0 + WINDOW).is_ok());
+    }
+
+    #[test]
+    fn test_token_limit_applies_after_usage_recorded() {
+        let tracker = QuotaTracker::new();
+        let limits = QuotaSettings {
+            requests_per_minute: None,
+            tokens_per_minute: Some(100),
+        };
+        let t0 = Instant::now();
+        assert!(tracker.admit_at("a", &limits, t0).is_ok());
+        tracker.record_tokens("a", 150);
+        let err = tracker.admit_at("a", &limits, t0).unwrap_err();
+        assert_eq!(err.limit, "tokens_per_minute");
+    }
+}
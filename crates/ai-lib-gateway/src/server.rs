@@ -0,0 +1,502 @@
+//! 网关服务：虚拟密钥鉴权、配额、护栏、缓存与 OpenAI 路由处理。
+//!
+//! The [`Gateway`] state and its axum routes:
+//!
+//! | Route | Behaviour |
+//! |-------|-----------|
+//! | `POST /v1/chat/completions` | JSON or SSE (`stream: true`) via [`AiClient`] |
+//! | `POST /v1/embeddings` | Batched via [`EmbeddingClient`] |
+//! | `GET /v1/models` | Models visible to the caller's tenant |
+//! | `GET /healthz` | Liveness probe, no auth |
+//!
+//! Each chat / embedding request runs: virtual key → tenant, model allow-list,
+//! request quota, input guardrails, response cache (non-streaming chat only),
+//! then the upstream call with the route's fallbacks.
+
+use crate::config::{GatewayConfig, ModelRoute, TenantConfig};
+use crate::error::GatewayError;
+use crate::openai::{self, ChatCompletionRequest, ChunkEncoder, EmbeddingRequest};
+use crate::quota::QuotaTracker;
+use ai_lib_rust::cache::{CacheConfig, CacheKeyGenerator, CacheManager, MemoryCache};
+use ai_lib_rust::embeddings::EmbeddingClient;
+use ai_lib_rust::guardrails::{CheckResult, FilterAction, Guardrails, GuardrailsConfig};
+use ai_lib_rust::protocol::{ProtocolLoader, ProtocolManifest};
+use ai_lib_rust::types::events::StreamingEvent;
+use ai_lib_rust::{AiClient, AiClientBuilder};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+
+/// Response header reporting `hit` / `miss` when the chat cache is enabled.
+pub const CACHE_HEADER: &str = "x-ai-lib-cache";
+
+struct ChatRoute {
+    route: ModelRoute,
+    client: AiClient,
+}
+
+struct EmbeddingRoute {
+    route: ModelRoute,
+    client: EmbeddingClient,
+}
+
+/// Shared gateway state: upstream clients per public model name plus policy.
+pub struct Gateway {
+    chat: HashMap<String, ChatRoute>,
+    embeddings: HashMap<String, EmbeddingRoute>,
+    /// Virtual keys, compared in constant time.
+    tenants: Vec<(String, Arc<TenantConfig>)>,
+    open_access: Option<Arc<TenantConfig>>,
+    quotas: Arc<QuotaTracker>,
+    cache: Option<CacheManager>,
+    guardrails: Option<Guardrails>,
+}
+
+impl Gateway {
+    /// Load manifests and build one upstream client per configured model.
+    ///
+    /// Fails fast on unknown providers or missing credentials so a bad config
+    /// never reaches the listening state.
+    pub async fn new(config: GatewayConfig) -> Result<Self, GatewayError> {
+        config.validate()?;
+        let mut loader = ProtocolLoader::new();
+        if let Some(path) = &config.protocol_path {
+            loader = loader.with_base_path(path);
+        }
+
+        let mut manifests: HashMap<String, ProtocolManifest> = HashMap::new();
+        for provider in &config.providers {
+            let mut manifest = loader
+                .load_provider(&provider.id)
+                .await
+                .map_err(|e| GatewayError::config(format!("provider '{}': {}", provider.id, e)))?;
+            if let Some(base_url) = &provider.base_url {
+                manifest.endpoint.base_url = base_url.clone();
+            }
+            manifests.insert(provider.id.clone(), manifest);
+        }
+
+        let mut chat = HashMap::new();
+        for route in &config.models {
+            let mut builder = AiClientBuilder::new().with_fallbacks(route.fallbacks.clone());
+            if let Some(path) = &config.protocol_path {
+                builder = builder.protocol_path(path.clone());
+            }
+            if let Some(key) = route.credential()? {
+                builder = builder.api_key(key);
+            }
+            for manifest in manifests.values() {
+                builder = builder.protocol_manifest(manifest.clone());
+            }
+            let client = builder
+                .build(&route.target)
+                .await
+                .map_err(|e| GatewayError::config(format!("model '{}': {}", route.name, e)))?;
+            chat.insert(
+                route.name.clone(),
+                ChatRoute {
+                    route: route.clone(),
+                    client,
+                },
+            );
+        }
+
+        let mut embeddings = HashMap::new();
+        for route in &config.embedding_models {
+            let manifest = match manifests.get(route.provider()) {
+                Some(manifest) => manifest.clone(),
+                None => loader
+                    .load_model(&route.target)
+                    .await
+                    .map_err(|e| GatewayError::config(format!("model '{}': {}", route.name, e)))?,
+            };
+            let model_id = route
+                .target
+                .split_once('/')
+                .map(|(_, id)| id)
+                .unwrap_or(&route.target);
+            let mut builder = EmbeddingClient::builder();
+            if let Some(key) = route.credential()? {
+                builder = builder.api_key(key);
+            }
+            let client = async { builder.from_manifest(&manifest, model_id)?.build().await }
+                .await
+                .map_err(|e| GatewayError::config(format!("model '{}': {}", route.name, e)))?;
+            embeddings.insert(
+                route.name.clone(),
+                EmbeddingRoute {
+                    route: route.clone(),
+                    client,
+                },
+            );
+        }
+
+        let mut tenants = Vec::new();
+        for tenant in &config.tenants {
+            let tenant = Arc::new(tenant.clone());
+            for key in &tenant.keys {
+                tenants.push((key.clone(), tenant.clone()));
+            }
+        }
+        let open_access = if config.tenants.is_empty() {
+            if !config.allow_anonymous {
+                return Err(GatewayError::config(
+                    "no tenants configured; add tenants with virtual keys or set \
+                     `allow_anonymous: true` (--allow-anonymous) to serve unauthenticated requests",
+                ));
+            }
+            tracing::warn!("allow_anonymous: gateway accepts unauthenticated requests");
+            Some(Arc::new(TenantConfig {
+                id: "anonymous".to_string(),
+                ..Default::default()
+            }))
+        } else {
+            None
+        };
+
+        let cache = config.cache.enabled.then(|| {
+            CacheManager::new(
+                CacheConfig::new()
+                    .with_enabled(true)
+                    .with_ttl(Duration::from_secs(config.cache.ttl_secs)),
+                Box::new(MemoryCache::new(config.cache.max_entries)),
+            )
+        });
+
+        let guardrails = config.guardrails.as_ref().map(|g| {
+            let mut builder = GuardrailsConfig::builder().enable_pii_detection(g.pii_detection);
+            for keyword in &g.block_keywords {
+                builder = builder.add_keyword_filter(keyword.clone(), FilterAction::Block);
+            }
+            for pattern in &g.block_patterns {
+                builder = builder.add_pattern_filter(pattern.clone(), FilterAction::Block);
+            }
+            Guardrails::new(builder.build())
+        });
+
+        Ok(Self {
+            chat,
+            embeddings,
+            tenants,
+            open_access,
+            quotas: Arc::new(QuotaTracker::new()),
+            cache,
+            guardrails,
+        })
+    }
+
+    /// Axum router serving the OpenAI-compatible routes.
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/v1/chat/completions", post(chat_completions))
+            .route("/v1/embeddings", post(embeddings))
+            .route("/v1/models", get(list_models))
+            .route("/healthz", get(|| async { "ok" }))
+            .with_state(self)
+    }
+
+    /// Serve on an already-bound listener until the process is stopped.
+    pub async fn serve(self, listener: tokio::net::TcpListener) -> std::io::Result<()> {
+        axum::serve(listener, Arc::new(self).router()).await
+    }
+
+    fn authenticate(&self, headers: &HeaderMap) -> Result<Arc<TenantConfig>, GatewayError> {
+        if let Some(tenant) = &self.open_access {
+            return Ok(tenant.clone());
+        }
+        let key = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or_else(GatewayError::unauthorized)?;
+        // Check every key so the response time does not reveal which one matched.
+        let mut found = None;
+        for (candidate, tenant) in &self.tenants {
+            if bool::from(candidate.as_bytes().ct_eq(key.as_bytes())) {
+                found = Some(tenant.clone());
+            }
+        }
+        found.ok_or_else(GatewayError::unauthorized)
+    }
+
+    fn admit(&self, tenant: &TenantConfig) -> Result<(), GatewayError> {
+        self.quotas.admit(&tenant.id, &tenant.quota).map_err(|e| {
+            GatewayError::quota_exceeded(
+                format!("Tenant '{}' exceeded {}", tenant.id, e.limit),
+                e.retry_after,
+            )
+        })
+    }
+
+    fn check_input(
+        &self,
+        check: impl FnOnce(&Guardrails) -> CheckResult,
+    ) -> Result<(), GatewayError> {
+        let Some(guardrails) = &self.guardrails else {
+            return Ok(());
+        };
+        let result = check(guardrails);
+        if !result.is_blocked() {
+            return Ok(());
+        }
+        let kinds: BTreeSet<String> = result
+            .blocking_violations()
+            .iter()
+            .map(|v| format!("{:?}", v.violation_type).to_lowercase())
+            .collect();
+        Err(GatewayError::content_policy(format!(
+            "Request blocked by content policy ({})",
+            kinds.into_iter().collect::<Vec<_>>().join(", ")
+        )))
+    }
+
+    async fn chat_completions(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Response, GatewayError> {
+        let tenant = self.authenticate(headers)?;
+        let req: ChatCompletionRequest = serde_json::from_slice(body)
+            .map_err(|e| GatewayError::invalid_request(format!("invalid request body: {}", e)))?;
+        let route = self
+            .chat
+            .get(&req.model)
+            .filter(|_| tenant.allows(&req.model))
+            .ok_or_else(|| GatewayError::model_not_found(&req.model))?;
+        self.admit(&tenant)?;
+        let messages = req.to_messages()?;
+        self.check_input(|g| g.check_messages(&messages))?;
+
+        let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+        let created = unix_now();
+        let mut builder = route.client.chat().messages(messages.clone());
+        if let Some(t) = req.temperature {
+            builder = builder.temperature(t);
+        }
+        if let Some(max) = req.max_tokens() {
+            builder = builder.max_tokens(max);
+        }
+        if let Some(tools) = req.tools.clone() {
+            builder = builder.tools(tools);
+        }
+        if let Some(choice) = req.tool_choice.clone() {
+            builder = builder.tool_choice(choice);
+        }
+
+        if req.stream {
+            let stream = builder.execute_stream().await.map_err(|e| {
+                tracing::warn!(model = %route.route.target, error = %e, "upstream stream failed");
+                GatewayError::upstream(&e)
+            })?;
+            let encoder = ChunkEncoder::new(id, created, req.model.clone());
+            return Ok(self.sse(stream, encoder, tenant, req.include_usage()));
+        }
+
+        let cache_key = self.cache.as_ref().map(|_| {
+            let messages: Vec<Value> = messages
+                .iter()
+                .map(|m| serde_json::to_value(m).unwrap_or_default())
+                .collect();
+            let salt = json!({
+                "tenant": tenant.id,
+                "max_tokens": req.max_tokens(),
+                "tools": req.tools,
+                "tool_choice": req.tool_choice,
+            });
+            CacheKeyGenerator::new()
+                .with_salt(salt.to_string())
+                .generate(Some(&req.model), &messages, req.temperature, None)
+        });
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key) {
+            if let Ok(Some(mut cached)) = cache.get::<Value>(key).await {
+                cached["id"] = json!(id);
+                cached["created"] = json!(created);
+                return Ok(with_cache_header(Json(cached).into_response(), "hit"));
+            }
+        }
+
+        let resp = builder.execute().await.map_err(|e| {
+            tracing::warn!(model = %route.route.target, error = %e, "upstream call failed");
+            GatewayError::upstream(&e)
+        })?;
+        self.quotas
+            .record_tokens(&tenant.id, openai::total_tokens(resp.usage.as_ref()));
+        let body = openai::completion_body(&id, created, &req.model, &resp);
+        match (&self.cache, &cache_key) {
+            (Some(cache), Some(key)) => {
+                if let Err(e) = cache.set(key, &body).await {
+                    tracing::warn!(error = %e, "failed to cache chat completion");
+                }
+                Ok(with_cache_header(Json(body).into_response(), "miss"))
+            }
+            _ => Ok(Json(body).into_response()),
+        }
+    }
+
+    /// Pump upstream events into an SSE body from a background task so a
+    /// client disconnect drops (and cancels) the upstream stream.
+    fn sse(
+        &self,
+        mut stream: ai_lib_rust::BoxStream<'static, StreamingEvent>,
+        mut encoder: ChunkEncoder,
+        tenant: Arc<TenantConfig>,
+        include_usage: bool,
+    ) -> Response {
+        let (mut tx, rx) = futures::channel::mpsc::channel::<Event>(16);
+        let data = |v: &Value| Event::default().data(v.to_string());
+        let quotas = self.quotas.clone();
+        tokio::spawn(async move {
+            if tx.send(data(&encoder.start())).await.is_err() {
+                return;
+            }
+            let mut failure = None;
+            while let Some(event) = stream.next().await {
+                let chunk = match event {
+                    Ok(StreamingEvent::StreamError { error, .. }) => {
+                        failure = Some(GatewayError::stream_aborted(match error {
+                            Value::String(s) => s,
+                            other => other.to_string(),
+                        }));
+                        break;
+                    }
+                    Ok(event) => encoder.encode(event),
+                    Err(e) => {
+                        failure = Some(GatewayError::upstream(&e));
+                        break;
+                    }
+                };
+                if let Some(chunk) = chunk {
+                    if tx.send(data(&chunk)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            quotas.record_tokens(&tenant.id, openai::total_tokens(encoder.usage()));
+            if let Some(err) = failure {
+                // Headers are already sent: report in-band like OpenAI and omit [DONE].
+                tracing::warn!(error = %err, "upstream stream aborted");
+                let _ = tx.send(data(&err.body())).await;
+                return;
+            }
+            for chunk in encoder.finish(include_usage) {
+                if tx.send(data(&chunk)).await.is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(Event::default().data("[DONE]")).await;
+        });
+        Sse::new(rx.map(Ok::<_, Infallible>)).into_response()
+    }
+
+    async fn embeddings(&self, headers: &HeaderMap, body: &[u8]) -> Result<Response, GatewayError> {
+        let tenant = self.authenticate(headers)?;
+        let req: EmbeddingRequest = serde_json::from_slice(body)
+            .map_err(|e| GatewayError::invalid_request(format!("invalid request body: {}", e)))?;
+        let route = self
+            .embeddings
+            .get(&req.model)
+            .filter(|_| tenant.allows(&req.model))
+            .ok_or_else(|| GatewayError::model_not_found(&req.model))?;
+        self.admit(&tenant)?;
+        let inputs = req.input.into_vec();
+        if inputs.is_empty() {
+            return Err(GatewayError::invalid_request("input must not be empty"));
+        }
+        self.check_input(|g| {
+            inputs.iter().fold(CheckResult::passed(), |acc, text| {
+                acc.merge(g.check_input(text))
+            })
+        })?;
+
+        let resp = route.client.embed_batch(&inputs).await.map_err(|e| {
+            tracing::warn!(model = %route.route.target, error = %e, "upstream embeddings failed");
+            GatewayError::upstream(&e)
+        })?;
+        self.quotas
+            .record_tokens(&tenant.id, u64::from(resp.usage.total_tokens));
+        let data: Vec<Value> = resp
+            .embeddings
+            .iter()
+            .map(|e| json!({"object": "embedding", "index": e.index, "embedding": e.vector}))
+            .collect();
+        Ok(Json(json!({
+            "object": "list",
+            "data": data,
+            "model": req.model,
+            "usage": {
+                "prompt_tokens": resp.usage.prompt_tokens,
+                "total_tokens": resp.usage.total_tokens,
+            },
+        }))
+        .into_response())
+    }
+
+    fn list_models(&self, headers: &HeaderMap) -> Result<Response, GatewayError> {
+        let tenant = self.authenticate(headers)?;
+        let routes = self
+            .chat
+            .values()
+            .map(|c| &c.route)
+            .chain(self.embeddings.values().map(|e| &e.route));
+        let mut data: Vec<Value> = routes
+            .filter(|r| tenant.allows(&r.name))
+            .map(|r| json!({"id": r.name, "object": "model", "created": 0, "owned_by": r.provider()}))
+            .collect();
+        data.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+        Ok(Json(json!({"object": "list", "data": data})).into_response())
+    }
+}
+
+async fn chat_completions(
+    State(gateway): State<Arc<Gateway>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    gateway
+        .chat_completions(&headers, &body)
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+async fn embeddings(
+    State(gateway): State<Arc<Gateway>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    gateway
+        .embeddings(&headers, &body)
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+async fn list_models(State(gateway): State<Arc<Gateway>>, headers: HeaderMap) -> Response {
+    gateway
+        .list_models(&headers)
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+fn with_cache_header(mut response: Response, status: &'static str) -> Response {
+    response
+        .headers_mut()
+        .insert(CACHE_HEADER, HeaderValue::from_static(status));
+    response
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,395 @@
+//! End-to-end tests: OpenAI-wire clients → gateway → mock upstream provider
+
+use ai_lib_gateway::{Gateway, GatewayConfig, CACHE_HEADER};
+use mockito::Matcher;
+use serde_json::{json, Value};
+
+const KEY_A: &str = "sk-gw-team-a";
+const KEY_B: &str = "sk-gw-team-b";
+
+fn protocols() -> String {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("ai-lib-rust")
+        .join("tests")
+        .join("fixtures")
+        .join("protocols")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Start a gateway whose `openai` provider points at `upstream`.
+async fn start(upstream: &str, extra: &str) -> String {
+    let yaml = format!(
+        r#"
+protocol_path: "{protocols}"
+providers:
+  - id: openai
+    base_url: "{upstream}"
+models:
+  - name: smart
+    target: openai/gpt-primary
+    fallbacks: [openai/gpt-backup]
+    api_key: sk-upstream
+  - name: other
+    target: openai/gpt-other
+    api_key: sk-upstream
+embedding_models:
+  - name: embed
+    target: openai/text-embedding-3-small
+    api_key: sk-upstream
+tenants:
+  - id: team-a
+    keys: ["{KEY_A}"]
+  - id: team-b
+    keys: ["{KEY_B}"]
+    models: [smart]
+    quota: {{ requests_per_minute: 2 }}
+{extra}
+"#,
+        protocols = protocols().replace('\\', "/"),
+    );
+    let config = GatewayConfig::from_yaml(&yaml).expect("config");
+    let gateway = Gateway::new(config).await.expect("gateway");
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(gateway.serve(listener));
+    format!("http://{addr}")
+}
+
+async fn post(gateway: &str, path: &str, key: &str, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{gateway}{path}"))
+        .bearer_auth(key)
+        .json(&body)
+        .send()
+        .await
+        .expect("gateway reachable")
+}
+
+fn chat(content: &str) -> Value {
+    json!({"model": "smart", "messages": [{"role": "user", "content": content}]})
+}
+
+fn upstream_json(content: &str) -> String {
+    json!({
+        "choices": [{"message": {"role": "assistant", "content": content}, "finish_reason": "stop"}],
+        "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}
+    })
+    .to_string()
+}
+
+fn for_model(model: &str) -> Matcher {
+    Matcher::PartialJson(json!({"model": model}))
+}
+
+#[tokio::test]
+async fn test_chat_completion_translates_request_and_response() {
+    let mut upstream = mockito::Server::new_async().await;
+    let mock = upstream
+        .mock("POST", "/chat/completions")
+        .match_header("authorization", "Bearer sk-upstream")
+        .match_body(Matcher::PartialJson(json!({
+            "model": "gpt-primary",
+            "messages": [{"role": "system", "content": "be brief"}, {"role": "user", "content": "hi"}]
+        })))
+        .with_header("content-type", "application/json")
+        .with_body(upstream_json("hello"))
+        .create_async()
+        .await;
+    let gateway = start(&upstream.url(), "").await;
+
+    let resp = post(
+        &gateway,
+        "/v1/chat/completions",
+        KEY_A,
+        json!({"model": "smart", "messages": [
+            {"role": "developer", "content": "be brief"},
+            {"role": "user", "content": [{"type": "text", "text": "hi"}]}
+        ]}),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    mock.assert_async().await;
+    assert_eq!(body["object"], "chat.completion");
+    assert_eq!(body["model"], "smart");
+    assert!(body["id"].as_str().unwrap().starts_with("chatcmpl-"));
+    assert_eq!(body["choices"][0]["message"]["content"], "hello");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    assert_eq!(body["usage"]["total_tokens"], 5);
+}
+
+#[tokio::test]
+async fn test_streaming_reencodes_openai_chunks() {
+    let mut upstream = mockito::Server::new_async().await;
+    upstream
+        .mock("POST", "/chat/completions")
+        .with_header("content-type", "text/event-stream")
+        .with_body(concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"},\"index\":0}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"},\"index\":0}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{},\"index\":0,\"finish_reason\":\"stop\"}]}\n\n",
+            "data: [DONE]\n\n",
+        ))
+        .create_async()
+        .await;
+    let gateway = start(&upstream.url(), "").await;
+
+    let mut body = chat("hi");
+    body["stream"] = json!(true);
+    body["stream_options"] = json!({"include_usage": true});
+    let resp = post(&gateway, "/v1/chat/completions", KEY_A, body).await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/event-stream"));
+    let text = resp.text().await.unwrap();
+    let frames: Vec<&str> = text
+        .split("\n\n")
+        .filter_map(|f| f.trim().strip_prefix("data: "))
+        .collect();
+    assert_eq!(frames.last(), Some(&"[DONE]"));
+    let chunks: Vec<Value> = frames[..frames.len() - 1]
+        .iter()
+        .map(|f| serde_json::from_str(f).unwrap())
+        .collect();
+    assert!(chunks
+        .iter()
+        .all(|c| c["object"] == "chat.completion.chunk"));
+    assert!(chunks.windows(2).all(|w| w[0]["id"] == w[1]["id"]));
+    assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+    let content: String = chunks
+        .iter()
+        .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+        .collect();
+    assert_eq!(content, "Hello");
+    let finish = &chunks[chunks.len() - 2];
+    assert_eq!(finish["choices"][0]["finish_reason"], "stop");
+    let usage = chunks.last().unwrap();
+    assert_eq!(usage["choices"], json!([]));
+    assert!(usage.get("usage").is_some());
+}
+
+#[tokio::test]
+async fn test_fallback_target_serves_when_primary_fails() {
+    let mut upstream = mockito::Server::new_async().await;
+    let primary = upstream
+        .mock("POST", "/chat/completions")
+        .match_body(for_model("gpt-primary"))
+        .with_status(500)
+        .with_body(r#"{"error":{"message":"down"}}"#)
+        .expect_at_least(1)
+        .create_async()
+        .await;
+    let backup = upstream
+        .mock("POST", "/chat/completions")
+        .match_body(for_model("gpt-backup"))
+        .with_header("content-type", "application/json")
+        .with_body(upstream_json("from backup"))
+        .create_async()
+        .await;
+    let gateway = start(&upstream.url(), "").await;
+
+    let resp = post(&gateway, "/v1/chat/completions", KEY_A, chat("hi")).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "from backup");
+    assert_eq!(body["model"], "smart");
+    primary.assert_async().await;
+    backup.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_virtual_keys_and_model_allow_list() {
+    let mut upstream = mockito::Server::new_async().await;
+    let mock = upstream
+        .mock("POST", "/chat/completions")
+        .expect(0)
+        .create_async()
+        .await;
+    let gateway = start(&upstream.url(), "").await;
+
+    let missing = reqwest::Client::new()
+        .post(format!("{gateway}/v1/chat/completions"))
+        .json(&chat("hi"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 401);
+    let body: Value = missing.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "invalid_api_key");
+
+    let unknown = post(&gateway, "/v1/chat/completions", "sk-nope", chat("hi")).await;
+    assert_eq!(unknown.status(), 401);
+
+    let mut other = chat("hi");
+    other["model"] = json!("other");
+    let denied = post(&gateway, "/v1/chat/completions", KEY_B, other).await;
+    assert_eq!(denied.status(), 404);
+    let body: Value = denied.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "model_not_found");
+
+    let models: Value = reqwest::Client::new()
+        .get(format!("{gateway}/v1/models"))
+        .bearer_auth(KEY_B)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let ids: Vec<&str> = models["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["smart"]);
+    assert_eq!(models["data"][0]["owned_by"], "openai");
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_request_quota_is_per_tenant() {
+    let mut upstream = mockito::Server::new_async().await;
+    upstream
+        .mock("POST", "/chat/completions")
+        .with_header("content-type", "application/json")
+        .with_body(upstream_json("ok"))
+        .create_async()
+        .await;
+    let gateway = start(&upstream.url(), "").await;
+
+    for _ in 0..2 {
+        let ok = post(&gateway, "/v1/chat/completions", KEY_B, chat("hi")).await;
+        assert_eq!(ok.status(), 200);
+    }
+    let limited = post(&gateway, "/v1/chat/completions", KEY_B, chat("hi")).await;
+    assert_eq!(limited.status(), 429);
+    let retry_after: u64 = limited.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+    let body: Value = limited.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "rate_limit_error");
+
+    // Team A has no quota and is unaffected.
+    let other = post(&gateway, "/v1/chat/completions", KEY_A, chat("hi")).await;
+    assert_eq!(other.status(), 200);
+}
+
+#[tokio::test]
+async fn test_guardrails_block_before_upstream() {
+    let mut upstream = mockito::Server::new_async().await;
+    let mock = upstream
+        .mock("POST", "/chat/completions")
+        .expect(0)
+        .create_async()
+        .await;
+    let gateway = start(
+        &upstream.url(),
+        "guardrails: { block_keywords: [\"project aurora\"] }",
+    )
+    .await;
+
+    let resp = post(
+        &gateway,
+        "/v1/chat/completions",
+        KEY_A,
+        chat("Tell me about Project Aurora"),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "content_policy_violation");
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_cache_serves_repeated_request() {
+    let mut upstream = mockito::Server::new_async().await;
+    let mock = upstream
+        .mock("POST", "/chat/completions")
+        .with_header("content-type", "application/json")
+        .with_body(upstream_json("cached answer"))
+        .expect(1)
+        .create_async()
+        .await;
+    let gateway = start(&upstream.url(), "cache: { enabled: true, ttl_secs: 60 }").await;
+
+    let first = post(&gateway, "/v1/chat/completions", KEY_A, chat("same")).await;
+    assert_eq!(first.headers()[CACHE_HEADER], "miss");
+    let first: Value = first.json().await.unwrap();
+    let second = post(&gateway, "/v1/chat/completions", KEY_A, chat("same")).await;
+    assert_eq!(second.headers()[CACHE_HEADER], "hit");
+    let second: Value = second.json().await.unwrap();
+    assert_eq!(second["choices"], first["choices"]);
+    assert_ne!(second["id"], first["id"]);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_embeddings_batch() {
+    let mut upstream = mockito::Server::new_async().await;
+    let mock = upstream
+        .mock("POST", "/embeddings")
+        .match_body(Matcher::PartialJson(json!({
+            "model": "text-embedding-3-small",
+            "input": ["a", "b"]
+        })))
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "object": "list",
+                "data": [
+                    {"object": "embedding", "index": 0, "embedding": [0.1, 0.2]},
+                    {"object": "embedding", "index": 1, "embedding": [0.3, 0.4]}
+                ],
+                "model": "text-embedding-3-small",
+                "usage": {"prompt_tokens": 2, "total_tokens": 2}
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+    let gateway = start(&upstream.url(), "").await;
+
+    let resp = post(
+        &gateway,
+        "/v1/embeddings",
+        KEY_A,
+        json!({"model": "embed", "input": ["a", "b"]}),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    mock.assert_async().await;
+    assert_eq!(body["object"], "list");
+    assert_eq!(body["model"], "embed");
+    assert_eq!(body["data"][1]["index"], 1);
+    assert_eq!(
+        body["data"][1]["embedding"][0].as_f64().unwrap() as f32,
+        0.3
+    );
+    assert_eq!(body["usage"]["total_tokens"], 2);
+}
+
+#[tokio::test]
+async fn test_refuses_to_start_without_tenants_unless_anonymous_allowed() {
+    let yaml = format!(
+        "protocol_path: \"{}\"\nmodels: [{{name: smart, target: openai/gpt-primary, api_key: k}}]\n",
+        protocols().replace('\\', "/")
+    );
+    let err = Gateway::new(GatewayConfig::from_yaml(&yaml).unwrap())
+        .await
+        .err()
+        .expect("no tenants");
+    assert!(err.to_string().contains("allow_anonymous"), "{}", err);
+
+    let open = GatewayConfig::from_yaml(&format!("{yaml}allow_anonymous: true\n")).unwrap();
+    assert!(Gateway::new(open).await.is_ok());
+}
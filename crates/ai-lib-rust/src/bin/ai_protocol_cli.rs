@@ -5,6 +5,16 @@
 //!   ai-protocol-cli info <provider>                Show provider capabilities
 //!   ai-protocol-cli check-compat <manifest>        Check runtime compatibility
 //!   ai-protocol-cli list                           List all available providers
+//!   ai-protocol-cli lint [<manifest>...]           Lint manifests for quality issues
+//!   ai-protocol-cli diff <old> <new>               Semantic diff between two manifests
+//!   ai-protocol-cli migrate <manifest>             Rewrite a V1 manifest as V2
+//!   ai-protocol-cli chat <provider/model>          Interactive chat REPL
+//!   ai-protocol-cli call <provider/model>          One-shot call (stdin / file / --prompt)
+
+#[path = "ai_protocol_cli/chat.rs"]
+mod chat;
+#[path = "ai_protocol_cli/manifest.rs"]
+mod manifest;
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -21,6 +31,11 @@ fn main() {
         "info" => cmd_info(&args[2..]),
         "list" => cmd_list(&args[2..]),
         "check-compat" => cmd_check_compat(&args[2..]),
+        "lint" => manifest::cmd_lint(&args[2..]),
+        "diff" => manifest::cmd_diff(&args[2..]),
+        "migrate" => manifest::cmd_migrate(&args[2..]),
+        "chat" => chat::cmd_chat(&args[2..]),
+        "call" => chat::cmd_call(&args[2..]),
         "version" | "--version" | "-V" => cmd_version(),
         "help" | "--help" | "-h" => print_usage(),
         other => {
@@ -44,6 +59,15 @@ COMMANDS:
     info <provider>             Show provider capabilities and configuration
     list [--dir <path>]         List all available provider manifests
     check-compat <manifest>     Check runtime feature compatibility
+    lint [<manifest>...]        Lint manifests (unused mappings, unreachable event rules,
+                                retry policy, capability/endpoint mismatches, deprecated
+                                fields); all manifests when none given; --strict
+    diff <old> <new>            Semantic diff; exit 1 when manifests differ
+    migrate <manifest>          Rewrite a V1 manifest as V2; --output <file>, --in-place
+                                (lint/diff/migrate accept --format json)
+    chat <provider/model>       Interactive chat REPL (streaming, /model, /system, /save, /load)
+    call <provider/model>       One-shot call; --prompt, --input <file> or stdin;
+                                --output text|json|ndjson, --stream, --base-url <url>
     version                     Show version information
     help                        Show this help message
 
@@ -60,28 +84,35 @@ fn cmd_version() {
     );
 }
 
-fn resolve_protocol_dir(args: &[String]) -> PathBuf {
+/// Protocol directory from `--dir`, `AI_PROTOCOL_DIR`, or a nearby `ai-protocol` checkout.
+fn find_protocol_dir(args: &[String]) -> Option<PathBuf> {
     // Check --dir flag
     for (i, arg) in args.iter().enumerate() {
         if arg == "--dir" {
             if let Some(path) = args.get(i + 1) {
-                return PathBuf::from(path);
+                return Some(PathBuf::from(path));
             }
         }
     }
     // Check environment variable
     if let Ok(dir) = std::env::var("AI_PROTOCOL_DIR") {
-        return PathBuf::from(dir);
+        return Some(PathBuf::from(dir));
     }
     // Try common relative paths
     for candidate in &["../ai-protocol", "../../ai-protocol", "ai-protocol"] {
         let p = PathBuf::from(candidate);
         if p.join("v2").join("providers").exists() || p.join("v1").join("providers").exists() {
-            return p;
+            return Some(p);
         }
     }
-    eprintln!("Error: Cannot find protocol directory. Set AI_PROTOCOL_DIR or use --dir.");
-    std::process::exit(1);
+    None
+}
+
+fn resolve_protocol_dir(args: &[String]) -> PathBuf {
+    find_protocol_dir(args).unwrap_or_else(|| {
+        eprintln!("Error: Cannot find protocol directory. Set AI_PROTOCOL_DIR or use --dir.");
+        std::process::exit(1);
+    })
 }
 
 fn cmd_validate(args: &[String]) {
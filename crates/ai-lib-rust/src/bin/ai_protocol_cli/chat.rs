@@ -0,0 +1,656 @@
+//! `chat` / `call` 子命令 — 交互式对话与一次性调用，用于调试清单行为。
+//!
+//! - `chat`: REPL with streaming output, `/model`, `/system`, history save/load
+//!   and tool-call display.
+//! - `call`: one-shot request; messages from `--prompt`, a file or stdin; text,
+//!   JSON or NDJSON event output with usage and `CallStats`.
+
+use ai_lib_rust::types::message::MessageContent;
+use ai_lib_rust::utils::tool_call_assembler::ToolCallAssembler;
+use ai_lib_rust::{
+    AiClient, AiClientBuilder, CallStats, Message, MessageRole, StreamingEvent, ToolCall,
+};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Flags shared by `chat` and `call`.
+#[derive(Debug, Default)]
+struct Options {
+    model: Option<String>,
+    dir: Option<PathBuf>,
+    base_url: Option<String>,
+    api_key: Option<String>,
+    system: Option<String>,
+    temperature: Option<f64>,
+    max_tokens: Option<u32>,
+    stream: bool,
+    no_stream: bool,
+    prompt: Option<String>,
+    input: Option<String>,
+    output: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String], usage: &str) -> Self {
+        let mut opts = Options::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| -> String {
+                iter.next().cloned().unwrap_or_else(|| {
+                    eprintln!("Missing value for {name}\n\n{usage}");
+                    std::process::exit(1);
+                })
+            };
+            match arg.as_str() {
+                "--dir" => opts.dir = Some(PathBuf::from(value("--dir"))),
+                "--base-url" => opts.base_url = Some(value("--base-url")),
+                "--api-key" => opts.api_key = Some(value("--api-key")),
+                "--system" => opts.system = Some(value("--system")),
+                "--temperature" => opts.temperature = Some(parse_num(&value("--temperature"))),
+                "--max-tokens" => opts.max_tokens = Some(parse_num(&value("--max-tokens"))),
+                "--prompt" | "-p" => opts.prompt = Some(value("--prompt")),
+                "--input" | "-i" => opts.input = Some(value("--input")),
+                "--output" | "-o" => opts.output = Some(value("--output")),
+                "--stream" => opts.stream = true,
+                "--no-stream" => opts.no_stream = true,
+                flag if flag.starts_with("--") => {
+                    eprintln!("Unknown option: {flag}\n\n{usage}");
+                    std::process::exit(1);
+                }
+                positional if opts.model.is_none() => opts.model = Some(positional.to_string()),
+                extra => {
+                    eprintln!("Unexpected argument: {extra}\n\n{usage}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        opts
+    }
+
+    fn model(&self, usage: &str) -> String {
+        self.model.clone().unwrap_or_else(|| {
+            eprintln!("{usage}");
+            std::process::exit(1);
+        })
+    }
+
+    async fn connect(&self, model: &str) -> ai_lib_rust::Result<AiClient> {
+        let mut builder = AiClientBuilder::new();
+        if let Some(dir) = self.dir.clone().or_else(|| super::find_protocol_dir(&[])) {
+            builder = builder.protocol_path(dir.to_string_lossy().to_string());
+        }
+        if let Some(url) = &self.base_url {
+            builder = builder.base_url_override(url);
+        }
+        if let Some(key) = &self.api_key {
+            builder = builder.api_key(key);
+        }
+        builder.build(model).await
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str) -> T {
+    s.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid number: {s}");
+        std::process::exit(1);
+    })
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Runtime::new().unwrap_or_else(|e| {
+        eprintln!("Cannot start async runtime: {e}");
+        std::process::exit(1);
+    })
+}
+
+fn with_system(system: Option<&str>, history: &[Message]) -> Vec<Message> {
+    system
+        .map(Message::system)
+        .into_iter()
+        .chain(history.iter().cloned())
+        .collect()
+}
+
+fn stats_json(stats: &CallStats, elapsed_ms: u128) -> Value {
+    json!({
+        "model": stats.model,
+        "endpoint": stats.endpoint,
+        "http_status": stats.http_status,
+        "retry_count": stats.retry_count,
+        "duration_ms": stats.duration_ms,
+        "first_event_ms": stats.first_event_ms,
+        "elapsed_ms": elapsed_ms,
+        "client_request_id": stats.client_request_id,
+        "upstream_request_id": stats.upstream_request_id,
+        "error_class": stats.error_class,
+        "usage": stats.usage,
+    })
+}
+
+fn tool_call_json(call: &ToolCall) -> Value {
+    json!({"id": call.id, "name": call.name, "arguments": call.arguments})
+}
+
+fn format_tool_call(call: &ToolCall) -> String {
+    format!("[tool call {}] {}({})", call.id, call.name, call.arguments)
+}
+
+/// Outcome of one streamed turn.
+#[derive(Default)]
+struct Turn {
+    content: String,
+    tool_calls: Vec<ToolCall>,
+    usage: Option<Value>,
+    finish_reason: Option<String>,
+}
+
+/// Drive a stream to completion, handing each event to `on_event` and assembling the turn.
+async fn collect_stream(
+    mut stream: std::pin::Pin<
+        Box<dyn futures::Stream<Item = ai_lib_rust::Result<StreamingEvent>> + Send>,
+    >,
+    mut on_event: impl FnMut(&StreamingEvent),
+) -> ai_lib_rust::Result<Turn> {
+    let mut turn = Turn::default();
+    let mut tools = ToolCallAssembler::new();
+    while let Some(event) = stream.next().await {
+        let event = event?;
+        on_event(&event);
+        match event {
+            StreamingEvent::PartialContentDelta { content, .. } => turn.content.push_str(&content),
+            StreamingEvent::ToolCallStarted {
+                tool_call_id,
+                tool_name,
+                ..
+            } => tools.on_started(tool_call_id, tool_name),
+            StreamingEvent::PartialToolCall {
+                tool_call_id,
+                arguments,
+                ..
+            } => tools.on_partial(&tool_call_id, &arguments),
+            StreamingEvent::Metadata {
+                usage,
+                finish_reason,
+                ..
+            } => {
+                turn.usage = usage.or(turn.usage);
+                turn.finish_reason = finish_reason.or(turn.finish_reason);
+            }
+            StreamingEvent::StreamEnd { finish_reason } => {
+                turn.finish_reason = finish_reason.or(turn.finish_reason);
+            }
+            StreamingEvent::StreamError { error, .. } => {
+                return Err(ai_lib_rust::Error::runtime_with_context(
+                    format!("stream error: {error}"),
+                    ai_lib_rust::ErrorContext::new().with_source("ai-protocol-cli"),
+                ));
+            }
+            _ => {}
+        }
+    }
+    turn.tool_calls = tools.finalize();
+    Ok(turn)
+}
+
+// ---------------------------------------------------------------------------
+// chat
+// ---------------------------------------------------------------------------
+
+const CHAT_USAGE: &str =
+    "Usage: ai-protocol-cli chat <provider/model> [--system <text>] [--dir <path>] \
+[--base-url <url>] [--api-key <key>] [--temperature <t>] [--max-tokens <n>] [--no-stream]";
+
+const REPL_HELP: &str = "Commands:
+  /model <provider/model>   Switch model (history is kept)
+  /system [text]            Set the system prompt (no text clears it)
+  /stream on|off            Toggle streaming output
+  /history                  Show the conversation
+  /save <file>              Save model, system prompt and history as JSON
+  /load <file>              Load a saved conversation
+  /clear                    Clear the history
+  /help                     Show this help
+  /exit                     Quit";
+
+struct Session {
+    opts: Options,
+    model: String,
+    client: AiClient,
+    system: Option<String>,
+    history: Vec<Message>,
+    stream: bool,
+}
+
+pub fn cmd_chat(args: &[String]) {
+    let opts = Options::parse(args, CHAT_USAGE);
+    let model = opts.model(CHAT_USAGE);
+    let rt = runtime();
+    let client = rt.block_on(opts.connect(&model)).unwrap_or_else(|e| {
+        eprintln!("Cannot create client for {model}: {e}");
+        std::process::exit(1);
+    });
+    let mut session = Session {
+        system: opts.system.clone(),
+        stream: !opts.no_stream,
+        opts,
+        model,
+        client,
+        history: Vec::new(),
+    };
+
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        println!(
+            "Chatting with {} — /help for commands, /exit to quit.",
+            session.model
+        );
+    }
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("> ");
+            let _ = std::io::stdout().flush();
+        }
+        let Some(Ok(line)) = lines.next() else { break };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(command) = line.strip_prefix('/') {
+            if !rt.block_on(session.command(command)) {
+                break;
+            }
+            continue;
+        }
+        session.history.push(Message::user(line));
+        if let Err(e) = rt.block_on(session.turn()) {
+            eprintln!("error: {e}");
+            // Drop the unanswered prompt so the next turn starts clean.
+            session.history.pop();
+        }
+    }
+}
+
+impl Session {
+    /// Handle a `/command`; returns `false` to quit.
+    async fn command(&mut self, command: &str) -> bool {
+        let (name, arg) = command
+            .split_once(char::is_whitespace)
+            .map(|(n, a)| (n, a.trim()))
+            .unwrap_or((command, ""));
+        match name {
+            "exit" | "quit" | "q" => return false,
+            "help" | "?" => println!("{REPL_HELP}"),
+            "model" if !arg.is_empty() => match self.opts.connect(arg).await {
+                Ok(client) => {
+                    self.client = client;
+                    self.model = arg.to_string();
+                    println!("model: {}", self.model);
+                }
+                Err(e) => eprintln!("error: cannot switch to {arg}: {e}"),
+            },
+            "model" => println!("model: {}", self.model),
+            "system" => {
+                self.system = (!arg.is_empty()).then(|| arg.to_string());
+                println!(
+                    "system prompt {}",
+                    if arg.is_empty() { "cleared" } else { "set" }
+                );
+            }
+            "stream" => match arg {
+                "on" => self.stream = true,
+                "off" => self.stream = false,
+                _ => println!("stream: {}", if self.stream { "on" } else { "off" }),
+            },
+            "history" => {
+                for m in with_system(self.system.as_deref(), &self.history) {
+                    println!("{}: {}", role_name(&m.role), text_of(&m.content));
+                }
+            }
+            "clear" => {
+                self.history.clear();
+                println!("history cleared");
+            }
+            "save" if !arg.is_empty() => {
+                let doc =
+                    json!({"model": self.model, "system": self.system, "messages": self.history});
+                match serde_json::to_string_pretty(&doc)
+                    .map_err(|e| e.to_string())
+                    .and_then(|s| std::fs::write(arg, s).map_err(|e| e.to_string()))
+                {
+                    Ok(()) => println!("saved {} message(s) to {arg}", self.history.len()),
+                    Err(e) => eprintln!("error: cannot save {arg}: {e}"),
+                }
+            }
+            "load" if !arg.is_empty() => match load_conversation(arg) {
+                Ok((system, messages)) => {
+                    if system.is_some() {
+                        self.system = system;
+                    }
+                    self.history = messages;
+                    println!("loaded {} message(s) from {arg}", self.history.len());
+                }
+                Err(e) => eprintln!("error: cannot load {arg}: {e}"),
+            },
+            other => eprintln!("unknown or incomplete command /{other} (try /help)"),
+        }
+        true
+    }
+
+    async fn turn(&mut self) -> ai_lib_rust::Result<()> {
+        let mut request = self
+            .client
+            .chat()
+            .messages(with_system(self.system.as_deref(), &self.history));
+        if let Some(t) = self.opts.temperature {
+            request = request.temperature(t);
+        }
+        if let Some(n) = self.opts.max_tokens {
+            request = request.max_tokens(n);
+        }
+
+        let turn = if self.stream {
+            let dim = std::io::stdout().is_terminal();
+            let mut thinking = false;
+            let stream = request.stream().execute_stream().await?;
+            let turn = collect_stream(stream, |event| {
+                let mut out = std::io::stdout();
+                match event {
+                    StreamingEvent::ThinkingDelta { thinking: t, .. } => {
+                        if !thinking && dim {
+                            let _ = write!(out, "\x1b[2m");
+                        }
+                        thinking = true;
+                        let _ = write!(out, "{t}");
+                    }
+                    StreamingEvent::PartialContentDelta { content, .. } => {
+                        if thinking {
+                            let _ = writeln!(out, "{}", if dim { "\x1b[0m" } else { "" });
+                            thinking = false;
+                        }
+                        let _ = write!(out, "{content}");
+                    }
+                    _ => {}
+                }
+                let _ = out.flush();
+            })
+            .await?;
+            if thinking && dim {
+                print!("\x1b[0m");
+            }
+            println!();
+            turn
+        } else {
+            let resp = request.execute().await?;
+            println!("{}", resp.content);
+            Turn {
+                content: resp.content,
+                tool_calls: resp.tool_calls,
+                usage: resp.usage,
+                finish_reason: None,
+            }
+        };
+
+        for call in &turn.tool_calls {
+            println!("{}", format_tool_call(call));
+        }
+        if let Some(usage) = &turn.usage {
+            eprintln!("[usage] {usage}");
+        }
+        self.history.push(Message::assistant(turn.content));
+        Ok(())
+    }
+}
+
+fn role_name(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::System => "system",
+        MessageRole::User => "user",
+        MessageRole::Assistant => "assistant",
+        MessageRole::Tool => "tool",
+    }
+}
+
+fn text_of(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text(t) => t.clone(),
+        MessageContent::Blocks(blocks) => serde_json::to_string(blocks).unwrap_or_default(),
+    }
+}
+
+/// Accepts a `/save` document, `{"messages": [...]}`, or a bare message array.
+fn load_conversation(path: &str) -> Result<(Option<String>, Vec<Message>), String> {
+    let raw = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let doc: Value = serde_json::from_str(&raw).map_err(|e| e.to_string())?;
+    let system = doc["system"].as_str().map(str::to_string);
+    let messages = if doc.is_array() {
+        doc
+    } else {
+        doc["messages"].clone()
+    };
+    let messages: Vec<Message> = serde_json::from_value(messages).map_err(|e| e.to_string())?;
+    Ok((system, messages))
+}
+
+// ---------------------------------------------------------------------------
+// call
+// ---------------------------------------------------------------------------
+
+const CALL_USAGE: &str =
+    "Usage: ai-protocol-cli call <provider/model> [--prompt <text> | --input <file|->] \
+[--system <text>] [--stream] [--output text|json|ndjson] [--dir <path>] [--base-url <url>] \
+[--api-key <key>] [--temperature <t>] [--max-tokens <n>]
+
+Without --prompt, input is read from --input or stdin: plain text (one user message),
+a JSON message array, or an object with \"messages\" and optional \"tools\".";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Text,
+    Json,
+    Ndjson,
+}
+
+pub fn cmd_call(args: &[String]) {
+    let opts = Options::parse(args, CALL_USAGE);
+    let model = opts.model(CALL_USAGE);
+    let output = match opts.output.as_deref().unwrap_or("text") {
+        "text" => Output::Text,
+        "json" => Output::Json,
+        "ndjson" => Output::Ndjson,
+        other => {
+            eprintln!("Unknown output format: {other} (expected text, json or ndjson)");
+            std::process::exit(1);
+        }
+    };
+    let (messages, tools) = read_call_input(&opts).unwrap_or_else(|e| {
+        eprintln!("Cannot read input: {e}");
+        std::process::exit(1);
+    });
+
+    let rt = runtime();
+    if let Err(e) = rt.block_on(run_call(&opts, &model, messages, tools, output)) {
+        match output {
+            Output::Text => eprintln!("error: {e}"),
+            _ => println!(
+                "{}",
+                json!({"event_type": "Error", "error": e.to_string(),
+                       "standard_code": e.standard_code().map(|c| c.code())})
+            ),
+        }
+        std::process::exit(1);
+    }
+}
+
+fn read_call_input(opts: &Options) -> Result<(Vec<Message>, Vec<Value>), String> {
+    let mut messages = Vec::new();
+    if let Some(system) = &opts.system {
+        messages.push(Message::system(system));
+    }
+    if let Some(prompt) = &opts.prompt {
+        messages.push(Message::user(prompt));
+        return Ok((messages, Vec::new()));
+    }
+    let raw = match opts.input.as_deref() {
+        None | Some("-") => {
+            let mut buf = String::new();
+            std::io::stdin()
+                .read_to_string(&mut buf)
+                .map_err(|e| e.to_string())?;
+            buf
+        }
+        Some(path) => std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?,
+    };
+    let (parsed, tools) = parse_call_input(&raw)?;
+    messages.extend(parsed);
+    Ok((messages, tools))
+}
+
+fn parse_call_input(raw: &str) -> Result<(Vec<Message>, Vec<Value>), String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err("no messages (empty input)".into());
+    }
+    let Ok(doc) = serde_json::from_str::<Value>(trimmed) else {
+        return Ok((vec![Message::user(trimmed)], Vec::new()));
+    };
+    let (messages, tools) = match doc {
+        Value::Array(_) => (doc, Value::Null),
+        Value::Object(ref obj) if obj.contains_key("messages") => {
+            (doc["messages"].clone(), doc["tools"].clone())
+        }
+        _ => return Err("JSON input must be a message array or {\"messages\": [...]}".into()),
+    };
+    let messages: Vec<Message> =
+        serde_json::from_value(messages).map_err(|e| format!("invalid messages: {e}"))?;
+    let tools = match tools {
+        Value::Array(tools) => tools,
+        _ => Vec::new(),
+    };
+    Ok((messages, tools))
+}
+
+async fn run_call(
+    opts: &Options,
+    model: &str,
+    messages: Vec<Message>,
+    tools: Vec<Value>,
+    output: Output,
+) -> ai_lib_rust::Result<()> {
+    let client = opts.connect(model).await?;
+    let started = Instant::now();
+    let mut request = client.chat().messages(messages);
+    if !tools.is_empty() {
+        request = request.tools_json(tools);
+    }
+    if let Some(t) = opts.temperature {
+        request = request.temperature(t);
+    }
+    if let Some(n) = opts.max_tokens {
+        request = request.max_tokens(n);
+    }
+
+    let (turn, stats) = if opts.stream {
+        let (stream, _cancel, stats) = request
+            .stream()
+            .execute_stream_with_cancel_and_stats()
+            .await?;
+        let turn = collect_stream(stream, |event| match output {
+            Output::Ndjson => println!("{}", serde_json::to_string(event).unwrap_or_default()),
+            Output::Text => {
+                if let StreamingEvent::PartialContentDelta { content, .. } = event {
+                    print!("{content}");
+                    let _ = std::io::stdout().flush();
+                }
+            }
+            Output::Json => {}
+        })
+        .await?;
+        if output == Output::Text {
+            println!();
+        }
+        (turn, stats)
+    } else {
+        let (resp, stats) = request.execute_with_stats().await?;
+        if output == Output::Text {
+            println!("{}", resp.content);
+        }
+        let turn = Turn {
+            content: resp.content,
+            tool_calls: resp.tool_calls,
+            usage: resp.usage,
+            finish_reason: None,
+        };
+        (turn, stats)
+    };
+    let elapsed = started.elapsed().as_millis();
+    let usage = turn.usage.clone().or_else(|| stats.usage.clone());
+    let stats = stats_json(&stats, elapsed);
+    let tool_calls: Vec<Value> = turn.tool_calls.iter().map(tool_call_json).collect();
+
+    match output {
+        Output::Text => {
+            for call in &turn.tool_calls {
+                println!("{}", format_tool_call(call));
+            }
+            if let Some(usage) = &usage {
+                eprintln!("[usage] {usage}");
+            }
+            eprintln!("[stats] {stats}");
+        }
+        Output::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "model": model,
+                "content": turn.content,
+                "tool_calls": tool_calls,
+                "finish_reason": turn.finish_reason,
+                "usage": usage,
+                "stats": stats,
+            }))
+            .unwrap_or_default()
+        ),
+        Output::Ndjson => {
+            if !opts.stream {
+                println!(
+                    "{}",
+                    json!({"event_type": "Response", "content": turn.content,
+                           "tool_calls": tool_calls, "usage": usage})
+                );
+            }
+            println!("{}", json!({"event_type": "CallStats", "stats": stats}));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_call_input_forms() {
+        let (plain, _) = parse_call_input("hello there\n").unwrap();
+        assert_eq!(plain.len(), 1);
+        assert!(matches!(&plain[0].content, MessageContent::Text(t) if t == "hello there"));
+
+        let (array, _) =
+            parse_call_input(r#"[{"role":"system","content":"s"},{"role":"user","content":"u"}]"#)
+                .unwrap();
+        assert_eq!(array[0].role, MessageRole::System);
+
+        let (object, tools) = parse_call_input(
+            r#"{"messages":[{"role":"user","content":"u"}],
+                "tools":[{"type":"function","function":{"name":"f"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(object.len(), 1);
+        assert_eq!(tools.len(), 1);
+
+        assert!(parse_call_input("   ").is_err());
+        assert!(parse_call_input(r#"{"prompt":"x"}"#).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! `lint` / `diff` / `migrate` 子命令 — 清单质量检查、语义差异与 V1→V2 迁移。
+//!
+//! Thin wrappers over `ai_lib_rust::protocol::{lint, diff, migrate}`. Each command
+//! accepts a manifest file path or a provider id resolved against the protocol
+//! directory, and supports `--format json` for machine-readable output.
+
+use ai_lib_rust::protocol::{diff_manifests, lint_manifest, migrate_to_v2, LintSeverity};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+const LINT_USAGE: &str = "Usage: ai-protocol-cli lint [<manifest|provider>...] [--dir <path>] [--format text|json] [--strict]";
+const DIFF_USAGE: &str =
+    "Usage: ai-protocol-cli diff <old> <new> [--dir <path>] [--format text|json]";
+const MIGRATE_USAGE: &str = "Usage: ai-protocol-cli migrate <manifest|provider> [--dir <path>] [--format yaml|json] [--output <file> | --in-place]";
+
+/// Positional targets and flags shared by the manifest commands.
+#[derive(Debug, Default)]
+struct Options {
+    targets: Vec<String>,
+    dir_args: Vec<String>,
+    format: Option<String>,
+    output: Option<PathBuf>,
+    in_place: bool,
+    strict: bool,
+}
+
+impl Options {
+    fn parse(args: &[String], usage: &str) -> Self {
+        let mut opts = Options::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| -> String {
+                iter.next().cloned().unwrap_or_else(|| {
+                    eprintln!("Missing value for {name}\n\n{usage}");
+                    std::process::exit(2);
+                })
+            };
+            match arg.as_str() {
+                "--dir" => opts.dir_args = vec!["--dir".to_string(), value("--dir")],
+                "--format" | "-f" => opts.format = Some(value("--format")),
+                "--output" | "-o" => opts.output = Some(PathBuf::from(value("--output"))),
+                "--in-place" => opts.in_place = true,
+                "--strict" => opts.strict = true,
+                flag if flag.starts_with("--") => {
+                    eprintln!("Unknown option: {flag}\n\n{usage}");
+                    std::process::exit(2);
+                }
+                target => opts.targets.push(target.to_string()),
+            }
+        }
+        opts
+    }
+
+    /// Output format, restricted to `allowed` (first entry is the default).
+    fn format(&self, allowed: &[&str], usage: &str) -> String {
+        let format = self.format.as_deref().unwrap_or(allowed[0]);
+        if !allowed.contains(&format) {
+            eprintln!("Unknown format '{format}'\n\n{usage}");
+            std::process::exit(2);
+        }
+        format.to_string()
+    }
+}
+
+/// Resolve a manifest file path, or a provider id under `<dir>/v2|v1/providers`.
+fn resolve(target: &str, dir_args: &[String]) -> Result<PathBuf, String> {
+    let path = PathBuf::from(target);
+    if path.is_file() {
+        return Ok(path);
+    }
+    let dir = super::find_protocol_dir(dir_args)
+        .ok_or_else(|| format!("'{target}' is not a file and no protocol directory is set"))?;
+    ["v2", "v1"]
+        .iter()
+        .map(|v| dir.join(v).join("providers").join(format!("{target}.yaml")))
+        .find(|p| p.is_file())
+        .ok_or_else(|| {
+            format!(
+                "'{target}' is not a file or a provider in {}",
+                dir.display()
+            )
+        })
+}
+
+fn read_manifest(path: &Path) -> Result<Value, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    serde_yaml::from_str(&content).map_err(|e| format!("invalid YAML in {}: {e}", path.display()))
+}
+
+fn load(target: &str, dir_args: &[String]) -> Result<(PathBuf, Value), String> {
+    let path = resolve(target, dir_args)?;
+    let manifest = read_manifest(&path)?;
+    Ok((path, manifest))
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("Error: {message}");
+    std::process::exit(2);
+}
+
+/// Every provider manifest in the protocol directory, V2 first.
+fn all_manifests(dir_args: &[String]) -> Vec<PathBuf> {
+    let Some(dir) = super::find_protocol_dir(dir_args) else {
+        fail("no targets given and no protocol directory found. Set AI_PROTOCOL_DIR or use --dir.");
+    };
+    let mut paths = Vec::new();
+    for version in ["v2", "v1"] {
+        let Ok(entries) = std::fs::read_dir(dir.join(version).join("providers")) else {
+            continue;
+        };
+        let mut found: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yaml" | "yml")))
+            .collect();
+        found.sort();
+        paths.extend(found);
+    }
+    paths
+}
+
+/// `lint`: exit 1 when any finding is an error (or a warning with `--strict`).
+pub fn cmd_lint(args: &[String]) {
+    let opts = Options::parse(args, LINT_USAGE);
+    let format = opts.format(&["text", "json"], LINT_USAGE);
+    let paths: Vec<PathBuf> = if opts.targets.is_empty() {
+        all_manifests(&opts.dir_args)
+    } else {
+        opts.targets
+            .iter()
+            .map(|t| resolve(t, &opts.dir_args).unwrap_or_else(|e| fail(e)))
+            .collect()
+    };
+    let threshold = if opts.strict {
+        LintSeverity::Warning
+    } else {
+        LintSeverity::Error
+    };
+
+    let mut failed = false;
+    let mut results = Vec::new();
+    for path in &paths {
+        let report = lint_manifest(&read_manifest(path).unwrap_or_else(|e| fail(e)));
+        failed |= report.has_at_least(threshold);
+        if format == "json" {
+            results.push(json!({
+                "file": path.display().to_string(),
+                "id": report.id,
+                "findings": report.findings,
+            }));
+            continue;
+        }
+        println!("{}", path.display());
+        if report.findings.is_empty() {
+            println!("  no findings");
+        }
+        for f in &report.findings {
+            println!(
+                "  {:<7} {:<26} {}: {}",
+                f.severity, f.code, f.path, f.message
+            );
+        }
+    }
+    if format == "json" {
+        println!("{}", Value::Array(results));
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// `diff`: exit 0 when equivalent, 1 when they differ, 2 on errors (like `diff(1)`).
+pub fn cmd_diff(args: &[String]) {
+    let opts = Options::parse(args, DIFF_USAGE);
+    let format = opts.format(&["text", "json"], DIFF_USAGE);
+    let [old, new] = opts.targets.as_slice() else {
+        fail(DIFF_USAGE);
+    };
+    let (old_path, old) = load(old, &opts.dir_args).unwrap_or_else(|e| fail(e));
+    let (new_path, new) = load(new, &opts.dir_args).unwrap_or_else(|e| fail(e));
+    let diff = diff_manifests(&old, &new);
+
+    if format == "json" {
+        println!(
+            "{}",
+            json!({
+                "old": old_path.display().to_string(),
+                "new": new_path.display().to_string(),
+                "breaking": diff.has_breaking(),
+                "changes": diff.changes,
+            })
+        );
+    } else {
+        println!("--- {}\n+++ {}", old_path.display(), new_path.display());
+        if diff.is_empty() {
+            println!("no semantic differences");
+        }
+        for c in &diff.changes {
+            let (sign, detail) = match (&c.old, &c.new) {
+                (Some(o), Some(n)) => ("~", format!("{o} -> {n}")),
+                (Some(o), None) => ("-", o.to_string()),
+                (None, Some(n)) => ("+", n.to_string()),
+                (None, None) => ("?", String::new()),
+            };
+            let tag = if c.breaking { " [breaking]" } else { "" };
+            println!("{sign} {}: {detail}{tag}", c.path);
+        }
+    }
+    if !diff.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// `migrate`: migrated manifest to stdout (or `--output` / `--in-place`), change log to stderr.
+pub fn cmd_migrate(args: &[String]) {
+    let opts = Options::parse(args, MIGRATE_USAGE);
+    let format = opts.format(&["yaml", "json"], MIGRATE_USAGE);
+    let [target] = opts.targets.as_slice() else {
+        fail(MIGRATE_USAGE);
+    };
+    let (path, manifest) = load(target, &opts.dir_args).unwrap_or_else(|e| fail(e));
+    let report =
+        migrate_to_v2(&manifest).unwrap_or_else(|e| fail(format!("{}: {e}", path.display())));
+
+    let destination = if opts.in_place {
+        Some(path.clone())
+    } else {
+        opts.output.clone()
+    };
+    // JSON on stdout carries the change log inline; otherwise it goes to stderr.
+    let inline_changes = format == "json" && destination.is_none();
+    let rendered = if format == "json" {
+        let doc = if inline_changes {
+            json!({
+                "file": path.display().to_string(),
+                "changes": report.changes,
+                "manifest": report.manifest,
+            })
+        } else {
+            report.manifest.clone()
+        };
+        serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())
+    } else {
+        let original = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|c| serde_yaml::from_str(&c).ok())
+            .unwrap_or(serde_yaml::Value::Null);
+        serde_yaml::to_string(&in_source_order(&original, &report.manifest))
+            .map_err(|e| e.to_string())
+    }
+    .unwrap_or_else(|e| fail(e));
+
+    match destination {
+        Some(dest) => std::fs::write(&dest, format!("{}\n", rendered.trim_end()))
+            .unwrap_or_else(|e| fail(format!("cannot write {}: {e}", dest.display()))),
+        None => println!("{}", rendered.trim_end()),
+    }
+    if !inline_changes {
+        if report.is_noop() {
+            eprintln!("{}: already V2, nothing to migrate", path.display());
+        }
+        for c in &report.changes {
+            eprintln!("migrated {}: {}", c.path, c.description);
+        }
+    }
+}
+
+/// Convert `migrated` to YAML keeping the key order of `original`; new keys go last.
+fn in_source_order(original: &serde_yaml::Value, migrated: &Value) -> serde_yaml::Value {
+    match migrated {
+        Value::Object(map) => {
+            let source = original.as_mapping();
+            let mut out = serde_yaml::Mapping::new();
+            let known = source
+                .into_iter()
+                .flat_map(|m| m.keys())
+                .filter_map(|k| k.as_str())
+                .filter(|k| map.contains_key(*k));
+            let added = map
+                .keys()
+                .map(String::as_str)
+                .filter(|k| source.map_or(true, |m| !m.contains_key(*k)));
+            for key in known.chain(added) {
+                let child = source
+                    .and_then(|m| m.get(key))
+                    .unwrap_or(&serde_yaml::Value::Null);
+                out.insert(key.into(), in_source_order(child, &map[key]));
+            }
+            serde_yaml::Value::Mapping(out)
+        }
+        Value::Array(items) => serde_yaml::Value::Sequence(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let child = original
+                        .as_sequence()
+                        .and_then(|s| s.get(i))
+                        .unwrap_or(&serde_yaml::Value::Null);
+                    in_source_order(child, v)
+                })
+                .collect(),
+        ),
+        other => serde_yaml::to_value(other).unwrap_or(serde_yaml::Value::Null),
+    }
+}
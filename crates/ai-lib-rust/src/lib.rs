@@ -13,10 +13,14 @@ pub use ai_lib_contact::resilience;
 
 #[cfg(feature = "batch")]
 pub use ai_lib_contact::batch;
+#[cfg(feature = "app_config")]
+pub use ai_lib_contact::config;
 #[cfg(feature = "guardrails")]
 pub use ai_lib_contact::guardrails;
 #[cfg(feature = "interceptors")]
 pub use ai_lib_contact::interceptors;
+#[cfg(feature = "rag")]
+pub use ai_lib_contact::rag;
 #[cfg(feature = "routing_mvp")]
 pub use ai_lib_contact::routing;
 #[cfg(feature = "telemetry")]
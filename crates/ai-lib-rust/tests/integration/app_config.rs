@@ -0,0 +1,202 @@
+//! Integration tests for building the client stack from a declarative config file
+
+use crate::integration::support::temp_file;
+use ai_lib_rust::config::{AppConfig, ConfigFormat, ConfigLoader};
+use ai_lib_rust::error_code::StandardErrorCode;
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use ai_lib_rust::Message;
+use std::collections::HashMap;
+
+const CONFIG: &str = r#"
+clients:
+  primary:
+    model: fake/primary
+    api_key: ${PRIMARY_KEY}
+    fallbacks: [backup]
+    circuit_breaker: { failure_threshold: 1, cooldown_secs: 60 }
+  backup:
+    model: fake/backup
+    rate_limit: { rps: 100 }
+routing:
+  chat:
+    strategy: health_based
+    members: [{ client: primary }, { client: backup }]
+cache: { ttl_secs: 60, max_entries: 10 }
+guardrails:
+  rules:
+    - { keyword: "project x", action: block, category: confidential }
+budgets:
+  tokens:
+    clients: [primary]
+    max_tokens: 100
+telemetry:
+  exporters: [{ type: memory, max_events: 10 }]
+profiles:
+  prod:
+    budgets: { tokens: { max_tokens: 1000000 } }
+    cache: null
+"#;
+
+#[tokio::test]
+async fn test_stack_built_from_file_routes_and_enforces_budget() {
+    let path = temp_file("stack.yaml", CONFIG);
+    let config = ConfigLoader::new()
+        .vars(HashMap::from([(
+            "PRIMARY_KEY".to_string(),
+            "sk-primary".to_string(),
+        )]))
+        .load_file(&path)
+        .expect("config");
+    std::fs::remove_file(&path).ok();
+
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model("primary", FakeResponse::text("hi").with_usage(80, 40));
+    let stack = config
+        .build_with(|| fake.client_builder())
+        .await
+        .expect("stack");
+    assert_eq!(
+        stack.client_names().collect::<Vec<_>>(),
+        ["backup", "primary"]
+    );
+    assert!(stack.cache().is_some());
+    assert!(stack.rate_limiter("backup").is_some());
+    assert!(stack
+        .guardrails()
+        .unwrap()
+        .check_input("status of Project X?")
+        .is_blocked());
+
+    let (name, client) = stack.route("chat").expect("route");
+    assert_eq!(name, "primary");
+    stack.admit(name).await.expect("within budget");
+    let (resp, stats) = client
+        .chat()
+        .messages(vec![Message::user("hello")])
+        .execute_with_stats()
+        .await
+        .expect("chat");
+    assert_eq!(resp.content, "hi");
+    stack.record_success(name, &stats);
+    assert_eq!(stack.budget("tokens").unwrap().used_tokens(), 120);
+
+    let err = stack.admit("primary").await.expect_err("budget exhausted");
+    assert_eq!(
+        err.context().and_then(|c| c.standard_code),
+        Some(StandardErrorCode::QuotaExhausted)
+    );
+    // The budget only covers `primary`.
+    stack.admit("backup").await.expect("backup unaffected");
+
+    // A failing member trips its breaker and can be taken out of rotation.
+    stack.record_failure("primary");
+    assert!(stack.circuit_breaker("primary").unwrap().allow().is_err());
+    stack.set_healthy("primary", false);
+    assert_eq!(stack.route("chat").unwrap().0, "backup");
+}
+
+#[tokio::test]
+async fn test_fallback_chain_resolves_client_names() {
+    let config = ConfigLoader::new()
+        .vars(HashMap::from([(
+            "PRIMARY_KEY".to_string(),
+            "k".to_string(),
+        )]))
+        .profile("prod")
+        .load_str(CONFIG, ConfigFormat::Yaml)
+        .expect("config");
+    assert!(config.cache.is_none());
+    assert_eq!(config.budgets["tokens"].max_tokens, Some(1_000_000));
+
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model(
+        "primary",
+        FakeResponse::error(StandardErrorCode::ServerError, "down"),
+    );
+    fake.push_for_model("backup", FakeResponse::text("from backup"));
+    let stack = config
+        .build_with(|| fake.client_builder())
+        .await
+        .expect("stack");
+    let resp = stack
+        .client("primary")
+        .unwrap()
+        .chat()
+        .messages(vec![Message::user("hello")])
+        .execute()
+        .await
+        .expect("fallback");
+    assert_eq!(resp.content, "from backup");
+    assert_eq!(
+        fake.requests()
+            .iter()
+            .map(|r| r.model.clone().unwrap_or_default())
+            .collect::<Vec<_>>(),
+        ["primary", "backup"]
+    );
+    assert!(stack.memory_sink().is_some());
+}
+
+#[tokio::test]
+async fn test_invalid_file_reports_path_and_file() {
+    let path = temp_file(
+        "invalid.toml",
+        "[clients.a]\nmodel = \"fake/a\"\n\n[routing.chat]\nmembers = [{ client = \"b\" }]\n",
+    );
+    let err = AppConfig::from_file(&path).unwrap_err().to_string();
+    std::fs::remove_file(&path).ok();
+    assert!(err.contains("unknown client 'b'"), "{}", err);
+    assert!(
+        err.contains("field: routing.chat.members[0].client"),
+        "{}",
+        err
+    );
+    assert!(err.contains("invalid.toml"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_named_fallback_uses_its_own_credential() {
+    let config = AppConfig::from_yaml(
+        r#"
+clients:
+  primary:
+    model: fake/primary
+    api_key: sk-primary
+    fallbacks: [backup]
+  backup:
+    model: fake/backup
+    api_key: sk-backup
+"#,
+    )
+    .expect("config");
+
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model(
+        "primary",
+        FakeResponse::error(StandardErrorCode::ServerError, "down"),
+    );
+    fake.push_for_model("backup", FakeResponse::text("from backup"));
+    let stack = config
+        .build_with(|| fake.client_builder())
+        .await
+        .expect("stack");
+    stack
+        .client("primary")
+        .unwrap()
+        .chat()
+        .messages(vec![Message::user("hello")])
+        .execute()
+        .await
+        .expect("fallback");
+
+    let requests = fake.requests();
+    assert_eq!(requests.len(), 2);
+    let auth = |i: usize| {
+        requests[i]
+            .header("authorization")
+            .unwrap_or_default()
+            .to_string()
+    };
+    assert!(auth(0).contains("sk-primary"), "{:?}", requests[0].headers);
+    assert!(auth(1).contains("sk-backup"), "{:?}", requests[1].headers);
+}
@@ -0,0 +1,154 @@
+//! Integration tests for cassette record/replay on the HTTP transport
+
+use crate::integration::support::{protocols, temp_path};
+use ai_lib_rust::transport::{Cassette, ReplayTiming};
+use ai_lib_rust::types::events::StreamingEvent;
+use ai_lib_rust::{AiClient, AiClientBuilder, Message};
+use futures::StreamExt;
+use std::sync::Arc;
+
+const SECRET: &str = "sk-cassette-test-0123456789abcdefghij";
+
+async fn client(base_url: &str, cassette: Arc<Cassette>) -> AiClient {
+    AiClientBuilder::new()
+        .protocol_path(protocols())
+        .base_url_override(base_url)
+        .api_key(SECRET)
+        .cassette(cassette)
+        .build("openai/gpt-4o")
+        .await
+        .expect("build client")
+}
+
+async fn stream_text(client: &AiClient, prompt: &str) -> String {
+    let mut stream = client
+        .chat()
+        .messages(vec![Message::user(prompt)])
+        .stream()
+        .execute_stream()
+        .await
+        .expect("start stream");
+    let mut collected = String::new();
+    while let Some(ev) = stream.next().await {
+        if let Ok(StreamingEvent::PartialContentDelta { content, .. }) = ev {
+            collected.push_str(&content);
+        }
+    }
+    collected
+}
+
+#[tokio::test]
+async fn test_record_then_replay_offline() {
+    let path = temp_path("cassette-roundtrip.json");
+    let mut server = mockito::Server::new_async().await;
+    let json = server
+        .mock("POST", "/chat/completions")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"messages":[{"role":"user","content":"ping"}]}"#.into(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"choices":[{"message":{"role":"assistant","content":"pong"},"finish_reason":"stop"}],
+                "usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#,
+        )
+        .create_async()
+        .await;
+    let sse = server
+        .mock("POST", "/chat/completions")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"messages":[{"role":"user","content":"stream"}]}"#.into(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"},\"index\":0}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" World\"},\"index\":0}]}\n\n",
+            "data: [DONE]\n\n",
+        ))
+        .create_async()
+        .await;
+
+    let recorder = Arc::new(Cassette::record(&path));
+    let live = client(&server.url(), recorder.clone()).await;
+    let recorded = live
+        .chat()
+        .messages(vec![Message::user("ping")])
+        .execute()
+        .await
+        .expect("recorded chat");
+    let recorded_stream = stream_text(&live, "stream").await;
+    json.assert_async().await;
+    sse.assert_async().await;
+    assert_eq!(recorded.content, "pong");
+    assert_eq!(recorded_stream, "Hello World");
+    assert_eq!(recorder.len(), 2);
+
+    let raw = std::fs::read_to_string(&path).expect("cassette written");
+    assert!(!raw.contains(SECRET), "credential leaked into cassette");
+    assert!(raw.contains("<REDACTED>"));
+
+    // Nothing listens on this address: replay must not touch the network.
+    let replayer = Arc::new(
+        Cassette::replay(&path)
+            .expect("load cassette")
+            .with_replay_timing(ReplayTiming::Instant),
+    );
+    let offline = client("http://127.0.0.1:9", replayer.clone()).await;
+    let replayed = offline
+        .chat()
+        .messages(vec![Message::user("ping")])
+        .execute()
+        .await
+        .expect("replayed chat");
+    assert_eq!(replayed.content, recorded.content);
+    assert_eq!(replayed.usage, recorded.usage);
+    assert_eq!(stream_text(&offline, "stream").await, recorded_stream);
+    assert!(replayer.unplayed().is_empty());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_replay_fails_loudly_on_unmatched_request() {
+    let path = temp_path("cassette-unmatched.json");
+    let mut server = mockito::Server::new_async().await;
+    let _mock = server
+        .mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"choices":[{"message":{"role":"assistant","content":"pong"}}]}"#)
+        .create_async()
+        .await;
+
+    let live = client(&server.url(), Arc::new(Cassette::record(&path))).await;
+    live.chat()
+        .messages(vec![Message::user("ping")])
+        .execute()
+        .await
+        .expect("recorded chat");
+
+    let offline = client(
+        "http://127.0.0.1:9",
+        Arc::new(Cassette::replay(&path).expect("load cassette")),
+    )
+    .await;
+    let err = offline
+        .chat()
+        .messages(vec![Message::user("something else")])
+        .execute()
+        .await
+        .expect_err("unmatched request must fail");
+    assert!(
+        err.to_string().contains("cassette has no match"),
+        "unexpected error: {err}"
+    );
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_replay_missing_cassette_is_configuration_error() {
+    let err = Cassette::replay(temp_path("cassette-missing.json")).expect_err("missing file");
+    assert!(err.to_string().contains("cannot read cassette"), "{err}");
+}
@@ -0,0 +1,269 @@
+//! Integration tests for `ai-protocol-cli`: `call` / `chat` against a mock provider,
+//! and the `lint` / `diff` / `migrate` manifest commands.
+
+use crate::integration::support::{protocols, temp_file, temp_path};
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the CLI with `stdin`, returning (exit success, stdout, stderr).
+fn run(args: &[&str], base_url: &str, stdin: &str) -> (bool, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ai-protocol-cli"))
+        .args(args)
+        .args(["--dir", &protocols(), "--base-url", base_url])
+        .args(["--api-key", "sk-cli-test"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn cli");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let out = child.wait_with_output().expect("cli output");
+    (
+        out.status.success(),
+        String::from_utf8_lossy(&out.stdout).to_string(),
+        String::from_utf8_lossy(&out.stderr).to_string(),
+    )
+}
+
+#[test]
+fn test_call_json_output_with_stdin_messages() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"messages":[{"role":"system","content":"terse"},{"role":"user","content":"ping"}]}"#
+                .into(),
+        ))
+        .with_header("content-type", "application/json")
+        .with_header("x-request-id", "req-42")
+        .with_body(
+            r#"{"choices":[{"message":{"role":"assistant","content":"pong"},"finish_reason":"stop"}],
+                "usage":{"prompt_tokens":2,"completion_tokens":1,"total_tokens":3}}"#,
+        )
+        .create();
+
+    let (ok, stdout, stderr) = run(
+        &["call", "openai/gpt-4o", "--output", "json"],
+        &server.url(),
+        r#"[{"role":"system","content":"terse"},{"role":"user","content":"ping"}]"#,
+    );
+    assert!(ok, "cli failed: {stderr}");
+    mock.assert();
+    let out: Value = serde_json::from_str(&stdout).expect("json output");
+    assert_eq!(out["content"], "pong");
+    assert_eq!(out["usage"]["total_tokens"], 3);
+    assert_eq!(out["stats"]["http_status"], 200);
+    assert_eq!(out["stats"]["retry_count"], 0);
+    assert_eq!(out["stats"]["upstream_request_id"], "req-42");
+}
+
+#[test]
+fn test_call_streaming_ndjson_events() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("POST", "/chat/completions")
+        .with_header("content-type", "text/event-stream")
+        .with_body(concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"},\"index\":0}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"},\"index\":0}]}\n\n",
+            "data: [DONE]\n\n",
+        ))
+        .create();
+
+    let (ok, stdout, stderr) = run(
+        &[
+            "call",
+            "openai/gpt-4o",
+            "--prompt",
+            "hi",
+            "--stream",
+            "--output",
+            "ndjson",
+        ],
+        &server.url(),
+        "",
+    );
+    assert!(ok, "cli failed: {stderr}");
+    let lines: Vec<Value> = stdout
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("ndjson line"))
+        .collect();
+    let content: String = lines
+        .iter()
+        .filter(|l| l["event_type"] == "PartialContentDelta")
+        .filter_map(|l| l["content"].as_str())
+        .collect();
+    assert_eq!(content, "Hello");
+    let last = lines.last().unwrap();
+    assert_eq!(last["event_type"], "CallStats");
+    assert_eq!(last["stats"]["http_status"], 200);
+}
+
+#[test]
+fn test_call_reports_upstream_error() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("POST", "/chat/completions")
+        .with_status(401)
+        .with_body(r#"{"error":{"message":"bad key"}}"#)
+        .create();
+
+    let (ok, stdout, _) = run(
+        &[
+            "call",
+            "openai/gpt-4o",
+            "--prompt",
+            "hi",
+            "--output",
+            "json",
+        ],
+        &server.url(),
+        "",
+    );
+    assert!(!ok);
+    let out: Value = serde_json::from_str(stdout.trim()).expect("json error");
+    assert_eq!(out["event_type"], "Error");
+    assert_eq!(out["standard_code"], "E1002");
+}
+
+#[test]
+fn test_chat_repl_system_prompt_and_history_save() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"messages":[{"role":"system","content":"be nice"},{"role":"user","content":"hello"}]}"#
+                .into(),
+        ))
+        .with_header("content-type", "text/event-stream")
+        .with_body(concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"hi there\"},\"index\":0}]}\n\n",
+            "data: [DONE]\n\n",
+        ))
+        .create();
+    let path = temp_path("cli-history.json");
+    let script = format!("/system be nice\nhello\n/save {}\n/exit\n", path.display());
+
+    let (ok, stdout, stderr) = run(&["chat", "openai/gpt-4o"], &server.url(), &script);
+    assert!(ok, "cli failed: {stderr}");
+    mock.assert();
+    assert!(stdout.contains("hi there"), "stdout: {stdout}");
+
+    let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(saved["model"], "openai/gpt-4o");
+    assert_eq!(saved["system"], "be nice");
+    assert_eq!(saved["messages"][0]["content"], "hello");
+    assert_eq!(saved["messages"][1]["role"], "assistant");
+    assert_eq!(saved["messages"][1]["content"], "hi there");
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Run a manifest command, returning (exit code, stdout).
+fn run_manifest_cmd(args: &[&str]) -> (i32, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_ai-protocol-cli"))
+        .args(args)
+        .output()
+        .expect("cli output");
+    (
+        out.status.code().unwrap_or(-1),
+        String::from_utf8_lossy(&out.stdout).to_string(),
+    )
+}
+
+const LEGACY_MANIFEST: &str = r#"
+id: acme
+protocol_version: "1.5"
+name: Acme
+status: stable
+category: ai_provider
+official_url: https://acme.test
+support_contact: ops@acme.test
+endpoint: { base_url: "https://api.acme.test/v1" }
+auth: { type: bearer, header_name: Authorization, token_env: ACME_API_KEY }
+capabilities: { streaming: true, tools: true, vision: false }
+parameter_mappings: { model: model, messages: messages, stream: stream, tools: tools, seed: seed }
+streaming:
+  event_map:
+    - { match: "", emit: PartialContentDelta }
+"#;
+
+#[test]
+fn test_lint_json_reports_findings_and_fails_on_errors() {
+    let path = temp_file("lint.yaml", LEGACY_MANIFEST);
+    let (code, stdout) = run_manifest_cmd(&["lint", path.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(code, 1, "error-level finding must fail: {stdout}");
+    let reports: Value = serde_json::from_str(&stdout).expect("json output");
+    let findings = reports[0]["findings"].as_array().unwrap();
+    let has = |code: &str, path: &str| {
+        findings
+            .iter()
+            .any(|f| f["code"] == code && f["path"] == path)
+    };
+    assert_eq!(reports[0]["id"], "acme");
+    assert!(has("unused_parameter_mapping", "parameter_mappings.seed"));
+    assert!(has("unreachable_event_rule", "streaming.event_map[0]"));
+    assert!(has("missing_retry_policy", "retry_policy"));
+    assert!(has("deprecated_field", "auth.header_name"));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_migrate_then_diff_round_trip() {
+    let source = temp_file("source.yaml", LEGACY_MANIFEST);
+    let migrated = temp_path("migrated.yaml");
+    let (code, _) = run_manifest_cmd(&[
+        "migrate",
+        source.to_str().unwrap(),
+        "--output",
+        migrated.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 0);
+    let yaml: Value = serde_yaml::from_str(&std::fs::read_to_string(&migrated).unwrap()).unwrap();
+    assert_eq!(yaml["protocol_version"], "2.0");
+    assert_eq!(
+        yaml["capabilities"]["optional"],
+        serde_json::json!(["tools"])
+    );
+    assert_eq!(yaml["endpoint"]["auth"]["header"], "Authorization");
+
+    // Migration changes representation only.
+    let (code, stdout) = run_manifest_cmd(&[
+        "diff",
+        source.to_str().unwrap(),
+        migrated.to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+    let diff: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(code, 1, "protocol_version differs: {stdout}");
+    assert_eq!(diff["changes"].as_array().unwrap().len(), 1);
+    assert_eq!(diff["changes"][0]["path"], "protocol_version");
+    assert_eq!(diff["breaking"], false);
+
+    // Migrating again is a no-op, and a moved base_url is a breaking difference.
+    let (_, stdout) =
+        run_manifest_cmd(&["migrate", migrated.to_str().unwrap(), "--format", "json"]);
+    let again: Value = serde_json::from_str(&stdout).unwrap();
+    assert!(again["changes"].as_array().unwrap().is_empty());
+    let moved = temp_file(
+        "moved.yaml",
+        &LEGACY_MANIFEST.replace("api.acme.test", "api.acme.example"),
+    );
+    let (code, stdout) =
+        run_manifest_cmd(&["diff", source.to_str().unwrap(), moved.to_str().unwrap()]);
+    assert_eq!(code, 1);
+    assert!(
+        stdout.contains("~ endpoint.base_url") && stdout.contains("[breaking]"),
+        "{stdout}"
+    );
+
+    for p in [source, migrated, moved] {
+        let _ = std::fs::remove_file(p);
+    }
+}
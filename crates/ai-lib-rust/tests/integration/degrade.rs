@@ -0,0 +1,316 @@
+//! Integration tests for capability-driven request degradation across the fallback chain
+
+use ai_lib_rust::client::{DegradeOptions, ImageFallback, Rewrite};
+use ai_lib_rust::error_code::StandardErrorCode;
+use ai_lib_rust::structured::JsonModeConfig;
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use ai_lib_rust::types::message::{ContentBlock, MessageContent, MessageRole};
+use ai_lib_rust::types::tool::{FunctionDefinition, ToolDefinition};
+use ai_lib_rust::Message;
+use serde_json::json;
+
+/// Text-only sibling of the fake manifest.
+fn weak_manifest(fake: &FakeProvider) -> ai_lib_rust::protocol::ProtocolManifest {
+    let mut manifest = fake.manifest().clone();
+    manifest.id = "weak".to_string();
+    manifest.capabilities.tools = false;
+    manifest.capabilities.vision = false;
+    manifest.capabilities.multimodal = false;
+    manifest
+}
+
+fn weather_tool() -> ToolDefinition {
+    ToolDefinition {
+        tool_type: "function".into(),
+        function: FunctionDefinition {
+            name: "get_weather".into(),
+            description: Some("Current weather for a city".into()),
+            parameters: Some(json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"]
+            })),
+        },
+    }
+}
+
+fn photo_question() -> Vec<Message> {
+    vec![
+        Message::system("Answer briefly."),
+        Message::with_content(
+            MessageRole::User,
+            MessageContent::blocks(vec![
+                ContentBlock::text("What is the weather where this was taken?"),
+                ContentBlock::image_url("https://example.com/paris.jpg"),
+            ]),
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn test_fallback_to_text_only_model_is_degraded_and_reported() {
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model(
+        "primary",
+        FakeResponse::error(StandardErrorCode::ServerError, "down"),
+    );
+    fake.push_for_model(
+        "tiny",
+        FakeResponse::text(
+            "Let me check.\n<tool_call>\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Paris\"}}\n</tool_call>",
+        ),
+    );
+
+    let client = fake
+        .client_builder()
+        .protocol_manifest(weak_manifest(&fake))
+        .with_fallbacks(vec!["weak/tiny".to_string()])
+        .degrade_requests(true)
+        .build("fake/primary")
+        .await
+        .expect("client");
+    let (resp, stats) = client
+        .chat()
+        .messages(photo_question())
+        .tools(vec![weather_tool()])
+        .execute_with_stats()
+        .await
+        .expect("degraded fallback succeeds");
+
+    let requests = fake.requests();
+    assert_eq!(requests.len(), 2);
+    // The primary supports everything and gets the request unchanged.
+    assert_eq!(
+        requests[0].body["tools"][0]["function"]["name"],
+        "get_weather"
+    );
+    assert_eq!(requests[0].messages()[0]["role"], "system");
+
+    let degraded = &requests[1];
+    assert_eq!(degraded.model.as_deref(), Some("tiny"));
+    assert!(degraded.body.get("tools").is_none());
+    assert_eq!(degraded.messages().len(), 2);
+    let system = degraded.messages()[0]["content"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(system.starts_with("Answer briefly.\n\n"));
+    assert!(system.contains("<tool_call>") && system.contains("\"required\":[\"city\"]"));
+    let prompt = degraded.messages()[1].to_string();
+    assert!(prompt.contains("[Image omitted (image, https://example.com/paris.jpg)"));
+
+    assert_eq!(resp.content.trim(), "Let me check.");
+    assert_eq!(resp.tool_calls.len(), 1);
+    assert_eq!(resp.tool_calls[0].name, "get_weather");
+    assert_eq!(resp.tool_calls[0].arguments, json!({ "city": "Paris" }));
+
+    let report = resp.degradation.expect("degradation report");
+    assert_eq!(report.model, "tiny");
+    assert_eq!(
+        report.rewrites,
+        vec![
+            Rewrite::ImagesDescribed { count: 1 },
+            Rewrite::ToolsAsText {
+                tools: vec!["get_weather".into()],
+                history_messages: 0,
+            },
+        ]
+    );
+    assert_eq!(stats.degradation, Some(report));
+}
+
+#[tokio::test]
+async fn test_streamed_text_tool_call_becomes_tool_call_events() {
+    use ai_lib_rust::types::events::StreamingEvent;
+    use futures::StreamExt;
+
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::events(
+        [
+            "Checking. <tool",
+            "_call>\n{\"name\": \"get_weather\", ",
+            "\"arguments\": {\"city\": \"Paris\"}}\n</tool_call>",
+        ]
+        .into_iter()
+        .map(|chunk| StreamingEvent::PartialContentDelta {
+            content: chunk.into(),
+            sequence_id: None,
+        })
+        .chain([StreamingEvent::StreamEnd {
+            finish_reason: Some("stop".into()),
+        }])
+        .collect(),
+    ));
+    let client = fake
+        .client_builder()
+        .protocol_manifest(weak_manifest(&fake))
+        .degrade_requests(true)
+        .build("weak/tiny")
+        .await
+        .expect("client");
+    let (mut stream, _cancel, stats) = client
+        .chat()
+        .messages(vec![Message::user("Weather in Paris?")])
+        .tools(vec![weather_tool()])
+        .stream()
+        .execute_stream_with_cancel_and_stats()
+        .await
+        .expect("stream");
+
+    let mut text = String::new();
+    let mut calls = Vec::new();
+    while let Some(event) = stream.next().await {
+        match event.expect("event") {
+            StreamingEvent::PartialContentDelta { content, .. } => text.push_str(&content),
+            StreamingEvent::ToolCallStarted { tool_name, .. } => calls.push(tool_name),
+            StreamingEvent::PartialToolCall { arguments, .. } => {
+                let args: serde_json::Value = serde_json::from_str(&arguments).unwrap();
+                assert_eq!(args, json!({ "city": "Paris" }));
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(text.trim(), "Checking.");
+    assert_eq!(calls, ["get_weather"]);
+    assert!(stats.degradation.is_some());
+}
+
+#[tokio::test]
+async fn test_without_degradation_unsupported_requests_are_rejected() {
+    let fake = FakeProvider::new();
+    let client = fake
+        .client_builder()
+        .protocol_manifest(weak_manifest(&fake))
+        .build("weak/tiny")
+        .await
+        .expect("client");
+    let err = client
+        .chat()
+        .messages(photo_question())
+        .execute()
+        .await
+        .expect_err("vision not supported");
+    assert!(err.to_string().contains("multimodal"));
+    fake.assert_request_count(0);
+}
+
+#[tokio::test]
+async fn test_degradation_still_validates_requests_that_need_no_rewrite() {
+    let fake = FakeProvider::new();
+    let mut manifest = weak_manifest(&fake);
+    manifest.capabilities.streaming = false;
+    let client = fake
+        .client_builder()
+        .protocol_manifest(manifest)
+        .degrade_requests(true)
+        .build("weak/tiny")
+        .await
+        .expect("client");
+    let err = client
+        .chat()
+        .messages(vec![Message::user("hi")])
+        .stream()
+        .execute_stream()
+        .await
+        .err()
+        .expect("streaming not supported");
+    assert!(err.to_string().contains("streaming"), "{}", err);
+    fake.assert_request_count(0);
+}
+
+#[tokio::test]
+async fn test_structured_output_becomes_forced_tool_call() {
+    // The fake manifest has native tools but no structured_output.
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::tool_call(
+        "call_1",
+        "verdict",
+        json!({ "label": "spam" }),
+    ));
+    let client = fake
+        .client_builder()
+        .degrade_options(DegradeOptions::default().with_images(ImageFallback::Drop))
+        .build("fake/model")
+        .await
+        .expect("client");
+    let resp = client
+        .chat()
+        .messages(vec![Message::user("WIN A PRIZE NOW")])
+        .response_format(JsonModeConfig::from_schema(
+            json!({ "type": "object", "properties": { "label": { "type": "string" } } }),
+            "verdict",
+            true,
+        ))
+        .execute()
+        .await
+        .expect("chat");
+
+    let body = &fake.last_request().unwrap().body;
+    assert!(body.get("response_format").is_none());
+    assert_eq!(body["tools"][0]["function"]["name"], "verdict");
+    assert_eq!(body["tool_choice"]["function"]["name"], "verdict");
+
+    assert!(resp.tool_calls.is_empty());
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&resp.content).unwrap(),
+        json!({ "label": "spam" })
+    );
+    assert_eq!(
+        resp.degradation.unwrap().rewrites,
+        vec![Rewrite::ResponseFormatAsTool {
+            tool: "verdict".into()
+        }]
+    );
+}
+
+#[cfg(feature = "pdf_text")]
+#[tokio::test]
+async fn test_pdf_text_extracted_for_model_without_documents() {
+    use base64::Engine;
+
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::text("Revenue grew."));
+    let client = fake
+        .client_builder()
+        .protocol_manifest(weak_manifest(&fake))
+        .degrade_requests(true)
+        .build("weak/tiny")
+        .await
+        .expect("client");
+
+    let stream = b"BT /F1 12 Tf 72 712 Td (Q3 revenue grew 12%) Tj ET";
+    let mut pdf = format!(
+        "%PDF-1.4\n1 0 obj\n<< /Length {} >>\nstream\n",
+        stream.len()
+    )
+    .into_bytes();
+    pdf.extend_from_slice(stream);
+    pdf.extend_from_slice(b"\nendstream\nendobj\n%%EOF");
+    let data = base64::engine::general_purpose::STANDARD.encode(pdf);
+
+    let resp = client
+        .chat()
+        .messages(vec![Message::with_content(
+            MessageRole::User,
+            MessageContent::blocks(vec![
+                ContentBlock::text("Summarize"),
+                ContentBlock::document_base64(
+                    data,
+                    Some("application/pdf".into()),
+                    Some("q3.pdf".into()),
+                ),
+            ]),
+        )])
+        .execute()
+        .await
+        .expect("chat");
+
+    assert_eq!(
+        fake.last_request().unwrap().last_user_text().as_deref(),
+        Some("Summarize\n\n[Document: q3.pdf]\nQ3 revenue grew 12%")
+    );
+    assert_eq!(
+        resp.degradation.unwrap().rewrites,
+        vec![Rewrite::DocumentsExtracted { count: 1 }]
+    );
+}
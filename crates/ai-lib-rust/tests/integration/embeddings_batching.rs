@@ -0,0 +1,163 @@
+//! Integration tests for sub-batch planning, caching and over-long inputs in EmbeddingClient
+
+use std::sync::Arc;
+
+use ai_lib_rust::embeddings::{
+    magnitude, EmbeddingClient, EmbeddingClientBuilder, InMemoryEmbeddingCache, OverlongInputPolicy,
+};
+use ai_lib_rust::Error;
+use serde_json::{json, Value};
+
+/// Embedding endpoint answering `[text length, 1.0]` for each input it is sent.
+async fn length_mock(server: &mut mockito::Server, expected_requests: usize) -> mockito::Mock {
+    server
+        .mock("POST", "/embeddings")
+        .with_body_from_request(|request| {
+            let body: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            let inputs: Vec<String> = match &body["input"] {
+                Value::String(s) => vec![s.clone()],
+                other => serde_json::from_value(other.clone()).unwrap(),
+            };
+            let data: Vec<Value> = inputs
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(i, text)| json!({ "index": i, "embedding": [text.len() as f32, 1.0] }))
+                .collect();
+            json!({ "data": data, "usage": { "prompt_tokens": inputs.len(), "total_tokens": inputs.len() } })
+                .to_string()
+                .into_bytes()
+        })
+        .expect(expected_requests)
+        .create_async()
+        .await
+}
+
+fn builder(server: &mockito::Server) -> EmbeddingClientBuilder {
+    EmbeddingClient::builder()
+        .model("embed-test")
+        .api_key("sk-test")
+        .base_url(server.url())
+        .endpoint_path("/embeddings")
+}
+
+#[tokio::test]
+async fn test_splits_by_count_and_tokens_and_keeps_input_order() {
+    let mut server = mockito::Server::new_async().await;
+    // Six short inputs fill two 3-input requests; the 10-token input exceeds what is
+    // left of the 8-token request budget and goes alone.
+    let mock = length_mock(&mut server, 3).await;
+    let client = builder(&server)
+        .max_batch_size(3)
+        .max_request_tokens(8)
+        .max_concurrency(3)
+        .build()
+        .await
+        .unwrap();
+
+    let texts: Vec<String> = (1..=6)
+        .map(|n| "x".repeat(n))
+        .chain(std::iter::once("y".repeat(40)))
+        .collect();
+    let response = client.embed_batch(&texts).await.unwrap();
+
+    assert_eq!(response.len(), texts.len());
+    for (i, (emb, text)) in response.embeddings.iter().zip(&texts).enumerate() {
+        assert_eq!(emb.index, i);
+        assert_eq!(emb.vector[0], text.len() as f32);
+    }
+    assert_eq!(response.usage.prompt_tokens, 7);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_cache_and_duplicates_skip_provider_calls() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = length_mock(&mut server, 2).await;
+    let cache = Arc::new(InMemoryEmbeddingCache::new(100));
+    let client = builder(&server).cache(cache.clone()).build().await.unwrap();
+
+    let first = client.embed_batch(&["a", "bb", "a"]).await.unwrap();
+    assert_eq!(first.len(), 3);
+    assert_eq!(first.embeddings[2].vector, first.embeddings[0].vector);
+    assert_eq!(cache.len(), 2);
+
+    // Only "ccc" reaches the provider.
+    let second = client.embed_batch(&["bb", "ccc", "a"]).await.unwrap();
+    assert_eq!(second.embeddings[0].vector[0], 2.0);
+    assert_eq!(second.embeddings[1].vector[0], 3.0);
+    assert_eq!(second.usage.prompt_tokens, 1);
+
+    // Fully cached: no request at all.
+    let third = client.embed("ccc").await.unwrap();
+    assert_eq!(third.embeddings[0].vector[0], 3.0);
+    assert_eq!(third.usage.prompt_tokens, 0);
+    assert_eq!(cache.hits(), 3);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_sub_batches_that_succeed_are_cached_when_another_fails() {
+    let mut server = mockito::Server::new_async().await;
+    let failing = server
+        .mock("POST", "/embeddings")
+        .match_body(mockito::Matcher::Regex("boom".into()))
+        .with_status(500)
+        .with_body("upstream down")
+        .expect(1)
+        .create_async()
+        .await;
+    let ok = length_mock(&mut server, 1).await;
+    let cache = Arc::new(InMemoryEmbeddingCache::new(100));
+    let client = builder(&server)
+        .max_batch_size(1)
+        .max_concurrency(1)
+        .cache(cache.clone())
+        .build()
+        .await
+        .unwrap();
+
+    let err = client.embed_batch(&["fine", "boom"]).await.unwrap_err();
+    assert!(err.to_string().contains("upstream down"), "{}", err);
+    assert_eq!(cache.len(), 1);
+
+    // The vector that arrived before the failure is served from the cache.
+    let again = client.embed("fine").await.unwrap();
+    assert_eq!(again.embeddings[0].vector[0], 4.0);
+    assert_eq!(again.usage.prompt_tokens, 0);
+    failing.assert_async().await;
+    ok.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_overlong_inputs_error_or_chunk_and_pool() {
+    let mut server = mockito::Server::new_async().await;
+    let long = "word ".repeat(20);
+
+    let strict = builder(&server).max_input_tokens(5).build().await.unwrap();
+    let err = strict.embed_batch(&["short", &long]).await.unwrap_err();
+    match &err {
+        Error::Validation { message, context } => {
+            assert!(message.contains("input 1"), "{message}");
+            assert!(context
+                .hint
+                .as_deref()
+                .is_some_and(|h| h.contains("ChunkAndPool")));
+        }
+        other => panic!("expected validation error, got {other:?}"),
+    }
+
+    let mock = length_mock(&mut server, 1).await;
+    let pooled = builder(&server)
+        .max_input_tokens(5)
+        .overlong_inputs(OverlongInputPolicy::ChunkAndPool)
+        .build()
+        .await
+        .unwrap();
+    let response = pooled.embed_batch(&["short", &long]).await.unwrap();
+    assert_eq!(response.len(), 2);
+    assert_eq!(response.embeddings[0].vector, [5.0, 1.0]);
+    assert!((magnitude(&response.embeddings[1].vector) - 1.0).abs() < 1e-5);
+    assert!(response.usage.prompt_tokens > 2, "long input was split");
+    mock.assert_async().await;
+}
@@ -0,0 +1,116 @@
+//! Integration tests for indexing and querying documents through an embedding endpoint
+
+use ai_lib_rust::embeddings::{
+    Document, EmbeddingClient, MetadataFilter, SimilarityMetric, VectorStore,
+};
+use mockito::Matcher;
+use serde_json::json;
+
+fn embeddings_body(vectors: &[[f32; 3]]) -> String {
+    let data: Vec<_> = vectors
+        .iter()
+        .enumerate()
+        .map(|(i, v)| json!({ "object": "embedding", "index": i, "embedding": v }))
+        .collect();
+    json!({
+        "object": "list",
+        "data": data,
+        "model": "embed-test",
+        "usage": { "prompt_tokens": 12, "total_tokens": 12 }
+    })
+    .to_string()
+}
+
+async fn client(server: &mockito::Server) -> EmbeddingClient {
+    EmbeddingClient::builder()
+        .model("embed-test")
+        .api_key("sk-test")
+        .base_url(server.url())
+        .endpoint_path("/embeddings")
+        .build()
+        .await
+        .expect("client")
+}
+
+#[tokio::test]
+async fn test_index_documents_then_search_with_filter() {
+    let mut server = mockito::Server::new_async().await;
+    let index = server
+        .mock("POST", "/embeddings")
+        .match_body(Matcher::PartialJson(json!({
+            "input": ["shipping times", "return policy", "envois"]
+        })))
+        .with_body(embeddings_body(&[
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.9, 0.1, 0.0],
+        ]))
+        .create_async()
+        .await;
+    let query = server
+        .mock("POST", "/embeddings")
+        .match_body(Matcher::PartialJson(
+            json!({ "input": "when does it ship?" }),
+        ))
+        .with_body(embeddings_body(&[[0.95, 0.05, 0.0]]))
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = client(&server).await;
+    let mut store = VectorStore::with_metric(SimilarityMetric::Cosine);
+    let usage = client
+        .index_documents(
+            &mut store,
+            &[
+                Document::new("a", "shipping times").with_metadata(json!({ "lang": "en" })),
+                Document::new("b", "return policy").with_metadata(json!({ "lang": "en" })),
+                Document::new("c", "envois").with_metadata(json!({ "lang": "fr" })),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(usage.prompt_tokens, 12);
+    assert_eq!(store.len(), 3);
+    assert_eq!(store.dimensions(), 3);
+
+    let hits = client
+        .search(&store, "when does it ship?", 2, None)
+        .await
+        .unwrap();
+    assert_eq!(hits[0].text.as_deref(), Some("shipping times"));
+    assert_eq!(hits[1].id, "c");
+
+    let english = MetadataFilter::eq("lang", "en");
+    let hits = client
+        .search(&store, "when does it ship?", 2, Some(&english))
+        .await
+        .unwrap();
+    let ids: Vec<_> = hits.iter().map(|h| h.id.as_str()).collect();
+    assert_eq!(ids, ["a", "b"]);
+
+    index.assert_async().await;
+    query.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_index_documents_rejects_short_response() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/embeddings")
+        .with_body(embeddings_body(&[[1.0, 0.0, 0.0]]))
+        .create_async()
+        .await;
+
+    let client = client(&server).await;
+    let mut store = VectorStore::default();
+    let err = client
+        .index_documents(
+            &mut store,
+            &[Document::new("a", "one"), Document::new("b", "two")],
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("1 vectors for 2 inputs"));
+    assert!(store.is_empty());
+}
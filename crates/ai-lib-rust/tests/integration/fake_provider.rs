@@ -0,0 +1,268 @@
+//! Integration tests for the in-process `FakeProvider` (no HTTP server)
+
+use ai_lib_rust::error_code::StandardErrorCode;
+use ai_lib_rust::protocol::UnifiedRequest;
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use ai_lib_rust::types::events::StreamingEvent;
+use ai_lib_rust::Message;
+use futures::StreamExt;
+use serde_json::json;
+use std::time::Duration;
+
+fn request(prompt: &str) -> UnifiedRequest {
+    UnifiedRequest {
+        operation: "chat".to_string(),
+        model: "model".to_string(),
+        messages: vec![Message::user(prompt)],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_retry_then_success_reports_call_stats() {
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::error(StandardErrorCode::Overloaded, "busy"));
+    fake.push(
+        FakeResponse::text("Hello there")
+            .with_usage(3, 2)
+            .with_latency(Duration::from_millis(20)),
+    );
+
+    let client = fake.client("model").await.expect("client");
+    let (resp, stats) = client
+        .call_model_with_stats(request("hi"))
+        .await
+        .expect("chat");
+
+    assert_eq!(resp.content, "Hello there");
+    assert_eq!(resp.usage.as_ref().unwrap()["total_tokens"], 5);
+    assert_eq!(stats.retry_count, 1);
+    assert_eq!(stats.http_status, 200);
+    assert!(stats.duration_ms >= 20, "latency not applied: {stats:?}");
+    assert_eq!(stats.upstream_request_id.as_deref(), Some("fake-2"));
+
+    fake.assert_request_count(2);
+    fake.assert_exhausted();
+    let last = fake.last_request().unwrap();
+    assert_eq!(last.path, "/v1/chat/completions");
+    assert_eq!(last.model.as_deref(), Some("model"));
+    assert_eq!(last.last_user_text().as_deref(), Some("hi"));
+}
+
+#[tokio::test]
+async fn test_non_retryable_error_keeps_standard_code() {
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::error(
+        StandardErrorCode::Authentication,
+        "bad key",
+    ));
+
+    let client = fake.client("model").await.expect("client");
+    let err = client
+        .chat()
+        .messages(vec![Message::user("hi")])
+        .execute()
+        .await
+        .expect_err("auth error");
+
+    assert_eq!(err.standard_code(), Some(StandardErrorCode::Authentication));
+    fake.assert_request_count(1);
+}
+
+#[tokio::test]
+async fn test_fallback_to_second_model() {
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model(
+        "fake/primary",
+        FakeResponse::error(StandardErrorCode::ServerError, "down"),
+    );
+    fake.push_for_model("fake/backup", FakeResponse::text("from backup"));
+
+    let client = fake
+        .client_builder()
+        .with_fallbacks(vec!["fake/backup".to_string()])
+        .build("fake/primary")
+        .await
+        .expect("client");
+    let resp = client
+        .chat()
+        .messages(vec![Message::user("hi")])
+        .execute()
+        .await
+        .expect("fallback succeeds");
+
+    assert_eq!(resp.content, "from backup");
+    let models: Vec<_> = fake.requests().into_iter().map(|r| r.model).collect();
+    assert_eq!(
+        models,
+        vec![Some("primary".to_string()), Some("backup".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn test_rate_limited_is_retried() {
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::rate_limited(Duration::ZERO));
+    fake.push(FakeResponse::text("ok").with_rate_limit(100, 99, Duration::from_secs(1)));
+
+    let client = fake.client("model").await.expect("client");
+    let (resp, stats) = client
+        .call_model_with_stats(request("hi"))
+        .await
+        .expect("chat");
+    assert_eq!(resp.content, "ok");
+    assert_eq!(stats.retry_count, 1);
+    fake.assert_request_count(2);
+}
+
+#[tokio::test]
+async fn test_scripted_event_sequence_streams_through_pipeline() {
+    let fake = FakeProvider::new();
+    fake.push(
+        FakeResponse::events(vec![
+            StreamingEvent::ThinkingDelta {
+                thinking: "hmm".into(),
+                tool_consideration: None,
+            },
+            StreamingEvent::PartialContentDelta {
+                content: "Looking up".into(),
+                sequence_id: None,
+            },
+            StreamingEvent::ToolCallStarted {
+                tool_call_id: "call_1".into(),
+                tool_name: "weather".into(),
+                index: None,
+            },
+            StreamingEvent::PartialToolCall {
+                tool_call_id: "call_1".into(),
+                arguments: "{\"city\":".into(),
+                index: None,
+                is_complete: None,
+            },
+            StreamingEvent::PartialToolCall {
+                tool_call_id: "call_1".into(),
+                arguments: "\"Paris\"}".into(),
+                index: None,
+                is_complete: None,
+            },
+            StreamingEvent::StreamEnd {
+                finish_reason: Some("tool_calls".into()),
+            },
+        ])
+        .with_chunk_delay(Duration::from_millis(1)),
+    );
+
+    let client = fake.client("model").await.expect("client");
+    let mut stream = client
+        .chat()
+        .messages(vec![Message::user("weather?")])
+        .stream()
+        .execute_stream()
+        .await
+        .expect("stream");
+
+    let mut thinking = String::new();
+    let mut content = String::new();
+    let mut tool = None;
+    let mut args = String::new();
+    let mut finish = None;
+    while let Some(ev) = stream.next().await {
+        match ev.expect("event") {
+            StreamingEvent::ThinkingDelta { thinking: t, .. } => thinking.push_str(&t),
+            StreamingEvent::PartialContentDelta { content: c, .. } => content.push_str(&c),
+            StreamingEvent::ToolCallStarted { tool_name, .. } => tool = Some(tool_name),
+            StreamingEvent::PartialToolCall { arguments, .. } => args.push_str(&arguments),
+            StreamingEvent::StreamEnd { finish_reason } => finish = finish.or(finish_reason),
+            _ => {}
+        }
+    }
+    assert_eq!(thinking, "hmm");
+    assert_eq!(content, "Looking up");
+    assert_eq!(tool.as_deref(), Some("weather"));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&args).unwrap(),
+        json!({"city": "Paris"})
+    );
+    assert_eq!(finish.as_deref(), Some("tool_calls"));
+    assert!(fake.last_request().unwrap().stream);
+}
+
+#[tokio::test]
+async fn test_tool_call_reply_non_streaming_and_streaming() {
+    let fake = FakeProvider::new();
+    let reply = FakeResponse::tool_call("call_9", "search", json!({"q": "rust"}));
+    fake.set_default(reply);
+
+    let client = fake.client("model").await.expect("client");
+    let plain = client
+        .chat()
+        .messages(vec![Message::user("find")])
+        .execute()
+        .await
+        .expect("non-streaming");
+    let streamed = client
+        .chat()
+        .messages(vec![Message::user("find")])
+        .stream()
+        .execute()
+        .await
+        .expect("streaming");
+
+    for resp in [plain, streamed] {
+        assert_eq!(resp.tool_calls.len(), 1);
+        assert_eq!(resp.tool_calls[0].id, "call_9");
+        assert_eq!(resp.tool_calls[0].name, "search");
+        assert_eq!(resp.tool_calls[0].arguments, json!({"q": "rust"}));
+    }
+}
+
+#[tokio::test]
+async fn test_stream_error_surfaces_mid_stream() {
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::events(vec![
+        StreamingEvent::PartialContentDelta {
+            content: "partial".into(),
+            sequence_id: None,
+        },
+        StreamingEvent::StreamError {
+            error: json!("connection reset"),
+            event_id: None,
+        },
+    ]));
+
+    let client = fake.client("model").await.expect("client");
+    let mut stream = client
+        .chat()
+        .messages(vec![Message::user("hi")])
+        .stream()
+        .execute_stream()
+        .await
+        .expect("stream");
+    let mut saw_content = false;
+    let mut saw_error = false;
+    while let Some(ev) = stream.next().await {
+        match ev {
+            Ok(StreamingEvent::PartialContentDelta { .. }) => saw_content = true,
+            Err(_) => saw_error = true,
+            _ => {}
+        }
+    }
+    assert!(saw_content && saw_error);
+}
+
+#[tokio::test]
+async fn test_unscripted_request_fails_loudly() {
+    let fake = FakeProvider::new();
+    let client = fake.client("model").await.expect("client");
+    let err = client
+        .chat()
+        .messages(vec![Message::user("hi")])
+        .execute()
+        .await
+        .expect_err("nothing scripted");
+    assert!(
+        err.to_string().contains("no scripted response"),
+        "unexpected: {err}"
+    );
+    fake.assert_request_count(1);
+}
@@ -0,0 +1,309 @@
+//! Integration tests for provider Files APIs, upload-once dedupe and offloading
+
+use std::sync::Arc;
+
+use ai_lib_rust::drivers::{AnthropicDriver, ProviderDriver};
+use ai_lib_rust::files::{FilesApi, FilesClient, InMemoryFileRegistry};
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use ai_lib_rust::types::message::{ContentBlock, MessageContent, MessageRole};
+use ai_lib_rust::Message;
+use mockito::Matcher;
+use serde_json::json;
+
+async fn client(server: &mockito::Server, api: FilesApi) -> FilesClient {
+    FilesClient::builder()
+        .api_key("sk-test")
+        .base_url(server.url())
+        .api(api)
+        .build()
+        .await
+        .expect("client")
+}
+
+#[tokio::test]
+async fn test_openai_upload_once_list_get_delete() {
+    let mut server = mockito::Server::new_async().await;
+    let upload = server
+        .mock("POST", "/files")
+        .match_header("authorization", "Bearer sk-test")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#"name="purpose"\r\n\r\nuser_data"#.into()),
+            Matcher::Regex(r#"name="file"; filename="report.pdf""#.into()),
+        ]))
+        .with_body(
+            json!({
+                "id": "file-1", "object": "file", "bytes": 3, "created_at": 1700000000,
+                "filename": "report.pdf", "purpose": "user_data"
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+    let page1 = server
+        .mock("GET", "/files")
+        .match_query(Matcher::Missing)
+        .with_body(
+            json!({ "data": [{ "id": "file-1", "bytes": 3 }], "has_more": true, "last_id": "file-1" })
+                .to_string(),
+        )
+        .create_async()
+        .await;
+    let page2 = server
+        .mock("GET", "/files")
+        .match_query(Matcher::UrlEncoded("after".into(), "file-1".into()))
+        .with_body(json!({ "data": [{ "id": "file-2" }], "has_more": false }).to_string())
+        .create_async()
+        .await;
+    let get = server
+        .mock("GET", "/files/file-1")
+        .with_body(json!({ "id": "file-1", "filename": "report.pdf" }).to_string())
+        .create_async()
+        .await;
+    let delete = server
+        .mock("DELETE", "/files/file-1")
+        .with_body(json!({ "id": "file-1", "deleted": true }).to_string())
+        .create_async()
+        .await;
+
+    let files = client(&server, FilesApi::OpenAi).await;
+    let first = files
+        .upload_once(b"pdf", "report.pdf", "application/pdf")
+        .await
+        .unwrap();
+    let second = files
+        .upload_once(b"pdf", "renamed.pdf", "application/pdf")
+        .await
+        .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.size_bytes, Some(3));
+    assert_eq!(first.created_at.as_deref(), Some("1700000000"));
+    upload.assert_async().await;
+
+    let listed = files.list().await.unwrap();
+    assert_eq!(
+        listed.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(),
+        ["file-1", "file-2"]
+    );
+    page1.assert_async().await;
+    page2.assert_async().await;
+
+    assert_eq!(
+        files.get("file-1").await.unwrap().filename.as_deref(),
+        Some("report.pdf")
+    );
+    files.delete("file-1").await.unwrap();
+    get.assert_async().await;
+    delete.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_anthropic_offload_swaps_large_blocks_for_file_ids() {
+    let mut server = mockito::Server::new_async().await;
+    let upload = server
+        .mock("POST", "/files")
+        .match_header("x-api-key", "sk-test")
+        .match_header("anthropic-beta", "files-api-2025-04-14")
+        .match_body(Matcher::Regex(r#"filename="document.pdf""#.into()))
+        .with_body(
+            json!({ "id": "file_011", "type": "file", "filename": "document.pdf",
+                    "mime_type": "application/pdf", "size_bytes": 12 })
+            .to_string(),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+    let image_upload = server
+        .mock("POST", "/files")
+        .match_body(Matcher::Regex(r#"filename="image.png""#.into()))
+        .with_body(json!({ "id": "file_012", "mime_type": "image/png" }).to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let registry = Arc::new(InMemoryFileRegistry::new());
+    let files = FilesClient::builder()
+        .api_key("sk-test")
+        .base_url(server.url())
+        .api(FilesApi::Anthropic)
+        .registry(registry.clone())
+        .build()
+        .await
+        .unwrap();
+
+    // "JVBERi0xLjQKJeLjz9M=" decodes to 14 bytes; "AQ==" to 1.
+    let pdf = || {
+        ContentBlock::document_base64(
+            "JVBERi0xLjQKJeLjz9M=".into(),
+            Some("application/pdf".into()),
+            None,
+        )
+    };
+    let mut messages = vec![
+        Message::with_content(
+            MessageRole::User,
+            MessageContent::blocks(vec![
+                ContentBlock::text("Compare"),
+                pdf(),
+                ContentBlock::image_base64(
+                    "iVBORw0KGgoAAAANSUhEUg==".into(),
+                    Some("image/png".into()),
+                ),
+                ContentBlock::image_base64("AQ==".into(), Some("image/png".into())),
+            ]),
+        ),
+        Message::with_content(MessageRole::User, MessageContent::blocks(vec![pdf()])),
+    ];
+    let replaced = files.offload(&mut messages, 8).await.unwrap();
+    assert_eq!(replaced, 3);
+    assert_eq!(registry.len(), 2);
+    upload.assert_async().await;
+    image_upload.assert_async().await;
+
+    let request = AnthropicDriver::new("anthropic", vec![])
+        .build_request(&messages, "claude-sonnet-4", None, None, false, None)
+        .unwrap();
+    assert_eq!(request.headers["anthropic-beta"], "files-api-2025-04-14");
+    let content = &request.body["messages"][0]["content"];
+    assert_eq!(
+        content[1]["source"],
+        json!({ "type": "file", "file_id": "file_011" })
+    );
+    assert_eq!(
+        content[2]["source"],
+        json!({ "type": "file", "file_id": "file_012" })
+    );
+    assert_eq!(content[3]["source"]["type"], "base64");
+    assert_eq!(
+        request.body["messages"][1]["content"][0]["source"]["file_id"],
+        "file_011"
+    );
+}
+
+#[tokio::test]
+async fn test_client_with_files_sends_uploaded_references() {
+    let mut server = mockito::Server::new_async().await;
+    let upload = server
+        .mock("POST", "/files")
+        .match_body(Matcher::Regex(r#"filename="q3.pdf""#.into()))
+        .with_body(json!({ "id": "file-q3", "filename": "q3.pdf" }).to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::text("Revenue grew."));
+    fake.push(FakeResponse::text("Margins held."));
+    let ai = fake
+        .client_builder()
+        .files(client(&server, FilesApi::OpenAi).await, 8)
+        .build("fake/model")
+        .await
+        .expect("client");
+    let question = || {
+        vec![Message::with_content(
+            MessageRole::User,
+            MessageContent::blocks(vec![
+                ContentBlock::text("Summarize"),
+                ContentBlock::document_base64(
+                    "JVBERi0xLjQKJeLjz9M=".into(),
+                    Some("application/pdf".into()),
+                    Some("q3.pdf".into()),
+                ),
+            ]),
+        )]
+    };
+    for _ in 0..2 {
+        ai.chat()
+            .messages(question())
+            .execute()
+            .await
+            .expect("chat");
+        let body = fake.last_request().unwrap().body;
+        let source = &body["messages"][0]["content"][1]["source"];
+        assert_eq!(source["type"], "file");
+        assert_eq!(source["data"], "file-q3");
+    }
+    upload.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_gemini_resumable_upload_and_list() {
+    let mut server = mockito::Server::new_async().await;
+    let start = server
+        .mock("POST", "/upload/files")
+        .match_header("x-goog-api-key", "g-key")
+        .match_header("x-goog-upload-protocol", "resumable")
+        .match_header("x-goog-upload-command", "start")
+        .match_header("x-goog-upload-header-content-length", "4")
+        .match_header("x-goog-upload-header-content-type", "application/pdf")
+        .match_body(Matcher::PartialJson(
+            json!({ "file": { "display_name": "doc.pdf" } }),
+        ))
+        .with_header(
+            "x-goog-upload-url",
+            &format!("{}/upload-session/1", server.url()),
+        )
+        .create_async()
+        .await;
+    let finalize = server
+        .mock("POST", "/upload-session/1")
+        .match_header("x-goog-upload-command", "upload, finalize")
+        .match_header("x-goog-upload-offset", "0")
+        .match_body("%PDF")
+        .with_body(
+            json!({ "file": {
+                "name": "files/abc", "displayName": "doc.pdf", "mimeType": "application/pdf",
+                "sizeBytes": "4", "uri": format!("{}/files/abc", server.url()), "state": "ACTIVE",
+                "expirationTime": "2026-01-03T00:00:00Z"
+            }})
+            .to_string(),
+        )
+        .create_async()
+        .await;
+    let list = server
+        .mock("GET", "/files")
+        .with_body(
+            json!({ "files": [{ "name": "files/abc" }, { "name": "files/def" }] }).to_string(),
+        )
+        .create_async()
+        .await;
+    let delete = server
+        .mock("DELETE", "/files/abc")
+        .with_body("{}")
+        .create_async()
+        .await;
+
+    let files = FilesClient::builder()
+        .api_key("g-key")
+        .base_url(server.url())
+        .api(FilesApi::Gemini)
+        .build()
+        .await
+        .unwrap();
+    let file = files
+        .upload(b"%PDF", "doc.pdf", "application/pdf")
+        .await
+        .unwrap();
+    start.assert_async().await;
+    finalize.assert_async().await;
+    assert_eq!(file.id, "files/abc");
+    assert_eq!(file.size_bytes, Some(4));
+    assert_eq!(file.reference(), format!("{}/files/abc", server.url()));
+
+    assert_eq!(files.list().await.unwrap().len(), 2);
+    files.delete(&file.id).await.unwrap();
+    list.assert_async().await;
+    delete.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_build_requires_base_url() {
+    let err = FilesClient::builder()
+        .api_key("sk-test")
+        .build()
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("base_url required"));
+}
@@ -0,0 +1,154 @@
+//! Integration tests for hedged streaming requests
+
+use ai_lib_rust::client::{HedgePolicy, HedgeWinner};
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use ai_lib_rust::types::events::StreamingEvent;
+use ai_lib_rust::{AiClientBuilder, Message};
+use futures::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+async fn collect_text(
+    mut stream: impl futures::Stream<Item = ai_lib_rust::Result<StreamingEvent>> + Unpin,
+) -> String {
+    let mut content = String::new();
+    while let Some(ev) = stream.next().await {
+        if let StreamingEvent::PartialContentDelta { content: c, .. } = ev.expect("event") {
+            content.push_str(&c);
+        }
+    }
+    content
+}
+
+#[tokio::test]
+async fn test_slow_primary_is_hedged_and_hedge_wins() {
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model(
+        "primary",
+        FakeResponse::text("from primary").with_latency(Duration::from_millis(2000)),
+    );
+    fake.push_for_model("backup", FakeResponse::text("from backup"));
+    let client = fake
+        .client_builder()
+        .hedging(HedgePolicy::fixed(Duration::from_millis(50)).with_model("fake/backup"))
+        .build("fake/primary")
+        .await
+        .expect("client");
+    assert_eq!(client.hedge_delay(), Some(Duration::from_millis(50)));
+
+    let started = std::time::Instant::now();
+    let (stream, _cancel, stats) = client
+        .chat()
+        .messages(vec![Message::user("hello")])
+        .stream()
+        .execute_stream_with_cancel_and_stats()
+        .await
+        .expect("stream");
+    assert!(started.elapsed() < Duration::from_millis(1500));
+    assert_eq!(collect_text(stream).await, "from backup");
+
+    let hedge = stats.hedge.expect("hedge fired");
+    assert_eq!(hedge.winner, HedgeWinner::Hedge);
+    assert_eq!(hedge.model, "fake/backup");
+    assert_eq!(hedge.delay_ms, 50);
+    assert_eq!(hedge.extra_requests, 1);
+    // The primary was still waiting for its response and was aborted, not cancelled.
+    assert!(hedge.loser.is_none());
+
+    let metrics = client.metrics();
+    assert_eq!(metrics.hedged_requests, 1);
+    assert_eq!(metrics.hedge_wins, 1);
+    assert_eq!(metrics.hedge_cancelled_streams, 0);
+    // The aborted primary's prompt ("hello") was still sent upstream.
+    assert_eq!(metrics.hedge_loser_tokens, 2);
+    assert_eq!(fake.request_count(), 2);
+}
+
+#[tokio::test]
+async fn test_fast_primary_does_not_hedge() {
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model("primary", FakeResponse::text("quick"));
+    let client = fake
+        .client_builder()
+        .hedging(HedgePolicy::fixed(Duration::from_millis(500)).with_model("fake/backup"))
+        .build("fake/primary")
+        .await
+        .expect("client");
+
+    let (stream, _cancel, stats) = client
+        .chat()
+        .messages(vec![Message::user("hello")])
+        .stream()
+        .execute_stream_with_cancel_and_stats()
+        .await
+        .expect("stream");
+    assert_eq!(collect_text(stream).await, "quick");
+    assert!(stats.hedge.is_none());
+    assert_eq!(client.metrics().hedged_requests, 0);
+    assert_eq!(fake.request_count(), 1);
+}
+
+fn sse_text(text: &str) -> String {
+    format!(
+        "data: {{\"choices\":[{{\"delta\":{{\"content\":\"{}\"}},\"index\":0}}]}}\n\ndata: [DONE]\n\n",
+        text
+    )
+}
+
+#[tokio::test]
+async fn test_same_model_hedge_keeps_base_url_override() {
+    // The manifest points at `default_host`; the client is built against `proxy`.
+    let mut default_host = mockito::Server::new_async().await;
+    let untouched = default_host
+        .mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body(sse_text("from default host"))
+        .expect(0)
+        .create_async()
+        .await;
+    let mut proxy = mockito::Server::new_async().await;
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let proxied = proxy
+        .mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_chunked_body(move |w| {
+            // Only the first request is slow to its first event.
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                std::thread::sleep(Duration::from_millis(1500));
+                return w.write_all(sse_text("slow primary").as_bytes());
+            }
+            w.write_all(sse_text("from proxy").as_bytes())
+        })
+        .expect(2)
+        .create_async()
+        .await;
+
+    let mut manifest = FakeProvider::new().with_max_retries(0).manifest().clone();
+    manifest.endpoint.base_url = default_host.url();
+    let client = AiClientBuilder::new()
+        .protocol_manifest(manifest)
+        .base_url_override(proxy.url())
+        .hedging(HedgePolicy::fixed(Duration::from_millis(100)))
+        .build("fake/primary")
+        .await
+        .expect("client");
+
+    let (stream, _cancel, stats) = client
+        .chat()
+        .messages(vec![Message::user("hello")])
+        .stream()
+        .execute_stream_with_cancel_and_stats()
+        .await
+        .expect("stream");
+    assert_eq!(collect_text(stream).await, "from proxy");
+    let hedge = stats.hedge.expect("hedge fired");
+    assert_eq!(hedge.winner, HedgeWinner::Hedge);
+    assert_eq!(hedge.model, "fake/primary");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    untouched.assert_async().await;
+    proxied.assert_async().await;
+}
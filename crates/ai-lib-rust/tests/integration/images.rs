@@ -0,0 +1,294 @@
+//! Integration tests for image generation, edits, variations and inline chat images
+
+use crate::integration::support::protocols;
+use ai_lib_rust::images::{ImageApi, ImageClient, ImageData, ImageInput, ImageOptions};
+use ai_lib_rust::types::message::ContentBlock;
+use ai_lib_rust::{AiClientBuilder, Message};
+use mockito::Matcher;
+use serde_json::json;
+
+async fn client(server: &mockito::Server, api: ImageApi, model: &str) -> ImageClient {
+    ImageClient::builder()
+        .model(model)
+        .api_key("sk-test")
+        .base_url(server.url())
+        .api(api)
+        .build()
+        .await
+        .expect("client")
+}
+
+#[tokio::test]
+async fn test_openai_generate_returns_bytes_and_revised_prompt() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/images/generations")
+        .match_header("authorization", "Bearer sk-test")
+        .match_body(Matcher::PartialJson(json!({
+            "model": "gpt-image-1",
+            "prompt": "a red fox",
+            "size": "1024x1024",
+            "n": 2,
+            "output_format": "webp"
+        })))
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "created": 1,
+                "output_format": "webp",
+                "data": [
+                    { "b64_json": "AQID", "revised_prompt": "a red fox in snow" },
+                    { "url": "https://cdn.example/fox.webp" }
+                ],
+                "usage": { "total_tokens": 50 }
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let options = ImageOptions {
+        n: Some(2),
+        size: Some("1024x1024".into()),
+        output_format: Some("webp".into()),
+        ..Default::default()
+    };
+    let response = client(&server, ImageApi::OpenAi, "gpt-image-1")
+        .await
+        .generate("a red fox", &options)
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(response.images[0].bytes(), Some(&[1u8, 2, 3][..]));
+    assert_eq!(response.images[0].media_type.as_deref(), Some("image/webp"));
+    assert_eq!(
+        response.images[0].revised_prompt.as_deref(),
+        Some("a red fox in snow")
+    );
+    assert_eq!(
+        response.images[1].data,
+        ImageData::Url("https://cdn.example/fox.webp".into())
+    );
+    assert_eq!(response.usage.unwrap()["total_tokens"], 50);
+    assert!(matches!(
+        response.images[0].to_content_block(),
+        ContentBlock::Image { source } if source.data == "AQID"
+    ));
+}
+
+#[tokio::test]
+async fn test_openai_edit_and_variation_are_multipart() {
+    let mut server = mockito::Server::new_async().await;
+    let edit = server
+        .mock("POST", "/images/edits")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#"name="image"; filename="image0.png""#.into()),
+            Matcher::Regex(r#"name="mask"; filename="mask.png""#.into()),
+            Matcher::Regex(r#"name="prompt"\r\n\r\nadd a hat"#.into()),
+            Matcher::Regex(r#"name="response_format"\r\n\r\nurl"#.into()),
+        ]))
+        .with_body(json!({ "data": [{ "url": "https://cdn.example/hat.png" }] }).to_string())
+        .create_async()
+        .await;
+    let variation = server
+        .mock("POST", "/images/variations")
+        .match_body(Matcher::Regex(r#"name="n"\r\n\r\n3"#.into()))
+        .with_body(json!({ "data": [{ "b64_json": "AQI=" }] }).to_string())
+        .create_async()
+        .await;
+
+    let client = client(&server, ImageApi::OpenAi, "dall-e-2").await;
+    let image = ImageInput::png(b"png-bytes".to_vec());
+    let mask = ImageInput::png(b"mask-bytes".to_vec());
+    let edited = client
+        .edit(
+            "add a hat",
+            std::slice::from_ref(&image),
+            Some(&mask),
+            &ImageOptions {
+                response_format: Some("url".into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(edited.images[0].url(), Some("https://cdn.example/hat.png"));
+
+    let varied = client
+        .variations(
+            &image,
+            &ImageOptions {
+                n: Some(3),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(varied.images[0].bytes(), Some(&[1u8, 2][..]));
+    edit.assert_async().await;
+    variation.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_gemini_generate_content_images_and_text() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/models/gemini-image:generateContent")
+        .match_header("x-goog-api-key", "sk-test")
+        .match_body(Matcher::PartialJson(json!({
+            "contents": [{ "parts": [
+                { "text": "make it blue" },
+                { "inlineData": { "mimeType": "image/png", "data": "cG5n" } }
+            ]}],
+            "generationConfig": {
+                "responseModalities": ["TEXT", "IMAGE"],
+                "imageConfig": { "aspectRatio": "16:9" }
+            }
+        })))
+        .with_body(
+            json!({
+                "candidates": [{ "content": { "parts": [
+                    { "text": "Here it is." },
+                    { "inlineData": { "mimeType": "image/png", "data": "BAU=" } }
+                ]}}],
+                "usageMetadata": { "totalTokenCount": 12 }
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let client = client(&server, ImageApi::Gemini, "gemini-image").await;
+    let options = ImageOptions {
+        size: Some("1920x1080".into()),
+        ..Default::default()
+    };
+    let response = client
+        .edit(
+            "make it blue",
+            &[ImageInput::png(b"png".to_vec())],
+            None,
+            &options,
+        )
+        .await
+        .unwrap();
+    mock.assert_async().await;
+    assert_eq!(response.text.as_deref(), Some("Here it is."));
+    assert_eq!(response.images[0].bytes(), Some(&[4u8, 5][..]));
+    assert_eq!(response.usage.unwrap()["totalTokenCount"], 12);
+
+    let err = client
+        .edit(
+            "x",
+            &[ImageInput::png(vec![1])],
+            Some(&ImageInput::png(vec![2])),
+            &options,
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("does not take a mask"));
+}
+
+#[tokio::test]
+async fn test_imagen_predict_with_mask_edit() {
+    let mut server = mockito::Server::new_async().await;
+    let generate = server
+        .mock("POST", "/models/imagen-test:predict")
+        .match_body(Matcher::PartialJson(json!({
+            "instances": [{ "prompt": "a castle" }],
+            "parameters": { "sampleCount": 1, "negativePrompt": "people" }
+        })))
+        .with_body(
+            json!({ "predictions": [
+                { "bytesBase64Encoded": "AQ==", "mimeType": "image/png", "prompt": "a stone castle" }
+            ]})
+            .to_string(),
+        )
+        .create_async()
+        .await;
+    let edit = server
+        .mock("POST", "/models/imagen-test:predict")
+        .match_body(Matcher::PartialJson(json!({
+            "parameters": { "editMode": "EDIT_MODE_INPAINT_INSERTION" }
+        })))
+        .with_body(json!({ "predictions": [{ "bytesBase64Encoded": "Ag==" }] }).to_string())
+        .create_async()
+        .await;
+
+    let client = client(&server, ImageApi::Imagen, "imagen-test").await;
+    let generated = client
+        .generate(
+            "a castle",
+            &ImageOptions {
+                negative_prompt: Some("people".into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(generated.images[0].bytes(), Some(&[1u8][..]));
+    assert_eq!(
+        generated.images[0].revised_prompt.as_deref(),
+        Some("a stone castle")
+    );
+
+    let edited = client
+        .edit(
+            "add a flag",
+            &[ImageInput::png(vec![1])],
+            Some(&ImageInput::png(vec![2])),
+            &ImageOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(edited.images[0].bytes(), Some(&[2u8][..]));
+    generate.assert_async().await;
+    edit.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_chat_response_inline_images() {
+    let mut server = mockito::Server::new_async().await;
+    let _mock = server
+        .mock("POST", "/chat/completions")
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": "Drawn.",
+                        "images": [{ "type": "image_url", "image_url": { "url": "data:image/png;base64,AQID" } }]
+                    },
+                    "finish_reason": "stop"
+                }]
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let client = AiClientBuilder::new()
+        .protocol_path(protocols())
+        .base_url_override(server.url())
+        .api_key("sk-test")
+        .build("openai/gpt-4o")
+        .await
+        .expect("build client");
+    let response = client
+        .chat()
+        .messages(vec![Message::user("draw")])
+        .execute()
+        .await
+        .unwrap();
+
+    assert_eq!(response.content, "Drawn.");
+    assert!(matches!(
+        &response.images[..],
+        [ContentBlock::Image { source }]
+            if source.source_type == "base64"
+                && source.media_type.as_deref() == Some("image/png")
+                && source.data == "AQID"
+    ));
+}
@@ -0,0 +1,125 @@
+//! Integration tests for remote manifest caching, revalidation and signature checks
+
+use crate::integration::support::{protocols, temp_path};
+use ai_lib_rust::protocol::{FetchSource, Provenance, RemoteManifestConfig, RemoteManifestStore};
+#[cfg(feature = "manifest_signatures")]
+use ai_lib_rust::protocol::{ProtocolError, ProtocolLoader, TrustPolicy};
+#[cfg(feature = "manifest_signatures")]
+use base64::Engine as _;
+#[cfg(feature = "manifest_signatures")]
+use ed25519_dalek::{Signer, SigningKey};
+use mockito::Matcher;
+use std::time::Duration;
+
+const MANIFEST_PATH: &str = "v1/providers/openai.yaml";
+
+fn fixture() -> String {
+    std::fs::read_to_string(std::path::Path::new(&protocols()).join(MANIFEST_PATH))
+        .expect("fixture manifest")
+}
+
+#[cfg(feature = "manifest_signatures")]
+fn sign(key: &SigningKey, body: &str) -> String {
+    base64::engine::general_purpose::STANDARD.encode(key.sign(body.as_bytes()).to_bytes())
+}
+
+#[tokio::test]
+async fn test_etag_revalidation_and_offline_cache() {
+    let mut server = mockito::Server::new_async().await;
+    let body = fixture();
+    let path = format!("/{MANIFEST_PATH}");
+    let full = server
+        .mock("GET", path.as_str())
+        .match_header("if-none-match", Matcher::Missing)
+        .with_status(200)
+        .with_header("etag", "\"rev-1\"")
+        .with_body(&body)
+        .expect(1)
+        .create_async()
+        .await;
+    let revalidate = server
+        .mock("GET", path.as_str())
+        .match_header("if-none-match", "\"rev-1\"")
+        .with_status(304)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let dir = temp_path("manifest-cache-etag");
+    let config = RemoteManifestConfig::new()
+        .with_root(server.url())
+        .with_cache_dir(&dir)
+        .with_max_age(Duration::ZERO);
+    let store = RemoteManifestStore::new(config.clone());
+
+    let first = store.fetch(MANIFEST_PATH).await.unwrap();
+    assert_eq!(first.source, FetchSource::Network);
+    assert_eq!(first.provenance, Provenance::Unverified);
+    assert_eq!(first.body, body.as_bytes());
+
+    let second = store.fetch(MANIFEST_PATH).await.unwrap();
+    assert_eq!(second.source, FetchSource::Revalidated);
+    assert_eq!(second.body, body.as_bytes());
+
+    // Offline mode never touches the network.
+    let offline = RemoteManifestStore::new(config.offline(true));
+    let third = offline.fetch(MANIFEST_PATH).await.unwrap();
+    assert_eq!(third.source, FetchSource::StaleCache);
+    assert_eq!(third.body, body.as_bytes());
+
+    full.assert_async().await;
+    revalidate.assert_async().await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "manifest_signatures")]
+#[tokio::test]
+async fn test_loader_verifies_signed_manifests() {
+    let key = SigningKey::from_bytes(&[42; 32]);
+    let trusted = base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes());
+    let body = fixture();
+    let path = format!("/{MANIFEST_PATH}");
+
+    let mut server = mockito::Server::new_async().await;
+    let _manifest = server
+        .mock("GET", path.as_str())
+        .with_body(&body)
+        .create_async()
+        .await;
+    let signature = server
+        .mock("GET", format!("{path}.sig").as_str())
+        .with_body(sign(&key, &body))
+        .create_async()
+        .await;
+
+    let config = RemoteManifestConfig::new()
+        .with_root(server.url())
+        .without_cache()
+        .with_bundled(false)
+        .with_trust(
+            TrustPolicy::new()
+                .with_trusted_key(&trusted)
+                .unwrap()
+                .require_verified(true),
+        );
+    let loader = ProtocolLoader::new().with_remote(config.clone());
+    let manifest = loader.load_provider("openai").await.unwrap();
+    assert_eq!(manifest.id, "openai");
+
+    // A signature from an untrusted key is an integrity failure, not a fallthrough.
+    signature.remove_async().await;
+    let _forged = server
+        .mock("GET", format!("{path}.sig").as_str())
+        .with_body(sign(&SigningKey::from_bytes(&[7; 32]), &body))
+        .create_async()
+        .await;
+    let err = ProtocolLoader::new()
+        .with_remote(config)
+        .load_provider("openai")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, ProtocolError::IntegrityError { .. }),
+        "{err:?}"
+    );
+}
@@ -1,7 +1,43 @@
 //! Integration tests with mock HTTP server
 
+#[cfg(all(feature = "app_config", feature = "testing"))]
+pub mod app_config;
 pub mod batch;
+pub mod cassette;
+pub mod cli;
+#[cfg(feature = "testing")]
+pub mod degrade;
+#[cfg(feature = "embeddings")]
+pub mod embeddings_batching;
+#[cfg(feature = "embeddings")]
+pub mod embeddings_store;
 pub mod error_handling;
+#[cfg(feature = "testing")]
+pub mod fake_provider;
+#[cfg(feature = "files")]
+pub mod files;
+#[cfg(feature = "testing")]
+pub mod hedging;
+#[cfg(feature = "images")]
+pub mod images;
+pub mod manifest_cache;
 pub mod mock_server;
 pub mod multimodal;
+#[cfg(all(feature = "prompts", feature = "testing"))]
+pub mod prompts;
+#[cfg(feature = "batch")]
+pub mod provider_batch;
+#[cfg(all(feature = "guardrails", feature = "testing"))]
+pub mod pseudonymize;
+#[cfg(all(feature = "rag", feature = "testing"))]
+pub mod rag;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+#[cfg(feature = "testing")]
+pub mod stream_recovery;
 pub mod streaming;
+#[cfg(feature = "stt")]
+pub mod stt;
+pub mod support;
+#[cfg(feature = "tts")]
+pub mod tts;
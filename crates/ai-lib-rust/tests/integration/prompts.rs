@@ -0,0 +1,96 @@
+//! Integration tests for prompt templates flowing into requests, `CallStats` and feedback
+
+use ai_lib_rust::feedback::{FeedbackEvent, ThumbsFeedback};
+use ai_lib_rust::prompts::{PromptRegistry, PromptTemplate};
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use ai_lib_rust::types::PromptRef;
+use futures::StreamExt;
+use serde_json::json;
+
+fn registry() -> PromptRegistry {
+    let mut registry = PromptRegistry::new();
+    for (version, style) in [("1.0.0", "briefly"), ("2.0.0", "in one sentence")] {
+        let template = PromptTemplate::from_yaml(&format!(
+            r#"
+id: support.answer
+version: "{version}"
+variables:
+  question: {{ type: string }}
+  product: {{ type: string, default: Acme }}
+messages:
+  - role: system
+    content: "You support {{{{ product }}}}. Answer {style}."
+  - role: user
+    content: "{{{{ question }}}}"
+"#
+        ))
+        .expect("template");
+        registry.register(template).expect("register");
+    }
+    registry
+}
+
+#[tokio::test]
+async fn test_rendered_prompt_is_sent_and_traced() {
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::text("Reset it from settings."));
+    let client = fake.client("model").await.expect("client");
+
+    let rendered = registry()
+        .render(
+            "support.answer",
+            "latest",
+            json!({ "question": "How do I reset my password?" }),
+        )
+        .expect("render");
+    let (resp, stats) = client
+        .chat()
+        .prompt(rendered)
+        .execute_with_stats()
+        .await
+        .expect("chat");
+
+    assert_eq!(resp.content, "Reset it from settings.");
+    let prompt = stats.prompt.clone().expect("prompt ref on stats");
+    assert_eq!(prompt, PromptRef::new("support.answer", "2.0.0"));
+
+    let request = fake.last_request().unwrap();
+    assert_eq!(
+        request.messages()[0]["content"],
+        "You support Acme. Answer in one sentence."
+    );
+    assert_eq!(
+        request.last_user_text().as_deref(),
+        Some("How do I reset my password?")
+    );
+
+    let feedback = FeedbackEvent::Thumbs(ThumbsFeedback::thumbs_up(&stats.client_request_id))
+        .with_prompt(stats.prompt);
+    assert_eq!(feedback.prompt(), Some(&prompt));
+    let wire = serde_json::to_value(&feedback).unwrap();
+    assert_eq!(wire["Thumbs"]["prompt"]["version"], "2.0.0");
+}
+
+#[tokio::test]
+async fn test_streaming_call_stats_carry_pinned_prompt_version() {
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::text("Hi"));
+    let client = fake.client("model").await.expect("client");
+
+    let rendered = registry()
+        .render("support.answer", "1.0.0", json!({ "question": "hello" }))
+        .expect("render");
+    let (mut stream, _cancel, stats) = client
+        .chat()
+        .prompt(rendered)
+        .stream()
+        .execute_stream_with_cancel_and_stats()
+        .await
+        .expect("stream");
+    while stream.next().await.is_some() {}
+
+    assert_eq!(
+        stats.prompt.map(|p| p.to_string()).as_deref(),
+        Some("support.answer@1.0.0")
+    );
+}
@@ -0,0 +1,237 @@
+//! Integration tests for provider batch APIs (OpenAI Batch, Anthropic Message Batches)
+
+use ai_lib_rust::batch::{
+    BatchClient, BatchItemErrorKind, BatchPollConfig, BatchRequestItem, BatchStatus,
+};
+use ai_lib_rust::protocol::ProtocolManifest;
+use ai_lib_rust::{ChatBatchRequest, Message};
+use std::time::Duration;
+
+fn manifest(adapter: &str) -> ProtocolManifest {
+    serde_yaml::from_str(&format!(
+        r#"
+id: batch-test
+protocol_version: "2.0"
+status: stable
+category: ai_provider
+official_url: "https://example.com"
+support_contact: "https://example.com"
+endpoint:
+  base_url: "https://api.example.com/v1"
+capabilities:
+  streaming: true
+  tools: true
+  vision: false
+endpoints:
+  chat:
+    path: "/chat/completions"
+  batches:
+    path: "/batches"
+    adapter: {adapter}
+  files:
+    path: "/files"
+"#
+    ))
+    .expect("manifest")
+}
+
+fn items() -> Vec<BatchRequestItem> {
+    vec![
+        BatchRequestItem::new("q1", ChatBatchRequest::new(vec![Message::user("2+2?")])),
+        BatchRequestItem::new("q2", ChatBatchRequest::new(vec![Message::user("3+3?")])),
+        BatchRequestItem::new("q3", ChatBatchRequest::new(vec![Message::user("4+4?")])),
+    ]
+}
+
+fn fast_poll() -> BatchPollConfig {
+    BatchPollConfig {
+        initial_interval: Duration::from_millis(10),
+        max_interval: Duration::from_millis(20),
+        multiplier: 2.0,
+        timeout: Some(Duration::from_secs(5)),
+    }
+}
+
+#[tokio::test]
+async fn test_openai_batch_run_maps_results_by_custom_id() {
+    let mut server = mockito::Server::new_async().await;
+    let upload = server
+        .mock("POST", "/files")
+        .match_header(
+            "content-type",
+            mockito::Matcher::Regex("multipart/form-data".into()),
+        )
+        .match_body(mockito::Matcher::Regex(r#""custom_id":"q2""#.into()))
+        .with_status(200)
+        .with_body(r#"{"id":"file-in","object":"file"}"#)
+        .create_async()
+        .await;
+    let create = server
+        .mock("POST", "/batches")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"input_file_id":"file-in","endpoint":"/v1/chat/completions","completion_window":"24h"}"#
+                .into(),
+        ))
+        .with_status(200)
+        .with_body(r#"{"id":"batch_1","status":"validating","request_counts":{"total":3,"completed":0,"failed":0}}"#)
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/batches/batch_1")
+        .with_status(200)
+        .with_body(
+            r#"{"id":"batch_1","status":"completed","output_file_id":"file-out","error_file_id":"file-err",
+                "request_counts":{"total":3,"completed":1,"failed":1}}"#,
+        )
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/files/file-out/content")
+        .with_status(200)
+        .with_body(concat!(
+            r#"{"custom_id":"q1","response":{"status_code":200,"body":{"choices":[{"message":{"role":"assistant","content":"4"},"finish_reason":"stop"}],"usage":{"prompt_tokens":5,"completion_tokens":1}}},"error":null}"#,
+            "\n"
+        ))
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/files/file-err/content")
+        .with_status(200)
+        .with_body(concat!(
+            r#"{"custom_id":"q2","response":{"status_code":400,"body":{"error":{"code":"invalid_request","message":"bad input"}}},"error":null}"#,
+            "\n",
+            r#"{"response":{"status_"#,
+            "\n"
+        ))
+        .create_async()
+        .await;
+
+    let client = BatchClient::from_manifest(
+        &manifest("openai"),
+        "gpt-4o-mini",
+        Some(&server.url()),
+        Some("sk-test"),
+    )
+    .unwrap();
+    let results = client.run(&items(), &fast_poll()).await.unwrap();
+
+    upload.assert_async().await;
+    create.assert_async().await;
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].custom_id, "q1");
+    let ok = results[0].outcome.as_ref().unwrap();
+    assert_eq!(ok.content, "4");
+    assert!(ok.usage.is_some());
+
+    let err = results[1].outcome.as_ref().unwrap_err();
+    assert_eq!(err.kind, BatchItemErrorKind::Errored);
+    assert_eq!(err.status, Some(400));
+    assert_eq!(err.message, "bad input");
+
+    // q3 never appeared in either file: reported as an error, not silently dropped.
+    assert!(results[2].outcome.is_err());
+
+    // A truncated line is an item error; the rest of the file still decodes.
+    let job = client.get("batch_1").await.unwrap();
+    let raw = client.results(&job).await.unwrap();
+    assert_eq!(raw.len(), 3);
+    let err = raw[2].outcome.as_ref().unwrap_err();
+    assert_eq!(err.kind, BatchItemErrorKind::Errored);
+    assert!(err.message.contains("malformed"), "{}", err.message);
+}
+
+#[tokio::test]
+async fn test_anthropic_batch_submit_cancel_and_results() {
+    let mut server = mockito::Server::new_async().await;
+    let create = server
+        .mock("POST", "/batches")
+        .match_header("anthropic-version", "2023-06-01")
+        .match_body(mockito::Matcher::Regex(
+            r#""custom_id":"q1","params":\{"#.into(),
+        ))
+        .with_status(200)
+        .with_body(r#"{"id":"msgbatch_1","processing_status":"in_progress","request_counts":{"processing":3}}"#)
+        .create_async()
+        .await;
+    let cancel = server
+        .mock("POST", "/batches/msgbatch_1/cancel")
+        .with_status(200)
+        .with_body(r#"{"id":"msgbatch_1","processing_status":"canceling","cancel_initiated_at":"2026-01-01T00:00:00Z"}"#)
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/batches/msgbatch_1/results")
+        .with_status(200)
+        .with_body(concat!(
+            r#"{"custom_id":"q1","result":{"type":"succeeded","message":{"content":[{"type":"text","text":"4"}],"stop_reason":"end_turn","usage":{"input_tokens":4,"output_tokens":1}}}}"#,
+            "\n",
+            r#"{"custom_id":"q2","result":{"type":"canceled"}}"#,
+            "\n",
+            r#"{"custom_id":"q3","result":{"type":"expired"}}"#,
+            "\n"
+        ))
+        .create_async()
+        .await;
+
+    let client = BatchClient::from_manifest(
+        &manifest("anthropic"),
+        "claude-3-5-haiku",
+        Some(&server.url()),
+        Some("sk-ant-test"),
+    )
+    .unwrap();
+    let job = client.submit(&items()).await.unwrap();
+    assert_eq!(job.status, BatchStatus::InProgress);
+    assert_eq!(job.counts.total, 3);
+
+    let job = client.cancel(&job.id).await.unwrap();
+    assert_eq!(job.status, BatchStatus::Cancelling);
+
+    let results = client.results(&job).await.unwrap();
+    create.assert_async().await;
+    cancel.assert_async().await;
+    assert_eq!(results[0].outcome.as_ref().unwrap().content, "4");
+    assert_eq!(
+        results[1].outcome.as_ref().unwrap_err().kind,
+        BatchItemErrorKind::Cancelled
+    );
+    assert_eq!(
+        results[2].outcome.as_ref().unwrap_err().kind,
+        BatchItemErrorKind::Expired
+    );
+}
+
+#[tokio::test]
+async fn test_wait_times_out_on_stuck_job() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/batches/msgbatch_slow")
+        .with_status(200)
+        .with_body(r#"{"id":"msgbatch_slow","processing_status":"in_progress"}"#)
+        .expect_at_least(1)
+        .create_async()
+        .await;
+
+    let client = BatchClient::from_manifest(
+        &manifest("anthropic"),
+        "claude-3-5-haiku",
+        Some(&server.url()),
+        Some("sk-ant-test"),
+    )
+    .unwrap();
+    let poll = BatchPollConfig {
+        timeout: Some(Duration::from_millis(50)),
+        ..fast_poll()
+    };
+    assert!(client.wait("msgbatch_slow", &poll).await.is_err());
+}
+
+#[test]
+fn test_missing_batches_endpoint_is_protocol_error() {
+    let mut m = manifest("openai");
+    m.endpoints.as_mut().unwrap().remove("batches");
+    let err = BatchClient::from_manifest(&m, "gpt-4o-mini", None, Some("k"))
+        .err()
+        .expect("must fail without endpoints.batches");
+    assert!(err.to_string().contains("batches"));
+}
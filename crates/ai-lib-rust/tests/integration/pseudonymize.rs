@@ -0,0 +1,254 @@
+//! Integration tests for PII pseudonymization as a client message guard
+
+use ai_lib_rust::client::AiClientBuilder;
+use ai_lib_rust::error_code::StandardErrorCode;
+use ai_lib_rust::guardrails::Pseudonymizer;
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use ai_lib_rust::types::events::StreamingEvent;
+use ai_lib_rust::Message;
+use futures::StreamExt;
+use std::sync::Arc;
+
+fn guarded(fake: &FakeProvider) -> AiClientBuilder {
+    fake.client_builder()
+        .message_guard(Arc::new(Pseudonymizer::new().with_nonce("ab12")))
+}
+
+#[tokio::test]
+async fn test_pii_is_tokenized_on_the_wire_and_restored_in_the_response() {
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model(
+        "primary",
+        FakeResponse::error(StandardErrorCode::ServerError, "down"),
+    );
+    fake.push_for_model("backup", FakeResponse::text("I wrote to <EMAIL_1_ab12>."));
+    let client = guarded(&fake)
+        .with_fallbacks(vec!["fake/backup".to_string()])
+        .build("fake/primary")
+        .await
+        .expect("client");
+
+    let resp = client
+        .chat()
+        .messages(vec![Message::user("Email jane@example.com the report")])
+        .execute()
+        .await
+        .expect("chat");
+
+    // Both candidates saw the same tokenized text; the original never left.
+    for request in fake.requests() {
+        assert_eq!(
+            request.last_user_text().as_deref(),
+            Some("Email <EMAIL_1_ab12> the report")
+        );
+    }
+    assert_eq!(fake.request_count(), 2);
+    assert_eq!(resp.content, "I wrote to jane@example.com.");
+}
+
+#[tokio::test]
+async fn test_streamed_tokens_split_across_deltas_are_restored() {
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::events(vec![
+        StreamingEvent::PartialContentDelta {
+            content: "Sent to <EMA".into(),
+            sequence_id: None,
+        },
+        StreamingEvent::PartialContentDelta {
+            content: "IL_1_ab12>.".into(),
+            sequence_id: None,
+        },
+        StreamingEvent::StreamEnd {
+            finish_reason: Some("stop".into()),
+        },
+    ]));
+    let client = guarded(&fake).build("fake/model").await.expect("client");
+
+    let mut stream = client
+        .chat()
+        .messages(vec![Message::user("Forward it to jane@example.com")])
+        .stream()
+        .execute_stream()
+        .await
+        .expect("stream");
+    let mut text = String::new();
+    while let Some(event) = stream.next().await {
+        if let StreamingEvent::PartialContentDelta { content, .. } = event.expect("event") {
+            text.push_str(&content);
+        }
+    }
+
+    assert_eq!(
+        fake.last_request().unwrap().last_user_text().as_deref(),
+        Some("Forward it to <EMAIL_1_ab12>")
+    );
+    assert_eq!(text, "Sent to jane@example.com.");
+}
+
+#[tokio::test]
+async fn test_document_text_extracted_by_degradation_is_tokenized() {
+    use ai_lib_rust::types::message::{ContentBlock, MessageContent, MessageRole};
+    use base64::Engine;
+
+    let fake = FakeProvider::new().with_max_retries(0);
+    let mut weak = fake.manifest().clone();
+    weak.id = "weak".to_string();
+    weak.capabilities.vision = false;
+    weak.capabilities.multimodal = false;
+    fake.push_for_model(
+        "primary",
+        FakeResponse::error(StandardErrorCode::ServerError, "down"),
+    );
+    fake.push_for_model("tiny", FakeResponse::text("Ask <EMAIL_1_ab12>."));
+    let client = guarded(&fake)
+        .protocol_manifest(weak)
+        .with_fallbacks(vec!["weak/tiny".to_string()])
+        .degrade_requests(true)
+        .build("fake/primary")
+        .await
+        .expect("client");
+
+    let notes = base64::engine::general_purpose::STANDARD.encode("Owner: jane@example.com");
+    let resp = client
+        .chat()
+        .messages(vec![Message::with_content(
+            MessageRole::User,
+            MessageContent::blocks(vec![
+                ContentBlock::text("Who owns this?"),
+                ContentBlock::document_base64(
+                    notes,
+                    Some("text/plain".into()),
+                    Some("notes.txt".into()),
+                ),
+            ]),
+        )])
+        .execute()
+        .await
+        .expect("chat");
+
+    let requests = fake.requests();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        assert!(!request.body.to_string().contains("jane@example.com"));
+    }
+    // The fallback cannot read documents and gets the extracted text, tokenized.
+    let degraded = requests[1].messages()[0].to_string();
+    assert!(degraded.contains("[Document: notes.txt]"), "{}", degraded);
+    assert!(degraded.contains("Owner: <EMAIL_1_ab12>"), "{}", degraded);
+    assert_eq!(resp.content, "Ask jane@example.com.");
+}
+
+/// Anthropic-style batch manifest for `batch-test/<model>`.
+#[cfg(feature = "batch")]
+fn batch_manifest() -> ai_lib_rust::protocol::ProtocolManifest {
+    serde_yaml::from_str(
+        r#"
+id: batch-test
+protocol_version: "2.0"
+status: stable
+category: ai_provider
+official_url: "https://example.com"
+support_contact: "https://example.com"
+endpoint:
+  base_url: "https://api.example.com/v1"
+capabilities:
+  streaming: true
+  tools: false
+  vision: false
+endpoints:
+  chat:
+    path: "/messages"
+  batches:
+    path: "/batches"
+    adapter: anthropic
+streaming:
+  decoder:
+    format: "sse"
+"#,
+    )
+    .expect("manifest")
+}
+
+#[cfg(feature = "batch")]
+#[tokio::test]
+async fn test_batch_items_are_tokenized_and_results_restored() {
+    use ai_lib_rust::batch::{BatchClient, BatchRequestItem};
+    use ai_lib_rust::ChatBatchRequest;
+    use std::sync::Mutex;
+
+    let mut server = mockito::Server::new_async().await;
+    let sent = Arc::new(Mutex::new(Vec::<String>::new()));
+    let captured = sent.clone();
+    let create = server
+        .mock("POST", "/batches")
+        .with_status(200)
+        .with_body_from_request(move |request| {
+            let mut sent = captured.lock().unwrap();
+            sent.push(String::from_utf8_lossy(request.body().unwrap()).into());
+            format!(
+                r#"{{"id":"msgbatch_{}","processing_status":"ended","request_counts":{{"succeeded":1}}}}"#,
+                sent.len()
+            )
+            .into_bytes()
+        })
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = AiClientBuilder::new()
+        .protocol_manifest(batch_manifest())
+        .base_url_override(server.url())
+        .api_key("sk-ant-test")
+        .message_guard(Arc::new(Pseudonymizer::new()))
+        .build("batch-test/claude-3-5-haiku")
+        .await
+        .expect("client");
+    let batch = BatchClient::from_client(&client).expect("batch client");
+    // Both batches reuse the custom id "q1".
+    let mut jobs = Vec::new();
+    for email in ["jane@example.com", "joe@example.com"] {
+        let items = vec![BatchRequestItem::new(
+            "q1",
+            ChatBatchRequest::new(vec![Message::user(format!("Email {email} the report"))]),
+        )];
+        jobs.push(batch.submit(&items).await.expect("submit"));
+    }
+    create.assert_async().await;
+
+    for (n, (job, email)) in jobs
+        .iter()
+        .zip(["jane@example.com", "joe@example.com"])
+        .enumerate()
+    {
+        let body = sent.lock().unwrap()[n].clone();
+        assert!(!body.contains(email), "{}", body);
+        let start = body.find("<EMAIL").expect("token sent");
+        let token = &body[start..start + body[start..].find('>').unwrap() + 1];
+        server
+            .mock("GET", format!("/batches/{}/results", job.id).as_str())
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"custom_id":"q1","result":{{"type":"succeeded","message":{{"content":[{{"type":"text","text":"I wrote to {}."}}],"stop_reason":"end_turn"}}}}}}"#,
+                token
+            ))
+            .create_async()
+            .await;
+    }
+
+    let results = batch.results(&jobs[1]).await.expect("results");
+    assert_eq!(
+        results[0].outcome.as_ref().unwrap().content,
+        "I wrote to joe@example.com."
+    );
+    let results = batch.results(&jobs[0]).await.expect("results");
+    assert_eq!(
+        results[0].outcome.as_ref().unwrap().content,
+        "I wrote to jane@example.com."
+    );
+
+    // Restore state is gone once collected, and never shared with another client:
+    // tokenized text is not handed out as if it were restored.
+    assert!(batch.results(&jobs[0]).await.is_err());
+    let other = BatchClient::from_client(&client).expect("batch client");
+    assert!(other.results(&jobs[1]).await.is_err());
+}
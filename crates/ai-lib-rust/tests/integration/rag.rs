@@ -0,0 +1,222 @@
+//! Integration tests for the RAG pipeline: chunk → embed → retrieve → rerank → assemble → answer
+
+use ai_lib_rust::context::ContextBudget;
+use ai_lib_rust::embeddings::{EmbeddingClient, MetadataFilter, SimilarityMetric, VectorStore};
+use ai_lib_rust::rag::{MarkdownChunker, RagConfig, RagPipeline, RagQuery, SourceDocument};
+use ai_lib_rust::rerank::RerankerClient;
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use serde_json::{json, Value};
+
+const VOCABULARY: [&str; 4] = ["ship", "return", "warranty", "international"];
+
+/// Embedding endpoint that returns keyword-count vectors for whatever it is sent.
+async fn embeddings_mock(server: &mut mockito::Server) -> mockito::Mock {
+    server
+        .mock("POST", "/embeddings")
+        .with_body_from_request(|request| {
+            let body: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            let inputs: Vec<String> = match &body["input"] {
+                Value::String(s) => vec![s.clone()],
+                other => serde_json::from_value(other.clone()).unwrap(),
+            };
+            let data: Vec<Value> = inputs
+                .iter()
+                .enumerate()
+                .map(|(i, text)| {
+                    let text = text.to_lowercase();
+                    let vector: Vec<f32> = VOCABULARY
+                        .iter()
+                        .map(|w| text.matches(w).count() as f32 + 0.01)
+                        .collect();
+                    json!({ "index": i, "embedding": vector })
+                })
+                .collect();
+            json!({ "data": data, "usage": { "prompt_tokens": 5, "total_tokens": 5 } })
+                .to_string()
+                .into_bytes()
+        })
+        .create_async()
+        .await
+}
+
+async fn embedder(server: &mockito::Server) -> EmbeddingClient {
+    EmbeddingClient::builder()
+        .model("embed-test")
+        .api_key("sk-test")
+        .base_url(server.url())
+        .endpoint_path("/embeddings")
+        .build()
+        .await
+        .expect("embedder")
+}
+
+fn documents() -> Vec<SourceDocument> {
+    vec![
+        SourceDocument::new(
+            "shipping.md",
+            "# Shipping\nOrders ship within two days.\n\n## International\nInternational orders ship in two weeks.\n",
+        )
+        .with_metadata(json!({ "team": "logistics" })),
+        SourceDocument::new(
+            "returns.md",
+            "# Returns\nYou can return items within 30 days. Returns are free.\n",
+        )
+        .with_metadata(json!({ "team": "support" })),
+        SourceDocument::new(
+            "warranty.md",
+            "# Warranty\nThe warranty covers defects for one year.\n",
+        )
+        .with_metadata(json!({ "team": "support" })),
+    ]
+}
+
+#[tokio::test]
+async fn test_index_retrieve_and_answer_with_sources() {
+    let mut server = mockito::Server::new_async().await;
+    let _embeddings = embeddings_mock(&mut server).await;
+
+    let mut rag = RagPipeline::new(embedder(&server).await)
+        .with_chunker(MarkdownChunker::new(64))
+        .with_config(RagConfig::default().with_top_k(2));
+    let report = rag.index(&documents()).await.unwrap();
+    assert_eq!(report.documents, 3);
+    assert_eq!(report.chunks, 4);
+    assert_eq!(report.usage.prompt_tokens, 5);
+
+    let hits = rag
+        .retrieve("Do international orders ship?", None)
+        .await
+        .unwrap();
+    assert_eq!(hits[0].chunk_id, "shipping.md#1");
+    assert_eq!(hits[0].heading.as_deref(), Some("Shipping › International"));
+    assert_eq!(hits[0].metadata["team"], "logistics");
+
+    let fake = FakeProvider::new();
+    fake.push(FakeResponse::text("About two weeks [1]."));
+    let client = fake.client("model").await.expect("client");
+    let answer = rag
+        .answer(
+            &client,
+            &RagQuery::new("Do international orders ship?")
+                .with_system("You are a store assistant."),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(answer.content, "About two weeks [1].");
+    assert_eq!(answer.sources.len(), 2);
+    let cited: Vec<_> = answer
+        .cited_sources()
+        .map(|s| s.chunk_id.as_str())
+        .collect();
+    assert_eq!(cited, ["shipping.md#1"]);
+
+    let request = fake.last_request().unwrap();
+    let messages = request.messages();
+    assert!(messages[0]["content"]
+        .as_str()
+        .unwrap()
+        .starts_with("You are a store assistant."));
+    assert!(messages[1]["content"]
+        .as_str()
+        .unwrap()
+        .starts_with("[1] shipping.md — Shipping › International\n"));
+    assert_eq!(
+        request.last_user_text().as_deref(),
+        Some("Do international orders ship?")
+    );
+
+    // Re-indexing a source replaces its chunks; removing drops them.
+    rag.index(&[SourceDocument::new(
+        "shipping.md",
+        "Orders ship the same day.",
+    )])
+    .await
+    .unwrap();
+    assert_eq!(rag.store().len(), 3);
+    assert!(rag.remove("returns.md"));
+    assert_eq!(rag.store().len(), 2);
+}
+
+#[tokio::test]
+async fn test_euclidean_store_ranks_nearest_first() {
+    let mut server = mockito::Server::new_async().await;
+    let _embeddings = embeddings_mock(&mut server).await;
+
+    let mut rag = RagPipeline::new(embedder(&server).await)
+        .with_chunker(MarkdownChunker::new(64))
+        .with_store(VectorStore::with_metric(SimilarityMetric::Euclidean));
+    rag.index(&documents()).await.unwrap();
+
+    let hits = rag.retrieve("Is there a warranty?", None).await.unwrap();
+    assert_eq!(hits[0].chunk_id, "warranty.md#0");
+    assert!(hits.windows(2).all(|w| w[0].score >= w[1].score));
+    // Raw distances grow down the list while scores shrink.
+    assert!(hits[0].vector_score.unwrap() < hits[hits.len() - 1].vector_score.unwrap());
+}
+
+#[tokio::test]
+async fn test_hybrid_rerank_filter_and_budget() {
+    let mut server = mockito::Server::new_async().await;
+    let _embeddings = embeddings_mock(&mut server).await;
+    // Reranker prefers the candidate it was sent last.
+    let rerank = server
+        .mock("POST", "/rerank")
+        .with_body_from_request(|request| {
+            let body: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            let n = body["documents"].as_array().unwrap().len();
+            let results: Vec<Value> = (0..n)
+                .rev()
+                .map(|i| json!({ "index": i, "relevance_score": (i + 1) as f32 / n as f32 }))
+                .collect();
+            json!({ "results": results }).to_string().into_bytes()
+        })
+        .create_async()
+        .await;
+    let reranker = RerankerClient::builder()
+        .model("rerank-test")
+        .api_key("sk-test")
+        .base_url(server.url())
+        .endpoint_path("/rerank")
+        .build()
+        .await
+        .unwrap();
+
+    let mut rag = RagPipeline::new(embedder(&server).await)
+        .with_chunker(MarkdownChunker::new(64))
+        .with_reranker(reranker)
+        .with_config(RagConfig::default().with_hybrid(0.5).with_top_k(3));
+    rag.index(&documents()).await.unwrap();
+
+    let support = MetadataFilter::eq("team", "support");
+    let hits = rag
+        .retrieve("returns policy", Some(&support))
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits.iter().all(|h| h.metadata["team"] == "support"));
+    assert!(hits.iter().all(|h| h.rerank_score.is_some()));
+    assert!(hits.iter().any(|h| h.keyword_score.is_some()));
+    assert!(hits[0].score >= hits[1].score);
+    rerank.assert_async().await;
+
+    // A tight budget keeps the question and drops the weakest passages first.
+    let rag = rag.with_config(
+        RagConfig::default()
+            .with_top_k(4)
+            .with_budget(ContextBudget::new(60, 0, 0))
+            .with_citation_instruction(None),
+    );
+    let context = rag
+        .build_context(&RagQuery::new("Do international orders ship?"))
+        .await
+        .unwrap();
+    assert!(context.omitted_chunks > 0);
+    assert!(!context.sources.is_empty());
+    assert_eq!(context.sources[0].marker, 1);
+    assert_eq!(
+        context.messages.len(),
+        context.sources.len() + 1,
+        "passages + question"
+    );
+}
@@ -0,0 +1,237 @@
+//! Integration tests for realtime sessions against a local WebSocket server
+
+use std::sync::{Arc, Mutex};
+
+use ai_lib_rust::realtime::{
+    RealtimeConfig, RealtimeDialect, RealtimeEvent, RealtimeSession, TranscriptRole,
+};
+use ai_lib_rust::types::tool::{FunctionDefinition, ToolDefinition};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+type ServerSocket = WebSocketStream<tokio::net::TcpStream>;
+
+/// Accept one connection, recording the request URI and `Authorization` header.
+// The handshake callback's error type is fixed by tungstenite.
+#[allow(clippy::result_large_err)]
+async fn serve<F, Fut>(handler: F) -> (String, Arc<Mutex<Vec<String>>>)
+where
+    F: FnOnce(ServerSocket) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let record = seen.clone();
+    tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let socket = tokio_tungstenite::accept_hdr_async(tcp, |req: &Request, res: Response| {
+            let mut seen = record.lock().unwrap();
+            seen.push(req.uri().to_string());
+            if let Some(auth) = req.headers().get("authorization") {
+                seen.push(auth.to_str().unwrap().to_string());
+            }
+            Ok(res)
+        })
+        .await
+        .unwrap();
+        handler(socket).await;
+    });
+    (format!("ws://{addr}"), seen)
+}
+
+async fn recv(socket: &mut ServerSocket) -> Value {
+    loop {
+        match socket.next().await.unwrap().unwrap() {
+            Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+            Message::Binary(bytes) => return serde_json::from_slice(&bytes).unwrap(),
+            _ => continue,
+        }
+    }
+}
+
+async fn send(socket: &mut ServerSocket, value: Value) {
+    socket.send(Message::Text(value.to_string())).await.unwrap();
+}
+
+fn clock_tool() -> ToolDefinition {
+    ToolDefinition {
+        tool_type: "function".into(),
+        function: FunctionDefinition {
+            name: "get_time".into(),
+            description: Some("Current time".into()),
+            parameters: Some(json!({ "type": "object" })),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_openai_audio_turn_with_tool_call() {
+    let (url, seen) = serve(|mut ws| async move {
+        let setup = recv(&mut ws).await;
+        assert_eq!(setup["type"], "session.update");
+        assert_eq!(setup["session"]["voice"], "alloy");
+        assert_eq!(setup["session"]["tools"][0]["name"], "get_time");
+        send(
+            &mut ws,
+            json!({ "type": "session.created", "session": { "id": "s1" } }),
+        )
+        .await;
+
+        let audio = recv(&mut ws).await;
+        assert_eq!(audio["type"], "input_audio_buffer.append");
+        assert_eq!(audio["audio"], "AQIDBA==");
+        assert_eq!(recv(&mut ws).await["type"], "input_audio_buffer.commit");
+        assert_eq!(recv(&mut ws).await["type"], "response.create");
+
+        send(&mut ws, json!({ "type": "input_audio_buffer.committed" })).await;
+        send(
+            &mut ws,
+            json!({ "type": "response.audio.delta", "delta": "CQg=" }),
+        )
+        .await;
+        send(
+            &mut ws,
+            json!({ "type": "response.audio_transcript.delta", "delta": "Let me" }),
+        )
+        .await;
+        send(
+            &mut ws,
+            json!({
+                "type": "response.function_call_arguments.done",
+                "call_id": "call_1", "name": "get_time", "arguments": "{\"tz\":\"UTC\"}"
+            }),
+        )
+        .await;
+
+        let output = recv(&mut ws).await;
+        assert_eq!(output["item"]["type"], "function_call_output");
+        assert_eq!(output["item"]["call_id"], "call_1");
+        assert_eq!(output["item"]["output"], "{\"time\":\"12:00\"}");
+        assert_eq!(recv(&mut ws).await["type"], "response.create");
+
+        send(
+            &mut ws,
+            json!({ "type": "response.done", "response": { "usage": { "total_tokens": 42 } } }),
+        )
+        .await;
+        ws.close(None).await.unwrap();
+    })
+    .await;
+
+    let mut session = RealtimeSession::builder()
+        .url(format!("{url}/v1/realtime?model=rt-model"))
+        .api_key("sk-test")
+        .config(
+            RealtimeConfig::new("rt-model")
+                .with_voice("alloy")
+                .with_tools(vec![clock_tool()]),
+        )
+        .connect()
+        .await
+        .expect("connect");
+
+    assert!(matches!(
+        session.next_event().await.unwrap().unwrap(),
+        RealtimeEvent::SessionReady { session } if session["id"] == "s1"
+    ));
+    session.send_audio(&[1, 2, 3, 4]).await.unwrap();
+    session.commit_audio().await.unwrap();
+    session.create_response().await.unwrap();
+
+    let mut events = Vec::new();
+    while let Some(event) = session.next_event().await {
+        let event = event.unwrap();
+        if let RealtimeEvent::ToolCall {
+            call_id, arguments, ..
+        } = &event
+        {
+            assert_eq!(arguments, &json!({ "tz": "UTC" }));
+            session
+                .send_tool_result(call_id, "get_time", json!({ "time": "12:00" }))
+                .await
+                .unwrap();
+        }
+        events.push(event);
+    }
+
+    assert_eq!(events[0], RealtimeEvent::InputCommitted);
+    assert_eq!(events[1], RealtimeEvent::AudioDelta { audio: vec![9, 8] });
+    assert_eq!(
+        events[2],
+        RealtimeEvent::TranscriptDelta {
+            role: TranscriptRole::Assistant,
+            text: "Let me".into()
+        }
+    );
+    assert!(matches!(events[3], RealtimeEvent::ToolCall { .. }));
+    assert_eq!(
+        events[4],
+        RealtimeEvent::ResponseDone {
+            usage: Some(json!({ "total_tokens": 42 }))
+        }
+    );
+    assert_eq!(
+        *seen.lock().unwrap(),
+        ["/v1/realtime?model=rt-model", "Bearer sk-test"]
+    );
+}
+
+#[tokio::test]
+async fn test_gemini_split_session_text_turn() {
+    let (url, seen) = serve(|mut ws| async move {
+        let setup = recv(&mut ws).await;
+        assert_eq!(setup["setup"]["model"], "models/live-model");
+        ws.send(Message::Binary(
+            json!({ "setupComplete": {} }).to_string().into_bytes(),
+        ))
+        .await
+        .unwrap();
+
+        let turn = recv(&mut ws).await;
+        assert_eq!(turn["clientContent"]["turns"][0]["parts"][0]["text"], "Hi");
+        assert_eq!(turn["clientContent"]["turnComplete"], true);
+        send(
+            &mut ws,
+            json!({
+                "serverContent": {
+                    "modelTurn": { "parts": [{ "text": "Hello" }] },
+                    "turnComplete": true
+                }
+            }),
+        )
+        .await;
+        ws.close(None).await.unwrap();
+    })
+    .await;
+
+    let session = RealtimeSession::builder()
+        .url(format!("{url}/ws/live"))
+        .api_key("g-key")
+        .dialect(RealtimeDialect::Gemini)
+        .config(RealtimeConfig::new("live-model").with_modalities(["text"]))
+        .connect()
+        .await
+        .expect("connect");
+    let (sender, events) = session.split();
+
+    let events = tokio::spawn(events.map(Result::unwrap).collect::<Vec<_>>());
+    sender.send_text("Hi").await.unwrap();
+    let events = events.await.unwrap();
+
+    assert!(matches!(events[0], RealtimeEvent::SessionReady { .. }));
+    assert_eq!(
+        events[1..],
+        [
+            RealtimeEvent::TextDelta {
+                text: "Hello".into()
+            },
+            RealtimeEvent::ResponseDone { usage: None },
+        ]
+    );
+    assert_eq!(*seen.lock().unwrap(), ["/ws/live?key=g-key"]);
+}
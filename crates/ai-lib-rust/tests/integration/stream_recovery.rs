@@ -0,0 +1,207 @@
+//! Integration tests for resuming streams that fail after emitting output
+
+use ai_lib_rust::client::RecoveryPolicy;
+use ai_lib_rust::testing::{FakeProvider, FakeResponse};
+use ai_lib_rust::types::events::StreamingEvent;
+use ai_lib_rust::{AiClientBuilder, Message};
+use futures::StreamExt;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+fn cut_off(text: &str) -> FakeResponse {
+    FakeResponse::events(vec![
+        StreamingEvent::PartialContentDelta {
+            content: text.into(),
+            sequence_id: None,
+        },
+        StreamingEvent::StreamError {
+            error: json!("connection reset"),
+            event_id: None,
+        },
+    ])
+}
+
+/// Concatenated text plus every `StreamRecovered` event.
+async fn drain(client: &ai_lib_rust::AiClient) -> (String, Vec<StreamingEvent>) {
+    let mut stream = client
+        .chat()
+        .messages(vec![Message::user("write a haiku")])
+        .stream()
+        .execute_stream()
+        .await
+        .expect("stream");
+    let mut content = String::new();
+    let mut recovered = Vec::new();
+    while let Some(ev) = stream.next().await {
+        match ev.expect("event") {
+            StreamingEvent::PartialContentDelta { content: c, .. } => content.push_str(&c),
+            ev @ StreamingEvent::StreamRecovered { .. } => recovered.push(ev),
+            _ => {}
+        }
+    }
+    (content, recovered)
+}
+
+#[tokio::test]
+async fn test_same_model_continues_with_instruction() {
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model("primary", cut_off("Autumn moonlight "));
+    fake.push_for_model("primary", FakeResponse::text(" a worm digs silently"));
+    let client = fake
+        .client_builder()
+        .stream_recovery(RecoveryPolicy::new())
+        .build("fake/primary")
+        .await
+        .expect("client");
+
+    let (content, recovered) = drain(&client).await;
+    assert_eq!(content, "Autumn moonlight a worm digs silently");
+    match &recovered[..] {
+        [StreamingEvent::StreamRecovered {
+            model,
+            mode,
+            retained_chars,
+            attempt,
+            ..
+        }] => {
+            assert_eq!(model, "fake/primary");
+            assert_eq!(mode, "continue");
+            assert_eq!(*retained_chars, "Autumn moonlight".len());
+            assert_eq!(*attempt, 1);
+        }
+        other => panic!("expected one recovery, got {:?}", other),
+    }
+
+    let resumed = fake.last_request().unwrap();
+    let messages = resumed.messages();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[1]["role"], "assistant");
+    assert_eq!(messages[1]["content"], "Autumn moonlight");
+    assert_eq!(messages[2]["role"], "user");
+}
+
+#[tokio::test]
+async fn test_fallback_continues_from_prefill() {
+    let fake = FakeProvider::new().with_max_retries(0).with_manifest(|m| {
+        m.capabilities.assistant_prefill = true;
+    });
+    fake.push_for_model("primary", cut_off("Autumn moonlight"));
+    fake.push_for_model("backup", FakeResponse::text(" - a worm digs silently"));
+    let client = fake
+        .client_builder()
+        .with_fallbacks(vec!["fake/backup".to_string()])
+        .stream_recovery(RecoveryPolicy::new().with_same_model_first(false))
+        .build("fake/primary")
+        .await
+        .expect("client");
+
+    let (content, recovered) = drain(&client).await;
+    assert_eq!(content, "Autumn moonlight - a worm digs silently");
+    assert!(matches!(
+        &recovered[..],
+        [StreamingEvent::StreamRecovered { model, mode, .. }]
+            if model == "fake/backup" && mode == "prefill"
+    ));
+
+    let requests = fake.requests();
+    assert_eq!(
+        requests
+            .iter()
+            .map(|r| r.model.clone().unwrap_or_default())
+            .collect::<Vec<_>>(),
+        ["primary", "backup"]
+    );
+    let messages = requests[1].messages();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[1]["role"], "assistant");
+}
+
+#[tokio::test]
+async fn test_failure_is_surfaced_once_recoveries_are_spent() {
+    let fake = FakeProvider::new().with_max_retries(0);
+    fake.push_for_model("primary", cut_off("one "));
+    fake.push_for_model("primary", cut_off("two"));
+    let client = fake
+        .client_builder()
+        .stream_recovery(RecoveryPolicy::new().with_max_recoveries(1))
+        .build("fake/primary")
+        .await
+        .expect("client");
+
+    let mut stream = client
+        .chat()
+        .messages(vec![Message::user("count")])
+        .stream()
+        .execute_stream()
+        .await
+        .expect("stream");
+    let mut content = String::new();
+    let mut failed = false;
+    while let Some(ev) = stream.next().await {
+        match ev {
+            Ok(StreamingEvent::PartialContentDelta { content: c, .. }) => content.push_str(&c),
+            Err(_) => failed = true,
+            _ => {}
+        }
+    }
+    assert_eq!(content, "one two");
+    assert!(failed);
+    assert_eq!(fake.request_count(), 2);
+}
+
+#[tokio::test]
+async fn test_same_model_continuation_uses_base_url_override() {
+    // The manifest points at `default_host`; the client is built against `proxy`.
+    let mut default_host = mockito::Server::new_async().await;
+    let untouched = default_host
+        .mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_body("data: [DONE]\n\n")
+        .expect(0)
+        .create_async()
+        .await;
+    let mut proxy = mockito::Server::new_async().await;
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let proxied = proxy
+        .mock("POST", "/chat/completions")
+        .with_status(200)
+        .with_header("content-type", "text/event-stream")
+        .with_chunked_body(move |w| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                // The connection drops after the first delta.
+                w.write_all(
+                    b"data: {\"choices\":[{\"delta\":{\"content\":\"Autumn moonlight\"},\"index\":0}]}\n\n",
+                )?;
+                w.flush()?;
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                return Err(std::io::Error::other("connection reset"));
+            }
+            w.write_all(concat!(
+                "data: {\"choices\":[{\"delta\":{\"content\":\" a worm digs\"},\"index\":0}]}\n\n",
+                "data: [DONE]\n\n",
+            ).as_bytes())
+        })
+        .expect(2)
+        .create_async()
+        .await;
+
+    let mut manifest = FakeProvider::new().with_max_retries(0).manifest().clone();
+    manifest.endpoint.base_url = default_host.url();
+    let client = AiClientBuilder::new()
+        .protocol_manifest(manifest)
+        .base_url_override(proxy.url())
+        .stream_recovery(RecoveryPolicy::new())
+        .build("fake/primary")
+        .await
+        .expect("client");
+
+    let (content, recovered) = drain(&client).await;
+    assert_eq!(content, "Autumn moonlight a worm digs");
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    untouched.assert_async().await;
+    proxied.assert_async().await;
+}
@@ -0,0 +1,103 @@
+//! Integration tests for long-audio STT: silence splitting, concurrent chunks and merging
+
+use ai_lib_rust::stt::{PcmFormat, SttClient, SttOptions};
+use serde_json::json;
+
+async fn client(server: &mockito::Server, max_upload_bytes: usize) -> SttClient {
+    SttClient::builder()
+        .model("stt-test")
+        .api_key("sk-test")
+        .base_url(server.url())
+        .endpoint_path("/audio/transcriptions")
+        .max_upload_bytes(max_upload_bytes)
+        .chunk_overlap_secs(0.2)
+        .build()
+        .await
+        .expect("client")
+}
+
+/// 1 kHz mono 16-bit PCM: 3 s of tone, 0.5 s of near-silence, 3.5 s of tone.
+fn pcm() -> Vec<u8> {
+    let mut data = Vec::new();
+    for (loud, samples) in [(true, 3000), (false, 500), (true, 3500)] {
+        for i in 0..samples {
+            let v: i16 = match (loud, i % 2) {
+                (true, 0) => 12_000,
+                (true, _) => -12_000,
+                _ => 20,
+            };
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    data
+}
+
+/// Transcription endpoint describing the WAV chunk it was sent (by its data size).
+async fn chunk_mock(server: &mut mockito::Server, expected_requests: usize) -> mockito::Mock {
+    server
+        .mock("POST", "/audio/transcriptions")
+        .with_body_from_request(|request| {
+            let body = request.body().unwrap();
+            let riff = body.windows(4).position(|w| w == b"RIFF").unwrap();
+            let size = u32::from_le_bytes(body[riff + 40..riff + 44].try_into().unwrap());
+            json!({
+                "text": format!("chunk of {size} bytes"),
+                "segments": [{ "start": 1.0, "end": 1.5, "text": format!("segment {size}"), "speaker": "A" }],
+                "words": [{ "start": 1.0, "end": 1.2, "word": "chunk" }]
+            })
+            .to_string()
+            .into_bytes()
+        })
+        .expect(expected_requests)
+        .create_async()
+        .await
+}
+
+#[tokio::test]
+async fn test_long_wav_is_split_on_silence_and_merged() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = chunk_mock(&mut server, 2).await;
+    let format = PcmFormat::s16(1000, 1);
+
+    // 8000 bytes of samples per request: the cut falls in the 3.0-3.5 s silence.
+    let t = client(&server, 8044)
+        .await
+        .transcribe_long_pcm(&pcm(), format, &SttOptions::default())
+        .await
+        .unwrap();
+    mock.assert_async().await;
+
+    assert_eq!(t.text, "chunk of 6930 bytes chunk of 7470 bytes");
+    let segments = t.segments.as_ref().unwrap();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].start, 1.0);
+    assert!((segments[1].start - 4.265).abs() < 1e-3, "{segments:?}");
+    assert_eq!(t.words.as_ref().unwrap().len(), 2);
+    assert_eq!(t.duration, Some(7.0));
+    assert!(t
+        .to_srt()
+        .starts_with("1\n00:00:01,000 --> 00:00:01,500\nA: segment 6930\n\n2\n00:00:04,265"));
+}
+
+#[tokio::test]
+async fn test_small_wav_goes_out_in_one_request() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = chunk_mock(&mut server, 1).await;
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(36u32 + 14000).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&[1, 0, 1, 0, 0xe8, 0x03, 0, 0, 0xd0, 0x07, 0, 0, 2, 0, 16, 0]);
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&14000u32.to_le_bytes());
+    wav.extend_from_slice(&pcm());
+
+    let t = client(&server, 1 << 20)
+        .await
+        .transcribe_long(&wav, &SttOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(t.text, "chunk of 14000 bytes");
+    assert_eq!(t.segments.unwrap()[0].start, 1.0);
+    mock.assert_async().await;
+}
@@ -0,0 +1,36 @@
+//! Helpers shared by the integration tests
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Fixture protocol directory (`tests/fixtures/protocols`).
+pub fn protocols() -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("protocols")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// A path under the system temp directory that no other test (or earlier run) uses;
+/// `name` is kept as the suffix so extensions survive. Nothing is created.
+pub fn temp_path(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!(
+        "ai-lib-{}-{nanos}-{}-{name}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Write `contents` to a fresh [`temp_path`].
+pub fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = temp_path(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
@@ -0,0 +1,125 @@
+//! Integration tests for streaming and long-form TTS synthesis
+
+use ai_lib_rust::tts::{AudioFormat, TtsClient, TtsOptions};
+use futures::StreamExt;
+use mockito::Matcher;
+use serde_json::{json, Value};
+
+async fn client(server: &mockito::Server) -> TtsClient {
+    TtsClient::builder()
+        .model("tts-test")
+        .api_key("sk-test")
+        .base_url(server.url())
+        .endpoint_path("/audio/speech")
+        .max_input_chars(20)
+        .build()
+        .await
+        .expect("client")
+}
+
+fn options(format: &str) -> TtsOptions {
+    TtsOptions {
+        response_format: Some(format.into()),
+        ..Default::default()
+    }
+}
+
+/// 16-bit mono WAV whose samples are the bytes of `text`.
+fn wav(text: &str) -> Vec<u8> {
+    let samples = text.as_bytes();
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&((36 + samples.len()) as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&[1, 0, 1, 0, 0xc0, 0x5d, 0, 0, 0x80, 0xbb, 0, 0, 2, 0, 16, 0]);
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+    out.extend_from_slice(samples);
+    out
+}
+
+#[tokio::test]
+async fn test_stream_passes_raw_chunks_through() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/audio/speech")
+        .match_body(Matcher::PartialJson(
+            json!({ "input": "Hi", "response_format": "pcm" }),
+        ))
+        .with_header("content-type", "audio/pcm")
+        .with_chunked_body(|w| {
+            w.write_all(&[1, 2, 3])?;
+            w.write_all(&[4, 5])
+        })
+        .create_async()
+        .await;
+
+    let stream = client(&server)
+        .await
+        .synthesize_stream("Hi", &options("pcm"))
+        .await
+        .unwrap();
+    assert_eq!(stream.format(), AudioFormat::Pcm);
+    let audio = stream.collect().await.unwrap();
+    assert_eq!(audio.data, [1, 2, 3, 4, 5]);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_stream_decodes_sse_audio_deltas() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/audio/speech")
+        .match_body(Matcher::PartialJson(json!({ "stream_format": "sse" })))
+        .with_header("content-type", "text/event-stream")
+        .with_body(concat!(
+            "data: {\"type\":\"speech.audio.delta\",\"audio\":\"AQI=\"}\n\n",
+            "data: {\"type\":\"speech.audio.delta\",\"audio\":\"Aw==\"}\n\n",
+            "data: {\"type\":\"speech.audio.done\",\"usage\":{\"total_tokens\":3}}\n\n",
+        ))
+        .create_async()
+        .await;
+
+    let options = TtsOptions {
+        stream_format: Some("sse".into()),
+        ..options("pcm")
+    };
+    let mut stream = client(&server)
+        .await
+        .synthesize_stream("Hi", &options)
+        .await
+        .unwrap();
+    let mut chunks = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        chunks.push(chunk.unwrap().to_vec());
+    }
+    assert_eq!(chunks, [vec![1, 2], vec![3]]);
+}
+
+#[tokio::test]
+async fn test_long_text_is_split_synthesized_and_joined() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/audio/speech")
+        .with_body_from_request(|request| {
+            let body: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            wav(body["input"].as_str().unwrap())
+        })
+        .expect(3)
+        .create_async()
+        .await;
+
+    let audio = client(&server)
+        .await
+        .synthesize_long(
+            "The first sentence. A second sentence. And the third one.",
+            &options("wav"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(audio.format, AudioFormat::Wav);
+    let expected = wav("The first sentence.A second sentence.And the third one.");
+    assert_eq!(audio.data, expected);
+    mock.assert_async().await;
+}
@@ -38,7 +38,10 @@ pub(crate) fn capabilities_json() -> &'static [u8] {
                     "resolve_credential",
                     "snapshot_state",
                     "restore_state",
-                    "metrics"
+                    "metrics",
+                    "stream_open",
+                    "stream_feed",
+                    "stream_close"
                 ],
                 "memory": {
                     "ownership_transfer": true,
@@ -50,7 +53,8 @@ pub(crate) fn capabilities_json() -> &'static [u8] {
                     "structured_input": true,
                     "additive_ctx": true,
                     "state_migration": true,
-                    "host_supplied_credentials": true
+                    "host_supplied_credentials": true,
+                    "streaming": true
                 }
             });
             serde_json::to_vec(&v).expect("serialize static capabilities")
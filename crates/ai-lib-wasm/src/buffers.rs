@@ -2,9 +2,11 @@
 //!
 //! 中文：WASM 全局缓冲、指标与指针辅助。
 
+use std::fmt;
 use std::sync::Mutex;
 
 use ai_lib_core::drivers::{create_driver, ProviderDriver};
+use ai_lib_core::error_code::StandardErrorCode;
 use ai_lib_core::protocol::v2::capabilities::{CapabilitiesV2, Capability, LegacyCapabilities};
 use ai_lib_core::protocol::v2::manifest::{ApiStyle, ManifestV2};
 use ai_lib_core::protocol::{ProtocolManifest, UnifiedRequest};
@@ -52,6 +54,9 @@ pub(crate) static MANIFESTS: Mutex<Vec<Option<(ProtocolManifest, Vec<u8>)>>> =
     Mutex::new(Vec::new());
 pub(crate) static LAST_OUT: Mutex<Vec<u8>> = Mutex::new(Vec::new());
 pub(crate) static LAST_ERR: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+/// Standard code of `LAST_ERR`, reported by the component exports.
+pub(crate) static LAST_ERR_CODE: Mutex<StandardErrorCode> =
+    Mutex::new(StandardErrorCode::InvalidRequest);
 pub(crate) static METRICS: Mutex<WasmMetrics> = Mutex::new(WasmMetrics {
     total_calls: 0,
     total_errors: 0,
@@ -63,12 +68,77 @@ pub(crate) fn set_out(bytes: Vec<u8>) {
     *LAST_OUT.lock().unwrap_or_else(|e| e.into_inner()) = bytes;
 }
 
+/// Record a rejected argument (malformed input, unknown handle, ...).
 pub(crate) fn set_err(s: impl AsRef<str>) {
+    set_err_with_code(StandardErrorCode::InvalidRequest, s);
+}
+
+pub(crate) fn set_err_with_code(code: StandardErrorCode, s: impl AsRef<str>) {
     *LAST_ERR.lock().unwrap_or_else(|e| e.into_inner()) = s.as_ref().as_bytes().to_vec();
+    *LAST_ERR_CODE.lock().unwrap_or_else(|e| e.into_inner()) = code;
+}
+
+/// Record a library error under its standard code.
+pub(crate) fn set_core_err(e: &ai_lib_core::Error) {
+    set_err_with_code(standard_code_for(e), e.to_string());
 }
 
 pub(crate) fn clear_err() {
     LAST_ERR.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    *LAST_ERR_CODE.lock().unwrap_or_else(|e| e.into_inner()) = StandardErrorCode::InvalidRequest;
+}
+
+/// Standard code for a library error: the one it carries (remote and classified
+/// runtime errors), otherwise one derived from its kind.
+pub(crate) fn standard_code_for(e: &ai_lib_core::Error) -> StandardErrorCode {
+    use ai_lib_core::pipeline::PipelineError;
+    use ai_lib_core::protocol::ProtocolError;
+    use ai_lib_core::Error;
+
+    if let Some(code) = e.standard_code() {
+        return code;
+    }
+    match e {
+        Error::Validation { .. } => StandardErrorCode::InvalidRequest,
+        Error::Protocol(ProtocolError::NotFound { .. }) => StandardErrorCode::NotFound,
+        // Manifests and request bodies that do not satisfy the protocol.
+        Error::Protocol(_) => StandardErrorCode::InvalidRequest,
+        Error::Pipeline(PipelineError::Configuration(_)) => StandardErrorCode::Unknown,
+        // Provider output the driver or pipeline could not process.
+        Error::Pipeline(_) | Error::Serialization(_) => StandardErrorCode::ServerError,
+        _ => StandardErrorCode::Unknown,
+    }
+}
+
+/// Failure of an op that is also surfaced through the component exports.
+#[derive(Debug)]
+pub(crate) struct OpError {
+    pub(crate) code: StandardErrorCode,
+    pub(crate) message: String,
+}
+
+impl OpError {
+    pub(crate) fn invalid(message: impl Into<String>) -> Self {
+        Self {
+            code: StandardErrorCode::InvalidRequest,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<ai_lib_core::Error> for OpError {
+    fn from(e: ai_lib_core::Error) -> Self {
+        Self {
+            code: standard_code_for(&e),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for OpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
 }
 
 pub(crate) fn bump_calls() {
@@ -136,7 +206,7 @@ pub(crate) fn write_out_json(v: &serde_json::Value) -> i32 {
             0
         }
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             -1
         }
     }
@@ -0,0 +1,343 @@
+//! Component Model exports for `wit/world.wit` (`ai-lib:protocol`).
+//!
+//! 中文：基于 wit-bindgen 的组件模型导出；类型化 record 与现有 `ailib_invoke`
+//! JSON ABI 一一对应，宿主无需手写指针 / 长度胶水代码。
+//!
+//! Every function is a thin typed adapter over the same `ailib_invoke` ops as the
+//! C ABI (streams go straight to `stream_ops` so chunks stay raw bytes), which
+//! keeps both surfaces behaviourally identical, metrics and snapshots included.
+//!
+//! The component exports are only emitted on `wasm32` (their `ns:pkg/iface#fn`
+//! symbol names do not link into native cdylibs); natively the `Guest` impl is
+//! still compiled so the typed mapping is covered by `native_tests`.
+
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+use ai_lib_core::error_code::StandardErrorCode;
+use serde_json::{json, Value};
+
+use crate::buffers::{OpError, LAST_ERR, LAST_ERR_CODE, LAST_OUT};
+use crate::invoke::ailib_invoke;
+use crate::stream_ops::{close_stream, feed_stream};
+use crate::AILIB_ABI_VERSION;
+
+wit_bindgen::generate!({
+    world: "protocol",
+    path: "wit",
+});
+
+use ai_lib::protocol::types::{
+    ChatRequest, ChatResponse, Error, ErrorClass, ManifestInfo, Role, StreamMetadata, StreamOutput,
+    StreamSummary, StreamingEvent, ToolCall, ToolCallDelta, ToolCallStart, Usage,
+};
+use exports::ai_lib::protocol::runtime::Guest;
+
+pub(crate) struct Component;
+
+#[cfg(target_arch = "wasm32")]
+export!(Component);
+
+fn error(code: StandardErrorCode, message: impl Into<String>) -> Error {
+    Error {
+        code: code.code().to_string(),
+        message: message.into(),
+    }
+}
+
+/// A rejected argument; everything else keeps the code of its cause.
+fn invalid(message: impl Into<String>) -> Error {
+    error(StandardErrorCode::InvalidRequest, message)
+}
+
+fn op_error(op: &str, e: OpError) -> Error {
+    error(e.code, format!("{op}: {e}"))
+}
+
+/// Run one `ailib_invoke` op and take its output (or error) buffer.
+fn call(op: &str, input: &[u8], ctx: &Value) -> Result<Value, Error> {
+    let ctx = if ctx.is_null() {
+        String::new()
+    } else {
+        ctx.to_string()
+    };
+    let rc = unsafe {
+        ailib_invoke(
+            op.as_ptr(),
+            op.len(),
+            input.as_ptr(),
+            input.len(),
+            ctx.as_ptr(),
+            ctx.len(),
+        )
+    };
+    if rc != 0 {
+        let err = LAST_ERR
+            .lock()
+            .map(|mut g| String::from_utf8_lossy(&std::mem::take(&mut *g)).into_owned())
+            .unwrap_or_default();
+        let code = LAST_ERR_CODE
+            .lock()
+            .map(|g| *g)
+            .unwrap_or(StandardErrorCode::Unknown);
+        return Err(error(code, err));
+    }
+    let out = LAST_OUT
+        .lock()
+        .map(|mut g| std::mem::take(&mut *g))
+        .unwrap_or_default();
+    serde_json::from_slice(&out)
+        .map_err(|e| error(StandardErrorCode::Unknown, format!("{op} output: {e}")))
+}
+
+fn ctx_for(handle: u32) -> Value {
+    json!({ "version": AILIB_ABI_VERSION, "manifest_handle": handle })
+}
+
+fn opt_str(v: &Value) -> Option<String> {
+    v.as_str().map(str::to_string)
+}
+
+fn json_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Accepts both normalized (`prompt_tokens`) and Anthropic-style (`input_tokens`) usage.
+fn usage_from_json(v: &Value) -> Option<Usage> {
+    let get = |a: &str, b: &str| v.get(a).or_else(|| v.get(b)).and_then(Value::as_u64);
+    let prompt = get("prompt_tokens", "input_tokens");
+    let completion = get("completion_tokens", "output_tokens");
+    if prompt.is_none() && completion.is_none() {
+        return None;
+    }
+    let (prompt, completion) = (prompt.unwrap_or(0), completion.unwrap_or(0));
+    Some(Usage {
+        prompt_tokens: prompt,
+        completion_tokens: completion,
+        total_tokens: v
+            .get("total_tokens")
+            .and_then(Value::as_u64)
+            .unwrap_or(prompt + completion),
+    })
+}
+
+/// Driver tool calls keep the provider shape (OpenAI `function`, Anthropic
+/// `tool_use`, Gemini `functionCall`); assembled stream calls are `{id, name, arguments}`.
+fn tool_call_from_json(v: &Value) -> ToolCall {
+    let f = v
+        .get("function")
+        .or_else(|| v.get("functionCall"))
+        .unwrap_or(v);
+    let args = f
+        .get("arguments")
+        .or_else(|| f.get("args"))
+        .or_else(|| v.get("input"))
+        .unwrap_or(&Value::Null);
+    ToolCall {
+        id: v.get("id").map(json_text).unwrap_or_default(),
+        name: f
+            .get("name")
+            .or_else(|| v.get("name"))
+            .map(json_text)
+            .unwrap_or_default(),
+        arguments_json: json_text(args),
+    }
+}
+
+fn tool_calls_from_json(v: &Value) -> Vec<ToolCall> {
+    v.as_array()
+        .map(|a| a.iter().map(tool_call_from_json).collect())
+        .unwrap_or_default()
+}
+
+fn event_from_json(v: &Value) -> StreamingEvent {
+    let s = |k: &str| v.get(k).map(json_text).unwrap_or_default();
+    match v.get("event_type").and_then(Value::as_str).unwrap_or("") {
+        "PartialContentDelta" => StreamingEvent::ContentDelta(s("content")),
+        "ThinkingDelta" => StreamingEvent::ThinkingDelta(s("thinking")),
+        "ToolCallStarted" => StreamingEvent::ToolCallStarted(ToolCallStart {
+            id: s("tool_call_id"),
+            name: s("tool_name"),
+        }),
+        "PartialToolCall" => StreamingEvent::ToolCallDelta(ToolCallDelta {
+            id: s("tool_call_id"),
+            arguments: s("arguments"),
+        }),
+        "Metadata" => StreamingEvent::Metadata(StreamMetadata {
+            usage: v.get("usage").and_then(usage_from_json),
+            finish_reason: v.get("finish_reason").and_then(opt_str),
+        }),
+        "StreamEnd" => StreamingEvent::StreamEnd(v.get("finish_reason").and_then(opt_str)),
+        "StreamError" => StreamingEvent::StreamError(s("error")),
+        _ => StreamingEvent::Other(v.to_string()),
+    }
+}
+
+fn events_from_json(v: &Value) -> Vec<StreamingEvent> {
+    v.as_array()
+        .map(|a| a.iter().map(event_from_json).collect())
+        .unwrap_or_default()
+}
+
+fn request_to_json(request: ChatRequest) -> Result<Value, Error> {
+    let messages: Vec<Value> = request
+        .messages
+        .into_iter()
+        .map(|m| {
+            let role = match m.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::Tool => "tool",
+            };
+            let mut msg = json!({ "role": role, "content": m.content });
+            if let Some(id) = m.tool_call_id {
+                msg["tool_call_id"] = Value::String(id);
+            }
+            msg
+        })
+        .collect();
+    let tools = request
+        .tools
+        .into_iter()
+        .map(|t| {
+            let parameters = match t.parameters_json {
+                Some(p) => Some(
+                    serde_json::from_str::<Value>(&p)
+                        .map_err(|e| invalid(format!("tool '{}' parameters: {e}", t.name)))?,
+                ),
+                None => None,
+            };
+            Ok(json!({
+                "type": "function",
+                "function": { "name": t.name, "description": t.description, "parameters": parameters },
+            }))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(json!({
+        "model": request.model,
+        "messages": messages,
+        "temperature": request.temperature,
+        "max_tokens": request.max_tokens,
+        "stream": request.stream,
+        "tools": if tools.is_empty() { Value::Null } else { Value::Array(tools) },
+    }))
+}
+
+impl Guest for Component {
+    fn abi_version() -> u32 {
+        AILIB_ABI_VERSION
+    }
+
+    fn load_manifest(yaml: String) -> Result<ManifestInfo, Error> {
+        let out = call("load_manifest", yaml.as_bytes(), &Value::Null)?;
+        let handle = out["handle"].as_u64().unwrap_or(0) as u32;
+        let g = crate::buffers::MANIFESTS
+            .lock()
+            .map_err(|e| error(StandardErrorCode::Unknown, e.to_string()))?;
+        let (m, _) = handle
+            .checked_sub(1)
+            .and_then(|i| g.get(i as usize))
+            .and_then(|x| x.as_ref())
+            .ok_or_else(|| {
+                error(
+                    StandardErrorCode::Unknown,
+                    "load_manifest: no manifest stored",
+                )
+            })?;
+        Ok(ManifestInfo {
+            handle,
+            id: m.id.clone(),
+            protocol_version: m.protocol_version.clone(),
+        })
+    }
+
+    fn check_capability(handle: u32, name: String) -> Result<bool, Error> {
+        let input = json!({ "handle": handle, "name": name }).to_string();
+        let out = call("check_capability", input.as_bytes(), &Value::Null)?;
+        Ok(out["supported"].as_bool().unwrap_or(false))
+    }
+
+    fn build_chat_request(handle: u32, request: ChatRequest) -> Result<String, Error> {
+        let input = request_to_json(request)?.to_string();
+        call("build_request", input.as_bytes(), &ctx_for(handle)).map(|v| v.to_string())
+    }
+
+    fn parse_chat_response(handle: u32, body: String) -> Result<ChatResponse, Error> {
+        let out = call("parse_response", body.as_bytes(), &ctx_for(handle))?;
+        Ok(ChatResponse {
+            content: out.get("content").and_then(opt_str),
+            finish_reason: out.get("finish_reason").and_then(opt_str),
+            usage: out.get("usage").and_then(usage_from_json),
+            tool_calls: tool_calls_from_json(&out["tool_calls"]),
+        })
+    }
+
+    fn classify_error(status: u16, body: Option<String>) -> ErrorClass {
+        let ctx = json!({ "version": AILIB_ABI_VERSION, "status_code": status });
+        let code = call("classify_error", body.unwrap_or_default().as_bytes(), &ctx)
+            .ok()
+            .and_then(|v| {
+                v["code"]
+                    .as_str()
+                    .and_then(StandardErrorCode::from_standard_code)
+            })
+            .unwrap_or_else(|| StandardErrorCode::from_http_status(status));
+        ErrorClass {
+            code: code.code().to_string(),
+            name: code.name().to_string(),
+            category: code.category().to_string(),
+            retryable: code.retryable(),
+            fallbackable: code.fallbackable(),
+        }
+    }
+
+    fn extract_usage(body: String) -> Result<Option<Usage>, Error> {
+        call("extract_usage", body.as_bytes(), &Value::Null).map(|v| usage_from_json(&v))
+    }
+
+    fn stream_open(handle: u32, model: String) -> Result<u32, Error> {
+        let input = json!({ "handle": handle, "model": model }).to_string();
+        let out = call("stream_open", input.as_bytes(), &Value::Null)?;
+        Ok(out["stream"].as_u64().unwrap_or(0) as u32)
+    }
+
+    fn stream_feed(stream: u32, chunk: Vec<u8>) -> Result<StreamOutput, Error> {
+        crate::buffers::bump_calls();
+        let out = feed_stream(stream, &chunk).map_err(|e| {
+            crate::buffers::bump_errors();
+            op_error("stream_feed", e)
+        })?;
+        Ok(StreamOutput {
+            events: events_from_json(&out["events"]),
+            ended: out["ended"].as_bool().unwrap_or(false),
+        })
+    }
+
+    fn stream_close(stream: u32) -> Result<StreamSummary, Error> {
+        crate::buffers::bump_calls();
+        let out = close_stream(stream).map_err(|e| {
+            crate::buffers::bump_errors();
+            op_error("stream_close", e)
+        })?;
+        Ok(StreamSummary {
+            events: events_from_json(&out["events"]),
+            content: json_text(&out["content"]),
+            thinking: json_text(&out["thinking"]),
+            tool_calls: tool_calls_from_json(&out["tool_calls"]),
+            usage: out.get("usage").and_then(usage_from_json),
+            finish_reason: out.get("finish_reason").and_then(opt_str),
+        })
+    }
+
+    fn snapshot_state() -> Result<String, Error> {
+        call("snapshot_state", &[], &Value::Null).map(|v| v.to_string())
+    }
+
+    fn restore_state(snapshot: String) -> Result<(), Error> {
+        call("restore_state", snapshot.as_bytes(), &Value::Null).map(|_| ())
+    }
+}
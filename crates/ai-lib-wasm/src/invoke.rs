@@ -2,12 +2,14 @@
 //!
 //! 中文：统一 ailib_invoke 分发器。
 
+use ai_lib_core::error_code::StandardErrorCode;
 use ai_lib_core::protocol::ProtocolManifest;
 use serde::{Deserialize, Serialize};
 
 use crate::abi::capabilities_json;
 use crate::buffers::*;
 use crate::snapshot_ops::{ailib_restore_state_inner, ailib_snapshot_state_inner};
+use crate::stream_ops::{ailib_stream_close, ailib_stream_feed, ailib_stream_open};
 use crate::v1::{
     ailib_build_chat_request, ailib_check_capability, ailib_classify_error, ailib_extract_usage,
     ailib_load_manifest, ailib_parse_chat_response,
@@ -109,7 +111,7 @@ pub unsafe extern "C" fn ailib_invoke(
                     0
                 }
                 Err(e) => {
-                    set_err(e.to_string());
+                    set_err_with_code(StandardErrorCode::Unknown, e.to_string());
                     -1
                 }
             }
@@ -123,6 +125,9 @@ pub unsafe extern "C" fn ailib_invoke(
         "resolve_credential" => ailib_invoke_resolve_credential(&ctx, input_ptr, input_len),
         "snapshot_state" => ailib_snapshot_state_inner(),
         "restore_state" => ailib_restore_state_inner(input_ptr, input_len),
+        "stream_open" => ailib_stream_open(ctx.manifest_handle, input_ptr, input_len),
+        "stream_feed" => ailib_stream_feed(input_ptr, input_len),
+        "stream_close" => ailib_stream_close(input_ptr, input_len),
         other => {
             set_err(format!("unknown op: {}", other));
             -1
@@ -299,7 +304,7 @@ unsafe fn ailib_invoke_resolve_credential(
     let g = match MANIFESTS.lock() {
         Ok(g) => g,
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             return -1;
         }
     };
@@ -350,7 +355,7 @@ unsafe fn ailib_invoke_resolve_credential(
             0
         }
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             -1
         }
     }
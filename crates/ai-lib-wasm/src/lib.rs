@@ -24,21 +24,36 @@
 //!   ilib_arena_reset (bulk release of LAST_OUT / LAST_ERR).
 //! - **State migration** (WASM-003) — ilib_snapshot_state,
 //!   ilib_restore_state for hot upgrades.
+//! - **Streaming** — `stream_open` / `stream_feed` / `stream_close` ops run the
+//!   core pipeline (decoder + event mapper + tool-call assembler) per stream
+//!   handle; the host feeds raw SSE / NDJSON bytes and receives `StreamingEvent`s.
+//!
+//! ## Component Model (`component` feature)
+//!
+//! `wit/world.wit` defines the `ai-lib:protocol` world: typed records for
+//! manifests, messages, requests, responses, streaming events and errors over
+//! the same ops. Build it as a component with
+//! `cargo build -p ai-lib-wasm --features component --target wasm32-wasip2 --release`;
+//! the C ABI above stays in the core module unchanged.
 
 mod abi;
 mod buffers;
+#[cfg(feature = "component")]
+mod component;
 mod invoke;
 mod io;
 mod memory;
 mod snapshot_ops;
 mod state;
+mod stream_ops;
 mod v1;
 
 #[cfg(test)]
 mod native_tests;
 
 pub use state::{
-    ManifestEntry, StreamState, WasmMetrics, WasmStateSnapshot, SNAPSHOT_FORMAT_VERSION,
+    ManifestEntry, StreamState, StreamToolCall, WasmMetrics, WasmStateSnapshot,
+    SNAPSHOT_FORMAT_VERSION,
 };
 
 /// Current ABI version reported by ilib_abi_version().
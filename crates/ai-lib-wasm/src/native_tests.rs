@@ -11,6 +11,7 @@ use crate::io::*;
 use crate::memory::*;
 use crate::snapshot_ops::*;
 use crate::state::*;
+use crate::stream_ops::*;
 use crate::v1::*;
 use crate::AILIB_ABI_VERSION;
 
@@ -31,6 +32,9 @@ fn reset_state() {
     if let Ok(mut g) = METRICS.lock() {
         *g = WasmMetrics::default();
     }
+    if let Ok(mut g) = STREAMS.lock() {
+        g.clear();
+    }
     ailib_arena_reset();
 }
 
@@ -468,3 +472,304 @@ fn test_invoke_snapshot_and_restore_ops() {
     let v: serde_json::Value = serde_json::from_slice(&read_out()).unwrap();
     assert_eq!(v["restored_manifests"], 1);
 }
+
+// ---- Streaming ops ---------------------------------------------------
+
+const STREAM_MANIFEST_YAML: &str = r#"---
+id: stream-provider
+name: Stream Provider
+version: "1.0.0"
+protocol_version: "2.0"
+endpoint:
+  base_url: https://api.example.com/v1
+capabilities:
+  streaming: true
+  tools: true
+  vision: false
+status: stable
+category: ai_provider
+official_url: https://example.com
+support_contact: https://example.com/support
+parameter_mappings: {}
+streaming:
+  decoder:
+    format: sse
+    strategy: openai_chat
+"#;
+
+const SSE_BODY: &str = concat!(
+    "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"function\":{\"name\":\"get_weather\",\"arguments\":\"{\\\"city\\\":\"}}]}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"Paris\\\"}\"}}]}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}],\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":5}}\n\n",
+    "data: [DONE]\n\n",
+);
+
+fn stream_open(handle: u32) -> u32 {
+    let ctx = format!(r#"{{"version": 2, "manifest_handle": {}}}"#, handle);
+    assert_eq!(
+        invoke(
+            "stream_open",
+            Some(br#"{"model":"m"}"#),
+            Some(ctx.as_bytes())
+        ),
+        0,
+        "err={}",
+        read_err()
+    );
+    let v: serde_json::Value = serde_json::from_slice(&read_out()).unwrap();
+    v["stream"].as_u64().unwrap() as u32
+}
+
+fn stream_feed(stream: u32, chunk: &str) -> Vec<serde_json::Value> {
+    let input =
+        serde_json::to_vec(&serde_json::json!({ "stream": stream, "chunk": chunk })).unwrap();
+    assert_eq!(
+        invoke("stream_feed", Some(&input), None),
+        0,
+        "err={}",
+        read_err()
+    );
+    let v: serde_json::Value = serde_json::from_slice(&read_out()).unwrap();
+    v["events"].as_array().cloned().unwrap_or_default()
+}
+
+fn stream_close(stream: u32) -> serde_json::Value {
+    let input = format!(r#"{{"stream": {}}}"#, stream);
+    assert_eq!(
+        invoke("stream_close", Some(input.as_bytes()), None),
+        0,
+        "err={}",
+        read_err()
+    );
+    serde_json::from_slice(&read_out()).unwrap()
+}
+
+fn load_stream_manifest() -> u32 {
+    let h =
+        unsafe { ailib_load_manifest(STREAM_MANIFEST_YAML.as_ptr(), STREAM_MANIFEST_YAML.len()) };
+    assert!(h >= 1, "load_manifest failed: {}", read_err());
+    h
+}
+
+#[test]
+fn test_stream_ops_assemble_split_sse() {
+    let _g = test_lock();
+    reset_state();
+    let h = load_stream_manifest();
+    let s = stream_open(h);
+
+    // Feed in small, frame-misaligned chunks: events come out as soon as a frame completes.
+    let mut events = Vec::new();
+    for chunk in SSE_BODY.as_bytes().chunks(17) {
+        events.extend(stream_feed(s, std::str::from_utf8(chunk).unwrap()));
+    }
+    let closed = stream_close(s);
+    events.extend(closed["events"].as_array().cloned().unwrap_or_default());
+
+    let deltas: Vec<_> = events
+        .iter()
+        .filter(|e| e["event_type"] == "PartialContentDelta")
+        .collect();
+    assert_eq!(deltas.len(), 2);
+    assert_eq!(events.last().unwrap()["index"], events.len());
+
+    assert_eq!(closed["content"], "Hello");
+    assert_eq!(closed["tool_calls"][0]["id"], "call_1");
+    assert_eq!(closed["tool_calls"][0]["name"], "get_weather");
+    assert_eq!(closed["tool_calls"][0]["arguments"]["city"], "Paris");
+    assert_eq!(closed["usage"]["completion_tokens"], 5);
+    assert_eq!(closed["finish_reason"], "tool_calls");
+
+    let m = METRICS.lock().unwrap().clone();
+    assert_eq!((m.total_tokens_in, m.total_tokens_out), (7, 5));
+
+    // Closed handles are gone.
+    let input = format!(r#"{{"stream": {}}}"#, s);
+    assert_eq!(invoke("stream_feed", Some(input.as_bytes()), None), -1);
+    assert!(read_err().contains("unknown stream"));
+}
+
+#[test]
+fn test_stream_open_requires_valid_handle() {
+    let _g = test_lock();
+    reset_state();
+    assert_eq!(invoke("stream_open", None, None), -1);
+    assert!(read_err().contains("missing manifest handle"));
+    assert_eq!(invoke("stream_open", Some(br#"{"handle": 9}"#), None), -1);
+    assert!(read_err().contains("invalid manifest handle"));
+
+    // A manifest without `streaming.decoder` cannot back a stream.
+    let h = unsafe { ailib_load_manifest(MIN_MANIFEST_YAML.as_ptr(), MIN_MANIFEST_YAML.len()) };
+    let input = format!(r#"{{"handle": {}}}"#, h);
+    assert_eq!(invoke("stream_open", Some(input.as_bytes()), None), -1);
+}
+
+#[test]
+fn test_stream_snapshot_restore_replays_without_duplicates() {
+    let _g = test_lock();
+    reset_state();
+    let h = load_stream_manifest();
+    let s = stream_open(h);
+    // Deliver the two content frames, then hot-upgrade before the tool call arrives.
+    let cut = SSE_BODY
+        .find("data: {\"choices\":[{\"delta\":{\"tool")
+        .unwrap();
+    let before = stream_feed(s, &SSE_BODY[..cut]);
+    assert_eq!(before.len(), 2);
+
+    assert_eq!(invoke("snapshot_state", None, None), 0);
+    let snap_bytes = read_out();
+    let snap: WasmStateSnapshot = serde_json::from_slice(&snap_bytes).unwrap();
+    assert_eq!(snap.active_streams.len(), 1);
+    assert_eq!(snap.active_streams[0].accumulated_content, "Hello");
+    assert!(snap.active_streams[0].needs_replay);
+
+    reset_state();
+    assert_eq!(
+        invoke("restore_state", Some(&snap_bytes), None),
+        0,
+        "err={}",
+        read_err()
+    );
+    let v: serde_json::Value = serde_json::from_slice(&read_out()).unwrap();
+    assert_eq!(v["restored_streams"], 1);
+
+    // The host replays the whole upstream body; already-delivered events are dropped.
+    let replayed = stream_feed(s, SSE_BODY);
+    assert!(replayed
+        .iter()
+        .all(|e| e["event_type"] != "PartialContentDelta"));
+    assert_eq!(replayed[0]["index"], 3);
+    let closed = stream_close(s);
+    assert_eq!(closed["content"], "Hello");
+    assert_eq!(closed["tool_calls"][0]["arguments"]["city"], "Paris");
+
+    // New streams never reuse a restored id.
+    assert_ne!(stream_open(1), s);
+}
+
+#[test]
+fn test_standard_code_for_maps_error_kinds() {
+    use ai_lib_core::error_code::StandardErrorCode;
+    use ai_lib_core::pipeline::PipelineError;
+    use ai_lib_core::Error;
+
+    assert_eq!(
+        standard_code_for(&Error::validation("bad")),
+        StandardErrorCode::InvalidRequest
+    );
+    assert_eq!(
+        standard_code_for(&Error::Pipeline(PipelineError::Decoder("bad frame".into()))),
+        StandardErrorCode::ServerError
+    );
+    assert_eq!(
+        standard_code_for(&Error::configuration("boom")),
+        StandardErrorCode::Unknown
+    );
+}
+
+// ---- Component Model (typed WIT surface) -----------------------------
+
+#[cfg(feature = "component")]
+mod component_tests {
+    use super::*;
+    use crate::component::ai_lib::protocol::types::{
+        ChatRequest, Message, Role, StreamingEvent, ToolDefinition,
+    };
+    use crate::component::exports::ai_lib::protocol::runtime::Guest;
+    use crate::component::Component;
+
+    #[test]
+    fn test_component_request_response_roundtrip() {
+        let _g = test_lock();
+        reset_state();
+        let info = Component::load_manifest(STREAM_MANIFEST_YAML.to_string()).unwrap();
+        assert_eq!(info.id, "stream-provider");
+        assert!(Component::check_capability(info.handle, "streaming".into()).unwrap());
+
+        let body = Component::build_chat_request(
+            info.handle,
+            ChatRequest {
+                model: "m".into(),
+                messages: vec![Message {
+                    role: Role::User,
+                    content: "hi".into(),
+                    tool_call_id: None,
+                }],
+                temperature: Some(0.5),
+                max_tokens: None,
+                stream: false,
+                tools: vec![ToolDefinition {
+                    name: "get_weather".into(),
+                    description: None,
+                    parameters_json: Some(r#"{"type":"object"}"#.into()),
+                }],
+            },
+        )
+        .unwrap();
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["messages"][0]["content"], "hi");
+
+        let resp = Component::parse_chat_response(
+            info.handle,
+            r#"{"choices":[{"message":{"role":"assistant","content":"ok","tool_calls":[
+                {"id":"c1","type":"function","function":{"name":"f","arguments":"{\"a\":1}"}}]},
+                "finish_reason":"stop"}],"usage":{"prompt_tokens":3,"completion_tokens":2,"total_tokens":5}}"#
+                .into(),
+        )
+        .unwrap();
+        assert_eq!(resp.content.as_deref(), Some("ok"));
+        assert_eq!(resp.usage.unwrap().total_tokens, 5);
+        assert_eq!(resp.tool_calls[0].name, "f");
+        assert_eq!(resp.tool_calls[0].arguments_json, r#"{"a":1}"#);
+
+        let class = Component::classify_error(429, None);
+        assert_eq!(class.code, "E2001");
+        assert!(class.retryable);
+
+        let err = Component::check_capability(42, "streaming".into()).unwrap_err();
+        assert_eq!(err.code, "E1001");
+        assert!(err.message.contains("invalid manifest handle"));
+    }
+
+    #[test]
+    fn test_component_errors_keep_their_standard_code() {
+        let _g = test_lock();
+        reset_state();
+        let yaml = STREAM_MANIFEST_YAML.replace("format: sse", "format: carrier_pigeon");
+        let info = Component::load_manifest(yaml).unwrap();
+        let err = Component::stream_open(info.handle, "m".into()).unwrap_err();
+        assert_eq!(err.code, "E9999", "{}", err.message);
+        assert!(err.message.starts_with("stream_open: "));
+
+        let err = Component::stream_feed(999, Vec::new()).unwrap_err();
+        assert_eq!(err.code, "E1001");
+    }
+
+    #[test]
+    fn test_component_stream_accepts_split_utf8() {
+        let _g = test_lock();
+        reset_state();
+        let info = Component::load_manifest(STREAM_MANIFEST_YAML.to_string()).unwrap();
+        let s = Component::stream_open(info.handle, "m".into()).unwrap();
+
+        // Raw bytes may split a multi-byte character; the decoder reassembles it.
+        let body = "data: {\"choices\":[{\"delta\":{\"content\":\"café\"}}]}\n\ndata: [DONE]\n\n";
+        let cut = body.find('é').unwrap() + 1;
+        let mut events = Component::stream_feed(s, body.as_bytes()[..cut].to_vec())
+            .unwrap()
+            .events;
+        events.extend(
+            Component::stream_feed(s, body.as_bytes()[cut..].to_vec())
+                .unwrap()
+                .events,
+        );
+        assert!(matches!(&events[0], StreamingEvent::ContentDelta(c) if c == "café"));
+
+        let summary = Component::stream_close(s).unwrap();
+        assert_eq!(summary.content, "café");
+        assert!(Component::stream_close(s).is_err());
+    }
+}
@@ -2,12 +2,15 @@
 //!
 //! 中文：状态快照与恢复。
 
+use ai_lib_core::error_code::StandardErrorCode;
 use ai_lib_core::protocol::{load_manifest_validated, ProtocolManifest};
 
 use crate::buffers::{
-    bytes_from_ptr, clear_err, set_err, set_out, write_out_json, MANIFESTS, METRICS,
+    bytes_from_ptr, clear_err, set_err, set_err_with_code, set_out, write_out_json, MANIFESTS,
+    METRICS,
 };
 use crate::state::{ManifestEntry, WasmStateSnapshot, SNAPSHOT_FORMAT_VERSION};
+use crate::stream_ops::{install_streams, restore_streams, snapshot_streams};
 use crate::AILIB_ABI_VERSION;
 
 // =============================================================================
@@ -33,7 +36,7 @@ pub(crate) fn snapshot_state_build() -> WasmStateSnapshot {
         version: SNAPSHOT_FORMAT_VERSION,
         abi_version: AILIB_ABI_VERSION,
         manifests,
-        active_streams: Vec::new(),
+        active_streams: snapshot_streams(),
         metrics,
     }
 }
@@ -46,7 +49,7 @@ pub(crate) fn ailib_snapshot_state_inner() -> i32 {
             0
         }
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             -1
         }
     }
@@ -95,20 +98,30 @@ pub(crate) unsafe fn ailib_restore_state_inner(ptr: *const u8, len: usize) -> i3
             }
         }
     }
+    // Streams are rebuilt against the restored manifests, still before any swap.
+    let new_streams = match restore_streams(&new_manifests, &snap.active_streams) {
+        Ok(s) => s,
+        Err(e) => {
+            set_err(e);
+            return -1;
+        }
+    };
     // Swap all state under the manifests lock so partial visibility is impossible.
     let mut g = match MANIFESTS.lock() {
         Ok(g) => g,
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             return -1;
         }
     };
     *g = new_manifests;
+    install_streams(new_streams);
     if let Ok(mut m) = METRICS.lock() {
         *m = snap.metrics.clone();
     }
     write_out_json(&serde_json::json!({
         "restored_manifests": snap.manifests.len(),
+        "restored_streams": snap.active_streams.len(),
         "needs_replay_streams": snap.active_streams.iter().filter(|s| s.needs_replay).count(),
     }))
 }
@@ -5,10 +5,10 @@
 //! - 加载新的 WASM 实例；
 //! - 调用 `ailib_restore_state` 注入快照。
 //!
-//! 当前 ai-lib-wasm 执行层基本是无状态纯函数，唯一持久化的状态是通过
-//! `ailib_load_manifest` 注册的 provider manifest 列表，以及累计的调用指标。
-//! 活跃的 SSE 流会话由宿主持有，不在本模块；因此 `active_streams` 字段预留
-//! 为空（`needs_replay` 策略由宿主执行）。
+//! 持久化的状态包括：通过 `ailib_load_manifest` 注册的 provider manifest 列表、
+//! 累计的调用指标，以及通过 `stream_open` 打开的流会话（`active_streams`）。
+//! 流会话只保存累计内容与工具调用；解码器缓冲与上游连接无法迁移，因此恢复后
+//! `needs_replay` 恒为 true，由宿主重新请求上游并继续 `stream_feed`。
 //!
 //! ## Schema versioning
 //!
@@ -55,10 +55,9 @@ pub struct WasmMetrics {
     pub total_tokens_out: u64,
 }
 
-/// Placeholder for in-flight stream sessions. The current execution layer is
-/// stateless (all stream accumulation lives in the host), so this field is
-/// always empty in v1. Reserved to keep the snapshot schema stable when
-/// stream state migrates into WASM in a future phase.
+/// Snapshot of an in-flight `stream_open` session: accumulated output only.
+/// Decoder buffers are not captured; restore recreates the pipeline under the
+/// same `stream_id` and the host replays the upstream response.
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct StreamState {
     pub stream_id: String,
@@ -78,6 +77,22 @@ pub struct StreamState {
     pub needs_replay: bool,
     #[serde(default)]
     pub started_at: u64,
+    /// Manifest handle the stream was opened against (handles survive restore
+    /// because manifests are re-registered in snapshot order).
+    #[serde(default)]
+    pub manifest_handle: u32,
+    /// Tool calls seen so far, with their raw (possibly partial) argument JSON.
+    #[serde(default)]
+    pub tool_calls: Vec<StreamToolCall>,
+}
+
+/// Tool call accumulated by a stream session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StreamToolCall {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
 }
 
 /// Full snapshot of WASM-internal state. Atomic restore contract: if any
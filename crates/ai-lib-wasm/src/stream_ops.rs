@@ -0,0 +1,444 @@
+//! Streaming ops: stream_open / stream_feed / stream_close.
+//!
+//! 中文：在 WASM 内运行真实的 `pipeline`（解码器 + 事件映射 + 工具调用组装），
+//! 宿主只需把上游 SSE / NDJSON 字节按块喂入，取回统一的 `StreamingEvent`。
+//!
+//! Each open stream owns a [`Pipeline`] whose byte input is an unbounded channel.
+//! `stream_feed` pushes a chunk and polls the event stream with a no-op waker
+//! until it returns `Pending`: every pipeline operator is pure (no timers or IO),
+//! so `Pending` means "needs more bytes". `stream_close` drops the sender, which
+//! lets decoders flush their trailing frame and the mapper emit `StreamEnd`.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use ai_lib_core::error_code::StandardErrorCode;
+use ai_lib_core::pipeline::Pipeline;
+use ai_lib_core::protocol::ProtocolManifest;
+use ai_lib_core::utils::tool_call_assembler::ToolCallAssembler;
+use ai_lib_core::{BoxStream, PipeResult, StreamingEvent};
+use bytes::Bytes;
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::StreamExt;
+use serde::Deserialize;
+
+use crate::buffers::{
+    bump_calls, bump_errors, parse_input_json, set_err, set_err_with_code, write_out_json, OpError,
+    MANIFESTS, METRICS,
+};
+use crate::state::{StreamState, StreamToolCall};
+
+pub(crate) struct StreamSession {
+    manifest_handle: u32,
+    provider_id: String,
+    model: String,
+    input: Option<UnboundedSender<PipeResult<Bytes>>>,
+    /// Incomplete UTF-8 sequence held back from the last chunk (the decoders
+    /// convert each chunk on its own, so a split character must not reach them).
+    pending: Vec<u8>,
+    events: BoxStream<'static, StreamingEvent>,
+    tool_asm: ToolCallAssembler,
+    content: String,
+    thinking: String,
+    usage: Option<serde_json::Value>,
+    finish_reason: Option<String>,
+    event_index: u32,
+    /// After a restore, events up to this index were already delivered and are
+    /// already part of the accumulators; the replayed copies are dropped.
+    replay_until: u32,
+    started_at: u64,
+    ended: bool,
+}
+
+/// Open streams keyed by handle (1-based; 0 is never issued).
+pub(crate) static STREAMS: Mutex<BTreeMap<u32, StreamSession>> = Mutex::new(BTreeMap::new());
+static NEXT_STREAM: Mutex<u32> = Mutex::new(1);
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl StreamSession {
+    fn open(
+        manifests: &[Option<(ProtocolManifest, Vec<u8>)>],
+        manifest_handle: u32,
+        model: String,
+    ) -> Result<Self, OpError> {
+        let (manifest, _) = manifest_handle
+            .checked_sub(1)
+            .and_then(|i| manifests.get(i as usize))
+            .and_then(|x| x.as_ref())
+            .ok_or_else(|| OpError::invalid("invalid manifest handle"))?;
+        let pipeline = Pipeline::from_manifest(manifest).map_err(ai_lib_core::Error::from)?;
+        let (tx, rx) = unbounded::<PipeResult<Bytes>>();
+        let events = futures::executor::block_on(pipeline.process_stream(Box::pin(rx)))?;
+        Ok(Self {
+            manifest_handle,
+            provider_id: manifest.id.clone(),
+            model,
+            input: Some(tx),
+            pending: Vec::new(),
+            events,
+            tool_asm: ToolCallAssembler::new(),
+            content: String::new(),
+            thinking: String::new(),
+            usage: None,
+            finish_reason: None,
+            event_index: 0,
+            replay_until: 0,
+            started_at: now_secs(),
+            ended: false,
+        })
+    }
+
+    /// Restore a session from a snapshot: a fresh pipeline with the accumulated
+    /// state carried over. The host replays the upstream response from the start;
+    /// the first `last_event_index` events it produces are skipped.
+    fn restore(
+        manifests: &[Option<(ProtocolManifest, Vec<u8>)>],
+        state: &StreamState,
+    ) -> Result<Self, OpError> {
+        let mut s = Self::open(manifests, state.manifest_handle, state.model.clone())?;
+        s.content = state.accumulated_content.clone();
+        s.thinking = state.accumulated_thinking.clone();
+        s.replay_until = state.last_event_index;
+        s.started_at = state.started_at;
+        for tc in &state.tool_calls {
+            s.tool_asm.on_started(tc.id.clone(), tc.name.clone());
+            s.tool_asm.on_partial(&tc.id, &tc.arguments);
+        }
+        Ok(s)
+    }
+
+    /// Forward bytes to the pipeline, up to the last complete UTF-8 character.
+    fn push(&mut self, chunk: &[u8]) -> Result<(), OpError> {
+        self.pending.extend_from_slice(chunk);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // Only an unfinished trailing sequence is held; invalid bytes pass through.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+        if complete == 0 {
+            return Ok(());
+        }
+        let bytes = Bytes::from(self.pending.drain(..complete).collect::<Vec<u8>>());
+        match &self.input {
+            Some(tx) if tx.unbounded_send(Ok(bytes)).is_ok() => Ok(()),
+            _ if self.ended => Ok(()),
+            _ => Err(OpError::invalid("stream input already closed")),
+        }
+    }
+
+    /// Poll the pipeline until it needs more input (or ends); returns new events.
+    fn drain(&mut self) -> Result<Vec<serde_json::Value>, OpError> {
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        let mut out = Vec::new();
+        while !self.ended {
+            match self.events.poll_next_unpin(&mut cx) {
+                Poll::Ready(Some(Ok(ev))) => {
+                    self.event_index += 1;
+                    if self.event_index <= self.replay_until {
+                        continue;
+                    }
+                    self.observe(&ev);
+                    let mut v = serde_json::to_value(&ev).map_err(ai_lib_core::Error::from)?;
+                    if let Some(obj) = v.as_object_mut() {
+                        obj.insert("index".to_string(), self.event_index.into());
+                    }
+                    out.push(v);
+                }
+                Poll::Ready(Some(Err(e))) => return Err(e.into()),
+                Poll::Ready(None) => self.ended = true,
+                Poll::Pending => break,
+            }
+        }
+        Ok(out)
+    }
+
+    fn observe(&mut self, ev: &StreamingEvent) {
+        match ev {
+            StreamingEvent::PartialContentDelta { content, .. } => self.content.push_str(content),
+            StreamingEvent::ThinkingDelta { thinking, .. } => self.thinking.push_str(thinking),
+            StreamingEvent::ToolCallStarted {
+                tool_call_id,
+                tool_name,
+                ..
+            } => self
+                .tool_asm
+                .on_started(tool_call_id.clone(), tool_name.clone()),
+            StreamingEvent::PartialToolCall {
+                tool_call_id,
+                arguments,
+                ..
+            } => self.tool_asm.on_partial(tool_call_id, arguments),
+            StreamingEvent::Metadata {
+                usage,
+                finish_reason,
+                ..
+            } => {
+                if usage.is_some() {
+                    self.usage = usage.clone();
+                }
+                if finish_reason.is_some() {
+                    self.finish_reason = finish_reason.clone();
+                }
+            }
+            StreamingEvent::StreamEnd {
+                finish_reason: Some(reason),
+            } => self.finish_reason = Some(reason.clone()),
+            _ => {}
+        }
+    }
+
+    fn tool_calls_json(&self) -> Vec<serde_json::Value> {
+        self.tool_asm
+            .clone()
+            .finalize()
+            .into_iter()
+            .filter_map(|tc| serde_json::to_value(tc).ok())
+            .collect()
+    }
+
+    fn tokens_so_far(&self) -> u64 {
+        self.usage
+            .as_ref()
+            .and_then(|u| {
+                u.get("completion_tokens")
+                    .or_else(|| u.get("output_tokens"))
+            })
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn to_state(&self, stream_id: u32) -> StreamState {
+        StreamState {
+            stream_id: stream_id.to_string(),
+            provider_id: self.provider_id.clone(),
+            model: self.model.clone(),
+            accumulated_content: self.content.clone(),
+            accumulated_thinking: self.thinking.clone(),
+            tokens_so_far: self.tokens_so_far(),
+            last_event_index: self.event_index,
+            // Decoder buffers and the upstream connection do not survive a restart.
+            needs_replay: true,
+            started_at: self.started_at,
+            manifest_handle: self.manifest_handle,
+            tool_calls: self
+                .tool_asm
+                .clone()
+                .finalize()
+                .into_iter()
+                .map(|tc| StreamToolCall {
+                    arguments: match tc.arguments {
+                        serde_json::Value::String(raw) => raw,
+                        v => v.to_string(),
+                    },
+                    id: tc.id,
+                    name: tc.name,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Snapshot entries for all open streams (used by `snapshot_state`).
+pub(crate) fn snapshot_streams() -> Vec<StreamState> {
+    STREAMS
+        .lock()
+        .map(|g| g.iter().map(|(id, s)| s.to_state(*id)).collect())
+        .unwrap_or_default()
+}
+
+/// Rebuild sessions from a snapshot against the (not yet installed) restored
+/// manifests. All-or-nothing: returns the new table without touching the live one.
+pub(crate) fn restore_streams(
+    manifests: &[Option<(ProtocolManifest, Vec<u8>)>],
+    states: &[StreamState],
+) -> Result<BTreeMap<u32, StreamSession>, String> {
+    let mut out = BTreeMap::new();
+    for st in states {
+        let id: u32 = st
+            .stream_id
+            .parse()
+            .map_err(|_| format!("restore stream '{}': invalid stream id", st.stream_id))?;
+        let session = StreamSession::restore(manifests, st)
+            .map_err(|e| format!("restore stream '{}': {}", id, e))?;
+        out.insert(id, session);
+    }
+    Ok(out)
+}
+
+pub(crate) fn install_streams(streams: BTreeMap<u32, StreamSession>) {
+    let next = streams.keys().next_back().map(|k| k + 1).unwrap_or(1);
+    *STREAMS.lock().unwrap_or_else(|e| e.into_inner()) = streams;
+    let mut n = NEXT_STREAM.lock().unwrap_or_else(|e| e.into_inner());
+    *n = (*n).max(next);
+}
+
+#[derive(Deserialize)]
+struct OpenIn {
+    #[serde(default)]
+    handle: Option<u32>,
+    #[serde(default)]
+    model: String,
+}
+
+#[derive(Deserialize)]
+struct FeedIn {
+    stream: u32,
+    #[serde(default)]
+    chunk: String,
+}
+
+#[derive(Deserialize)]
+struct CloseIn {
+    stream: u32,
+}
+
+pub(crate) unsafe fn ailib_stream_open(
+    ctx_handle: Option<u32>,
+    input_ptr: *const u8,
+    input_len: usize,
+) -> i32 {
+    bump_calls();
+    let input: OpenIn = if input_len == 0 || input_ptr.is_null() {
+        OpenIn {
+            handle: None,
+            model: String::new(),
+        }
+    } else {
+        match parse_input_json(input_ptr, input_len) {
+            Ok(v) => v,
+            Err(e) => {
+                set_err(e);
+                bump_errors();
+                return -1;
+            }
+        }
+    };
+    let Some(handle) = input.handle.or(ctx_handle) else {
+        set_err("stream_open: missing manifest handle (in ctx or input)");
+        bump_errors();
+        return -1;
+    };
+    let opened = match MANIFESTS.lock() {
+        Ok(g) => StreamSession::open(&g, handle, input.model),
+        Err(e) => Err(OpError {
+            code: StandardErrorCode::Unknown,
+            message: e.to_string(),
+        }),
+    };
+    let session = match opened {
+        Ok(s) => s,
+        Err(e) => {
+            set_err_with_code(e.code, format!("stream_open: {}", e));
+            bump_errors();
+            return -1;
+        }
+    };
+    let id = {
+        let mut n = NEXT_STREAM.lock().unwrap_or_else(|e| e.into_inner());
+        let id = *n;
+        *n = n.wrapping_add(1).max(1);
+        id
+    };
+    STREAMS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(id, session);
+    write_out_json(&serde_json::json!({ "stream": id }))
+}
+
+/// Push raw upstream bytes into a stream; returns `{events, ended}`.
+pub(crate) fn feed_stream(stream: u32, chunk: &[u8]) -> Result<serde_json::Value, OpError> {
+    let mut g = STREAMS.lock().unwrap_or_else(|e| e.into_inner());
+    let session = g
+        .get_mut(&stream)
+        .ok_or_else(|| OpError::invalid(format!("unknown stream {}", stream)))?;
+    session.push(chunk)?;
+    let events = session.drain()?;
+    Ok(serde_json::json!({ "events": events, "ended": session.ended }))
+}
+
+/// Signal EOF, drain the remaining events and remove the stream; returns the
+/// trailing events plus the accumulated result.
+pub(crate) fn close_stream(stream: u32) -> Result<serde_json::Value, OpError> {
+    let mut session = STREAMS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&stream)
+        .ok_or_else(|| OpError::invalid(format!("unknown stream {}", stream)))?;
+    // EOF: decoders flush their last frame, the mapper emits its terminal events.
+    if !session.pending.is_empty() {
+        let rest = std::mem::take(&mut session.pending);
+        if let Some(tx) = &session.input {
+            let _ = tx.unbounded_send(Ok(Bytes::from(rest)));
+        }
+    }
+    session.input = None;
+    let events = session.drain()?;
+    if let Some(u) = &session.usage {
+        if let Ok(mut m) = METRICS.lock() {
+            let tin = u
+                .get("prompt_tokens")
+                .or_else(|| u.get("input_tokens"))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+            m.total_tokens_in = m.total_tokens_in.saturating_add(tin);
+            m.total_tokens_out = m.total_tokens_out.saturating_add(session.tokens_so_far());
+        }
+    }
+    Ok(serde_json::json!({
+        "events": events,
+        "content": session.content,
+        "thinking": session.thinking,
+        "tool_calls": session.tool_calls_json(),
+        "usage": session.usage,
+        "finish_reason": session.finish_reason,
+    }))
+}
+
+pub(crate) unsafe fn ailib_stream_feed(input_ptr: *const u8, input_len: usize) -> i32 {
+    bump_calls();
+    let input: FeedIn = match parse_input_json(input_ptr, input_len) {
+        Ok(v) => v,
+        Err(e) => {
+            set_err(e);
+            bump_errors();
+            return -1;
+        }
+    };
+    match feed_stream(input.stream, input.chunk.as_bytes()) {
+        Ok(out) => write_out_json(&out),
+        Err(e) => {
+            set_err_with_code(e.code, format!("stream_feed: {}", e));
+            bump_errors();
+            -1
+        }
+    }
+}
+
+pub(crate) unsafe fn ailib_stream_close(input_ptr: *const u8, input_len: usize) -> i32 {
+    bump_calls();
+    let input: CloseIn = match parse_input_json(input_ptr, input_len) {
+        Ok(v) => v,
+        Err(e) => {
+            set_err(e);
+            bump_errors();
+            return -1;
+        }
+    };
+    match close_stream(input.stream) {
+        Ok(out) => write_out_json(&out),
+        Err(e) => {
+            set_err_with_code(e.code, format!("stream_close: {}", e));
+            bump_errors();
+            -1
+        }
+    }
+}
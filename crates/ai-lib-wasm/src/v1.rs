@@ -31,7 +31,7 @@ pub unsafe extern "C" fn ailib_load_manifest(ptr: *const u8, len: usize) -> u32
             let mut g = match MANIFESTS.lock() {
                 Ok(g) => g,
                 Err(e) => {
-                    set_err(e.to_string());
+                    set_err_with_code(StandardErrorCode::Unknown, e.to_string());
                     bump_errors();
                     return 0;
                 }
@@ -40,7 +40,8 @@ pub unsafe extern "C" fn ailib_load_manifest(ptr: *const u8, len: usize) -> u32
             g.len() as u32
         }
         Err(e) => {
-            set_err(e.to_string());
+            let message = e.to_string();
+            set_err_with_code(standard_code_for(&e.into()), message);
             bump_errors();
             0
         }
@@ -65,7 +66,7 @@ pub unsafe extern "C" fn ailib_check_capability(
     let g = match MANIFESTS.lock() {
         Ok(g) => g,
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             return -1;
         }
     };
@@ -115,7 +116,7 @@ pub unsafe extern "C" fn ailib_build_chat_request(
     let g = match MANIFESTS.lock() {
         Ok(g) => g,
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             bump_errors();
             return -1;
         }
@@ -147,7 +148,7 @@ pub unsafe extern "C" fn ailib_build_chat_request(
     ) {
         Ok(r) => r,
         Err(e) => {
-            set_err(e.to_string());
+            set_core_err(&e);
             bump_errors();
             return -1;
         }
@@ -158,7 +159,7 @@ pub unsafe extern "C" fn ailib_build_chat_request(
             0
         }
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             bump_errors();
             -1
         }
@@ -215,7 +216,7 @@ pub unsafe extern "C" fn ailib_parse_chat_response(
     } = match driver.parse_response(&body) {
         Ok(r) => r,
         Err(e) => {
-            set_err(e.to_string());
+            set_core_err(&e);
             bump_errors();
             return -1;
         }
@@ -241,7 +242,7 @@ pub unsafe extern "C" fn ailib_parse_chat_response(
             0
         }
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             bump_errors();
             -1
         }
@@ -286,7 +287,7 @@ pub unsafe extern "C" fn ailib_classify_error(
             0
         }
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             -1
         }
     }
@@ -317,7 +318,7 @@ pub unsafe extern "C" fn ailib_extract_usage(json_ptr: *const u8, json_len: usiz
             0
         }
         Err(e) => {
-            set_err(e.to_string());
+            set_err_with_code(StandardErrorCode::Unknown, e.to_string());
             -1
         }
     }
@@ -132,3 +132,171 @@ fn wasmtime_protocol_loading_and_message_building() {
         String::from_utf8_lossy(&body)
     );
 }
+
+/// Manifest with an OpenAI-style SSE decoder so `stream_open` can build a pipeline.
+const WASM_STREAM_MANIFEST_YAML: &str = r#"
+id: qwen
+protocol_version: "2.0"
+status: stable
+category: ai_provider
+official_url: "https://example.com"
+support_contact: "mailto:test@example.com"
+endpoint:
+  base_url: "https://dashscope.aliyuncs.com/compatible-mode/v1"
+capabilities:
+  required: ["text", "streaming", "tools"]
+  optional: []
+streaming:
+  decoder:
+    format: sse
+    strategy: openai_chat
+"#;
+
+struct Guest {
+    store: Store<WasiP1Ctx>,
+    instance: wasmtime::Instance,
+    memory: wasmtime::Memory,
+}
+
+impl Guest {
+    fn load() -> Self {
+        let wasm_path = workspace_target_wasm();
+        assert!(
+            wasm_path.exists(),
+            "missing {} — run: cargo build -p ai-lib-wasm --target wasm32-wasip1 --release",
+            wasm_path.display()
+        );
+        let engine = Engine::default();
+        let mut linker: Linker<WasiP1Ctx> = Linker::new(&engine);
+        preview1::add_to_linker_sync(&mut linker, |s| s).expect("link wasi preview1");
+        let module = Module::from_file(&engine, &wasm_path).expect("load wasm module");
+        let wasi = WasiCtxBuilder::new().inherit_stdio().build_p1();
+        let mut store = Store::new(&engine, wasi);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .expect("instantiate wasm");
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .expect("guest memory export");
+        Self {
+            store,
+            instance,
+            memory,
+        }
+    }
+
+    /// Call `ailib_invoke(op, input, ctx)`; returns the parsed output JSON or the error text.
+    fn invoke(&mut self, op: &str, input: &[u8], ctx: &[u8]) -> Result<serde_json::Value, String> {
+        let f = self
+            .instance
+            .get_typed_func::<(u32, u32, u32, u32, u32, u32), i32>(&mut self.store, "ailib_invoke")
+            .expect("ailib_invoke");
+        let (op_ptr, op_len) = copy_to_guest(&mut self.store, &self.memory, op.as_bytes());
+        let (in_ptr, in_len) = copy_to_guest(&mut self.store, &self.memory, input);
+        let (ctx_ptr, ctx_len) = copy_to_guest(&mut self.store, &self.memory, ctx);
+        let rc = f
+            .call(
+                &mut self.store,
+                (op_ptr, op_len, in_ptr, in_len, ctx_ptr, ctx_len),
+            )
+            .expect("ailib_invoke call");
+        let (ptr_fn, len_fn) = if rc == 0 {
+            ("ailib_out_ptr", "ailib_out_len")
+        } else {
+            ("ailib_err_ptr", "ailib_err_len")
+        };
+        let ptr = self
+            .instance
+            .get_typed_func::<(), u32>(&mut self.store, ptr_fn)
+            .expect(ptr_fn)
+            .call(&mut self.store, ())
+            .expect(ptr_fn);
+        let len = self
+            .instance
+            .get_typed_func::<(), u32>(&mut self.store, len_fn)
+            .expect(len_fn)
+            .call(&mut self.store, ())
+            .expect(len_fn);
+        let mut buf = vec![0u8; len as usize];
+        self.memory
+            .read(&self.store, ptr as usize, &mut buf)
+            .expect("read guest buffer");
+        if rc == 0 {
+            Ok(serde_json::from_slice(&buf).expect("ailib_out must be JSON"))
+        } else {
+            Err(String::from_utf8_lossy(&buf).into_owned())
+        }
+    }
+}
+
+const WASM_SSE_BODY: &str = concat!(
+    "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"function\":{\"name\":\"get_weather\",\"arguments\":\"{\\\"city\\\":\\\"Paris\\\"}\"}}]}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+    "data: [DONE]\n\n",
+);
+
+#[test]
+fn wasmtime_stream_ops_and_hot_upgrade() {
+    let mut old = Guest::load();
+    let loaded = old
+        .invoke(
+            "load_manifest",
+            WASM_STREAM_MANIFEST_YAML.trim_start().as_bytes(),
+            b"",
+        )
+        .expect("load_manifest");
+    let ctx = format!(r#"{{"version":2,"manifest_handle":{}}}"#, loaded["handle"]);
+    let stream = old
+        .invoke("stream_open", br#"{"model":"qwen-turbo"}"#, ctx.as_bytes())
+        .expect("stream_open")["stream"]
+        .clone();
+
+    // First frame split mid-way: nothing until the frame terminator arrives.
+    let cut = WASM_SSE_BODY
+        .find("data: {\"choices\":[{\"delta\":{\"tool")
+        .unwrap();
+    let (head, tail) = WASM_SSE_BODY[..cut].split_at(20);
+    let feed = |g: &mut Guest, chunk: &str| {
+        let input = serde_json::json!({ "stream": stream, "chunk": chunk }).to_string();
+        g.invoke("stream_feed", input.as_bytes(), b"")
+            .expect("stream_feed")["events"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+    };
+    assert!(feed(&mut old, head).is_empty());
+    assert_eq!(feed(&mut old, tail).len(), 2);
+
+    // Hot upgrade into a fresh instance mid-stream.
+    let snap = old
+        .invoke("snapshot_state", b"", b"")
+        .expect("snapshot_state");
+    assert_eq!(snap["active_streams"][0]["accumulated_content"], "Hello");
+    let mut new = Guest::load();
+    let restored = new
+        .invoke("restore_state", snap.to_string().as_bytes(), b"")
+        .expect("restore_state");
+    assert_eq!(restored["needs_replay_streams"], 1);
+
+    // The host replays the upstream body; delivered events are skipped.
+    let replayed = feed(&mut new, WASM_SSE_BODY);
+    assert!(replayed
+        .iter()
+        .all(|e| e["event_type"] != "PartialContentDelta"));
+
+    let input = serde_json::json!({ "stream": stream }).to_string();
+    let closed = new
+        .invoke("stream_close", input.as_bytes(), b"")
+        .expect("stream_close");
+    assert_eq!(closed["content"], "Hello");
+    assert_eq!(closed["tool_calls"][0]["name"], "get_weather");
+    assert_eq!(closed["tool_calls"][0]["arguments"]["city"], "Paris");
+    assert_eq!(closed["finish_reason"], "tool_calls");
+
+    let err = new
+        .invoke("stream_close", input.as_bytes(), b"")
+        .expect_err("stream already closed");
+    assert!(err.contains("unknown stream"), "{err}");
+}
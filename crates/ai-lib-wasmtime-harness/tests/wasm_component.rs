@@ -0,0 +1,174 @@
+//! Load the `ai-lib:protocol` **component** (`wit/world.wit`) in wasmtime and run the same
+//! scenarios as `wasm_compliance.rs` through the typed bindings — no pointer/length glue.
+//!
+//! Run from workspace root:
+//! `cargo build -p ai-lib-wasm --features component --target wasm32-wasip2 --release`
+//! `cargo test -p ai-lib-wasmtime-harness --test wasm_component`
+
+use std::path::{Path, PathBuf};
+
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Config, Engine, Store};
+use wasmtime_wasi::{IoView, WasiCtx, WasiCtxBuilder, WasiView};
+
+wasmtime::component::bindgen!({
+    world: "protocol",
+    path: "../ai-lib-wasm/wit",
+});
+
+use ai_lib::protocol::types::{ChatRequest, Message, Role, StreamingEvent};
+
+fn workspace_target_component() -> PathBuf {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    manifest_dir.join("../../target/wasm32-wasip2/release/ai_lib_wasm.wasm")
+}
+
+const MANIFEST_YAML: &str = r#"
+id: qwen
+protocol_version: "2.0"
+status: stable
+category: ai_provider
+official_url: "https://example.com"
+support_contact: "mailto:test@example.com"
+endpoint:
+  base_url: "https://dashscope.aliyuncs.com/compatible-mode/v1"
+capabilities:
+  required: ["text", "streaming", "tools"]
+  optional: []
+streaming:
+  decoder:
+    format: sse
+    strategy: openai_chat
+"#;
+
+struct Host {
+    wasi: WasiCtx,
+    table: ResourceTable,
+}
+
+impl IoView for Host {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+impl WasiView for Host {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+fn instantiate() -> (Store<Host>, Protocol) {
+    let path = workspace_target_component();
+    assert!(
+        path.exists(),
+        "missing {} — run: cargo build -p ai-lib-wasm --features component --target wasm32-wasip2 --release",
+        path.display()
+    );
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    let engine = Engine::new(&config).expect("engine");
+    let component = Component::from_file(&engine, &path).expect("load component");
+    let mut linker: Linker<Host> = Linker::new(&engine);
+    wasmtime_wasi::add_to_linker_sync(&mut linker).expect("link wasi p2");
+    let host = Host {
+        wasi: WasiCtxBuilder::new().inherit_stdio().build(),
+        table: ResourceTable::new(),
+    };
+    let mut store = Store::new(&engine, host);
+    let protocol = Protocol::instantiate(&mut store, &component, &linker).expect("instantiate");
+    (store, protocol)
+}
+
+#[test]
+fn component_protocol_loading_and_message_building() {
+    let (mut store, protocol) = instantiate();
+    let rt = protocol.ai_lib_protocol_runtime();
+
+    let info = rt
+        .call_load_manifest(&mut store, MANIFEST_YAML.trim_start())
+        .expect("call")
+        .expect("load-manifest");
+    assert_eq!(info.id, "qwen");
+    assert!(rt
+        .call_check_capability(&mut store, info.handle, "streaming")
+        .expect("call")
+        .expect("check-capability"));
+
+    let request = ChatRequest {
+        model: "qwen-turbo".into(),
+        messages: vec![Message {
+            role: Role::User,
+            content: "hello".into(),
+            tool_call_id: None,
+        }],
+        temperature: None,
+        max_tokens: None,
+        stream: false,
+        tools: vec![],
+    };
+    let body = rt
+        .call_build_chat_request(&mut store, info.handle, &request)
+        .expect("call")
+        .expect("build-chat-request");
+    let body: serde_json::Value = serde_json::from_str(&body).expect("request body is JSON");
+    assert_eq!(body["messages"][0]["content"], "hello");
+
+    let class = rt.call_classify_error(&mut store, 429, None).expect("call");
+    assert_eq!(class.code, "E2001");
+    assert!(class.retryable);
+
+    let err = rt
+        .call_check_capability(&mut store, 99, "streaming")
+        .expect("call")
+        .expect_err("unknown handle");
+    assert!(err.message.contains("invalid manifest handle"));
+}
+
+#[test]
+fn component_stream_and_hot_upgrade() {
+    let (mut store, protocol) = instantiate();
+    let rt = protocol.ai_lib_protocol_runtime();
+    let info = rt
+        .call_load_manifest(&mut store, MANIFEST_YAML.trim_start())
+        .expect("call")
+        .expect("load-manifest");
+    let stream = rt
+        .call_stream_open(&mut store, info.handle, "qwen-turbo")
+        .expect("call")
+        .expect("stream-open");
+
+    let head = "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n";
+    let out = rt
+        .call_stream_feed(&mut store, stream, head.as_bytes())
+        .expect("call")
+        .expect("stream-feed");
+    assert!(matches!(&out.events[0], StreamingEvent::ContentDelta(c) if c == "Hel"));
+
+    // Snapshot JSON is shared with the C ABI, so it restores into a fresh component.
+    let snapshot = rt
+        .call_snapshot_state(&mut store)
+        .expect("call")
+        .expect("snapshot-state");
+    let (mut store2, protocol2) = instantiate();
+    let rt2 = protocol2.ai_lib_protocol_runtime();
+    rt2.call_restore_state(&mut store2, &snapshot)
+        .expect("call")
+        .expect("restore-state");
+
+    let body = concat!(
+        "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
+        "data: [DONE]\n\n",
+    );
+    rt2.call_stream_feed(&mut store2, stream, body.as_bytes())
+        .expect("call")
+        .expect("stream-feed after restore");
+    let summary = rt2
+        .call_stream_close(&mut store2, stream)
+        .expect("call")
+        .expect("stream-close");
+    assert_eq!(summary.content, "Hello");
+    assert_eq!(summary.finish_reason.as_deref(), Some("stop"));
+}